@@ -0,0 +1,36 @@
+//! Converts the sample video into animated GIFs of several widths, with at most two
+//! conversion jobs at the same time (using a `Batch`), and prints the outcome of each
+//! job. A missing input file is also included, to show how failures are collected.
+//!
+//! `cargo run --example batch`
+
+use std::collections::HashMap;
+
+use ffmpeg_gif_maker::{Batch, BatchEvent, Message, Settings};
+
+mod common;
+
+const WIDTHS: [u16; 3] = [120, 240, 480];
+
+fn main() {
+    let input = common::input_video();
+
+    let mut batch = Batch::new(2);
+    let mut names = HashMap::new();
+    for width in WIDTHS {
+        let id = batch.add(Settings::with_standard_fps(input.clone(), width));
+        names.insert(id, format!("{}px", width));
+    }
+    let id = batch.add(Settings::with_standard_fps("missing.mp4".into(), 200));
+    names.insert(id, "missing.mp4".into());
+
+    let report = batch.run(|event| {
+        if let BatchEvent::Message(id, Message::Success(bytes)) = event {
+            println!("{}: {} bytes", names[&id], bytes.len());
+        }
+    });
+
+    for (id, error) in report.failures {
+        println!("{}: failed ({})", names[&id], error);
+    }
+}
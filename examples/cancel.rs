@@ -0,0 +1,55 @@
+//! Starts a conversion job and cancels it after one second.
+//!
+//! `cargo run --example cancel`
+
+use std::time::Duration;
+
+use ffmpeg_gif_maker::{Command, Converter, Error, Message, Settings};
+
+mod common;
+
+const CANCEL_AFTER: Duration = Duration::from_secs(1);
+
+fn main() {
+    let settings = Settings::with_standard_fps(common::input_video(), 480);
+
+    let (converter, tx, mut rx) = Converter::new_with_channels();
+    let handle_converter_thread = std::thread::spawn(move || converter.convert(settings));
+
+    std::thread::spawn(move || {
+        std::thread::sleep(CANCEL_AFTER);
+        println!("Sending cancel command...");
+        // NOTE: This fails if the job is already over, which is fine.
+        common::send(&tx, Command::Cancel);
+    });
+
+    while let Some(message) = common::recv(&mut rx) {
+        match message {
+            Message::Progress(progress) => {
+                println!("Progress: {:.02} %", progress * 100.0);
+            }
+            Message::Error(Error::Cancelled {
+                progress_at_cancel,
+                bytes_discarded,
+            }) => {
+                println!(
+                    "Job cancelled (progress: {:?}, bytes discarded: {})",
+                    progress_at_cancel, bytes_discarded
+                );
+            }
+            Message::Error(e) => {
+                eprintln!("Error message received: {:?}", e);
+            }
+            Message::Success(bytes) => {
+                println!(
+                    "Job completed before being cancelled ({} bytes)",
+                    bytes.len()
+                );
+            }
+            Message::Done => break,
+            _ => {}
+        }
+    }
+
+    handle_converter_thread.join().expect("Failed to join");
+}
@@ -0,0 +1,52 @@
+//! Helpers shared by the examples (this is not an example itself).
+
+// NOTE: Not every example uses every helper.
+#![allow(dead_code)]
+
+use ffmpeg_gif_maker::{Command, CommandSender, Message, MessageReceiver};
+
+/// The sample video bundled with the repository.
+pub const SAMPLE_VIDEO_PATH: &str = "./assets/big-buck-bunny-clip.mp4";
+
+/// Returns the path of the sample video bundled with the repository or, if it is
+/// missing (e.g. when the crate was downloaded without its assets), the path of a
+/// 5 seconds long test video generated using FFmpeg's `lavfi` input device.
+pub fn input_video() -> String {
+    if std::path::Path::new(SAMPLE_VIDEO_PATH).exists() {
+        return SAMPLE_VIDEO_PATH.into();
+    }
+    let path = std::env::temp_dir().join("ffmpeg_gif_maker_example_input.mkv");
+    if !path.exists() {
+        println!("Sample video not found, so generating one at {:?}...", path);
+        let status = std::process::Command::new("ffmpeg")
+            .args(["-nostdin", "-loglevel", "error", "-y", "-f", "lavfi"])
+            .args(["-i", "testsrc2=duration=5:size=640x360:rate=24"])
+            .args(["-c:v", "ffv1"])
+            .arg(&path)
+            .status()
+            .expect("Failed to run FFmpeg (is it installed?)");
+        assert!(status.success(), "Failed to generate sample video");
+    }
+    path.to_string_lossy().into()
+}
+
+/// Blocks until the next message is received, or returns `None` once the channel
+/// is closed, whichever kind of channel is used (see the `tokio` and `async-channel`
+/// feature flags).
+pub fn recv(rx: &mut MessageReceiver) -> Option<Message> {
+    #[cfg(not(any(feature = "tokio", feature = "async-channel")))]
+    return rx.recv().ok();
+    #[cfg(feature = "tokio")]
+    return rx.blocking_recv();
+    #[cfg(feature = "async-channel")]
+    return rx.recv_blocking().ok();
+}
+
+/// Sends `command` to the job, returning `false` if the job is already over, whichever
+/// kind of channel is used (see the `tokio` and `async-channel` feature flags).
+pub fn send(tx: &CommandSender, command: Command) -> bool {
+    #[cfg(not(feature = "async-channel"))]
+    return tx.send(command).is_ok();
+    #[cfg(feature = "async-channel")]
+    return tx.send_blocking(command).is_ok();
+}
@@ -1,5 +1,6 @@
 use ffmpeg_gif_maker::{Converter, Message, Settings};
 
+#[allow(clippy::redundant_static_lifetimes)]
 const INPUT_VIDEO_PATH: &'static str = "./assets/big-buck-bunny-clip.mp4";
 const OUTPUT_GIF_WIDTH: u16 = 200;
 
@@ -27,12 +28,78 @@ async fn main() {
                 println!("Generated GIF size: {} bytes", bytes.len());
                 break;
             }
-            Message::Progress(progress) => {
+            Message::Progress(progress) | Message::InterpolatedProgress(progress) => {
                 println!("Progress: {:.02} %", (progress * 100.0).round() / 100.0);
             }
+            Message::FramesProcessed(n) => {
+                // NOTE: Emitted instead of `Message::Progress` while the duration is unknown.
+                println!("Frames processed so far: {}", n);
+            }
             Message::VideoDuration(duration) => {
                 println!("Received info about video duration: {:?}", duration);
             }
+            Message::OutputBytes(n) => {
+                println!("Output size so far: {} bytes", n);
+            }
+            Message::BytesWritten(n) => {
+                println!("Written to writer so far: {} bytes", n);
+            }
+            Message::Warning(warning) => {
+                eprintln!("Warning message received: {:?}", warning);
+            }
+            Message::ColorCountSelected(n) => {
+                println!("Number of colors selected for the palette: {}", n);
+            }
+            Message::CodecSelection {
+                decoder, encoder, ..
+            } => {
+                println!(
+                    "FFmpeg is using {} to decode and {} to encode",
+                    decoder, encoder
+                );
+            }
+            Message::OutputDimensions { width, height } => {
+                println!("The animated GIF will be {}x{}", width, height);
+            }
+            Message::FrameMap(timestamps) => {
+                println!(
+                    "Timestamps of the {} frames: {:?}",
+                    timestamps.len(),
+                    timestamps
+                );
+            }
+            Message::Summary(summary) => {
+                println!("Job summary: {:?}", summary);
+            }
+            Message::Preview(bytes) => {
+                // NOTE: Only emitted when `Settings::preview_width` is used.
+                println!("Generated a preview of {} bytes", bytes.len());
+            }
+            Message::Thumbnail { .. } => {
+                // NOTE: Only emitted by `Converter::extract_thumbnail_strip`.
+            }
+            Message::Saved { .. } | Message::Skipped { .. } => {
+                // NOTE: Only emitted when `Settings::output_file` is used.
+            }
+            Message::Internal(_) => {
+                // NOTE: Only emitted when `Settings::emit_internal_events` is enabled.
+            }
+            Message::Attempt { n, .. } => {
+                // NOTE: Only emitted when `Settings::max_output_bytes` is used.
+                println!("Attempt {} started", n);
+            }
+            Message::SuccessVariant { width, bytes } => {
+                // NOTE: Only emitted when `Settings::additional_widths` is used.
+                println!("Generated {} bytes at width {}", bytes.len(), width);
+            }
+            Message::SuccessFile(path) => {
+                // NOTE: Only emitted when `Settings::output_path` is used.
+                println!("Animated GIF written to {:?}", path);
+            }
+            Message::SuccessWritten(n) => {
+                // NOTE: Only emitted when `Converter::convert_to_writer` is used.
+                println!("Animated GIF written to writer ({} bytes)", n);
+            }
         }
     }
 
@@ -0,0 +1,135 @@
+//! Converts the sample video into animated GIFs of several widths, with at most two
+//! conversion jobs at the same time (using a `Batch`), while displaying one progress
+//! bar per job (using the `indicatif` crate's `MultiProgress`), and then a summary
+//! line per job.
+//!
+//! `cargo run --example multi_progress`
+
+use std::collections::HashMap;
+
+use ffmpeg_gif_maker::{Batch, BatchEvent, JobId, Message, Settings, Summary};
+use indicatif::{MultiProgress, ProgressBar, ProgressStyle};
+
+mod common;
+
+// NOTE: The items below are public because this example is also run by the test suite
+// (see `tests/examples.rs`), using generated inputs and without progress bars.
+
+/// The maximum number of conversion jobs running at the same time.
+pub const MAX_CONCURRENT_JOBS: usize = 2;
+
+/// The progress bars' length (i.e. the progress values are in thousandths).
+const PROGRESS_BAR_LENGTH: u64 = 1000;
+
+const WIDTHS: [u16; 3] = [120, 240, 480];
+
+/// What displays the progress of the jobs run by [`run_jobs`], each job being identified
+/// by the name it was given.
+pub trait ProgressSink {
+    /// The job named `name` was queued.
+    fn add(&mut self, name: &str);
+    /// The job named `name` reported its `progress` (between 0 and 1).
+    fn set_progress(&mut self, name: &str, progress: f64);
+    /// The job named `name` ended, as described by its `summary`.
+    fn finish(&mut self, name: &str, summary: &Summary);
+}
+
+/// A [`ProgressSink`] that displays nothing (e.g. when not running in a terminal).
+pub struct NoProgress;
+
+impl ProgressSink for NoProgress {
+    fn add(&mut self, _name: &str) {}
+    fn set_progress(&mut self, _name: &str, _progress: f64) {}
+    fn finish(&mut self, _name: &str, _summary: &Summary) {}
+}
+
+/// A [`ProgressSink`] that displays one progress bar per job.
+#[derive(Default)]
+pub struct ProgressBars {
+    multi: MultiProgress,
+    bars: HashMap<String, ProgressBar>,
+}
+
+impl ProgressSink for ProgressBars {
+    fn add(&mut self, name: &str) {
+        let bar = self.multi.add(ProgressBar::new(PROGRESS_BAR_LENGTH));
+        bar.set_style(
+            ProgressStyle::with_template("{prefix:>8} [{elapsed_precise}] {wide_bar} {percent}%")
+                .expect("Invalid template"),
+        );
+        bar.set_prefix(name.to_string());
+        self.bars.insert(name.into(), bar);
+    }
+
+    fn set_progress(&mut self, name: &str, progress: f64) {
+        if let Some(bar) = self.bars.get(name) {
+            bar.set_position((progress * PROGRESS_BAR_LENGTH as f64) as u64);
+        }
+    }
+
+    fn finish(&mut self, name: &str, summary: &Summary) {
+        if let Some(bar) = self.bars.get(name) {
+            match summary.error {
+                None => bar.finish(),
+                Some(_) => bar.abandon(),
+            }
+        }
+    }
+}
+
+/// Runs the conversion jobs (each one being given a name and its settings), with at most
+/// [`MAX_CONCURRENT_JOBS`] jobs at the same time, reporting their progress to `sink`, and
+/// returns their summaries (in completion order).
+pub fn run_jobs(jobs: Vec<(String, Settings)>, sink: &mut impl ProgressSink) -> Vec<Summary> {
+    let mut batch = Batch::new(MAX_CONCURRENT_JOBS);
+    for (name, settings) in jobs {
+        sink.add(&name);
+        batch.add_with_id(name, settings.smooth_progress(true));
+    }
+
+    let mut summaries = Vec::new();
+    batch.run(|event| match event {
+        BatchEvent::Message(
+            JobId::String(name),
+            Message::Progress(progress) | Message::InterpolatedProgress(progress),
+        ) => sink.set_progress(&name, progress),
+        BatchEvent::Message(JobId::String(name), Message::Summary(summary)) => {
+            sink.finish(&name, &summary);
+            summaries.push(summary);
+        }
+        _ => {}
+    });
+    summaries
+}
+
+/// A line describing the outcome of a job (e.g. `240px: Succeeded, 123456 bytes in 2.1s`).
+pub fn summary_line(summary: &Summary) -> String {
+    let outcome = match (&summary.error, summary.output_bytes) {
+        (Some(e), _) => format!("{:?} ({})", summary.outcome(), e),
+        (None, Some(n)) => format!("{:?}, {} bytes", summary.outcome(), n),
+        (None, None) => format!("{:?}", summary.outcome()),
+    };
+    format!(
+        "{}: {} in {:.1}s",
+        summary.id,
+        outcome,
+        summary.elapsed.as_secs_f64()
+    )
+}
+
+fn main() {
+    let input = common::input_video();
+    let jobs = WIDTHS
+        .iter()
+        .map(|&width| {
+            let settings = Settings::with_standard_fps(input.clone(), width);
+            (format!("{}px", width), settings)
+        })
+        .collect();
+
+    let mut bars = ProgressBars::default();
+    let summaries = run_jobs(jobs, &mut bars);
+    for summary in &summaries {
+        println!("{}", summary_line(summary));
+    }
+}
@@ -0,0 +1,49 @@
+//! Converts the sample video into an animated GIF and saves it to a file.
+//!
+//! `cargo run --example to_file -- [OUTPUT_PATH]` (`output.gif` by default)
+
+use std::path::Path;
+
+use ffmpeg_gif_maker::{Converter, Error, Message, Settings};
+
+// NOTE: Public because this example is also run by the test suite (see `tests/examples.rs`).
+pub mod common;
+
+const OUTPUT_GIF_WIDTH: u16 = 200;
+
+/// Converts the video at `input` into an animated GIF, which is written to `output`,
+/// and returns the number of bytes written.
+pub fn convert_to_file(input: &str, output: &Path) -> Result<usize, Box<dyn std::error::Error>> {
+    let settings = Settings::with_standard_fps(input.into(), OUTPUT_GIF_WIDTH);
+    let (converter, _tx, mut rx) = Converter::new_with_channels();
+    let handle_converter_thread = std::thread::spawn(move || converter.convert(settings));
+
+    let mut bytes = None;
+    let mut error = None;
+    while let Some(message) = common::recv(&mut rx) {
+        match message {
+            Message::Success(b) => bytes = Some(b),
+            Message::Error(e) => {
+                error.get_or_insert(e);
+            }
+            Message::Done => break,
+            _ => {}
+        }
+    }
+    handle_converter_thread.join().expect("Failed to join");
+
+    if let Some(e) = error {
+        return Err(e.into());
+    }
+    let bytes = bytes.ok_or(Error::MissingResult)?;
+    std::fs::write(output, &bytes)?;
+    Ok(bytes.len())
+}
+
+fn main() {
+    let output = std::env::args().nth(1).unwrap_or("output.gif".into());
+    match convert_to_file(&common::input_video(), Path::new(&output)) {
+        Ok(n) => println!("Saved {} bytes to {:?}", n, output),
+        Err(e) => eprintln!("Conversion failed: {:?}", e),
+    }
+}
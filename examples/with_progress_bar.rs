@@ -0,0 +1,49 @@
+//! Converts the sample video into an animated GIF while displaying a progress bar
+//! (using the `indicatif` crate).
+//!
+//! `cargo run --example with_progress_bar`
+
+use ffmpeg_gif_maker::{Converter, Message, Settings};
+use indicatif::{ProgressBar, ProgressStyle};
+
+mod common;
+
+/// The progress bar's length (i.e. the progress values are in thousandths).
+const PROGRESS_BAR_LENGTH: u64 = 1000;
+
+fn main() {
+    let settings = Settings::with_standard_fps(common::input_video(), 320).smooth_progress(true);
+
+    let (converter, _tx, mut rx) = Converter::new_with_channels();
+    let handle_converter_thread = std::thread::spawn(move || converter.convert(settings));
+
+    let bar = ProgressBar::new(PROGRESS_BAR_LENGTH);
+    bar.set_style(
+        ProgressStyle::with_template("{spinner} [{elapsed_precise}] {wide_bar} {percent}% {msg}")
+            .expect("Invalid template"),
+    );
+
+    while let Some(message) = common::recv(&mut rx) {
+        match message {
+            Message::Progress(progress) | Message::InterpolatedProgress(progress) => {
+                bar.set_position((progress * PROGRESS_BAR_LENGTH as f64) as u64);
+            }
+            Message::OutputBytes(n) => {
+                bar.set_message(format!("{} KiB", n / 1024));
+            }
+            Message::Warning(warning) => {
+                bar.println(format!("Warning: {:?}", warning));
+            }
+            Message::Error(e) => {
+                bar.abandon_with_message(format!("Error: {}", e));
+            }
+            Message::Success(bytes) => {
+                bar.finish_with_message(format!("{} bytes", bytes.len()));
+            }
+            Message::Done => break,
+            _ => {}
+        }
+    }
+
+    handle_converter_thread.join().expect("Failed to join");
+}
@@ -0,0 +1,179 @@
+//! Helpers used to build the arguments passed to the FFmpeg child processes.
+
+/// Makes sure that a user-supplied file `path` (e.g. the input video, but also any
+/// other file FFmpeg reads, such as a watermark, subtitles or a LUT) cannot be
+/// misinterpreted by FFmpeg, which is the case for:
+///
+/// * Paths starting with `-` (e.g. `-i.mp4`), which could be parsed as options;
+///   these are prefixed with `./` (a relative path being the only kind that can
+///   start with `-`).
+/// * Relative paths containing a `:` (e.g. `clip:1.mp4`), which could be parsed
+///   as a protocol; these are prefixed with `file:`.
+///
+/// Any other path is returned as is, so that the generated commands stay readable.
+pub(crate) fn path_arg(path: &str) -> String {
+    if path.starts_with('-') {
+        let path = format!("./{}", path);
+        return if path.contains(':') {
+            format!("file:{}", path)
+        } else {
+            path
+        };
+    }
+    if path.contains(':') && !std::path::Path::new(path).is_absolute() {
+        return format!("file:{}", path);
+    }
+    path.to_string()
+}
+
+/// The program to spawn and its leading arguments, i.e. the FFmpeg binary at `program`
+/// itself or, given a `wrapper` (see [`crate::Settings::command_wrapper`]), the wrapper's
+/// program, followed by the wrapper's own arguments and by the FFmpeg binary's path.
+pub(crate) fn wrapped_argv(
+    wrapper: &[std::ffi::OsString],
+    program: &std::path::Path,
+) -> (std::ffi::OsString, Vec<std::ffi::OsString>) {
+    match wrapper.split_first() {
+        None => (program.into(), vec![]),
+        Some((wrapper_program, wrapper_args)) => {
+            let mut args = wrapper_args.to_vec();
+            args.push(program.into());
+            (wrapper_program.clone(), args)
+        }
+    }
+}
+
+/// The command that runs the FFmpeg binary at `program`, through the `wrapper`, if any (see
+/// [`wrapped_argv`]), to which the FFmpeg arguments are then added.
+pub(crate) fn ffmpeg_command(
+    wrapper: &[std::ffi::OsString],
+    program: &std::path::Path,
+) -> std::process::Command {
+    let (program, args) = wrapped_argv(wrapper, program);
+    let mut command = std::process::Command::new(program);
+    command.args(args);
+    command
+}
+
+/// The callback provided using [`crate::Settings::argv_hook`], which can edit the FFmpeg
+/// arguments of the conversion job right before it is spawned.
+#[derive(Clone)]
+#[allow(clippy::type_complexity)]
+pub(crate) struct ArgvHook(
+    std::sync::Arc<std::sync::Mutex<Box<dyn Fn(&mut Vec<std::ffi::OsString>) + Send>>>,
+);
+
+impl ArgvHook {
+    pub(crate) fn new(hook: impl Fn(&mut Vec<std::ffi::OsString>) + Send + 'static) -> Self {
+        Self(std::sync::Arc::new(std::sync::Mutex::new(Box::new(hook))))
+    }
+
+    /// Calls the hook with the arguments, returning the panic's message if it panicked (in
+    /// which case the arguments may have been partially edited).
+    pub(crate) fn call(&self, args: &mut Vec<std::ffi::OsString>) -> Result<(), String> {
+        // NOTE: A hook that panicked before (e.g. in another job using the same settings)
+        // is called again, since the mutex guards no state of its own.
+        let hook = self.0.lock().unwrap_or_else(|e| e.into_inner());
+        std::panic::catch_unwind(std::panic::AssertUnwindSafe(|| hook(args))).map_err(|payload| {
+            match (
+                payload.downcast_ref::<&str>(),
+                payload.downcast_ref::<String>(),
+            ) {
+                (Some(message), _) => message.to_string(),
+                (_, Some(message)) => message.clone(),
+                _ => "unknown panic payload".into(),
+            }
+        })
+    }
+}
+
+impl std::fmt::Debug for ArgvHook {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str("ArgvHook")
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn os_strings(args: &[&str]) -> Vec<std::ffi::OsString> {
+        Vec::from_iter(args.iter().map(std::ffi::OsString::from))
+    }
+
+    #[test]
+    fn test_wrapped_argv() {
+        let ffmpeg = std::path::Path::new("/opt/ffmpeg/bin/ffmpeg");
+        // (wrapper, program, leading arguments)
+        let cases: [(&[&str], &str, &[&str]); 5] = [
+            (&[], "/opt/ffmpeg/bin/ffmpeg", &[]),
+            (
+                &["nice", "-n", "19"],
+                "nice",
+                &["-n", "19", "/opt/ffmpeg/bin/ffmpeg"],
+            ),
+            (
+                &["ionice", "-c", "3", "nice"],
+                "ionice",
+                &["-c", "3", "nice", "/opt/ffmpeg/bin/ffmpeg"],
+            ),
+            (
+                &["firejail", "--profile=ffmpeg"],
+                "firejail",
+                &["--profile=ffmpeg", "/opt/ffmpeg/bin/ffmpeg"],
+            ),
+            (&["wsl.exe"], "wsl.exe", &["/opt/ffmpeg/bin/ffmpeg"]),
+        ];
+        for (wrapper, program, args) in cases {
+            assert_eq!(
+                wrapped_argv(&os_strings(wrapper), ffmpeg),
+                (program.into(), os_strings(args)),
+                "{:?}",
+                wrapper
+            );
+        }
+    }
+
+    #[test]
+    fn test_ffmpeg_command() {
+        let ffmpeg = std::path::Path::new("ffmpeg");
+        let mut command = ffmpeg_command(&os_strings(&["nice", "-n", "19"]), ffmpeg);
+        command.args(["-stats", "-i", "video.mp4"]);
+        assert_eq!(command.get_program(), "nice");
+        assert_eq!(
+            Vec::from_iter(command.get_args()),
+            ["-n", "19", "ffmpeg", "-stats", "-i", "video.mp4"]
+        );
+        let command = ffmpeg_command(&[], ffmpeg);
+        assert_eq!(command.get_program(), "ffmpeg");
+        assert_eq!(command.get_args().count(), 0);
+    }
+
+    #[test]
+    fn test_path_arg_unchanged() {
+        for path in [
+            "video.mp4",
+            "./assets/big-buck-bunny-clip.mp4",
+            "/tmp/-i.mp4",
+            "a-b.mp4",
+        ] {
+            assert_eq!(path_arg(path), path);
+        }
+    }
+
+    #[test]
+    fn test_path_arg_leading_dash() {
+        assert_eq!(path_arg("-i.mp4"), "./-i.mp4");
+        assert_eq!(path_arg("-"), "./-");
+        assert_eq!(path_arg("--help"), "./--help");
+        assert_eq!(path_arg("-f:lavfi"), "file:./-f:lavfi");
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_path_arg_protocol_like() {
+        assert_eq!(path_arg("clip:1.mp4"), "file:clip:1.mp4");
+        assert_eq!(path_arg("http://example.com"), "file:http://example.com");
+        assert_eq!(path_arg("/tmp/clip:1.mp4"), "/tmp/clip:1.mp4");
+    }
+}
@@ -0,0 +1,91 @@
+use std::cell::Cell;
+
+const LOG_TARGET: &str = "ffmpeg_gif_maker::async_context";
+
+thread_local! {
+    /// Whether panics raised on the current thread should be kept quiet by
+    /// the panic hook installed by [`install_panic_hook`].
+    static SILENCE_PANICS: Cell<bool> = const { Cell::new(false) };
+}
+
+static INSTALL_PANIC_HOOK: std::sync::Once = std::sync::Once::new();
+
+/// Wraps the current panic hook (only once) so that the expected panic
+/// raised by [`is_blocking_in_async_context`] does not get printed.
+fn install_panic_hook() {
+    INSTALL_PANIC_HOOK.call_once(|| {
+        let previous = std::panic::take_hook();
+        std::panic::set_hook(Box::new(move |info| {
+            if !SILENCE_PANICS.with(|s| s.get()) {
+                previous(info);
+            }
+        }));
+    });
+}
+
+/// Returns `true` if the calling thread is one of tokio's runtime worker
+/// threads (or the thread running a runtime's `block_on`), i.e. a thread
+/// that must not be blocked, and `false` if it is a thread from tokio's
+/// blocking pool (e.g. inside `tokio::task::spawn_blocking`) or a thread
+/// unrelated to tokio.
+///
+/// NOTE: tokio does not expose that information directly, so we rely on the fact
+/// that `Handle::block_on` refuses (i.e. panics) when called from a thread
+/// that is driving asynchronous tasks, while it is allowed from the blocking
+/// pool. The panic is raised before the runtime's state is modified.
+pub(crate) fn is_blocking_in_async_context() -> bool {
+    let Ok(handle) = tokio::runtime::Handle::try_current() else {
+        log::trace!(target: LOG_TARGET, "No tokio runtime found for current thread.");
+        return false;
+    };
+    install_panic_hook();
+    SILENCE_PANICS.with(|s| s.set(true));
+    let result =
+        std::panic::catch_unwind(std::panic::AssertUnwindSafe(|| handle.block_on(async {})));
+    SILENCE_PANICS.with(|s| s.set(false));
+    let blocking = result.is_err();
+    log::debug!(target: LOG_TARGET, "Tokio runtime found for current thread (blocking in async context: {}).", blocking);
+    blocking
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_outside_runtime() {
+        assert!(!is_blocking_in_async_context());
+    }
+
+    #[tokio::test(flavor = "multi_thread")]
+    async fn test_multi_thread_runtime() {
+        assert!(is_blocking_in_async_context());
+        let spawned = tokio::spawn(async { is_blocking_in_async_context() })
+            .await
+            .unwrap();
+        assert!(spawned);
+    }
+
+    #[tokio::test(flavor = "current_thread")]
+    async fn test_current_thread_runtime() {
+        assert!(is_blocking_in_async_context());
+    }
+
+    #[tokio::test(flavor = "multi_thread")]
+    async fn test_spawn_blocking() {
+        let blocking = tokio::task::spawn_blocking(is_blocking_in_async_context)
+            .await
+            .unwrap();
+        assert!(!blocking);
+        // Make sure that the probe did not leave the runtime in a bad state.
+        assert!(tokio::spawn(async { 42 }).await.is_ok());
+    }
+
+    #[tokio::test(flavor = "multi_thread")]
+    async fn test_std_thread_inside_runtime() {
+        let blocking = std::thread::spawn(is_blocking_in_async_context)
+            .join()
+            .unwrap();
+        assert!(!blocking);
+    }
+}
@@ -0,0 +1,314 @@
+//! Helpers to run the short-lived auxiliary FFmpeg child processes that
+//! some options require before the main conversion job (e.g. the palette
+//! analysis pass used by [`crate::Settings::auto_colors`]).
+
+use std::time::Duration;
+
+use crate::deadline::Deadline;
+use crate::input::{self, InputSource};
+
+const LOG_TARGET: &str = "ffmpeg_gif_maker::auxiliary";
+
+/// The interval at which the auxiliary child process is polled for completion.
+const POLL_INTERVAL_MS: u64 = 20;
+
+#[derive(Debug)]
+/// An error returned by [`run_auxiliary`].
+pub(crate) enum AuxiliaryError {
+    /// The child process could not be spawned.
+    Spawn(std::io::Error),
+    /// The child process' output could not be read, or waiting on it failed.
+    Io(std::io::Error),
+    /// The child process exited with a non-zero exit code (or was terminated by a signal).
+    ExitCode(Option<i32>),
+    /// The `should_cancel` callback returned `true`, so the child process was killed.
+    Cancelled,
+    /// The child process did not exit within the given timeout, so it was killed.
+    TimedOut(Duration),
+}
+
+impl std::fmt::Display for AuxiliaryError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::Spawn(e) => write!(f, "failed to spawn child process: {}", e),
+            Self::Io(e) => write!(f, "child process I/O error: {}", e),
+            Self::ExitCode(code) => write!(f, "child process exited with code {:?}", code),
+            Self::Cancelled => write!(f, "child process cancelled"),
+            Self::TimedOut(d) => write!(f, "child process did not exit within {:?}", d),
+        }
+    }
+}
+
+#[derive(Debug)]
+/// What an auxiliary child process wrote and how it exited.
+pub(crate) struct AuxiliaryOutput {
+    /// Everything the child process wrote to `stdout` (empty when streamed to a callback).
+    pub(crate) stdout: Vec<u8>,
+    /// Everything the child process wrote to `stderr`.
+    pub(crate) stderr: Vec<u8>,
+    /// The child process' exit status.
+    pub(crate) status: std::process::ExitStatus,
+}
+
+impl AuxiliaryOutput {
+    /// Returns the data written to `stdout`, provided that the child process exited successfully.
+    pub(crate) fn into_stdout(self) -> Result<Vec<u8>, AuxiliaryError> {
+        if !self.status.success() {
+            return Err(AuxiliaryError::ExitCode(self.status.code()));
+        }
+        Ok(self.stdout)
+    }
+}
+
+/// Runs `command`, collecting everything it writes to `stdout` and `stderr`, while
+/// regularly calling `should_cancel` to know whether the child process should be killed.
+/// The child process is also killed if it does not exit within `timeout` (if any), in
+/// which case [`AuxiliaryError::TimedOut`] is returned.
+pub(crate) fn run_auxiliary(
+    mut command: std::process::Command,
+    timeout: Option<Duration>,
+    should_cancel: impl FnMut() -> bool,
+) -> Result<AuxiliaryOutput, AuxiliaryError> {
+    command.stdin(std::process::Stdio::null());
+    collect(command, None, timeout, should_cancel)
+}
+
+/// Same as [`run_auxiliary`], but first sets `command` up so that the child process can
+/// read `input` (see [`InputSource::prepare`]), whose bytes (if any, see
+/// [`InputSource::Bytes`]) are written to its `stdin` by a separate thread. An input that
+/// can only be read once (see [`InputSource::Reader`]) is left for the conversion itself.
+pub(crate) fn run_auxiliary_reading(
+    mut command: std::process::Command,
+    input: &InputSource,
+    timeout: Option<Duration>,
+    should_cancel: impl FnMut() -> bool,
+) -> Result<AuxiliaryOutput, AuxiliaryError> {
+    if input.is_read_once() {
+        return Err(AuxiliaryError::Spawn(std::io::Error::new(
+            std::io::ErrorKind::Unsupported,
+            "the input can only be read once",
+        )));
+    }
+    command.stdin(std::process::Stdio::null());
+    input.prepare(&mut command).map_err(AuxiliaryError::Spawn)?;
+    collect(command, input.bytes().cloned(), timeout, should_cancel)
+}
+
+/// Runs `command`, writing `stdin` (if any) to it, and collecting its `stdout`.
+fn collect(
+    command: std::process::Command,
+    stdin: Option<std::sync::Arc<Vec<u8>>>,
+    timeout: Option<Duration>,
+    should_cancel: impl FnMut() -> bool,
+) -> Result<AuxiliaryOutput, AuxiliaryError> {
+    let stdout = std::sync::Arc::new(std::sync::Mutex::new(vec![]));
+    let stdout_clone = std::sync::Arc::clone(&stdout);
+    let mut output = spawn(command, stdin, timeout, should_cancel, move |chunk| {
+        stdout_clone
+            .lock()
+            .unwrap_or_else(|e| e.into_inner())
+            .extend_from_slice(chunk)
+    })?;
+    output.stdout = std::mem::take(&mut *stdout.lock().unwrap_or_else(|e| e.into_inner()));
+    Ok(output)
+}
+
+/// Same as [`run_auxiliary`], but `on_stdout` gets called (on a separate thread) with
+/// each chunk of data read from `stdout` instead of collecting it.
+pub(crate) fn run_auxiliary_streaming(
+    mut command: std::process::Command,
+    timeout: Option<Duration>,
+    should_cancel: impl FnMut() -> bool,
+    on_stdout: impl FnMut(&[u8]) + Send + 'static,
+) -> Result<AuxiliaryOutput, AuxiliaryError> {
+    command.stdin(std::process::Stdio::null());
+    spawn(command, None, timeout, should_cancel, on_stdout)
+}
+
+/// Runs `command`, writing `stdin` (if any) to it, and calling `on_stdout` with its `stdout`.
+/// The child process' `stdin` must already be set up (see [`InputSource::prepare`]).
+fn spawn(
+    mut command: std::process::Command,
+    stdin: Option<std::sync::Arc<Vec<u8>>>,
+    timeout: Option<Duration>,
+    mut should_cancel: impl FnMut() -> bool,
+    mut on_stdout: impl FnMut(&[u8]) + Send + 'static,
+) -> Result<AuxiliaryOutput, AuxiliaryError> {
+    log::debug!(target: LOG_TARGET, "Spawning auxiliary child process: {:?}", command);
+    let mut child = command
+        .stdout(std::process::Stdio::piped())
+        .stderr(std::process::Stdio::piped())
+        .spawn()
+        .map_err(AuxiliaryError::Spawn)?;
+    let deadline = timeout.map(Deadline::after);
+
+    // NOTE: Written on a separate thread as well, since the child process may only read
+    // its input as fast as its outputs are drained. The thread is not joined: it exits
+    // once everything is written, or once the child process exits (or gets killed).
+    if let (Some(bytes), Some(pipe)) = (stdin, child.stdin.take()) {
+        std::thread::spawn(move || {
+            if let Err(e) = input::pump(pipe, &bytes[..], || false) {
+                log::warn!(target: LOG_TARGET, "Failed to write input to auxiliary child process: {:?}", e);
+            }
+        });
+    }
+
+    // NOTE: The outputs need to be drained on separate threads, else the child
+    // process could block on a full pipe while we are waiting for it to exit.
+    let (Some(mut stdout), Some(mut stderr)) = (child.stdout.take(), child.stderr.take()) else {
+        let _ = child.kill();
+        return Err(AuxiliaryError::Io(std::io::Error::other(
+            "Failed to take STDOUT or STDERR",
+        )));
+    };
+    let handle_stdout = std::thread::spawn(move || -> std::io::Result<()> {
+        use std::io::Read;
+        let mut buf = vec![0u8; 64 * 1024];
+        loop {
+            match stdout.read(&mut buf) {
+                Ok(0) => return Ok(()),
+                Ok(n) => on_stdout(&buf[..n]),
+                Err(e) if e.kind() == std::io::ErrorKind::Interrupted => {}
+                Err(e) => return Err(e),
+            }
+        }
+    });
+    let handle_stderr = std::thread::spawn(move || {
+        use std::io::Read;
+        let mut buf = vec![];
+        stderr.read_to_end(&mut buf).map(|_| buf)
+    });
+
+    let status = loop {
+        if should_cancel() {
+            log::info!(target: LOG_TARGET, "Cancellation requested, so killing auxiliary child process...");
+            let _ = child.kill();
+            let _ = child.wait();
+            // NOTE: Not joining the output threads here, since the pipes could be kept
+            // open by the child's own children; the threads will exit once they close.
+            return Err(AuxiliaryError::Cancelled);
+        }
+        if let Some(deadline) = deadline.filter(Deadline::expired) {
+            log::warn!(target: LOG_TARGET, "Auxiliary child process did not exit within {:?}, so killing it...", deadline.timeout());
+            let _ = child.kill();
+            let _ = child.wait();
+            return Err(AuxiliaryError::TimedOut(deadline.timeout()));
+        }
+        match child.try_wait() {
+            Ok(Some(status)) => break status,
+            Ok(None) => std::thread::sleep(Duration::from_millis(POLL_INTERVAL_MS)),
+            Err(e) => {
+                let _ = child.kill();
+                return Err(AuxiliaryError::Io(e));
+            }
+        }
+    };
+
+    let joined = (handle_stdout.join(), handle_stderr.join());
+    let stderr = match joined {
+        (Ok(Ok(())), Ok(Ok(stderr))) => stderr,
+        (Ok(Err(e)), _) | (_, Ok(Err(e))) => return Err(AuxiliaryError::Io(e)),
+        _ => {
+            return Err(AuxiliaryError::Io(std::io::Error::other(
+                "Output thread panicked",
+            )))
+        }
+    };
+    log::debug!(target: LOG_TARGET, "Auxiliary child process exited with status {:?}.", status);
+    Ok(AuxiliaryOutput {
+        stdout: vec![],
+        stderr,
+        status,
+    })
+}
+
+#[cfg(all(test, unix))]
+mod tests {
+    use super::*;
+
+    fn sh(script: &str) -> std::process::Command {
+        let mut command = std::process::Command::new("sh");
+        command.arg("-c").arg(script);
+        command
+    }
+
+    #[test]
+    fn test_run_auxiliary_output() {
+        let output = run_auxiliary(sh("printf hello; printf world >&2"), None, || false).unwrap();
+        assert!(output.status.success());
+        assert_eq!(output.stderr, b"world");
+        assert_eq!(output.into_stdout().unwrap(), b"hello");
+    }
+
+    #[test]
+    fn test_run_auxiliary_exit_code() {
+        let result = run_auxiliary(sh("exit 3"), None, || false).and_then(|o| o.into_stdout());
+        assert!(matches!(result, Err(AuxiliaryError::ExitCode(Some(3)))));
+    }
+
+    #[test]
+    fn test_run_auxiliary_spawn_error() {
+        let result = run_auxiliary(
+            std::process::Command::new("./non-existing-binary"),
+            None,
+            || false,
+        );
+        assert!(matches!(result, Err(AuxiliaryError::Spawn(_))));
+    }
+
+    #[test]
+    fn test_run_auxiliary_streaming() {
+        let chunks = std::sync::Arc::new(std::sync::Mutex::new(vec![]));
+        let chunks_clone = std::sync::Arc::clone(&chunks);
+        let output = run_auxiliary_streaming(
+            sh("printf a; sleep 0.2; printf b"),
+            None,
+            || false,
+            move |chunk| chunks_clone.lock().unwrap().push(chunk.to_vec()),
+        )
+        .unwrap();
+        assert!(output.stdout.is_empty());
+        assert_eq!(chunks.lock().unwrap().concat(), b"ab");
+    }
+
+    #[test]
+    fn test_run_auxiliary_reading() {
+        // NOTE: More than a pipe's capacity, which must be drained while being written.
+        let bytes = Vec::from_iter((0..1_000_000u32).map(|i| i as u8));
+        let input = InputSource::from(bytes.clone());
+        let output =
+            run_auxiliary_reading(sh("cat; printf done >&2"), &input, None, || false).unwrap();
+        assert_eq!(output.stderr, b"done");
+        assert_eq!(output.into_stdout().unwrap(), bytes);
+
+        // NOTE: A child process that does not read its input is not blocked by it.
+        let output = run_auxiliary_reading(sh("head -c 3"), &input, None, || false).unwrap();
+        assert_eq!(output.into_stdout().unwrap(), [0, 1, 2]);
+    }
+
+    #[test]
+    fn test_run_auxiliary_cancelled() {
+        let started = std::time::Instant::now();
+        let mut polls = 0;
+        let result = run_auxiliary(sh("sleep 10"), None, || {
+            polls += 1;
+            polls > 3
+        });
+        assert!(matches!(result, Err(AuxiliaryError::Cancelled)));
+        assert!(started.elapsed() < Duration::from_secs(5));
+    }
+
+    #[test]
+    fn test_run_auxiliary_timed_out() {
+        // NOTE: A stand-in for a child process that hangs on a crafted input.
+        let started = std::time::Instant::now();
+        let timeout = Duration::from_millis(200);
+        let result = run_auxiliary(sh("while :; do sleep 1; done"), Some(timeout), || false);
+        assert!(matches!(result, Err(AuxiliaryError::TimedOut(t)) if t == timeout));
+        assert!(started.elapsed() < Duration::from_secs(5));
+
+        // Not killed when exiting in time.
+        let output = run_auxiliary(sh("printf done"), Some(Duration::from_secs(5)), || false);
+        assert_eq!(output.unwrap().into_stdout().unwrap(), b"done");
+    }
+}
@@ -0,0 +1,662 @@
+//! The conversion of several videos (e.g. a whole folder), using a bounded number of
+//! concurrent conversion jobs, whose failures are collected instead of requiring the
+//! application to handle them job by job (see [`Batch`]).
+//!
+//! The jobs wait in a single queue, from which the workers take them. The settings of a
+//! job that is still in the queue can be replaced (see [`BatchHandle::update_pending`]),
+//! which is done while holding the queue's lock, as is the removal of a job from the
+//! queue: the update wins only if the job has not been taken yet, and a job always runs
+//! with one set of settings or the other, as a whole.
+
+use std::collections::{HashMap, HashSet, VecDeque};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Mutex};
+
+use crate::JobId;
+use crate::{
+    CacheConfig, Command, CommandSender, Converter, Error, Message, MessageReceiver, Settings,
+};
+
+const LOG_TARGET: &str = "ffmpeg_gif_maker::batch";
+
+/// A queued conversion job.
+struct Job {
+    converter: Converter,
+    tx: CommandSender,
+    rx: MessageReceiver,
+    settings: Settings,
+}
+
+/// The jobs that have not been started yet, in order, along with the identifiers of the
+/// jobs that have been taken from the queue (whether they were then started or aborted).
+#[derive(Default)]
+struct Queue {
+    pending: VecDeque<Job>,
+    dequeued: HashSet<JobId>,
+}
+
+impl Queue {
+    /// Takes the next job from the queue.
+    fn pop(&mut self) -> Option<Job> {
+        let job = self.pending.pop_front()?;
+        self.dequeued.insert(job.converter.id());
+        Some(job)
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+/// A problem found by [`BatchHandle::update_pending`].
+pub enum UpdateError {
+    /// The job has already been taken from the queue (i.e. it has started, or has been
+    /// aborted, see [`Batch::fail_fast`]), so its settings can no longer be replaced.
+    AlreadyStarted,
+    /// No job with the given identifier was added to the batch.
+    UnknownJob,
+}
+
+impl std::error::Error for UpdateError {}
+
+impl std::fmt::Display for UpdateError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::AlreadyStarted => write!(f, "the job has already been started"),
+            Self::UnknownJob => write!(f, "no such job in the batch"),
+        }
+    }
+}
+
+#[derive(Clone)]
+/// A handle to the queue of a [`Batch`] (see [`Batch::handle`]), which stays usable while
+/// the batch is running (e.g. from the application's UI thread).
+pub struct BatchHandle {
+    queue: Arc<Mutex<Queue>>,
+}
+
+impl BatchHandle {
+    /// Replaces the settings of the given job, provided that it has not been started yet
+    /// (see [`UpdateError::AlreadyStarted`]), e.g. after the user changed the width of the
+    /// animated GIFs while the batch is running. The job then runs with `settings` only.
+    pub fn update_pending(&self, id: &JobId, settings: Settings) -> Result<(), UpdateError> {
+        let mut queue = self.queue.lock().unwrap_or_else(|e| e.into_inner());
+        if let Some(job) = queue
+            .pending
+            .iter_mut()
+            .find(|job| job.converter.id() == *id)
+        {
+            log::debug!(target: LOG_TARGET, "Settings of pending job {} replaced.", id);
+            job.settings = settings;
+            return Ok(());
+        }
+        match queue.dequeued.contains(id) {
+            true => Err(UpdateError::AlreadyStarted),
+            false => Err(UpdateError::UnknownJob),
+        }
+    }
+}
+
+#[derive(Debug)]
+/// An event reported by [`Batch::run`], tagged with the job it relates to.
+// NOTE: Almost all the events are messages, so boxing them would not save any memory.
+#[allow(clippy::large_enum_variant)]
+pub enum BatchEvent {
+    /// A message sent by the given job (e.g. [`Message::Error`] when the job failed,
+    /// in which case the error is also recorded in [`BatchReport::failures`]).
+    Message(JobId, Message),
+    /// The given job was never started, since another job failed and
+    /// [`Batch::fail_fast`] is enabled.
+    Aborted(JobId),
+}
+
+#[derive(Debug, Clone, Default)]
+/// The outcome of the jobs run by [`Batch::run`], each job being listed exactly once.
+pub struct BatchReport {
+    /// The jobs that succeeded (i.e. that sent [`Message::Success`] or [`Message::SuccessFile`]),
+    /// in completion order.
+    pub succeeded: Vec<JobId>,
+    /// The jobs that failed, along with the first error they sent, in failure order.
+    pub failures: Vec<(JobId, Error)>,
+    /// The jobs that were running when another job failed, and that were cancelled
+    /// because [`Batch::fail_fast`] is enabled.
+    pub cancelled: Vec<JobId>,
+    /// The jobs that were never started, because [`Batch::fail_fast`] is enabled.
+    pub aborted: Vec<JobId>,
+    /// The jobs whose output file already existed (i.e. that sent [`Message::Skipped`]; see
+    /// [`crate::Settings::output_file`]), in completion order.
+    pub skipped: Vec<JobId>,
+}
+
+/// What the workers share with each other.
+struct Shared {
+    queue: Arc<Mutex<Queue>>,
+    /// The command senders of the running jobs, used to cancel them.
+    running: Mutex<HashMap<JobId, CommandSender>>,
+    /// Whether the remaining jobs are being aborted (see [`Batch::fail_fast`]).
+    aborting: AtomicBool,
+    fail_fast: bool,
+    cache: Option<CacheConfig>,
+}
+
+impl Shared {
+    /// Cancels the running jobs and makes sure that the queued ones never start,
+    /// once a job (i.e. `failed`) has failed.
+    fn abort(&self, failed: &JobId) {
+        // NOTE: The flag is set while holding the lock, so that a job is either
+        // registered before (and then cancelled) or never started.
+        let running = self.running.lock().unwrap_or_else(|e| e.into_inner());
+        if self.aborting.swap(true, Ordering::SeqCst) {
+            return;
+        }
+        log::info!(target: LOG_TARGET, "Job {} failed, so aborting the batch ({} running job(s) to cancel)...", failed, running.len().saturating_sub(1));
+        for (id, tx) in running.iter().filter(|(id, _)| *id != failed) {
+            // NOTE: The job may have ended in the meantime.
+            #[cfg(not(feature = "async-channel"))]
+            let sent = tx.send(Command::Cancel);
+            #[cfg(feature = "async-channel")]
+            let sent = tx.send_blocking(Command::Cancel);
+            if let Err(e) = sent {
+                log::debug!(target: LOG_TARGET, "Failed to send cancel command to job {}: {:?}", id, e);
+            }
+        }
+    }
+}
+
+/// A set of conversion jobs, which are run (by [`Batch::run`]) with at most a given
+/// number of jobs at the same time. By default, a failed job does not stop the other
+/// ones: the failures are recorded in the [`BatchReport`] instead (see also
+/// [`Batch::fail_fast`]).
+///
+/// ```no_run
+/// use ffmpeg_gif_maker::{Batch, BatchEvent, Message, Settings};
+///
+/// let mut batch = Batch::new(2);
+/// for path in ["a.mp4", "b.mp4", "c.mp4"] {
+///     batch.add(Settings::with_standard_fps(path.into(), 200));
+/// }
+/// let report = batch.run(|event| {
+///     if let BatchEvent::Message(id, Message::Success(bytes)) = event {
+///         println!("Job {}: {} bytes", id, bytes.len());
+///     }
+/// });
+/// for (id, error) in report.failures {
+///     eprintln!("Job {} failed: {}", id, error);
+/// }
+/// ```
+pub struct Batch {
+    max_concurrent_jobs: usize,
+    fail_fast: bool,
+    cache: Option<CacheConfig>,
+    queue: Arc<Mutex<Queue>>,
+}
+
+impl Batch {
+    /// Creates an empty batch, whose jobs are run with at most `max_concurrent_jobs`
+    /// (at least 1) jobs at the same time.
+    pub fn new(max_concurrent_jobs: usize) -> Self {
+        Self {
+            max_concurrent_jobs: max_concurrent_jobs.max(1),
+            fail_fast: false,
+            cache: None,
+            queue: Arc::default(),
+        }
+    }
+
+    /// A setter method that allows stopping the batch as soon as a job fails (i.e.
+    /// sends a [`Message::Error`] other than [`Error::Cancelled`]), in which case:
+    /// * the jobs that are still queued are never started (see [`BatchEvent::Aborted`]
+    ///   and [`BatchReport::aborted`]);
+    /// * the jobs that are running are cancelled (see [`Command::Cancel`]) and listed
+    ///   in [`BatchReport::cancelled`], except for those that end (successfully or not)
+    ///   before processing the cancellation, which are reported as usual.
+    ///
+    /// Only the first failure triggers the abort. Disabled by default.
+    pub fn fail_fast(self, fail_fast: bool) -> Self {
+        Self { fail_fast, ..self }
+    }
+
+    /// A setter method that allows the batch's jobs to share a conversion result cache
+    /// (see [`Converter::cache`]), so that a job whose animated GIF has already been
+    /// generated (e.g. by an earlier batch) succeeds without spawning FFmpeg.
+    pub fn cache(self, cache: CacheConfig) -> Self {
+        Self {
+            cache: Some(cache),
+            ..self
+        }
+    }
+
+    /// Queues a conversion job using `settings`, and returns the job's identifier
+    /// (i.e. its [`Converter::id`]), which tags its events.
+    pub fn add(&mut self, settings: Settings) -> JobId {
+        let (converter, tx, rx) = Converter::new_with_channels();
+        self.push(converter, tx, rx, settings)
+    }
+
+    /// Same as [`Batch::add`], except that the job is identified by the application's
+    /// own identifier (see [`Converter::with_id`]), which must be unique within the batch.
+    pub fn add_with_id(&mut self, id: impl Into<JobId>, settings: Settings) -> JobId {
+        let (converter, tx, rx) = Converter::new_with_channels();
+        self.push(converter.with_id(id), tx, rx, settings)
+    }
+
+    /// Queues the job run by `converter`, and returns its identifier.
+    fn push(
+        &mut self,
+        converter: Converter,
+        tx: CommandSender,
+        rx: MessageReceiver,
+        settings: Settings,
+    ) -> JobId {
+        let id = converter.id();
+        self.queue
+            .lock()
+            .unwrap_or_else(|e| e.into_inner())
+            .pending
+            .push_back(Job {
+                converter,
+                tx,
+                rx,
+                settings,
+            });
+        id
+    }
+
+    /// The number of queued jobs.
+    pub fn len(&self) -> usize {
+        self.queue
+            .lock()
+            .unwrap_or_else(|e| e.into_inner())
+            .pending
+            .len()
+    }
+
+    /// Whether no job has been queued.
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// Returns a handle to the batch's queue, through which the settings of the jobs that
+    /// have not been started yet can be replaced, including while [`Batch::run`] is running.
+    pub fn handle(&self) -> BatchHandle {
+        BatchHandle {
+            queue: Arc::clone(&self.queue),
+        }
+    }
+
+    /// Same as [`BatchHandle::update_pending`].
+    pub fn update_pending(&self, id: &JobId, settings: Settings) -> Result<(), UpdateError> {
+        self.handle().update_pending(id, settings)
+    }
+
+    /// Runs the queued jobs (in order) and blocks until they have all ended, passing
+    /// their events to `on_event` as they arrive (from the calling thread), and returns
+    /// the [`BatchReport`].
+    ///
+    /// NOTE: Just like [`Converter::convert`], this method is blocking (e.g. it should
+    /// be called inside `tokio::task::spawn_blocking` when using the `tokio` feature flag).
+    pub fn run(self, mut on_event: impl FnMut(BatchEvent)) -> BatchReport {
+        let jobs = self.len();
+        let workers = self.max_concurrent_jobs.min(jobs);
+        log::info!(target: LOG_TARGET, "Running {} job(s) using {} worker(s)...", jobs, workers);
+        let shared = Arc::new(Shared {
+            queue: self.queue,
+            running: Mutex::new(HashMap::new()),
+            aborting: AtomicBool::new(false),
+            fail_fast: self.fail_fast,
+            cache: self.cache,
+        });
+        let (events_tx, events_rx) = std::sync::mpsc::channel();
+        let handles: Vec<_> = (0..workers)
+            .map(|_| {
+                let shared = Arc::clone(&shared);
+                let events_tx = events_tx.clone();
+                std::thread::spawn(move || work(&shared, &events_tx))
+            })
+            .collect();
+        drop(events_tx);
+
+        let mut report = BatchReport::default();
+        let mut failed = std::collections::HashSet::new();
+        // NOTE: The loop ends once all the workers have exited.
+        while let Ok(event) = events_rx.recv() {
+            match &event {
+                BatchEvent::Message(id, Message::Success(_) | Message::SuccessFile(_)) => {
+                    report.succeeded.push(id.clone())
+                }
+                BatchEvent::Message(id, Message::Skipped { .. }) => report.skipped.push(id.clone()),
+                BatchEvent::Message(id, Message::Error(e)) if failed.insert(id.clone()) => {
+                    match e {
+                        Error::Cancelled { .. } if shared.aborting.load(Ordering::SeqCst) => {
+                            report.cancelled.push(id.clone())
+                        }
+                        e => report.failures.push((id.clone(), e.clone())),
+                    }
+                }
+                BatchEvent::Aborted(id) => report.aborted.push(id.clone()),
+                _ => {}
+            }
+            on_event(event);
+        }
+        for handle in handles {
+            if let Err(e) = handle.join() {
+                log::error!(target: LOG_TARGET, "Failed to join worker thread: {:?}", e);
+            }
+        }
+        log::info!(target: LOG_TARGET, "Batch ended: {} succeeded, {} failed, {} cancelled, {} aborted, {} skipped.", report.succeeded.len(), report.failures.len(), report.cancelled.len(), report.aborted.len(), report.skipped.len());
+        report
+    }
+}
+
+/// Runs the queued jobs, one at a time, until the queue is empty.
+fn work(shared: &Shared, events: &std::sync::mpsc::Sender<BatchEvent>) {
+    loop {
+        let Some(job) = shared.queue.lock().unwrap_or_else(|e| e.into_inner()).pop() else {
+            break;
+        };
+        let id = job.converter.id();
+        {
+            let mut running = shared.running.lock().unwrap_or_else(|e| e.into_inner());
+            if shared.aborting.load(Ordering::SeqCst) {
+                log::debug!(target: LOG_TARGET, "Job {} aborted before being started.", id);
+                let _ = events.send(BatchEvent::Aborted(id));
+                continue;
+            }
+            running.insert(id.clone(), job.tx.clone());
+        }
+        run_job(shared, job, events);
+        shared
+            .running
+            .lock()
+            .unwrap_or_else(|e| e.into_inner())
+            .remove(&id);
+    }
+}
+
+/// Runs `job`, forwarding its messages (until [`Message::Done`]) to `events`.
+fn run_job(shared: &Shared, job: Job, events: &std::sync::mpsc::Sender<BatchEvent>) {
+    let Job {
+        converter,
+        tx: _tx,
+        rx,
+        settings,
+    } = job;
+    let id = converter.id();
+    let converter = match &shared.cache {
+        Some(cache) => converter.cache(cache.clone()),
+        None => converter,
+    };
+    log::debug!(target: LOG_TARGET, "Starting job {}...", id);
+    let handle = std::thread::spawn(move || converter.convert(settings));
+    #[cfg(not(any(feature = "tokio", feature = "async-channel")))]
+    let next = move || rx.recv().ok();
+    #[cfg(feature = "tokio")]
+    let mut next = {
+        let mut rx = rx;
+        move || rx.blocking_recv()
+    };
+    #[cfg(feature = "async-channel")]
+    let next = move || rx.recv_blocking().ok();
+    while let Some(message) = next() {
+        let done = matches!(message, Message::Done);
+        if let Message::Error(e) = &message {
+            if shared.fail_fast && !matches!(e, Error::Cancelled { .. }) {
+                shared.abort(&id);
+            }
+        }
+        // NOTE: The application's side of the batch may only be gone if `on_event` panicked.
+        let _ = events.send(BatchEvent::Message(id.clone(), message));
+        if done {
+            break;
+        }
+    }
+    if let Err(e) = handle.join() {
+        log::error!(target: LOG_TARGET, "Failed to join converter thread of job {}: {:?}", id, e);
+    }
+}
+
+#[cfg(all(test, unix))]
+mod tests {
+    use super::*;
+    use crate::test_utils::{
+        fake_ffmpeg, fake_ffmpeg_with_progress_script, fake_ffmpeg_with_script, init_logging,
+        sample_gif, temp_dir, SAMPLE_STDERR, SAMPLE_VIDEO_PATH,
+    };
+
+    fn settings(path: std::path::PathBuf) -> Settings {
+        Settings::with_standard_fps(SAMPLE_VIDEO_PATH.into(), 200)
+            .ffmpeg_path(path.to_string_lossy())
+    }
+
+    /// Runs `batch`, and returns its report along with its events.
+    fn run(batch: Batch) -> (BatchReport, Vec<BatchEvent>) {
+        let mut events = vec![];
+        let report = batch.run(|event| events.push(event));
+        (report, events)
+    }
+
+    #[test]
+    fn test_soft_fail() {
+        init_logging();
+
+        let gif = sample_gif(2, Some(0));
+        let mut batch = Batch::new(2);
+        let first = batch.add(settings(fake_ffmpeg(SAMPLE_STDERR, &gif, 0)));
+        let corrupt = batch.add(settings(fake_ffmpeg(SAMPLE_STDERR, b"", 0)));
+        let last = batch.add(settings(fake_ffmpeg(SAMPLE_STDERR, &gif, 0)));
+        assert_eq!(batch.len(), 3);
+        let (report, events) = run(batch);
+
+        let mut succeeded = report.succeeded.clone();
+        succeeded.sort();
+        let mut expected = vec![first.clone(), last.clone()];
+        expected.sort();
+        assert_eq!(succeeded, expected);
+        assert!(matches!(
+            &report.failures[..],
+            [(id, Error::EmptyStdout)] if *id == corrupt
+        ));
+        assert!(report.cancelled.is_empty());
+        assert!(report.aborted.is_empty());
+
+        // The failure was also reported as it happened, tagged with the job's identifier.
+        assert!(events.iter().any(|e| matches!(
+            e,
+            BatchEvent::Message(id, Message::Error(Error::EmptyStdout)) if *id == corrupt
+        )));
+        for id in [first, corrupt, last] {
+            assert_eq!(
+                events
+                    .iter()
+                    .filter(|e| matches!(e, BatchEvent::Message(i, Message::Done) if *i == id))
+                    .count(),
+                1
+            );
+        }
+    }
+
+    #[test]
+    fn test_fail_fast() {
+        init_logging();
+
+        let gif = sample_gif(2, Some(0));
+        let mut batch = Batch::new(2).fail_fast(true);
+        // Still running when the failure happens (and waiting for the 'q' written
+        // to its `stdin` when cancelled).
+        let slow = batch.add(settings(fake_ffmpeg_with_progress_script(
+            2,
+            0.05,
+            "",
+            "timeout 10 head -c 1 > /dev/null",
+        )));
+        let failing = batch.add(settings(fake_ffmpeg_with_script(
+            SAMPLE_STDERR,
+            b"",
+            "sleep 0.3; exit 1",
+        )));
+        let queued: Vec<_> = (0..2)
+            .map(|_| batch.add(settings(fake_ffmpeg(SAMPLE_STDERR, &gif, 0))))
+            .collect();
+        let started = std::time::Instant::now();
+        let (report, events) = run(batch);
+
+        assert!(started.elapsed() < std::time::Duration::from_secs(5));
+        assert!(report.succeeded.is_empty());
+        assert!(matches!(
+            &report.failures[..],
+            [(id, Error::ExitCode(1))] if *id == failing
+        ));
+        assert_eq!(report.cancelled, [slow]);
+        assert_eq!(report.aborted, queued);
+        for id in &queued {
+            assert!(events
+                .iter()
+                .any(|e| matches!(e, BatchEvent::Aborted(i) if i == id)));
+            // The aborted jobs were never started.
+            assert!(!events
+                .iter()
+                .any(|e| matches!(e, BatchEvent::Message(i, _) if i == id)));
+        }
+    }
+
+    #[test]
+    fn test_cache() {
+        init_logging();
+
+        let gif = sample_gif(2, Some(0));
+        let cache = CacheConfig::new(temp_dir(), 1_000_000);
+        let mut batch = Batch::new(2).cache(cache.clone());
+        batch.add(settings(fake_ffmpeg(SAMPLE_STDERR, &gif, 0)));
+        let (report, _) = run(batch);
+        assert_eq!(report.succeeded.len(), 1);
+
+        // The jobs of another batch find the animated GIF in the cache.
+        let failing = fake_ffmpeg(SAMPLE_STDERR, b"", 1);
+        let mut batch = Batch::new(2).cache(cache);
+        let ids: Vec<_> = (0..2)
+            .map(|_| batch.add(settings(failing.clone())))
+            .collect();
+        let (report, events) = run(batch);
+        assert_eq!(report.succeeded.len(), 2);
+        for id in ids {
+            assert!(events.iter().any(|e| matches!(
+                e,
+                BatchEvent::Message(i, Message::Success(bytes)) if *i == id && *bytes == gif
+            )));
+        }
+    }
+
+    #[test]
+    fn test_add_with_id() {
+        init_logging();
+
+        let gif = sample_gif(2, Some(0));
+        let mut batch = Batch::new(2);
+        let ok = batch.add_with_id(41, settings(fake_ffmpeg(SAMPLE_STDERR, &gif, 0)));
+        let corrupt = batch.add_with_id("row-42", settings(fake_ffmpeg(SAMPLE_STDERR, b"", 0)));
+        assert_eq!(
+            (ok.clone(), corrupt.clone()),
+            (JobId::U64(41), JobId::from("row-42"))
+        );
+        let (report, events) = run(batch);
+
+        assert_eq!(report.succeeded, [JobId::U64(41)]);
+        assert!(matches!(
+            &report.failures[..],
+            [(JobId::String(id), Error::EmptyStdout)] if id == "row-42"
+        ));
+        for id in [ok, corrupt] {
+            assert!(events.iter().any(|e| matches!(
+                e,
+                BatchEvent::Message(i, Message::Summary(summary)) if *i == id && summary.id == id
+            )));
+        }
+    }
+
+    #[test]
+    fn test_skipped() {
+        init_logging();
+
+        let dir = temp_dir();
+        std::fs::write(dir.join("existing.gif"), b"GIF").unwrap();
+        let path = fake_ffmpeg(SAMPLE_STDERR, &sample_gif(2, Some(0)), 0);
+        let naming = |template: &str| {
+            crate::OutputNaming::new(template).collision(crate::CollisionPolicy::Skip)
+        };
+        let mut batch = Batch::new(2);
+        let skipped = batch.add(settings(path.clone()).output_file(&dir, naming("existing.gif")));
+        let succeeded = batch.add(settings(path).output_file(&dir, naming("new.gif")));
+        let (report, _) = run(batch);
+        assert_eq!(report.skipped, [skipped]);
+        assert_eq!(report.succeeded, [succeeded]);
+        assert!(report.failures.is_empty());
+        assert_eq!(std::fs::read(dir.join("existing.gif")).unwrap(), b"GIF");
+    }
+
+    #[test]
+    fn test_update_pending() {
+        init_logging();
+
+        // NOTE: Each job appends its arguments to the log, as a single line.
+        let log = temp_dir().join("args.txt");
+        let path = fake_ffmpeg_with_script(
+            SAMPLE_STDERR,
+            &sample_gif(2, Some(0)),
+            &format!("echo \"$*\" >> '{}'\nsleep 0.02", log.display()),
+        );
+        // NOTE: The frame limit tells the jobs apart.
+        let settings = |width: u16, max_colors: u16, frames: u32| {
+            Settings::with_standard_fps(SAMPLE_VIDEO_PATH.into(), width)
+                .max_colors(max_colors)
+                .output_frame_limit(frames)
+                .ffmpeg_path(path.to_string_lossy())
+        };
+        let mut batch = Batch::new(4);
+        let ids: Vec<_> = (1..=40)
+            .map(|frames| batch.add(settings(100, 64, frames)))
+            .collect();
+        let handle = batch.handle();
+        let running = std::thread::spawn(move || run(batch).0);
+        let updated: Vec<_> = ids
+            .iter()
+            .zip(1..)
+            .map(|(id, frames)| {
+                std::thread::sleep(std::time::Duration::from_millis(5));
+                handle.update_pending(id, settings(300, 128, frames))
+            })
+            .collect();
+        let report = running.join().unwrap();
+        assert_eq!(report.succeeded.len(), ids.len());
+
+        // Each job ran with one set of settings or the other, as a whole, depending on
+        // whether the update won the race.
+        let args = std::fs::read_to_string(&log).unwrap();
+        assert_eq!(args.lines().count(), ids.len());
+        for (result, frames) in updated.iter().zip(1..) {
+            let line = args
+                .lines()
+                .find(|line| line.contains(&format!(" -frames:v {} ", frames)))
+                .unwrap();
+            let expected = match result {
+                Ok(()) => ["scale=300", "max_colors=128"],
+                Err(UpdateError::AlreadyStarted) => ["scale=100", "max_colors=64"],
+                Err(e) => panic!("Unexpected error: {:?}", e),
+            };
+            assert!(expected.iter().all(|s| line.contains(s)), "{}", line);
+        }
+
+        // Once the batch has run, no job can be updated.
+        assert_eq!(
+            handle.update_pending(&ids[39], settings(300, 128, 40)),
+            Err(UpdateError::AlreadyStarted)
+        );
+        assert_eq!(
+            handle.update_pending(&JobId::from("unknown"), settings(300, 128, 1)),
+            Err(UpdateError::UnknownJob)
+        );
+    }
+
+    #[test]
+    fn test_empty() {
+        let (report, events) = run(Batch::new(0));
+        assert!(events.is_empty());
+        assert!(report.succeeded.is_empty() && report.failures.is_empty());
+    }
+}
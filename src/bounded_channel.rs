@@ -0,0 +1,183 @@
+//! The bounded message channel created by [`crate::Converter::new_with_bounded_channel`].
+//!
+//! The channel bounds the number of progress messages (see [`Message::is_progress`]) that the
+//! application hasn't received yet, which are dropped once `capacity` messages are waiting.
+//! The other messages (e.g. [`Message::Error`] and [`Message::Done`]), of which each job only
+//! sends a handful, never wait for that backlog to be received: they take the slots reserved
+//! for them, so that sending never blocks the converter's threads, nor delays the job's
+//! cancellation. Both kinds of messages are received in the order they were sent.
+
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::Arc;
+
+use crate::converter::{MessageReceiver, MessageSender};
+use crate::outbox::SendError;
+use crate::Message;
+
+const LOG_TARGET: &str = "ffmpeg_gif_maker::bounded_channel";
+
+/// Creates a bounded message channel of the given `capacity` (at least 1).
+pub(crate) fn channel(capacity: usize) -> (BoundedMessageSender, BoundedMessageReceiver) {
+    #[cfg(not(any(feature = "tokio", feature = "async-channel")))]
+    let (tx, rx) = std::sync::mpsc::channel();
+    #[cfg(feature = "tokio")]
+    let (tx, rx) = tokio::sync::mpsc::unbounded_channel();
+    #[cfg(feature = "async-channel")]
+    let (tx, rx) = async_channel::unbounded();
+    let queued = Arc::new(AtomicUsize::new(0));
+    (
+        BoundedMessageSender {
+            tx,
+            queued: Arc::clone(&queued),
+            capacity: capacity.max(1),
+        },
+        BoundedMessageReceiver { rx, queued },
+    )
+}
+
+#[derive(Debug, Clone)]
+/// The sender's end of the bounded message channel created by
+/// [`crate::Converter::new_with_bounded_channel`].
+pub struct BoundedMessageSender {
+    tx: MessageSender,
+    /// The number of messages that the application hasn't received yet.
+    queued: Arc<AtomicUsize>,
+    /// The number of waiting messages from which progress messages are dropped.
+    capacity: usize,
+}
+
+impl BoundedMessageSender {
+    /// Sends `message` down the channel, without ever blocking: a progress message is
+    /// dropped (in which case `Ok` is returned) if `capacity` messages are already waiting,
+    /// while the other messages take the slots reserved for them.
+    // NOTE: The error type is the channel's own, which hands the (unsent) message back.
+    #[allow(clippy::result_large_err)]
+    pub(crate) fn send(&self, message: Message) -> Result<(), SendError> {
+        if message.is_progress() && self.queued.load(Ordering::SeqCst) >= self.capacity {
+            log::trace!(target: LOG_TARGET, "Progress message dropped: {:?}", message);
+            return Ok(());
+        }
+        // NOTE: Counted before being sent, so that the receiver never counts it first.
+        self.queued.fetch_add(1, Ordering::SeqCst);
+        #[cfg(not(feature = "async-channel"))]
+        let sent = self.tx.send(message);
+        #[cfg(feature = "async-channel")]
+        let sent = self.tx.send_blocking(message);
+        if sent.is_err() {
+            self.queued.fetch_sub(1, Ordering::SeqCst);
+        }
+        sent
+    }
+}
+
+#[derive(Debug)]
+/// The receiver's end of the bounded message channel created by
+/// [`crate::Converter::new_with_bounded_channel`], whose methods are the same as those of
+/// the underlying channel's receiver (see the `tokio` and `async-channel` feature flags).
+pub struct BoundedMessageReceiver {
+    rx: MessageReceiver,
+    /// The number of messages that the application hasn't received yet.
+    queued: Arc<AtomicUsize>,
+}
+
+impl BoundedMessageReceiver {
+    /// Makes room for another message, once `message` has been received.
+    fn received<T>(&self, message: T) -> T {
+        self.queued.fetch_sub(1, Ordering::SeqCst);
+        message
+    }
+}
+
+#[cfg(not(any(feature = "tokio", feature = "async-channel")))]
+impl BoundedMessageReceiver {
+    /// Same as [`std::sync::mpsc::Receiver::recv`].
+    pub fn recv(&self) -> Result<Message, std::sync::mpsc::RecvError> {
+        self.rx.recv().map(|m| self.received(m))
+    }
+
+    /// Same as [`std::sync::mpsc::Receiver::try_recv`].
+    pub fn try_recv(&self) -> Result<Message, std::sync::mpsc::TryRecvError> {
+        self.rx.try_recv().map(|m| self.received(m))
+    }
+
+    /// Same as [`std::sync::mpsc::Receiver::recv_timeout`].
+    pub fn recv_timeout(
+        &self,
+        timeout: std::time::Duration,
+    ) -> Result<Message, std::sync::mpsc::RecvTimeoutError> {
+        self.rx.recv_timeout(timeout).map(|m| self.received(m))
+    }
+}
+
+#[cfg(feature = "tokio")]
+impl BoundedMessageReceiver {
+    /// Same as [`tokio::sync::mpsc::UnboundedReceiver::recv`].
+    pub async fn recv(&mut self) -> Option<Message> {
+        let message = self.rx.recv().await;
+        message.map(|m| self.received(m))
+    }
+
+    /// Same as [`tokio::sync::mpsc::UnboundedReceiver::blocking_recv`].
+    pub fn blocking_recv(&mut self) -> Option<Message> {
+        let message = self.rx.blocking_recv();
+        message.map(|m| self.received(m))
+    }
+
+    /// Same as [`tokio::sync::mpsc::UnboundedReceiver::try_recv`].
+    pub fn try_recv(&mut self) -> Result<Message, tokio::sync::mpsc::error::TryRecvError> {
+        let message = self.rx.try_recv();
+        message.map(|m| self.received(m))
+    }
+}
+
+#[cfg(feature = "async-channel")]
+impl BoundedMessageReceiver {
+    /// Same as [`async_channel::Receiver::recv`].
+    pub async fn recv(&self) -> Result<Message, async_channel::RecvError> {
+        self.rx.recv().await.map(|m| self.received(m))
+    }
+
+    /// Same as [`async_channel::Receiver::recv_blocking`].
+    pub fn recv_blocking(&self) -> Result<Message, async_channel::RecvError> {
+        self.rx.recv_blocking().map(|m| self.received(m))
+    }
+
+    /// Same as [`async_channel::Receiver::try_recv`].
+    pub fn try_recv(&self) -> Result<Message, async_channel::TryRecvError> {
+        self.rx.try_recv().map(|m| self.received(m))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::time::Duration;
+
+    #[test]
+    fn test_reserved_slots() {
+        #[cfg(not(feature = "tokio"))]
+        let (tx, rx) = channel(2);
+        #[cfg(feature = "tokio")]
+        let (tx, mut rx) = channel(2);
+
+        tx.send(Message::VideoDuration(Duration::from_secs(1)))
+            .unwrap();
+        tx.send(Message::Progress(0.0)).unwrap();
+        // Two messages are waiting, so the progress messages are dropped, while the
+        // other messages are still sent, without blocking.
+        tx.send(Message::Progress(0.5)).unwrap();
+        tx.send(Message::Done).unwrap();
+        assert!(matches!(rx.try_recv(), Ok(Message::VideoDuration(_))));
+        assert!(matches!(rx.try_recv(), Ok(Message::Progress(p)) if p == 0.0));
+        assert!(matches!(rx.try_recv(), Ok(Message::Done)));
+        assert!(rx.try_recv().is_err());
+
+        // Receiving the messages makes room for the progress messages again.
+        tx.send(Message::Progress(1.0)).unwrap();
+        assert!(matches!(rx.try_recv(), Ok(Message::Progress(p)) if p == 1.0));
+
+        // Sending fails once the receiver is dropped.
+        drop(rx);
+        assert!(tx.send(Message::Done).is_err());
+    }
+}
@@ -0,0 +1,457 @@
+//! The on-disk cache of conversion results (see [`crate::Converter::cache`]), which lets
+//! an application that regenerates the same animated GIFs (e.g. when a user revisits a
+//! file) skip the FFmpeg child process altogether.
+//!
+//! An entry is keyed by a fingerprint of the input (its size, its modification time, and
+//! its first and last few bytes, so that large files don't need to be read in full) and
+//! by the conversion plan (see [`crate::Settings::plan`]), without the input's location,
+//! along with the fingerprints of the other files read by FFmpeg (i.e. the watermark and
+//! the subtitles, see [`crate::Settings::watermark`] and [`crate::Settings::subtitles`]),
+//! and is stored as a file named after the key, with the output format's extension. Once
+//! the total size of the entries exceeds the configured limit, the least recently used
+//! ones are evicted, an entry's modification time being updated each time it is used.
+
+use std::io::{Read, Seek};
+use std::path::PathBuf;
+use std::time::SystemTime;
+
+use crate::{ConversionPlan, InputSource};
+
+/// The number of bytes read from both the beginning and the end of the input.
+const FINGERPRINT_LEN: u64 = 64 * 1024;
+/// The extensions of the files holding the entries, i.e. those of every output format
+/// (see [`crate::OutputFormat::extension`]).
+const ENTRY_EXTENSIONS: [&str; 3] = ["gif", "webp", "png"];
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+/// The configuration of the conversion result cache (see [`crate::Converter::cache`]).
+pub struct CacheConfig {
+    /// The directory in which the animated GIFs are stored (created if needed), which
+    /// should not contain any other `.gif`, `.webp` or `.png` file.
+    pub dir: PathBuf,
+    /// The maximum total size (in bytes) of the stored animated GIFs, beyond which the
+    /// least recently used ones are evicted.
+    pub max_bytes: u64,
+}
+
+impl CacheConfig {
+    /// Creates a new configuration, storing at most `max_bytes` of animated GIFs in `dir`.
+    pub fn new(dir: impl Into<PathBuf>, max_bytes: u64) -> Self {
+        Self {
+            dir: dir.into(),
+            max_bytes,
+        }
+    }
+
+    /// The path of the file holding the entry for `key`.
+    fn path(&self, key: CacheKey) -> PathBuf {
+        self.dir.join(format!("{}.{}", key, key.extension))
+    }
+
+    /// Returns the animated GIF stored for `key`, if any, marking it as recently used.
+    pub(crate) fn load(&self, key: CacheKey) -> Option<Vec<u8>> {
+        let path = self.path(key);
+        let bytes = std::fs::read(&path).ok()?;
+        // NOTE: Not a problem if it fails (e.g. if the entry was evicted in the meantime).
+        let _ = std::fs::File::options()
+            .write(true)
+            .open(&path)
+            .and_then(|f| f.set_modified(SystemTime::now()));
+        Some(bytes)
+    }
+
+    /// Stores `bytes` for `key`, then evicts the least recently used entries until
+    /// the total size is back under the limit. An animated GIF larger than the limit
+    /// is not stored.
+    pub(crate) fn store(&self, key: CacheKey, bytes: &[u8]) -> std::io::Result<()> {
+        if bytes.len() as u64 > self.max_bytes {
+            return Ok(());
+        }
+        std::fs::create_dir_all(&self.dir)?;
+        // NOTE: Written under a temporary name first, so that concurrent jobs never
+        // read a partially written entry.
+        let temporary = self
+            .dir
+            .join(format!("{}.{}.tmp", key, uuid::Uuid::new_v4()));
+        std::fs::write(&temporary, bytes)?;
+        if let Err(e) = std::fs::rename(&temporary, self.path(key)) {
+            let _ = std::fs::remove_file(&temporary);
+            return Err(e);
+        }
+        self.evict()
+    }
+
+    /// Removes the least recently used entries until their total size is under the limit.
+    fn evict(&self) -> std::io::Result<()> {
+        let mut entries = vec![];
+        for entry in std::fs::read_dir(&self.dir)? {
+            let entry = entry?;
+            let path = entry.path();
+            let extension = path.extension().and_then(|e| e.to_str());
+            if !extension.is_some_and(|e| ENTRY_EXTENSIONS.contains(&e)) {
+                continue;
+            }
+            // NOTE: The entry may have been evicted by another job in the meantime.
+            let Ok(metadata) = entry.metadata() else {
+                continue;
+            };
+            let used = metadata.modified().unwrap_or(SystemTime::UNIX_EPOCH);
+            entries.push((used, metadata.len(), path));
+        }
+        let mut total: u64 = entries.iter().map(|(_, len, _)| len).sum();
+        entries.sort();
+        for (_, len, path) in entries {
+            if total <= self.max_bytes {
+                break;
+            }
+            match std::fs::remove_file(&path) {
+                Err(e) if e.kind() != std::io::ErrorKind::NotFound => return Err(e),
+                _ => total -= len,
+            }
+        }
+        Ok(())
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+/// The key of a cache entry (i.e. a 128-bit hash, displayed in hexadecimal), along with
+/// the extension of the output format (see [`crate::OutputFormat::extension`]).
+pub(crate) struct CacheKey {
+    hash: u128,
+    extension: &'static str,
+}
+
+impl std::fmt::Display for CacheKey {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{:032x}", self.hash)
+    }
+}
+
+/// The 128-bit FNV-1a hash function, which (unlike [`std::hash::DefaultHasher`]) gives the
+/// same values across Rust releases, as required for keys that outlive the process.
+struct Fnv128(u128);
+
+impl Fnv128 {
+    const OFFSET_BASIS: u128 = 0x6c62272e07bb014262b821756295c58d;
+    const PRIME: u128 = 0x0000000001000000000000000000013b;
+
+    fn new() -> Self {
+        Self(Self::OFFSET_BASIS)
+    }
+
+    fn write(&mut self, bytes: &[u8]) {
+        for byte in bytes {
+            self.0 ^= *byte as u128;
+            self.0 = self.0.wrapping_mul(Self::PRIME);
+        }
+    }
+
+    /// Same as [`Fnv128::write`], preceded by the length of `bytes`, so that two
+    /// consecutive fields cannot be confused with each other.
+    fn write_field(&mut self, bytes: &[u8]) {
+        self.write(&(bytes.len() as u64).to_le_bytes());
+        self.write(bytes);
+    }
+
+    /// Same as [`Fnv128::write_field`], with the little-endian bytes of `value`.
+    fn write_u64(&mut self, value: u64) {
+        self.write_field(&value.to_le_bytes());
+    }
+
+    /// Same as [`Fnv128::write_u64`], with 0 standing for `None`.
+    fn write_option(&mut self, value: Option<u64>) {
+        self.write_u64(value.map_or(0, |v| v + 1));
+    }
+}
+
+/// Derives the key of the animated GIF generated from `input` using `plan`.
+///
+/// NOTE: Fails if one of the files read by FFmpeg (e.g. the watermark) cannot be
+/// fingerprinted, in which case the cache is bypassed (and FFmpeg reports the error).
+pub(crate) fn key(input: &InputSource, plan: &ConversionPlan) -> std::io::Result<CacheKey> {
+    let mut hasher = Fnv128::new();
+    hasher.write_field(env!("CARGO_PKG_VERSION").as_bytes());
+    match input {
+        InputSource::Path(path) => fingerprint(&std::fs::File::open(path)?, &mut hasher)?,
+        InputSource::File(file) => fingerprint(file, &mut hasher)?,
+        // NOTE: Without a modification time, the whole content makes up the fingerprint.
+        InputSource::Bytes(bytes) => hasher.write_field(bytes),
+        InputSource::Reader(_) => {
+            return Err(std::io::Error::new(
+                std::io::ErrorKind::Unsupported,
+                "the input can only be read once, so it cannot be fingerprinted",
+            ))
+        }
+        InputSource::Url(_) => {
+            return Err(std::io::Error::new(
+                std::io::ErrorKind::Unsupported,
+                "a remote input cannot be fingerprinted",
+            ))
+        }
+    }
+    // NOTE: The paths of these files are also part of the plan, but an edited file (e.g.
+    // the subtitles) must give another key as well.
+    for path in [&plan.watermark, &plan.subtitles].into_iter().flatten() {
+        fingerprint(&std::fs::File::open(path)?, &mut hasher)?;
+    }
+    // NOTE: The input's location (i.e. `-i`'s value) is left out, so that the same
+    // video found at another path (or passed as an open file) gives the same key.
+    hasher.write_u64(plan.args.len() as u64);
+    for arg in &plan.args {
+        match *arg == plan.input {
+            true => hasher.write_field(b""),
+            false => hasher.write_field(arg.as_bytes()),
+        }
+    }
+    // NOTE: The arguments already hold the filter graph, and so the settings that only
+    // shape it (e.g. the rotation, or the crop keyframes). The other fields are fed one
+    // by one (rather than e.g. the plan's `Debug` output), so that the encoding stays the
+    // same as the plan gains fields.
+    hasher.write_field(plan.filter_complex.as_bytes());
+    hasher.write_field(plan.output_format.extension().as_bytes());
+    hasher.write_field(plan.dither.as_bytes());
+    hasher.write_u64(plan.fps as u64);
+    hasher.write_u64(plan.width as u64);
+    hasher.write_option(plan.height.map(u64::from));
+    hasher.write_u64(plan.additional_widths.len() as u64);
+    for width in &plan.additional_widths {
+        hasher.write_u64(*width as u64);
+    }
+    hasher.write_u64(plan.max_colors as u64);
+    hasher.write_option(plan.output_frame_limit.map(u64::from));
+    hasher.write_option(plan.frame_step.map(u64::from));
+    for value in [plan.speed, plan.saturation, plan.brightness, plan.contrast] {
+        hasher.write_u64(value.to_bits() as u64);
+    }
+    for flag in [
+        plan.auto_colors,
+        plan.global_palette_only,
+        plan.preserve_last_frame,
+        plan.interpolate_frame_delays,
+        plan.boomerang,
+        plan.hflip,
+        plan.vflip,
+    ] {
+        hasher.write_u64(flag as u64);
+    }
+    Ok(CacheKey {
+        hash: hasher.0,
+        extension: plan.output_format.extension(),
+    })
+}
+
+/// Feeds the input's size, modification time, and first and last [`FINGERPRINT_LEN`]
+/// bytes to `hasher`.
+fn fingerprint(mut file: &std::fs::File, hasher: &mut Fnv128) -> std::io::Result<()> {
+    let metadata = file.metadata()?;
+    let size = file.seek(std::io::SeekFrom::End(0))?;
+    hasher.write(&size.to_le_bytes());
+    hasher.write(&modified(&metadata).to_le_bytes());
+    let mut buf = vec![];
+    file.seek(std::io::SeekFrom::Start(0))?;
+    file.take(FINGERPRINT_LEN).read_to_end(&mut buf)?;
+    if size > FINGERPRINT_LEN {
+        file.seek(std::io::SeekFrom::Start(
+            size.saturating_sub(FINGERPRINT_LEN).max(FINGERPRINT_LEN),
+        ))?;
+        file.take(FINGERPRINT_LEN).read_to_end(&mut buf)?;
+    }
+    hasher.write_field(&buf);
+    Ok(())
+}
+
+/// The modification time of a file (in nanoseconds since the Unix epoch), or 0 if unknown.
+fn modified(metadata: &std::fs::Metadata) -> u128 {
+    metadata
+        .modified()
+        .ok()
+        .and_then(|t| t.duration_since(SystemTime::UNIX_EPOCH).ok())
+        .map_or(0, |d| d.as_nanos())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_utils::{sample_gif, temp_dir};
+    use crate::Settings;
+
+    /// Writes `bytes` to a new file named `name` in `dir`, with the given modification time.
+    fn write(dir: &std::path::Path, name: &str, bytes: &[u8], modified: u64) -> String {
+        let path = dir.join(name);
+        std::fs::write(&path, bytes).unwrap();
+        set_modified(&path, modified);
+        path.to_string_lossy().into()
+    }
+
+    fn set_modified(path: &std::path::Path, seconds: u64) {
+        std::fs::File::options()
+            .write(true)
+            .open(path)
+            .unwrap()
+            .set_modified(SystemTime::UNIX_EPOCH + std::time::Duration::from_secs(seconds))
+            .unwrap();
+    }
+
+    fn gif_key(hash: u128) -> CacheKey {
+        CacheKey {
+            hash,
+            extension: "gif",
+        }
+    }
+
+    fn key_for(path: &str, settings: impl Fn(Settings) -> Settings) -> CacheKey {
+        let settings = settings(Settings::with_standard_fps(path.into(), 200));
+        key(&InputSource::from(path), &settings.plan()).unwrap()
+    }
+
+    #[test]
+    fn test_key() {
+        let dir = temp_dir();
+        let video = vec![7u8; 200 * 1024];
+        let a = write(&dir, "a.mp4", &video, 1_000);
+        let key_a = key_for(&a, |s| s);
+        assert_eq!(key_a.to_string().len(), 32);
+        // The same video (i.e. same content and modification time) at another path.
+        let b = write(&dir, "-b.mp4", &video, 1_000);
+        assert_eq!(key_for(&b, |s| s), key_a);
+        // The same file, passed as an open file.
+        let file = std::fs::File::open(&a).unwrap();
+        let settings = Settings::with_input(file, 200);
+        assert_eq!(key(&settings.input, &settings.plan()).unwrap(), key_a);
+        // Other settings.
+        assert_ne!(key_for(&a, |s| s.max_colors(64)), key_a);
+        assert_ne!(key_for(&a, |s| s.auto_colors(true)), key_a);
+        // A modified video.
+        let mut modified = video.clone();
+        modified[video.len() - 1] = 8;
+        let c = write(&dir, "c.mp4", &modified, 1_000);
+        assert_ne!(key_for(&c, |s| s), key_a);
+        set_modified(std::path::Path::new(&b), 2_000);
+        assert_ne!(key_for(&b, |s| s), key_a);
+        let d = write(&dir, "d.mp4", &video[1..], 1_000);
+        assert_ne!(key_for(&d, |s| s), key_a);
+        // A missing video.
+        assert!(key(&InputSource::from("missing.mp4"), &settings.plan()).is_err());
+    }
+
+    #[test]
+    fn test_key_is_stable() {
+        // NOTE: The keys of the entries already on disk must stay the same, so this value
+        // must only change along with the crate's version (which is part of the key).
+        let input = InputSource::from(vec![7u8; 1024]);
+        let plan = ConversionPlan {
+            input: "pipe:0".into(),
+            filter_complex: "[0:v]fps=10[out]".into(),
+            args: [
+                "-i",
+                "pipe:0",
+                "-filter_complex",
+                "[0:v]fps=10[out]",
+                "-f",
+                "gif",
+                "-",
+            ]
+            .map(String::from)
+            .to_vec(),
+            ..Settings::with_standard_fps("video.mp4".into(), 200).plan()
+        };
+        assert_eq!(
+            key(&input, &plan).unwrap().to_string(),
+            "39fc5769dc58f742511e20f32033ba39"
+        );
+    }
+
+    #[test]
+    fn test_key_auxiliary_files() {
+        let dir = temp_dir();
+        let video = write(&dir, "a.mp4", &[7; 1024], 1_000);
+        let srt = dir.join("a.srt");
+        write(
+            &dir,
+            "a.srt",
+            b"1\n00:00:00,000 --> 00:00:01,000\nHello\n",
+            1_000,
+        );
+        let with_subtitles = |s: Settings| s.subtitles(srt.clone());
+        let key_a = key_for(&video, with_subtitles);
+        assert_eq!(key_for(&video, with_subtitles), key_a);
+        // The same file, edited in place.
+        write(
+            &dir,
+            "a.srt",
+            b"1\n00:00:00,000 --> 00:00:01,000\nWorld\n",
+            1_000,
+        );
+        assert_ne!(key_for(&video, with_subtitles), key_a);
+        let png = dir.join("logo.png");
+        let with_watermark = |s: Settings| s.watermark(png.clone(), crate::Position::TopLeft, 1.0);
+        // A missing watermark.
+        let settings = with_watermark(Settings::with_standard_fps(video.clone(), 200));
+        assert!(key(&settings.input, &settings.plan()).is_err());
+        write(&dir, "logo.png", &[1; 100], 1_000);
+        let key_b = key_for(&video, with_watermark);
+        set_modified(&png, 2_000);
+        assert_ne!(key_for(&video, with_watermark), key_b);
+    }
+
+    #[test]
+    fn test_key_extension() {
+        let dir = temp_dir();
+        let video = write(&dir, "a.mp4", &[7; 1024], 1_000);
+        let cache = CacheConfig::new(dir.join("cache"), 1000);
+        let gif = key_for(&video, |s| s);
+        assert!(cache.path(gif).ends_with(format!("{}.gif", gif)));
+        let apng = key_for(&video, |s| s.output_format(crate::OutputFormat::Apng));
+        assert_ne!(apng, gif);
+        assert!(cache.path(apng).ends_with(format!("{}.png", apng)));
+        let webp = crate::OutputFormat::WebP {
+            quality: 75,
+            lossless: false,
+        };
+        let webp = key_for(&video, |s| s.output_format(webp));
+        assert!(cache.path(webp).ends_with(format!("{}.webp", webp)));
+        // All of them are entries.
+        for (i, key) in [gif, apng].into_iter().enumerate() {
+            cache.store(key, &[0; 400]).unwrap();
+            set_modified(&cache.path(key), i as u64);
+        }
+        cache.store(webp, &[0; 400]).unwrap();
+        assert!(cache.load(gif).is_none());
+        assert!(cache.load(apng).is_some());
+        assert!(cache.load(webp).is_some());
+    }
+
+    #[test]
+    fn test_store_and_load() {
+        let cache = CacheConfig::new(temp_dir().join("cache"), 1000);
+        let (a, b) = (gif_key(1), gif_key(2));
+        assert_eq!(cache.load(a), None);
+        let gif = sample_gif(2, Some(0));
+        cache.store(a, &gif).unwrap();
+        assert_eq!(cache.load(a), Some(gif.clone()));
+        assert_eq!(cache.load(b), None);
+        // Larger than the whole cache, so not stored.
+        cache.store(b, &[0; 1001]).unwrap();
+        assert_eq!(cache.load(b), None);
+        // No temporary file is left behind.
+        assert_eq!(std::fs::read_dir(&cache.dir).unwrap().count(), 1);
+    }
+
+    #[test]
+    fn test_eviction() {
+        let cache = CacheConfig::new(temp_dir(), 250);
+        for i in 1..=2 {
+            cache.store(gif_key(i), &[i as u8; 100]).unwrap();
+            set_modified(&cache.path(gif_key(i)), i as u64);
+        }
+        // Other files are not entries.
+        write(&cache.dir, "notes.txt", &[0; 1000], 0);
+        // The first entry becomes the most recently used one.
+        assert!(cache.load(gif_key(1)).is_some());
+        cache.store(gif_key(3), &[3; 100]).unwrap();
+        assert!(cache.load(gif_key(2)).is_none());
+        assert!(cache.load(gif_key(1)).is_some());
+        assert!(cache.load(gif_key(3)).is_some());
+        assert!(cache.dir.join("notes.txt").exists());
+    }
+}
@@ -0,0 +1,318 @@
+//! The filters and encoders available in the FFmpeg binary (see
+//! [`crate::Settings::check_capabilities`]), which depend on the options FFmpeg was built
+//! with (e.g. `--enable-gpl` for the `eq` filter, or `--enable-libass` for the `subtitles`
+//! filter), and which would otherwise only show up as an opaque error in FFmpeg's `stderr`.
+//!
+//! The capabilities are probed (using `ffmpeg -filters` and `ffmpeg -encoders`) once per
+//! binary path, and the settings are then checked against them using the [`REQUIREMENTS`]
+//! table, which maps the settings to the capabilities that they require (and which the
+//! features that depend on FFmpeg's build options should extend).
+
+use std::collections::{BTreeSet, HashMap};
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex, OnceLock};
+
+use crate::{OutputFormat, Settings};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+/// A filter or an encoder that the FFmpeg binary may lack (see [`crate::Error::MissingCapability`]).
+pub enum Capability {
+    /// A filter (e.g. `subtitles`), as listed by `ffmpeg -filters`.
+    Filter(&'static str),
+    /// An encoder (e.g. `gif`), as listed by `ffmpeg -encoders`.
+    Encoder(&'static str),
+}
+
+impl std::fmt::Display for Capability {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::Filter(name) => write!(f, "the '{}' filter", name),
+            Self::Encoder(name) => write!(f, "the '{}' encoder", name),
+        }
+    }
+}
+
+/// A capability required by some settings.
+pub(crate) struct Requirement {
+    pub(crate) capability: Capability,
+    /// An actionable message for the user, should the capability be missing.
+    pub(crate) hint: &'static str,
+    /// Whether the settings require the capability.
+    pub(crate) required_by: fn(&Settings) -> bool,
+}
+
+/// Whether the settings use the generated filter graph (see [`Settings::custom_filter_complex`]).
+fn generated_graph(settings: &Settings) -> bool {
+    settings.custom_filter_complex.is_none()
+}
+
+/// Whether the settings use the generated filter graph, and it ends with the palette.
+fn generated_palette(settings: &Settings) -> bool {
+    generated_graph(settings) && settings.output_format.uses_palette()
+}
+
+/// The capabilities required by the settings, in the order in which they are checked.
+pub(crate) static REQUIREMENTS: [Requirement; 7] = [
+    Requirement {
+        capability: Capability::Encoder("gif"),
+        hint: "your FFmpeg lacks the GIF encoder; install a full build",
+        required_by: |s| s.output_format == OutputFormat::Gif,
+    },
+    Requirement {
+        capability: Capability::Encoder("libwebp_anim"),
+        hint: "your FFmpeg lacks libwebp (--enable-libwebp), needed by the animated WebP \
+            output format; install a full build",
+        required_by: |s| matches!(s.output_format, OutputFormat::WebP { .. }),
+    },
+    Requirement {
+        capability: Capability::Encoder("apng"),
+        hint: "your FFmpeg lacks the APNG encoder; install a full build",
+        required_by: |s| s.output_format == OutputFormat::Apng,
+    },
+    Requirement {
+        capability: Capability::Filter("palettegen"),
+        hint: "your FFmpeg lacks the palettegen filter (FFmpeg 2.6 or later); install a recent full build",
+        required_by: generated_palette,
+    },
+    Requirement {
+        capability: Capability::Filter("paletteuse"),
+        hint: "your FFmpeg lacks the paletteuse filter (FFmpeg 2.6 or later); install a recent full build",
+        required_by: generated_palette,
+    },
+    Requirement {
+        capability: Capability::Filter("eq"),
+        hint: "your FFmpeg lacks the eq filter, which is only part of GPL builds (--enable-gpl), \
+            needed by the brightness, contrast and saturation settings; install a full build",
+        required_by: |s| {
+            generated_graph(s) && (s.brightness != 0.0 || s.contrast != 1.0 || s.saturation != 1.0)
+        },
+    },
+    Requirement {
+        capability: Capability::Filter("subtitles"),
+        hint: "your FFmpeg lacks libass (--enable-libass), needed by the subtitles setting; \
+            install a full build",
+        required_by: |s| generated_graph(s) && s.subtitles.is_some(),
+    },
+];
+
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+/// The filters and encoders available in an FFmpeg binary.
+pub(crate) struct Capabilities {
+    filters: BTreeSet<String>,
+    encoders: BTreeSet<String>,
+}
+
+impl Capabilities {
+    /// The arguments that make FFmpeg list its filters.
+    pub(crate) const FILTERS_ARGS: [&'static str; 2] = ["-hide_banner", "-filters"];
+    /// The arguments that make FFmpeg list its encoders.
+    pub(crate) const ENCODERS_ARGS: [&'static str; 2] = ["-hide_banner", "-encoders"];
+
+    /// Parses the outputs of `ffmpeg -filters` and `ffmpeg -encoders`, returning `None` if
+    /// either of them is not recognized (in which case the settings cannot be checked).
+    pub(crate) fn parse(filters: &str, encoders: &str) -> Option<Self> {
+        Some(Self {
+            filters: parse_filters(filters)?,
+            encoders: parse_encoders(encoders)?,
+        })
+    }
+
+    /// Whether the binary has `capability`.
+    pub(crate) fn has(&self, capability: &Capability) -> bool {
+        match capability {
+            Capability::Filter(name) => self.filters.contains(*name),
+            Capability::Encoder(name) => self.encoders.contains(*name),
+        }
+    }
+
+    /// The first of the capabilities required by `settings` that the binary lacks, if any.
+    pub(crate) fn missing(&self, settings: &Settings) -> Option<&'static Requirement> {
+        REQUIREMENTS
+            .iter()
+            .find(|r| (r.required_by)(settings) && !self.has(&r.capability))
+    }
+}
+
+/// Parses the output of `ffmpeg -filters`, whose lines list a filter each (e.g.
+/// ` TSC eq                V->V       Adjust brightness, contrast, gamma, and saturation.`)
+/// after a legend (e.g. `  T.. = Timeline support`).
+fn parse_filters(output: &str) -> Option<BTreeSet<String>> {
+    let mut lines = output.lines();
+    lines.find(|line| line.trim() == "Filters:")?;
+    Some(BTreeSet::from_iter(lines.filter_map(
+        |line| match Vec::from_iter(line.split_whitespace().take(3))[..] {
+            [_, name, io] if io.contains("->") => Some(name.to_string()),
+            _ => None,
+        },
+    )))
+}
+
+/// Parses the output of `ffmpeg -encoders`, whose lines list an encoder each (e.g.
+/// ` V....D gif                  GIF (Graphics Interchange Format)`) after a legend,
+/// which ends with a ` ------` line.
+fn parse_encoders(output: &str) -> Option<BTreeSet<String>> {
+    let mut lines = output.lines();
+    lines.find(|line| line.trim() == "Encoders:")?;
+    lines.find(|line| line.trim() == "------")?;
+    Some(BTreeSet::from_iter(lines.filter_map(|line| {
+        line.split_whitespace().nth(1).map(String::from)
+    })))
+}
+
+/// The capabilities probed so far, per binary path (`None` if the output was not recognized).
+static CACHE: OnceLock<Mutex<HashMap<PathBuf, Option<Arc<Capabilities>>>>> = OnceLock::new();
+
+/// The capabilities of the binary at `binary_path`, probed using `probe` (which runs FFmpeg
+/// with the given arguments and returns its `stdout`) unless they were probed before. An
+/// error returned by `probe` is not cached, so that the next job probes again.
+pub(crate) fn cached<E>(
+    binary_path: &Path,
+    mut probe: impl FnMut(&[&str]) -> Result<String, E>,
+) -> Result<Option<Arc<Capabilities>>, E> {
+    let cache = CACHE.get_or_init(Default::default);
+    if let Some(capabilities) = cache
+        .lock()
+        .unwrap_or_else(|e| e.into_inner())
+        .get(binary_path)
+    {
+        return Ok(capabilities.clone());
+    }
+    // NOTE: Probed without holding the lock, so concurrent jobs may both probe the binary.
+    let filters = probe(&Capabilities::FILTERS_ARGS)?;
+    let encoders = probe(&Capabilities::ENCODERS_ARGS)?;
+    let capabilities = Capabilities::parse(&filters, &encoders).map(Arc::new);
+    cache
+        .lock()
+        .unwrap_or_else(|e| e.into_inner())
+        .insert(binary_path.to_path_buf(), capabilities.clone());
+    Ok(capabilities)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_utils::SAMPLE_VIDEO_PATH;
+
+    const FILTERS_FULL: &str = include_str!("../tests/fixtures/filters_full.txt");
+    const FILTERS_SLIM: &str = include_str!("../tests/fixtures/filters_slim.txt");
+    const ENCODERS_FULL: &str = include_str!("../tests/fixtures/encoders_full.txt");
+    const ENCODERS_SLIM: &str = include_str!("../tests/fixtures/encoders_slim.txt");
+
+    fn settings() -> Settings {
+        Settings::with_standard_fps(SAMPLE_VIDEO_PATH.into(), 200)
+    }
+
+    #[test]
+    fn test_parse() {
+        let full = Capabilities::parse(FILTERS_FULL, ENCODERS_FULL).unwrap();
+        for capability in [
+            Capability::Filter("eq"),
+            Capability::Filter("subtitles"),
+            Capability::Filter("palettegen"),
+            Capability::Filter("zscale"),
+            Capability::Filter("color"),
+            Capability::Encoder("gif"),
+            Capability::Encoder("libwebp"),
+        ] {
+            assert!(full.has(&capability), "{}", capability);
+        }
+        let slim = Capabilities::parse(FILTERS_SLIM, ENCODERS_SLIM).unwrap();
+        for capability in [
+            Capability::Filter("eq"),
+            Capability::Filter("subtitles"),
+            Capability::Filter("zscale"),
+            Capability::Encoder("libwebp"),
+        ] {
+            assert!(!slim.has(&capability), "{}", capability);
+        }
+        assert!(slim.has(&Capability::Filter("paletteuse")));
+        assert!(slim.has(&Capability::Encoder("gif")));
+        // NOTE: The legends are not mistaken for filters (or encoders).
+        assert!(!full.has(&Capability::Filter("=")));
+        assert!(!full.has(&Capability::Encoder("=")));
+        assert!(!full.has(&Capability::Encoder("------")));
+    }
+
+    #[test]
+    fn test_parse_unrecognized() {
+        assert_eq!(Capabilities::parse("", ENCODERS_FULL), None);
+        assert_eq!(Capabilities::parse(FILTERS_FULL, "GIF89a"), None);
+        // NOTE: E.g. a legend without any encoder.
+        assert_eq!(
+            Capabilities::parse(FILTERS_FULL, "Encoders:\n V..... = Video\n"),
+            None
+        );
+    }
+
+    #[test]
+    fn test_missing() {
+        let full = Capabilities::parse(FILTERS_FULL, ENCODERS_FULL).unwrap();
+        let slim = Capabilities::parse(FILTERS_SLIM, ENCODERS_SLIM).unwrap();
+        let cases: [(Settings, Option<Capability>); 8] = [
+            (settings(), None),
+            (settings().saturation(0.0), Some(Capability::Filter("eq"))),
+            (settings().brightness(0.2), Some(Capability::Filter("eq"))),
+            (
+                settings().subtitles("captions.srt".into()),
+                Some(Capability::Filter("subtitles")),
+            ),
+            // NOTE: The generated graph is ignored along with its filters.
+            (
+                settings()
+                    .subtitles("captions.srt".into())
+                    .custom_filter_complex("fps=10"),
+                None,
+            ),
+            (
+                settings().saturation(0.0).subtitles("captions.srt".into()),
+                Some(Capability::Filter("eq")),
+            ),
+            (
+                settings().output_format(OutputFormat::WebP {
+                    quality: 75,
+                    lossless: true,
+                }),
+                Some(Capability::Encoder("libwebp_anim")),
+            ),
+            (settings().output_format(OutputFormat::Apng), None),
+        ];
+        for (settings, expected) in cases {
+            assert_eq!(full.missing(&settings).map(|r| r.capability), None);
+            assert_eq!(slim.missing(&settings).map(|r| r.capability), expected);
+        }
+        let no_gif = Capabilities::parse(FILTERS_FULL, "Encoders:\n ------\n").unwrap();
+        assert_eq!(
+            no_gif.missing(&settings()).map(|r| r.capability),
+            Some(Capability::Encoder("gif"))
+        );
+        let apng = settings().output_format(OutputFormat::Apng);
+        assert_eq!(
+            no_gif.missing(&apng).map(|r| r.capability),
+            Some(Capability::Encoder("apng"))
+        );
+    }
+
+    #[test]
+    fn test_cached() {
+        let path = Path::new("/nonexistent/test_cached/ffmpeg");
+        let mut probes = vec![];
+        let mut probe = |args: &[&str]| -> Result<String, ()> {
+            probes.push(args[1].to_string());
+            Ok(match args[1] {
+                "-filters" => FILTERS_SLIM.into(),
+                _ => ENCODERS_SLIM.into(),
+            })
+        };
+        let capabilities = cached(path, &mut probe).unwrap().unwrap();
+        assert!(capabilities.has(&Capability::Encoder("gif")));
+        assert_eq!(cached(path, &mut probe).unwrap(), Some(capabilities));
+        assert_eq!(probes, ["-filters", "-encoders"]);
+
+        // NOTE: Errors are not cached.
+        let path = Path::new("/nonexistent/test_cached/ffmpeg-broken");
+        assert_eq!(cached(path, |_| Err::<String, _>(())), Err(()));
+        assert!(cached(path, |_| Ok::<_, ()>(String::new()))
+            .unwrap()
+            .is_none());
+    }
+}
@@ -0,0 +1,213 @@
+//! The cleanup of a conversion job's threads and child process (see [`crate::Converter::convert`])
+//! when the main thread does not reach the end of the job, i.e. when it panics (or unwinds
+//! because one of the threads it joins panicked) after the child process was spawned.
+//!
+//! The guard owns the child process until the CHILD thread takes it over (see
+//! [`CleanupGuard::spawn_with_child`]), and the handles of the threads until they are joined. Whatever
+//! it still owns when dropped gets cleaned up: the job is marked as cancelled and ended (so
+//! that the threads stop sending messages, and exit), the child process is killed (by the
+//! guard itself, or by the CHILD thread), and the threads are joined, those that do not exit
+//! within [`GRACE_PERIOD_MS`] being left behind. The panic itself keeps unwinding.
+
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Mutex};
+use std::thread::JoinHandle;
+use std::time::Duration;
+
+use crate::deadline::Deadline;
+use crate::internal_events::ThreadKind;
+use crate::job_tag::{job_log, JobTag};
+
+const LOG_TARGET: &str = "ffmpeg_gif_maker::converter::cleanup_guard";
+/// How long the guard waits for the threads to exit, once the child process is gone.
+const GRACE_PERIOD_MS: u64 = 2_000;
+/// The interval at which the guard checks whether the threads have exited.
+const SLEEP_DURATION_MS: u64 = 10;
+
+#[cfg(test)]
+#[derive(Debug, Default, Clone, PartialEq, Eq)]
+/// What the guard cleaned up when it was dropped, for the tests to check.
+pub(crate) struct CleanupReport {
+    /// Whether the guard still owned the child process, which it then killed and reaped.
+    pub(crate) child_reaped: bool,
+    /// The threads that were joined, in the order in which they were spawned.
+    pub(crate) joined: Vec<ThreadKind>,
+    /// The threads that did not exit in time.
+    pub(crate) left_behind: Vec<ThreadKind>,
+}
+
+/// Cleans up after a conversion job when dropped (see the module's documentation).
+pub(crate) struct CleanupGuard {
+    tag: JobTag,
+    job_cancelled: Arc<Mutex<bool>>,
+    job_ended: Arc<Mutex<bool>>,
+    kill_requested: Arc<AtomicBool>,
+    /// The child process, until the CHILD thread takes it over.
+    child: Option<std::process::Child>,
+    /// The threads that have not been joined yet.
+    threads: Vec<(ThreadKind, JoinHandle<()>)>,
+    #[cfg(test)]
+    report: Option<Arc<Mutex<Option<CleanupReport>>>>,
+}
+
+impl CleanupGuard {
+    pub(crate) fn new(
+        tag: JobTag,
+        job_cancelled: Arc<Mutex<bool>>,
+        job_ended: Arc<Mutex<bool>>,
+        kill_requested: Arc<AtomicBool>,
+        child: std::process::Child,
+    ) -> Self {
+        Self {
+            tag,
+            job_cancelled,
+            job_ended,
+            kill_requested,
+            child: Some(child),
+            threads: vec![],
+            #[cfg(test)]
+            report: None,
+        }
+    }
+
+    #[cfg(test)]
+    /// Makes the guard write what it cleaned up to `report` when dropped.
+    pub(crate) fn report_to(&mut self, report: Arc<Mutex<Option<CleanupReport>>>) {
+        self.report = Some(report);
+    }
+
+    /// Spawns the thread of the given `kind`, running `f`.
+    pub(crate) fn spawn(&mut self, kind: ThreadKind, f: impl FnOnce() + Send + 'static) {
+        self.threads.push((kind, std::thread::spawn(f)));
+    }
+
+    /// Same as [`CleanupGuard::spawn`], but hands the child process over to the thread, which
+    /// then waits for it to exit (and kills it when the `kill_requested` flag is set).
+    pub(crate) fn spawn_with_child(
+        &mut self,
+        kind: ThreadKind,
+        f: impl FnOnce(std::process::Child) + Send + 'static,
+    ) {
+        if let Some(child) = self.child.take() {
+            self.spawn(kind, move || f(child));
+        }
+    }
+
+    /// Whether the thread of the given `kind` has exited (or is not tracked by the guard).
+    pub(crate) fn is_finished(&self, kind: ThreadKind) -> bool {
+        self.threads
+            .iter()
+            .all(|(k, handle)| *k != kind || handle.is_finished())
+    }
+
+    /// Joins the thread of the given `kind`, if it is tracked by the guard.
+    pub(crate) fn join(&mut self, kind: ThreadKind) -> Option<std::thread::Result<()>> {
+        let index = self.threads.iter().position(|(k, _)| *k == kind)?;
+        Some(self.threads.remove(index).1.join())
+    }
+
+    /// Stops tracking the thread of the given `kind` (which keeps running on its own).
+    pub(crate) fn abandon(&mut self, kind: ThreadKind) {
+        self.threads.retain(|(k, _)| *k != kind);
+    }
+}
+
+impl Drop for CleanupGuard {
+    fn drop(&mut self) {
+        #[cfg(test)]
+        let mut report = CleanupReport::default();
+        if self.child.is_none() && self.threads.is_empty() {
+            #[cfg(test)]
+            if let Some(slot) = self.report.as_ref() {
+                *slot.lock().unwrap_or_else(|e| e.into_inner()) = Some(report);
+            }
+            return;
+        }
+        job_log!(
+            error,
+            LOG_TARGET,
+            self.tag,
+            "Job did not run to completion (panicking: {}), so cleaning up {} thread(s) and the child process...",
+            std::thread::panicking(),
+            self.threads.len()
+        );
+        *self.job_cancelled.lock().unwrap_or_else(|e| e.into_inner()) = true;
+        *self.job_ended.lock().unwrap_or_else(|e| e.into_inner()) = true;
+        self.kill_requested.store(true, Ordering::SeqCst);
+
+        if let Some(mut child) = self.child.take() {
+            if let Err(e) = child.kill() {
+                job_log!(
+                    warn,
+                    LOG_TARGET,
+                    self.tag,
+                    "Failed to kill child process: {:?}",
+                    e
+                );
+            }
+            match child.wait() {
+                Ok(status) => {
+                    job_log!(
+                        debug,
+                        LOG_TARGET,
+                        self.tag,
+                        "Child process reaped: {:?}",
+                        status
+                    );
+                    #[cfg(test)]
+                    {
+                        report.child_reaped = true;
+                    }
+                }
+                Err(e) => job_log!(
+                    warn,
+                    LOG_TARGET,
+                    self.tag,
+                    "Failed to wait for child process: {:?}",
+                    e
+                ),
+            }
+        }
+
+        let grace_period = Deadline::after(Duration::from_millis(GRACE_PERIOD_MS));
+        while !self.threads.iter().all(|(_, handle)| handle.is_finished())
+            && !grace_period.expired()
+        {
+            std::thread::sleep(Duration::from_millis(SLEEP_DURATION_MS));
+        }
+        for (name, handle) in self.threads.drain(..) {
+            if !handle.is_finished() {
+                job_log!(
+                    error,
+                    LOG_TARGET,
+                    self.tag,
+                    "{} thread still running after {:?}, so leaving it behind.",
+                    name,
+                    grace_period.timeout()
+                );
+                #[cfg(test)]
+                report.left_behind.push(name);
+                continue;
+            }
+            // NOTE: A thread that panicked has already reported it, and the main thread
+            // is most likely unwinding already.
+            if let Err(e) = handle.join() {
+                job_log!(
+                    warn,
+                    LOG_TARGET,
+                    self.tag,
+                    "{} thread panicked: {:?}",
+                    name,
+                    e
+                );
+            }
+            #[cfg(test)]
+            report.joined.push(name);
+        }
+        job_log!(info, LOG_TARGET, self.tag, "Job cleaned up.");
+        #[cfg(test)]
+        if let Some(slot) = self.report.as_ref() {
+            *slot.lock().unwrap_or_else(|e| e.into_inner()) = Some(report);
+        }
+    }
+}
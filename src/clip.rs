@@ -0,0 +1,274 @@
+//! The selection of the part of the source video that gets converted (see
+//! [`crate::Settings::clip`]), which is passed to FFmpeg using the `-ss` (start
+//! offset) and `-t` (length, unless the part extends to the end of the video) input options,
+//! or using the `trim` filter (see [`SeekMode`]).
+
+use std::time::Duration;
+
+use crate::SettingsError;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
+/// The part of the source video to convert into an animated GIF (see [`crate::Settings::clip`]).
+pub enum ClipSelection {
+    /// The given duration, starting at the beginning of the video.
+    FromStart(Duration),
+    /// The given duration, ending at the end of the video.
+    ///
+    /// NOTE: The video's duration needs to be probed before starting the job.
+    FromEnd(Duration),
+    /// The given duration, centered on the middle of the video.
+    ///
+    /// NOTE: The video's duration needs to be probed before starting the job.
+    Middle(Duration),
+    /// The part between the given start and end timestamps.
+    Range(Duration, Duration),
+    /// The part starting at the given timestamp, until the end of the video.
+    StartingAt(Duration),
+}
+
+impl ClipSelection {
+    /// Whether the video's duration is needed to find the part to convert.
+    pub(crate) fn needs_duration(&self) -> bool {
+        matches!(self, Self::FromEnd(_) | Self::Middle(_))
+    }
+
+    /// Makes sure that the selection is not empty.
+    pub(crate) fn validate(&self) -> Result<(), SettingsError> {
+        let empty = match *self {
+            Self::FromStart(d) | Self::FromEnd(d) | Self::Middle(d) => d.is_zero(),
+            Self::Range(start, end) => end <= start,
+            Self::StartingAt(_) => false,
+        };
+        if empty {
+            return Err(SettingsError::EmptyClip);
+        }
+        Ok(())
+    }
+
+    /// The start offset and the length (if the part does not extend to the end of the
+    /// video) of the part to convert, given the video's `duration`, or `None` if the
+    /// duration is needed but unknown. Selections that are longer than the video are
+    /// clamped to the video's bounds.
+    pub(crate) fn bounds(
+        &self,
+        duration: Option<Duration>,
+    ) -> Option<(Duration, Option<Duration>)> {
+        match *self {
+            Self::FromStart(length) => Some((Duration::ZERO, Some(length))),
+            Self::Range(start, end) => Some((start, Some(end.saturating_sub(start)))),
+            Self::StartingAt(start) => Some((start, None)),
+            Self::FromEnd(length) => {
+                let duration = duration?;
+                Some((duration.saturating_sub(length), Some(length.min(duration))))
+            }
+            Self::Middle(length) => {
+                let duration = duration?;
+                let length = length.min(duration);
+                Some(((duration - length) / 2, Some(length)))
+            }
+        }
+    }
+
+    /// Resolves the selection into a [`ClipSelection::Range`], which does not need the
+    /// video's duration anymore, or `None` if the duration is needed but unknown.
+    pub(crate) fn resolve(&self, duration: Option<Duration>) -> Option<Self> {
+        match self.bounds(duration)? {
+            (start, Some(length)) => Some(Self::Range(start, start + length)),
+            (start, None) => Some(Self::StartingAt(start)),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
+/// How FFmpeg seeks to the start of the part of the video selected using
+/// [`crate::Settings::clip`] (see [`crate::Settings::seek_mode`]).
+///
+/// NOTE: Whatever the mode, the progress values are relative to the selected part's length,
+/// since FFmpeg's `time` values are the output's timestamps, which start at zero with the
+/// selected part's start (they are negative with [`SeekMode::Fast`] for the frames between
+/// the keyframe and the start, which count as no progress).
+pub enum SeekMode {
+    /// FFmpeg jumps to the last keyframe before the start (using the `-ss` and
+    /// `-noaccurate_seek` input options) and converts from there, so that the animated GIF
+    /// may start up to a keyframe interval early. The fastest mode.
+    Fast,
+    /// FFmpeg reads the video from its beginning (up to the end of the selected part, using
+    /// the `-t` input option), the frames before the start being dropped by the generated
+    /// filter graph (using the `trim` filter), so that every frame before the start gets
+    /// decoded. The slowest mode, for the inputs whose seeking is unreliable (e.g. some
+    /// MPEG-TS streams, or files with a broken index).
+    ///
+    /// NOTE: The generated filter graph being needed, this mode cannot be used along with
+    /// [`crate::Settings::custom_filter_complex`].
+    Accurate,
+    /// FFmpeg jumps to the last keyframe before the start (using the `-ss` input option), then
+    /// decodes (and drops) the frames up to the start, so that the animated GIF starts exactly
+    /// at the start.
+    #[default]
+    FastThenAccurate,
+}
+
+impl SeekMode {
+    /// The input options (placed before `-i`) that select the part of the video given by
+    /// `bounds` (see [`ClipSelection::bounds`]).
+    pub(crate) fn input_args(&self, (start, length): (Duration, Option<Duration>)) -> Vec<String> {
+        let mut args = vec![];
+        match self {
+            Self::Accurate => {
+                if let Some(length) = length {
+                    args.extend(["-t".into(), seconds(start + length)]);
+                }
+            }
+            Self::Fast | Self::FastThenAccurate => {
+                args.extend(["-ss".into(), seconds(start)]);
+                if *self == Self::Fast {
+                    args.push("-noaccurate_seek".into());
+                }
+                if let Some(length) = length {
+                    args.extend(["-t".into(), seconds(length)]);
+                }
+            }
+        }
+        args
+    }
+
+    /// The filter that drops the frames before `start` with [`SeekMode::Accurate`], shifting
+    /// the timestamps of the others so that the first one is zero (as with the other modes),
+    /// or `None` if there is nothing to drop.
+    pub(crate) fn trim_filter(&self, start: Duration) -> Option<String> {
+        (*self == Self::Accurate && !start.is_zero())
+            .then(|| format!("trim=start={},setpts=PTS-STARTPTS", seconds(start)))
+    }
+
+    /// Whether FFmpeg's `time` values can be negative, for the frames between the keyframe
+    /// and the start.
+    pub(crate) fn leads_in(&self) -> bool {
+        *self == Self::Fast
+    }
+}
+
+/// Formats a timestamp (in seconds) for FFmpeg's `-ss` and `-t` options.
+pub(crate) fn seconds(timestamp: Duration) -> String {
+    format!("{:.3}", timestamp.as_secs_f64())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn s(n: f64) -> Duration {
+        Duration::from_secs_f64(n)
+    }
+
+    #[test]
+    fn test_bounds() {
+        let duration = Some(s(10.0));
+        assert_eq!(
+            ClipSelection::FromStart(s(5.0)).bounds(None),
+            Some((s(0.0), Some(s(5.0))))
+        );
+        assert_eq!(
+            ClipSelection::Range(s(2.0), s(4.5)).bounds(None),
+            Some((s(2.0), Some(s(2.5))))
+        );
+        assert_eq!(
+            ClipSelection::StartingAt(s(42.0)).bounds(None),
+            Some((s(42.0), None))
+        );
+        assert_eq!(
+            ClipSelection::FromEnd(s(3.0)).bounds(duration),
+            Some((s(7.0), Some(s(3.0))))
+        );
+        assert_eq!(
+            ClipSelection::Middle(s(4.0)).bounds(duration),
+            Some((s(3.0), Some(s(4.0))))
+        );
+    }
+
+    #[test]
+    fn test_bounds_longer_than_video() {
+        let duration = Some(s(10.0));
+        assert_eq!(
+            ClipSelection::FromEnd(s(30.0)).bounds(duration),
+            Some((s(0.0), Some(s(10.0))))
+        );
+        assert_eq!(
+            ClipSelection::Middle(s(30.0)).bounds(duration),
+            Some((s(0.0), Some(s(10.0))))
+        );
+    }
+
+    #[test]
+    fn test_bounds_need_duration() {
+        for clip in [
+            ClipSelection::FromEnd(s(3.0)),
+            ClipSelection::Middle(s(3.0)),
+        ] {
+            assert!(clip.needs_duration());
+            assert_eq!(clip.bounds(None), None);
+            assert_eq!(clip.resolve(None), None);
+        }
+        assert!(!ClipSelection::FromStart(s(3.0)).needs_duration());
+        assert!(!ClipSelection::Range(s(1.0), s(3.0)).needs_duration());
+        assert!(!ClipSelection::StartingAt(s(1.0)).needs_duration());
+    }
+
+    #[test]
+    fn test_resolve() {
+        assert_eq!(
+            ClipSelection::FromEnd(s(2.0)).resolve(Some(s(5.0))),
+            Some(ClipSelection::Range(s(3.0), s(5.0)))
+        );
+        assert_eq!(
+            ClipSelection::Middle(s(1.0)).resolve(Some(s(5.0))),
+            Some(ClipSelection::Range(s(2.0), s(3.0)))
+        );
+    }
+
+    #[test]
+    fn test_seek_mode_input_args() {
+        let bounds = (s(3.0), Some(s(2.0)));
+        assert_eq!(
+            SeekMode::FastThenAccurate.input_args(bounds),
+            ["-ss", "3.000", "-t", "2.000"]
+        );
+        assert_eq!(
+            SeekMode::Fast.input_args(bounds),
+            ["-ss", "3.000", "-noaccurate_seek", "-t", "2.000"]
+        );
+        assert_eq!(SeekMode::Accurate.input_args(bounds), ["-t", "5.000"]);
+        assert!(SeekMode::Accurate.input_args((s(3.0), None)).is_empty());
+        assert_eq!(
+            SeekMode::Fast.input_args((s(3.0), None)),
+            ["-ss", "3.000", "-noaccurate_seek"]
+        );
+    }
+
+    #[test]
+    fn test_seek_mode_trim_filter() {
+        assert_eq!(
+            SeekMode::Accurate.trim_filter(s(3.0)).as_deref(),
+            Some("trim=start=3.000,setpts=PTS-STARTPTS")
+        );
+        assert_eq!(SeekMode::Accurate.trim_filter(Duration::ZERO), None);
+        assert_eq!(SeekMode::Fast.trim_filter(s(3.0)), None);
+        assert_eq!(SeekMode::FastThenAccurate.trim_filter(s(3.0)), None);
+    }
+
+    #[test]
+    fn test_validate() {
+        assert_eq!(ClipSelection::FromStart(s(1.0)).validate(), Ok(()));
+        assert_eq!(ClipSelection::Range(s(1.0), s(1.5)).validate(), Ok(()));
+        assert_eq!(ClipSelection::StartingAt(Duration::ZERO).validate(), Ok(()));
+        assert_eq!(
+            ClipSelection::Middle(Duration::ZERO).validate(),
+            Err(SettingsError::EmptyClip)
+        );
+        assert_eq!(
+            ClipSelection::Range(s(2.0), s(1.0)).validate(),
+            Err(SettingsError::EmptyClip)
+        );
+    }
+}
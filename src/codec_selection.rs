@@ -0,0 +1,241 @@
+//! The parsing of the `Stream mapping:` section written by FFmpeg to `stderr`, which
+//! tells which decoder and encoder were actually selected for the video stream (see
+//! [`crate::Message::CodecSelection`]), and of the lines reporting that a hardware
+//! accelerated decoder could not be set up (see [`crate::Warning::HardwareAccelerationFailed`]).
+//!
+//! The section comes in two forms: without any filter graph, each line maps an input
+//! stream to an output stream (e.g. `Stream #0:0 -> #0:0 (h264 (native) -> gif (native))`,
+//! where the implementation in parentheses is either `native` or the name of a specific
+//! decoder or encoder, such as `h264_cuvid`), and with a filter graph (as used by the
+//! conversion job), the decoder and the encoder are given on separate lines (e.g.
+//! `Stream #0:0 (h264) -> fps:default` and `paletteuse:default -> Stream #0:0 (gif)`).
+
+/// The suffixes of the names of FFmpeg's hardware decoders and encoders.
+const HARDWARE_SUFFIXES: [&str; 13] = [
+    "_amf",
+    "_cuvid",
+    "_d3d11va",
+    "_dxva2",
+    "_mediacodec",
+    "_mmal",
+    "_nvdec",
+    "_nvenc",
+    "_qsv",
+    "_rkmpp",
+    "_v4l2m2m",
+    "_vaapi",
+    "_videotoolbox",
+];
+
+/// The substrings of the lines written by FFmpeg when a hardware accelerated decoder
+/// could not be set up, in which case it falls back to a software decoder.
+const HARDWARE_FAILURE_PATTERNS: [&str; 3] = [
+    "hwaccel initialisation returned error",
+    "Device creation failed",
+    "Hardware device setup failed",
+];
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+/// The decoder and encoder selected for the video stream.
+pub(crate) struct CodecSelection {
+    pub(crate) decoder: String,
+    pub(crate) encoder: String,
+}
+
+impl CodecSelection {
+    /// Whether the decoder or the encoder is a hardware one (e.g. `h264_cuvid`).
+    pub(crate) fn hw_accelerated(&self) -> bool {
+        [&self.decoder, &self.encoder]
+            .iter()
+            .any(|name| HARDWARE_SUFFIXES.iter().any(|s| name.ends_with(s)))
+    }
+}
+
+#[derive(Debug, Default)]
+/// Finds the [`CodecSelection`] in FFmpeg's `stderr` output, fed line by line.
+pub(crate) struct StreamMappingParser {
+    in_section: bool,
+    decoder: Option<String>,
+    encoder: Option<String>,
+    done: bool,
+}
+
+impl StreamMappingParser {
+    /// Parses the next `line`, returning the codec selection once both the decoder and
+    /// the encoder have been found (which happens at most once).
+    pub(crate) fn push(&mut self, line: &str) -> Option<CodecSelection> {
+        if self.done {
+            return None;
+        }
+        let line = line.trim();
+        if line == "Stream mapping:" {
+            self.in_section = true;
+            return None;
+        }
+        if !self.in_section {
+            return None;
+        }
+        if let Some((_, output)) = line
+            .strip_prefix("Stream #")
+            .and_then(|rest| rest.split_once(" -> #"))
+        {
+            // NOTE: E.g. `0:0 -> #0:0 (h264 (native) -> gif (native))`, or `(copy)`.
+            if let Some((decoder, encoder)) = parenthesized(output)
+                .and_then(|codecs| codecs.split_once(" -> "))
+                .and_then(|(d, e)| Some((implementation(d)?, implementation(e)?)))
+            {
+                self.decoder.get_or_insert(decoder);
+                self.encoder.get_or_insert(encoder);
+            }
+        } else if let Some(rest) = line.strip_prefix("Stream #") {
+            // NOTE: E.g. `0:0 (h264) -> fps:default`.
+            if let Some((input, _)) = rest.split_once(" -> ") {
+                if let Some(decoder) = parenthesized(input) {
+                    self.decoder.get_or_insert(decoder.to_string());
+                }
+            }
+        } else if let Some((_, output)) = line.split_once(" -> Stream #") {
+            // NOTE: E.g. `paletteuse:default -> Stream #0:0 (gif)`.
+            if let Some(encoder) = parenthesized(output) {
+                self.encoder.get_or_insert(encoder.to_string());
+            }
+        } else {
+            self.in_section = false;
+        }
+        match (&self.decoder, &self.encoder) {
+            (Some(decoder), Some(encoder)) => {
+                self.done = true;
+                Some(CodecSelection {
+                    decoder: decoder.clone(),
+                    encoder: encoder.clone(),
+                })
+            }
+            _ => None,
+        }
+    }
+}
+
+/// The text between the first `(` of `s` and its last `)`.
+fn parenthesized(s: &str) -> Option<&str> {
+    let start = s.find('(')?;
+    let end = s.rfind(')')?;
+    s.get(start + 1..end)
+}
+
+/// The name of the decoder or encoder given by `codec`, which is either `name (native)`
+/// (i.e. FFmpeg's own implementation, named after the codec), or `codec (name)`.
+fn implementation(codec: &str) -> Option<String> {
+    match codec.trim().split_once(' ') {
+        Some((name, "(native)")) => Some(name.to_string()),
+        Some((_, implementation)) => implementation
+            .strip_prefix('(')
+            .and_then(|s| s.strip_suffix(')'))
+            .map(String::from),
+        None => Some(codec.trim().to_string()).filter(|name| !name.is_empty()),
+    }
+}
+
+/// Whether `line` reports that a hardware accelerated decoder could not be set up.
+pub(crate) fn is_hardware_failure(line: &str) -> bool {
+    HARDWARE_FAILURE_PATTERNS.iter().any(|p| line.contains(p))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn parse(stderr: &str) -> Option<CodecSelection> {
+        let mut parser = StreamMappingParser::default();
+        stderr.lines().find_map(|line| parser.push(line))
+    }
+
+    fn selection(decoder: &str, encoder: &str) -> Option<CodecSelection> {
+        Some(CodecSelection {
+            decoder: decoder.into(),
+            encoder: encoder.into(),
+        })
+    }
+
+    #[test]
+    fn test_stream_mapping_fixtures() {
+        let cases = [
+            (
+                "native",
+                "Stream mapping:\n  Stream #0:0 -> #0:0 (h264 (native) -> gif (native))\nPress [q] to stop, [?] for help",
+                selection("h264", "gif"),
+                false,
+            ),
+            (
+                "cuvid",
+                "Stream mapping:\n  Stream #0:0 -> #0:0 (h264 (h264_cuvid) -> gif (native))\n",
+                selection("h264_cuvid", "gif"),
+                true,
+            ),
+            (
+                "videotoolbox",
+                "Stream mapping:\n  Stream #0:0 -> #0:0 (hevc (native) -> h264 (h264_videotoolbox))\n  Stream #0:1 -> #0:1 (aac (native) -> aac (native))\n",
+                selection("hevc", "h264_videotoolbox"),
+                true,
+            ),
+            (
+                "filter graph",
+                crate::test_utils::SAMPLE_STDERR,
+                selection("h264", "gif"),
+                false,
+            ),
+            (
+                "filter graph (cuvid)",
+                "Stream mapping:\n  Stream #0:0 (h264_cuvid) -> fps:default\n  paletteuse:default -> Stream #0:0 (gif)\n",
+                selection("h264_cuvid", "gif"),
+                true,
+            ),
+            (
+                "stream copy",
+                "Stream mapping:\n  Stream #0:0 -> #0:0 (copy)\n",
+                None,
+                false,
+            ),
+            (
+                "no mapping",
+                "Input #0, mov,mp4,m4a,3gp,3g2,mj2, from 'in.mp4':\n  Stream #0:0 -> #0:0 (h264 (native) -> gif (native))\n",
+                None,
+                false,
+            ),
+        ];
+        for (name, stderr, expected, hw_accelerated) in cases {
+            let parsed = parse(stderr);
+            assert_eq!(parsed, expected, "{}", name);
+            if let Some(parsed) = parsed {
+                assert_eq!(parsed.hw_accelerated(), hw_accelerated, "{}", name);
+            }
+        }
+    }
+
+    #[test]
+    fn test_stream_mapping_section_end() {
+        // The encoder is only found after the section, so it is not reported.
+        let stderr = "Stream mapping:\n  Stream #0:0 (h264) -> fps:default\nOutput #0, gif, to 'pipe:':\n  paletteuse:default -> Stream #0:0 (gif)\n";
+        assert_eq!(parse(stderr), None);
+        // The selection is only reported once.
+        let mut parser = StreamMappingParser::default();
+        let line = "  Stream #0:0 -> #0:0 (h264 (native) -> gif (native))";
+        assert_eq!(parser.push("Stream mapping:"), None);
+        assert_eq!(parser.push(line), selection("h264", "gif"));
+        assert_eq!(parser.push(line), None);
+    }
+
+    #[test]
+    fn test_is_hardware_failure() {
+        for line in [
+            "[h264 @ 0x7f8b5c00a000] Failed setup for format cuda: hwaccel initialisation returned error.",
+            "[h264 @ 0x7fd1f8821e00] Failed setup for format videotoolbox_vld: hwaccel initialisation returned error.",
+            "Device creation failed: -12.",
+            "[vist#0:0/h264 @ 0x600003d5c000] Hardware device setup failed for decoder: Cannot allocate memory",
+        ] {
+            assert!(is_hardware_failure(line), "{}", line);
+        }
+        assert!(!is_hardware_failure(
+            "  Stream #0:0 -> #0:0 (h264 (h264_cuvid) -> gif (native))"
+        ));
+    }
+}
@@ -0,0 +1,893 @@
+//! The options of [`crate::Settings`] that conflict with each other (see
+//! [`crate::Settings::conflicts`]), i.e. combinations in which one option silently
+//! overrides, or breaks, another one.
+//!
+//! Soft conflicts are resolved by the [`crate::Converter`] (e.g. by ignoring one of
+//! the options), and are reported using [`crate::Warning::SettingsConflict`], while
+//! hard conflicts make [`crate::Settings::validate`] fail with
+//! [`crate::SettingsError::Conflict`].
+//!
+//! NOTE: [`find_conflicts`] destructures the settings without `..`, so that adding an
+//! option does not compile until its interactions with the other options are declared.
+
+use crate::{
+    EvenDimensionPolicy, FitMode, HwAccelFallback, OutputFormat, PaletteStatsMode, SeekMode,
+    Settings,
+};
+
+/// The options overridden by [`crate::Settings::custom_filter_complex`] (see
+/// [`SettingsConflict::CustomFilterComplexOverrides`]), i.e. those that only feed the
+/// generated filter graph, or whose handling relies on it (e.g. the frame rate).
+static CUSTOM_FILTER_COMPLEX_OVERRIDES: [[&str; 2]; 29] = [
+    ["custom_filter_complex", "gif_height"],
+    ["custom_filter_complex", "fit_mode"],
+    ["custom_filter_complex", "scale_algorithm"],
+    ["custom_filter_complex", "even_dimension_policy"],
+    ["custom_filter_complex", "rotation"],
+    ["custom_filter_complex", "hflip"],
+    ["custom_filter_complex", "vflip"],
+    ["custom_filter_complex", "saturation"],
+    ["custom_filter_complex", "brightness"],
+    ["custom_filter_complex", "contrast"],
+    ["custom_filter_complex", "watermark"],
+    ["custom_filter_complex", "subtitles"],
+    ["custom_filter_complex", "preserve_last_frame"],
+    ["custom_filter_complex", "max_colors"],
+    ["custom_filter_complex", "auto_colors"],
+    ["custom_filter_complex", "max_palette_bit_depth"],
+    ["custom_filter_complex", "palette_stats_mode"],
+    ["custom_filter_complex", "crop_keyframes"],
+    ["custom_filter_complex", "speed"],
+    ["custom_filter_complex", "frame_step"],
+    ["custom_filter_complex", "boomerang"],
+    ["custom_filter_complex", "output_frame_limit"],
+    ["custom_filter_complex", "emit_frame_map"],
+    ["custom_filter_complex", "interpolate_frame_delays"],
+    ["custom_filter_complex", "max_output_bytes"],
+    ["custom_filter_complex", "additional_widths"],
+    ["custom_filter_complex", "seek_mode"],
+    ["custom_filter_complex", "video_stream_index"],
+    ["custom_filter_complex", "badge_overlay"],
+];
+
+/// The options that only apply to an animated GIF (see
+/// [`SettingsConflict::OutputFormatOverrides`]), i.e. those that feed its palette, or
+/// whose handling relies on the GIF's structure (e.g. its frames being counted).
+static OUTPUT_FORMAT_OVERRIDES: [[&str; 2]; 9] = [
+    ["output_format", "max_colors"],
+    ["output_format", "auto_colors"],
+    ["output_format", "max_palette_bit_depth"],
+    ["output_format", "palette_stats_mode"],
+    ["output_format", "global_palette_only"],
+    ["output_format", "emit_frame_map"],
+    ["output_format", "interpolate_frame_delays"],
+    ["output_format", "max_output_bytes"],
+    ["output_format", "additional_widths"],
+];
+
+/// The options that read the video more than once (see [`SettingsConflict::ReadOnceInput`]),
+/// i.e. before the conversion (e.g. to analyze its colors, or to generate a preview), or once
+/// per attempt (e.g. when falling back to software decoding).
+static READ_ONCE_INPUT_CONFLICTS: [[&str; 2]; 5] = [
+    ["with_input", "auto_colors"],
+    ["with_input", "clip"],
+    ["with_input", "max_output_bytes"],
+    ["with_input", "hwaccel_fallback"],
+    ["with_input", "preview_width"],
+];
+
+/// The options that need the animated GIF's bytes (see [`SettingsConflict::OutputPathOverrides`]),
+/// i.e. those that read or rewrite it once complete, or that write it to a file themselves.
+static OUTPUT_PATH_CONFLICTS: [[&str; 2]; 4] = [
+    ["output_path", "output_file"],
+    ["output_path", "emit_frame_map"],
+    ["output_path", "interpolate_frame_delays"],
+    ["output_path", "max_output_bytes"],
+];
+
+/// The options that cannot be used when the animated GIF is streamed to a writer (see
+/// [`SettingsConflict::WriterOverrides`]), i.e. those of [`OUTPUT_PATH_CONFLICTS`], along
+/// with `output_path` itself, which leaves nothing to stream.
+static WRITER_CONFLICTS: [[&str; 2]; 5] = [
+    ["convert_to_writer", "output_path"],
+    ["convert_to_writer", "output_file"],
+    ["convert_to_writer", "emit_frame_map"],
+    ["convert_to_writer", "interpolate_frame_delays"],
+    ["convert_to_writer", "max_output_bytes"],
+];
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+/// How a [`SettingsConflict`] is handled.
+pub enum ConflictSeverity {
+    /// The conflict is resolved by the [`crate::Converter`] (see [`SettingsConflict`]'s
+    /// variants), and is reported using [`crate::Warning::SettingsConflict`].
+    Soft,
+    /// The conflict cannot be resolved, so [`crate::Settings::validate`] fails with
+    /// [`crate::SettingsError::Conflict`].
+    Hard,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+/// A combination of options of the [`crate::Settings`] in which one option overrides,
+/// or breaks, another one (see [`crate::Settings::conflicts`]).
+pub enum SettingsConflict {
+    /// Both [`crate::Settings::auto_colors`] and [`crate::Settings::max_colors`] were
+    /// provided, in which case the number of colors selected by the analysis is used,
+    /// and the value provided using [`crate::Settings::max_colors`] is ignored.
+    AutoColorsOverrideMaxColors,
+    /// The value provided using [`crate::Settings::max_colors`] is larger than the number
+    /// of colors allowed by [`crate::Settings::max_palette_bit_depth`], in which case the
+    /// number of colors is capped.
+    MaxColorsCappedByBitDepth {
+        /// The value provided using [`crate::Settings::max_colors`].
+        max_colors: u16,
+        /// The number of colors actually used.
+        cap: u16,
+    },
+    /// A [`FitMode`] was provided using [`crate::Settings::fit_mode`], but no height was
+    /// provided using [`crate::Settings::gif_height`], in which case the fit mode is ignored.
+    FitModeWithoutHeight,
+    /// Both [`crate::Settings::boomerang`] and [`crate::Settings::output_frame_limit`] were
+    /// provided, in which case the limit applies to the frames played forward, then backward,
+    /// so that the end of the backward half (or all of it) may be cut.
+    BoomerangFrameLimit,
+    /// A [`SeekMode`] was provided using [`crate::Settings::seek_mode`], but no part of the
+    /// video was selected using [`crate::Settings::clip`], in which case the seek mode is
+    /// ignored.
+    SeekModeWithoutClip,
+    /// A badge was provided using [`crate::Settings::badge_overlay`], but no preview was
+    /// requested using [`crate::Settings::preview_width`], in which case the badge is ignored
+    /// (the animated GIF itself never being stamped).
+    BadgeWithoutPreview,
+    /// The input (see [`crate::Settings::with_input`]) is the [`crate::Converter`]'s own
+    /// `stdin`, which is used to send the application's commands (e.g. [`crate::Command::Cancel`])
+    /// to the FFmpeg child process, and which cannot carry the video as well.
+    StdinInput,
+    /// Both [`crate::Settings::global_palette_only`] and [`PaletteStatsMode::Single`] (see
+    /// [`crate::Settings::palette_stats_mode`]) were provided, while the latter gives each
+    /// frame its own (i.e. local) color table, which the former rules out.
+    PerFramePalettesGlobalPaletteOnly,
+    /// The graph provided using [`crate::Settings::custom_filter_complex`] replaces the
+    /// generated one, which the given option would otherwise feed (e.g. `gif_height`).
+    CustomFilterComplexOverrides {
+        /// The name of the overridden option (i.e. of its setter method).
+        option: &'static str,
+    },
+    /// Both [`crate::Settings::max_output_bytes`] and [`crate::Settings::additional_widths`]
+    /// were provided, while the former re-encodes a single animated GIF.
+    SizeTargetAdditionalWidths,
+    /// The format provided using [`crate::Settings::output_format`] is not
+    /// [`OutputFormat::Gif`], while the given option only applies to an animated GIF
+    /// (e.g. `max_colors`).
+    OutputFormatOverrides {
+        /// The name of the overridden option (i.e. of its setter method).
+        option: &'static str,
+    },
+    /// The input can only be read once (see [`crate::InputSource::Reader`]), while the given
+    /// option reads the video more than once (e.g. `auto_colors`, which analyzes its colors
+    /// first, `clip`, when the part to convert depends on the video's duration, or
+    /// `hwaccel_fallback`, which runs the job again without hardware acceleration).
+    ReadOnceInput {
+        /// The name of the option (i.e. of its setter method).
+        option: &'static str,
+    },
+    /// The animated GIF is written by FFmpeg directly to the file provided using
+    /// [`crate::Settings::output_path`], without being kept in memory, while the given
+    /// option needs its bytes (e.g. `emit_frame_map`, which counts its frames).
+    OutputPathOverrides {
+        /// The name of the option (i.e. of its setter method).
+        option: &'static str,
+    },
+    /// The animated GIF is streamed to the writer provided to [`crate::Converter::convert_to_writer`]
+    /// as it is produced, without being kept in memory, while the given option needs its bytes
+    /// (or writes it elsewhere). Unlike the other conflicts, this one is not returned by
+    /// [`crate::Settings::conflicts`], since the writer is not one of the settings.
+    WriterOverrides {
+        /// The name of the option (i.e. of its setter method).
+        option: &'static str,
+    },
+}
+
+impl SettingsConflict {
+    /// The names of the options (i.e. of their setter methods) involved in the conflict,
+    /// the overriding option, if any, coming first.
+    pub fn options(&self) -> &'static [&'static str] {
+        match self {
+            Self::AutoColorsOverrideMaxColors => &["auto_colors", "max_colors"],
+            Self::MaxColorsCappedByBitDepth { .. } => &["max_palette_bit_depth", "max_colors"],
+            Self::FitModeWithoutHeight => &["gif_height", "fit_mode"],
+            Self::BoomerangFrameLimit => &["output_frame_limit", "boomerang"],
+            Self::SeekModeWithoutClip => &["clip", "seek_mode"],
+            Self::BadgeWithoutPreview => &["preview_width", "badge_overlay"],
+            Self::StdinInput => &["with_input"],
+            Self::PerFramePalettesGlobalPaletteOnly => {
+                &["global_palette_only", "palette_stats_mode"]
+            }
+            Self::CustomFilterComplexOverrides { option } => CUSTOM_FILTER_COMPLEX_OVERRIDES
+                .iter()
+                .find(|options| options[1] == *option)
+                .map_or(&["custom_filter_complex"], |options| options),
+            Self::SizeTargetAdditionalWidths => &["max_output_bytes", "additional_widths"],
+            Self::OutputFormatOverrides { option } => OUTPUT_FORMAT_OVERRIDES
+                .iter()
+                .find(|options| options[1] == *option)
+                .map_or(&["output_format"], |options| options),
+            Self::ReadOnceInput { option } => READ_ONCE_INPUT_CONFLICTS
+                .iter()
+                .find(|options| options[1] == *option)
+                .map_or(&["with_input"], |options| options),
+            Self::OutputPathOverrides { option } => OUTPUT_PATH_CONFLICTS
+                .iter()
+                .find(|options| options[1] == *option)
+                .map_or(&["output_path"], |options| options),
+            Self::WriterOverrides { option } => WRITER_CONFLICTS
+                .iter()
+                .find(|options| options[1] == *option)
+                .map_or(&["convert_to_writer"], |options| options),
+        }
+    }
+
+    /// How the conflict is handled.
+    pub fn severity(&self) -> ConflictSeverity {
+        match self {
+            Self::AutoColorsOverrideMaxColors
+            | Self::MaxColorsCappedByBitDepth { .. }
+            | Self::FitModeWithoutHeight
+            | Self::BoomerangFrameLimit
+            | Self::SeekModeWithoutClip
+            | Self::BadgeWithoutPreview => ConflictSeverity::Soft,
+            Self::StdinInput
+            | Self::PerFramePalettesGlobalPaletteOnly
+            | Self::CustomFilterComplexOverrides { .. }
+            | Self::SizeTargetAdditionalWidths
+            | Self::OutputFormatOverrides { .. }
+            | Self::ReadOnceInput { .. }
+            | Self::OutputPathOverrides { .. }
+            | Self::WriterOverrides { .. } => ConflictSeverity::Hard,
+        }
+    }
+
+    /// A short, stable, machine-friendly name for the kind of conflict (see [`crate::Error::kind`]).
+    pub fn kind(&self) -> &'static str {
+        match self {
+            Self::AutoColorsOverrideMaxColors => "auto_colors_override_max_colors",
+            Self::MaxColorsCappedByBitDepth { .. } => "max_colors_capped_by_bit_depth",
+            Self::FitModeWithoutHeight => "fit_mode_without_height",
+            Self::BoomerangFrameLimit => "boomerang_frame_limit",
+            Self::SeekModeWithoutClip => "seek_mode_without_clip",
+            Self::BadgeWithoutPreview => "badge_without_preview",
+            Self::StdinInput => "stdin_input",
+            Self::PerFramePalettesGlobalPaletteOnly => "per_frame_palettes_global_palette_only",
+            Self::CustomFilterComplexOverrides { .. } => "custom_filter_complex_overrides",
+            Self::SizeTargetAdditionalWidths => "size_target_additional_widths",
+            Self::OutputFormatOverrides { .. } => "output_format_overrides",
+            Self::ReadOnceInput { .. } => "read_once_input",
+            Self::OutputPathOverrides { .. } => "output_path_overrides",
+            Self::WriterOverrides { .. } => "writer_overrides",
+        }
+    }
+}
+
+impl std::fmt::Display for SettingsConflict {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::AutoColorsOverrideMaxColors => {
+                write!(f, "auto_colors overrides max_colors, which is ignored")
+            }
+            Self::MaxColorsCappedByBitDepth { max_colors, cap } => write!(
+                f,
+                "max_palette_bit_depth caps max_colors ({}) to {} colors",
+                max_colors, cap
+            ),
+            Self::FitModeWithoutHeight => {
+                write!(f, "fit_mode is ignored, since gif_height is not provided")
+            }
+            Self::BoomerangFrameLimit => write!(
+                f,
+                "output_frame_limit may cut the backward half of the boomerang"
+            ),
+            Self::SeekModeWithoutClip => {
+                write!(f, "seek_mode is ignored, since no clip is selected")
+            }
+            Self::BadgeWithoutPreview => {
+                write!(f, "badge_overlay is ignored, since no preview is generated")
+            }
+            Self::StdinInput => write!(
+                f,
+                "the input cannot be read from stdin, which carries the commands"
+            ),
+            Self::PerFramePalettesGlobalPaletteOnly => write!(
+                f,
+                "per-frame palettes cannot be used with global_palette_only"
+            ),
+            Self::CustomFilterComplexOverrides { option } => write!(
+                f,
+                "custom_filter_complex replaces the generated graph, so {} cannot be used",
+                option
+            ),
+            Self::SizeTargetAdditionalWidths => write!(
+                f,
+                "max_output_bytes re-encodes a single GIF, so additional_widths cannot be used"
+            ),
+            Self::OutputFormatOverrides { option } => {
+                write!(f, "output_format is not GIF, so {} cannot be used", option)
+            }
+            Self::ReadOnceInput { option } => write!(
+                f,
+                "the input can only be read once, so {} cannot be used",
+                option
+            ),
+            Self::OutputPathOverrides { option } => write!(
+                f,
+                "output_path writes the GIF directly to a file, so {} cannot be used",
+                option
+            ),
+            Self::WriterOverrides { option } => write!(
+                f,
+                "convert_to_writer streams the GIF to a writer, so {} cannot be used",
+                option
+            ),
+        }
+    }
+}
+
+/// The conflicts between the options of `settings`, in a stable order.
+///
+/// NOTE: When adding an option to [`Settings`], list its field below and check it
+/// against every other option; any conflict gets its own [`SettingsConflict`] variant
+/// (along with a test case in `test_conflicts_pairwise`).
+pub(crate) fn find_conflicts(settings: &Settings) -> Vec<SettingsConflict> {
+    let Settings {
+        ffmpeg_location: _,
+        command_wrapper: _,
+        argv_hook: _,
+        check_capabilities: _,
+        input,
+        input_format_hints: _,
+        video_stream_index,
+        hwaccel,
+        hwaccel_fallback,
+        threads: _,
+        gif_fps: _,
+        gif_width: _,
+        gif_height,
+        fit_mode,
+        scale_algorithm,
+        even_dimension_policy,
+        rotation,
+        hflip,
+        vflip,
+        saturation,
+        brightness,
+        contrast,
+        watermark,
+        subtitles,
+        preview_width,
+        badge_overlay,
+        pass: _,
+        preserve_last_frame,
+        interpolate_frame_delays,
+        max_colors,
+        auto_colors,
+        auxiliary_timeout: _,
+        deadline: _,
+        memory_budget: _,
+        output_format,
+        global_palette_only,
+        max_palette_bit_depth,
+        palette_stats_mode,
+        smooth_progress: _,
+        progress_curve: _,
+        crop_keyframes,
+        deny_stderr_patterns: _,
+        #[cfg(feature = "regex")]
+            deny_stderr_regexes: _,
+        output_size_warning_ratio: _,
+        max_output_bytes,
+        max_output_attempts: _,
+        clip,
+        seek_mode,
+        output_frame_limit,
+        emit_frame_map,
+        custom_filter_complex,
+        additional_widths,
+        extra_input_args: _,
+        extra_output_args: _,
+        emit_internal_events: _,
+        parse_provenance: _,
+        strict_parsing: _,
+        speed,
+        frame_step,
+        boomerang,
+        output_file,
+        output_path,
+        invalid_time_spec: _,
+        #[cfg(feature = "tokio")]
+            strict_async_context: _,
+    } = settings;
+
+    let mut conflicts = vec![];
+    if input.reads_stdin() {
+        conflicts.push(SettingsConflict::StdinInput);
+    }
+    if *auto_colors && max_colors.is_some() {
+        conflicts.push(SettingsConflict::AutoColorsOverrideMaxColors);
+    }
+    if let (Some(max_colors), Some(depth)) = (*max_colors, *max_palette_bit_depth) {
+        // NOTE: An out of range bit depth is reported by `Settings::validate` instead.
+        let cap = 1u16.checked_shl(u32::from(depth)).unwrap_or(u16::MAX);
+        if max_colors > cap {
+            conflicts.push(SettingsConflict::MaxColorsCappedByBitDepth { max_colors, cap });
+        }
+    }
+    if gif_height.is_none() && *fit_mode != FitMode::default() {
+        conflicts.push(SettingsConflict::FitModeWithoutHeight);
+    }
+    if *boomerang && output_frame_limit.is_some() {
+        conflicts.push(SettingsConflict::BoomerangFrameLimit);
+    }
+    if clip.is_none() && *seek_mode != SeekMode::default() {
+        conflicts.push(SettingsConflict::SeekModeWithoutClip);
+    }
+    if preview_width.is_none() && badge_overlay.is_some() {
+        conflicts.push(SettingsConflict::BadgeWithoutPreview);
+    }
+    if *global_palette_only && *palette_stats_mode == PaletteStatsMode::Single {
+        conflicts.push(SettingsConflict::PerFramePalettesGlobalPaletteOnly);
+    }
+    if max_output_bytes.is_some() && !additional_widths.is_empty() {
+        conflicts.push(SettingsConflict::SizeTargetAdditionalWidths);
+    }
+    if custom_filter_complex.is_some() {
+        // NOTE: In the same order as `CUSTOM_FILTER_COMPLEX_OVERRIDES`.
+        let overridden = [
+            gif_height.is_some(),
+            *fit_mode != FitMode::default(),
+            scale_algorithm.is_some(),
+            *even_dimension_policy != EvenDimensionPolicy::default(),
+            rotation.is_some(),
+            *hflip,
+            *vflip,
+            *saturation != 1.0,
+            *brightness != 0.0,
+            *contrast != 1.0,
+            watermark.is_some(),
+            subtitles.is_some(),
+            *preserve_last_frame,
+            max_colors.is_some(),
+            *auto_colors,
+            max_palette_bit_depth.is_some(),
+            *palette_stats_mode != PaletteStatsMode::default(),
+            !crop_keyframes.is_empty(),
+            *speed != 1.0,
+            frame_step.is_some(),
+            *boomerang,
+            output_frame_limit.is_some(),
+            *emit_frame_map,
+            *interpolate_frame_delays,
+            max_output_bytes.is_some(),
+            !additional_widths.is_empty(),
+            clip.is_some() && *seek_mode == SeekMode::Accurate,
+            video_stream_index.is_some(),
+            badge_overlay.is_some(),
+        ];
+        conflicts.extend(
+            CUSTOM_FILTER_COMPLEX_OVERRIDES
+                .iter()
+                .zip(overridden)
+                .filter(|(_, overridden)| *overridden)
+                .map(
+                    |(options, _)| SettingsConflict::CustomFilterComplexOverrides {
+                        option: options[1],
+                    },
+                ),
+        );
+    }
+    if *output_format != OutputFormat::Gif {
+        // NOTE: In the same order as `OUTPUT_FORMAT_OVERRIDES`.
+        let overridden = [
+            max_colors.is_some(),
+            *auto_colors,
+            max_palette_bit_depth.is_some(),
+            *palette_stats_mode != PaletteStatsMode::default(),
+            *global_palette_only,
+            *emit_frame_map,
+            *interpolate_frame_delays,
+            max_output_bytes.is_some(),
+            !additional_widths.is_empty(),
+        ];
+        conflicts.extend(
+            OUTPUT_FORMAT_OVERRIDES
+                .iter()
+                .zip(overridden)
+                .filter(|(_, overridden)| *overridden)
+                .map(|(options, _)| SettingsConflict::OutputFormatOverrides { option: options[1] }),
+        );
+    }
+    if input.is_read_once() {
+        // NOTE: In the same order as `READ_ONCE_INPUT_CONFLICTS`.
+        let rereads = [
+            *auto_colors,
+            clip.is_some_and(|clip| clip.needs_duration()),
+            max_output_bytes.is_some(),
+            hwaccel.is_some() && *hwaccel_fallback == HwAccelFallback::Software,
+            preview_width.is_some(),
+        ];
+        conflicts.extend(
+            READ_ONCE_INPUT_CONFLICTS
+                .iter()
+                .zip(rereads)
+                .filter(|(_, rereads)| *rereads)
+                .map(|(options, _)| SettingsConflict::ReadOnceInput { option: options[1] }),
+        );
+    }
+    if output_path.is_some() {
+        // NOTE: In the same order as `OUTPUT_PATH_CONFLICTS`.
+        let needs_bytes = [
+            output_file.is_some(),
+            *emit_frame_map,
+            *interpolate_frame_delays,
+            max_output_bytes.is_some(),
+        ];
+        conflicts.extend(
+            OUTPUT_PATH_CONFLICTS
+                .iter()
+                .zip(needs_bytes)
+                .filter(|(_, needs_bytes)| *needs_bytes)
+                .map(|(options, _)| SettingsConflict::OutputPathOverrides { option: options[1] }),
+        );
+    }
+    conflicts
+}
+
+/// The conflicts between the options of `settings` and the writer to which the animated GIF
+/// is streamed (see [`crate::Converter::convert_to_writer`]), in a stable order.
+pub(crate) fn find_writer_conflicts(settings: &Settings) -> Vec<SettingsConflict> {
+    // NOTE: In the same order as `WRITER_CONFLICTS`.
+    let needs_bytes = [
+        settings.output_path.is_some(),
+        settings.output_file.is_some(),
+        settings.emit_frame_map,
+        settings.interpolate_frame_delays,
+        settings.max_output_bytes.is_some(),
+    ];
+    WRITER_CONFLICTS
+        .iter()
+        .zip(needs_bytes)
+        .filter(|(_, needs_bytes)| *needs_bytes)
+        .map(|(options, _)| SettingsConflict::WriterOverrides { option: options[1] })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn settings() -> Settings {
+        Settings::with_standard_fps("./assets/big-buck-bunny-clip.mp4".into(), 200)
+    }
+
+    #[test]
+    fn test_conflicts_pairwise() {
+        let cases = [
+            (
+                settings().auto_colors(true).max_colors(64),
+                Some(SettingsConflict::AutoColorsOverrideMaxColors),
+            ),
+            (settings().auto_colors(true), None),
+            (settings().max_colors(64), None),
+            (
+                settings().max_palette_bit_depth(7).max_colors(200),
+                Some(SettingsConflict::MaxColorsCappedByBitDepth {
+                    max_colors: 200,
+                    cap: 128,
+                }),
+            ),
+            (settings().max_palette_bit_depth(7).max_colors(128), None),
+            (settings().max_palette_bit_depth(8).max_colors(256), None),
+            (
+                settings().fit_mode(FitMode::Crop),
+                Some(SettingsConflict::FitModeWithoutHeight),
+            ),
+            (settings().fit_mode(FitMode::Crop).gif_height(100), None),
+            (settings().gif_height(100), None),
+            (
+                settings().boomerang(true).output_frame_limit(48),
+                Some(SettingsConflict::BoomerangFrameLimit),
+            ),
+            (settings().boomerang(true), None),
+            (settings().output_frame_limit(48), None),
+            (
+                settings().seek_mode(SeekMode::Fast),
+                Some(SettingsConflict::SeekModeWithoutClip),
+            ),
+            (
+                settings()
+                    .seek_mode(SeekMode::Accurate)
+                    .clip(crate::ClipSelection::FromStart(
+                        std::time::Duration::from_secs(2),
+                    )),
+                None,
+            ),
+            (
+                settings().badge_overlay(crate::Text::new("rendering…")),
+                Some(SettingsConflict::BadgeWithoutPreview),
+            ),
+            (
+                settings()
+                    .badge_overlay(crate::Text::new("rendering…"))
+                    .preview_width(100),
+                None,
+            ),
+            (
+                settings()
+                    .global_palette_only(true)
+                    .palette_stats_mode(PaletteStatsMode::Single),
+                Some(SettingsConflict::PerFramePalettesGlobalPaletteOnly),
+            ),
+            (
+                settings()
+                    .global_palette_only(true)
+                    .palette_stats_mode(PaletteStatsMode::Diff),
+                None,
+            ),
+            (
+                settings().palette_stats_mode(PaletteStatsMode::Single),
+                None,
+            ),
+            (
+                Settings::with_standard_fps("/dev/stdin".into(), 200),
+                Some(SettingsConflict::StdinInput),
+            ),
+            // NOTE: Passed to FFmpeg as `./-` and `file:pipe:0`, i.e. as files.
+            (Settings::with_standard_fps("-".into(), 200), None),
+            (Settings::with_standard_fps("pipe:0".into(), 200), None),
+            (
+                settings()
+                    .max_output_bytes(1_000)
+                    .additional_widths(vec![400]),
+                Some(SettingsConflict::SizeTargetAdditionalWidths),
+            ),
+            (settings().additional_widths(vec![400]), None),
+            (settings(), None),
+        ];
+        for (i, (settings, expected)) in cases.into_iter().enumerate() {
+            let conflicts = settings.conflicts();
+            assert_eq!(conflicts, Vec::from_iter(expected), "case {}", i);
+            if let Some(conflict) = expected {
+                assert_eq!(
+                    settings.validate().err(),
+                    match conflict.severity() {
+                        ConflictSeverity::Soft => None,
+                        ConflictSeverity::Hard => Some(crate::SettingsError::Conflict(conflict)),
+                    },
+                    "case {}",
+                    i
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn test_custom_filter_complex_conflicts() {
+        let custom = || settings().custom_filter_complex("[0:v]null[s]; [s]split[a][b]");
+        assert!(custom().conflicts().is_empty());
+        let cases = [
+            custom().gif_height(100),
+            custom().fit_mode(FitMode::Crop),
+            custom().scale_algorithm(crate::ScaleAlgorithm::Lanczos),
+            custom().even_dimension_policy(EvenDimensionPolicy::Even),
+            custom().rotation(crate::Rotation::Cw90),
+            custom().hflip(true),
+            custom().vflip(true),
+            custom().saturation(0.0),
+            custom().brightness(0.2),
+            custom().contrast(1.5),
+            custom().watermark("logo.png".into(), crate::Position::TopLeft, 1.0),
+            custom().subtitles("captions.srt".into()),
+            custom().preserve_last_frame(true),
+            custom().max_colors(64),
+            custom().auto_colors(true),
+            custom().max_palette_bit_depth(6),
+            custom().palette_stats_mode(PaletteStatsMode::Diff),
+            custom().crop(0, 0, 10, 10),
+            custom().speed(2.0),
+            custom().frame_step(2),
+            custom().boomerang(true),
+            custom().output_frame_limit(10),
+            custom().emit_frame_map(true),
+            custom().interpolate_frame_delays(true),
+            custom().max_output_bytes(1_000),
+            custom().additional_widths(vec![400]),
+            custom()
+                .clip(crate::ClipSelection::FromStart(
+                    std::time::Duration::from_secs(2),
+                ))
+                .seek_mode(SeekMode::Accurate),
+            custom().video_stream_index(1),
+            custom()
+                .preview_width(100)
+                .badge_overlay(crate::Text::new("rendering…")),
+        ];
+        for (settings, options) in cases.into_iter().zip(CUSTOM_FILTER_COMPLEX_OVERRIDES) {
+            let conflict = SettingsConflict::CustomFilterComplexOverrides { option: options[1] };
+            // NOTE: A fit mode without a height is also ignored.
+            assert_eq!(
+                settings.conflicts().last(),
+                Some(&conflict),
+                "{}",
+                options[1]
+            );
+            assert_eq!(conflict.options(), options);
+            assert_eq!(
+                settings.validate(),
+                Err(crate::SettingsError::Conflict(conflict))
+            );
+        }
+    }
+
+    #[test]
+    fn test_output_format_conflicts() {
+        let webp = || {
+            settings().output_format(OutputFormat::WebP {
+                quality: 75,
+                lossless: false,
+            })
+        };
+        assert!(webp().conflicts().is_empty());
+        let cases = [
+            webp().max_colors(64),
+            webp().auto_colors(true),
+            webp().max_palette_bit_depth(6),
+            webp().palette_stats_mode(PaletteStatsMode::Diff),
+            webp().global_palette_only(true),
+            webp().emit_frame_map(true),
+            webp().interpolate_frame_delays(true),
+            webp().max_output_bytes(1_000),
+            webp().additional_widths(vec![400]),
+        ];
+        for (settings, options) in cases.into_iter().zip(OUTPUT_FORMAT_OVERRIDES) {
+            let conflict = SettingsConflict::OutputFormatOverrides { option: options[1] };
+            assert_eq!(settings.conflicts(), [conflict], "{}", options[1]);
+            assert_eq!(conflict.options(), options);
+            assert_eq!(
+                settings.validate(),
+                Err(crate::SettingsError::Conflict(conflict))
+            );
+        }
+        assert!(settings()
+            .output_format(OutputFormat::Apng)
+            .gif_height(100)
+            .boomerang(true)
+            .conflicts()
+            .is_empty());
+    }
+
+    #[test]
+    fn test_read_once_input_conflicts() {
+        let reader = || Settings::from_reader(std::io::empty(), 200);
+        let seconds = std::time::Duration::from_secs;
+        assert!(reader().conflicts().is_empty());
+        assert!(reader()
+            .clip(crate::ClipSelection::FromStart(seconds(2)))
+            .conflicts()
+            .is_empty());
+        let cases = [
+            reader().auto_colors(true),
+            reader().clip(crate::ClipSelection::FromEnd(seconds(2))),
+            reader().max_output_bytes(1_000),
+            // NOTE: The default fallback runs the job again.
+            reader().hwaccel(crate::HwAccel::Cuda),
+            reader().preview_width(100),
+        ];
+        for (settings, options) in cases.into_iter().zip(READ_ONCE_INPUT_CONFLICTS) {
+            let conflict = SettingsConflict::ReadOnceInput { option: options[1] };
+            assert_eq!(settings.conflicts(), [conflict], "{}", options[1]);
+            assert_eq!(conflict.options(), options);
+            assert_eq!(
+                settings.validate(),
+                Err(crate::SettingsError::Conflict(conflict))
+            );
+        }
+        assert!(reader()
+            .hwaccel(crate::HwAccel::Cuda)
+            .hwaccel_fallback(HwAccelFallback::Fail)
+            .conflicts()
+            .is_empty());
+        // NOTE: The bytes can be streamed again to each child process.
+        assert!(Settings::from_bytes(vec![0; 16], 200)
+            .auto_colors(true)
+            .conflicts()
+            .is_empty());
+    }
+
+    #[test]
+    fn test_output_path_conflicts() {
+        let file = || settings().output_path("out.gif");
+        assert!(file().conflicts().is_empty());
+        assert!(file().additional_widths(vec![400]).conflicts().is_empty());
+        let cases = [
+            file().output_file("out", crate::OutputNaming::new("{stem}.gif")),
+            file().emit_frame_map(true),
+            file().interpolate_frame_delays(true),
+            file().max_output_bytes(1_000),
+        ];
+        for (settings, options) in cases.into_iter().zip(OUTPUT_PATH_CONFLICTS) {
+            let conflict = SettingsConflict::OutputPathOverrides { option: options[1] };
+            assert_eq!(settings.conflicts(), [conflict], "{}", options[1]);
+            assert_eq!(conflict.options(), options);
+            assert_eq!(
+                settings.validate(),
+                Err(crate::SettingsError::Conflict(conflict))
+            );
+        }
+    }
+
+    #[test]
+    fn test_writer_conflicts() {
+        assert!(find_writer_conflicts(&settings()).is_empty());
+        assert!(find_writer_conflicts(&settings().additional_widths(vec![400])).is_empty());
+        let cases = [
+            settings().output_path("out.gif"),
+            settings().output_file("out", crate::OutputNaming::new("{stem}.gif")),
+            settings().emit_frame_map(true),
+            settings().interpolate_frame_delays(true),
+            settings().max_output_bytes(1_000),
+        ];
+        for (settings, options) in cases.into_iter().zip(WRITER_CONFLICTS) {
+            let conflict = SettingsConflict::WriterOverrides { option: options[1] };
+            assert_eq!(
+                find_writer_conflicts(&settings),
+                [conflict],
+                "{}",
+                options[1]
+            );
+            assert_eq!(conflict.options(), options);
+            assert_eq!(conflict.severity(), ConflictSeverity::Hard);
+            // NOTE: Not a conflict between the settings themselves.
+            assert!(settings.conflicts().is_empty());
+        }
+    }
+
+    #[test]
+    fn test_conflicts_combined() {
+        let settings = settings()
+            .auto_colors(true)
+            .max_colors(200)
+            .max_palette_bit_depth(6)
+            .fit_mode(FitMode::Stretch)
+            .boomerang(true)
+            .output_frame_limit(10);
+        assert_eq!(
+            settings.conflicts(),
+            [
+                SettingsConflict::AutoColorsOverrideMaxColors,
+                SettingsConflict::MaxColorsCappedByBitDepth {
+                    max_colors: 200,
+                    cap: 64
+                },
+                SettingsConflict::FitModeWithoutHeight,
+                SettingsConflict::BoomerangFrameLimit,
+            ]
+        );
+        assert_eq!(settings.validate(), Ok(()));
+    }
+
+    #[test]
+    fn test_conflict_metadata() {
+        let conflict = SettingsConflict::MaxColorsCappedByBitDepth {
+            max_colors: 200,
+            cap: 128,
+        };
+        assert_eq!(conflict.options(), ["max_palette_bit_depth", "max_colors"]);
+        assert_eq!(conflict.kind(), "max_colors_capped_by_bit_depth");
+        assert_eq!(
+            conflict.to_string(),
+            "max_palette_bit_depth caps max_colors (200) to 128 colors"
+        );
+        assert_eq!(
+            SettingsConflict::StdinInput.severity(),
+            ConflictSeverity::Hard
+        );
+    }
+}
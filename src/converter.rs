@@ -1,30 +1,98 @@
 use std::{cell::RefCell, time::Duration};
 
-use crate::time_parsing::{progress_from_durations, try_extract_duration, try_extract_frame_time};
+use crate::argv;
+use crate::auxiliary::{
+    run_auxiliary, run_auxiliary_reading, run_auxiliary_streaming, AuxiliaryError,
+};
+use crate::cache::{self, CacheConfig};
+use crate::cleanup_guard::CleanupGuard;
+use crate::codec_selection::{self, StreamMappingParser};
+use crate::conflicts;
+use crate::deadline::{self, Deadline};
+use crate::duration_source::{DurationAdjustments, DurationSource, DurationTracker};
+use crate::frame_map::FrameMap;
+use crate::gif_info::{parse_gif_info, GifData, GifInfo};
+use crate::input::PumpError;
+use crate::input_format;
+use crate::internal_events::{ExitKind, InternalEvent, ThreadKind};
+use crate::job_guard::JobGuard;
+use crate::job_metrics;
+use crate::job_tag::{self, job_log, JobTag};
+use crate::memory_budget::{BudgetMeasure, MemoryBudget, OutputBuffer};
+use crate::outbox::Outbox;
+use crate::output_naming::{OutputFile, OutputTarget};
+use crate::output_path::{self, OutputPath};
+use crate::output_stream::OutputStreamParser;
+use crate::output_writer::OutputWriter;
+use crate::palette;
+use crate::progress::{ProgressInterpolator, ProgressRemapper};
+use crate::provenance::{ParsedEvent, ProvenanceLog};
+use crate::resource_usage;
+use crate::size_target;
+use crate::stats_anomaly::AnomalyDetector;
+use crate::stderr_lines::{self, is_banner, LineAssembler, StderrLine};
+use crate::strict_parsing::{is_stats_line, ParseChecker};
+use crate::thumbnails;
+use crate::time_parsing::{
+    has_negative_frame_time, progress_from_durations, try_extract_duration,
+    try_extract_frame_count, try_extract_frame_time,
+};
+use crate::variants::VariantDir;
 
-use super::{Command, Error, Message, Settings};
+use super::{
+    BoundedMessageReceiver, Command, Error, FfmpegLocation, HwAccel, HwAccelFallback,
+    InputFormatHints, InputSource, JobId, Message, OutputFormat, ProbeError, Settings,
+    SettingsError, StripSettings, Warning,
+};
 
 const STDIN_THREAD_SLEEP_DURATION_MS: u64 = 50;
+/// The interval at which the main thread checks for the messages of an attempt (see
+/// [`Settings::max_output_bytes`]) and for the application's commands.
+const ATTEMPT_SLEEP_DURATION_MS: u64 = 10;
+/// How long the main thread waits for the STDIN thread to exit, once the other threads have
+/// exited, before giving up on it (see [`Warning::StdinThreadTimeout`]).
+const STDIN_THREAD_GRACE_PERIOD_MS: u64 = 2_000;
+const SMOOTHER_THREAD_SLEEP_DURATION_MS: u64 = 50;
+/// The interval at which the CHILD thread checks whether the child process has exited.
+const CHILD_THREAD_SLEEP_DURATION_MS: u64 = 10;
+/// The minimum interval between two [`Message::OutputBytes`] messages.
+const OUTPUT_BYTES_INTERVAL_MS: u64 = 100;
 
+#[allow(clippy::redundant_static_lifetimes)]
 const LOG_TARGET_MAIN: &'static str = "ffmpeg_gif_maker::converter::main_thread";
+#[allow(clippy::redundant_static_lifetimes)]
 const LOG_TARGET_STDIN: &'static str = "ffmpeg_gif_maker::converter::stdin_thread";
+#[allow(clippy::redundant_static_lifetimes)]
 const LOG_TARGET_STDOUT: &'static str = "ffmpeg_gif_maker::converter::stdout_thread";
+#[allow(clippy::redundant_static_lifetimes)]
 const LOG_TARGET_STDERR: &'static str = "ffmpeg_gif_maker::converter::stderr_thread";
+#[allow(clippy::redundant_static_lifetimes)]
 const LOG_TARGET_CHILD: &'static str = "ffmpeg_gif_maker::converter::child_thread";
+const LOG_TARGET_SMOOTHER: &str = "ffmpeg_gif_maker::converter::smoother_thread";
+const LOG_TARGET_WRITER: &str = "ffmpeg_gif_maker::converter::writer_thread";
 
-#[cfg(not(feature = "tokio"))]
+#[cfg(not(any(feature = "tokio", feature = "async-channel")))]
 /// The sender's end of an mpsc [`Command`] channel.
 pub type CommandSender = std::sync::mpsc::Sender<Command>;
-#[cfg(not(feature = "tokio"))]
+#[cfg(not(any(feature = "tokio", feature = "async-channel")))]
 /// The reciever's end of an mpsc [`Command`] channel.
 pub type CommandReceiver = std::sync::mpsc::Receiver<Command>;
-#[cfg(not(feature = "tokio"))]
+#[cfg(not(any(feature = "tokio", feature = "async-channel")))]
 /// The sender's end of an mpsc [`Message`] channel.
 pub type MessageSender = std::sync::mpsc::Sender<Message>;
-#[cfg(not(feature = "tokio"))]
+#[cfg(not(any(feature = "tokio", feature = "async-channel")))]
 /// The reciever's end of an mpsc [`Message`] channel.
 pub type MessageReceiver = std::sync::mpsc::Receiver<Message>;
 
+#[cfg(not(any(feature = "tokio", feature = "async-channel")))]
+/// The sender's end of the bounded (and lossy) progress channel created by
+/// [`Converter::new_with_split_channels`].
+pub type ProgressSender = std::sync::mpsc::SyncSender<Message>;
+#[cfg(not(any(feature = "tokio", feature = "async-channel")))]
+/// The receiver's end of the bounded (and lossy) progress channel created by
+/// [`Converter::new_with_split_channels`].
+pub type ProgressReceiver = std::sync::mpsc::Receiver<Message>;
+
 #[cfg(feature = "tokio")]
 /// The sender's end of an mpsc [`Command`] channel.
 pub type CommandSender = tokio::sync::mpsc::UnboundedSender<Command>;
@@ -37,12 +105,40 @@ pub type MessageSender = tokio::sync::mpsc::UnboundedSender<Message>;
 #[cfg(feature = "tokio")]
 /// The reciever's end of an mpsc [`Message`] channel.
 pub type MessageReceiver = tokio::sync::mpsc::UnboundedReceiver<Message>;
-
+#[cfg(feature = "tokio")]
+/// The sender's end of the bounded (and lossy) progress channel created by
+/// [`Converter::new_with_split_channels`].
+pub type ProgressSender = tokio::sync::mpsc::Sender<Message>;
+#[cfg(feature = "tokio")]
+/// The receiver's end of the bounded (and lossy) progress channel created by
+/// [`Converter::new_with_split_channels`].
+pub type ProgressReceiver = tokio::sync::mpsc::Receiver<Message>;
+#[cfg(feature = "async-channel")]
+/// The sender's end of an mpsc [`Command`] channel.
+pub type CommandSender = async_channel::Sender<Command>;
+#[cfg(feature = "async-channel")]
+/// The reciever's end of an mpsc [`Command`] channel.
+pub type CommandReceiver = async_channel::Receiver<Command>;
+#[cfg(feature = "async-channel")]
+/// The sender's end of an mpsc [`Message`] channel.
+pub type MessageSender = async_channel::Sender<Message>;
+#[cfg(feature = "async-channel")]
+/// The reciever's end of an mpsc [`Message`] channel.
+pub type MessageReceiver = async_channel::Receiver<Message>;
+#[cfg(feature = "async-channel")]
+/// The sender's end of the bounded (and lossy) progress channel created by
+/// [`Converter::new_with_split_channels`].
+pub type ProgressSender = async_channel::Sender<Message>;
+#[cfg(feature = "async-channel")]
+/// The receiver's end of the bounded (and lossy) progress channel created by
+/// [`Converter::new_with_split_channels`].
+pub type ProgressReceiver = async_channel::Receiver<Message>;
 /// A structure containing the information required to
 /// perform the conversion job.
 pub struct Converter {
-    /// The sender's end of the [`Message`] channel.
-    tx: MessageSender,
+    /// The sender's end of the [`Message`] channel, wrapped inside
+    /// an [`Outbox`] which gathers the data for the job's [`crate::Summary`].
+    tx: Outbox,
     /// The receiver's end of the [`Command`] channel, wrapped inside
     /// an [`Option`] and then again inside a [`std::cell::RefCell`].
     rx: RefCell<Option<CommandReceiver>>,
@@ -56,25 +152,254 @@ pub struct Converter {
     /// NOTE: Just like `job_cancelled`, this wouldn't have to be stored in the structure,
     /// but it's OK for now (besides, better be consistent).
     job_ended: std::sync::Arc<std::sync::Mutex<bool>>,
+    /// Whether the child process should be terminated, which is requested when the
+    /// application has dropped its end of the [`Message`] channel (see [`JobSender`]).
+    kill_requested: std::sync::Arc<std::sync::atomic::AtomicBool>,
+    /// Whether the [`JobGuard`] returned by [`Converter::spawn`] was dropped, which cancels
+    /// the job just like a [`Command::Cancel`] command (and is cleared once handled).
+    guard_dropped: std::sync::Arc<std::sync::atomic::AtomicBool>,
     /// A unique identifier for the instance, used by internal logging logic
     /// to be able to output meaningful logs.
-    id: uuid::Uuid,
+    id: JobId,
+    /// The instance's sequential number (see [`Converter::job_number`]).
+    number: u64,
+    /// An optional label provided by the application (see [`Converter::job_label`]).
+    label: Option<String>,
+    /// The conversion result cache, if any (see [`Converter::cache`]).
+    cache: Option<CacheConfig>,
+    /// The identifiers included in the instance's log lines, i.e. the sequential
+    /// number and the label (if any) as a prefix, and the identifier as a key-value.
+    tag: JobTag,
+    /// The memory budget of the job being run, if any (see [`Settings::memory_budget`]).
+    memory_budget: Option<std::sync::Arc<MemoryBudget>>,
+    /// Whether the job being run sends [`Message::Internal`] (see [`Settings::emit_internal_events`]),
+    /// which is turned off once a thread is abandoned, so that nothing is sent after [`Message::Done`].
+    internal_events: std::sync::Arc<std::sync::atomic::AtomicBool>,
+    /// The writer to which the animated GIF is streamed, if any (see [`Converter::convert_to_writer`]).
+    output_writer: Option<OutputWriter>,
+    #[cfg(test)]
+    /// How long the STDIN thread stalls before exiting, to simulate a stuck thread.
+    stdin_thread_stall: Option<Duration>,
+    #[cfg(test)]
+    /// Where the main thread panics, to simulate a bug (see [`CleanupGuard`]).
+    panic_at: Option<PanicPoint>,
+    #[cfg(test)]
+    /// What the job's [`CleanupGuard`] cleaned up.
+    cleanup_report: std::sync::Arc<std::sync::Mutex<Option<crate::cleanup_guard::CleanupReport>>>,
+}
+
+#[cfg(test)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+/// Where the main thread of [`Converter::convert`] panics (see `Converter::panic_at`).
+enum PanicPoint {
+    /// Right after the child process was spawned, before any thread.
+    AfterSpawn,
+    /// Once all the threads were spawned, before they are joined.
+    AfterThreads,
 }
 
 impl Converter {
     /// A unique identifier for the instance, used by internal logging logic
-    /// to be able to output meaningful logs.
-    pub fn id(&self) -> uuid::Uuid {
-        self.id
+    /// to be able to output meaningful logs. By default, a random UUID is generated,
+    /// which can be replaced using [`Converter::with_id`].
+    pub fn id(&self) -> JobId {
+        self.id.clone()
+    }
+
+    /// A setter method that allows replacing the generated [`Converter::id`] by the
+    /// application's own identifier for the job (e.g. an integer from a database), which is
+    /// then used in every log line, in the job's [`crate::Summary`], and by [`crate::Batch`].
+    ///
+    /// NOTE: The application is responsible for keeping the identifiers unique.
+    pub fn with_id(self, id: impl Into<JobId>) -> Self {
+        let id = id.into();
+        let tag = JobTag::new(id.clone(), self.number, self.label.clone());
+        let tx = self.tx.with_job_id(id.clone());
+        Self {
+            id,
+            tag,
+            tx,
+            ..self
+        }
+    }
+
+    /// A short sequential number (starting at 1, for the whole process) identifying
+    /// the instance, which prefixes every log line (e.g. `#12 Job ended ...`), so that
+    /// the lines of concurrent jobs are easy to tell apart.
+    ///
+    /// NOTE: Each log line also carries the [`Converter::id`], the number and the label
+    /// (if any) as structured key-values (named `job_id`, `job` and `job_label`, which
+    /// are available to loggers that support the `log` crate's `kv` feature).
+    pub fn job_number(&self) -> u64 {
+        self.number
+    }
+
+    /// A setter method that allows attaching a label to the job (e.g. the application's
+    /// own request identifier), which is included in every log line (right after the
+    /// [`Converter::job_number`]) and in the job's [`crate::Summary`].
+    pub fn job_label(self, label: impl Into<String>) -> Self {
+        let label = label.into();
+        let tag = JobTag::new(self.id.clone(), self.number, Some(label.clone()));
+        Self {
+            label: Some(label),
+            tag,
+            ..self
+        }
+    }
+
+    /// A setter method that allows looking up the animated GIF in (and, once generated,
+    /// storing it into) an on-disk cache, keyed by the input's content and the conversion
+    /// plan. On a hit, no FFmpeg child process is spawned: the job sends the cached
+    /// animated GIF as [`Message::Success`], followed by the usual [`Message::Summary`]
+    /// and [`Message::Done`] messages.
+    ///
+    /// NOTE: The input's content is only sampled (i.e. its size, its modification time,
+    /// and its first and last few bytes), so a file edited in place without any of these
+    /// changing would be considered the same input.
+    pub fn cache(self, cache: CacheConfig) -> Self {
+        Self {
+            cache: Some(cache),
+            ..self
+        }
+    }
+
+    /// The label provided using [`Converter::job_label`], if any.
+    pub fn label(&self) -> Option<&str> {
+        self.label.as_deref()
+    }
+
+    /// The identifiers included in the instance's log lines.
+    fn tag(&self) -> JobTag {
+        self.tag.clone()
     }
 
     /// A factory method that takes care of creating the channels to send [`Message`]'s
     /// and [`Command`]'s between the [`Converter`] and the application. The method returns
     /// a tuple containing the [`Converter`], the [`CommandSender`], and the [`MessageReceiver`],
     pub fn new_with_channels() -> (Self, CommandSender, MessageReceiver) {
-        #[cfg(not(feature = "tokio"))]
+        let (command_tx, command_rx, message_tx, message_rx) = Self::create_channels();
+        let out = (
+            Self::new(Outbox::new(message_tx), command_rx),
+            command_tx,
+            message_rx,
+        );
+        job_log!(
+            info,
+            LOG_TARGET_MAIN,
+            out.0.tag(),
+            "Instance created (id: {})",
+            out.0.id()
+        );
+        out
+    }
+
+    #[cfg(feature = "v2-messages")]
+    /// Same as [`Converter::new_with_channels`], except that the messages are received in
+    /// their new shape (see [`crate::v2::Message`]), e.g. tagged with the job's identifier,
+    /// instead of the legacy [`Message`]'s (only available with the `v2-messages` feature flag).
+    pub fn new_with_v2_channels() -> (Self, CommandSender, crate::v2::MessageReceiver) {
+        let (command_tx, command_rx, _, _) = Self::create_channels();
+        #[cfg(not(any(feature = "tokio", feature = "async-channel")))]
+        let (message_tx, message_rx) = std::sync::mpsc::channel();
+        #[cfg(feature = "tokio")]
+        let (message_tx, message_rx) = tokio::sync::mpsc::unbounded_channel();
+        #[cfg(feature = "async-channel")]
+        let (message_tx, message_rx) = async_channel::unbounded();
+        let out = (
+            Self::new(Outbox::v2(message_tx), command_rx),
+            command_tx,
+            message_rx,
+        );
+        job_log!(
+            info,
+            LOG_TARGET_MAIN,
+            out.0.tag(),
+            "Instance created (id: {}, with v2 messages)",
+            out.0.id()
+        );
+        out
+    }
+
+    /// The capacity of the progress channel created by [`Converter::new_with_split_channels`].
+    pub const PROGRESS_CHANNEL_CAPACITY: usize = 64;
+
+    /// Same as [`Converter::new_with_channels`], except that the messages that only
+    /// report progress (i.e. those for which [`Message::is_progress`] returns `true`)
+    /// are sent down a separate channel, returned as a [`ProgressReceiver`] (in
+    /// second to last position). The [`MessageReceiver`] then only receives the
+    /// other messages (e.g. [`Message::VideoDuration`], [`Message::Success`],
+    /// [`Message::Error`] and [`Message::Done`]), in the same order as usual.
+    ///
+    /// NOTE: The progress channel is bounded (see [`Converter::PROGRESS_CHANNEL_CAPACITY`])
+    /// and lossy: progress messages are simply dropped when it is full (or when its
+    /// receiver has been dropped), so that a slow consumer never delays the job.
+    pub fn new_with_split_channels() -> (Self, CommandSender, ProgressReceiver, MessageReceiver) {
+        let (command_tx, command_rx, message_tx, message_rx) = Self::create_channels();
+        #[cfg(not(any(feature = "tokio", feature = "async-channel")))]
+        let (progress_tx, progress_rx): (ProgressSender, ProgressReceiver) =
+            std::sync::mpsc::sync_channel(Self::PROGRESS_CHANNEL_CAPACITY);
+        #[cfg(feature = "tokio")]
+        let (progress_tx, progress_rx): (ProgressSender, ProgressReceiver) =
+            tokio::sync::mpsc::channel(Self::PROGRESS_CHANNEL_CAPACITY);
+        #[cfg(feature = "async-channel")]
+        let (progress_tx, progress_rx): (ProgressSender, ProgressReceiver) =
+            async_channel::bounded(Self::PROGRESS_CHANNEL_CAPACITY);
+        let out = (
+            Self::new(Outbox::with_progress(message_tx, progress_tx), command_rx),
+            command_tx,
+            progress_rx,
+            message_rx,
+        );
+        job_log!(
+            info,
+            LOG_TARGET_MAIN,
+            out.0.tag(),
+            "Instance created (id: {}, with split channels)",
+            out.0.id()
+        );
+        out
+    }
+
+    /// Same as [`Converter::new_with_channels`], except that all the messages are
+    /// sent down a single bounded channel of the given `capacity` (at least 1), returned
+    /// as a [`BoundedMessageReceiver`], which bounds the memory used by the messages
+    /// that the application hasn't received yet.
+    ///
+    /// NOTE: A full channel never delays the job (nor its cancellation): the messages
+    /// that only report progress (see [`Message::is_progress`]) are simply dropped when
+    /// the channel is full, while the other messages (e.g. [`Message::Error`] and
+    /// [`Message::Done`]), of which each job only sends a handful, take the slots reserved
+    /// for them, and are delivered in order.
+    pub fn new_with_bounded_channel(
+        capacity: usize,
+    ) -> (Self, CommandSender, BoundedMessageReceiver) {
+        let (command_tx, command_rx, _, _) = Self::create_channels();
+        let (message_tx, message_rx) = crate::bounded_channel::channel(capacity);
+        let out = (
+            Self::new(Outbox::bounded(message_tx), command_rx),
+            command_tx,
+            message_rx,
+        );
+        job_log!(
+            info,
+            LOG_TARGET_MAIN,
+            out.0.tag(),
+            "Instance created (id: {}, with a bounded channel of capacity {})",
+            out.0.id(),
+            capacity
+        );
+        out
+    }
+
+    fn create_channels() -> (
+        CommandSender,
+        CommandReceiver,
+        MessageSender,
+        MessageReceiver,
+    ) {
+        #[cfg(not(any(feature = "tokio", feature = "async-channel")))]
         let (command_tx, command_rx): (CommandSender, CommandReceiver) = std::sync::mpsc::channel();
-        #[cfg(not(feature = "tokio"))]
+        #[cfg(not(any(feature = "tokio", feature = "async-channel")))]
         let (message_tx, message_rx): (MessageSender, MessageReceiver) = std::sync::mpsc::channel();
 
         #[cfg(feature = "tokio")]
@@ -83,529 +408,7412 @@ impl Converter {
         #[cfg(feature = "tokio")]
         let (message_tx, message_rx): (MessageSender, MessageReceiver) =
             tokio::sync::mpsc::unbounded_channel();
-        let out = (
-            Self {
-                tx: message_tx,
-                rx: RefCell::new(Some(command_rx)),
-                job_cancelled: std::sync::Arc::new(std::sync::Mutex::new(false)),
-                job_ended: std::sync::Arc::new(std::sync::Mutex::new(false)),
-                id: uuid::Uuid::new_v4(),
-            },
-            command_tx,
-            message_rx,
+
+        #[cfg(feature = "async-channel")]
+        let (command_tx, command_rx): (CommandSender, CommandReceiver) = async_channel::unbounded();
+        #[cfg(feature = "async-channel")]
+        let (message_tx, message_rx): (MessageSender, MessageReceiver) = async_channel::unbounded();
+        (command_tx, command_rx, message_tx, message_rx)
+    }
+
+    fn new(tx: Outbox, rx: CommandReceiver) -> Self {
+        let id = JobId::generate();
+        let number = job_tag::next_job_number();
+        Self {
+            tx: tx.with_job_id(id.clone()),
+            rx: RefCell::new(Some(rx)),
+            job_cancelled: std::sync::Arc::new(std::sync::Mutex::new(false)),
+            job_ended: std::sync::Arc::new(std::sync::Mutex::new(false)),
+            kill_requested: std::sync::Arc::new(std::sync::atomic::AtomicBool::new(false)),
+            guard_dropped: std::sync::Arc::new(std::sync::atomic::AtomicBool::new(false)),
+            number,
+            label: None,
+            cache: None,
+            tag: JobTag::new(id.clone(), number, None),
+            id,
+            memory_budget: None,
+            internal_events: Default::default(),
+            output_writer: None,
+            #[cfg(test)]
+            stdin_thread_stall: None,
+            #[cfg(test)]
+            panic_at: None,
+            #[cfg(test)]
+            cleanup_report: Default::default(),
+        }
+    }
+
+    /// The handle through which the thread whose log lines use `target` sends its messages.
+    fn sender(&self, target: &'static str) -> JobSender {
+        JobSender {
+            tx: self.tx.clone(),
+            tag: self.tag(),
+            target,
+            job_cancelled: std::sync::Arc::clone(&self.job_cancelled),
+            job_ended: std::sync::Arc::clone(&self.job_ended),
+            kill_requested: std::sync::Arc::clone(&self.kill_requested),
+            memory_budget: self.memory_budget.clone(),
+            internal_events: std::sync::Arc::clone(&self.internal_events),
+        }
+    }
+
+    /// Sends `message` down the [`Message`] channel from the main thread, returning
+    /// `false` if the job is being shut down (see [`JobSender::send_or_shutdown`]).
+    fn send_or_shutdown(&self, message: Message) -> bool {
+        self.sender(LOG_TARGET_MAIN).send_or_shutdown(message)
+    }
+
+    /// Non-blockingly checks whether a [`Command::Cancel`] command was received
+    /// from the application, which is used before the STDIN thread (which otherwise
+    /// takes care of listening for commands) gets spawned.
+    fn cancel_requested(&self) -> bool {
+        if self
+            .guard_dropped
+            .swap(false, std::sync::atomic::Ordering::SeqCst)
+        {
+            job_log!(
+                info,
+                LOG_TARGET_MAIN,
+                self.tag(),
+                "Job guard dropped, so cancelling job..."
+            );
+            return true;
+        }
+        let mut rx = self.rx.borrow_mut();
+        let Some(rx) = rx.as_mut() else {
+            return false;
+        };
+        matches!(rx.try_recv(), Ok(Command::Cancel))
+    }
+
+    /// Returns [`Error::DeadlineExceeded`] if the job's `deadline` (see [`Settings::deadline`])
+    /// has already passed, in which case no child process should be spawned.
+    fn check_deadline(&self, deadline: Option<std::time::Instant>) -> Result<(), Error> {
+        let Some(overshoot) = deadline.and_then(deadline::overshoot) else {
+            return Ok(());
+        };
+        job_log!(
+            error,
+            LOG_TARGET_MAIN,
+            self.tag(),
+            "Deadline passed {:?} ago, so not spawning child process.",
+            overshoot
         );
-        log::info!(target: LOG_TARGET_MAIN, "{} Instance created", out.0.id());
-        out
+        Err(Error::DeadlineExceeded { overshoot })
+    }
+
+    /// Runs the palette analysis pass used by [`Settings::auto_colors`], returning the
+    /// selected number of colors (or `None` if the analysis failed), or [`Error::Cancelled`]
+    /// if the job was cancelled in the meantime, or [`Error::DeadlineExceeded`] if the job's
+    /// deadline passed in the meantime.
+    fn select_color_count(
+        &self,
+        binary_path: &std::path::Path,
+        settings: &Settings,
+    ) -> Result<Option<u16>, Error> {
+        job_log!(
+            info,
+            LOG_TARGET_MAIN,
+            self.tag(),
+            "Running palette analysis pass..."
+        );
+        let mut command = argv::ffmpeg_command(&settings.command_wrapper, binary_path);
+        command.args(settings.palette_analysis_args());
+        let timeout = deadline::earliest(settings.auxiliary_timeout, settings.deadline);
+        match run_auxiliary_reading(command, &settings.input, timeout, || {
+            self.cancel_requested()
+        })
+        .and_then(|output| output.into_stdout())
+        {
+            Ok(data) => {
+                let stats = palette::ColorStats::from_rgb24(&data);
+                let n = palette::choose_color_count(&stats);
+                job_log!(
+                    info,
+                    LOG_TARGET_MAIN,
+                    self.tag(),
+                    "Palette analysis pass selected {} colors ({} pixels sampled).",
+                    n,
+                    stats.total
+                );
+                Ok(Some(n))
+            }
+            Err(AuxiliaryError::Cancelled) => {
+                job_log!(
+                    info,
+                    LOG_TARGET_MAIN,
+                    self.tag(),
+                    "Job cancelled during palette analysis pass."
+                );
+                Err(Error::Cancelled {
+                    progress_at_cancel: None,
+                    bytes_discarded: 0,
+                })
+            }
+            Err(AuxiliaryError::TimedOut(d)) => {
+                match settings.deadline.and_then(deadline::overshoot) {
+                    Some(overshoot) => {
+                        job_log!(
+                            error,
+                            LOG_TARGET_MAIN,
+                            self.tag(),
+                            "Deadline passed {:?} ago during palette analysis pass.",
+                            overshoot
+                        );
+                        Err(Error::DeadlineExceeded { overshoot })
+                    }
+                    None => {
+                        job_log!(
+                        warn,
+                        LOG_TARGET_MAIN,
+                        self.tag(),
+                        "Palette analysis pass did not finish within {:?}, so using default palette size.",
+                        d
+                    );
+                        Ok(None)
+                    }
+                }
+            }
+            Err(e) => {
+                job_log!(
+                    warn,
+                    LOG_TARGET_MAIN,
+                    self.tag(),
+                    "Palette analysis pass failed, so using default palette size: {}",
+                    e
+                );
+                Ok(None)
+            }
+        }
     }
 
-    pub fn convert(self, settings: Settings) {
-        log::debug!(target: LOG_TARGET_MAIN, "{} Trying to spawn FFmpeg child process...", self.id());
-        let binary_path = match &settings.ffmpeg_path {
-            Some(path) => {
-                log::info!(target: LOG_TARGET_MAIN, "{} FFmpeg binary path provided: {}", self.id(), path);
-                path.to_string()
+    #[cfg(feature = "tokio")]
+    /// Makes sure that the job is not run from a thread that is driving asynchronous
+    /// tasks, emitting a warning if it is, or an error (in which case `false` is returned)
+    /// if `strict` is `true`.
+    fn check_async_context(&self, strict: bool) -> bool {
+        if crate::async_context::is_blocking_in_async_context() {
+            job_log!(warn, LOG_TARGET_MAIN, self.tag(), "Job started from a thread that is driving asynchronous tasks, which will be blocked until the job is done. Please call it from a blocking thread instead (e.g. using 'tokio::task::spawn_blocking').");
+            if strict {
+                job_log!(
+                    error,
+                    LOG_TARGET_MAIN,
+                    self.tag(),
+                    "Refusing to run because strict async context checking is enabled."
+                );
+                self.send_or_shutdown(Message::Error(Error::BlockingInAsyncContext));
+                return false;
             }
+            self.send_or_shutdown(Message::Warning(Warning::BlockingInAsyncContext));
+        }
+        true
+    }
+
+    /// Returns the FFmpeg binary path to use, given the location provided by the application
+    /// (if any), or [`Error::FfmpegLocation`] if the location could not be resolved.
+    fn binary_path(
+        &self,
+        ffmpeg_location: &Option<FfmpegLocation>,
+    ) -> Result<std::path::PathBuf, Error> {
+        match ffmpeg_location {
+            Some(location) => match location.resolve() {
+                Ok(path) => {
+                    job_log!(
+                        info,
+                        LOG_TARGET_MAIN,
+                        self.tag(),
+                        "FFmpeg binary path provided: {} (resolved from {:?})",
+                        path.display(),
+                        location
+                    );
+                    Ok(path)
+                }
+                Err(e) => {
+                    job_log!(
+                        error,
+                        LOG_TARGET_MAIN,
+                        self.tag(),
+                        "Failed to resolve FFmpeg binary location {:?}: {}",
+                        location,
+                        e
+                    );
+                    Err(Error::FfmpegLocation(e))
+                }
+            },
             None => {
-                log::info!(target: LOG_TARGET_MAIN, "{} No FFmpeg binary path provided, so expecting to find 'ffmpeg' on system path.", self.id());
-                "ffmpeg".to_string()
-            }
-        };
-        let mut child = match std::process::Command::new(binary_path)
-            .arg("-stats")
-            .arg("-i")
-            .arg(&settings.video_path)
-            .arg("-filter_complex")
-            .arg(settings.generate_filter_complex())
-            .arg("-f")
-            .arg("gif")
-            .arg("-")
-            .stdin(std::process::Stdio::piped())
-            .stdout(std::process::Stdio::piped())
-            .stderr(std::process::Stdio::piped())
-            .spawn()
-        {
-            Ok(c) => {
-                log::debug!(target: LOG_TARGET_MAIN, "{} FFmpeg child process successfully spawned.", self.id());
-                c
+                job_log!(
+                    info,
+                    LOG_TARGET_MAIN,
+                    self.tag(),
+                    "No FFmpeg binary path provided, so expecting to find 'ffmpeg' on system path."
+                );
+                Ok("ffmpeg".into())
+            }
+        }
+    }
+
+    /// Returns [`Error::MissingCapability`] if the FFmpeg binary at `binary_path` lacks a
+    /// filter (or an encoder) required by the `settings` (see [`Settings::check_capabilities`]),
+    /// its capabilities being listed by short-lived FFmpeg child processes unless they were
+    /// listed before. If they cannot be listed, the job runs anyway.
+    fn check_capabilities(
+        &self,
+        binary_path: &std::path::Path,
+        settings: &Settings,
+    ) -> Result<(), Error> {
+        let timeout = deadline::earliest(settings.auxiliary_timeout, settings.deadline);
+        let capabilities = crate::capabilities::cached(binary_path, |args| {
+            job_log!(
+                debug,
+                LOG_TARGET_MAIN,
+                self.tag(),
+                "Listing FFmpeg capabilities ({:?})...",
+                args
+            );
+            let mut command = argv::ffmpeg_command(&settings.command_wrapper, binary_path);
+            command.args(args);
+            run_auxiliary(command, timeout, || false)
+                .and_then(|output| output.into_stdout())
+                .map(|stdout| String::from_utf8_lossy(&stdout).into_owned())
+        });
+        let capabilities = match capabilities {
+            Ok(Some(capabilities)) => capabilities,
+            Ok(None) => {
+                job_log!(
+                    warn,
+                    LOG_TARGET_MAIN,
+                    self.tag(),
+                    "FFmpeg capabilities not recognized, so not checking them."
+                );
+                return Ok(());
             }
             Err(e) => {
-                log::error!(target: LOG_TARGET_MAIN, "{} Failed to spawn child process: {:?}", self.id(), e);
-                panic!();
+                job_log!(
+                    warn,
+                    LOG_TARGET_MAIN,
+                    self.tag(),
+                    "Unable to list FFmpeg capabilities ({}), so not checking them.",
+                    e
+                );
+                return Ok(());
             }
         };
+        let Some(requirement) = capabilities.missing(settings) else {
+            return Ok(());
+        };
+        job_log!(
+            error,
+            LOG_TARGET_MAIN,
+            self.tag(),
+            "FFmpeg binary lacks {}: {}",
+            requirement.capability,
+            requirement.hint
+        );
+        Err(Error::MissingCapability {
+            needed: requirement.capability,
+            hint: requirement.hint.into(),
+        })
+    }
 
-        let mut stdin = match child.stdin.take() {
-            Some(io) => io,
-            None => {
-                log::error!(target: LOG_TARGET_MAIN, "{} Failed to take STDIN from child process.", self.id());
-                panic!();
+    /// Runs a short-lived FFmpeg child process (through the `command_wrapper`, if any) that
+    /// only reads the input file's header (read using the `input_format_hints`, if any), to find the video's duration
+    /// (or `None` if it could not be found), or [`Error::Cancelled`] if the job was
+    /// cancelled in the meantime, or [`Error::Probe`] if the child process did not
+    /// finish within `timeout` (if any), or [`Error::DeadlineExceeded`] if the job's
+    /// `deadline` (if any) passed first.
+    fn probe_duration(
+        &self,
+        binary_path: &std::path::Path,
+        command_wrapper: &[std::ffi::OsString],
+        input: &InputSource,
+        input_format_hints: Option<&InputFormatHints>,
+        timeout: Option<Duration>,
+        deadline: Option<std::time::Instant>,
+    ) -> Result<Option<Duration>, Error> {
+        job_log!(
+            info,
+            LOG_TARGET_MAIN,
+            self.tag(),
+            "Probing video duration..."
+        );
+        let mut command = argv::ffmpeg_command(command_wrapper, binary_path);
+        command.args(thumbnails::probe_args(input_format::input_args(
+            input_format_hints,
+            input,
+        )));
+        // NOTE: FFmpeg exits with an error when no output file is specified, so
+        // the exit code is ignored here: only the `stderr` output matters.
+        let timeout = deadline::earliest(timeout, deadline);
+        match run_auxiliary_reading(command, input, timeout, || self.cancel_requested()) {
+            Ok(output) => {
+                let tag = self.tag().to_string();
+                let duration =
+                    try_extract_duration(&stderr_lines::decode(&output.stderr), Some(&tag));
+                job_log!(
+                    info,
+                    LOG_TARGET_MAIN,
+                    self.tag(),
+                    "Video duration probed: {:?}",
+                    duration
+                );
+                Ok(duration)
+            }
+            Err(AuxiliaryError::Cancelled) => {
+                job_log!(
+                    info,
+                    LOG_TARGET_MAIN,
+                    self.tag(),
+                    "Job cancelled while probing video duration."
+                );
+                Err(Error::Cancelled {
+                    progress_at_cancel: None,
+                    bytes_discarded: 0,
+                })
+            }
+            Err(AuxiliaryError::TimedOut(d)) => match deadline.and_then(deadline::overshoot) {
+                Some(overshoot) => {
+                    job_log!(
+                        error,
+                        LOG_TARGET_MAIN,
+                        self.tag(),
+                        "Deadline passed {:?} ago while probing video duration.",
+                        overshoot
+                    );
+                    Err(Error::DeadlineExceeded { overshoot })
+                }
+                None => {
+                    job_log!(
+                        error,
+                        LOG_TARGET_MAIN,
+                        self.tag(),
+                        "Probing video duration did not finish within {:?}.",
+                        d
+                    );
+                    Err(Error::Probe(ProbeError::Timeout(d)))
+                }
+            },
+            Err(AuxiliaryError::Spawn(e)) => {
+                job_log!(
+                    error,
+                    LOG_TARGET_MAIN,
+                    self.tag(),
+                    "Failed to spawn child process: {:?}",
+                    e
+                );
+                Err(Error::ChildProcess(std::sync::Arc::new(e)))
+            }
+            Err(e) => {
+                job_log!(
+                    warn,
+                    LOG_TARGET_MAIN,
+                    self.tag(),
+                    "Failed to probe video duration: {}",
+                    e
+                );
+                Ok(None)
+            }
+        }
+    }
+
+    /// Joins the thread of the given `kind` (if spawned), re-raising its panic, if any, in
+    /// which case the `guard` cleans up the other threads and the child process.
+    fn join_thread(&self, guard: &mut CleanupGuard, kind: ThreadKind) {
+        job_log!(
+            debug,
+            LOG_TARGET_MAIN,
+            self.tag(),
+            "Trying to join {} thread...",
+            kind
+        );
+        match guard.join(kind) {
+            Some(Ok(_)) => {
+                job_log!(
+                    debug,
+                    LOG_TARGET_MAIN,
+                    self.tag(),
+                    "Successfully joined {} thread",
+                    kind
+                );
+                self.sender(LOG_TARGET_MAIN)
+                    .send_internal(InternalEvent::ThreadJoined(kind));
+            }
+            Some(Err(e)) => {
+                job_log!(
+                    error,
+                    LOG_TARGET_MAIN,
+                    self.tag(),
+                    "Failed to join {} thread: {:?}",
+                    kind,
+                    e
+                );
+                std::panic::resume_unwind(e);
+            }
+            None => {}
+        }
+    }
+
+    /// Runs the job as a series of attempts, until the animated GIF fits within `max_bytes`
+    /// (see [`Settings::max_output_bytes`] and the `size_target` module), then sends the
+    /// smallest one down the channel (once written to `output_file`, if any).
+    fn convert_within_size(
+        &self,
+        settings: Settings,
+        max_bytes: u64,
+        output_file: Option<OutputFile>,
+    ) {
+        let attempts = settings.max_output_attempts;
+        let mut next = Some(Settings {
+            max_output_bytes: None,
+            output_file: None,
+            ..settings
+        });
+        let mut smallest: Option<Vec<u8>> = None;
+        let mut n = 0;
+        while let Some(settings) = next.take() {
+            n += 1;
+            let Some(buf) = self.run_attempt(n, attempts, settings.clone()) else {
+                return;
+            };
+            job_log!(
+                info,
+                LOG_TARGET_MAIN,
+                self.tag(),
+                "Attempt {} of {} produced {} bytes (target: {} bytes).",
+                n,
+                attempts,
+                buf.len(),
+                max_bytes
+            );
+            let output_bytes = buf.len();
+            if smallest.as_ref().is_none_or(|s| output_bytes < s.len()) {
+                smallest = Some(buf);
+            }
+            if output_bytes as u64 > max_bytes && n < attempts {
+                next = size_target::next_attempt(&settings, output_bytes, max_bytes);
+                if next.is_none() {
+                    job_log!(
+                        info,
+                        LOG_TARGET_MAIN,
+                        self.tag(),
+                        "Nothing left to reduce, so giving up."
+                    );
+                }
             }
+        }
+        let Some(buf) = smallest else {
+            return;
         };
-        let mut stdout = match child.stdout.take() {
-            Some(io) => io,
-            None => {
-                log::error!(target: LOG_TARGET_MAIN, "{} Failed to take STDOUT from child process.", self.id());
-                panic!();
+        if buf.len() as u64 > max_bytes {
+            let warning = Warning::OutputSizeTargetMissed {
+                max_bytes,
+                output_bytes: buf.len() as u64,
+                attempts: n,
+            };
+            if !self.send_or_shutdown(Message::Warning(warning)) {
+                return;
+            }
+        }
+        if self.send_or_shutdown(Message::Progress(1.0)) {
+            self.sender(LOG_TARGET_MAIN)
+                .deliver(output_file.as_ref(), buf);
+        }
+    }
+
+    /// Runs the `n`-th attempt (out of at most `attempts`) of [`Converter::convert_within_size`]
+    /// (see [`Converter::run_nested`]), with the progress rescaled (see
+    /// [`size_target::overall_progress`]). Returns the animated GIF, or `None` if the attempt
+    /// failed (in which case the error has been forwarded) or if the job is being shut down.
+    fn run_attempt(&self, n: u32, attempts: u32, settings: Settings) -> Option<Vec<u8>> {
+        job_log!(
+            info,
+            LOG_TARGET_MAIN,
+            self.tag(),
+            "Starting attempt {} of {}...",
+            n,
+            attempts
+        );
+        if !self.send_or_shutdown(Message::Attempt {
+            n,
+            settings: Box::new(settings.clone()),
+        }) {
+            return None;
+        }
+        let mut output = None;
+        let completed =
+            self.run_nested(
+                &format!("attempt {}", n),
+                settings,
+                None,
+                |message| match message {
+                    Message::Progress(p) => Some(Message::Progress(size_target::overall_progress(
+                        n, attempts, p,
+                    ))),
+                    Message::InterpolatedProgress(p) => Some(Message::InterpolatedProgress(
+                        size_target::overall_progress(n, attempts, p),
+                    )),
+                    Message::Success(buf) => {
+                        output = Some(buf);
+                        None
+                    }
+                    message => Some(message),
+                },
+            );
+        output.filter(|_| completed)
+    }
+
+    /// Runs the preview pass (see [`Settings::preview_width`] and [`Converter::run_nested`]),
+    /// sending its animated GIF using [`Message::Preview`], while the other messages of the
+    /// pass (e.g. its progress) are left out. Returns `false` if the pass failed (in which
+    /// case the error has been forwarded) or if the job is being shut down.
+    fn run_preview(&self, settings: Settings) -> bool {
+        job_log!(info, LOG_TARGET_MAIN, self.tag(), "Running preview pass...");
+        let mut failed = false;
+        let completed = self.run_nested("preview pass", settings, None, |message| match message {
+            Message::Success(buf) => Some(Message::Preview(buf)),
+            Message::Error(e) => {
+                failed = true;
+                Some(Message::Error(e))
             }
+            _ => None,
+        });
+        completed && !failed
+    }
+
+    /// Runs the job with the hardware acceleration method provided using [`Settings::hwaccel`]
+    /// (see [`Converter::run_nested`]), failing as soon as FFmpeg reports that the method
+    /// could not be set up, in which case the job is run again without acceleration, after a
+    /// [`Warning::HardwareAccelerationFailed`] warning (see [`HwAccelFallback::Software`]).
+    fn convert_with_fallback(&mut self, settings: Settings) {
+        // NOTE: FFmpeg fails before producing any output, so the writer is left untouched.
+        let sink = self.output_writer.take().map(OutputWriter::into_shared);
+        let lend = || sink.clone().map(OutputWriter::new);
+        let accelerated = Settings {
+            hwaccel_fallback: HwAccelFallback::Fail,
+            ..settings.clone()
         };
-        let mut stderr = match child.stderr.take() {
-            Some(io) => io,
-            None => {
-                log::error!(target: LOG_TARGET_MAIN, "{} Failed to take STDERR from child process.", self.id());
-                panic!()
+        let mut failure = None;
+        let forward = |message| match message {
+            Message::Error(Error::HardwareAcceleration { line, .. }) => {
+                failure = Some(line);
+                None
             }
+            // NOTE: E.g. the nonzero exit code of FFmpeg, stopped because of the failure.
+            Message::Error(_) if failure.is_some() => None,
+            message => Some(message),
         };
-
-        let tx_stdin = self.tx.clone();
-        #[cfg(not(feature = "tokio"))]
-        let Some(rx_command) = self.rx.take() else {
-            log::error!(target: LOG_TARGET_MAIN, "{} Unable to take command receiver.", self.id());
-            panic!();
+        let completed = self.run_nested("accelerated job", accelerated, lend(), forward);
+        let Some(line) = failure.filter(|_| completed) else {
+            return;
         };
-        #[cfg(feature = "tokio")]
-        let Some(mut rx_command) = self.rx.take() else {
-            log::error!(target: LOG_TARGET_MAIN, "{} Unable to take command receiver.", self.id());
-            panic!();
+        job_log!(
+            warn,
+            LOG_TARGET_MAIN,
+            self.tag(),
+            "Hardware acceleration failed, so running job again without it..."
+        );
+        if !self.send_or_shutdown(Message::Warning(Warning::HardwareAccelerationFailed {
+            line,
+        })) {
+            return;
+        }
+        let software = Settings {
+            hwaccel: None,
+            ..settings
         };
-        let job_cancelled_stdin = std::sync::Arc::clone(&self.job_cancelled);
-        let job_ended_stdin = std::sync::Arc::clone(&self.job_ended);
-        let id_stdin = self.id();
-        let handle_stdin = std::thread::spawn(move || {
-            log::info!(target: LOG_TARGET_STDIN, "{} Entered STDIN thread.", id_stdin);
-            {
-                use std::io::Write;
-                // NOTE: Here (i.e. inside the loop) we use `trace` instead of `debug` because we are no longer
-                // "receive blocking": we are no polling the channel. The reason for polling instead of blocking is that
-                // we needed a way for this thread to check whether the child process' stdout
-                // had returned, else the current thread would keep waiting until receiving
-                // a "Cancel" command or the other channel's end being dropped.
-                loop {
-                    #[cfg(not(feature = "tokio"))]
-                    let recv = rx_command.try_recv();
-                    #[cfg(feature = "tokio")]
-                    let recv = rx_command.try_recv();
-
-                    log::trace!(target: LOG_TARGET_STDIN, "{} Non-blockingly polling channel for next message...", id_stdin);
-                    match recv {
-                        Ok(c) => match c {
-                            Command::Cancel => {
-                                log::info!(target: LOG_TARGET_STDIN, "{} Received 'cancel' command.", id_stdin);
-                                log::trace!(target: LOG_TARGET_STDIN, "{} Trying to write 'q' to STDIN...", id_stdin);
-                                match stdin.write_all(b"q") {
-                                    Ok(_) => {
-                                        log::trace!(target: LOG_TARGET_STDIN, "{} Successfully wrote 'q' to STDIN.", id_stdin);
-                                    }
-                                    Err(e) => {
-                                        log::error!(target: LOG_TARGET_STDIN, "{} Failed to write 'q' to STDIN: {:?}", id_stdin, e);
-                                        panic!();
-                                    }
-                                }
-                                log::trace!(target: LOG_TARGET_STDIN, "{} Trying to send cancellation confirmation message...", id_stdin);
-                                match tx_stdin.send(Message::Error(Error::Cancelled)) {
-                                    Ok(_) => {
-                                        log::trace!(target: LOG_TARGET_STDIN, "{} Successfully sent cancellation confirmation message.", id_stdin);
-                                    }
-                                    Err(e) => {
-                                        log::error!(target: LOG_TARGET_STDIN, "{} Failed to send cancellation confirmation message: {:?}", id_stdin, e);
-                                        panic!();
-                                    }
-                                }
-                                {
-                                    log::trace!(target: LOG_TARGET_STDIN, "{} Trying to acquire job cancellation mutex to set it to 'true'...", id_stdin);
-                                    let mut job_cancelled = match job_cancelled_stdin.lock() {
-                                        Ok(m) => {
-                                            log::trace!(target: LOG_TARGET_STDIN, "{} Job cancellation mutex successfully acquired and set 'true'.", id_stdin);
-                                            m
-                                        }
-                                        Err(e) => {
-                                            log::error!(target: LOG_TARGET_STDIN, "{} Failed to acquire job cancellation mutex: {:?}", id_stdin, e);
-                                            panic!();
-                                        }
-                                    };
-                                    *job_cancelled = true;
-                                }
-                                log::info!(target: LOG_TARGET_STDIN, "{} Breaking out of STDIN thread because job cancelled...", id_stdin);
-                                break;
-                            }
-                        },
-                        #[cfg(feature = "tokio")]
-                        Err(e) => match e {
-                            tokio::sync::mpsc::error::TryRecvError::Empty => {
-                                log::trace!(target: LOG_TARGET_STDIN, "{} Channel empty. Sleeping for {} milliseconds...", id_stdin, STDIN_THREAD_SLEEP_DURATION_MS);
-                                std::thread::sleep(std::time::Duration::from_millis(
-                                    STDIN_THREAD_SLEEP_DURATION_MS,
-                                ));
-                            }
-                            tokio::sync::mpsc::error::TryRecvError::Disconnected => {
-                                log::info!(target: LOG_TARGET_STDIN, "{} Breaking out of STDIN thread because channel closed...", id_stdin);
-                                break;
-                            }
-                        },
-                        #[cfg(not(feature = "tokio"))]
-                        Err(e) => match e {
-                            std::sync::mpsc::TryRecvError::Empty => {
-                                log::trace!(target: LOG_TARGET_STDIN, "{} Channel empty. Sleeping for {} milliseconds...", id_stdin, STDIN_THREAD_SLEEP_DURATION_MS);
-                                std::thread::sleep(std::time::Duration::from_millis(
-                                    STDIN_THREAD_SLEEP_DURATION_MS,
-                                ));
-                            }
-                            std::sync::mpsc::TryRecvError::Disconnected => {
-                                log::info!(target: LOG_TARGET_STDIN, "{} Breaking out of STDIN thread because channel closed...", id_stdin);
-                                break;
-                            }
-                        },
-                    }
+        self.run_nested("software job", software, lend(), Some);
+    }
 
-                    log::trace!(target: LOG_TARGET_STDIN, "{} Trying to acquire 'job ended' mutex to see if the job has completed...", id_stdin);
-                    let job_ended = match job_ended_stdin.lock() {
-                        Err(e) => {
-                            log::error!(target: LOG_TARGET_STDIN, "{} Failed to acquire 'job ended' mutex: {:?}", id_stdin, e);
-                            panic!();
-                        }
-                        Ok(m) => {
-                            log::trace!(target: LOG_TARGET_STDIN, "{} Successfully acquired 'job ended' mutex.", id_stdin);
-                            m
-                        }
-                    };
-                    if *job_ended {
-                        log::info!(target: LOG_TARGET_STDIN, "{} Job has ended, so breaking out of 'read loop'...", id_stdin);
-                        break;
-                    } else {
-                        log::trace!(target: LOG_TARGET_STDIN, "{} Job has not ended yet.", id_stdin);
+    /// Runs the job described by `what` (e.g. `attempt 2`) with the given `settings`, using
+    /// its own [`Converter`] (streaming to `output_writer`, if any), whose messages are passed
+    /// through `forward` (which returns those to be sent down the channel), and to which the
+    /// application's commands are forwarded. The settings conflicts (already sent) and the
+    /// final [`Message::Summary`] (recorded instead) are left out. Returns `false` if the job
+    /// is being shut down.
+    fn run_nested(
+        &self,
+        what: &str,
+        settings: Settings,
+        output_writer: Option<OutputWriter>,
+        mut forward: impl FnMut(Message) -> Option<Message>,
+    ) -> bool {
+        let (converter, command_tx, rx) = Converter::new_with_channels();
+        let mut converter = converter.with_id(self.id());
+        if let Some(label) = self.label.clone() {
+            converter = converter.job_label(label);
+        }
+        if let Some(cache) = self.cache.clone() {
+            converter = converter.cache(cache);
+        }
+        converter.output_writer = output_writer;
+        let handle = std::thread::spawn(move || converter.convert(settings));
+        let cancel = || {
+            #[cfg(not(feature = "async-channel"))]
+            let sent = command_tx.send(Command::Cancel);
+            #[cfg(feature = "async-channel")]
+            let sent = command_tx.send_blocking(Command::Cancel);
+            // NOTE: The nested job may have ended in the meantime.
+            if let Err(e) = sent {
+                job_log!(
+                    debug,
+                    LOG_TARGET_MAIN,
+                    self.tag(),
+                    "Failed to forward cancel command to {}: {:?}",
+                    what,
+                    e
+                );
+            }
+        };
+        #[cfg(feature = "tokio")]
+        let mut rx = rx;
+        let mut shutting_down = false;
+        loop {
+            if self.cancel_requested() {
+                job_log!(
+                    info,
+                    LOG_TARGET_MAIN,
+                    self.tag(),
+                    "Cancel command received, so forwarding it to {}...",
+                    what
+                );
+                cancel();
+            }
+            // NOTE: Checked before trying to receive, so that no message is left behind.
+            let finished = handle.is_finished();
+            let message = match rx.try_recv() {
+                Ok(message) => message,
+                Err(_) if finished => break,
+                Err(_) => {
+                    std::thread::sleep(Duration::from_millis(ATTEMPT_SLEEP_DURATION_MS));
+                    continue;
+                }
+            };
+            let message = match message {
+                Message::Warning(Warning::SettingsConflict(_)) => continue,
+                Message::Summary(summary) => {
+                    if let Some(source) = summary.duration_source {
+                        self.tx.record_duration_source(source);
                     }
+                    self.tx.record_provenance(summary.provenance);
+                    continue;
                 }
+                Message::Done => break,
+                message => match forward(message) {
+                    Some(message) => message,
+                    None => continue,
+                },
+            };
+            if !shutting_down && !self.send_or_shutdown(message) {
+                shutting_down = true;
+                cancel();
+            }
+        }
+        if let Err(e) = handle.join() {
+            job_log!(
+                error,
+                LOG_TARGET_MAIN,
+                self.tag(),
+                "Failed to join converter thread of {}: {:?}",
+                what,
+                e
+            );
+        }
+        !shutting_down
+    }
+
+    /// The method picked by [`HwAccel::Auto`] for the FFmpeg binary at `binary_path` (see the
+    /// `hwaccel` module), the methods being listed by a short-lived FFmpeg child process
+    /// unless they were listed before. If they cannot be listed, FFmpeg picks the method.
+    fn resolve_hwaccel(
+        &self,
+        binary_path: &std::path::Path,
+        settings: &Settings,
+    ) -> Option<HwAccel> {
+        let timeout = deadline::earliest(settings.auxiliary_timeout, settings.deadline);
+        let hwaccel = crate::hwaccel::cached(binary_path, |args| {
+            job_log!(
+                debug,
+                LOG_TARGET_MAIN,
+                self.tag(),
+                "Listing FFmpeg hardware acceleration methods ({:?})...",
+                args
+            );
+            let mut command = argv::ffmpeg_command(&settings.command_wrapper, binary_path);
+            command.args(args);
+            run_auxiliary(command, timeout, || false)
+                .and_then(|output| output.into_stdout())
+                .map(|stdout| String::from_utf8_lossy(&stdout).into_owned())
+        });
+        match hwaccel {
+            Ok(hwaccel) => {
+                job_log!(
+                    info,
+                    LOG_TARGET_MAIN,
+                    self.tag(),
+                    "Hardware acceleration method picked: {:?}",
+                    hwaccel
+                );
+                hwaccel
+            }
+            Err(e) => {
+                job_log!(
+                    warn,
+                    LOG_TARGET_MAIN,
+                    self.tag(),
+                    "Unable to list FFmpeg hardware acceleration methods ({}), so letting FFmpeg pick one.",
+                    e
+                );
+                Some(HwAccel::Auto)
+            }
+        }
+    }
+
+    /// Sends the job's [`crate::Summary`] and the final [`Message::Done`] message
+    /// down the channel.
+    fn finish(&self, started: std::time::Instant) {
+        let summary = self
+            .tx
+            .summary(self.id(), self.label.clone(), started.elapsed());
+        job_log!(
+            info,
+            LOG_TARGET_MAIN,
+            self.tag(),
+            "Job ended with outcome {:?} after {:?}.",
+            summary.outcome(),
+            summary.elapsed
+        );
+        job_metrics::record_summary(&summary);
+        self.send_or_shutdown(Message::Summary(summary));
+        self.send_or_shutdown(Message::Done);
+    }
+
+    /// Runs [`Converter::convert`] on a new thread, and returns the [`JobGuard`] that cancels
+    /// the job when dropped (e.g. when the task owning it is aborted), unless detached (see
+    /// [`JobGuard::detach`]). The messages are still received using the [`Message`] channel.
+    pub fn spawn(self, settings: Settings) -> JobGuard {
+        let dropped = std::sync::Arc::clone(&self.guard_dropped);
+        #[cfg(feature = "tokio")]
+        let (done_tx, done_rx) = tokio::sync::oneshot::channel();
+        std::thread::spawn(move || {
+            self.convert(settings);
+            #[cfg(feature = "tokio")]
+            let _ = done_tx.send(());
+        });
+        JobGuard::new(
+            dropped,
+            #[cfg(feature = "tokio")]
+            done_rx,
+        )
+    }
+
+    /// Same as [`Converter::convert`], but copies the animated GIF to `writer` as FFmpeg
+    /// produces it (e.g. to upload it while it is being generated), instead of keeping it in
+    /// memory: [`Message::BytesWritten`] is sent (instead of [`Message::OutputBytes`]) as the
+    /// bytes are written, and the job completes with [`Message::SuccessWritten`] (instead of
+    /// [`Message::Success`]) once the output has been validated and `writer` flushed.
+    ///
+    /// An error returned by `writer` stops the job (just like [`Command::Cancel`]), which then
+    /// fails with [`Error::OutputWrite`]. The options that need the animated GIF's bytes (e.g.
+    /// [`Settings::emit_frame_map`]) cannot be used (see [`crate::SettingsConflict::WriterOverrides`]),
+    /// and the cache (see [`Converter::cache`]) is bypassed.
+    ///
+    /// NOTE: The bytes are written as soon as FFmpeg produces them, so when the job does not
+    /// succeed (e.g. when it gets cancelled, or when the output turns out to be invalid),
+    /// `writer` may already have received part of the output (at most
+    /// [`Error::Cancelled`]'s `bytes_discarded`), which the application should then discard.
+    /// Nothing more is written once the job has been cancelled. Since FFmpeg cannot produce
+    /// its output faster than it is written, a slow `writer` slows the job down.
+    pub fn convert_to_writer(
+        mut self,
+        settings: Settings,
+        writer: impl std::io::Write + Send + 'static,
+    ) {
+        self.output_writer = Some(OutputWriter::new(writer));
+        self.convert(settings);
+    }
+
+    pub fn convert(mut self, settings: Settings) {
+        let started = std::time::Instant::now();
+        job_metrics::record_started();
+        self.memory_budget = settings
+            .memory_budget
+            .map(|bytes| std::sync::Arc::new(MemoryBudget::new(bytes)));
+        if let Some(budget) = self.memory_budget.as_ref() {
+            job_log!(
+                debug,
+                LOG_TARGET_MAIN,
+                self.tag(),
+                "Memory budget: {} bytes.",
+                budget.limit()
+            );
+        }
+        self.internal_events.store(
+            settings.emit_internal_events,
+            std::sync::atomic::Ordering::SeqCst,
+        );
+
+        #[cfg(feature = "tokio")]
+        if !self.check_async_context(settings.strict_async_context) {
+            self.finish(started);
+            return;
+        }
+
+        if let Err(e) = settings.validate() {
+            job_log!(
+                error,
+                LOG_TARGET_MAIN,
+                self.tag(),
+                "Invalid settings: {:?}",
+                e
+            );
+            self.send_or_shutdown(Message::Error(Error::InvalidSettings(e)));
+            self.finish(started);
+            return;
+        }
+        for conflict in settings.conflicts() {
+            job_log!(
+                warn,
+                LOG_TARGET_MAIN,
+                self.tag(),
+                "Conflicting settings: {}.",
+                conflict
+            );
+            self.send_or_shutdown(Message::Warning(Warning::SettingsConflict(conflict)));
+        }
+        // NOTE: Checked separately, since the writer is not one of the settings.
+        if self.output_writer.is_some() {
+            if let Some(conflict) = conflicts::find_writer_conflicts(&settings)
+                .into_iter()
+                .next()
+            {
+                job_log!(
+                    error,
+                    LOG_TARGET_MAIN,
+                    self.tag(),
+                    "Invalid settings: {:?}",
+                    conflict
+                );
+                self.send_or_shutdown(Message::Error(Error::InvalidSettings(
+                    SettingsError::Conflict(conflict),
+                )));
+                self.finish(started);
+                return;
+            }
+        }
+
+        // NOTE: The name was validated above, and an existing file that is to be kept is
+        // checked for before any work is done (it is checked for again once the GIF is ready).
+        let output_file = settings.resolved_output_file().and_then(Result::ok);
+        if let Some(file) = output_file.as_ref() {
+            match file.resolve() {
+                Ok(OutputTarget::Write(path)) => {
+                    job_log!(
+                        debug,
+                        LOG_TARGET_MAIN,
+                        self.tag(),
+                        "Output file: {:?}",
+                        path
+                    );
+                }
+                Ok(OutputTarget::Skip(existing_path)) => {
+                    job_log!(
+                        info,
+                        LOG_TARGET_MAIN,
+                        self.tag(),
+                        "Output file already exists, so skipping job: {:?}",
+                        existing_path
+                    );
+                    self.send_or_shutdown(Message::Skipped { existing_path });
+                    self.finish(started);
+                    return;
+                }
+                Err(e) => {
+                    job_log!(
+                        error,
+                        LOG_TARGET_MAIN,
+                        self.tag(),
+                        "Unable to resolve output file: {:?}",
+                        e
+                    );
+                    self.send_or_shutdown(Message::Error(Error::OutputFile {
+                        path: file.path(),
+                        error: std::sync::Arc::new(e),
+                    }));
+                    self.finish(started);
+                    return;
+                }
+            }
+        }
+
+        let deny_list = match settings.deny_patterns() {
+            Ok(patterns) => crate::deny::DenyList::new(patterns),
+            Err(e) => {
+                job_log!(
+                    error,
+                    LOG_TARGET_MAIN,
+                    self.tag(),
+                    "Invalid settings: {:?}",
+                    e
+                );
+                self.send_or_shutdown(Message::Error(Error::InvalidSettings(e)));
+                self.finish(started);
+                return;
+            }
+        };
+
+        // NOTE: FFmpeg would otherwise fail without any output (i.e. `Error::EmptyStdout`).
+        if let Some(watermark) = settings.watermark.as_ref() {
+            if let Err(e) = std::fs::metadata(&watermark.path) {
+                job_log!(
+                    error,
+                    LOG_TARGET_MAIN,
+                    self.tag(),
+                    "Unable to find watermark {:?}: {:?}",
+                    watermark.path,
+                    e
+                );
+                self.send_or_shutdown(Message::Error(Error::WatermarkFile {
+                    path: watermark.path.clone(),
+                    error: std::sync::Arc::new(e),
+                }));
+                self.finish(started);
+                return;
+            }
+        }
+
+        if let Err(e) = self.check_deadline(settings.deadline) {
+            self.send_or_shutdown(Message::Error(e));
+            self.finish(started);
+            return;
+        }
+
+        if let Some(preview) = settings.preview_settings() {
+            if !self.run_preview(preview) {
+                self.finish(started);
+                return;
+            }
+        }
+
+        if let Some(max_bytes) = settings.max_output_bytes {
+            self.convert_within_size(settings, max_bytes, output_file);
+            self.finish(started);
+            return;
+        }
+
+        // NOTE: The key is derived from the settings as provided (i.e. before the clip gets
+        // resolved or the number of colors selected), so that a hit needs no FFmpeg at all.
+        // Only the animated GIF sent using `Message::Success` would be cached, so the jobs
+        // that generate other widths as well bypass the cache, as do the jobs whose arguments
+        // may be edited by the `argv_hook` (which the key knows nothing about), and those
+        // whose animated GIF is written directly to a file (or streamed to a writer).
+        let cacheable = settings.additional_widths.is_empty()
+            && settings.argv_hook.is_none()
+            && settings.output_path.is_none()
+            && self.output_writer.is_none();
+        let cache_entry = self.cache.as_ref().filter(|_| cacheable).and_then(|cache| {
+            match cache::key(&settings.input, &settings.plan()) {
+                Ok(key) => Some((cache.clone(), key)),
+                Err(e) => {
+                    job_log!(
+                        warn,
+                        LOG_TARGET_MAIN,
+                        self.tag(),
+                        "Unable to derive cache key, so not using cache: {:?}",
+                        e
+                    );
+                    None
+                }
+            }
+        });
+        // NOTE: The timestamps of the frames depend on the start of the clip, which (on a
+        // hit) is not known when it must be resolved using the video's duration.
+        let frame_map_unresolved = settings.emit_frame_map && settings.frame_map().is_none();
+        if let Some((cache, key)) = cache_entry.as_ref().filter(|_| !frame_map_unresolved) {
+            if let Some(buf) = cache.load(*key) {
+                job_log!(
+                    info,
+                    LOG_TARGET_MAIN,
+                    self.tag(),
+                    "Cache hit (key: {}), so not spawning FFmpeg child process.",
+                    key
+                );
+                if let Ok(info) = parse_gif_info(&buf) {
+                    self.send_or_shutdown(Message::OutputDimensions {
+                        width: u32::from(info.width),
+                        height: u32::from(info.height),
+                    });
+                }
+                let threshold = settings
+                    .output_size_warning_ratio
+                    .and_then(|ratio| Some((settings.input.size()?, ratio)));
+                if let Some(warning) = output_size_warning(threshold, buf.len() as u64) {
+                    self.send_or_shutdown(Message::Warning(warning));
+                }
+                if let Some(message) = frame_map_message(settings.frame_map(), &buf) {
+                    self.send_or_shutdown(message);
+                }
+                self.sender(LOG_TARGET_MAIN)
+                    .deliver(output_file.as_ref(), buf);
+                self.finish(started);
+                return;
+            }
+            job_log!(
+                debug,
+                LOG_TARGET_MAIN,
+                self.tag(),
+                "Cache miss (key: {}).",
+                key
+            );
+        }
+
+        job_log!(
+            debug,
+            LOG_TARGET_MAIN,
+            self.tag(),
+            "Trying to spawn FFmpeg child process..."
+        );
+        let binary_path = match self.binary_path(&settings.ffmpeg_location) {
+            Ok(p) => p,
+            Err(e) => {
+                self.send_or_shutdown(Message::Error(e));
+                self.finish(started);
+                return;
+            }
+        };
+        if settings.check_capabilities {
+            if let Err(e) = self.check_capabilities(&binary_path, &settings) {
+                self.send_or_shutdown(Message::Error(e));
+                self.finish(started);
+                return;
+            }
+        }
+        let settings = match settings.hwaccel {
+            Some(HwAccel::Auto) => Settings {
+                hwaccel: self.resolve_hwaccel(&binary_path, &settings),
+                ..settings
+            },
+            _ => settings,
+        };
+        if settings.hwaccel.is_some() && settings.hwaccel_fallback == HwAccelFallback::Software {
+            self.convert_with_fallback(settings);
+            self.finish(started);
+            return;
+        }
+
+        // NOTE: The probed duration (if any) is the most reliable source for the progress.
+        let (settings, probed_duration) = match settings.clip {
+            Some(clip) if clip.needs_duration() => {
+                let duration = match self.probe_duration(
+                    &binary_path,
+                    &settings.command_wrapper,
+                    &settings.input,
+                    settings.input_format_hints.as_ref(),
+                    settings.auxiliary_timeout,
+                    settings.deadline,
+                ) {
+                    Ok(d) => d,
+                    Err(e) => {
+                        self.send_or_shutdown(Message::Error(e));
+                        self.finish(started);
+                        return;
+                    }
+                };
+                match clip.resolve(duration) {
+                    Some(resolved) => {
+                        job_log!(
+                            info,
+                            LOG_TARGET_MAIN,
+                            self.tag(),
+                            "Clip {:?} resolved to {:?}.",
+                            clip,
+                            resolved
+                        );
+                        (settings.clip(resolved), duration)
+                    }
+                    None => {
+                        job_log!(
+                            error,
+                            LOG_TARGET_MAIN,
+                            self.tag(),
+                            "Unable to select clip {:?} without knowing the video's duration.",
+                            clip
+                        );
+                        self.send_or_shutdown(Message::Error(Error::InvalidSettings(
+                            SettingsError::ClipRequiresDuration,
+                        )));
+                        self.finish(started);
+                        return;
+                    }
+                }
+            }
+            _ => (settings, None),
+        };
+        let duration_adjustments = DurationAdjustments {
+            clip_bounds: settings.clip.and_then(|c| c.bounds(None)),
+            speed: settings.effective_speed(),
+            boomerang: settings.boomerang,
+            frame_limit: settings.output_frame_limit_duration(),
+        };
+        let mut duration_tracker = DurationTracker::new(settings.input.size());
+        let mut provenance = settings.parse_provenance.map(ProvenanceLog::new);
+        let progress_curve = settings.progress_curve;
+        let mut parse_checker = ParseChecker::new(settings.strict_parsing);
+        let strict_parsing = settings.strict_parsing;
+        let output_format = settings.output_format;
+        let frame_map = settings.frame_map();
+        let frame_rate = settings
+            .interpolate_frame_delays
+            .then_some(settings.gif_fps);
+
+        let settings = if settings.auto_colors {
+            match self.select_color_count(&binary_path, &settings) {
+                Ok(Some(n)) => {
+                    // NOTE: Capped by `Settings::max_palette_bit_depth`, if provided.
+                    let settings = settings.max_colors(n);
+                    let n = settings.effective_max_colors().unwrap_or(n);
+                    if !self.send_or_shutdown(Message::ColorCountSelected(n)) {
+                        // NOTE: No point in spawning the child process, since nobody is listening.
+                        self.finish(started);
+                        return;
+                    }
+                    settings
+                }
+                Ok(None) => settings,
+                Err(e) => {
+                    self.send_or_shutdown(Message::Error(e));
+                    self.finish(started);
+                    return;
+                }
+            }
+        } else {
+            settings
+        };
+
+        // NOTE: The input's size is found before starting the job, since the input file
+        // could be modified (or, in the case of a path, replaced) in the meantime.
+        let output_size_threshold = settings.output_size_warning_ratio.and_then(|ratio| {
+            let input_bytes = settings.input.size()?;
+            job_log!(
+                debug,
+                LOG_TARGET_MAIN,
+                self.tag(),
+                "Input size: {} bytes.",
+                input_bytes
+            );
+            Some((input_bytes, ratio))
+        });
+
+        let additional_widths = settings.additional_widths.clone();
+        let variant_dir = if additional_widths.is_empty() {
+            None
+        } else {
+            match VariantDir::create() {
+                Ok(dir) => Some(dir),
+                Err(e) => {
+                    job_log!(
+                        error,
+                        LOG_TARGET_MAIN,
+                        self.tag(),
+                        "Unable to create temporary directory for additional widths: {:?}",
+                        e
+                    );
+                    self.send_or_shutdown(Message::Error(Error::OutputFile {
+                        path: std::env::temp_dir(),
+                        error: std::sync::Arc::new(e),
+                    }));
+                    self.finish(started);
+                    return;
+                }
+            }
+        };
+        let plan = settings.plan_in(variant_dir.as_ref().map(VariantDir::path));
+        job_log!(
+            debug,
+            LOG_TARGET_MAIN,
+            self.tag(),
+            "Conversion plan: {}",
+            plan
+        );
+        // NOTE: The auxiliary child processes (if any) may have used up the time left.
+        if let Err(e) = self.check_deadline(settings.deadline) {
+            self.send_or_shutdown(Message::Error(e));
+            self.finish(started);
+            return;
+        }
+        // NOTE: Removed when dropped (i.e. if the job fails), if created for the job.
+        let mut output_path = match settings
+            .output_path
+            .as_deref()
+            .map(OutputPath::prepare)
+            .transpose()
+        {
+            Ok(output_path) => output_path,
+            Err(e) => {
+                job_log!(
+                    error,
+                    LOG_TARGET_MAIN,
+                    self.tag(),
+                    "Unable to write output file: {:?}",
+                    e
+                );
+                self.send_or_shutdown(Message::Error(e));
+                self.finish(started);
+                return;
+            }
+        };
+        // NOTE: The same arguments as the plan's, without the lossy conversion (unless edited
+        // by the `argv_hook`).
+        let mut args = settings.command_args(variant_dir.as_ref().map(VariantDir::path));
+        if let Some(hook) = settings.argv_hook.as_ref() {
+            if let Err(panic) = hook.call(&mut args) {
+                job_log!(
+                    error,
+                    LOG_TARGET_MAIN,
+                    self.tag(),
+                    "Argv hook panicked: {}",
+                    panic
+                );
+                self.send_or_shutdown(Message::Error(Error::InvalidSettings(
+                    SettingsError::ArgvHookPanicked(panic),
+                )));
+                self.finish(started);
+                return;
+            }
+        }
+        job_log!(
+            debug,
+            LOG_TARGET_MAIN,
+            self.tag(),
+            "FFmpeg arguments: {:?}",
+            args
+        );
+        // NOTE: FFmpeg's banner is not printed when hidden on purpose (see `Warning::MissingBanner`).
+        let expects_banner = !args
+            .iter()
+            .any(|arg| matches!(arg.to_str(), Some("-hide_banner" | "-loglevel" | "-v")));
+        let mut command = argv::ffmpeg_command(&settings.command_wrapper, &binary_path);
+        command.args(args);
+        command.stdin(std::process::Stdio::piped());
+        if let Err(e) = settings.input.prepare(&mut command) {
+            job_log!(
+                error,
+                LOG_TARGET_MAIN,
+                self.tag(),
+                "Failed to prepare input source for child process: {:?}",
+                e
+            );
+            self.send_or_shutdown(Message::Error(Error::ChildProcess(std::sync::Arc::new(e))));
+            self.finish(started);
+            return;
+        }
+        let mut child = match command
+            .stdout(std::process::Stdio::piped())
+            .stderr(std::process::Stdio::piped())
+            .spawn()
+        {
+            Ok(c) => {
+                job_log!(
+                    debug,
+                    LOG_TARGET_MAIN,
+                    self.tag(),
+                    "FFmpeg child process successfully spawned."
+                );
+                c
+            }
+            Err(e) => {
+                job_log!(
+                    error,
+                    LOG_TARGET_MAIN,
+                    self.tag(),
+                    "Failed to spawn child process: {:?}",
+                    e
+                );
+                self.send_or_shutdown(Message::Error(Error::ChildProcess(std::sync::Arc::new(e))));
+                self.finish(started);
+                return;
+            }
+        };
+
+        let (stdin, mut stdout, mut stderr) = match (
+            child.stdin.take(),
+            child.stdout.take(),
+            child.stderr.take(),
+        ) {
+            // NOTE: The child process' `stdin` is not a pipe when it is the input file itself.
+            (stdin, Some(stdout), Some(stderr))
+                if stdin.is_some() || settings.input.is_child_stdin() =>
+            {
+                (stdin, stdout, stderr)
+            }
+            pipes => {
+                job_log!(
+                        error,
+                        LOG_TARGET_MAIN,
+                        self.tag(),
+                        "Failed to take pipes from child process (stdin: {}, stdout: {}, stderr: {}), so killing it.",
+                        pipes.0.is_some(),
+                        pipes.1.is_some(),
+                        pipes.2.is_some()
+                    );
+                if let Err(e) = child.kill().and_then(|_| child.wait()) {
+                    job_log!(
+                        warn,
+                        LOG_TARGET_MAIN,
+                        self.tag(),
+                        "Failed to kill child process: {:?}",
+                        e
+                    );
+                }
+                self.send_or_shutdown(Message::Error(Error::ChildProcess(std::sync::Arc::new(
+                    std::io::Error::other("failed to take pipes from child process"),
+                ))));
+                self.finish(started);
+                return;
+            }
+        };
+        // NOTE: With in-memory (or streamed) input, the child process' `stdin` carries the
+        // video, so it is handed over to the WRITER thread (see below), and the STDIN thread
+        // kills the child process instead of writing `q` to it (see `stop_child`).
+        let input_data = settings.input.take_stdin_data();
+        let (stdin, writer_stdin) = match input_data {
+            Some(_) => (None, stdin),
+            None => (stdin, None),
+        };
+        // NOTE: Shared with the main thread, which drops it if the STDIN thread fails to exit
+        // in time (see `STDIN_THREAD_GRACE_PERIOD_MS`).
+        let stdin = std::sync::Arc::new(std::sync::Mutex::new(stdin));
+
+        // NOTE: From here on, the guard owns the child process and the threads, which it
+        // cleans up if the main thread panics before joining them (see `CleanupGuard`).
+        let mut guard = CleanupGuard::new(
+            self.tag(),
+            std::sync::Arc::clone(&self.job_cancelled),
+            std::sync::Arc::clone(&self.job_ended),
+            std::sync::Arc::clone(&self.kill_requested),
+            child,
+        );
+        #[cfg(test)]
+        guard.report_to(std::sync::Arc::clone(&self.cleanup_report));
+        #[cfg(test)]
+        if self.panic_at == Some(PanicPoint::AfterSpawn) {
+            panic!("injected panic (after spawn)");
+        }
+
+        // NOTE: The interpolator is always fed, but only used when the
+        // `smooth_progress` option is enabled (see the SMOOTHER thread below).
+        let interpolator =
+            std::sync::Arc::new(std::sync::Mutex::new(ProgressInterpolator::default()));
+        let clock = std::time::Instant::now();
+
+        // NOTE: The number of bytes read so far from the child process' `stdout`,
+        // which the STDIN thread reports when the job gets cancelled.
+        let stdout_bytes = std::sync::Arc::new(std::sync::atomic::AtomicUsize::new(0));
+
+        // NOTE: Set by the STDERR thread when it fails the job (i.e. when a line matches one
+        // of the deny patterns, or cannot be parsed in strict mode), by the WRITER thread
+        // when the input cannot be read, or by the STDOUT thread when the output cannot be
+        // written (see `Converter::convert_to_writer`), so that the STDIN thread stops the
+        // child process.
+        let denied = std::sync::Arc::new(std::sync::atomic::AtomicBool::new(false));
+        // NOTE: Claimed (i.e. set) by the first thread that settles the job's outcome: the
+        // STDOUT thread when the output has been read to end, the STDIN thread when the job
+        // gets cancelled, or the STDERR (or WRITER) thread when it fails the job. The other
+        // threads then drop their own outcome, so that exactly one terminal message (e.g.
+        // either `Success` or `Cancelled`, when both happen at once) is sent.
+        let outcome_claimed = std::sync::Arc::new(std::sync::atomic::AtomicBool::new(false));
+        // NOTE: The STDOUT thread waits for the STDERR thread to be done (i.e. for its report)
+        // and for the child process' exit code before resolving the job's outcome, so that a
+        // deny pattern matched near the end of the job cannot be missed, and so that a valid
+        // GIF is not discarded because FFmpeg exited with a nonzero code while cleaning up.
+        let (stderr_report_tx, stderr_report_rx) = std::sync::mpsc::channel::<StderrReport>();
+        let (exit_code_tx, exit_code_rx) = std::sync::mpsc::channel::<Option<i32>>();
+
+        // NOTE: Runs concurrently with the STDOUT and STDERR threads, since FFmpeg only
+        // reads its input as fast as its outputs are drained.
+        if let (Some(data), Some(pipe)) = (input_data, writer_stdin) {
+            let tx_writer = self.sender(LOG_TARGET_WRITER);
+            let job_cancelled_writer = std::sync::Arc::clone(&self.job_cancelled);
+            let denied_writer = std::sync::Arc::clone(&denied);
+            let outcome_claimed_writer = std::sync::Arc::clone(&outcome_claimed);
+            let id_writer = self.tag();
+            guard.spawn(ThreadKind::Writer, move || {
+                job_log!(info, LOG_TARGET_WRITER, id_writer, "Entered WRITER thread.");
+                let _lifecycle = tx_writer.lifecycle(ThreadKind::Writer);
+                let written = crate::input::pump(pipe, data, || {
+                    *job_cancelled_writer
+                        .lock()
+                        .unwrap_or_else(|e| e.into_inner())
+                });
+                match written {
+                    Ok(n) => job_log!(
+                        debug,
+                        LOG_TARGET_WRITER,
+                        id_writer,
+                        "Wrote {} bytes to STDIN, and closed it.",
+                        n
+                    ),
+                    Err(PumpError::Read(e)) => {
+                        job_log!(
+                            warn,
+                            LOG_TARGET_WRITER,
+                            id_writer,
+                            "Failed to read input ({:?}), so failing job...",
+                            e
+                        );
+                        // NOTE: Just like when the STDERR thread fails the job, since FFmpeg
+                        // would otherwise convert a truncated video.
+                        *job_cancelled_writer
+                            .lock()
+                            .unwrap_or_else(|e| e.into_inner()) = true;
+                        denied_writer.store(true, std::sync::atomic::Ordering::SeqCst);
+                        if !outcome_claimed_writer.swap(true, std::sync::atomic::Ordering::SeqCst) {
+                            tx_writer.send_or_shutdown(Message::Error(Error::InputRead(
+                                std::sync::Arc::new(e),
+                            )));
+                        }
+                    }
+                    Err(PumpError::Write(e)) => job_log!(
+                        warn,
+                        LOG_TARGET_WRITER,
+                        id_writer,
+                        "Failed to write input to STDIN: {:?}",
+                        e
+                    ),
+                }
+                job_log!(
+                    info,
+                    LOG_TARGET_WRITER,
+                    id_writer,
+                    "Exiting WRITER thread..."
+                );
+            });
+        }
+
+        let tx_stdin = self.sender(LOG_TARGET_STDIN);
+        let interpolator_stdin = std::sync::Arc::clone(&interpolator);
+        let stdout_bytes_stdin = std::sync::Arc::clone(&stdout_bytes);
+        let denied_stdin = std::sync::Arc::clone(&denied);
+        let guard_dropped_stdin = std::sync::Arc::clone(&self.guard_dropped);
+        let kill_requested_stdin = std::sync::Arc::clone(&self.kill_requested);
+        let outcome_claimed_stdin = std::sync::Arc::clone(&outcome_claimed);
+        #[cfg(not(feature = "tokio"))]
+        let Some(rx_command) = self.rx.take() else {
+            job_log!(
+                error,
+                LOG_TARGET_MAIN,
+                self.tag(),
+                "Unable to take command receiver."
+            );
+            self.send_or_shutdown(Message::Error(Error::Internal(
+                "command receiver already taken",
+            )));
+            self.finish(started);
+            return;
+        };
+        #[cfg(feature = "tokio")]
+        let Some(mut rx_command) = self.rx.take() else {
+            job_log!(
+                error,
+                LOG_TARGET_MAIN,
+                self.tag(),
+                "Unable to take command receiver."
+            );
+            self.send_or_shutdown(Message::Error(Error::Internal(
+                "command receiver already taken",
+            )));
+            self.finish(started);
+            return;
+        };
+        let job_cancelled_stdin = std::sync::Arc::clone(&self.job_cancelled);
+        let job_ended_stdin = std::sync::Arc::clone(&self.job_ended);
+        let stdin_stdin = std::sync::Arc::clone(&stdin);
+        // NOTE: The child process' `stdin` carries the video, so it cannot be asked to stop.
+        let kill_to_stop = settings
+            .input
+            .pipes_stdin()
+            .then(|| std::sync::Arc::clone(&self.kill_requested));
+        // NOTE: Checked along with the commands, and cleared once the outcome is settled.
+        let mut deadline_stdin = settings.deadline;
+        #[cfg(test)]
+        let stdin_thread_stall = self.stdin_thread_stall;
+        let id_stdin = self.tag();
+        guard.spawn(ThreadKind::Stdin, move || {
+            job_log!(info, LOG_TARGET_STDIN, id_stdin, "Entered STDIN thread.");
+            let _lifecycle = tx_stdin.lifecycle(ThreadKind::Stdin);
+            {
+                // NOTE: Here (i.e. inside the loop) we use `trace` instead of `debug` because we are no longer
+                // "receive blocking": we are no polling the channel. The reason for polling instead of blocking is that
+                // we needed a way for this thread to check whether the child process' stdout
+                // had returned, else the current thread would keep waiting until receiving
+                // a "Cancel" command or the other channel's end being dropped.
+                loop {
+                    if denied_stdin.load(std::sync::atomic::Ordering::SeqCst) {
+                        job_log!(
+                            info,
+                            LOG_TARGET_STDIN,
+                            id_stdin,
+                            "Job failed by STDERR (or WRITER, or STDOUT) thread, so stopping child process..."
+                        );
+                        interpolator_stdin
+                            .lock()
+                            .unwrap_or_else(|e| e.into_inner())
+                            .finish();
+                        // NOTE: The child process may already have exited.
+                        if let Err(e) = stop_child(&stdin_stdin, kill_to_stop.as_deref()) {
+                            job_log!(
+                                warn,
+                                LOG_TARGET_STDIN,
+                                id_stdin,
+                                "Failed to write 'q' to STDIN: {:?}",
+                                e
+                            );
+                        }
+                        break;
+                    }
+
+                    if let Some(overshoot) = deadline_stdin.and_then(deadline::overshoot) {
+                        deadline_stdin = None;
+                        if outcome_claimed_stdin.swap(true, std::sync::atomic::Ordering::SeqCst) {
+                            job_log!(
+                                info,
+                                LOG_TARGET_STDIN,
+                                id_stdin,
+                                "Job outcome already settled, so ignoring deadline."
+                            );
+                        } else {
+                            job_log!(
+                                info,
+                                LOG_TARGET_STDIN,
+                                id_stdin,
+                                "Deadline passed {:?} ago, so stopping child process...",
+                                overshoot
+                            );
+                            interpolator_stdin
+                                .lock()
+                                .unwrap_or_else(|e| e.into_inner())
+                                .finish();
+                            // NOTE: The child process may already have exited.
+                            if let Err(e) = stop_child(&stdin_stdin, kill_to_stop.as_deref()) {
+                                job_log!(
+                                    debug,
+                                    LOG_TARGET_STDIN,
+                                    id_stdin,
+                                    "Failed to write 'q' to STDIN: {:?}",
+                                    e
+                                );
+                            }
+                            tx_stdin.send_or_shutdown(Message::Error(Error::DeadlineExceeded {
+                                overshoot,
+                            }));
+                            // NOTE: So that the output is thrown away, just like when cancelled.
+                            *job_cancelled_stdin
+                                .lock()
+                                .unwrap_or_else(|e| e.into_inner()) = true;
+                            break;
+                        }
+                    }
+
+                    // NOTE: A dropped job guard cancels the job just like the command.
+                    let guard_dropped =
+                        guard_dropped_stdin.swap(false, std::sync::atomic::Ordering::SeqCst);
+                    let recv = match guard_dropped {
+                        true => Ok(Command::Cancel),
+                        false => rx_command.try_recv(),
+                    };
+
+                    job_log!(
+                        trace,
+                        LOG_TARGET_STDIN,
+                        id_stdin,
+                        "Non-blockingly polling channel for next message..."
+                    );
+                    match recv {
+                        Ok(c) => {
+                            match c {
+                                Command::Cancel => {
+                                    job_log!(
+                                        info,
+                                        LOG_TARGET_STDIN,
+                                        id_stdin,
+                                        "Received 'cancel' command."
+                                    );
+                                    if outcome_claimed_stdin
+                                        .swap(true, std::sync::atomic::Ordering::SeqCst)
+                                    {
+                                        job_log!(
+                                            info,
+                                            LOG_TARGET_STDIN,
+                                            id_stdin,
+                                            "Job outcome already settled, so ignoring 'cancel' command."
+                                        );
+                                        continue;
+                                    }
+                                    interpolator_stdin
+                                        .lock()
+                                        .unwrap_or_else(|e| e.into_inner())
+                                        .finish();
+                                    job_log!(
+                                        trace,
+                                        LOG_TARGET_STDIN,
+                                        id_stdin,
+                                        "Trying to write 'q' to STDIN..."
+                                    );
+                                    match stop_child(&stdin_stdin, kill_to_stop.as_deref()) {
+                                        Ok(_) => {
+                                            job_log!(
+                                                trace,
+                                                LOG_TARGET_STDIN,
+                                                id_stdin,
+                                                "Successfully wrote 'q' to STDIN."
+                                            );
+                                        }
+                                        // NOTE: The child process may already have exited (or been killed).
+                                        Err(e) if e.kind() == std::io::ErrorKind::BrokenPipe => {
+                                            job_log!(
+                                                debug,
+                                                LOG_TARGET_STDIN,
+                                                id_stdin,
+                                                "Child process already exited, so not writing 'q' to STDIN."
+                                            );
+                                        }
+                                        Err(e) => {
+                                            job_log!(
+                                                warn,
+                                                LOG_TARGET_STDIN,
+                                                id_stdin,
+                                                "Failed to write 'q' to STDIN: {:?}",
+                                                e
+                                            );
+                                        }
+                                    }
+                                    job_log!(
+                                        trace,
+                                        LOG_TARGET_STDIN,
+                                        id_stdin,
+                                        "Trying to send cancellation confirmation message..."
+                                    );
+                                    let error = Error::Cancelled {
+                                        progress_at_cancel: tx_stdin.last_progress(),
+                                        bytes_discarded: stdout_bytes_stdin
+                                            .load(std::sync::atomic::Ordering::SeqCst),
+                                    };
+                                    if tx_stdin.send_or_shutdown(Message::Error(error)) {
+                                        job_log!(
+                                            trace,
+                                            LOG_TARGET_STDIN,
+                                            id_stdin,
+                                            "Successfully sent cancellation confirmation message."
+                                        );
+                                    }
+                                    {
+                                        job_log!(trace, LOG_TARGET_STDIN, id_stdin, "Trying to acquire job cancellation mutex to set it to 'true'...");
+                                        let mut job_cancelled = match job_cancelled_stdin.lock() {
+                                            Ok(m) => {
+                                                job_log!(trace, LOG_TARGET_STDIN, id_stdin, "Job cancellation mutex successfully acquired and set 'true'.");
+                                                m
+                                            }
+                                            Err(e) => {
+                                                job_log!(error, LOG_TARGET_STDIN, id_stdin, "Failed to acquire job cancellation mutex: {:?}", e);
+                                                drop(e);
+                                                tx_stdin.fail(Error::Internal("job cancellation mutex poisoned"), &denied_stdin, &outcome_claimed_stdin);
+                                                job_cancelled_stdin.lock().unwrap_or_else(|e| e.into_inner())
+                                            }
+                                        };
+                                        *job_cancelled = true;
+                                    }
+                                    if guard_dropped {
+                                        // NOTE: Nobody waits for the job anymore, so the child process
+                                        // is killed rather than left to finish what it was doing.
+                                        kill_requested_stdin
+                                            .store(true, std::sync::atomic::Ordering::SeqCst);
+                                    }
+                                    job_log!(
+                                        info,
+                                        LOG_TARGET_STDIN,
+                                        id_stdin,
+                                        "Breaking out of STDIN thread because job cancelled..."
+                                    );
+                                    break;
+                                }
+                            }
+                        }
+                        #[cfg(feature = "tokio")]
+                        Err(e) => match e {
+                            tokio::sync::mpsc::error::TryRecvError::Empty => {
+                                job_log!(
+                                    trace,
+                                    LOG_TARGET_STDIN,
+                                    id_stdin,
+                                    "Channel empty. Sleeping for {} milliseconds...",
+                                    STDIN_THREAD_SLEEP_DURATION_MS
+                                );
+                                std::thread::sleep(std::time::Duration::from_millis(
+                                    STDIN_THREAD_SLEEP_DURATION_MS,
+                                ));
+                            }
+                            tokio::sync::mpsc::error::TryRecvError::Disconnected
+                                if deadline_stdin.is_some() =>
+                            {
+                                // NOTE: Still enforcing the deadline, until the job ends.
+                                std::thread::sleep(std::time::Duration::from_millis(
+                                    STDIN_THREAD_SLEEP_DURATION_MS,
+                                ));
+                            }
+                            tokio::sync::mpsc::error::TryRecvError::Disconnected => {
+                                job_log!(
+                                    info,
+                                    LOG_TARGET_STDIN,
+                                    id_stdin,
+                                    "Breaking out of STDIN thread because channel closed..."
+                                );
+                                break;
+                            }
+                        },
+                        #[cfg(not(any(feature = "tokio", feature = "async-channel")))]
+                        Err(e) => match e {
+                            std::sync::mpsc::TryRecvError::Empty => {
+                                job_log!(
+                                    trace,
+                                    LOG_TARGET_STDIN,
+                                    id_stdin,
+                                    "Channel empty. Sleeping for {} milliseconds...",
+                                    STDIN_THREAD_SLEEP_DURATION_MS
+                                );
+                                std::thread::sleep(std::time::Duration::from_millis(
+                                    STDIN_THREAD_SLEEP_DURATION_MS,
+                                ));
+                            }
+                            std::sync::mpsc::TryRecvError::Disconnected
+                                if deadline_stdin.is_some() =>
+                            {
+                                // NOTE: Still enforcing the deadline, until the job ends.
+                                std::thread::sleep(std::time::Duration::from_millis(
+                                    STDIN_THREAD_SLEEP_DURATION_MS,
+                                ));
+                            }
+                            std::sync::mpsc::TryRecvError::Disconnected => {
+                                job_log!(
+                                    info,
+                                    LOG_TARGET_STDIN,
+                                    id_stdin,
+                                    "Breaking out of STDIN thread because channel closed..."
+                                );
+                                break;
+                            }
+                        },
+                        #[cfg(feature = "async-channel")]
+                        Err(e) => match e {
+                            async_channel::TryRecvError::Empty => {
+                                job_log!(
+                                    trace,
+                                    LOG_TARGET_STDIN,
+                                    id_stdin,
+                                    "Channel empty. Sleeping for {} milliseconds...",
+                                    STDIN_THREAD_SLEEP_DURATION_MS
+                                );
+                                std::thread::sleep(std::time::Duration::from_millis(
+                                    STDIN_THREAD_SLEEP_DURATION_MS,
+                                ));
+                            }
+                            async_channel::TryRecvError::Closed if deadline_stdin.is_some() => {
+                                // NOTE: Still enforcing the deadline, until the job ends.
+                                std::thread::sleep(std::time::Duration::from_millis(
+                                    STDIN_THREAD_SLEEP_DURATION_MS,
+                                ));
+                            }
+                            async_channel::TryRecvError::Closed => {
+                                job_log!(
+                                    info,
+                                    LOG_TARGET_STDIN,
+                                    id_stdin,
+                                    "Breaking out of STDIN thread because channel closed..."
+                                );
+                                break;
+                            }
+                        },
+                    }
+
+                    job_log!(
+                        trace,
+                        LOG_TARGET_STDIN,
+                        id_stdin,
+                        "Trying to acquire 'job ended' mutex to see if the job has completed..."
+                    );
+                    let job_ended = match job_ended_stdin.lock() {
+                        Err(e) => {
+                            job_log!(
+                                error,
+                                LOG_TARGET_STDIN,
+                                id_stdin,
+                                "Failed to acquire 'job ended' mutex: {:?}",
+                                e
+                            );
+                            drop(e);
+                            tx_stdin.fail(
+                                Error::Internal("'job ended' mutex poisoned"),
+                                &denied_stdin,
+                                &outcome_claimed_stdin,
+                            );
+                            job_ended_stdin.lock().unwrap_or_else(|e| e.into_inner())
+                        }
+                        Ok(m) => {
+                            job_log!(
+                                trace,
+                                LOG_TARGET_STDIN,
+                                id_stdin,
+                                "Successfully acquired 'job ended' mutex."
+                            );
+                            m
+                        }
+                    };
+                    if *job_ended {
+                        job_log!(
+                            info,
+                            LOG_TARGET_STDIN,
+                            id_stdin,
+                            "Job has ended, so breaking out of 'read loop'..."
+                        );
+                        break;
+                    } else {
+                        job_log!(trace, LOG_TARGET_STDIN, id_stdin, "Job has not ended yet.");
+                    }
+                }
+
+                // NOTE: Simulates a STDIN thread that is stuck (e.g. on a pipe that is kept
+                // open), for the main thread's watchdog to be tested.
+                #[cfg(test)]
+                if let Some(stall) = stdin_thread_stall {
+                    std::thread::sleep(stall);
+                }
+
+                job_log!(info, LOG_TARGET_STDIN, id_stdin, "Exiting STDIN thread...");
+            }
+        });
+
+        // NOTE: The child process' `stdout` and `stderr` are always drained concurrently,
+        // each by its own thread (the STDOUT thread being spawned first), so that FFmpeg can
+        // never block on a full pipe while the other one is being processed: the STDOUT thread
+        // only waits for the STDERR thread's report once `stdout` has been read to end, and
+        // neither of them ever blocks on sending a message (see `Outbox::bounded`). Any other
+        // way of consuming the output (e.g. streaming it) must keep that guarantee. When FFmpeg
+        // writes the animated GIF to a file (see `Settings::output_path`), `stdout` carries
+        // nothing, so the STDOUT thread buffers nothing, and validates the file instead. When
+        // the animated GIF is streamed to a writer (see `Converter::convert_to_writer`), the
+        // STDOUT thread buffers nothing either, and only blocks on the writer itself.
+        let tx_stdout = self.sender(LOG_TARGET_STDOUT);
+        let interpolator_stdout = std::sync::Arc::clone(&interpolator);
+        let stdout_bytes_stdout = std::sync::Arc::clone(&stdout_bytes);
+        let job_cancelled_stdout = std::sync::Arc::clone(&self.job_cancelled);
+        let job_ended_stdout = std::sync::Arc::clone(&self.job_ended);
+        let outcome_claimed_stdout = std::sync::Arc::clone(&outcome_claimed);
+        let memory_budget_stdout = self.memory_budget.clone();
+        let output_path_stdout = output_path.as_ref().map(|o| o.path().to_path_buf());
+        let mut output_writer_stdout = self.output_writer.take();
+        let denied_stdout = std::sync::Arc::clone(&denied);
+        let id_stdout = self.tag();
+        guard.spawn(ThreadKind::Stdout, move || {
+            job_log!(info, LOG_TARGET_STDOUT, id_stdout, "Entered STDOUT thread.");
+            let _lifecycle = tx_stdout.lifecycle(ThreadKind::Stdout);
+
+            let mut buf = OutputBuffer::new(memory_budget_stdout);
+            job_log!(
+                info,
+                LOG_TARGET_STDOUT,
+                id_stdout,
+                "Waiting to read all STDOUT bytes into buffer..."
+            );
+            // NOTE: Not reporting the number of bytes anymore once the job has been cancelled.
+            let report_output_bytes = |n: usize| {
+                if *job_cancelled_stdout
+                    .lock()
+                    .unwrap_or_else(|e| e.into_inner())
+                {
+                    return;
+                }
+                job_log!(
+                    trace,
+                    LOG_TARGET_STDOUT,
+                    id_stdout,
+                    "Sending number of bytes read so far down channel: {}",
+                    n
+                );
+                // NOTE: Once the job is shut down, the child process gets killed, so that
+                // the output ends (and no more bytes are reported, since it is cancelled).
+                tx_stdout.send_or_shutdown(Message::OutputBytes(n as u64));
+            };
+            let report_spill = || {
+                job_log!(
+                    info,
+                    LOG_TARGET_STDOUT,
+                    id_stdout,
+                    "Output would exceed memory budget, so spilled over to disk."
+                );
+                tx_stdout.send_or_shutdown(Message::Warning(Warning::MemoryBudgetSpill {
+                    measure: BudgetMeasure::StdoutSpilledToDisk,
+                }));
+            };
+            // NOTE: Same as `report_output_bytes`, when streaming the output to a writer.
+            let report_bytes_written = |n: u64| {
+                if *job_cancelled_stdout
+                    .lock()
+                    .unwrap_or_else(|e| e.into_inner())
+                {
+                    return;
+                }
+                job_log!(
+                    trace,
+                    LOG_TARGET_STDOUT,
+                    id_stdout,
+                    "Sending number of bytes written so far down channel: {}",
+                    n
+                );
+                tx_stdout.send_or_shutdown(Message::BytesWritten(n));
+            };
+            let is_cancelled = || {
+                *job_cancelled_stdout
+                    .lock()
+                    .unwrap_or_else(|e| e.into_inner())
+            };
+            // Fails the job when the writer fails, just like the STDERR thread does.
+            let write_failed = |e: std::io::Error| {
+                job_log!(
+                    warn,
+                    LOG_TARGET_STDOUT,
+                    id_stdout,
+                    "Failed to write output ({:?}), so failing job...",
+                    e
+                );
+                // NOTE: So that the rest of the output is thrown away, just like when cancelled.
+                *job_cancelled_stdout
+                    .lock()
+                    .unwrap_or_else(|e| e.into_inner()) = true;
+                denied_stdout.store(true, std::sync::atomic::Ordering::SeqCst);
+                if !outcome_claimed_stdout.swap(true, std::sync::atomic::Ordering::SeqCst) {
+                    tx_stdout.send_or_shutdown(Message::Error(Error::OutputWrite(
+                        std::sync::Arc::new(e),
+                    )));
+                }
+            };
+            // NOTE: The output is read back from disk (if spilled over) once complete, unless
+            // streamed to a writer, in which case nothing is kept.
+            let read = match output_writer_stdout.as_mut() {
+                Some(writer) => copy_counting(
+                    &mut stdout,
+                    writer,
+                    &stdout_bytes_stdout,
+                    Duration::from_millis(OUTPUT_BYTES_INTERVAL_MS),
+                    report_bytes_written,
+                    is_cancelled,
+                    write_failed,
+                )
+                .map(|_| Vec::new())
+                .map_err(|e| Error::ChildProcess(std::sync::Arc::new(e))),
+                None => read_to_end_counting(
+                    &mut stdout,
+                    &mut buf,
+                    &stdout_bytes_stdout,
+                    Duration::from_millis(OUTPUT_BYTES_INTERVAL_MS),
+                    report_output_bytes,
+                    report_spill,
+                )
+                .and_then(|_| buf.into_bytes()),
+            };
+            match read {
+                Err(error) => {
+                    job_log!(
+                        error,
+                        LOG_TARGET_STDOUT,
+                        id_stdout,
+                        "Failed to read to end, so failing job: {:?}",
+                        error
+                    );
+                    // NOTE: Just like when the writer fails (see `write_failed`).
+                    *job_cancelled_stdout
+                        .lock()
+                        .unwrap_or_else(|e| e.into_inner()) = true;
+                    denied_stdout.store(true, std::sync::atomic::Ordering::SeqCst);
+                    if !outcome_claimed_stdout.swap(true, std::sync::atomic::Ordering::SeqCst) {
+                        tx_stdout.send_or_shutdown(Message::Error(error));
+                    }
+                }
+                Ok(buf) => {
+                    // NOTE: Nothing is kept when the output is streamed to a writer.
+                    job_log!(
+                        info,
+                        LOG_TARGET_STDOUT,
+                        id_stdout,
+                        "Successfully read to end (size: {}).",
+                        stdout_bytes_stdout.load(std::sync::atomic::Ordering::SeqCst)
+                    );
+                    job_log!(
+                        debug,
+                        LOG_TARGET_STDOUT,
+                        id_stdout,
+                        "Waiting for STDERR thread to be done..."
+                    );
+                    let stderr_report = stderr_report_rx.recv().ok();
+                    job_log!(
+                        debug,
+                        LOG_TARGET_STDOUT,
+                        id_stdout,
+                        "Waiting for child process' exit code..."
+                    );
+                    let exit_code = exit_code_rx.recv().ok().flatten().filter(|code| *code > 0);
+                    interpolator_stdout
+                        .lock()
+                        .unwrap_or_else(|e| e.into_inner())
+                        .finish();
+                    job_log!(
+                        trace,
+                        LOG_TARGET_STDOUT,
+                        id_stdout,
+                        "Logging full buffer:\n{:?}",
+                        buf
+                    );
+
+                    job_log!(debug, LOG_TARGET_STDOUT, id_stdout, "Trying to acquire job cancellation mutex to check whether job has been cancelled, to avoid sending bytes down channel it case it has...");
+                    let job_cancelled = {
+                        let job_cancelled = match job_cancelled_stdout.lock() {
+                            Ok(m) => {
+                                job_log!(
+                                    debug,
+                                    LOG_TARGET_STDOUT,
+                                    id_stdout,
+                                    "Successfully acquired job cancellation mutex."
+                                );
+                                m
+                            }
+                            Err(e) => {
+                                job_log!(
+                                    error,
+                                    LOG_TARGET_STDOUT,
+                                    id_stdout,
+                                    "Failed to acquire job cancellation mutex: {:?}",
+                                    e
+                                );
+                                drop(e);
+                                tx_stdout.fail(
+                                    Error::Internal("job cancellation mutex poisoned"),
+                                    &denied_stdout,
+                                    &outcome_claimed_stdout,
+                                );
+                                job_cancelled_stdout
+                                    .lock()
+                                    .unwrap_or_else(|e| e.into_inner())
+                            }
+                        };
+                        *job_cancelled
+                    };
+                    // NOTE: A job cancelled right before this point has already been claimed
+                    // by the STDIN thread, which might not have marked it as cancelled yet.
+                    let claimed =
+                        !outcome_claimed_stdout.swap(true, std::sync::atomic::Ordering::SeqCst);
+
+                    if claimed && !job_cancelled {
+                        job_log!(
+                            debug,
+                            LOG_TARGET_STDOUT,
+                            id_stdout,
+                            "Job has not been cancelled, so validating buffer (exit code: {:?})...",
+                            exit_code
+                        );
+                        let anomaly = stderr_report.as_ref().and_then(|r| r.anomalies.detect());
+                        // NOTE: A suspect output only fails the job with `Settings::strict_parsing`.
+                        // NOTE: When written directly to a file, the output is not on `stdout`,
+                        // and when streamed to a writer, it is no longer in memory.
+                        let validated = match (output_path_stdout.as_deref(), &output_writer_stdout)
+                        {
+                            (Some(path), _) => output_path::validate(path, output_format),
+                            (None, Some(writer)) => writer.validate(output_format),
+                            (None, None) => validate_output(&buf, output_format),
+                        };
+                        let outcome = resolve_outcome(validated, exit_code, stderr_report).and_then(
+                            |dirty_exit| match anomaly {
+                                Some(reason) if strict_parsing => {
+                                    Err(Error::SuspectOutput { reason })
+                                }
+                                _ => Ok(dirty_exit),
+                            },
+                        );
+                        match outcome {
+                            Err(e) => {
+                                job_log!(
+                                    warn,
+                                    LOG_TARGET_STDOUT,
+                                    id_stdout,
+                                    "Output rejected ({}), so sending error message down channel.",
+                                    e
+                                );
+                                if tx_stdout.send_or_shutdown(Message::Error(e)) {
+                                    job_log!(
+                                        debug,
+                                        LOG_TARGET_STDOUT,
+                                        id_stdout,
+                                        "Successfully sent error message down channel."
+                                    );
+                                }
+                            }
+                            Ok(dirty_exit) => {
+                                let buf = interpolate_frame_delays(buf, frame_rate);
+                                match dirty_exit {
+                                    Some(warning) => {
+                                        job_log!(warn, LOG_TARGET_STDOUT, id_stdout, "Valid output found despite nonzero exit code, so sending warning down channel: {:?}", warning);
+                                        tx_stdout.send_or_shutdown(Message::Warning(warning));
+                                    }
+                                    // NOTE: An output that came with a nonzero exit code (or that
+                                    // is suspect) is not cached.
+                                    None if anomaly.is_none() => {
+                                        if let Some((cache, key)) = &cache_entry {
+                                            if let Err(e) = cache.store(*key, &buf) {
+                                                job_log!(
+                                                    warn,
+                                                    LOG_TARGET_STDOUT,
+                                                    id_stdout,
+                                                    "Failed to store output in cache: {:?}",
+                                                    e
+                                                );
+                                            }
+                                        }
+                                    }
+                                    None => {}
+                                }
+                                if let Some(reason) = anomaly {
+                                    job_log!(
+                                        warn,
+                                        LOG_TARGET_STDOUT,
+                                        id_stdout,
+                                        "Anomaly found in stats lines ({:?}), so sending warning down channel.",
+                                        reason
+                                    );
+                                    tx_stdout.send_or_shutdown(Message::Warning(
+                                        Warning::SuspectOutput { reason },
+                                    ));
+                                }
+                                let output_len =
+                                    match (output_path_stdout.as_deref(), &output_writer_stdout) {
+                                        (Some(path), _) => {
+                                            std::fs::metadata(path).map_or(0, |m| m.len())
+                                        }
+                                        (None, Some(writer)) => writer.written(),
+                                        (None, None) => buf.len() as u64,
+                                    };
+                                if let Some(warning) =
+                                    output_size_warning(output_size_threshold, output_len)
+                                {
+                                    job_log!(
+                                        info,
+                                        LOG_TARGET_STDOUT,
+                                        id_stdout,
+                                        "Output larger than input, so sending warning down channel: {:?}",
+                                        warning
+                                    );
+                                    tx_stdout.send_or_shutdown(Message::Warning(warning));
+                                }
+                                if let Some(message) = frame_map_message(frame_map, &buf) {
+                                    tx_stdout.send_or_shutdown(message);
+                                }
+                                let delivered = match (output_path_stdout, &output_writer_stdout) {
+                                    (Some(path), _) => {
+                                        tx_stdout.send_or_shutdown(Message::SuccessFile(path))
+                                    }
+                                    (None, Some(writer)) => tx_stdout
+                                        .send_or_shutdown(Message::SuccessWritten(writer.written())),
+                                    (None, None) => tx_stdout.deliver(output_file.as_ref(), buf),
+                                };
+                                if delivered {
+                                    job_log!(
+                                        debug,
+                                        LOG_TARGET_STDOUT,
+                                        id_stdout,
+                                        "Successfully sent STDOUT data down channel."
+                                    );
+                                }
+                            }
+                        }
+                    } else {
+                        job_log!(
+                            warn,
+                            LOG_TARGET_STDOUT,
+                            id_stdout,
+                            "Job has been marked as cancelled, so not sending data down channel."
+                        );
+                        if let Some(code) = exit_code {
+                            job_log!(
+                                debug,
+                                LOG_TARGET_STDOUT,
+                                id_stdout,
+                                "Trying to send exit code error message down channel..."
+                            );
+                            tx_stdout.send_or_shutdown(Message::Error(Error::ExitCode(code)));
+                        }
+                    }
+                }
+            }
+
+            job_log!(
+                debug,
+                LOG_TARGET_STDOUT,
+                id_stdout,
+                "Trying to acquire 'job ended' mutex to set it to 'true'..."
+            );
+            let mut job_ended = match job_ended_stdout.lock() {
+                Err(e) => {
+                    job_log!(
+                        error,
+                        LOG_TARGET_STDOUT,
+                        id_stdout,
+                        "Failed to acquire 'job ended' mutex to set it to 'true': {:?}",
+                        e
+                    );
+                    drop(e);
+                    tx_stdout.fail(
+                        Error::Internal("'job ended' mutex poisoned"),
+                        &denied_stdout,
+                        &outcome_claimed_stdout,
+                    );
+                    job_ended_stdout.lock().unwrap_or_else(|e| e.into_inner())
+                }
+                Ok(m) => {
+                    job_log!(
+                        debug,
+                        LOG_TARGET_STDOUT,
+                        id_stdout,
+                        "Successfully acquired 'job ended' mutex and set it to 'true'."
+                    );
+                    m
+                }
+            };
+            *job_ended = true;
+
+            job_log!(
+                info,
+                LOG_TARGET_STDOUT,
+                id_stdout,
+                "Exiting STDOUT thread..."
+            );
+        });
+
+        let tx_stderr = self.sender(LOG_TARGET_STDERR);
+        let interpolator_stderr = std::sync::Arc::clone(&interpolator);
+        let id_stderr = self.tag();
+        let job_cancelled_stderr = std::sync::Arc::clone(&self.job_cancelled);
+        let denied_stderr = std::sync::Arc::clone(&denied);
+        let outcome_claimed_stderr = std::sync::Arc::clone(&outcome_claimed);
+        let memory_budget_stderr = self.memory_budget.clone();
+        // NOTE: With the other policy, the job is run by `Converter::convert_with_fallback`.
+        let hwaccel_stderr = settings
+            .hwaccel
+            .filter(|_| settings.hwaccel_fallback == HwAccelFallback::Fail);
+        // NOTE: FFmpeg's `time` values are negative until the clip's start (see `SeekMode`).
+        let leads_in_stderr = settings.clip.is_some() && settings.seek_mode.leads_in();
+        // NOTE: Ignored along with the generated graph (see `Settings::custom_filter_complex`).
+        let subtitles_stderr = settings
+            .subtitles
+            .clone()
+            .filter(|_| settings.custom_filter_complex.is_none());
+        let video_stream_index_stderr = settings.video_stream_index;
+        let stdout_bytes_stderr = std::sync::Arc::clone(&stdout_bytes);
+        guard.spawn(ThreadKind::Stderr, move || {
+            job_log!(info, LOG_TARGET_STDERR, id_stderr, "Entered STDERR thread.");
+            let _lifecycle = tx_stderr.lifecycle(ThreadKind::Stderr);
+            let mut remapper = ProgressRemapper::new(progress_curve);
+            // Fails the job with `error`, e.g. when a line matches one of the deny patterns.
+            let fail =
+                |error: Error| tx_stderr.fail(error, &denied_stderr, &outcome_claimed_stderr);
+            // Fails the job when a line matches one of the deny patterns.
+            let deny = |(pattern, line): (String, String)| {
+                job_log!(
+                    warn,
+                    LOG_TARGET_STDERR,
+                    id_stderr,
+                    "Line {:?} matches deny pattern {:?}, so failing job...",
+                    line,
+                    pattern
+                );
+                fail(Error::DeniedWarning { pattern, line });
+            };
+            // Fails the job when its output could not be parsed (see `Settings::strict_parsing`).
+            let parse_failure = |error: Error| {
+                job_log!(
+                    warn,
+                    LOG_TARGET_STDERR,
+                    id_stderr,
+                    "Failed to parse output in strict mode ({:?}), so failing job...",
+                    error
+                );
+                fail(error);
+            };
+
+            use std::io::Read;
+
+            let id_stderr_string = id_stderr.to_string();
+            // NOTE: The duration relative to which the progress is computed, which may be
+            // replaced by a more reliable one along the way (see the `duration_source` module).
+            let mut duration: Option<Duration> = None;
+            // Adjusts the video's duration `d` found by `source`, records it, and sends it down
+            // the channel, returning `false` if the channel is closed.
+            let establish_duration = |duration: &mut Option<Duration>,
+                                      report: &mut StderrReport,
+                                      source: DurationSource,
+                                      d: Duration| {
+                job_log!(
+                    info,
+                    LOG_TARGET_STDERR,
+                    id_stderr,
+                    "Video duration found ({:?}): {:?}",
+                    source,
+                    d
+                );
+                let adjusted = duration_adjustments.apply(d);
+                // NOTE: An estimate is too rough to tell that the clip is out of range.
+                if source != DurationSource::BitrateEstimate {
+                    report.clip_out_of_range = adjusted.clip_out_of_range;
+                }
+                if duration.replace(adjusted.progress).is_none() {
+                    interpolator_stderr
+                        .lock()
+                        .unwrap_or_else(|e| e.into_inner())
+                        .observe(clock.elapsed(), 0.0);
+                }
+                tx_stderr.tx.record_duration_source(source);
+                tx_stderr.send_or_shutdown(Message::VideoDuration(adjusted.reported))
+            };
+
+            // NOTE: The output is processed line by line, and only the last few lines are
+            // kept (see `StderrReport`), so that the memory used by this thread does not grow
+            // with the total volume of the output (see the `stderr_lines` module).
+            let mut assembler = LineAssembler::default();
+            let mut report = StderrReport {
+                subtitles: subtitles_stderr,
+                video_stream_index: video_stream_index_stderr,
+                memory_budget: memory_budget_stderr,
+                ..StderrReport::default()
+            };
+            if let Some(d) = probed_duration {
+                duration_tracker.offer(DurationSource::Probe, d);
+                if !establish_duration(&mut duration, &mut report, DurationSource::Probe, d) {
+                    return;
+                }
+            }
+            let mut stream_mapping = StreamMappingParser::default();
+            let mut output_stream = OutputStreamParser::default();
+            let mut hardware_failure_reported = false;
+            let mut banner_checked = !expects_banner;
+            let mut buffer = vec![0u8; 1000];
+
+            job_log!(
+                info,
+                LOG_TARGET_STDERR,
+                id_stderr,
+                "Entering STDERR read loop..."
+            );
+            'read: loop {
+                match stderr.read(&mut buffer) {
+                    Ok(n) => {
+                        job_log!(debug, LOG_TARGET_STDERR, id_stderr, "{} bytes read.", n);
+
+                        job_log!(debug, LOG_TARGET_STDERR, id_stderr, "Trying to acquire 'job cancelled' mutex to make sure job has not been cancelled...");
+                        let job_cancelled = {
+                            let job_cancelled = match job_cancelled_stderr.lock() {
+                                Ok(m) => {
+                                    job_log!(
+                                        debug,
+                                        LOG_TARGET_STDERR,
+                                        id_stderr,
+                                        "Successfully acquired 'job cancelled' mutex."
+                                    );
+                                    m
+                                }
+                                Err(e) => {
+                                    job_log!(
+                                        error,
+                                        LOG_TARGET_STDERR,
+                                        id_stderr,
+                                        "Failed to acquire 'job cancelled' mutex: {:?}",
+                                        e
+                                    );
+                                    drop(e);
+                                    fail(Error::Internal("job cancellation mutex poisoned"));
+                                    job_cancelled_stderr
+                                        .lock()
+                                        .unwrap_or_else(|e| e.into_inner())
+                                }
+                            };
+                            *job_cancelled
+                        };
+                        if job_cancelled {
+                            job_log!(
+                                info,
+                                LOG_TARGET_STDERR,
+                                id_stderr,
+                                "Job has been cancelled, so breaking out of loop..."
+                            );
+                            break;
+                        } else {
+                            job_log!(
+                                debug,
+                                LOG_TARGET_STDERR,
+                                id_stderr,
+                                "Job has not been cancelled, so carrying on with output parsing..."
+                            );
+                        }
+
+                        let lines = if n > 0 {
+                            assembler.push(&buffer[..n])
+                        } else {
+                            job_log!(
+                                info,
+                                LOG_TARGET_STDERR,
+                                id_stderr,
+                                "No more data to read, so processing last line (if any)..."
+                            );
+                            assembler.finish().into_iter().collect()
+                        };
+
+                        for line in lines {
+                            job_log!(
+                                trace,
+                                LOG_TARGET_STDERR,
+                                id_stderr,
+                                "Logging line (truncated: {}):\n{}",
+                                line.truncated,
+                                line.text
+                            );
+                            if let Some(hit) = deny_list.check(&line.text) {
+                                deny(hit);
+                                break 'read;
+                            }
+                            if report.push(&line) {
+                                job_log!(
+                                    info,
+                                    LOG_TARGET_STDERR,
+                                    id_stderr,
+                                    "Dropped lines to keep within memory budget."
+                                );
+                                tx_stderr.send_or_shutdown(Message::Warning(
+                                    Warning::MemoryBudgetSpill {
+                                        measure: BudgetMeasure::StderrTruncated,
+                                    },
+                                ));
+                            }
+                            if !banner_checked {
+                                banner_checked = true;
+                                if !is_banner(&line.text) {
+                                    job_log!(
+                                        warn,
+                                        LOG_TARGET_STDERR,
+                                        id_stderr,
+                                        "First line is not FFmpeg's banner, so FFmpeg may be run through a wrapper script: {:?}",
+                                        line.text
+                                    );
+                                    if !tx_stderr.send_or_shutdown(Message::Warning(
+                                        Warning::MissingBanner {
+                                            first_line: line.text.clone(),
+                                        },
+                                    )) {
+                                        break 'read;
+                                    }
+                                }
+                            }
+
+                            if line.truncated {
+                                // NOTE: A token could have been cut in the middle (e.g. `time=00:00:04.9`
+                                // instead of `time=00:00:04.95`), so not trying to parse anything.
+                                job_log!(
+                                    debug,
+                                    LOG_TARGET_STDERR,
+                                    id_stderr,
+                                    "Line truncated to {} bytes, so not parsing it.",
+                                    line.text.len()
+                                );
+                                continue;
+                            }
+
+                            if let Some((source, d)) =
+                                duration_tracker.push(&line.text, Some(&id_stderr_string))
+                            {
+                                if let Some(provenance) = provenance.as_mut() {
+                                    let event = ParsedEvent::VideoDuration {
+                                        duration: duration_adjustments.apply(d).reported,
+                                        source,
+                                    };
+                                    provenance.record(&id_stderr, event, &line.text);
+                                }
+                                if !establish_duration(&mut duration, &mut report, source, d) {
+                                    break 'read;
+                                }
+                            }
+                            if let Err(e) = parse_checker.check_line(&line.text, duration.is_some())
+                            {
+                                parse_failure(e);
+                                break 'read;
+                            }
+
+                            if let Some(selection) = stream_mapping.push(&line.text) {
+                                job_log!(
+                                    info,
+                                    LOG_TARGET_STDERR,
+                                    id_stderr,
+                                    "Codec selection found: {:?}",
+                                    selection
+                                );
+                                let hw_accelerated = selection.hw_accelerated();
+                                if !tx_stderr.send_or_shutdown(Message::CodecSelection {
+                                    decoder: selection.decoder,
+                                    encoder: selection.encoder,
+                                    hw_accelerated,
+                                }) {
+                                    break 'read;
+                                }
+                            }
+                            if let Some((width, height)) = output_stream.push(&line.text) {
+                                job_log!(
+                                    info,
+                                    LOG_TARGET_STDERR,
+                                    id_stderr,
+                                    "Output dimensions found: {}x{}",
+                                    width,
+                                    height
+                                );
+                                if !tx_stderr
+                                    .send_or_shutdown(Message::OutputDimensions { width, height })
+                                {
+                                    break 'read;
+                                }
+                            }
+                            if !hardware_failure_reported
+                                && codec_selection::is_hardware_failure(&line.text)
+                            {
+                                if let Some(hwaccel) = hwaccel_stderr {
+                                    job_log!(
+                                        warn,
+                                        LOG_TARGET_STDERR,
+                                        id_stderr,
+                                        "Hardware acceleration ({}) failed, so failing job: {:?}",
+                                        hwaccel,
+                                        line.text
+                                    );
+                                    fail(Error::HardwareAcceleration {
+                                        hwaccel,
+                                        line: line.text.clone(),
+                                    });
+                                    break 'read;
+                                }
+                                job_log!(
+                                    warn,
+                                    LOG_TARGET_STDERR,
+                                    id_stderr,
+                                    "Hardware acceleration failed: {:?}",
+                                    line.text
+                                );
+                                hardware_failure_reported = true;
+                                if !tx_stderr.send_or_shutdown(Message::Warning(
+                                    Warning::HardwareAccelerationFailed {
+                                        line: line.text.clone(),
+                                    },
+                                )) {
+                                    break 'read;
+                                }
+                            }
+
+                            if is_stats_line(&line.text) {
+                                job_log!(debug, LOG_TARGET_STDERR, id_stderr, "Line starts with 'frame=', so trying to extra frame time from it...");
+                                let time =
+                                    try_extract_frame_time(&line.text, Some(&id_stderr_string))
+                                        .or_else(|| {
+                                            (leads_in_stderr
+                                                && has_negative_frame_time(&line.text))
+                                            .then_some(Duration::ZERO)
+                                        });
+                                if let Err(e) =
+                                    parse_checker.check_frame_time(&line.text, time.is_some())
+                                {
+                                    parse_failure(e);
+                                    break 'read;
+                                }
+                                if let Some(time) = time {
+                                    job_log!(
+                                        debug,
+                                        LOG_TARGET_STDERR,
+                                        id_stderr,
+                                        "Successfully extracted 'time' from line: {:?}",
+                                        time
+                                    );
+                                    if let Some(duration) = duration {
+                                        let progress = remapper.remap(
+                                            progress_from_durations(duration, time),
+                                            stdout_bytes_stderr
+                                                .load(std::sync::atomic::Ordering::SeqCst)
+                                                > 0,
+                                        );
+                                        job_log!(
+                                            info,
+                                            LOG_TARGET_STDERR,
+                                            id_stderr,
+                                            "New progress calculated: {:.04}",
+                                            progress
+                                        );
+                                        // NOTE: Holding the lock while sending, so that the SMOOTHER thread's
+                                        // estimates are consistent with the order of the messages.
+                                        let mut interpolator = interpolator_stderr
+                                            .lock()
+                                            .unwrap_or_else(|e| e.into_inner());
+                                        interpolator.observe(clock.elapsed(), progress);
+                                        if let Some(provenance) = provenance.as_mut() {
+                                            provenance.record(
+                                                &id_stderr,
+                                                ParsedEvent::Progress(progress),
+                                                &line.text,
+                                            );
+                                        }
+                                        job_log!(debug, LOG_TARGET_STDERR, id_stderr, "Trying to send newly calculated progress down channel...");
+                                        if !tx_stderr.send_or_shutdown(Message::Progress(progress))
+                                        {
+                                            break 'read;
+                                        }
+                                        job_log!(debug, LOG_TARGET_STDERR, id_stderr, "Successfully sent newly calculated progress down channel.");
+                                    }
+                                } else {
+                                    // So this is possible if we input an invalid file (e.g. a png), in which case we will get something
+                                    // similar to this (i.e. a "frame=" without first a duration):
+                                    //     out#0/gif @ 0x7fe0a5714b00] Error writing trailer: Invalid argumentbitrate=  -0.0kbits/s speed=N/A
+                                    //         frame=    0 fps=0.0 q=0.0 Lsize=       0kB time=-577014:32:22.77 bitrate=  -0.0kbits/s speed=N/A
+
+                                    // NOTE: No need to panic here I think. We can just do nothing. If it
+                                    // was an invalid input, `stderr` will close and the loop will automatically
+                                    // break...
+                                    job_log!(warn, LOG_TARGET_STDERR, id_stderr, "NOTE: frame= received without duration parsed. This may have been caused by invalid input file type.");
+                                }
+                                // NOTE: Without a duration (e.g. with a non-seekable input), the
+                                // number of frames is all there is to tell that the job is moving.
+                                if duration.is_none() {
+                                    if let Some(frames) = try_extract_frame_count(&line.text) {
+                                        if !tx_stderr.send_or_shutdown(Message::FramesProcessed(
+                                            frames as u64,
+                                        )) {
+                                            break 'read;
+                                        }
+                                    }
+                                }
+                            }
+                        }
+
+                        if n == 0 {
+                            job_log!(
+                                info,
+                                LOG_TARGET_STDERR,
+                                id_stderr,
+                                "Breaking out of STDERR thread loop..."
+                            );
+                            break;
+                        }
+                    }
+                    Err(e) => {
+                        if let std::io::ErrorKind::WouldBlock = e.kind() {
+                        } else {
+                            job_log!(
+                                error,
+                                LOG_TARGET_STDERR,
+                                id_stderr,
+                                "Error reading STDERR: {:?}",
+                                e
+                            );
+                            fail(Error::ChildProcess(std::sync::Arc::new(e)));
+                            break;
+                        }
+                    }
+                }
+            }
+
+            if let Some(provenance) = provenance {
+                tx_stderr.tx.record_provenance(provenance.records());
+            }
+            // NOTE: The STDOUT thread might already be gone if it failed.
+            let _ = stderr_report_tx.send(report);
+
+            job_log!(
+                info,
+                LOG_TARGET_STDERR,
+                id_stderr,
+                "Exiting STDERR thread..."
+            );
+        });
+
+        if settings.smooth_progress {
+            let tx_smoother = self.sender(LOG_TARGET_SMOOTHER);
+            let interpolator_smoother = std::sync::Arc::clone(&interpolator);
+            let id_smoother = self.tag();
+            guard.spawn(ThreadKind::Smoother, move || {
+                job_log!(
+                    info,
+                    LOG_TARGET_SMOOTHER,
+                    id_smoother,
+                    "Entered SMOOTHER thread."
+                );
+                let _lifecycle = tx_smoother.lifecycle(ThreadKind::Smoother);
+                loop {
+                    std::thread::sleep(Duration::from_millis(SMOOTHER_THREAD_SLEEP_DURATION_MS));
+                    let mut interpolator = interpolator_smoother
+                        .lock()
+                        .unwrap_or_else(|e| e.into_inner());
+                    if interpolator.finished() {
+                        break;
+                    }
+                    if let Some(progress) = interpolator.estimate(clock.elapsed()) {
+                        job_log!(
+                            trace,
+                            LOG_TARGET_SMOOTHER,
+                            id_smoother,
+                            "Sending interpolated progress down channel: {:.04}",
+                            progress
+                        );
+                        if !tx_smoother.send_or_shutdown(Message::InterpolatedProgress(progress)) {
+                            break;
+                        }
+                    }
+                }
+                job_log!(
+                    info,
+                    LOG_TARGET_SMOOTHER,
+                    id_smoother,
+                    "Exiting SMOOTHER thread..."
+                );
+            });
+        }
+
+        let tx_child = self.sender(LOG_TARGET_CHILD);
+        let id_child = self.tag();
+        let kill_requested_child = std::sync::Arc::clone(&self.kill_requested);
+        let outbox_child = self.tx.clone();
+        guard.spawn_with_child(ThreadKind::Child, move |mut child| {
+            job_log!(
+                info,
+                LOG_TARGET_CHILD,
+                id_child,
+                "Entered CHILD process thread"
+            );
+            let _lifecycle = tx_child.lifecycle(ThreadKind::Child);
+
+            job_log!(
+                debug,
+                LOG_TARGET_CHILD,
+                id_child,
+                "Polling the child process instance until it exits..."
+            );
+            // NOTE: Polling instead of calling `wait`, so that the child process can be
+            // killed when the job is shut down (see `JobSender::send_or_shutdown`).
+            let status = loop {
+                match resource_usage::try_wait(&mut child) {
+                    Ok(Some(exited)) => break Ok(exited),
+                    Ok(None) if kill_requested_child.load(std::sync::atomic::Ordering::SeqCst) => {
+                        job_log!(
+                            info,
+                            LOG_TARGET_CHILD,
+                            id_child,
+                            "Job shut down, so killing child process..."
+                        );
+                        if let Err(e) = child.kill() {
+                            job_log!(
+                                warn,
+                                LOG_TARGET_CHILD,
+                                id_child,
+                                "Failed to kill child process: {:?}",
+                                e
+                            );
+                        }
+                        break resource_usage::wait(&mut child);
+                    }
+                    Ok(None) => {
+                        std::thread::sleep(Duration::from_millis(CHILD_THREAD_SLEEP_DURATION_MS))
+                    }
+                    Err(e) => break Err(e),
+                }
+            };
+            match status {
+                Ok((status, usage)) => {
+                    job_log!(
+                        info,
+                        LOG_TARGET_CHILD,
+                        id_child,
+                        "Child process completed with exit status: {:?} (exit code: {:?}, resource usage: {:?})",
+                        status,
+                        status.code(),
+                        usage
+                    );
+                    outbox_child.record_resource_usage(usage);
+                    tx_child.send_internal(InternalEvent::ChildReaped {
+                        code: status.code(),
+                    });
+                    // NOTE: The STDOUT thread decides whether a nonzero exit code fails the job.
+                    job_log!(
+                        debug,
+                        LOG_TARGET_CHILD,
+                        id_child,
+                        "Passing exit code to STDOUT thread..."
+                    );
+                    let _ = exit_code_tx.send(status.code());
+                }
+                Err(e) => {
+                    job_log!(
+                        warn,
+                        LOG_TARGET_CHILD,
+                        id_child,
+                        "Child process error: {:?}",
+                        e
+                    );
+                    job_log!(
+                        debug,
+                        LOG_TARGET_CHILD,
+                        id_child,
+                        "Trying to send child process error down channel..."
+                    );
+                    if tx_child.send_or_shutdown(Message::Error(Error::ChildProcess(
+                        std::sync::Arc::new(e),
+                    ))) {
+                        job_log!(
+                            debug,
+                            LOG_TARGET_CHILD,
+                            id_child,
+                            "Successfully sent child process error down channel."
+                        );
+                    }
+                }
+            }
+
+            job_log!(
+                info,
+                LOG_TARGET_CHILD,
+                id_child,
+                "Exiting CHILD process thread..."
+            );
+        });
+
+        #[cfg(test)]
+        if self.panic_at == Some(PanicPoint::AfterThreads) {
+            panic!("injected panic (after threads)");
+        }
+
+        job_log!(debug, LOG_TARGET_MAIN, self.tag(), "All threads spawned. Now trying to join them sequentially in the following order: child process, stderr, stdout, stdin...");
+
+        // NOTE: The WRITER thread is only spawned with in-memory input.
+        for kind in [
+            ThreadKind::Child,
+            ThreadKind::Stderr,
+            ThreadKind::Stdout,
+            ThreadKind::Writer,
+        ] {
+            self.join_thread(&mut guard, kind);
+        }
+        job_log!(
+            debug,
+            LOG_TARGET_MAIN,
+            self.tag(),
+            "Waiting for STDIN thread to exit..."
+        );
+        // NOTE: The STDIN thread exits shortly after the job has ended (which the STDOUT thread
+        // has marked by now), so if it hasn't after a grace period, it is stuck (e.g. on a pipe
+        // that is kept open), in which case it is left behind, rather than blocking the job forever.
+        let grace_period = Deadline::after(Duration::from_millis(STDIN_THREAD_GRACE_PERIOD_MS));
+        while !guard.is_finished(ThreadKind::Stdin) && !grace_period.expired() {
+            std::thread::sleep(Duration::from_millis(CHILD_THREAD_SLEEP_DURATION_MS));
+        }
+        if !guard.is_finished(ThreadKind::Stdin) {
+            job_log!(
+                error,
+                LOG_TARGET_MAIN,
+                self.tag(),
+                "STDIN thread still running {:?} after the other threads exited, so giving up on it.",
+                grace_period.timeout()
+            );
+            guard.abandon(ThreadKind::Stdin);
+            self.sender(LOG_TARGET_MAIN)
+                .send_internal(InternalEvent::ThreadAbandoned(ThreadKind::Stdin));
+            // NOTE: The STDIN thread may exit after `Message::Done`.
+            self.internal_events
+                .store(false, std::sync::atomic::Ordering::SeqCst);
+            *self.job_ended.lock().unwrap_or_else(|e| e.into_inner()) = true;
+            // NOTE: The STDIN thread only holds the lock while writing to the child process.
+            match stdin.try_lock() {
+                Ok(mut stdin) => drop(stdin.take()),
+                Err(e) => job_log!(
+                    warn,
+                    LOG_TARGET_MAIN,
+                    self.tag(),
+                    "Unable to close STDIN: {:?}",
+                    e
+                ),
+            }
+            self.send_or_shutdown(Message::Warning(Warning::StdinThreadTimeout));
+        } else {
+            self.join_thread(&mut guard, ThreadKind::Stdin);
+        }
+        // NOTE: Only spawned with the `smooth_progress` option.
+        self.join_thread(&mut guard, ThreadKind::Smoother);
+
+        // NOTE: FFmpeg has exited by now, so the other widths are complete (if the job
+        // succeeded), and their directory is removed either way.
+        if let Some(dir) = variant_dir.filter(|_| self.tx.succeeded()) {
+            self.send_variants(&dir, &additional_widths, frame_rate);
+        }
+        if let Some(output_path) = output_path.as_mut().filter(|_| self.tx.succeeded()) {
+            output_path.keep();
+        }
+
+        job_log!(
+            info,
+            LOG_TARGET_MAIN,
+            self.tag(),
+            "Trying to send 'summary' and 'done' messages down channel..."
+        );
+        self.finish(started);
+
+        job_log!(
+            info,
+            LOG_TARGET_MAIN,
+            self.tag(),
+            "End of 'convert' method reached."
+        );
+    }
+
+    /// Sends the animated GIFs generated at the other `widths` (see
+    /// [`Settings::additional_widths`]), each of which is attributed its own error, if any,
+    /// and whose frame delays are rewritten the same way as the main one's (see
+    /// [`Settings::interpolate_frame_delays`]).
+    fn send_variants(&self, dir: &VariantDir, widths: &[u16], frame_rate: Option<u16>) {
+        for width in widths.iter().copied() {
+            let message = match dir.read(width) {
+                Ok(bytes) => {
+                    let bytes = interpolate_frame_delays(bytes, frame_rate);
+                    job_log!(
+                        debug,
+                        LOG_TARGET_MAIN,
+                        self.tag(),
+                        "Animated GIF at width {} is {} bytes long.",
+                        width,
+                        bytes.len()
+                    );
+                    Message::SuccessVariant { width, bytes }
+                }
+                Err(e) => {
+                    job_log!(
+                        error,
+                        LOG_TARGET_MAIN,
+                        self.tag(),
+                        "Animated GIF at width {} is unusable: {:?}",
+                        width,
+                        e
+                    );
+                    Message::Error(Error::VariantOutput {
+                        width,
+                        error: Box::new(e),
+                    })
+                }
+            };
+            if !self.send_or_shutdown(message) {
+                return;
+            }
+        }
+    }
+    /// Extracts `count` small images (see [`StripSettings::new`]) at evenly spaced
+    /// timestamps of the video, using a single FFmpeg child process (after a short
+    /// one used to probe the video's duration). This is meant to be used before
+    /// [`Converter::convert`], e.g. to help users pick a trim range.
+    ///
+    /// Each image is sent to the application as soon as it is ready, using
+    /// [`Message::Thumbnail`], followed by the usual [`Message::Summary`] and
+    /// [`Message::Done`] messages. Just like the conversion job, the extraction
+    /// can be cancelled using [`Command::Cancel`], in which case no other
+    /// thumbnails are sent after [`Error::Cancelled`].
+    pub fn extract_thumbnail_strip(self, settings: StripSettings) {
+        let started = std::time::Instant::now();
+        job_metrics::record_started();
+
+        #[cfg(feature = "tokio")]
+        if !self.check_async_context(false) {
+            self.finish(started);
+            return;
+        }
+
+        if let Err(e) = settings.validate() {
+            job_log!(
+                error,
+                LOG_TARGET_MAIN,
+                self.tag(),
+                "Invalid settings: {:?}",
+                e
+            );
+            self.send_or_shutdown(Message::Error(Error::InvalidSettings(e)));
+            self.finish(started);
+            return;
+        }
+
+        let binary_path = match self.binary_path(&settings.ffmpeg_location) {
+            Ok(p) => p,
+            Err(e) => {
+                self.send_or_shutdown(Message::Error(e));
+                self.finish(started);
+                return;
+            }
+        };
+        let input = InputSource::Path(settings.video_path.clone());
+        let duration = match self.probe_duration(
+            &binary_path,
+            &[],
+            &input,
+            None,
+            Some(Settings::DEFAULT_AUXILIARY_TIMEOUT),
+            None,
+        ) {
+            Ok(Some(d)) => d,
+            Ok(None) => {
+                job_log!(
+                    error,
+                    LOG_TARGET_MAIN,
+                    self.tag(),
+                    "Unable to extract thumbnails without knowing the video's duration."
+                );
+                self.send_or_shutdown(Message::Error(Error::UnknownDuration));
+                self.finish(started);
+                return;
+            }
+            Err(e) => {
+                self.send_or_shutdown(Message::Error(e));
+                self.finish(started);
+                return;
+            }
+        };
+        self.send_or_shutdown(Message::VideoDuration(duration));
+
+        job_log!(
+            info,
+            LOG_TARGET_MAIN,
+            self.tag(),
+            "Extracting {} thumbnails...",
+            settings.count
+        );
+        let mut command = std::process::Command::new(&binary_path);
+        command.args(thumbnails::strip_args(&settings, duration));
+        let emitted = std::sync::Arc::new(std::sync::atomic::AtomicUsize::new(0));
+        let emitted_stdout = std::sync::Arc::clone(&emitted);
+        let splitter = std::sync::Arc::new(std::sync::Mutex::new(thumbnails::ImageSplitter::new(
+            settings.format,
+        )));
+        let splitter_stdout = std::sync::Arc::clone(&splitter);
+        let tx_stdout = self.sender(LOG_TARGET_STDOUT);
+        let job_cancelled_stdout = std::sync::Arc::clone(&self.job_cancelled);
+        let id_stdout = self.tag();
+        let count = settings.count;
+        let on_stdout = move |chunk: &[u8]| {
+            // NOTE: Holding the splitter's lock while sending (the main thread needs it to
+            // confirm the cancellation, after marking the job as cancelled), so that no
+            // thumbnail can be sent after the cancellation confirmation.
+            let mut splitter = splitter_stdout.lock().unwrap_or_else(|e| e.into_inner());
+            for bytes in splitter.push(chunk) {
+                if *job_cancelled_stdout
+                    .lock()
+                    .unwrap_or_else(|e| e.into_inner())
+                {
+                    job_log!(
+                        debug,
+                        LOG_TARGET_STDOUT,
+                        id_stdout,
+                        "Job has been cancelled, so not sending thumbnail down channel."
+                    );
+                    return;
+                }
+                let index = emitted_stdout.fetch_add(1, std::sync::atomic::Ordering::SeqCst);
+                let timestamp = thumbnails::thumbnail_timestamp(duration, count, index);
+                job_log!(
+                    debug,
+                    LOG_TARGET_STDOUT,
+                    id_stdout,
+                    "Trying to send thumbnail {} ({} bytes) down channel...",
+                    index,
+                    bytes.len()
+                );
+                if !tx_stdout.send_or_shutdown(Message::Thumbnail {
+                    index,
+                    timestamp,
+                    bytes,
+                }) {
+                    return;
+                }
+            }
+        };
+        let kill_requested = std::sync::Arc::clone(&self.kill_requested);
+        let should_cancel =
+            || self.cancel_requested() || kill_requested.load(std::sync::atomic::Ordering::SeqCst);
+        let result = run_auxiliary_streaming(command, None, should_cancel, on_stdout);
+        let emitted = emitted.load(std::sync::atomic::Ordering::SeqCst);
+        let pending = splitter.lock().unwrap_or_else(|e| e.into_inner()).pending();
+        if pending > 0 {
+            job_log!(
+                warn,
+                LOG_TARGET_MAIN,
+                self.tag(),
+                "{} bytes left over after the last complete thumbnail.",
+                pending
+            );
+        }
+        let error = match result {
+            Err(AuxiliaryError::Cancelled) => {
+                *self.job_cancelled.lock().unwrap_or_else(|e| e.into_inner()) = true;
+                Some(Error::Cancelled {
+                    progress_at_cancel: None,
+                    bytes_discarded: splitter.lock().unwrap_or_else(|e| e.into_inner()).pending(),
+                })
+            }
+            Err(AuxiliaryError::Spawn(e)) | Err(AuxiliaryError::Io(e)) => {
+                Some(Error::ChildProcess(std::sync::Arc::new(e)))
+            }
+            Err(AuxiliaryError::ExitCode(code)) => Some(Error::ExitCode(code.unwrap_or(-1))),
+            Err(AuxiliaryError::TimedOut(_)) => unreachable!("no timeout was given"),
+            Ok(output) => match output.status.code() {
+                Some(code) if code != 0 => Some(Error::ExitCode(code)),
+                None => Some(Error::ExitCode(-1)),
+                _ if emitted == 0 => Some(Error::EmptyStdout),
+                _ => None,
+            },
+        };
+        match error {
+            Some(e) => {
+                job_log!(
+                    warn,
+                    LOG_TARGET_MAIN,
+                    self.tag(),
+                    "Thumbnail extraction failed after {} thumbnails: {:?}",
+                    emitted,
+                    e
+                );
+                self.send_or_shutdown(Message::Error(e));
+            }
+            None => {
+                job_log!(
+                    info,
+                    LOG_TARGET_MAIN,
+                    self.tag(),
+                    "Successfully extracted {} thumbnails.",
+                    emitted
+                );
+            }
+        }
+        self.finish(started);
+    }
+}
+
+#[derive(Clone)]
+/// The handle through which the converter's threads send their messages, which shuts the
+/// job down (instead of panicking) once the application has dropped its end of the channel.
+struct JobSender {
+    tx: Outbox,
+    tag: JobTag,
+    /// The log target of the thread using the handle.
+    target: &'static str,
+    job_cancelled: std::sync::Arc<std::sync::Mutex<bool>>,
+    job_ended: std::sync::Arc<std::sync::Mutex<bool>>,
+    kill_requested: std::sync::Arc<std::sync::atomic::AtomicBool>,
+    memory_budget: Option<std::sync::Arc<MemoryBudget>>,
+    /// Whether [`Message::Internal`] is sent (see [`Settings::emit_internal_events`]).
+    internal_events: std::sync::Arc<std::sync::atomic::AtomicBool>,
+}
+
+/// Sends [`InternalEvent::ThreadStarted`] when created (by the thread of the given `kind`),
+/// and [`InternalEvent::ThreadExited`] when dropped, whether the thread returns or panics.
+struct ThreadLifecycle {
+    tx: JobSender,
+    kind: ThreadKind,
+}
+
+impl Drop for ThreadLifecycle {
+    fn drop(&mut self) {
+        let exit = match std::thread::panicking() {
+            true => ExitKind::Panicked,
+            false => ExitKind::Returned,
+        };
+        self.tx
+            .send_internal(InternalEvent::ThreadExited(self.kind, exit));
+    }
+}
+
+impl JobSender {
+    /// Sends `event` down the channel, if enabled (see [`Settings::emit_internal_events`]),
+    /// returning `false` if the job is being shut down.
+    fn send_internal(&self, event: InternalEvent) -> bool {
+        !self
+            .internal_events
+            .load(std::sync::atomic::Ordering::SeqCst)
+            || self.send_or_shutdown(Message::Internal(event))
+    }
+
+    /// Sends [`InternalEvent::ThreadStarted`], and returns the value that sends
+    /// [`InternalEvent::ThreadExited`] when dropped (i.e. when the thread exits).
+    fn lifecycle(&self, kind: ThreadKind) -> ThreadLifecycle {
+        self.send_internal(InternalEvent::ThreadStarted(kind));
+        ThreadLifecycle {
+            tx: self.clone(),
+            kind,
+        }
+    }
+
+    /// Sends `message` down the channel, returning `true` on success. A closed channel
+    /// means that nobody is listening anymore, so the job is marked as both cancelled and
+    /// ended (which makes the other threads stop sending messages, and exit), the CHILD
+    /// thread is asked to terminate the child process, and `false` is returned so that the
+    /// caller can break out of its loop.
+    ///
+    /// Progress messages are dropped (in which case `true` is returned) when they don't
+    /// make enough progress to fit within the job's memory budget, if any.
+    ///
+    /// NOTE: The job cancellation mutex must not be held by the caller.
+    fn send_or_shutdown(&self, message: Message) -> bool {
+        if let Some(budget) = self.memory_budget.as_ref() {
+            let admitted = match message {
+                Message::Progress(p) | Message::InterpolatedProgress(p) => budget.admit_progress(p),
+                Message::OutputBytes(_) | Message::BytesWritten(_) => budget.admit_output_bytes(),
+                _ => true,
+            };
+            if !admitted {
+                job_log!(
+                    trace,
+                    self.target,
+                    self.tag,
+                    "Dropping progress message to stay within memory budget: {:?}",
+                    message
+                );
+                return !budget.engage(BudgetMeasure::ProgressThinned)
+                    || self.send_or_shutdown(Message::Warning(Warning::MemoryBudgetSpill {
+                        measure: BudgetMeasure::ProgressThinned,
+                    }));
+            }
+        }
+        job_log!(
+            trace,
+            self.target,
+            self.tag,
+            "Trying to send message down channel: {:?}",
+            message
+        );
+        let Err(e) = self.tx.send(message) else {
+            return true;
+        };
+        job_log!(
+            warn,
+            self.target,
+            self.tag,
+            "Failed to send message down channel (receiver dropped), so shutting job down: {:?}",
+            e
+        );
+        *self.job_cancelled.lock().unwrap_or_else(|e| e.into_inner()) = true;
+        *self.job_ended.lock().unwrap_or_else(|e| e.into_inner()) = true;
+        self.kill_requested
+            .store(true, std::sync::atomic::Ordering::SeqCst);
+        false
+    }
+
+    /// Fails the job with `error`, unless its outcome is already settled (see `denied`, which
+    /// makes the STDIN thread stop the child process, and `outcome_claimed`).
+    ///
+    /// NOTE: The job cancellation mutex must not be held by the caller.
+    fn fail(
+        &self,
+        error: Error,
+        denied: &std::sync::atomic::AtomicBool,
+        outcome_claimed: &std::sync::atomic::AtomicBool,
+    ) {
+        // NOTE: Marking the job as cancelled first, so that the STDOUT thread
+        // does not send the output down the channel.
+        *self.job_cancelled.lock().unwrap_or_else(|e| e.into_inner()) = true;
+        denied.store(true, std::sync::atomic::Ordering::SeqCst);
+        if outcome_claimed.swap(true, std::sync::atomic::Ordering::SeqCst) {
+            job_log!(
+                info,
+                self.target,
+                self.tag,
+                "Job outcome already settled, so not sending {:?}.",
+                error
+            );
+            return;
+        }
+        self.send_or_shutdown(Message::Error(error));
+    }
+
+    /// The last progress value sent down the channel, if any.
+    fn last_progress(&self) -> Option<f64> {
+        self.tx.last_progress()
+    }
+
+    /// Sends the animated GIF down the channel as [`Message::Success`], once written to
+    /// the output file, if any (see [`Settings::output_file`]), in which case it is
+    /// preceded by [`Message::Saved`], or replaced by either [`Message::Skipped`] or
+    /// [`Error::OutputFile`]. Returns `false` if the job is being shut down.
+    fn deliver(&self, output_file: Option<&OutputFile>, buf: Vec<u8>) -> bool {
+        let Some(file) = output_file else {
+            return self.send_or_shutdown(Message::Success(buf));
+        };
+        match file.save(&buf) {
+            Ok(OutputTarget::Write(path)) => {
+                job_log!(debug, self.target, self.tag, "Output saved: {:?}", path);
+                self.send_or_shutdown(Message::Saved { path })
+                    && self.send_or_shutdown(Message::Success(buf))
+            }
+            Ok(OutputTarget::Skip(existing_path)) => {
+                job_log!(
+                    info,
+                    self.target,
+                    self.tag,
+                    "Output file appeared during job, so not overwriting it: {:?}",
+                    existing_path
+                );
+                self.send_or_shutdown(Message::Skipped { existing_path })
+            }
+            Err(e) => {
+                job_log!(
+                    error,
+                    self.target,
+                    self.tag,
+                    "Failed to save output: {:?}",
+                    e
+                );
+                self.send_or_shutdown(Message::Error(Error::OutputFile {
+                    path: file.path(),
+                    error: std::sync::Arc::new(e),
+                }))
+            }
+        }
+    }
+}
+
+/// The [`Warning::OutputLargerThanInput`] warning, if an output of `output_bytes` is
+/// larger than the input by more than the ratio given by `threshold`, i.e. an
+/// `(input_bytes, ratio)` pair (see [`Settings::output_size_warning_ratio`]).
+fn output_size_warning(threshold: Option<(u64, f64)>, output_bytes: u64) -> Option<Warning> {
+    let (input_bytes, ratio) = threshold?;
+    (output_bytes as f64 > input_bytes as f64 * ratio).then_some(Warning::OutputLargerThanInput {
+        input_bytes,
+        output_bytes,
+    })
+}
+
+/// The [`Message::FrameMap`] message for the animated GIF made of `buf`, if requested
+/// (see [`Settings::emit_frame_map`]).
+fn frame_map_message(frame_map: Option<FrameMap>, buf: &[u8]) -> Option<Message> {
+    let frame_map = frame_map?;
+    let frame_count = parse_gif_info(buf).ok()?.frame_count;
+    Some(Message::FrameMap(frame_map.timestamps(frame_count)))
+}
+
+/// Rewrites the delays of the frames of the animated GIF made of `buf` so that it plays
+/// at `frame_rate` frames per second exactly (see [`Settings::interpolate_frame_delays`]),
+/// if requested.
+fn interpolate_frame_delays(buf: Vec<u8>, frame_rate: Option<u16>) -> Vec<u8> {
+    let Some(fps) = frame_rate else {
+        return buf;
+    };
+    // NOTE: The output has already been validated, but is left untouched otherwise.
+    if parse_gif_info(&buf).is_err() {
+        return buf;
+    }
+    let mut gif = GifData::parse(buf).expect("validated by parse_gif_info");
+    // NOTE: A frame rate above `GifData::MAX_FRAME_RATE` (which FFmpeg's GIF muxer cannot
+    // honor either) leaves the delays untouched.
+    let _ = gif.set_frame_rate(fps as f64);
+    gif.into_bytes()
+}
+
+/// Makes the child process stop: by writing `q` to its `stdin` (see [`write_quit`]), or,
+/// when its `stdin` carries the video (see [`InputSource::pipes_stdin`]), by setting the
+/// `kill_requested` flag, which makes the CHILD thread kill it.
+fn stop_child(
+    stdin: &std::sync::Mutex<Option<std::process::ChildStdin>>,
+    kill_requested: Option<&std::sync::atomic::AtomicBool>,
+) -> std::io::Result<()> {
+    match kill_requested {
+        Some(kill_requested) => {
+            kill_requested.store(true, std::sync::atomic::Ordering::SeqCst);
+            Ok(())
+        }
+        None => write_quit(stdin),
+    }
+}
+
+/// Writes `q` to the child process' `stdin` (which makes FFmpeg stop), unless it has
+/// already been closed by the main thread.
+fn write_quit(stdin: &std::sync::Mutex<Option<std::process::ChildStdin>>) -> std::io::Result<()> {
+    use std::io::Write;
+    match stdin.lock().unwrap_or_else(|e| e.into_inner()).as_mut() {
+        Some(stdin) => stdin.write_all(b"q"),
+        None => Err(std::io::ErrorKind::BrokenPipe.into()),
+    }
+}
+
+/// The number of `stderr` lines included in [`Warning::DirtyExit`].
+const STDERR_TAIL_LINES: usize = 10;
+
+/// What the STDERR thread reports to the STDOUT thread once done, which is used
+/// to decide whether output produced despite a nonzero exit code can be trusted.
+#[derive(Debug, Default)]
+struct StderrReport {
+    /// The last few (non-blank) lines written by FFmpeg.
+    tail: std::collections::VecDeque<String>,
+    /// The number of frames from FFmpeg's last stats line, if any.
+    frame_count: Option<usize>,
+    /// The start of the selected part of the video (see [`Settings::clip`]) and the
+    /// video's duration, if the former is past the latter.
+    clip_out_of_range: Option<(Duration, Duration)>,
+    /// The line that reports that the crop region (see [`Settings::crop`]) is larger than
+    /// the video's frames, if any.
+    crop_out_of_bounds: Option<String>,
+    /// The subtitles file provided using [`Settings::subtitles`], if any.
+    subtitles: Option<std::path::PathBuf>,
+    /// The line that reports that the subtitles file could not be opened (or parsed), if any.
+    subtitles_error: Option<String>,
+    /// The index provided using [`Settings::video_stream_index`], if any.
+    video_stream_index: Option<u32>,
+    /// The line that reports that the input has no such video stream, if any.
+    video_stream_error: Option<String>,
+    /// The anomalies of the stats lines (see [`Warning::SuspectOutput`]).
+    anomalies: AnomalyDetector,
+    /// The job's memory budget (see [`Settings::memory_budget`]), from which the
+    /// memory used by the `tail` lines is reserved.
+    memory_budget: Option<std::sync::Arc<MemoryBudget>>,
+}
+
+impl StderrReport {
+    /// Keeps `line` among the last few lines, returning `true` if older lines had to be
+    /// dropped (or `line` itself skipped) to stay within the memory budget, for the first time.
+    fn push(&mut self, line: &StderrLine) -> bool {
+        let text = line.text.trim_end();
+        if text.trim_start().is_empty() {
+            return false;
+        }
+        if !line.truncated && text.trim_start().starts_with("frame=") {
+            self.frame_count = try_extract_frame_count(text).or(self.frame_count);
+            self.anomalies.push(text);
+        }
+        if self.crop_out_of_bounds.is_none() && crate::crop::is_out_of_bounds_error(text) {
+            self.crop_out_of_bounds = Some(text.trim_start().to_string());
+        }
+        if self.subtitles.is_some()
+            && self.subtitles_error.is_none()
+            && crate::subtitles::is_subtitles_error(text)
+        {
+            self.subtitles_error = Some(text.trim_start().to_string());
+        }
+        if self.video_stream_index.is_some()
+            && self.video_stream_error.is_none()
+            && crate::video_stream::is_missing_stream_error(text)
+        {
+            self.video_stream_error = Some(text.trim_start().to_string());
+        }
+        if self.tail.len() == STDERR_TAIL_LINES {
+            self.pop_front();
+        }
+        let Some(budget) = self.memory_budget.clone() else {
+            self.tail.push_back(text.to_string());
+            return false;
+        };
+        let mut truncated = false;
+        while !budget.try_reserve_stderr(text.len()) {
+            truncated = true;
+            if !self.pop_front() {
+                return budget.engage(BudgetMeasure::StderrTruncated);
+            }
+        }
+        self.tail.push_back(text.to_string());
+        truncated && budget.engage(BudgetMeasure::StderrTruncated)
+    }
+
+    /// Drops the oldest line (releasing its memory), returning `false` if there was none.
+    fn pop_front(&mut self) -> bool {
+        let Some(line) = self.tail.pop_front() else {
+            return false;
+        };
+        if let Some(budget) = self.memory_budget.as_ref() {
+            budget.release_stderr(line.len());
+        }
+        true
+    }
+
+    /// The last few lines, joined.
+    fn tail(&self) -> String {
+        self.tail
+            .iter()
+            .map(String::as_str)
+            .collect::<Vec<_>>()
+            .join("\n")
+    }
+}
+
+/// Makes sure that the bytes written by FFmpeg to `stdout` make up a complete GIF
+/// (i.e. with a valid signature and a trailer), returning its metadata, or (for the
+/// other formats) that they start with the format's signature.
+fn validate_output(buf: &[u8], format: OutputFormat) -> Result<Option<GifInfo>, Error> {
+    if buf.is_empty() {
+        return Err(Error::EmptyStdout);
+    }
+    match format {
+        OutputFormat::Gif => parse_gif_info(buf).map(Some).map_err(Error::InvalidOutput),
+        _ if format.has_signature(buf) => Ok(None),
+        _ => Err(Error::InvalidOutputSignature(format)),
+    }
+}
+
+/// Resolves the outcome of a job that was not cancelled, given the validation of the output
+/// written by FFmpeg (see [`validate_output`] and [`output_path::validate`]) and its nonzero
+/// exit code (if any). The output's validation comes first: a nonzero exit code only fails the job (taking precedence over the validation
+/// error, if any) when the output is invalid, or when it has fewer frames than reported
+/// by FFmpeg's last stats line (or when there is no such line, or when its frames are not
+/// counted, i.e. when it is not an animated GIF). Otherwise, the output is delivered
+/// along with a [`Warning::DirtyExit`] warning.
+fn resolve_outcome(
+    validated: Result<Option<GifInfo>, Error>,
+    exit_code: Option<i32>,
+    stderr_report: Option<StderrReport>,
+) -> Result<Option<Warning>, Error> {
+    let mut stderr_report = stderr_report.unwrap_or_default();
+    // NOTE: Whatever the exit code, since FFmpeg had nothing to convert, or could not set up
+    // the filter graph or find the stream to convert, which the stderr report explains
+    // better than the missing (or, but for a clip out of range, broken) output does. The
+    // first diagnosis that applies wins.
+    let diagnoses = [
+        (
+            false,
+            stderr_report
+                .clip_out_of_range
+                .map(|(start, duration)| Error::ClipOutOfRange { start, duration }),
+        ),
+        (
+            true,
+            stderr_report
+                .crop_out_of_bounds
+                .take()
+                .map(|line| Error::CropOutOfBounds { line }),
+        ),
+        (
+            true,
+            stderr_report
+                .subtitles
+                .take()
+                .zip(stderr_report.subtitles_error.take())
+                .map(|(path, line)| Error::SubtitlesFile { path, line }),
+        ),
+        (
+            true,
+            stderr_report
+                .video_stream_index
+                .zip(stderr_report.video_stream_error.take())
+                .map(|(index, line)| Error::VideoStreamNotFound { index, line }),
+        ),
+    ];
+    let diagnosis = diagnoses.into_iter().find_map(|(or_invalid, error)| {
+        error.filter(|_| is_missing_output(&validated, or_invalid))
+    });
+    if let Some(error) = diagnosis {
+        return Err(error);
+    }
+    let Some(code) = exit_code else {
+        return validated.map(|_| None);
+    };
+    match (validated, stderr_report.frame_count) {
+        (Ok(Some(info)), Some(expected)) if info.frame_count >= expected => {
+            Ok(Some(Warning::DirtyExit {
+                code,
+                stderr_tail: stderr_report.tail(),
+            }))
+        }
+        _ => Err(Error::ExitCode(code)),
+    }
+}
+
+/// Whether `validated` failed because FFmpeg produced no output at all (or, if `or_invalid`,
+/// output that is not a valid GIF).
+fn is_missing_output(validated: &Result<Option<GifInfo>, Error>, or_invalid: bool) -> bool {
+    match validated {
+        Err(Error::EmptyStdout | Error::EmptyOutputFile(_)) => true,
+        Err(Error::InvalidOutput(_) | Error::InvalidOutputSignature(_)) => or_invalid,
+        _ => false,
+    }
+}
+
+/// Same as [`std::io::Read::read_to_end`] (reading in chunks into `buf`), except that the
+/// number of bytes read so far is kept up to date in `count`, and `report` is called with
+/// that number at most once every `interval` (and once more at the end, if it changed
+/// since the last call), and `on_spill` if `buf` spills over to disk. Fails with
+/// [`Error::ChildProcess`] if `reader` fails, or with [`Error::OutputSpill`] if the
+/// temporary file cannot be written.
+fn read_to_end_counting(
+    reader: &mut impl std::io::Read,
+    buf: &mut OutputBuffer,
+    count: &std::sync::atomic::AtomicUsize,
+    interval: Duration,
+    mut report: impl FnMut(usize),
+    mut on_spill: impl FnMut(),
+) -> Result<usize, Error> {
+    let mut chunk = vec![0u8; 64 * 1024];
+    let mut last_report: Option<(std::time::Instant, usize)> = None;
+    loop {
+        match reader.read(&mut chunk) {
+            Ok(0) => {
+                if buf.len() != last_report.map_or(0, |(_, n)| n) {
+                    report(buf.len());
+                }
+                return Ok(buf.len());
+            }
+            Ok(n) => {
+                if buf.extend(&chunk[..n])? {
+                    on_spill();
+                }
+                count.store(buf.len(), std::sync::atomic::Ordering::SeqCst);
+                if last_report.is_none_or(|(at, _)| at.elapsed() >= interval) {
+                    report(buf.len());
+                    last_report = Some((std::time::Instant::now(), buf.len()));
+                }
+            }
+            Err(e) if e.kind() == std::io::ErrorKind::Interrupted => {}
+            Err(e) => return Err(Error::ChildProcess(std::sync::Arc::new(e))),
+        }
+    }
+}
+
+/// Same as [`read_to_end_counting`], but copies the bytes read from `reader` to `writer`
+/// (see [`Converter::convert_to_writer`]) instead of keeping them, `report` being given the
+/// number of bytes written so far, and flushes `writer` once the output ends. Once `writer`
+/// has failed (in which case `on_write_error` is called, right away), or once `cancelled`
+/// returns `true`, the rest of the output is read without being written, so that FFmpeg
+/// never blocks on a full pipe.
+fn copy_counting(
+    reader: &mut impl std::io::Read,
+    writer: &mut OutputWriter,
+    count: &std::sync::atomic::AtomicUsize,
+    interval: Duration,
+    mut report: impl FnMut(u64),
+    mut cancelled: impl FnMut() -> bool,
+    mut on_write_error: impl FnMut(std::io::Error),
+) -> std::io::Result<usize> {
+    let mut chunk = vec![0u8; 64 * 1024];
+    let mut read = 0;
+    let mut writing = true;
+    let mut last_report: Option<(std::time::Instant, u64)> = None;
+    loop {
+        match reader.read(&mut chunk) {
+            Ok(0) => {
+                if writing {
+                    match writer.flush() {
+                        Err(e) => on_write_error(e),
+                        Ok(_) if writer.written() != last_report.map_or(0, |(_, n)| n) => {
+                            report(writer.written())
+                        }
+                        Ok(_) => {}
+                    }
+                }
+                return Ok(read);
+            }
+            Ok(n) => {
+                read += n;
+                count.store(read, std::sync::atomic::Ordering::SeqCst);
+                writing = writing && !cancelled();
+                if !writing {
+                    continue;
+                }
+                if let Err(e) = writer.write_all(&chunk[..n]) {
+                    writing = false;
+                    on_write_error(e);
+                    continue;
+                }
+                if last_report.is_none_or(|(at, _)| at.elapsed() >= interval) {
+                    report(writer.written());
+                    last_report = Some((std::time::Instant::now(), writer.written()));
+                }
+            }
+            Err(e) if e.kind() == std::io::ErrorKind::Interrupted => {}
+            Err(e) => return Err(e),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    #[cfg(unix)]
+    use crate::test_utils::{
+        fake_ffmpeg, fake_ffmpeg_with_progress, fake_ffmpeg_with_progress_script,
+        fake_ffmpeg_with_script, run_to_completion, run_to_completion_with, sample_gif, sample_png,
+        send_command, success_bytes, temp_dir, write_script, SAMPLE_STDERR,
+        STDERR_RENAMED_DURATION, STDERR_RENAMED_TIME,
+    };
+    use crate::test_utils::{
+        final_summary, init_logging, run_job_to_completion_with, SAMPLE_VIDEO_PATH,
+    };
+
+    #[cfg(unix)]
+    #[test]
+    fn test_converter_validates_output() {
+        init_logging();
+
+        let gif = sample_gif(3, Some(0));
+        let path = fake_ffmpeg(SAMPLE_STDERR, &gif, 0);
+        let settings = Settings::with_standard_fps(SAMPLE_VIDEO_PATH.into(), 200)
+            .ffmpeg_path(path.to_string_lossy());
+        let messages = run_to_completion(settings);
+        assert_eq!(success_bytes(&messages), Some(&gif[..]));
+
+        let path = fake_ffmpeg(SAMPLE_STDERR, &gif[..gif.len() - 5], 0);
+        let settings = Settings::with_standard_fps(SAMPLE_VIDEO_PATH.into(), 200)
+            .ffmpeg_path(path.to_string_lossy());
+        let messages = run_to_completion(settings);
+        assert!(success_bytes(&messages).is_none());
+        assert!(messages
+            .iter()
+            .any(|m| matches!(m, Message::Error(Error::InvalidOutput(_)))));
+        assert!(matches!(messages.last(), Some(Message::Done)));
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_converter_validates_output_format() {
+        init_logging();
+
+        let webp = b"RIFF\x0c\x00\x00\x00WEBPVP8X".to_vec();
+        let png = sample_png(b"frame");
+        let stderr = SAMPLE_STDERR.replace("frame=   50", "frame=    1");
+        let run = |format: OutputFormat, stdout: &[u8], exit_code: i32| {
+            let path = fake_ffmpeg(&stderr, stdout, exit_code);
+            let settings = Settings::with_standard_fps(SAMPLE_VIDEO_PATH.into(), 200)
+                .ffmpeg_path(path.to_string_lossy())
+                .output_format(format);
+            run_to_completion(settings)
+        };
+        let webp_format = OutputFormat::WebP {
+            quality: 75,
+            lossless: false,
+        };
+        for (format, bytes) in [(webp_format, &webp), (OutputFormat::Apng, &png)] {
+            let messages = run(format, bytes, 0);
+            assert_eq!(success_bytes(&messages), Some(&bytes[..]), "{:?}", format);
+            let messages = run(format, &sample_gif(1, Some(0)), 0);
+            assert!(success_bytes(&messages).is_none());
+            assert!(messages.iter().any(
+                |m| matches!(m, Message::Error(Error::InvalidOutputSignature(f)) if *f == format)
+            ));
+            let messages = run(format, b"", 0);
+            assert!(messages
+                .iter()
+                .any(|m| matches!(m, Message::Error(Error::EmptyStdout))));
+            // NOTE: The frames are not counted, so the exit code takes precedence.
+            let messages = run(format, bytes, 3);
+            assert!(success_bytes(&messages).is_none());
+            assert!(messages
+                .iter()
+                .any(|m| matches!(m, Message::Error(Error::ExitCode(3)))));
+        }
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_converter_dirty_exit() {
+        init_logging();
+
+        let gif = sample_gif(3, Some(0));
+        // NOTE: FFmpeg's last stats line must not report more frames than the GIF has.
+        let stderr = SAMPLE_STDERR.replace("frame=   50", "frame=    3");
+        let errors = |messages: &[Message]| -> Vec<String> {
+            messages
+                .iter()
+                .filter_map(|m| match m {
+                    Message::Error(e) => Some(e.kind().to_string()),
+                    _ => None,
+                })
+                .collect()
+        };
+        let run = |stdout: &[u8], exit_code: i32| {
+            let path = fake_ffmpeg(&stderr, stdout, exit_code);
+            let settings = Settings::with_standard_fps(SAMPLE_VIDEO_PATH.into(), 200)
+                .ffmpeg_path(path.to_string_lossy());
+            run_to_completion(settings)
+        };
+
+        // Valid output, clean exit.
+        let messages = run(&gif, 0);
+        assert_eq!(success_bytes(&messages), Some(&gif[..]));
+        assert!(!messages.iter().any(|m| matches!(m, Message::Warning(_))));
+        assert!(errors(&messages).is_empty());
+
+        // Valid output, dirty exit.
+        let messages = run(&gif, 3);
+        assert_eq!(success_bytes(&messages), Some(&gif[..]));
+        assert!(errors(&messages).is_empty());
+        let position = messages
+            .iter()
+            .position(|m| matches!(m, Message::Warning(Warning::DirtyExit { .. })))
+            .expect("Expected a 'DirtyExit' warning");
+        assert!(matches!(messages[position + 1], Message::Success(_)));
+        let Message::Warning(Warning::DirtyExit { code, stderr_tail }) = &messages[position] else {
+            unreachable!()
+        };
+        assert_eq!(*code, 3);
+        assert!(stderr_tail.ends_with("speed=4.71x"));
+        assert_eq!(stderr_tail.lines().count(), STDERR_TAIL_LINES);
+
+        // Invalid output, clean exit.
+        let messages = run(&gif[..gif.len() - 5], 0);
+        assert!(success_bytes(&messages).is_none());
+        assert_eq!(errors(&messages), vec!["invalid_output"]);
+
+        // Invalid output, dirty exit: the exit code takes precedence.
+        let messages = run(&gif[..gif.len() - 5], 3);
+        assert!(success_bytes(&messages).is_none());
+        assert_eq!(errors(&messages), vec!["exit_code"]);
+        let messages = run(b"", 3);
+        assert_eq!(errors(&messages), vec!["exit_code"]);
+
+        // Valid but incomplete output (i.e. fewer frames than reported), dirty exit.
+        let path = fake_ffmpeg(SAMPLE_STDERR, &gif, 3);
+        let settings = Settings::with_standard_fps(SAMPLE_VIDEO_PATH.into(), 200)
+            .ffmpeg_path(path.to_string_lossy());
+        let messages = run_to_completion(settings);
+        assert!(success_bytes(&messages).is_none());
+        assert_eq!(errors(&messages), vec!["exit_code"]);
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_converter_long_stderr_line() {
+        init_logging();
+
+        // A multi-megabyte line (e.g. a filter graph dump), whose kept prefix ends with a
+        // `time=` token cut in the middle (i.e. `time=00:00:03.3` instead of `time=00:00:03.33`).
+        let (header, stats) = SAMPLE_STDERR.split_once("frame=").unwrap();
+        let mut line = String::from("frame=    1 [graph] ");
+        let cut = "time=00:00:03.3";
+        line.push_str(&"x".repeat(crate::stderr_lines::MAX_LINE_LEN - line.len() - cut.len()));
+        line.push_str("time=00:00:03.33 ");
+        line.push_str(&"y".repeat(4 * 1024 * 1024));
+        let stderr = format!("{}{}\nframe={}", header, line, stats);
+        let gif = sample_gif(3, Some(0));
+        let path = fake_ffmpeg(&stderr, &gif, 0);
+        let settings = Settings::with_standard_fps(SAMPLE_VIDEO_PATH.into(), 200)
+            .ffmpeg_path(path.to_string_lossy())
+            .deny_stderr_patterns(vec!["[graph] xxx".into()]);
+        let messages = run_to_completion(settings);
+        // The (truncated) line is still matched against the deny patterns.
+        assert!(matches!(
+            final_summary(&messages).error,
+            Some(Error::DeniedWarning { .. })
+        ));
+
+        let settings = Settings::with_standard_fps(SAMPLE_VIDEO_PATH.into(), 200)
+            .ffmpeg_path(path.to_string_lossy());
+        let messages = run_to_completion(settings);
+        assert!(messages
+            .iter()
+            .any(|m| matches!(m, Message::VideoDuration(d) if *d == Duration::from_secs(5))));
+        let progress: Vec<f64> = messages
+            .iter()
+            .filter_map(|m| match m {
+                Message::Progress(p) => Some(*p),
+                _ => None,
+            })
+            .collect();
+        // Only the progress reported by the two stats lines that follow.
+        let expected: Vec<f64> = stats
+            .lines()
+            .filter_map(|l| try_extract_frame_time(l, None))
+            .map(|t| progress_from_durations(Duration::from_secs(5), t))
+            .collect();
+        assert_eq!(expected.len(), 2);
+        assert_eq!(progress, expected);
+        assert_eq!(success_bytes(&messages), Some(&gif[..]));
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_converter_watermark_not_found() {
+        init_logging();
+
+        let path = fake_ffmpeg(SAMPLE_STDERR, &sample_gif(2, Some(0)), 0);
+        let missing = temp_dir().join("logo.png");
+        let settings = Settings::with_standard_fps(SAMPLE_VIDEO_PATH.into(), 200)
+            .ffmpeg_path(path.to_string_lossy())
+            .watermark(missing.clone(), crate::Position::TopLeft, 1.0);
+        let messages = run_to_completion(settings);
+        match final_summary(&messages).error {
+            Some(Error::WatermarkFile { path, error }) => {
+                assert_eq!(path, missing);
+                assert_eq!(error.kind(), std::io::ErrorKind::NotFound);
+            }
+            e => panic!("Expected a 'WatermarkFile' error: {:?}", e),
+        }
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_converter_command_wrapper() {
+        init_logging();
+
+        // NOTE: The fake FFmpeg binary fails unless run by `env`, which sets the variable.
+        let dir = temp_dir();
+        std::fs::write(dir.join("stderr.txt"), SAMPLE_STDERR).unwrap();
+        std::fs::write(dir.join("stdout.bin"), sample_gif(2, Some(0))).unwrap();
+        let path = write_script(
+            &dir,
+            &format!(
+                "[ \"$WRAPPED\" = 1 ] || exit 3\ncat '{dir}/stderr.txt' >&2\ncat '{dir}/stdout.bin'",
+                dir = dir.display()
+            ),
+        );
+        let settings = Settings::with_standard_fps(SAMPLE_VIDEO_PATH.into(), 200)
+            .ffmpeg_path(path.to_string_lossy())
+            // NOTE: The duration gets probed (by the same wrapped binary) for this clip.
+            .clip(crate::ClipSelection::FromEnd(Duration::from_secs(2)));
+
+        let messages = run_to_completion(settings.clone());
+        assert!(success_bytes(&messages).is_none());
+
+        let wrapper = vec!["/usr/bin/env".into(), "WRAPPED=1".into()];
+        let messages = run_to_completion(settings.command_wrapper(wrapper));
+        assert_eq!(success_bytes(&messages), Some(&sample_gif(2, Some(0))[..]));
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_converter_argv_hook() {
+        use std::sync::atomic::{AtomicUsize, Ordering};
+
+        init_logging();
+
+        let gif = sample_gif(2, Some(0));
+        let path = fake_ffmpeg(SAMPLE_STDERR, &gif, 0);
+        // NOTE: The wrapper saves the arguments it gets (i.e. the FFmpeg binary's path,
+        // followed by FFmpeg's arguments), one per line.
+        let dir = temp_dir();
+        let args_path = dir.join("args.txt");
+        let wrapper = write_script(
+            &dir,
+            &format!(
+                "printf '%s\\n' \"$@\" > '{}'\nexec \"$@\"",
+                args_path.display()
+            ),
+        );
+        let settings = Settings::with_standard_fps(SAMPLE_VIDEO_PATH.into(), 200)
+            .ffmpeg_path(path.to_string_lossy())
+            .command_wrapper(vec![wrapper.into()]);
+        let calls = std::sync::Arc::new(AtomicUsize::new(0));
+        let calls_hook = std::sync::Arc::clone(&calls);
+        let hooked = settings.clone().argv_hook(move |args| {
+            calls_hook.fetch_add(1, Ordering::SeqCst);
+            args.retain(|arg| arg != "-stats");
+            args.insert(0, "-hide_banner".into());
+        });
+
+        let messages = run_to_completion(hooked);
+        assert_eq!(success_bytes(&messages), Some(&gif[..]));
+        assert_eq!(calls.load(Ordering::SeqCst), 1);
+        // The child process got the edited arguments.
+        let mut expected = vec![path.to_string_lossy().into_owned(), "-hide_banner".into()];
+        expected.extend(
+            settings
+                .plan()
+                .args
+                .into_iter()
+                .filter(|arg| arg != "-stats"),
+        );
+        let args = std::fs::read_to_string(&args_path).unwrap();
+        assert_eq!(Vec::from_iter(args.lines()), expected);
+
+        // A panic fails the job, before FFmpeg is spawned.
+        std::fs::remove_file(&args_path).unwrap();
+        let messages = run_to_completion(settings.argv_hook(|_| panic!("bad argv")));
+        assert!(messages.iter().any(|m| matches!(
+            m,
+            Message::Error(Error::InvalidSettings(SettingsError::ArgvHookPanicked(message)))
+                if message == "bad argv"
+        )));
+        assert!(matches!(messages.last(), Some(Message::Done)));
+        assert!(!args_path.exists());
+    }
+
+    #[cfg(unix)]
+    #[cfg(feature = "v2-messages")]
+    #[test]
+    fn test_converter_v2_messages() {
+        init_logging();
+
+        let gif = sample_gif(3, Some(0));
+        let path = fake_ffmpeg(SAMPLE_STDERR, &gif, 0);
+        let settings = Settings::with_standard_fps(SAMPLE_VIDEO_PATH.into(), 200)
+            .ffmpeg_path(path.to_string_lossy());
+
+        #[cfg(not(feature = "tokio"))]
+        let (converter, tx, rx) = Converter::new_with_v2_channels();
+        #[cfg(feature = "tokio")]
+        let (converter, tx, mut rx) = Converter::new_with_v2_channels();
+        let converter = converter.with_id(42);
+        let job_settings = settings.clone();
+        let handle = std::thread::spawn(move || converter.convert(job_settings));
+        let mut messages = vec![];
+        loop {
+            #[cfg(not(any(feature = "tokio", feature = "async-channel")))]
+            let message = rx.recv().ok();
+            #[cfg(feature = "tokio")]
+            let message = rx.blocking_recv();
+            #[cfg(feature = "async-channel")]
+            let message = rx.recv_blocking().ok();
+            let Some(message) = message else { break };
+            let done = matches!(message.event, crate::v2::Event::Done);
+            messages.push(message);
+            if done {
+                break;
+            }
+        }
+        handle.join().unwrap();
+        drop(tx);
+
+        // The new data: the job's identifier, and the GIF's metadata.
+        assert!(messages.iter().all(|m| m.job_id == JobId::U64(42)));
+        match messages.iter().find_map(|m| match &m.event {
+            crate::v2::Event::Success(success) => Some(success),
+            _ => None,
+        }) {
+            Some(success) => {
+                assert_eq!(success.bytes, gif);
+                assert_eq!(success.dimensions, Some((200, 112)));
+            }
+            None => panic!("{:?}", messages),
+        }
+        assert!(messages.iter().any(|m| matches!(
+            &m.event,
+            crate::v2::Event::Progress(p) if !p.interpolated && p.position.is_some()
+        )));
+
+        // The legacy messages derived from them are those of the legacy channel.
+        // NOTE: Except for the summary, whose identifier and timings differ.
+        let legacy = |messages: Vec<Message>| {
+            Vec::from_iter(
+                messages
+                    .into_iter()
+                    .filter(|m| !matches!(m, Message::Summary(_)))
+                    .map(|m| format!("{:?}", m)),
+            )
+        };
+        assert_eq!(
+            legacy(messages.into_iter().map(Message::from).collect()),
+            legacy(run_to_completion(settings))
+        );
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_converter_suspect_output() {
+        init_logging();
+
+        let gif = sample_gif(3, Some(0));
+        let stderr = format!(
+            "{}frame=   50 fps=3.9 q=-0.0 Lsize=   23430kB time=00:00:04.91 bitrate=  -0.0kbits/s speed=0.379x\n",
+            SAMPLE_STDERR
+        );
+        let path = fake_ffmpeg(&stderr, &gif, 0);
+        let settings = Settings::with_standard_fps(SAMPLE_VIDEO_PATH.into(), 200)
+            .ffmpeg_path(path.to_string_lossy());
+
+        // Lenient: the GIF is delivered, after a warning.
+        let messages = run_to_completion(settings.clone());
+        let reason = crate::StatsAnomaly::NegativeBitrate;
+        assert!(messages.iter().any(
+            |m| matches!(m, Message::Warning(Warning::SuspectOutput { reason: r }) if *r == reason)
+        ));
+        assert_eq!(success_bytes(&messages), Some(&gif[..]));
+        let summary = final_summary(&messages);
+        assert!(summary.error.is_none());
+        assert!(summary
+            .warnings
+            .contains(&Warning::SuspectOutput { reason }));
+
+        // Strict: the job fails, and the GIF is thrown away.
+        let messages = run_to_completion(settings.strict_parsing(true));
+        assert_eq!(success_bytes(&messages), None);
+        match final_summary(&messages).error {
+            Some(Error::SuspectOutput { reason: r }) => assert_eq!(r, reason),
+            other => panic!("{:?}", other),
+        }
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_converter_strict_parsing() {
+        init_logging();
+
+        let gif = sample_gif(3, Some(0));
+        for (stderr, context) in [
+            (STDERR_RENAMED_DURATION, crate::ParseContext::Duration),
+            (STDERR_RENAMED_TIME, crate::ParseContext::FrameTime),
+        ] {
+            let path = fake_ffmpeg(stderr, &gif, 0);
+            let settings = Settings::with_standard_fps(SAMPLE_VIDEO_PATH.into(), 200)
+                .ffmpeg_path(path.to_string_lossy());
+
+            // Lenient: the GIF is delivered, without any progress.
+            let messages = run_to_completion(settings.clone());
+            assert!(!messages.iter().any(|m| matches!(m, Message::Progress(_))));
+            assert_eq!(success_bytes(&messages), Some(&gif[..]));
+            assert!(final_summary(&messages).error.is_none());
+
+            // Strict: the job fails, and the GIF is thrown away.
+            let messages = run_to_completion(settings.strict_parsing(true));
+            assert_eq!(success_bytes(&messages), None);
+            match final_summary(&messages).error {
+                Some(Error::ParseFailure { context: c, sample }) => {
+                    assert_eq!(c, context);
+                    assert!(stderr.lines().any(|l| l == sample), "{:?}", sample);
+                }
+                other => panic!("{:?}", other),
+            }
+        }
+
+        // A valid transcript is not affected.
+        let path = fake_ffmpeg(SAMPLE_STDERR, &gif, 0);
+        let settings = Settings::with_standard_fps(SAMPLE_VIDEO_PATH.into(), 200)
+            .ffmpeg_path(path.to_string_lossy())
+            .strict_parsing(true);
+        let messages = run_to_completion(settings);
+        assert_eq!(success_bytes(&messages), Some(&gif[..]));
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_converter_summary() {
+        init_logging();
+
+        let gif = sample_gif(3, Some(0));
+        let path = fake_ffmpeg(SAMPLE_STDERR, &gif, 0);
+        let settings = Settings::with_standard_fps(SAMPLE_VIDEO_PATH.into(), 200)
+            .ffmpeg_path(path.to_string_lossy());
+        let summary = final_summary(&run_to_completion(settings));
+        assert_eq!(summary.outcome(), crate::Outcome::Succeeded);
+        assert_eq!(summary.output_bytes, Some(gif.len()));
+        assert!(summary.error.is_none());
+        assert!(summary.resource_usage.is_some());
+
+        let path = fake_ffmpeg(SAMPLE_STDERR, b"", 1);
+        let settings = Settings::with_standard_fps(SAMPLE_VIDEO_PATH.into(), 200)
+            .ffmpeg_path(path.to_string_lossy());
+        let summary = final_summary(&run_to_completion(settings));
+        assert_eq!(summary.outcome(), crate::Outcome::Failed);
+        assert_eq!(summary.output_bytes, None);
+        assert!(summary.error.is_some());
+
+        let settings = Settings::with_standard_fps(SAMPLE_VIDEO_PATH.into(), 200).max_colors(1);
+        let summary = final_summary(&run_to_completion(settings));
+        assert_eq!(summary.outcome(), crate::Outcome::Failed);
+        assert!(matches!(summary.error, Some(Error::InvalidSettings(_))));
+        // NOTE: No child process was spawned.
+        assert!(summary.resource_usage.is_none());
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_converter_resource_usage_end_to_end() {
+        init_logging();
+        if !crate::test_utils::ffmpeg_available() {
+            return;
+        }
+
+        let settings = Settings::with_standard_fps(SAMPLE_VIDEO_PATH.into(), 200);
+        let summary = final_summary(&run_to_completion(settings));
+        assert_eq!(summary.outcome(), crate::Outcome::Succeeded);
+        let usage = summary.resource_usage.expect("Expected resource usage");
+        assert!(usage.cpu_time() > Duration::ZERO, "{:?}", usage);
+        assert!(usage.max_rss_bytes.is_some_and(|n| n > 0), "{:?}", usage);
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_converter_with_id() {
+        init_logging();
+
+        let path = fake_ffmpeg(SAMPLE_STDERR, &sample_gif(3, Some(0)), 0);
+        let settings = Settings::with_standard_fps(SAMPLE_VIDEO_PATH.into(), 200)
+            .ffmpeg_path(path.to_string_lossy());
+        let id = JobId::U64(9_000_000_000 + std::process::id() as u64);
+        let expected = id.clone();
+        let messages = run_job_to_completion_with(
+            move |converter| {
+                let converter = converter.job_label("request-42").with_id(id);
+                assert_eq!(converter.label(), Some("request-42"));
+                converter.convert(settings)
+            },
+            drop,
+        );
+        let summary = final_summary(&messages);
+        assert_eq!(summary.id, expected);
+        assert_eq!(summary.label.as_deref(), Some("request-42"));
+
+        // The log lines carry the application's identifier.
+        let logs = crate::test_utils::captured_logs(|log| {
+            log.fields
+                .iter()
+                .any(|(k, v)| k == "job_id" && *v == expected.to_string())
+        });
+        assert!(logs
+            .iter()
+            .any(|log| log.target == LOG_TARGET_STDERR && log.message.contains("[request-42] ")));
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_converter_job_label() {
+        init_logging();
+
+        let path = fake_ffmpeg(SAMPLE_STDERR, &sample_gif(3, Some(0)), 0);
+        let settings = Settings::with_standard_fps(SAMPLE_VIDEO_PATH.into(), 200)
+            .ffmpeg_path(path.to_string_lossy());
+        let (number_tx, number_rx) = std::sync::mpsc::channel();
+        let messages = run_job_to_completion_with(
+            move |converter| {
+                number_tx.send(converter.job_number()).unwrap();
+                let converter = converter.job_label("request-42");
+                assert_eq!(converter.label(), Some("request-42"));
+                converter.convert(settings)
+            },
+            drop,
+        );
+        let number = number_rx.recv().unwrap();
+        let summary = final_summary(&messages);
+        assert_eq!(summary.label.as_deref(), Some("request-42"));
+
+        // Every line carries the job's identifiers as key-values...
+        let id = summary.id.to_string();
+        let field = |log: &crate::test_utils::CapturedLog, key: &str| {
+            log.fields
+                .iter()
+                .find(|(k, _)| k == key)
+                .map(|(_, v)| v.clone())
+        };
+        let logs =
+            crate::test_utils::captured_logs(|log| field(log, "job_id").as_ref() == Some(&id));
+        assert!(logs
+            .iter()
+            .all(|log| field(log, "job") == Some(number.to_string())));
+        // ... and the job's number (and label) as a prefix.
+        let tag = format!("#{} [request-42] ", number);
+        // NOTE: Only the instance's creation is logged before the label is attached.
+        let unlabeled: Vec<_> = logs
+            .iter()
+            .filter(|log| !log.message.starts_with(&tag))
+            .collect();
+        assert_eq!(unlabeled.len(), 1, "{:?}", unlabeled);
+        assert_eq!(
+            unlabeled[0].message,
+            format!("#{} Instance created (id: {})", number, id)
+        );
+        assert_eq!(field(unlabeled[0], "job_label").as_deref(), Some(""));
+        for target in [
+            LOG_TARGET_MAIN,
+            LOG_TARGET_STDIN,
+            LOG_TARGET_STDOUT,
+            LOG_TARGET_STDERR,
+            LOG_TARGET_CHILD,
+        ] {
+            let log = logs
+                .iter()
+                .find(|log| log.target == target && log.message.starts_with(&tag));
+            assert!(log.is_some(), "No log line found for target '{}'", target);
+            assert_eq!(
+                field(log.unwrap(), "job_label").as_deref(),
+                Some("request-42")
+            );
+        }
+
+        // Without a label, the summary doesn't have one either.
+        let path = fake_ffmpeg(SAMPLE_STDERR, &sample_gif(3, Some(0)), 0);
+        let settings = Settings::with_standard_fps(SAMPLE_VIDEO_PATH.into(), 200)
+            .ffmpeg_path(path.to_string_lossy());
+        assert_eq!(final_summary(&run_to_completion(settings)).label, None);
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_converter_deny_stderr_patterns() {
+        init_logging();
+
+        let gif = sample_gif(3, Some(0));
+        let patterns = vec![
+            "non-monotonous DTS".to_string(),
+            "deprecated pixel format".to_string(),
+        ];
+        let run = |stderr: &str| {
+            let path = fake_ffmpeg(stderr, &gif, 0);
+            let settings = Settings::with_standard_fps(SAMPLE_VIDEO_PATH.into(), 200)
+                .ffmpeg_path(path.to_string_lossy())
+                .deny_stderr_patterns(patterns.clone());
+            run_to_completion(settings)
+        };
+
+        // A transcript lacking the patterns.
+        let messages = run(SAMPLE_STDERR);
+        assert_eq!(success_bytes(&messages), Some(&gif[..]));
+        assert!(final_summary(&messages).error.is_none());
+
+        // A transcript containing one of the patterns.
+        let line = "[swscaler @ 0x7f8] deprecated pixel format used, make sure you did set range correctly";
+        let stderr =
+            SAMPLE_STDERR.replacen("Stream mapping:", &format!("{}\nStream mapping:", line), 1);
+        assert_ne!(stderr, SAMPLE_STDERR);
+        let messages = run(&stderr);
+        assert_eq!(success_bytes(&messages), None);
+        match final_summary(&messages).error {
+            Some(Error::DeniedWarning { pattern, line: l }) => {
+                assert_eq!(pattern, "deprecated pixel format");
+                assert_eq!(l, line);
+            }
+            e => panic!("Unexpected error: {:?}", e),
+        }
+
+        // An empty pattern is not allowed.
+        let settings = Settings::with_standard_fps(SAMPLE_VIDEO_PATH.into(), 200)
+            .deny_stderr_patterns(vec!["".into()]);
+        assert!(matches!(
+            final_summary(&run_to_completion(settings)).error,
+            Some(Error::InvalidSettings(
+                crate::SettingsError::InvalidDenyPattern(_)
+            ))
+        ));
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_converter_output_dimensions() {
+        init_logging();
+
+        let gif = sample_gif(3, Some(0));
+        let run = |stderr: &str| {
+            let path = fake_ffmpeg(stderr, &gif, 0);
+            let settings = Settings::with_standard_fps(SAMPLE_VIDEO_PATH.into(), 200)
+                .ffmpeg_path(path.to_string_lossy());
+            run_to_completion(settings)
+        };
+        let dimensions = |messages: &[Message]| -> Vec<(u32, u32)> {
+            messages
+                .iter()
+                .filter_map(|m| match m {
+                    Message::OutputDimensions { width, height } => Some((*width, *height)),
+                    _ => None,
+                })
+                .collect()
+        };
+
+        // Announced once, after the video's duration, and before any progress.
+        let messages = run(SAMPLE_STDERR);
+        assert_eq!(dimensions(&messages), [(200, 112)]);
+        let position = |f: fn(&Message) -> bool| messages.iter().position(f).unwrap();
+        let announced = position(|m| matches!(m, Message::OutputDimensions { .. }));
+        assert!(position(|m| matches!(m, Message::VideoDuration(_))) < announced);
+        assert!(announced < position(|m| matches!(m, Message::Progress(_))));
+
+        // Not announced when FFmpeg does not describe its output.
+        let (header, progress) = SAMPLE_STDERR.split_once("Output #0").unwrap();
+        let (_, progress) = progress.split_once("frame=").unwrap();
+        let messages = run(&format!("{}frame={}", header, progress));
+        assert!(dimensions(&messages).is_empty());
+        assert!(success_bytes(&messages).is_some());
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_converter_codec_selection() {
+        init_logging();
+
+        let gif = sample_gif(3, Some(0));
+        let run = |stderr: &str| {
+            let path = fake_ffmpeg(stderr, &gif, 0);
+            let settings = Settings::with_standard_fps(SAMPLE_VIDEO_PATH.into(), 200)
+                .ffmpeg_path(path.to_string_lossy());
+            run_to_completion(settings)
+        };
+        let selections = |messages: &[Message]| -> Vec<(String, String, bool)> {
+            messages
+                .iter()
+                .filter_map(|m| match m {
+                    Message::CodecSelection {
+                        decoder,
+                        encoder,
+                        hw_accelerated,
+                    } => Some((decoder.clone(), encoder.clone(), *hw_accelerated)),
+                    _ => None,
+                })
+                .collect()
+        };
+        let warnings = |messages: &[Message]| -> Vec<Warning> {
+            messages
+                .iter()
+                .filter_map(|m| match m {
+                    Message::Warning(w) => Some(w.clone()),
+                    _ => None,
+                })
+                .collect()
+        };
+
+        // A software decoder, announced after the video's duration.
+        let messages = run(SAMPLE_STDERR);
+        assert_eq!(
+            selections(&messages),
+            [("h264".into(), "gif".into(), false)]
+        );
+        assert!(warnings(&messages).is_empty());
+        let position = |f: fn(&Message) -> bool| messages.iter().position(f).unwrap();
+        assert!(
+            position(|m| matches!(m, Message::VideoDuration(_)))
+                < position(|m| matches!(m, Message::CodecSelection { .. }))
+        );
+
+        // A hardware decoder that could not be set up, FFmpeg falling back to its own.
+        let failures =
+            "[h264 @ 0x7f8] Failed setup for format cuda: hwaccel initialisation returned error.\n\
+            [h264 @ 0x7f9] Failed setup for format cuda: hwaccel initialisation returned error.\n";
+        let stderr = SAMPLE_STDERR.replacen("Output #0", &format!("{}Output #0", failures), 1);
+        let messages = run(&stderr);
+        assert_eq!(success_bytes(&messages), Some(&gif[..]));
+        assert_eq!(
+            selections(&messages),
+            [("h264".into(), "gif".into(), false)]
+        );
+        assert_eq!(
+            warnings(&messages),
+            [Warning::HardwareAccelerationFailed {
+                line: failures.lines().next().unwrap().into()
+            }]
+        );
+
+        // A hardware decoder.
+        let stderr = SAMPLE_STDERR.replace("Stream #0:0 (h264)", "Stream #0:0 (h264_cuvid)");
+        let messages = run(&stderr);
+        assert_eq!(
+            selections(&messages),
+            [("h264_cuvid".into(), "gif".into(), true)]
+        );
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_converter_cmd_shim_stderr() {
+        init_logging();
+
+        let dir = temp_dir();
+        let gif = sample_gif(3, Some(0));
+        std::fs::write(
+            dir.join("stderr.txt"),
+            include_bytes!("../tests/fixtures/stderr_cmd_shim_utf16.txt"),
+        )
+        .unwrap();
+        std::fs::write(dir.join("stdout.bin"), &gif).unwrap();
+        let path = write_script(
+            &dir,
+            &format!(
+                "cat '{dir}/stderr.txt' >&2\ncat '{dir}/stdout.bin'",
+                dir = dir.display()
+            ),
+        );
+        let settings = Settings::with_standard_fps(SAMPLE_VIDEO_PATH.into(), 200)
+            .ffmpeg_path(path.to_string_lossy());
+        let warnings = |messages: &[Message]| -> Vec<Warning> {
+            messages
+                .iter()
+                .filter_map(|m| match m {
+                    Message::Warning(w) => Some(w.clone()),
+                    _ => None,
+                })
+                .collect()
+        };
+
+        // UTF-16 with CRLF line endings is parsed like FFmpeg's own output.
+        let messages = run_to_completion(settings);
+        assert_eq!(success_bytes(&messages), Some(&gif[..]));
+        assert!(messages
+            .iter()
+            .any(|m| matches!(m, Message::VideoDuration(d) if *d == Duration::from_secs(5))));
+        let progress: Vec<_> = messages
+            .iter()
+            .filter_map(|m| match m {
+                Message::Progress(p) => Some(*p),
+                _ => None,
+            })
+            .collect();
+        assert_eq!(progress, [0.4, 0.98]);
+        assert!(warnings(&messages).is_empty());
+
+        // A wrapper script that writes something before FFmpeg's banner.
+        let shim_line = "Active code page: 65001";
+        let stderr = format!("\r\n{}\r\n{}", shim_line, SAMPLE_STDERR);
+        let path = fake_ffmpeg(&stderr, &gif, 0);
+        let settings = Settings::with_standard_fps(SAMPLE_VIDEO_PATH.into(), 200)
+            .ffmpeg_path(path.to_string_lossy());
+        let messages = run_to_completion(settings.clone());
+        assert_eq!(success_bytes(&messages), Some(&gif[..]));
+        assert_eq!(
+            warnings(&messages),
+            [Warning::MissingBanner {
+                first_line: shim_line.into()
+            }]
+        );
+
+        // NOTE: The banner is not expected when hidden on purpose.
+        let messages = run_to_completion(settings.extra_input_args(vec!["-hide_banner".into()]));
+        assert!(warnings(&messages).is_empty());
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_converter_deny_stderr_patterns_late_match() {
+        init_logging();
+
+        // The warning only appears once the whole video has been processed.
+        let path = fake_ffmpeg_with_progress_script(
+            4,
+            0.2,
+            "",
+            // NOTE: Sleeping first, so that the warning is not read along with the last progress line.
+            "sleep 0.2; printf '[mp4 @ 0x7f8] non-monotonous DTS in output stream 0:0\\n' >&2; sleep 0.2",
+        );
+        let settings = Settings::with_standard_fps(SAMPLE_VIDEO_PATH.into(), 200)
+            .ffmpeg_path(path.to_string_lossy())
+            .deny_stderr_patterns(vec!["non-monotonous DTS".into()]);
+        let messages = run_to_completion(settings);
+        assert!(messages
+            .iter()
+            .any(|m| matches!(m, Message::Progress(p) if *p == 1.0)));
+        assert_eq!(success_bytes(&messages), None);
+        let summary = final_summary(&messages);
+        assert_eq!(summary.outcome(), crate::Outcome::Failed);
+        assert!(matches!(
+            summary.error,
+            Some(Error::DeniedWarning { ref line, .. }) if line == "[mp4 @ 0x7f8] non-monotonous DTS in output stream 0:0"
+        ));
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_converter_output_larger_than_input() {
+        init_logging();
+
+        let gif = sample_gif(3, Some(0));
+        let video_path = temp_dir().join("video.mp4");
+        std::fs::write(&video_path, "video data").unwrap();
+        let input_bytes = 10;
+        let output_bytes = gif.len() as u64;
+        let run = |input: crate::InputSource, ratio: Option<f64>| {
+            let path = fake_ffmpeg(SAMPLE_STDERR, &gif, 0);
+            let settings = Settings::with_input(input, 200)
+                .ffmpeg_path(path.to_string_lossy())
+                .output_size_warning_ratio(ratio);
+            run_to_completion(settings)
+        };
+        let expected = Warning::OutputLargerThanInput {
+            input_bytes,
+            output_bytes,
+        };
+
+        for use_file in [false, true] {
+            let input = || match use_file {
+                false => crate::InputSource::from(video_path.to_string_lossy().to_string()),
+                true => crate::InputSource::from(std::fs::File::open(&video_path).unwrap()),
+            };
+            let messages = run(input(), Some(Settings::DEFAULT_OUTPUT_SIZE_WARNING_RATIO));
+            // The warning comes right before the output.
+            let position = messages
+                .iter()
+                .position(|m| matches!(m, Message::Warning(w) if *w == expected))
+                .expect("Expected an 'OutputLargerThanInput' warning");
+            assert!(matches!(messages[position + 1], Message::Success(_)));
+            assert_eq!(final_summary(&messages).warnings, vec![expected.clone()]);
+
+            // Below the threshold, or disabled.
+            let ratio = output_bytes as f64 / input_bytes as f64;
+            for ratio in [Some(ratio), None] {
+                let messages = run(input(), ratio);
+                assert!(success_bytes(&messages).is_some());
+                assert!(final_summary(&messages).warnings.is_empty());
+            }
+        }
+
+        let settings = Settings::with_standard_fps(SAMPLE_VIDEO_PATH.into(), 200)
+            .output_size_warning_ratio(Some(0.0));
+        assert_eq!(
+            settings.validate(),
+            Err(crate::SettingsError::InvalidOutputSizeWarningRatio)
+        );
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_converter_settings_conflicts() {
+        init_logging();
+
+        let path = fake_ffmpeg(SAMPLE_STDERR, &sample_gif(2, Some(0)), 0);
+        let settings = Settings::with_standard_fps(SAMPLE_VIDEO_PATH.into(), 200)
+            .ffmpeg_path(path.to_string_lossy())
+            .max_colors(64)
+            .auto_colors(true)
+            .fit_mode(crate::FitMode::Stretch);
+        let messages = run_to_completion(settings);
+        assert!(success_bytes(&messages).is_some());
+        assert_eq!(
+            final_summary(&messages).warnings,
+            [
+                Warning::SettingsConflict(crate::SettingsConflict::AutoColorsOverrideMaxColors),
+                Warning::SettingsConflict(crate::SettingsConflict::FitModeWithoutHeight),
+            ]
+        );
+
+        // A hard conflict, so the job is not started.
+        let settings = Settings::with_standard_fps("/dev/stdin".into(), 200)
+            .ffmpeg_path(path.to_string_lossy());
+        let messages = run_to_completion(settings);
+        assert!(messages.iter().any(|m| matches!(
+            m,
+            Message::Error(Error::InvalidSettings(crate::SettingsError::Conflict(
+                crate::SettingsConflict::StdinInput
+            )))
+        )));
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_converter_output_file() {
+        use crate::{CollisionPolicy, NamingError, Outcome, OutputNaming};
+
+        init_logging();
+
+        let gif = sample_gif(2, Some(0));
+        let spawned = temp_dir().join("spawned");
+        let path = fake_ffmpeg_with_script(
+            SAMPLE_STDERR,
+            &gif,
+            &format!("touch '{}'; exit 0", spawned.display()),
+        );
+        let dir = temp_dir();
+        let settings = |collision| {
+            Settings::with_standard_fps(SAMPLE_VIDEO_PATH.into(), 200)
+                .ffmpeg_path(path.to_string_lossy())
+                .output_file(
+                    &dir,
+                    OutputNaming::new("{stem}_{width}px.gif").collision(collision),
+                )
+        };
+        let expected_path = dir.join("big-buck-bunny-clip_200px.gif");
+
+        let messages = run_to_completion(settings(CollisionPolicy::Skip));
+        let position = messages
+            .iter()
+            .position(|m| matches!(m, Message::Saved { path } if *path == expected_path))
+            .expect("saved");
+        assert!(matches!(&messages[position + 1], Message::Success(bytes) if *bytes == gif));
+        assert_eq!(std::fs::read(&expected_path).unwrap(), gif);
+        let summary = final_summary(&messages);
+        assert_eq!(summary.outcome(), Outcome::Succeeded);
+        assert_eq!(summary.output_path.as_ref(), Some(&expected_path));
+
+        // The file now exists, so the job ends right away, without spawning FFmpeg.
+        std::fs::remove_file(&spawned).unwrap();
+        let messages = run_to_completion(settings(CollisionPolicy::Skip));
+        assert!(messages.iter().any(
+            |m| matches!(m, Message::Skipped { existing_path } if *existing_path == expected_path)
+        ));
+        assert!(success_bytes(&messages).is_none());
+        assert!(!spawned.exists());
+        let summary = final_summary(&messages);
+        assert_eq!(summary.outcome(), Outcome::Skipped);
+        assert_eq!(summary.output_path.as_ref(), Some(&expected_path));
+
+        let messages = run_to_completion(settings(CollisionPolicy::AutoNumber));
+        let numbered = dir.join("big-buck-bunny-clip_200px-2.gif");
+        assert!(messages
+            .iter()
+            .any(|m| matches!(m, Message::Saved { path } if *path == numbered)));
+        assert_eq!(std::fs::read(&numbered).unwrap(), gif);
+
+        let messages = run_to_completion(
+            settings(CollisionPolicy::Overwrite).output_file(&dir, OutputNaming::new("{size}.gif")),
+        );
+        assert!(messages.iter().any(|m| matches!(
+            m,
+            Message::Error(Error::InvalidSettings(SettingsError::OutputNaming(
+                NamingError::UnknownPlaceholder(name)
+            ))) if name == "size"
+        )));
+
+        let missing = dir.join("missing");
+        let messages = run_to_completion(
+            settings(CollisionPolicy::Overwrite).output_file(&missing, OutputNaming::new("a.gif")),
+        );
+        assert!(messages.iter().any(|m| matches!(
+            m,
+            Message::Error(Error::OutputFile { path, .. }) if *path == missing.join("a.gif")
+        )));
+        assert_eq!(final_summary(&messages).outcome(), Outcome::Failed);
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_converter_output_path() {
+        use crate::Outcome;
+
+        init_logging();
+
+        // NOTE: The fake FFmpeg binary saves its arguments, and writes the GIF (if any) to
+        // its last argument, i.e. the output, instead of `stdout`.
+        let dir = temp_dir();
+        let gif = sample_gif(2, Some(0));
+        std::fs::write(dir.join("stderr.txt"), SAMPLE_STDERR).unwrap();
+        let fake = |stdout: &[u8]| {
+            std::fs::write(dir.join("stdout.bin"), stdout).unwrap();
+            write_script(
+                &dir,
+                &format!(
+                    "printf '%s\\n' \"$@\" > '{dir}/args.txt'\ncat '{dir}/stderr.txt' >&2\nfor last; do :; done\ncat '{dir}/stdout.bin' > \"$last\"",
+                    dir = dir.display()
+                ),
+            )
+        };
+        let out_dir = temp_dir();
+        let output = out_dir.join("out.gif");
+        let settings = |ffmpeg: &std::path::Path| {
+            Settings::with_standard_fps(SAMPLE_VIDEO_PATH.into(), 200)
+                .ffmpeg_path(ffmpeg.to_string_lossy())
+                .output_path(&output)
+        };
+
+        let messages = run_to_completion(settings(&fake(&gif)));
+        assert!(messages
+            .iter()
+            .any(|m| matches!(m, Message::SuccessFile(path) if *path == output)));
+        assert!(success_bytes(&messages).is_none());
+        assert_eq!(std::fs::read(&output).unwrap(), gif);
+        let args = std::fs::read_to_string(dir.join("args.txt")).unwrap();
+        let args = Vec::from_iter(args.lines());
+        assert_eq!(args[..2], ["-stats", "-y"]);
+        assert_eq!(args.last(), Some(&output.to_str().unwrap()));
+        assert!(!args.contains(&"-"));
+        let summary = final_summary(&messages);
+        assert_eq!(summary.outcome(), Outcome::Succeeded);
+        assert_eq!(summary.output_bytes, Some(gif.len()));
+        assert_eq!(summary.output_path.as_ref(), Some(&output));
+
+        // An empty (or missing) file fails the job, and is removed if created for it.
+        std::fs::remove_file(&output).unwrap();
+        let messages = run_to_completion(settings(&fake(b"")));
+        assert!(messages
+            .iter()
+            .any(|m| matches!(m, Message::Error(Error::EmptyOutputFile(path)) if *path == output)));
+        assert!(!output.exists());
+        assert_eq!(final_summary(&messages).outcome(), Outcome::Failed);
+
+        let messages = run_to_completion(settings(&fake(&gif[..gif.len() - 1])));
+        assert!(messages
+            .iter()
+            .any(|m| matches!(m, Message::Error(Error::InvalidOutput(_)))));
+        assert!(!output.exists());
+
+        // A missing directory fails the job before FFmpeg is spawned.
+        std::fs::remove_file(dir.join("args.txt")).unwrap();
+        let missing = out_dir.join("missing");
+        let messages =
+            run_to_completion(settings(&fake(&gif)).output_path(missing.join("out.gif")));
+        assert!(messages.iter().any(
+            |m| matches!(m, Message::Error(Error::OutputDirectoryNotFound(path)) if *path == missing)
+        ));
+        assert!(!dir.join("args.txt").exists());
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_converter_output_path_end_to_end() {
+        init_logging();
+        if !crate::test_utils::ffmpeg_available() {
+            return;
+        }
+
+        let output = temp_dir().join("out.gif");
+        let settings =
+            Settings::with_standard_fps(SAMPLE_VIDEO_PATH.into(), 100).output_path(&output);
+        let messages = run_to_completion(settings);
+        assert!(messages
+            .iter()
+            .any(|m| matches!(m, Message::SuccessFile(path) if *path == output)));
+        let bytes = std::fs::read(&output).unwrap();
+        assert!(parse_gif_info(&bytes).is_ok());
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_converter_convert_to_writer() {
+        use crate::test_utils::SharedWriter;
+        use crate::{Outcome, SettingsConflict};
+
+        init_logging();
+
+        let run = |settings: Settings, writer: SharedWriter| {
+            run_job_to_completion_with(
+                move |converter| converter.convert_to_writer(settings, writer),
+                |tx| {
+                    std::thread::sleep(Duration::from_secs(60));
+                    drop(tx);
+                },
+            )
+        };
+        let settings = |stdout: &[u8]| {
+            let path = fake_ffmpeg(SAMPLE_STDERR, stdout, 0);
+            Settings::with_standard_fps(SAMPLE_VIDEO_PATH.into(), 200)
+                .ffmpeg_path(path.to_string_lossy())
+        };
+        let gif = sample_gif(3, Some(0));
+
+        let writer = SharedWriter::default();
+        let messages = run(settings(&gif), writer.clone());
+        assert_eq!(writer.bytes(), gif);
+        assert!(messages
+            .iter()
+            .any(|m| matches!(m, Message::SuccessWritten(n) if *n == gif.len() as u64)));
+        assert!(success_bytes(&messages).is_none());
+        let written: Vec<_> = messages
+            .iter()
+            .filter_map(|m| match m {
+                Message::BytesWritten(n) => Some(*n),
+                _ => None,
+            })
+            .collect();
+        assert_eq!(written.last(), Some(&(gif.len() as u64)));
+        // NOTE: Replaced by `Message::BytesWritten`.
+        assert!(!messages
+            .iter()
+            .any(|m| matches!(m, Message::OutputBytes(_))));
+        let summary = final_summary(&messages);
+        assert_eq!(summary.outcome(), Outcome::Succeeded);
+        assert_eq!(summary.output_bytes, Some(gif.len()));
+
+        // An invalid output fails the job, although it has already been written.
+        let writer = SharedWriter::default();
+        let messages = run(settings(&gif[..gif.len() - 1]), writer.clone());
+        assert!(messages
+            .iter()
+            .any(|m| matches!(m, Message::Error(Error::InvalidOutput(_)))));
+        assert!(!messages
+            .iter()
+            .any(|m| matches!(m, Message::SuccessWritten(_))));
+        assert_eq!(writer.bytes(), gif[..gif.len() - 1]);
+
+        // The options that need the animated GIF's bytes cannot be used.
+        let writer = SharedWriter::default();
+        let messages = run(settings(&gif).emit_frame_map(true), writer.clone());
+        let conflict = SettingsConflict::WriterOverrides {
+            option: "emit_frame_map",
+        };
+        assert!(messages.iter().any(|m| matches!(
+            m,
+            Message::Error(Error::InvalidSettings(SettingsError::Conflict(c))) if *c == conflict
+        )));
+        assert!(writer.bytes().is_empty());
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_converter_convert_to_writer_failure() {
+        use crate::test_utils::FailingWriter;
+        use crate::Outcome;
+
+        init_logging();
+
+        // NOTE: Like FFmpeg, the fake binary keeps running until it reads `q` from `stdin`.
+        let path = fake_ffmpeg_with_script(
+            SAMPLE_STDERR,
+            &sample_gif(3, Some(0)),
+            "timeout 30 head -c 1 > /dev/null",
+        );
+        let settings = Settings::with_standard_fps(SAMPLE_VIDEO_PATH.into(), 200)
+            .ffmpeg_path(path.to_string_lossy());
+        let started = std::time::Instant::now();
+        let messages = run_job_to_completion_with(
+            move |converter| converter.convert_to_writer(settings, FailingWriter::new(10)),
+            |tx| {
+                std::thread::sleep(Duration::from_secs(60));
+                drop(tx);
+            },
+        );
+        // The failure stops the job right away, just like a cancellation.
+        assert!(started.elapsed() < Duration::from_secs(10));
+        let errors: Vec<_> = messages
+            .iter()
+            .filter_map(|m| match m {
+                Message::Error(e) => Some(e),
+                _ => None,
+            })
+            .collect();
+        assert!(matches!(errors[..], [Error::OutputWrite(_)]));
+        assert!(!messages
+            .iter()
+            .any(|m| matches!(m, Message::SuccessWritten(_))));
+        let summary = final_summary(&messages);
+        assert_eq!(summary.outcome(), Outcome::Failed);
+        assert_eq!(summary.error.map(|e| e.kind()), Some("output_write"));
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_converter_cache() {
+        init_logging();
+
+        let gif = sample_gif(3, Some(0));
+        let cache = CacheConfig::new(temp_dir().join("cache"), 1_000_000);
+        let run = |path: &std::path::Path, width: u16| {
+            let settings = Settings::with_standard_fps(SAMPLE_VIDEO_PATH.into(), width)
+                .ffmpeg_path(path.to_string_lossy());
+            run_job_to_completion_with(
+                {
+                    let cache = cache.clone();
+                    move |converter| converter.cache(cache).convert(settings)
+                },
+                |tx| {
+                    std::thread::sleep(std::time::Duration::from_secs(60));
+                    drop(tx);
+                },
+            )
+        };
+
+        // Miss, so the output gets stored.
+        let messages = run(&fake_ffmpeg(SAMPLE_STDERR, &gif, 0), 200);
+        assert_eq!(success_bytes(&messages), Some(&gif[..]));
+        assert_eq!(std::fs::read_dir(&cache.dir).unwrap().count(), 1);
+
+        // Hit, so FFmpeg is not needed at all.
+        let missing = temp_dir().join("missing-ffmpeg");
+        let messages = run(&missing, 200);
+        assert!(matches!(
+            &messages[..],
+            [
+                Message::OutputDimensions {
+                    width: 1,
+                    height: 1
+                },
+                Message::Success(bytes),
+                Message::Summary(_),
+                Message::Done
+            ] if *bytes == gif
+        ));
+        assert!(final_summary(&messages).error.is_none());
+
+        // Other settings miss.
+        let messages = run(&fake_ffmpeg(SAMPLE_STDERR, b"", 1), 320);
+        assert!(success_bytes(&messages).is_none());
+
+        // An output that came with a nonzero exit code is not stored (see
+        // `test_converter_dirty_exit` for the frame count).
+        let stderr = SAMPLE_STDERR.replace("frame=   50", "frame=    3");
+        let dirty = fake_ffmpeg(&stderr, &gif, 1);
+        let messages = run(&dirty, 480);
+        assert_eq!(success_bytes(&messages), Some(&gif[..]));
+        assert_eq!(std::fs::read_dir(&cache.dir).unwrap().count(), 1);
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_converter_frame_map() {
+        init_logging();
+
+        let gif = sample_gif(3, Some(0));
+        let path = fake_ffmpeg(SAMPLE_STDERR, &gif, 0);
+        let run =
+            |settings: Settings| run_to_completion(settings.ffmpeg_path(path.to_string_lossy()));
+        let frame_maps = |messages: &[Message]| -> Vec<Vec<Duration>> {
+            messages
+                .iter()
+                .filter_map(|m| match m {
+                    Message::FrameMap(timestamps) => Some(timestamps.clone()),
+                    _ => None,
+                })
+                .collect()
+        };
+        let millis = |ms: [u64; 3]| vec![ms.map(Duration::from_millis).to_vec()];
+        let settings = Settings::with_standard_fps(SAMPLE_VIDEO_PATH.into(), 200);
+
+        // Disabled by default.
+        assert!(frame_maps(&run(settings.clone())).is_empty());
+
+        // One timestamp per frame of the GIF, right before the output.
+        let messages = run(settings.clone().emit_frame_map(true));
+        assert_eq!(frame_maps(&messages), millis([0, 100, 200]));
+        let position = messages
+            .iter()
+            .position(|m| matches!(m, Message::FrameMap(_)))
+            .unwrap();
+        assert!(matches!(messages[position + 1], Message::Success(_)));
+
+        // Relative to the start of the clip, and capped by its end.
+        let clip =
+            crate::ClipSelection::Range(Duration::from_secs(2), Duration::from_millis(2_150));
+        let messages = run(settings.emit_frame_map(true).clip(clip));
+        assert_eq!(frame_maps(&messages), millis([2_000, 2_100, 2_150]));
+
+        // Once the clip has been resolved using the video's duration (i.e. 5 seconds).
+        let settings = Settings::with_standard_fps(SAMPLE_VIDEO_PATH.into(), 200)
+            .ffmpeg_path(fake_ffmpeg_with_clip(true).to_string_lossy())
+            .clip(crate::ClipSelection::FromEnd(Duration::from_secs(2)))
+            .emit_frame_map(true);
+        let messages = run_to_completion(settings);
+        assert_eq!(
+            frame_maps(&messages),
+            vec![[3_000, 3_100].map(Duration::from_millis).to_vec()]
+        );
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_converter_interpolate_frame_delays() {
+        init_logging();
+
+        // NOTE: Each frame of the sample GIF has a delay of 10 centiseconds.
+        let path = fake_ffmpeg(SAMPLE_STDERR, &sample_gif(6, Some(0)), 0);
+        let settings = Settings {
+            gif_fps: 15,
+            ..Settings::with_standard_fps(SAMPLE_VIDEO_PATH.into(), 200)
+                .ffmpeg_path(path.to_string_lossy())
+        };
+        let delays = |settings: Settings| -> Vec<u16> {
+            let messages = run_to_completion(settings);
+            let bytes = messages
+                .iter()
+                .find_map(|m| match m {
+                    Message::Success(bytes) => Some(bytes),
+                    _ => None,
+                })
+                .expect("no output");
+            crate::gif_info::parse_layout(bytes)
+                .unwrap()
+                .blocks
+                .iter()
+                .filter_map(|b| match b.kind {
+                    crate::gif_info::BlockKind::GraphicControl { delay } => Some(delay),
+                    _ => None,
+                })
+                .collect()
+        };
+
+        // Disabled by default.
+        assert_eq!(delays(settings.clone()), [10; 6]);
+
+        // 6 frames at 15 fps last 40 centiseconds.
+        let interpolated = delays(settings.interpolate_frame_delays(true));
+        assert_eq!(interpolated, [7, 7, 6, 7, 7, 6]);
+        assert_eq!(interpolated.iter().sum::<u16>(), 40);
+    }
+
+    #[cfg(unix)]
+    /// A fake FFmpeg binary that writes the sample transcript's header when probing the
+    /// duration, and otherwise saves its arguments (to `args.txt`, next to the binary),
+    /// reports 1 second of processed video, and writes a valid GIF. If `duration` is
+    /// `false`, the header does not contain the video's duration.
+    fn fake_ffmpeg_with_clip(duration: bool) -> std::path::PathBuf {
+        let dir = temp_dir();
+        let (header, _) = SAMPLE_STDERR.split_once("frame=").unwrap();
+        let header = match duration {
+            true => header.to_string(),
+            false => header.replace("Duration:", "Length:"),
+        };
+        std::fs::write(dir.join("header.txt"), header).unwrap();
+        std::fs::write(dir.join("stdout.bin"), sample_gif(2, Some(0))).unwrap();
+        write_script(
+            &dir,
+            &format!(
+                r#"case "$*" in
+  *filter_complex*) echo "$*" > '{dir}/args.txt';;
+  *) cat '{dir}/header.txt' >&2; exit 1;;
+esac
+cat '{dir}/header.txt' >&2
+sleep 0.1
+printf 'frame= 10 fps=0.0 q=-0.0 size= 0kB time=00:00:01.00 bitrate= 0.0kbits/s speed=1x\r' >&2
+sleep 0.1
+cat '{dir}/stdout.bin'"#,
+                dir = dir.display()
+            ),
+        )
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_converter_clip_from_end() {
+        init_logging();
+
+        let path = fake_ffmpeg_with_clip(true);
+        let settings = Settings::with_standard_fps(SAMPLE_VIDEO_PATH.into(), 200)
+            .ffmpeg_path(path.to_string_lossy())
+            .clip(crate::ClipSelection::FromEnd(Duration::from_secs(2)));
+        let messages = run_to_completion(settings);
+        assert!(success_bytes(&messages).is_some());
+        let args = std::fs::read_to_string(path.with_file_name("args.txt")).unwrap();
+        assert!(
+            args.starts_with("-stats -ss 3.000 -t 2.000 -i "),
+            "{}",
+            args
+        );
+        // The progress is relative to the selected part of the video.
+        assert!(messages
+            .iter()
+            .any(|m| matches!(m, Message::VideoDuration(d) if *d == Duration::from_secs(5))));
+        assert!(messages
+            .iter()
+            .any(|m| matches!(m, Message::Progress(p) if *p == 0.5)));
+        assert_eq!(
+            final_summary(&messages).duration_source,
+            Some(DurationSource::Probe)
+        );
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_converter_seek_modes() {
+        use crate::SeekMode;
+
+        init_logging();
+
+        // NOTE: Each mode's stats lines, until the clip's start, then 1 second into the clip.
+        let lead_in = |seek_mode| match seek_mode {
+            SeekMode::Fast => "time=-00:00:00.48",
+            SeekMode::Accurate => "time=N/A",
+            SeekMode::FastThenAccurate => "",
+        };
+        for seek_mode in [
+            SeekMode::Fast,
+            SeekMode::Accurate,
+            SeekMode::FastThenAccurate,
+        ] {
+            let dir = temp_dir();
+            let (header, _) = SAMPLE_STDERR.split_once("frame=").unwrap();
+            std::fs::write(dir.join("header.txt"), header).unwrap();
+            std::fs::write(dir.join("stdout.bin"), sample_gif(2, Some(0))).unwrap();
+            let lead_in = match lead_in(seek_mode) {
+                "" => String::new(),
+                time => format!(
+                    "printf 'frame= 0 fps=0.0 q=0.0 size= 0kB {} bitrate=N/A speed=N/A\\r' >&2\nsleep 0.1\n",
+                    time
+                ),
+            };
+            let path = write_script(
+                &dir,
+                &format!(
+                    r#"echo "$*" > '{dir}/args.txt'
+cat '{dir}/header.txt' >&2
+sleep 0.1
+{lead_in}printf 'frame= 10 fps=0.0 q=-0.0 size= 0kB time=00:00:01.00 bitrate= 0.0kbits/s speed=1x\r' >&2
+sleep 0.1
+cat '{dir}/stdout.bin'"#,
+                    dir = dir.display()
+                ),
+            );
+            let settings = Settings::with_standard_fps(SAMPLE_VIDEO_PATH.into(), 200)
+                .ffmpeg_path(path.to_string_lossy())
+                .clip(crate::ClipSelection::Range(
+                    Duration::from_secs(3),
+                    Duration::from_secs(5),
+                ))
+                .seek_mode(seek_mode)
+                .strict_parsing(true);
+            let messages = run_to_completion(settings);
+            assert!(success_bytes(&messages).is_some(), "{:?}", seek_mode);
+            let args = std::fs::read_to_string(dir.join("args.txt")).unwrap();
+            let expected_args = match seek_mode {
+                SeekMode::Fast => "-stats -ss 3.000 -noaccurate_seek -t 2.000 -i ",
+                SeekMode::Accurate => "-stats -t 5.000 -i ",
+                SeekMode::FastThenAccurate => "-stats -ss 3.000 -t 2.000 -i ",
+            };
+            assert!(args.starts_with(expected_args), "{:?}: {}", seek_mode, args);
+            // The progress is relative to the clip's length in every mode, the frames before
+            // the clip's start (with negative timestamps) counting as no progress.
+            let progress = Vec::from_iter(messages.iter().filter_map(|m| match m {
+                Message::Progress(p) => Some(*p),
+                _ => None,
+            }));
+            let expected_progress: &[f64] = match seek_mode {
+                SeekMode::Fast => &[0.0, 0.5],
+                SeekMode::Accurate | SeekMode::FastThenAccurate => &[0.5],
+            };
+            assert_eq!(progress, expected_progress, "{:?}", seek_mode);
+        }
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_converter_duration_sources() {
+        init_logging();
+
+        let banner = "Duration: 00:00:05.00, start: 0.000000, bitrate: 1785 kb/s";
+        let stream = "12288 tbn (default)\n";
+        let no_duration =
+            SAMPLE_STDERR.replace(banner, "Duration: N/A, start: 0.000000, bitrate: 1785 kb/s");
+        let run = |stderr: &str| {
+            let settings = Settings::with_standard_fps(SAMPLE_VIDEO_PATH.into(), 200)
+                .ffmpeg_path(fake_ffmpeg(stderr, &sample_gif(2, Some(0)), 0).to_string_lossy());
+            let messages = run_to_completion(settings);
+            assert!(success_bytes(&messages).is_some());
+            let durations = Vec::from_iter(messages.iter().filter_map(|m| match m {
+                Message::VideoDuration(d) => Some(*d),
+                _ => None,
+            }));
+            let progress = Vec::from_iter(messages.iter().filter_map(|m| match m {
+                Message::Progress(p) => Some(*p),
+                _ => None,
+            }));
+            (
+                final_summary(&messages).duration_source,
+                durations,
+                progress,
+            )
+        };
+        let expected_progress = |duration: Duration| {
+            Vec::from_iter(
+                [2_000, 4_900].map(|t| progress_from_durations(duration, Duration::from_millis(t))),
+            )
+        };
+        // NOTE: The estimate is based on the size of the input.
+        let input_bytes = std::fs::metadata(SAMPLE_VIDEO_PATH).unwrap().len();
+        let estimate = Duration::from_secs_f64((input_bytes * 8) as f64 / 1_785_000.0);
+
+        let cases = [
+            (
+                SAMPLE_STDERR.to_string(),
+                Some(DurationSource::Banner),
+                vec![Duration::from_secs(5)],
+            ),
+            (
+                no_duration.clone(),
+                Some(DurationSource::BitrateEstimate),
+                vec![estimate],
+            ),
+            // The stream's metadata, found later in the banner, replaces the estimate.
+            (
+                no_duration.replace(
+                    stream,
+                    &format!(
+                        "{}    Metadata:\n      DURATION        : 00:00:04.000000000\n",
+                        stream
+                    ),
+                ),
+                Some(DurationSource::StreamMetadata),
+                vec![estimate, Duration::from_secs(4)],
+            ),
+            // Without any source, no progress is reported.
+            (
+                no_duration.replace("bitrate: 1785 kb/s", "bitrate: N/A"),
+                None,
+                vec![],
+            ),
+        ];
+        for (i, (stderr, source, durations)) in cases.into_iter().enumerate() {
+            let (actual_source, actual_durations, progress) = run(&stderr);
+            assert_eq!(actual_source, source, "case {}", i);
+            assert_eq!(actual_durations, durations, "case {}", i);
+            match durations.last() {
+                Some(d) => assert_eq!(progress, expected_progress(*d), "case {}", i),
+                None => assert!(progress.is_empty(), "case {}", i),
+            }
+        }
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_converter_parse_provenance() {
+        use crate::{ParseProvenance, ParsedEvent};
+
+        init_logging();
+
+        let path = fake_ffmpeg(SAMPLE_STDERR, &sample_gif(2, Some(0)), 0);
+        let run = |settings: Settings| {
+            let messages = run_to_completion(settings.ffmpeg_path(path.to_string_lossy()));
+            assert!(success_bytes(&messages).is_some());
+            final_summary(&messages).provenance
+        };
+        let settings = Settings::with_standard_fps(SAMPLE_VIDEO_PATH.into(), 200);
+        assert!(run(settings.clone()).is_empty());
+
+        let line = |prefix: &str| {
+            SAMPLE_STDERR
+                .lines()
+                .find(|line| line.trim_start().starts_with(prefix))
+                .unwrap()
+                .to_string()
+        };
+        let progress = |line: String, t: u64| ParseProvenance {
+            event: ParsedEvent::Progress(progress_from_durations(
+                Duration::from_secs(5),
+                Duration::from_millis(t),
+            )),
+            line,
+        };
+        let duration = ParseProvenance {
+            event: ParsedEvent::VideoDuration {
+                duration: Duration::from_secs(5),
+                source: DurationSource::Banner,
+            },
+            line: line("Duration:"),
+        };
+        assert_eq!(
+            run(settings.clone().parse_provenance(8)),
+            [
+                duration,
+                progress(line("frame=   20"), 2_000),
+                progress(line("frame=   50"), 4_900),
+            ]
+        );
+        // Only the last records are kept.
+        assert_eq!(
+            run(settings.parse_provenance(1)),
+            [progress(line("frame=   50"), 4_900)]
+        );
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_converter_additional_widths() {
+        init_logging();
+
+        // NOTE: The fake FFmpeg copies the GIF named after each output file (i.e. after its
+        // width) from `dir`, and records the output files' paths.
+        let dir = temp_dir();
+        let gif = |width: u16| {
+            let mut bytes = sample_gif(2, Some(0));
+            bytes[6..8].copy_from_slice(&width.to_le_bytes());
+            bytes
+        };
+        for width in [400, 800] {
+            std::fs::write(dir.join(format!("{}px.gif", width)), gif(width)).unwrap();
+        }
+        let path = fake_ffmpeg_with_script(
+            SAMPLE_STDERR,
+            &gif(200),
+            &format!(
+                "prev=\nfor arg in \"$@\"; do\n  if [ \"$prev\" = gif ] && [ \"$arg\" != - ]; then\n    echo \"$arg\" >> '{dir}/targets.txt'\n    cp \"{dir}/$(basename \"$arg\")\" \"$arg\" 2>/dev/null\n  fi\n  prev=$arg\ndone\nexit 0",
+                dir = dir.display()
+            ),
+        );
+        let settings = Settings::with_standard_fps(SAMPLE_VIDEO_PATH.into(), 200)
+            .additional_widths(vec![400, 800])
+            .ffmpeg_path(path.to_string_lossy());
+        let widths = |messages: &[Message]| {
+            Vec::from_iter(messages.iter().filter_map(|m| match m {
+                Message::Success(bytes) => Some(Ok(parse_gif_info(bytes).unwrap().width)),
+                Message::SuccessVariant { width, bytes } => {
+                    assert_eq!(parse_gif_info(bytes).unwrap().width, *width);
+                    Some(Ok(*width))
+                }
+                Message::Error(Error::VariantOutput { width, error }) => {
+                    Some(Err((*width, error.kind())))
+                }
+                _ => None,
+            }))
+        };
+
+        let messages = run_to_completion(settings.clone());
+        assert_eq!(widths(&messages), [Ok(200), Ok(400), Ok(800)]);
+        assert_eq!(
+            final_summary(&messages).outcome(),
+            crate::Outcome::Succeeded
+        );
+        // The temporary files are removed once read.
+        let targets = std::fs::read_to_string(dir.join("targets.txt")).unwrap();
+        let targets = Vec::from_iter(targets.lines().map(std::path::PathBuf::from));
+        assert_eq!(targets.len(), 2);
+        assert!(targets
+            .iter()
+            .all(|target| !target.parent().unwrap().exists()));
+
+        // A missing width is reported on its own, the others being sent anyway.
+        std::fs::remove_file(dir.join("400px.gif")).unwrap();
+        let messages = run_to_completion(settings);
+        assert_eq!(
+            widths(&messages),
+            [Ok(200), Err((400, "empty_stdout")), Ok(800)]
+        );
+        assert!(matches!(
+            final_summary(&messages).error,
+            Some(Error::VariantOutput { width: 400, .. })
+        ));
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_converter_max_output_bytes() {
+        init_logging();
+
+        // NOTE: The first attempt (at 400 px) produces a larger GIF than the following ones.
+        let large = sample_gif(20, Some(0));
+        let small = sample_gif(2, Some(0));
+        let dir = temp_dir();
+        std::fs::write(dir.join("large.gif"), &large).unwrap();
+        std::fs::write(dir.join("small.gif"), &small).unwrap();
+        let path = fake_ffmpeg_with_script(
+            SAMPLE_STDERR,
+            b"",
+            &format!(
+                "case \"$*\" in *scale=400*) cat '{dir}/large.gif' ;; *) cat '{dir}/small.gif' ;; esac",
+                dir = dir.display()
+            ),
+        );
+        let settings = Settings::with_standard_fps(SAMPLE_VIDEO_PATH.into(), 400)
+            .ffmpeg_path(path.to_string_lossy());
+        let attempts = |messages: &[Message]| {
+            Vec::from_iter(messages.iter().filter_map(|m| match m {
+                Message::Attempt { n, settings } => Some((*n, settings.gif_width)),
+                _ => None,
+            }))
+        };
+        let progress = |messages: &[Message]| {
+            Vec::from_iter(messages.iter().filter_map(|m| match m {
+                Message::Progress(p) => Some(*p),
+                _ => None,
+            }))
+        };
+
+        // The second attempt fits.
+        let messages = run_to_completion(settings.clone().max_output_bytes(small.len() as u64));
+        assert_eq!(attempts(&messages), [(1, 400), (2, 200)]);
+        assert_eq!(success_bytes(&messages), Some(&small[..]));
+        let summary = final_summary(&messages);
+        assert!(summary.error.is_none() && summary.warnings.is_empty());
+        assert_eq!(summary.duration_source, Some(DurationSource::Banner));
+        assert_eq!(
+            messages
+                .iter()
+                .filter(|m| matches!(m, Message::Summary(_) | Message::Done))
+                .count(),
+            2
+        );
+        // Each attempt fills its share of the progress bar (i.e. a quarter).
+        let second = messages
+            .iter()
+            .position(|m| matches!(m, Message::Attempt { n: 2, .. }))
+            .unwrap();
+        assert!(progress(&messages[..second]).iter().all(|p| *p <= 0.25));
+        assert!(progress(&messages[second..]).iter().all(|p| *p > 0.25));
+        let progress = progress(&messages);
+        assert!(progress.windows(2).all(|w| w[0] <= w[1]));
+        assert_eq!(progress.last(), Some(&1.0));
+
+        // Giving up after the last attempt, with the smallest GIF.
+        let messages =
+            run_to_completion(settings.clone().max_output_bytes(1).max_output_attempts(2));
+        assert_eq!(attempts(&messages), [(1, 400), (2, 200)]);
+        assert_eq!(success_bytes(&messages), Some(&small[..]));
+        assert!(matches!(
+            final_summary(&messages).warnings[..],
+            [Warning::OutputSizeTargetMissed { max_bytes: 1, output_bytes, attempts: 2 }]
+                if output_bytes == small.len() as u64
+        ));
+
+        // The first attempt fits.
+        let messages = run_to_completion(settings.max_output_bytes(large.len() as u64));
+        assert_eq!(attempts(&messages), [(1, 400)]);
+        assert_eq!(success_bytes(&messages), Some(&large[..]));
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_converter_preview() {
+        init_logging();
+
+        // NOTE: Only the preview pass stamps the badge.
+        let preview = sample_gif(2, Some(0));
+        let gif = sample_gif(4, Some(0));
+        let dir = temp_dir();
+        std::fs::write(dir.join("preview.gif"), &preview).unwrap();
+        std::fs::write(dir.join("final.gif"), &gif).unwrap();
+        let script = |preview_script: &str| {
+            fake_ffmpeg_with_script(
+                SAMPLE_STDERR,
+                b"",
+                &format!(
+                    "case \"$*\" in *scale=100*drawtext=*) {} ;; *drawtext=*) exit 1 ;; *) cat '{dir}/final.gif' ;; esac",
+                    preview_script,
+                    dir = dir.display()
+                ),
+            )
+        };
+        let path = script(&format!("cat '{}/preview.gif'", dir.display()));
+        let settings = Settings::with_standard_fps(SAMPLE_VIDEO_PATH.into(), 400)
+            .ffmpeg_path(path.to_string_lossy())
+            .preview_width(100)
+            .badge_overlay(crate::Text::new("rendering…"));
+        let messages = run_to_completion(settings.clone());
+        let position = |matches: fn(&Message) -> bool| messages.iter().position(matches);
+        let previewed = position(|m| matches!(m, Message::Preview(_))).unwrap();
+        assert!(matches!(&messages[previewed], Message::Preview(bytes) if *bytes == preview));
+        assert!(previewed < position(|m| matches!(m, Message::Success(_))).unwrap());
+        assert_eq!(success_bytes(&messages), Some(&gif[..]));
+        assert!(final_summary(&messages).error.is_none());
+
+        // A failed preview pass fails the job.
+        let path = script("exit 1");
+        let messages = run_to_completion(settings.ffmpeg_path(path.to_string_lossy()));
+        assert!(!messages.iter().any(|m| matches!(m, Message::Preview(_))));
+        assert_eq!(success_bytes(&messages), None);
+        assert!(matches!(
+            final_summary(&messages).error,
+            Some(Error::ExitCode(1) | Error::EmptyStdout)
+        ));
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_converter_panic_cleanup() {
+        use crate::cleanup_guard::CleanupReport;
+
+        init_logging();
+
+        let cases = [
+            (
+                PanicPoint::AfterSpawn,
+                CleanupReport {
+                    child_reaped: true,
+                    ..Default::default()
+                },
+            ),
+            (
+                PanicPoint::AfterThreads,
+                CleanupReport {
+                    child_reaped: false,
+                    joined: vec![
+                        ThreadKind::Stdin,
+                        ThreadKind::Stdout,
+                        ThreadKind::Stderr,
+                        ThreadKind::Smoother,
+                        ThreadKind::Child,
+                    ],
+                    left_behind: vec![],
+                },
+            ),
+        ];
+        for (panic_at, expected) in cases {
+            // NOTE: The child process would keep running for a while if it was not killed.
+            let pid_path = temp_dir().join("pid");
+            let path = fake_ffmpeg_with_script(
+                SAMPLE_STDERR,
+                b"",
+                &format!("echo $$ > '{}'; exec sleep 30", pid_path.display()),
+            );
+            let settings = Settings::with_standard_fps(SAMPLE_VIDEO_PATH.into(), 200)
+                .ffmpeg_path(path.to_string_lossy())
+                .smooth_progress(true);
+            let (mut converter, _tx, _rx) = Converter::new_with_channels();
+            converter.panic_at = Some(panic_at);
+            let report = std::sync::Arc::clone(&converter.cleanup_report);
+            let started = std::time::Instant::now();
+            let result = std::thread::spawn(move || converter.convert(settings)).join();
+            assert!(result.is_err(), "{:?}", panic_at);
+            assert!(
+                started.elapsed() < Duration::from_secs(10),
+                "{:?}",
+                panic_at
+            );
+            assert_eq!(
+                report.lock().unwrap().clone(),
+                Some(expected),
+                "{:?}",
+                panic_at
+            );
+            // NOTE: The child process may have been killed before writing its PID.
+            if let Ok(pid) = std::fs::read_to_string(&pid_path) {
+                let alive = std::process::Command::new("kill")
+                    .args(["-0", pid.trim()])
+                    .stderr(std::process::Stdio::null())
+                    .status()
+                    .unwrap()
+                    .success();
+                assert!(!alive, "{:?}", panic_at);
+            }
+        }
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_converter_internal_failures() {
+        init_logging();
+
+        // NOTE: The job's mutexes are poisoned by a thread that panics while holding one.
+        let path = fake_ffmpeg(SAMPLE_STDERR, &sample_gif(2, Some(0)), 0);
+        let settings = Settings::with_standard_fps(SAMPLE_VIDEO_PATH.into(), 200)
+            .ffmpeg_path(path.to_string_lossy());
+        let messages = run_job_to_completion_with(
+            {
+                let settings = settings.clone();
+                move |converter| {
+                    let job_cancelled = std::sync::Arc::clone(&converter.job_cancelled);
+                    let _ = std::thread::spawn(move || {
+                        let _lock = job_cancelled.lock().unwrap();
+                        panic!("injected panic (holding job cancellation mutex)");
+                    })
+                    .join();
+                    converter.convert(settings)
+                }
+            },
+            |_tx| {},
+        );
+        assert_eq!(success_bytes(&messages), None);
+        assert!(matches!(
+            final_summary(&messages).error,
+            Some(Error::Internal("job cancellation mutex poisoned"))
+        ));
+
+        // NOTE: The command receiver can only be taken once.
+        let messages = run_job_to_completion_with(
+            move |converter| {
+                converter.rx.take();
+                converter.convert(settings)
+            },
+            |_tx| {},
+        );
+        assert_eq!(success_bytes(&messages), None);
+        assert!(matches!(
+            final_summary(&messages).error,
+            Some(Error::Internal("command receiver already taken"))
+        ));
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_converter_internal_events() {
+        init_logging();
+
+        let path = fake_ffmpeg(SAMPLE_STDERR, &sample_gif(2, Some(0)), 0);
+        let settings = Settings::with_standard_fps(SAMPLE_VIDEO_PATH.into(), 200)
+            .ffmpeg_path(path.to_string_lossy())
+            .smooth_progress(true);
+        let messages = run_to_completion(settings.clone());
+        assert!(success_bytes(&messages).is_some());
+        assert!(!messages.iter().any(|m| matches!(m, Message::Internal(_))));
+
+        let messages = run_to_completion(settings.clone().emit_internal_events(true));
+        assert!(success_bytes(&messages).is_some());
+        let position = |event: InternalEvent| {
+            messages
+                .iter()
+                .position(|m| matches!(m, Message::Internal(e) if *e == event))
+                .unwrap_or_else(|| panic!("{:?} not found", event))
+        };
+        let summary = messages
+            .iter()
+            .position(|m| matches!(m, Message::Summary(_)))
+            .unwrap();
+        for kind in [
+            ThreadKind::Stdin,
+            ThreadKind::Stdout,
+            ThreadKind::Stderr,
+            ThreadKind::Smoother,
+            ThreadKind::Child,
+        ] {
+            let started = position(InternalEvent::ThreadStarted(kind));
+            let exited = position(InternalEvent::ThreadExited(kind, ExitKind::Returned));
+            let joined = position(InternalEvent::ThreadJoined(kind));
+            assert!(
+                started < exited && exited < joined && joined < summary,
+                "{}",
+                kind
+            );
+        }
+        let reaped = position(InternalEvent::ChildReaped { code: Some(0) });
+        assert!(
+            reaped
+                < position(InternalEvent::ThreadExited(
+                    ThreadKind::Child,
+                    ExitKind::Returned
+                ))
+        );
+        // NOTE: The main thread joins the CHILD thread first, and the STDOUT thread only
+        // resolves the outcome once the child process has exited.
+        assert!(
+            position(InternalEvent::ThreadJoined(ThreadKind::Child))
+                < position(InternalEvent::ThreadJoined(ThreadKind::Stdout))
+        );
+        assert!(
+            reaped
+                < messages
+                    .iter()
+                    .position(|m| matches!(m, Message::Success(_)))
+                    .unwrap()
+        );
+
+        // A stuck STDIN thread is given up on, instead of being joined.
+        let messages = run_job_to_completion_with(
+            move |mut converter| {
+                converter.stdin_thread_stall = Some(Duration::from_secs(5));
+                converter.convert(settings.emit_internal_events(true));
+            },
+            |_| {},
+        );
+        let internal: Vec<_> = messages
+            .iter()
+            .filter_map(|m| match m {
+                Message::Internal(e) => Some(*e),
+                _ => None,
+            })
+            .collect();
+        assert!(internal.contains(&InternalEvent::ThreadAbandoned(ThreadKind::Stdin)));
+        assert!(!internal.contains(&InternalEvent::ThreadJoined(ThreadKind::Stdin)));
+        assert!(internal.contains(&InternalEvent::ThreadJoined(ThreadKind::Stdout)));
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_converter_stdin_thread_timeout() {
+        init_logging();
+
+        let path = fake_ffmpeg(SAMPLE_STDERR, &sample_gif(2, Some(0)), 0);
+        let settings = Settings::with_standard_fps(SAMPLE_VIDEO_PATH.into(), 200)
+            .ffmpeg_path(path.to_string_lossy());
+        let started = std::time::Instant::now();
+        let messages = run_job_to_completion_with(
+            move |mut converter| {
+                converter.stdin_thread_stall = Some(Duration::from_secs(30));
+                converter.convert(settings);
+            },
+            |_| {},
+        );
+        assert!(started.elapsed() < Duration::from_secs(20));
+        assert!(success_bytes(&messages).is_some());
+        assert!(matches!(messages.last(), Some(Message::Done)));
+        let summary = final_summary(&messages);
+        assert_eq!(summary.warnings, [Warning::StdinThreadTimeout]);
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_converter_speed_progress() {
+        init_logging();
+
+        let path = fake_ffmpeg_with_clip(true);
+        let settings = Settings::with_standard_fps(SAMPLE_VIDEO_PATH.into(), 200)
+            .ffmpeg_path(path.to_string_lossy())
+            .speed(2.0);
+        let messages = run_to_completion(settings);
+        assert!(success_bytes(&messages).is_some());
+        let args = std::fs::read_to_string(path.with_file_name("args.txt")).unwrap();
+        assert!(
+            args.contains(" -filter_complex setpts=PTS/2,fps=10,"),
+            "{}",
+            args
+        );
+        // NOTE: 1 second of the GIF, which plays the 5 seconds long video in 2.5 seconds.
+        assert!(messages
+            .iter()
+            .any(|m| matches!(m, Message::VideoDuration(d) if *d == Duration::from_secs(5))));
+        assert!(messages
+            .iter()
+            .any(|m| matches!(m, Message::Progress(p) if *p == 0.4)));
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_converter_frame_step_progress() {
+        init_logging();
+
+        let path = fake_ffmpeg_with_clip(true);
+        let settings = Settings::with_standard_fps(SAMPLE_VIDEO_PATH.into(), 200)
+            .ffmpeg_path(path.to_string_lossy())
+            .frame_step(2);
+        let messages = run_to_completion(settings);
+        assert!(success_bytes(&messages).is_some());
+        let args = std::fs::read_to_string(path.with_file_name("args.txt")).unwrap();
+        assert!(
+            args.contains(" -filter_complex select='not(mod(n,2))',setpts=N/FRAME_RATE/TB,fps=10,"),
+            "{}",
+            args
+        );
+        // NOTE: 1 second of the GIF, whose kept frames play the 5 seconds long video in 2.5 seconds.
+        assert!(messages
+            .iter()
+            .any(|m| matches!(m, Message::VideoDuration(d) if *d == Duration::from_secs(5))));
+        assert!(messages
+            .iter()
+            .any(|m| matches!(m, Message::Progress(p) if *p == 0.4)));
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_converter_boomerang_progress() {
+        init_logging();
+
+        let path = fake_ffmpeg_with_clip(true);
+        let settings = Settings::with_standard_fps(SAMPLE_VIDEO_PATH.into(), 200)
+            .ffmpeg_path(path.to_string_lossy())
+            .boomerang(true);
+        let messages = run_to_completion(settings);
+        assert!(success_bytes(&messages).is_some());
+        let args = std::fs::read_to_string(path.with_file_name("args.txt")).unwrap();
+        assert!(
+            args.contains(",split[f][r]; [r]reverse[rr]; [f][rr]concat=n=2[s]; "),
+            "{}",
+            args
+        );
+        // NOTE: 1 second of the GIF, which plays the 5 seconds long video twice.
+        assert!(messages
+            .iter()
+            .any(|m| matches!(m, Message::VideoDuration(d) if *d == Duration::from_secs(10))));
+        assert!(messages
+            .iter()
+            .any(|m| matches!(m, Message::Progress(p) if *p == 0.1)));
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_converter_start_time() {
+        init_logging();
+
+        let path = fake_ffmpeg_with_clip(true);
+        let settings = Settings::with_standard_fps(SAMPLE_VIDEO_PATH.into(), 200)
+            .ffmpeg_path(path.to_string_lossy())
+            .start_time(Duration::from_secs(3));
+        let messages = run_to_completion(settings);
+        assert!(success_bytes(&messages).is_some());
+        let args = std::fs::read_to_string(path.with_file_name("args.txt")).unwrap();
+        assert!(args.starts_with("-stats -ss 3.000 -i "), "{}", args);
+        // The progress is relative to the remaining 2 seconds of the video.
+        assert!(messages
+            .iter()
+            .any(|m| matches!(m, Message::Progress(p) if *p == 0.5)));
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_converter_start_time_past_end() {
+        init_logging();
+
+        // NOTE: Like FFmpeg, which reports the video's duration, and then has nothing to encode.
+        let (header, _) = SAMPLE_STDERR.split_once("frame=").unwrap();
+        let path = fake_ffmpeg(header, &[], 0);
+        let settings = Settings::with_standard_fps(SAMPLE_VIDEO_PATH.into(), 200)
+            .ffmpeg_path(path.to_string_lossy())
+            .start_time(Duration::from_secs(42));
+        let messages = run_to_completion(settings);
+        assert!(messages.iter().any(|m| matches!(
+            m,
+            Message::Error(Error::ClipOutOfRange { start, duration })
+                if *start == Duration::from_secs(42) && *duration == Duration::from_secs(5)
+        )));
+        assert!(!messages
+            .iter()
+            .any(|m| matches!(m, Message::Error(Error::EmptyStdout))));
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_converter_check_capabilities() {
+        init_logging();
+
+        // NOTE: Like FFmpeg, which lists its capabilities when asked to, and converts otherwise.
+        let gif = sample_gif(3, Some(0));
+        let fake_ffmpeg_listing = |filters: &str, encoders: &str| {
+            let dir = temp_dir();
+            std::fs::write(dir.join("filters.txt"), filters).unwrap();
+            std::fs::write(dir.join("encoders.txt"), encoders).unwrap();
+            std::fs::write(dir.join("stderr.txt"), SAMPLE_STDERR).unwrap();
+            std::fs::write(dir.join("stdout.bin"), &gif).unwrap();
+            write_script(
+                &dir,
+                &format!(
+                    "case \"$2\" in\n-filters) cat '{dir}/filters.txt'; exit 0;;\n-encoders) cat '{dir}/encoders.txt'; exit 0;;\nesac\necho \"$@\" >> '{dir}/runs.txt'\ncat '{dir}/stderr.txt' >&2\ncat '{dir}/stdout.bin'",
+                    dir = dir.display()
+                ),
+            )
+        };
+        let settings = |path: &std::path::Path| {
+            Settings::with_standard_fps(SAMPLE_VIDEO_PATH.into(), 200)
+                .ffmpeg_path(path.to_string_lossy())
+                .subtitles("captions.srt".into())
+                .check_capabilities(true)
+        };
+
+        // A slim build: the job fails before FFmpeg is run.
+        let slim = fake_ffmpeg_listing(
+            include_str!("../tests/fixtures/filters_slim.txt"),
+            include_str!("../tests/fixtures/encoders_slim.txt"),
+        );
+        let messages = run_to_completion(settings(&slim));
+        match final_summary(&messages).error {
+            Some(Error::MissingCapability { needed, hint }) => {
+                assert_eq!(needed, crate::Capability::Filter("subtitles"));
+                assert!(hint.contains("libass"), "{}", hint);
+            }
+            e => panic!("Expected a 'MissingCapability' error: {:?}", e),
+        }
+        assert!(!slim.with_file_name("runs.txt").exists());
+        // NOTE: Unless the settings do not require the missing capability.
+        let messages = run_to_completion(
+            Settings::with_standard_fps(SAMPLE_VIDEO_PATH.into(), 200)
+                .ffmpeg_path(slim.to_string_lossy())
+                .check_capabilities(true),
+        );
+        assert_eq!(success_bytes(&messages), Some(&gif[..]));
+
+        // A full build: the job runs.
+        let full = fake_ffmpeg_listing(
+            include_str!("../tests/fixtures/filters_full.txt"),
+            include_str!("../tests/fixtures/encoders_full.txt"),
+        );
+        let messages = run_to_completion(settings(&full));
+        assert_eq!(success_bytes(&messages), Some(&gif[..]));
+
+        // Capabilities that cannot be recognized: the job runs anyway.
+        let path = fake_ffmpeg(SAMPLE_STDERR, &gif, 0);
+        let messages = run_to_completion(settings(&path));
+        assert_eq!(success_bytes(&messages), Some(&gif[..]));
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_converter_hwaccel() {
+        use crate::test_utils::SharedWriter;
+        use crate::{HwAccel, HwAccelFallback};
+
+        init_logging();
+
+        // NOTE: Like FFmpeg on a machine without the GPU it lists, which fails to set up the
+        // hardware accelerated decoder, and exits.
+        let gif = sample_gif(3, Some(0));
+        let (header, _) = SAMPLE_STDERR.split_once("frame=").unwrap();
+        let line =
+            "[h264 @ 0x7f8] Failed setup for format cuda: hwaccel initialisation returned error.";
+        let dir = temp_dir();
+        std::fs::write(
+            dir.join("hwaccels.txt"),
+            "Hardware acceleration methods:\nvdpau\ncuda\nvaapi\n\n",
+        )
+        .unwrap();
+        std::fs::write(dir.join("failure.txt"), format!("{}{}\n", header, line)).unwrap();
+        std::fs::write(dir.join("stderr.txt"), SAMPLE_STDERR).unwrap();
+        std::fs::write(dir.join("stdout.bin"), &gif).unwrap();
+        let path = write_script(
+            &dir,
+            &format!(
+                "if [ \"$2\" = -hwaccels ]; then cat '{dir}/hwaccels.txt'; exit 0; fi\necho \"$@\" >> '{dir}/runs.txt'\ncase \" $* \" in *' -hwaccel '*) cat '{dir}/failure.txt' >&2; exit 1;; esac\ncat '{dir}/stderr.txt' >&2\ncat '{dir}/stdout.bin'",
+                dir = dir.display()
+            ),
+        );
+        let runs = || -> Vec<String> {
+            let runs = std::fs::read_to_string(dir.join("runs.txt")).unwrap_or_default();
+            std::fs::remove_file(dir.join("runs.txt")).ok();
+            runs.lines().map(String::from).collect()
+        };
+        let settings = Settings::with_standard_fps(SAMPLE_VIDEO_PATH.into(), 200)
+            .ffmpeg_path(path.to_string_lossy());
+        let failures = |messages: &[Message]| {
+            messages
+                .iter()
+                .filter(|m| {
+                    matches!(
+                        m,
+                        Message::Warning(Warning::HardwareAccelerationFailed { line: l })
+                            if l == line
+                    )
+                })
+                .count()
+        };
+
+        // Failing: the job fails as soon as FFmpeg reports the failure.
+        let messages = run_to_completion(
+            settings
+                .clone()
+                .hwaccel(HwAccel::Cuda)
+                .hwaccel_fallback(HwAccelFallback::Fail),
+        );
+        match final_summary(&messages).error {
+            Some(Error::HardwareAcceleration { hwaccel, line: l }) => {
+                assert_eq!(hwaccel, HwAccel::Cuda);
+                assert_eq!(l, line);
+            }
+            e => panic!("Expected a 'HardwareAcceleration' error: {:?}", e),
+        }
+        assert_eq!(failures(&messages), 0);
+        let runs_failing = runs();
+        assert_eq!(runs_failing.len(), 1);
+        assert!(runs_failing[0].contains("-hwaccel cuda"));
+
+        // Falling back (by default): the job is run again without acceleration, with the
+        // method picked from those listed by FFmpeg.
+        let messages = run_to_completion(settings.clone().hwaccel(HwAccel::Auto));
+        assert_eq!(success_bytes(&messages), Some(&gif[..]));
+        assert!(
+            final_summary(&messages).error.is_none(),
+            "{:?}",
+            final_summary(&messages).error
+        );
+        assert_eq!(failures(&messages), 1);
+        let runs_falling_back = runs();
+        assert_eq!(runs_falling_back.len(), 2);
+        assert!(runs_falling_back[0].contains("-hwaccel cuda"));
+        assert!(!runs_falling_back[1].contains("-hwaccel"));
+
+        // Also when streaming, the writer being lent to both jobs.
+        let writer = SharedWriter::default();
+        let streamed = writer.clone();
+        let settings_streamed = settings.hwaccel(HwAccel::Cuda);
+        let messages = run_job_to_completion_with(
+            move |converter| converter.convert_to_writer(settings_streamed, streamed),
+            |tx| {
+                std::thread::sleep(Duration::from_secs(60));
+                drop(tx);
+            },
+        );
+        assert_eq!(writer.bytes(), gif);
+        assert!(messages
+            .iter()
+            .any(|m| matches!(m, Message::SuccessWritten(n) if *n == gif.len() as u64)));
+        assert_eq!(failures(&messages), 1);
+        assert_eq!(runs().len(), 2);
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_converter_subtitles_file() {
+        init_logging();
+
+        // NOTE: Like FFmpeg, which fails to initialize the `subtitles` filter, and exits.
+        let (header, _) = SAMPLE_STDERR.split_once("frame=").unwrap();
+        let subtitles = std::path::PathBuf::from("/nonexistent/my captions: part 1.srt");
+        let line = "[Parsed_subtitles_0 @ 0x5581c0a0c2c0] Unable to open /nonexistent/my captions: part 1.srt";
+        let stderr = format!(
+            "{}{}\n[AVFilterGraph @ 0x5581c0a0c2c0] Error initializing filter 'subtitles' with args '/nonexistent/my captions\\: part 1.srt'\n",
+            header, line
+        );
+        let path = fake_ffmpeg(&stderr, &[], 1);
+        let settings = Settings::with_standard_fps(SAMPLE_VIDEO_PATH.into(), 200)
+            .ffmpeg_path(path.to_string_lossy())
+            .subtitles(subtitles.clone());
+        let messages = run_to_completion(settings.clone());
+        match final_summary(&messages).error {
+            Some(Error::SubtitlesFile { path, line: l }) => {
+                assert_eq!(path, subtitles);
+                assert_eq!(l, line);
+            }
+            e => panic!("Expected a 'SubtitlesFile' error: {:?}", e),
+        }
+
+        // NOTE: Without subtitles, such a line is not mistaken for the problem.
+        let settings = Settings::with_standard_fps(SAMPLE_VIDEO_PATH.into(), 200)
+            .ffmpeg_path(path.to_string_lossy());
+        let messages = run_to_completion(settings);
+        assert!(matches!(
+            final_summary(&messages).error,
+            Some(Error::EmptyStdout | Error::ExitCode(1))
+        ));
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_converter_crop_out_of_bounds() {
+        init_logging();
+
+        // NOTE: Like FFmpeg, which fails to configure the filter graph, and exits.
+        let (header, _) = SAMPLE_STDERR.split_once("frame=").unwrap();
+        let line = "[Parsed_crop_0 @ 0x5581c0a0c2c0] Invalid too big or non positive size for width '5000' or height '400'";
+        let stderr = format!("{}{}\nError reinitializing filters!\n", header, line);
+        for exit_code in [0, 1] {
+            let path = fake_ffmpeg(&stderr, &[], exit_code);
+            let settings = Settings::with_standard_fps(SAMPLE_VIDEO_PATH.into(), 200)
+                .ffmpeg_path(path.to_string_lossy())
+                .crop(0, 0, 5000, 400);
+            let messages = run_to_completion(settings);
+            assert!(messages.iter().any(|m| matches!(
+                m,
+                Message::Error(Error::CropOutOfBounds { line: l }) if l == line
+            )));
+        }
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_converter_video_stream_not_found() {
+        init_logging();
+
+        // NOTE: Like FFmpeg, which fails to find the filter graph's input, and exits.
+        let (header, _) = SAMPLE_STDERR.split_once("frame=").unwrap();
+        let line = "[fc#0 @ 0x55d4c8f0c2c0] Stream specifier ':v:1' in filtergraph description [0:v:1]fps=10,scale=200:-1[s]; [s]split[a][b]; [a]palettegen[palette]; [b][palette]paletteuse matches no streams.";
+        let stderr = format!(
+            "{}{}\nError initializing complex filters: Invalid argument\n",
+            header, line
+        );
+        for exit_code in [0, 1] {
+            let path = fake_ffmpeg(&stderr, &[], exit_code);
+            let settings = Settings::with_standard_fps(SAMPLE_VIDEO_PATH.into(), 200)
+                .ffmpeg_path(path.to_string_lossy())
+                .video_stream_index(1);
+            let messages = run_to_completion(settings);
+            assert!(messages.iter().any(|m| matches!(
+                m,
+                Message::Error(Error::VideoStreamNotFound { index: 1, line: l }) if l == line
+            )));
+        }
+
+        // The line is only reported when a stream is selected.
+        let path = fake_ffmpeg(&stderr, &[], 1);
+        let settings = Settings::with_standard_fps(SAMPLE_VIDEO_PATH.into(), 200)
+            .ffmpeg_path(path.to_string_lossy());
+        let messages = run_to_completion(settings);
+        assert!(matches!(
+            final_summary(&messages).error,
+            Some(Error::EmptyStdout | Error::ExitCode(1))
+        ));
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_converter_output_frame_limit() {
+        init_logging();
+
+        let path = fake_ffmpeg_with_clip(true);
+        // NOTE: 20 frames at 10 fps, i.e. the first 2 seconds of the video.
+        let settings = Settings::with_standard_fps(SAMPLE_VIDEO_PATH.into(), 200)
+            .ffmpeg_path(path.to_string_lossy())
+            .output_frame_limit(20);
+        let messages = run_to_completion(settings);
+        assert!(success_bytes(&messages).is_some());
+        let args = std::fs::read_to_string(path.with_file_name("args.txt")).unwrap();
+        assert!(args.contains(" -frames:v 20 -f gif -"), "{}", args);
+        // The progress is relative to the duration of the allowed frames.
+        assert!(messages
+            .iter()
+            .any(|m| matches!(m, Message::VideoDuration(d) if *d == Duration::from_secs(5))));
+        assert!(messages
+            .iter()
+            .any(|m| matches!(m, Message::Progress(p) if *p == 0.5)));
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_converter_clip_requires_duration() {
+        init_logging();
+
+        for clip in [
+            crate::ClipSelection::FromEnd(Duration::from_secs(2)),
+            crate::ClipSelection::Middle(Duration::from_secs(2)),
+        ] {
+            let path = fake_ffmpeg_with_clip(false);
+            let settings = Settings::with_standard_fps(SAMPLE_VIDEO_PATH.into(), 200)
+                .ffmpeg_path(path.to_string_lossy())
+                .clip(clip);
+            let summary = final_summary(&run_to_completion(settings));
+            assert!(matches!(
+                summary.error,
+                Some(Error::InvalidSettings(SettingsError::ClipRequiresDuration))
+            ));
+            // The conversion itself was never started.
+            assert!(!path.with_file_name("args.txt").exists());
+        }
+
+        // Selections that don't depend on the video's duration don't need it.
+        let path = fake_ffmpeg_with_clip(false);
+        let settings = Settings::with_standard_fps(SAMPLE_VIDEO_PATH.into(), 200)
+            .ffmpeg_path(path.to_string_lossy())
+            .clip(crate::ClipSelection::FromStart(Duration::from_secs(2)));
+        let args = {
+            run_to_completion(settings);
+            std::fs::read_to_string(path.with_file_name("args.txt")).unwrap()
+        };
+        assert!(
+            args.starts_with("-stats -ss 0.000 -t 2.000 -i "),
+            "{}",
+            args
+        );
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_converter_probe_timed_out() {
+        init_logging();
+
+        // NOTE: A stand-in for FFmpeg hanging on a malformed file while probing it.
+        let path = write_script(&temp_dir(), "sleep 30");
+        let timeout = Duration::from_millis(200);
+        let settings = Settings::with_standard_fps(SAMPLE_VIDEO_PATH.into(), 200)
+            .ffmpeg_path(path.to_string_lossy())
+            .clip(crate::ClipSelection::FromEnd(Duration::from_secs(2)))
+            .auxiliary_timeout(Some(timeout));
+        let started = std::time::Instant::now();
+        let summary = final_summary(&run_to_completion(settings));
+        assert!(started.elapsed() < Duration::from_secs(10));
+        assert!(matches!(
+            summary.error,
+            Some(Error::Probe(ProbeError::Timeout(t))) if t == timeout
+        ));
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_converter_deadline_exceeded() {
+        init_logging();
+
+        // NOTE: The script waits for the 'q' written to its `stdin` when the deadline passes,
+        // after having written a valid GIF, which must be thrown away.
+        let path = fake_ffmpeg_with_script(
+            SAMPLE_STDERR,
+            &sample_gif(2, None),
+            "timeout 30 head -c 1 > /dev/null",
+        );
+        let deadline = std::time::Instant::now() + Duration::from_millis(500);
+        let settings = Settings::with_standard_fps(SAMPLE_VIDEO_PATH.into(), 200)
+            .ffmpeg_path(path.to_string_lossy())
+            .deadline(deadline);
+        // NOTE: The deadline is still enforced once the command sender has been dropped.
+        let messages = run_to_completion_with(settings, drop);
+        assert!(deadline.elapsed() < Duration::from_secs(10));
+        assert!(success_bytes(&messages).is_none());
+        let summary = final_summary(&messages);
+        assert_eq!(summary.outcome(), crate::Outcome::Failed);
+        assert!(matches!(
+            summary.error,
+            Some(Error::DeadlineExceeded { overshoot }) if overshoot < Duration::from_secs(5)
+        ));
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_converter_deadline_in_past() {
+        init_logging();
+
+        let dir = temp_dir();
+        let path = write_script(&dir, &format!("touch '{}'", dir.join("spawned").display()));
+        let deadline = std::time::Instant::now();
+        std::thread::sleep(Duration::from_millis(50));
+        let settings = Settings::with_standard_fps(SAMPLE_VIDEO_PATH.into(), 200)
+            .ffmpeg_path(path.to_string_lossy())
+            .clip(crate::ClipSelection::FromEnd(Duration::from_secs(2)))
+            .deadline(deadline);
+        let summary = final_summary(&run_to_completion(settings));
+        assert!(matches!(
+            summary.error,
+            Some(Error::DeadlineExceeded { overshoot }) if overshoot >= Duration::from_millis(50)
+        ));
+        assert!(!dir.join("spawned").exists());
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_converter_deadline_and_auxiliary_timeout() {
+        init_logging();
+
+        // NOTE: A stand-in for FFmpeg hanging on a malformed file while probing it.
+        let path = write_script(&temp_dir(), "sleep 30");
+        let settings = |timeout: u64, deadline: u64| {
+            Settings::with_standard_fps(SAMPLE_VIDEO_PATH.into(), 200)
+                .ffmpeg_path(path.to_string_lossy())
+                .clip(crate::ClipSelection::FromEnd(Duration::from_secs(2)))
+                .auxiliary_timeout(Some(Duration::from_millis(timeout)))
+                .deadline(std::time::Instant::now() + Duration::from_millis(deadline))
+        };
+
+        // The deadline comes first.
+        let started = std::time::Instant::now();
+        let summary = final_summary(&run_to_completion(settings(5_000, 200)));
+        assert!(started.elapsed() < Duration::from_secs(4));
+        assert!(matches!(
+            summary.error,
+            Some(Error::DeadlineExceeded { .. })
+        ));
+
+        // The auxiliary timeout comes first.
+        let summary = final_summary(&run_to_completion(settings(200, 30_000)));
+        assert!(matches!(
+            summary.error,
+            Some(Error::Probe(ProbeError::Timeout(t))) if t == Duration::from_millis(200)
+        ));
+    }
+
+    /// The measures reported using [`Warning::MemoryBudgetSpill`], in order.
+    fn budget_measures(messages: &[Message]) -> Vec<BudgetMeasure> {
+        messages
+            .iter()
+            .filter_map(|m| match m {
+                Message::Warning(Warning::MemoryBudgetSpill { measure }) => Some(*measure),
+                _ => None,
+            })
+            .collect()
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_converter_memory_budget_spill() {
+        init_logging();
+
+        let gif = sample_gif(2_000, Some(0));
+        let path = fake_ffmpeg(SAMPLE_STDERR, &gif, 0);
+        let settings = Settings::with_standard_fps(SAMPLE_VIDEO_PATH.into(), 200)
+            .ffmpeg_path(path.to_string_lossy())
+            .memory_budget(16 * 1024);
+        let messages = run_to_completion(settings);
+        assert_eq!(success_bytes(&messages), Some(&gif[..]));
+        let measures = budget_measures(&messages);
+        assert_eq!(
+            measures
+                .iter()
+                .filter(|m| **m == BudgetMeasure::StdoutSpilledToDisk)
+                .count(),
+            1
+        );
+        assert!(final_summary(&messages)
+            .warnings
+            .contains(&Warning::MemoryBudgetSpill {
+                measure: BudgetMeasure::StdoutSpilledToDisk
+            }));
+
+        // NOTE: Kept in memory within a large enough budget.
+        let settings = Settings::with_standard_fps(SAMPLE_VIDEO_PATH.into(), 200)
+            .ffmpeg_path(path.to_string_lossy())
+            .memory_budget(16 * 1024 * 1024);
+        let messages = run_to_completion(settings);
+        assert_eq!(success_bytes(&messages), Some(&gif[..]));
+        assert!(budget_measures(&messages).is_empty());
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_converter_memory_budget_progress() {
+        init_logging();
+
+        let path = fake_ffmpeg_with_progress(40, 0.01);
+        // NOTE: A sixteenth of the budget for 8 progress messages and 8 output size reports.
+        let budget = 16 * 16 * std::mem::size_of::<Message>();
+        let steps = MemoryBudget::new(budget).progress_steps();
+        assert_eq!(steps, 8);
+        let settings = Settings::with_standard_fps(SAMPLE_VIDEO_PATH.into(), 200)
+            .ffmpeg_path(path.to_string_lossy())
+            .smooth_progress(true)
+            .memory_budget(budget);
+        let messages = run_to_completion(settings);
+        assert!(success_bytes(&messages).is_some());
+        let progress = messages.iter().filter(|m| m.is_progress()).count();
+        assert!(
+            progress > 0 && progress <= 2 * (steps + 1),
+            "{:?}",
+            messages
+        );
+        assert!(budget_measures(&messages).contains(&BudgetMeasure::ProgressThinned));
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_converter_memory_budget_stderr() {
+        init_logging();
+
+        // NOTE: Each line is larger than the budget's share for `stderr` (i.e. 128 bytes).
+        let line = "x".repeat(200);
+        let path = fake_ffmpeg(&format!("{}\n{}\n", SAMPLE_STDERR, line), &[], 1);
+        let settings = Settings::with_standard_fps(SAMPLE_VIDEO_PATH.into(), 200)
+            .ffmpeg_path(path.to_string_lossy())
+            .memory_budget(1024);
+        let messages = run_to_completion(settings);
+        assert!(matches!(
+            final_summary(&messages).error,
+            Some(Error::ExitCode(1))
+        ));
+        let measures = budget_measures(&messages);
+        assert_eq!(
+            measures
+                .iter()
+                .filter(|m| **m == BudgetMeasure::StderrTruncated)
+                .count(),
+            1
+        );
+    }
+
+    #[test]
+    fn test_stderr_report_memory_budget() {
+        let budget = std::sync::Arc::new(MemoryBudget::new(8 * 100));
+        let mut report = StderrReport {
+            memory_budget: Some(std::sync::Arc::clone(&budget)),
+            ..StderrReport::default()
+        };
+        let line = |text: &str| StderrLine {
+            text: text.into(),
+            truncated: false,
+        };
+        assert!(!report.push(&line(&"a".repeat(40))));
+        assert!(!report.push(&line(&"b".repeat(40))));
+        // NOTE: The first line is dropped to make room (within the share of 100 bytes).
+        assert!(report.push(&line(&"c".repeat(40))));
+        assert_eq!(
+            report.tail(),
+            format!("{}\n{}", "b".repeat(40), "c".repeat(40))
+        );
+        // NOTE: Reported once only.
+        assert!(!report.push(&line(&"d".repeat(200))));
+        assert_eq!(report.tail(), "");
+        assert!(!report.push(&line("e")));
+        assert_eq!(report.tail(), "e");
+    }
+
+    #[test]
+    fn test_converter_memory_budget_end_to_end() {
+        init_logging();
+        if !crate::test_utils::ffmpeg_available() {
+            return;
+        }
+
+        let settings = || Settings::with_standard_fps(SAMPLE_VIDEO_PATH.into(), 1280);
+        let expected = crate::test_utils::run_to_completion(settings());
+        let expected =
+            crate::test_utils::success_bytes(&expected).expect("Expected a 'Success' message");
+        let messages = crate::test_utils::run_to_completion(settings().memory_budget(64 * 1024));
+        let bytes =
+            crate::test_utils::success_bytes(&messages).expect("Expected a 'Success' message");
+        assert!(budget_measures(&messages).contains(&BudgetMeasure::StdoutSpilledToDisk));
+        assert_eq!(bytes, expected);
+        assert_eq!(parse_gif_info(bytes).unwrap().width, 1280);
+    }
+
+    /// The values of the [`Message::OutputBytes`] messages, in order.
+    fn output_bytes(messages: &[Message]) -> Vec<u64> {
+        messages
+            .iter()
+            .filter_map(|m| match m {
+                Message::OutputBytes(n) => Some(*n),
+                _ => None,
+            })
+            .collect()
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_converter_output_bytes() {
+        init_logging();
+
+        // Writing the GIF in three parts, far enough apart to be reported separately.
+        let gif = sample_gif(3, Some(0));
+        let dir = temp_dir();
+        let (a, b) = (gif.len() / 3, 2 * gif.len() / 3);
+        for (name, part) in [("a", &gif[..a]), ("b", &gif[a..b]), ("c", &gif[b..])] {
+            std::fs::write(dir.join(name), part).unwrap();
+        }
+        std::fs::write(dir.join("stderr.txt"), SAMPLE_STDERR).unwrap();
+        let path = write_script(
+            &dir,
+            &format!(
+                "cat '{dir}/stderr.txt' >&2\ncat '{dir}/a'\nsleep 0.3\ncat '{dir}/b'\nsleep 0.3\ncat '{dir}/c'",
+                dir = dir.display()
+            ),
+        );
+        let settings = Settings::with_standard_fps(SAMPLE_VIDEO_PATH.into(), 200)
+            .ffmpeg_path(path.to_string_lossy());
+        let messages = run_to_completion(settings);
+        assert_eq!(
+            output_bytes(&messages),
+            vec![a as u64, b as u64, gif.len() as u64]
+        );
+        let last = messages
+            .iter()
+            .rposition(|m| matches!(m, Message::OutputBytes(_)))
+            .unwrap();
+        let success = messages
+            .iter()
+            .position(|m| matches!(m, Message::Success(_)))
+            .unwrap();
+        assert!(last < success);
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_converter_progress_curve() {
+        init_logging();
+
+        // NOTE: FFmpeg's `time` reaches 96% of the duration before the first bytes of
+        // output are written, and then stalls while the rest of the GIF is written.
+        let (header, _) = SAMPLE_STDERR.split_once("frame=").unwrap();
+        let gif = sample_gif(2, Some(0));
+        let dir = temp_dir();
+        std::fs::write(dir.join("header.txt"), header).unwrap();
+        std::fs::write(dir.join("a"), &gif[..6]).unwrap();
+        std::fs::write(dir.join("b"), &gif[6..]).unwrap();
+        let line = |time: &str| {
+            format!(
+                "sleep 0.2; printf 'frame= 1 fps=0.0 q=-0.0 size= 0kB time=00:00:{} bitrate= 0.0kbits/s speed=1x\\r' >&2",
+                time
+            )
+        };
+        let path = write_script(
+            &dir,
+            &format!(
+                "cat '{dir}/header.txt' >&2\n{}\n{}\ncat '{dir}/a'\nsleep 0.3\n{}\n{}\ncat '{dir}/b'",
+                line("02.00"),
+                line("04.80"),
+                line("04.90"),
+                line("05.00"),
+                dir = dir.display()
+            ),
+        );
+        let progress = |curve| {
+            let settings = Settings::with_standard_fps(SAMPLE_VIDEO_PATH.into(), 200)
+                .ffmpeg_path(path.to_string_lossy())
+                .progress_curve(curve);
+            let messages = run_to_completion(settings);
+            assert!(success_bytes(&messages).is_some());
+            messages
+                .iter()
+                .filter_map(|m| match m {
+                    Message::Progress(p) => Some(*p),
+                    _ => None,
+                })
+                .collect::<Vec<_>>()
+        };
+        let close = |actual: Vec<f64>, expected: &[f64]| {
+            actual.len() == expected.len()
+                && actual
+                    .iter()
+                    .zip(expected)
+                    .all(|(a, e)| (a - e).abs() < 1e-9)
+        };
+
+        let linear = progress(crate::ProgressCurve::Linear);
+        assert!(
+            close(linear.clone(), &[0.4, 0.96, 0.98, 1.0]),
+            "{:?}",
+            linear
+        );
+        let calibrated = progress(crate::ProgressCurve::CALIBRATED);
+        assert!(
+            close(calibrated.clone(), &[0.2, 0.48, 0.74, 1.0]),
+            "{:?}",
+            calibrated
+        );
+
+        let settings = Settings::with_standard_fps(SAMPLE_VIDEO_PATH.into(), 200).progress_curve(
+            crate::ProgressCurve::TwoPhase {
+                analysis_share: 1.0,
+            },
+        );
+        assert_eq!(
+            settings.validate(),
+            Err(crate::SettingsError::InvalidProgressCurve)
+        );
+    }
+
+    /// Measures the share of the wall time taken by the palette analysis regime (i.e.
+    /// until the first bytes of output are read) when converting the bundled clip, which
+    /// [`crate::ProgressCurve::CALIBRATED`] is based on.
+    #[test]
+    fn test_progress_curve_calibration() {
+        init_logging();
+        if !crate::test_utils::ffmpeg_available() {
+            return;
+        }
+
+        let settings = Settings::with_standard_fps(SAMPLE_VIDEO_PATH.into(), 200)
+            .progress_curve(crate::ProgressCurve::CALIBRATED);
+        let messages = crate::test_utils::run_to_completion_timed(settings);
+        let time_of = |f: fn(&Message) -> bool| {
+            messages
+                .iter()
+                .find(|(_, m)| f(m))
+                .map(|(at, _)| at.as_secs_f64())
+                .unwrap()
+        };
+        let first_output = time_of(|m| matches!(m, Message::OutputBytes(_)));
+        let first_progress = time_of(|m| matches!(m, Message::Progress(_)));
+        let success = time_of(|m| matches!(m, Message::Success(_)));
+        let share = (first_output - first_progress) / (success - first_progress);
+        log::info!("Palette analysis share of the wall time: {:.2}", share);
+        let crate::ProgressCurve::TwoPhase { analysis_share } = crate::ProgressCurve::CALIBRATED
+        else {
+            unreachable!();
+        };
+        assert!((share - analysis_share).abs() < 0.3, "{}", share);
+    }
+
+    #[test]
+    fn test_read_to_end_counting_rate_limit() {
+        let data = vec![7u8; 1000];
+        // NOTE: A reader returning a single byte at a time.
+        struct OneByte<R>(R);
+        impl<R: std::io::Read> std::io::Read for OneByte<R> {
+            fn read(&mut self, buf: &mut [u8]) -> std::io::Result<usize> {
+                let n = buf.len().min(1);
+                self.0.read(&mut buf[..n])
+            }
+        }
+        let count = std::sync::atomic::AtomicUsize::new(0);
+        let mut buf = OutputBuffer::default();
+        let mut reports = vec![];
+        let n = read_to_end_counting(
+            &mut OneByte(&data[..]),
+            &mut buf,
+            &count,
+            Duration::from_secs(60),
+            |n| reports.push(n),
+            || panic!("Not spilled over without a memory budget"),
+        )
+        .unwrap();
+        assert_eq!(n, 1000);
+        assert_eq!(buf.into_bytes().unwrap(), data);
+        assert_eq!(count.load(std::sync::atomic::Ordering::SeqCst), 1000);
+        // The first chunk, and the final count.
+        assert_eq!(reports, vec![1, 1000]);
+    }
+
+    #[test]
+    fn test_read_to_end_counting_read_failure() {
+        struct Failing;
+        impl std::io::Read for Failing {
+            fn read(&mut self, _: &mut [u8]) -> std::io::Result<usize> {
+                Err(std::io::Error::other("broken pipe"))
+            }
+        }
+        let count = std::sync::atomic::AtomicUsize::new(0);
+        let result = read_to_end_counting(
+            &mut Failing,
+            &mut OutputBuffer::default(),
+            &count,
+            Duration::from_secs(60),
+            |_| {},
+            || {},
+        );
+        assert!(matches!(result, Err(Error::ChildProcess(_))));
+    }
+
+    #[test]
+    fn test_converter_output_bytes_end_to_end() {
+        init_logging();
+        if !crate::test_utils::ffmpeg_available() {
+            return;
+        }
+
+        let settings = Settings::with_standard_fps(SAMPLE_VIDEO_PATH.into(), 200);
+        let messages = crate::test_utils::run_to_completion(settings);
+        let bytes =
+            crate::test_utils::success_bytes(&messages).expect("Expected a 'Success' message");
+        let counts = output_bytes(&messages);
+        assert!(!counts.is_empty());
+        assert!(counts.windows(2).all(|w| w[0] < w[1]), "{:?}", counts);
+        assert_eq!(counts.last().copied(), Some(bytes.len() as u64));
+    }
+
+    #[test]
+    fn test_converter_clip_from_end_end_to_end() {
+        init_logging();
+        if !crate::test_utils::ffmpeg_available() {
+            return;
+        }
+
+        let settings = Settings::with_standard_fps(SAMPLE_VIDEO_PATH.into(), 100)
+            .clip(crate::ClipSelection::FromEnd(Duration::from_secs(2)));
+        let messages = crate::test_utils::run_to_completion(settings);
+        let bytes =
+            crate::test_utils::success_bytes(&messages).expect("Expected a 'Success' message");
+        let info = parse_gif_info(bytes).unwrap();
+        // About 2 seconds at 10 frames per second.
+        assert!((18..=22).contains(&info.frame_count), "{:?}", info);
+    }
+
+    #[test]
+    fn test_converter_unresolved_ffmpeg_location() {
+        init_logging();
+
+        // NOTE: The application's executable is the test binary itself.
+        let location = crate::FfmpegLocation::RelativeToExe("no-such-dir/ffmpeg".into());
+        let settings = Settings::with_standard_fps(SAMPLE_VIDEO_PATH.into(), 200)
+            .ffmpeg_location(location.clone());
+        let summary = final_summary(&run_to_completion(settings));
+        assert_eq!(summary.outcome(), crate::Outcome::Failed);
+        assert!(matches!(
+            summary.error,
+            Some(Error::FfmpegLocation(crate::FfmpegLocationError::Missing(
+                _
+            )))
+        ));
+
+        let settings =
+            StripSettings::new(SAMPLE_VIDEO_PATH.into(), 4, 100).ffmpeg_location(location);
+        let messages = run_job_to_completion_with(
+            move |converter| converter.extract_thumbnail_strip(settings),
+            drop,
+        );
+        assert!(matches!(
+            final_summary(&messages).error,
+            Some(Error::FfmpegLocation(_))
+        ));
+    }
+
+    #[cfg(unix)]
+    /// A fake FFmpeg binary that writes `images` during the thumbnail strip extraction
+    /// (followed by `script`), and the sample transcript when probing the duration.
+    fn fake_ffmpeg_with_thumbnails(images: &[u8], script: &str) -> std::path::PathBuf {
+        let dir = temp_dir();
+        std::fs::write(dir.join("images.bin"), images).unwrap();
+        std::fs::write(dir.join("stderr.txt"), SAMPLE_STDERR).unwrap();
+        write_script(
+            &dir,
+            &format!(
+                r#"case "$*" in
+  *image2pipe*) cat '{dir}/images.bin'; {script};;
+  *) cat '{dir}/stderr.txt' >&2; exit 1;;
+esac"#,
+                dir = dir.display(),
+                script = script
+            ),
+        )
+    }
+
+    fn thumbnails(messages: &[Message]) -> Vec<(usize, Duration, &[u8])> {
+        messages
+            .iter()
+            .filter_map(|m| match m {
+                Message::Thumbnail {
+                    index,
+                    timestamp,
+                    bytes,
+                } => Some((*index, *timestamp, &bytes[..])),
+                _ => None,
+            })
+            .collect()
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_thumbnail_strip_scripted() {
+        init_logging();
+
+        let images: Vec<Vec<u8>> = (0..4u8).map(|i| sample_png(&[i; 100])).collect();
+        let path = fake_ffmpeg_with_thumbnails(&images.concat(), "exit 0");
+        let settings = StripSettings::new(SAMPLE_VIDEO_PATH.into(), 4, 100)
+            .format(crate::ThumbnailFormat::Png)
+            .ffmpeg_path(path.to_string_lossy());
+        let messages = run_job_to_completion_with(
+            move |converter| converter.extract_thumbnail_strip(settings),
+            drop,
+        );
+        assert!(matches!(
+            messages.first(),
+            Some(Message::VideoDuration(d)) if *d == Duration::from_secs(5)
+        ));
+        let thumbnails = thumbnails(&messages);
+        assert_eq!(thumbnails.len(), 4);
+        for (i, (index, timestamp, bytes)) in thumbnails.iter().enumerate() {
+            assert_eq!(*index, i);
+            assert_eq!(*timestamp, Duration::from_millis(1250 * i as u64));
+            assert_eq!(*bytes, &images[i][..]);
+        }
+        let summary = final_summary(&messages);
+        assert_eq!(summary.outcome(), crate::Outcome::Succeeded);
+        assert_eq!(summary.output_bytes, Some(images.concat().len()));
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_thumbnail_strip_errors() {
+        init_logging();
+
+        let run = |settings: StripSettings| {
+            final_summary(&run_job_to_completion_with(
+                move |converter| converter.extract_thumbnail_strip(settings),
+                drop,
+            ))
+            .error
+        };
+
+        let path = fake_ffmpeg_with_thumbnails(b"", "exit 0");
+        let settings = StripSettings::new(SAMPLE_VIDEO_PATH.into(), 4, 100);
+        assert!(matches!(
+            run(settings.clone().ffmpeg_path(path.to_string_lossy())),
+            Some(Error::EmptyStdout)
+        ));
+        assert!(matches!(
+            run(StripSettings::new(SAMPLE_VIDEO_PATH.into(), 0, 100)),
+            Some(Error::InvalidSettings(_))
+        ));
+        let path = fake_ffmpeg("no duration here", b"", 1);
+        assert!(matches!(
+            run(settings.ffmpeg_path(path.to_string_lossy())),
+            Some(Error::UnknownDuration)
+        ));
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_thumbnail_strip_cancelled() {
+        init_logging();
+
+        let path = fake_ffmpeg_with_thumbnails(&sample_png(b"first"), "exec sleep 30");
+        let settings = StripSettings::new(SAMPLE_VIDEO_PATH.into(), 4, 100)
+            .format(crate::ThumbnailFormat::Png)
+            .ffmpeg_path(path.to_string_lossy());
+        let started = std::time::Instant::now();
+        let messages = run_job_to_completion_with(
+            move |converter| converter.extract_thumbnail_strip(settings),
+            |tx| {
+                std::thread::sleep(Duration::from_millis(500));
+                assert!(send_command(&tx, Command::Cancel));
+            },
+        );
+        assert!(started.elapsed() < Duration::from_secs(10));
+        assert!(matches!(
+            &messages[..],
+            [
+                Message::VideoDuration(_),
+                Message::Thumbnail { index: 0, .. },
+                Message::Error(Error::Cancelled {
+                    progress_at_cancel: None,
+                    bytes_discarded: 0
+                }),
+                Message::Summary(_),
+                Message::Done
+            ]
+        ));
+        assert_eq!(
+            final_summary(&messages).outcome(),
+            crate::Outcome::Cancelled
+        );
+    }
+
+    #[test]
+    fn test_thumbnail_strip_end_to_end() {
+        init_logging();
+        if !crate::test_utils::ffmpeg_available() {
+            return;
+        }
 
-                log::info!(target: LOG_TARGET_STDIN, "{} Exiting STDIN thread...", id_stdin);
-            }
-        });
+        let settings = StripSettings::new(SAMPLE_VIDEO_PATH.into(), 5, 80);
+        let messages = run_job_to_completion_with(
+            move |converter| converter.extract_thumbnail_strip(settings),
+            |tx| {
+                std::thread::sleep(Duration::from_secs(60));
+                drop(tx);
+            },
+        );
+        let thumbnails = thumbnails(&messages);
+        assert_eq!(thumbnails.len(), 5);
+        assert!(thumbnails.windows(2).all(|w| w[0].1 < w[1].1));
+        assert!(thumbnails
+            .iter()
+            .all(|(_, _, bytes)| bytes.starts_with(&[0xff, 0xd8])));
+        assert_eq!(
+            final_summary(&messages).outcome(),
+            crate::Outcome::Succeeded
+        );
+    }
 
-        let tx_stdout = self.tx.clone();
-        let job_cancelled_stdout = std::sync::Arc::clone(&self.job_cancelled);
-        let job_ended_stdout = std::sync::Arc::clone(&self.job_ended);
-        let id_stdout = self.id();
-        let handle_stdout = std::thread::spawn(move || {
-            log::info!(target: LOG_TARGET_STDOUT, "{} Entered STDOUT thread.", id_stdout);
+    #[cfg(unix)]
+    #[test]
+    fn test_converter_split_channels() {
+        init_logging();
 
-            use std::io::Read;
+        let path = fake_ffmpeg_with_progress(10, 0.05);
+        let settings = Settings::with_standard_fps(SAMPLE_VIDEO_PATH.into(), 200)
+            .ffmpeg_path(path.to_string_lossy());
 
-            let mut buf: Vec<u8> = vec![];
-            log::info!(target: LOG_TARGET_STDOUT, "{} Waiting to read all STDOUT bytes into buffer...", id_stdout);
-            match stdout.read_to_end(&mut buf) {
-                Err(e) => {
-                    log::error!(target: LOG_TARGET_STDOUT, "{} Failed to read to end: {:?}", id_stdout, e);
-                    panic!();
-                }
-                Ok(n) => {
-                    log::info!(target: LOG_TARGET_STDOUT, "{} Successfully read to end (size: {}).", id_stdout, n);
-                    log::trace!(target: LOG_TARGET_STDOUT, "{} Logging full buffer:\n{:?}", id_stdout, buf);
+        #[cfg(not(feature = "tokio"))]
+        let (converter, _tx, progress_rx, rx) = Converter::new_with_split_channels();
+        #[cfg(feature = "tokio")]
+        let (converter, _tx, mut progress_rx, mut rx) = Converter::new_with_split_channels();
+        let handle = std::thread::spawn(move || converter.convert(settings));
+        let mut lifecycle = vec![];
+        #[cfg(not(any(feature = "tokio", feature = "async-channel")))]
+        while let Ok(message) = rx.recv() {
+            lifecycle.push(message);
+        }
+        #[cfg(feature = "async-channel")]
+        while let Ok(message) = rx.recv_blocking() {
+            lifecycle.push(message);
+        }
+        #[cfg(feature = "tokio")]
+        while let Some(message) = rx.blocking_recv() {
+            lifecycle.push(message);
+        }
+        handle.join().unwrap();
+        let mut progress = vec![];
+        while let Ok(message) = progress_rx.try_recv() {
+            progress.push(message);
+        }
 
-                    log::debug!(target: LOG_TARGET_STDOUT, "{} Trying to acquire job cancellation mutex to check whether job has been cancelled, to avoid sending bytes down channel it case it has...", id_stdout);
-                    let job_cancelled = {
-                        let job_cancelled = match job_cancelled_stdout.lock() {
-                            Ok(m) => {
-                                log::debug!(target: LOG_TARGET_STDOUT, "{} Successfully acquired job cancellation mutex.", id_stdout);
-                                m
-                            }
-                            Err(e) => {
-                                log::error!(target: LOG_TARGET_STDOUT, "{} Failed to acquire job cancellation mutex: {:?}", id_stdout, e);
-                                panic!();
-                            }
-                        };
-                        *job_cancelled
-                    };
+        assert!(lifecycle.iter().all(|m| !m.is_progress()));
+        assert!(matches!(lifecycle.first(), Some(Message::VideoDuration(_))));
+        assert!(success_bytes(&lifecycle).is_some());
+        assert!(matches!(
+            final_summary(&lifecycle).outcome(),
+            crate::Outcome::Succeeded
+        ));
+        assert!(progress.iter().all(|m| m.is_progress()));
+        assert_eq!(
+            progress
+                .iter()
+                .filter(|m| matches!(m, Message::Progress(_)))
+                .count(),
+            10
+        );
+        assert!(progress
+            .iter()
+            .any(|m| matches!(m, Message::OutputBytes(_))));
+    }
 
-                    if !job_cancelled {
-                        log::debug!(target: LOG_TARGET_STDOUT, "{} Job has not been cancelled, so checking whether there is data in buffer...", id_stdout);
-                        if buf.is_empty() {
-                            log::warn!(target: LOG_TARGET_STDOUT, "{} Empty buffer found, so send 'empty stdout' error message down channel.", id_stdout);
-                            match tx_stdout.send(Message::Error(Error::EmptyStdout)) {
-                                Ok(_) => {
-                                    log::debug!(target: LOG_TARGET_STDOUT, "{} Successfully sent error message down channel.", id_stdout);
-                                }
-                                Err(e) => {
-                                    log::error!(target: LOG_TARGET_STDOUT, "{} Failed to send error message down channel: {:?}", id_stdout, e);
-                                    panic!();
-                                }
-                            }
-                        } else {
-                            match tx_stdout.send(Message::Success(buf)) {
-                                Ok(_) => {
-                                    log::debug!(target: LOG_TARGET_STDOUT, "{} Successfully sent STDOUT data down channel.", id_stdout);
-                                }
-                                Err(e) => {
-                                    log::error!(target: LOG_TARGET_STDOUT, "{} Failed to send STDOUT data down channel: {:?}", id_stdout, e);
-                                    panic!();
-                                }
-                            }
-                        }
-                    } else {
-                        log::warn!(target: LOG_TARGET_STDOUT, "{} Job has been marked as cancelled, so not sending data down channel.", id_stdout);
-                    }
-                }
+    #[cfg(unix)]
+    #[test]
+    fn test_converter_bounded_channel_cancel() {
+        init_logging();
+
+        // NOTE: The script waits for the 'q' written to its `stdin` when cancelling.
+        let path = fake_ffmpeg_with_progress_script(20, 0.02, "", "head -c 1 > /dev/null");
+        let settings = Settings::with_standard_fps(SAMPLE_VIDEO_PATH.into(), 200)
+            .ffmpeg_path(path.to_string_lossy());
+
+        #[cfg(not(feature = "tokio"))]
+        let (converter, tx, rx) = Converter::new_with_bounded_channel(4);
+        #[cfg(feature = "tokio")]
+        let (converter, tx, mut rx) = Converter::new_with_bounded_channel(4);
+        let handle = std::thread::spawn(move || converter.convert(settings));
+        // Letting the channel fill up with progress messages.
+        std::thread::sleep(Duration::from_millis(1000));
+        let cancelled_at = std::time::Instant::now();
+        assert!(send_command(&tx, Command::Cancel));
+        let mut messages = vec![];
+        loop {
+            #[cfg(not(any(feature = "tokio", feature = "async-channel")))]
+            let message = rx.recv().unwrap();
+            #[cfg(feature = "tokio")]
+            let message = rx.blocking_recv().unwrap();
+            #[cfg(feature = "async-channel")]
+            let message = rx.recv_blocking().unwrap();
+            if let Message::Done = message {
+                break;
             }
+            messages.push(message);
+        }
+        let elapsed = cancelled_at.elapsed();
+        handle.join().unwrap();
 
-            log::debug!(target: LOG_TARGET_STDOUT, "{} Trying to acquire 'job ended' mutex to set it to 'true'...", id_stdout);
-            let mut job_ended = match job_ended_stdout.lock() {
-                Err(e) => {
-                    log::error!(target: LOG_TARGET_STDOUT, "{} Failed to acquire 'job ended' mutex to set it to 'true': {:?}", id_stdout, e);
-                    panic!();
-                }
-                Ok(m) => {
-                    log::debug!(target: LOG_TARGET_STDOUT, "{} Successfully acquired 'job ended' mutex and set it to 'true'.", id_stdout);
-                    m
-                }
-            };
-            *job_ended = true;
+        // The first four messages were sent before the cancellation, and the
+        // progress messages sent while the channel was full were dropped.
+        assert!(matches!(messages[0], Message::VideoDuration(_)));
+        assert!(matches!(messages[1], Message::CodecSelection { .. }));
+        assert!(matches!(messages[2], Message::OutputDimensions { .. }));
+        assert!(messages[3].is_progress());
+        assert!(messages[4..].iter().all(|m| !m.is_progress()));
+        assert!(matches!(
+            messages[4..],
+            [Message::Error(Error::Cancelled { .. }), Message::Summary(_)]
+        ));
+        // NOTE: Generous, since the fake FFmpeg process needs to exit first.
+        assert!(
+            elapsed < Duration::from_millis(10 * STDIN_THREAD_SLEEP_DURATION_MS),
+            "{:?}",
+            elapsed
+        );
+    }
+
+    #[cfg(unix)]
+    /// Writes a fake FFmpeg script which records its PID, writes the sample output's
+    /// header, and then runs `body` (in which `{dir}` is the directory containing a
+    /// valid GIF named `stdout.bin`).
+    fn fake_ffmpeg_recording_pid(body: &str) -> std::path::PathBuf {
+        let (header, _) = SAMPLE_STDERR.split_once("frame=").unwrap();
+        let dir = temp_dir();
+        std::fs::write(dir.join("header.txt"), header).unwrap();
+        std::fs::write(dir.join("stdout.bin"), sample_gif(2, Some(0))).unwrap();
+        let body = body.replace("{dir}", &dir.display().to_string());
+        write_script(
+            &dir,
+            &format!(
+                "echo $$ > '{dir}/pid.txt'\ncat '{dir}/header.txt' >&2\n{body}",
+                dir = dir.display()
+            ),
+        )
+    }
+
+    #[cfg(unix)]
+    /// The body of a fake FFmpeg binary (see [`fake_ffmpeg_recording_pid`]) standing for a
+    /// long job (5 seconds), which is made of short `sleep` calls, since those would keep the
+    /// pipes open after the shell gets killed.
+    const LONG_JOB: &str = "for i in $(seq 1 50); do sleep 0.1; printf 'frame= %d fps=0.0 q=-0.0 size= 0kB time=00:00:%02d.00 bitrate= 0.0kbits/s speed=1x\\r' $i $((i / 10)) >&2; done\ncat '{dir}/stdout.bin'";
 
-            log::info!(target: LOG_TARGET_STDOUT, "{} Exiting STDOUT thread...", id_stdout);
+    #[cfg(unix)]
+    /// Runs a job using the fake FFmpeg binary at `path`, drops the message receiver
+    /// once `kept` messages have been received, and makes sure that the job shuts down
+    /// cleanly: `convert` returns (i.e. all the threads exit, without panicking), and the
+    /// child process is reaped. Returns the kept messages and the job's duration.
+    fn assert_clean_shutdown(path: std::path::PathBuf, kept: usize) -> (Vec<Message>, Duration) {
+        let settings = Settings::with_standard_fps(SAMPLE_VIDEO_PATH.into(), 200)
+            .ffmpeg_path(path.to_string_lossy());
+        #[cfg(not(feature = "tokio"))]
+        let (converter, _tx, rx) = Converter::new_with_channels();
+        #[cfg(feature = "tokio")]
+        let (converter, _tx, mut rx) = Converter::new_with_channels();
+        let (done_tx, done_rx) = std::sync::mpsc::channel();
+        let started = std::time::Instant::now();
+        let handle = std::thread::spawn(move || {
+            converter.convert(settings);
+            let _ = done_tx.send(());
         });
+        let mut messages = vec![];
+        while messages.len() < kept {
+            #[cfg(not(any(feature = "tokio", feature = "async-channel")))]
+            let message = rx.recv().unwrap();
+            #[cfg(feature = "tokio")]
+            let message = rx.blocking_recv().unwrap();
+            #[cfg(feature = "async-channel")]
+            let message = rx.recv_blocking().unwrap();
+            messages.push(message);
+        }
+        drop(rx);
 
-        let tx_stderr = self.tx.clone();
-        let id_stderr = self.id();
-        let job_cancelled_stderr = std::sync::Arc::clone(&self.job_cancelled);
-        let handle_stderr = std::thread::spawn(move || {
-            log::info!(target: LOG_TARGET_STDERR, "{} Entered STDERR thread.", id_stderr);
+        done_rx
+            .recv_timeout(Duration::from_secs(5))
+            .expect("Job did not shut down");
+        let elapsed = started.elapsed();
+        handle.join().expect("Converter thread panicked");
+        assert_child_reaped(&path);
+        (messages, elapsed)
+    }
 
-            use std::io::Read;
+    #[cfg(unix)]
+    /// Makes sure that the child process spawned from the fake FFmpeg binary at `path`
+    /// (see [`fake_ffmpeg_recording_pid`]) is gone.
+    fn assert_child_reaped(path: &std::path::Path) {
+        let pid = std::fs::read_to_string(path.with_file_name("pid.txt")).unwrap();
+        let alive = std::process::Command::new("kill")
+            .args(["-0", pid.trim()])
+            .stderr(std::process::Stdio::null())
+            .status()
+            .unwrap();
+        assert!(!alive.success(), "Child process {} not reaped", pid.trim());
+    }
 
-            let id_stderr_string = id_stderr.to_string();
-            let mut duration: Option<Duration> = None;
+    #[cfg(unix)]
+    #[test]
+    fn test_converter_receiver_dropped() {
+        init_logging();
 
-            let mut full_buffer: Vec<u8> = vec![];
-            let mut buffer = vec![0u8; 1000]; // this needs to be set such that we'll be able to get "Duration unbroken" (frame should be ok)
+        // Before the video's duration is sent.
+        let (_, elapsed) = assert_clean_shutdown(fake_ffmpeg_recording_pid(LONG_JOB), 0);
+        assert!(elapsed < Duration::from_secs(2), "{:?}", elapsed);
 
-            log::info!(target: LOG_TARGET_STDERR, "{} Entering STDERR read loop...", id_stderr);
-            loop {
-                match stderr.read(&mut buffer) {
-                    Ok(n) => {
-                        log::debug!(target: LOG_TARGET_STDERR, "{} {} bytes read.", id_stderr, n);
+        // While progress messages are being sent.
+        let (messages, elapsed) = assert_clean_shutdown(fake_ffmpeg_recording_pid(LONG_JOB), 4);
+        assert!(matches!(
+            messages[..],
+            [
+                Message::VideoDuration(_),
+                Message::CodecSelection { .. },
+                Message::OutputDimensions { .. },
+                Message::Progress(_)
+            ]
+        ));
+        assert!(elapsed < Duration::from_secs(2), "{:?}", elapsed);
 
-                        log::debug!(target: LOG_TARGET_STDERR, "{} Trying to acquire 'job cancelled' mutex to make sure job has not been cancelled...", id_stderr);
-                        let job_cancelled = {
-                            let job_cancelled = match job_cancelled_stderr.lock() {
-                                Ok(m) => {
-                                    log::debug!(target: LOG_TARGET_STDERR, "{} Successfully acquired 'job cancelled' mutex.", id_stderr);
-                                    m
-                                }
-                                Err(e) => {
-                                    log::error!(target: LOG_TARGET_STDERR, "{} Failed to acquire 'job cancelled' mutex: {:?}", id_stderr, e);
-                                    panic!();
-                                }
-                            };
-                            *job_cancelled
-                        };
-                        if job_cancelled {
-                            log::info!(target: LOG_TARGET_STDERR, "{} Job has been cancelled, so breaking out of loop...", id_stderr);
-                            break;
-                        } else {
-                            log::debug!(target: LOG_TARGET_STDERR, "{} Job has not been cancelled, so carrying on with output parsing...", id_stderr);
-                        }
+        // Before the output is sent (once the child process has exited).
+        let path = fake_ffmpeg_recording_pid("sleep 0.3\ncat '{dir}/stdout.bin'");
+        let (messages, _) = assert_clean_shutdown(path, 1);
+        assert!(matches!(messages[..], [Message::VideoDuration(_)]));
+    }
 
-                        if n > 0 {
-                            full_buffer.append(&mut buffer[..n].to_vec());
+    #[cfg(unix)]
+    #[test]
+    fn test_converter_drains_pipes_concurrently() {
+        init_logging();
 
-                            if duration.is_none() {
-                                log::debug!(target: LOG_TARGET_STDERR, "{} Trying to parse buffer into string...", id_stderr);
-                                let s = match std::str::from_utf8(&full_buffer[..]) {
-                                    Ok(s) => {
-                                        log::debug!(target: LOG_TARGET_STDERR, "{} Successfully parsed buffer into string.", id_stderr);
-                                        log::trace!(target: LOG_TARGET_STDERR, "{} Logging parsed buffer:\n{}", id_stderr, s);
-                                        s
-                                    }
-                                    Err(e) => {
-                                        log::error!(target: LOG_TARGET_STDERR, "{} Failed to parse buffer into string: {:?}", id_stderr, e);
-                                        panic!();
-                                    }
-                                };
-                                log::debug!(target: LOG_TARGET_STDERR, "{} Trying to extract video duration from parsed string...", id_stderr);
-                                if let Some(d) = try_extract_duration(s, Some(&id_stderr_string)) {
-                                    log::info!(target: LOG_TARGET_STDERR, "{} Video duration successfully extracted: {:?}", id_stderr, d);
-                                    duration = Some(d);
-                                    log::debug!(target: LOG_TARGET_STDERR, "{} Trying to send video duration down channel...", id_stderr);
-                                    match tx_stderr.send(Message::VideoDuration(d)) {
-                                        Ok(_) => {
-                                            log::debug!(target: LOG_TARGET_STDERR, "{} Video duration successfully sent down channel.", id_stderr);
-                                        }
-                                        Err(e) => {
-                                            log::error!(target: LOG_TARGET_STDERR, "{} Failed to send video duration down channel: {:?}", id_stderr, e);
-                                            panic!();
-                                        }
-                                    }
-                                }
-                            }
+        // NOTE: About 10 MB of output, written in 1 MB chunks, each of them followed by
+        // a burst of `stderr` lines larger than a pipe's buffer (64 kB on Linux), so that
+        // the child process would block forever if both pipes were not drained at once.
+        let gif = sample_gif(10 * 1024 * 1024 / 23, Some(0));
+        let burst: String = (0..1500)
+            .map(|i| format!("[gif @ 0x{:012x}] Skipping unsupported side data\n", i))
+            .collect();
+        let (header, _) = SAMPLE_STDERR.split_once("frame=").unwrap();
+        let dir = temp_dir();
+        std::fs::write(dir.join("header.txt"), header).unwrap();
+        std::fs::write(dir.join("burst.txt"), &burst).unwrap();
+        std::fs::write(dir.join("stdout.bin"), &gif).unwrap();
+        let path = write_script(
+            &dir,
+            &format!(
+                "cat '{dir}/header.txt' >&2\nfor i in $(seq 0 10); do\n  dd if='{dir}/stdout.bin' bs=1048576 skip=$i count=1 2>/dev/null\n  cat '{dir}/burst.txt' >&2\n  printf 'frame= %d fps=0.0 q=-0.0 size= 0kB time=00:00:0%d.00 bitrate= 0.0kbits/s speed=1x\\r' $i $((i / 2)) >&2\ndone",
+                dir = dir.display()
+            ),
+        );
+        let settings = Settings::with_standard_fps(SAMPLE_VIDEO_PATH.into(), 200)
+            .ffmpeg_path(path.to_string_lossy());
 
-                            log::debug!(target: LOG_TARGET_STDERR, "{} Trying to parse buffer into string...", id_stderr);
-                            let s = match std::str::from_utf8(&buffer[..n]) {
-                                Ok(s) => {
-                                    log::debug!(target: LOG_TARGET_STDERR, "{} Successfully parsed buffer into string.", id_stderr);
-                                    log::trace!(target: LOG_TARGET_STDERR, "{} Logging parsed buffer:\n{}", id_stderr, s);
-                                    s
-                                }
-                                Err(e) => {
-                                    log::error!(target: LOG_TARGET_STDERR, "{} Failed to parse buffer into string: {:?}", id_stderr, e);
-                                    panic!();
-                                }
-                            };
+        let (done_tx, done_rx) = std::sync::mpsc::channel();
+        let handle = std::thread::spawn(move || {
+            let _ = done_tx.send(run_to_completion(settings));
+        });
+        let messages = done_rx
+            .recv_timeout(Duration::from_secs(1))
+            .expect("Job deadlocked");
+        handle.join().unwrap();
+        assert_eq!(success_bytes(&messages), Some(&gif[..]));
+        assert!(messages.iter().any(|m| matches!(m, Message::Progress(_))));
+    }
 
-                            if s.starts_with("frame=") {
-                                log::debug!(target: LOG_TARGET_STDERR, "{} Parsed string starts with 'frame=', so trying to extra frame time from it...", id_stderr);
-                                if let Some(time) =
-                                    try_extract_frame_time(s, Some(&id_stderr_string))
-                                {
-                                    log::debug!(target: LOG_TARGET_STDERR, "{} Successfully extracted 'time' from string: {:?}", id_stderr, time);
-                                    if let Some(duration) = duration {
-                                        let progress = progress_from_durations(duration, time);
-                                        log::info!(target: LOG_TARGET_STDERR, "{} New progress calculated: {:.04}", id_stderr, progress);
-                                        log::debug!(target: LOG_TARGET_STDERR, "{} Trying to send newly calculated progress down channel...", id_stderr);
-                                        match tx_stderr.send(Message::Progress(progress)) {
-                                            Ok(_) => {
-                                                log::debug!(target: LOG_TARGET_STDERR, "{} Successfully sent newly calculated progress down channel.", id_stderr);
-                                            }
-                                            Err(e) => {
-                                                log::error!(target: LOG_TARGET_STDERR, "{} Failed to send newly calculated progress down channel: {:?}", id_stderr, e);
-                                                panic!();
-                                            }
-                                        }
-                                    }
-                                } else {
-                                    // So this is possible if we input an invalid file (e.g. a png), in which case we will get something
-                                    // similar to this (i.e. a "frame=" without first a duration):
-                                    //     out#0/gif @ 0x7fe0a5714b00] Error writing trailer: Invalid argumentbitrate=  -0.0kbits/s speed=N/A
-                                    //         frame=    0 fps=0.0 q=0.0 Lsize=       0kB time=-577014:32:22.77 bitrate=  -0.0kbits/s speed=N/A
+    #[cfg(unix)]
+    #[test]
+    fn test_converter_smooth_progress() {
+        init_logging();
 
-                                    // NOTE: No need to panic here I think. We can just do nothing. If it
-                                    // was an invalid input, `stderr` will close and the loop will automatically
-                                    // break...
-                                    log::warn!(target: LOG_TARGET_STDERR, "{} NOTE: frame= received without duration parsed. This may have been caused by invalid input file type.", id_stderr);
-                                }
-                            }
-                        } else {
-                            log::info!(target: LOG_TARGET_STDERR, "{} No more data to read. Breaking out of STDERR thread loop...", id_stderr);
-                            break;
-                        }
-                    }
-                    Err(e) => {
-                        if let std::io::ErrorKind::WouldBlock = e.kind() {
-                        } else {
-                            log::error!(target: LOG_TARGET_STDERR, "{} Error reading STDERR: {:?}", id_stderr, e);
-                            panic!();
-                        }
-                    }
+        let path = fake_ffmpeg_with_progress(4, 0.4);
+        let settings = Settings::with_standard_fps(SAMPLE_VIDEO_PATH.into(), 200)
+            .ffmpeg_path(path.to_string_lossy());
+        let messages = run_to_completion(settings.clone());
+        assert!(!messages
+            .iter()
+            .any(|m| matches!(m, Message::InterpolatedProgress(_))));
+
+        let messages = run_to_completion(settings.smooth_progress(true));
+        assert!(success_bytes(&messages).is_some());
+        let mut interpolated = 0;
+        let mut previous = 0.0;
+        for message in &messages {
+            match message {
+                Message::Progress(p) => previous = *p,
+                Message::InterpolatedProgress(p) => {
+                    // The real values are 0.25, 0.5, 0.75 and 1.0.
+                    assert!(*p > previous && *p < 1.0);
+                    assert!(*p <= previous + 0.25);
+                    previous = *p;
+                    interpolated += 1;
                 }
+                _ => {}
             }
+        }
+        assert!(interpolated > 0);
+        let success = messages
+            .iter()
+            .position(|m| matches!(m, Message::Success(_)))
+            .unwrap();
+        assert!(!messages[success..].iter().any(|m| m.is_progress()));
+    }
 
-            log::info!(target: LOG_TARGET_STDERR, "{} Exiting STDERR thread...", id_stderr);
-        });
+    #[cfg(unix)]
+    #[test]
+    fn test_converter_cancelled_early_and_late() {
+        init_logging();
 
-        let tx_child = self.tx.clone();
-        let id_child = self.id();
-        let handle_child = std::thread::spawn(move || {
-            log::info!(target: LOG_TARGET_CHILD, "{} Entered CHILD process thread", id_child);
-
-            log::debug!(target: LOG_TARGET_CHILD, "{} Calling 'wait' method on the child process instance...", id_child);
-            match child.wait() {
-                Ok(status) => {
-                    log::info!(target: LOG_TARGET_CHILD, "{} Child process completed with exit status: {:?} (exit code: {:?})", id_child, status, status.code());
-                    if let Some(code) = status.code() {
-                        if code > 0 {
-                            log::debug!(target: LOG_TARGET_CHILD, "{} Trying to send error message down channel...", id_child);
-                            match tx_child.send(Message::Error(Error::ExitCode(code))) {
-                                Ok(_) => {
-                                    log::debug!(target: LOG_TARGET_CHILD, "{} Successfully sent error message down channel", id_child);
-                                }
-                                Err(e) => {
-                                    log::error!(target: LOG_TARGET_CHILD, "{} Failed to send error message down channel: {:?}", id_child, e);
-                                    panic!();
-                                }
-                            }
-                        }
-                    }
-                }
-                Err(e) => {
-                    log::warn!(target: LOG_TARGET_CHILD, "{} Child process error: {:?}", id_child, e);
-                    log::debug!(target: LOG_TARGET_CHILD, "{} Trying to send child process error down channel...", id_child);
-                    match tx_child.send(Message::Error(Error::ChildProcess(std::sync::Arc::new(e))))
-                    {
-                        Ok(_) => {
-                            log::debug!(target: LOG_TARGET_CHILD, "{} Successfully sent child process error down channel.", id_child);
-                        }
-                        Err(e) => {
-                            log::error!(target: LOG_TARGET_CHILD, "{} Failed to send child process error down channel: {:?}", id_child, e);
-                            panic!();
-                        }
-                    }
-                }
+        // Each progress line comes with 1000 bytes of output.
+        let path = fake_ffmpeg_with_progress_script(4, 0.2, "head -c 1000 /dev/zero", "sleep 3");
+        let cancel_after = |delay: u64| {
+            let settings = Settings::with_standard_fps(SAMPLE_VIDEO_PATH.into(), 200)
+                .ffmpeg_path(path.to_string_lossy());
+            let messages = run_to_completion_with(settings, move |tx| {
+                std::thread::sleep(Duration::from_millis(delay));
+                assert!(send_command(&tx, Command::Cancel));
+            });
+            assert!(success_bytes(&messages).is_none());
+            match final_summary(&messages).error {
+                Some(Error::Cancelled {
+                    progress_at_cancel,
+                    bytes_discarded,
+                }) => (progress_at_cancel, bytes_discarded),
+                e => panic!("Expected a 'Cancelled' error: {:?}", e),
             }
+        };
 
-            log::info!(target: LOG_TARGET_CHILD, "{} Exiting CHILD process thread...", id_child);
-        });
+        assert_eq!(cancel_after(0), (None, 0));
+        let (progress_at_cancel, bytes_discarded) = cancel_after(1500);
+        assert_eq!(progress_at_cancel, Some(1.0));
+        assert_eq!(bytes_discarded, 4000);
+    }
 
-        log::debug!(target: LOG_TARGET_MAIN, "{} All threads spawned. Now trying to join them sequentially in the following order: child process, stderr, stdout, stdin...", self.id());
+    #[cfg(unix)]
+    #[test]
+    fn test_converter_cancelled_while_completing() {
+        init_logging();
 
-        log::debug!(target: LOG_TARGET_MAIN, "{} Trying to join CHILD process thread...", self.id());
-        match handle_child.join() {
-            Ok(_) => {
-                log::debug!(target: LOG_TARGET_MAIN, "{} Successfully joined CHILD process thread", self.id());
-            }
-            Err(e) => {
-                log::error!(target: LOG_TARGET_MAIN, "{} Failed to join CHILD process thread: {:?}", self.id(), e);
-                panic!();
-            }
-        }
-        log::debug!(target: LOG_TARGET_MAIN, "{} Trying to join STDERR thread...", self.id());
-        match handle_stderr.join() {
-            Ok(_) => {
-                log::debug!(target: LOG_TARGET_MAIN, "{} Successfully joined STDERR thread", self.id());
-            }
-            Err(e) => {
-                log::error!(target: LOG_TARGET_MAIN, "{} Failed to join STDERR thread: {:?}", self.id(), e);
-                panic!();
-            }
-        }
-        log::debug!(target: LOG_TARGET_MAIN, "{} Trying to join STDOUT thread...", self.id());
-        match handle_stdout.join() {
-            Ok(_) => {
-                log::debug!(target: LOG_TARGET_MAIN, "{} Successfully joined STDOUT thread", self.id());
-            }
-            Err(e) => {
-                log::error!(target: LOG_TARGET_MAIN, "{} Failed to join STDOUT thread: {:?}", self.id(), e);
-                panic!();
+        // NOTE: The STDIN thread polls the commands every `STDIN_THREAD_SLEEP_DURATION_MS`,
+        // and the command is sent in between its first two polls, so that it gets processed
+        // at the second one, around which the jobs complete (at slightly different times).
+        let gif = sample_gif(2, Some(0));
+        let paths: Vec<_> = (0..10)
+            .map(|i| {
+                let dir = temp_dir();
+                std::fs::write(dir.join("stderr.txt"), SAMPLE_STDERR).unwrap();
+                std::fs::write(dir.join("stdout.bin"), &gif).unwrap();
+                let delay = (STDIN_THREAD_SLEEP_DURATION_MS - 30 + 3 * i) as f64 / 1000.0;
+                write_script(
+                    &dir,
+                    &format!(
+                        "cat '{dir}/stderr.txt' >&2\nsleep {delay}\ncat '{dir}/stdout.bin'",
+                        dir = dir.display()
+                    ),
+                )
+            })
+            .collect();
+        let (mut succeeded, mut cancelled) = (0, 0);
+        for path in paths.iter().cycle().take(60) {
+            let settings = Settings::with_standard_fps(SAMPLE_VIDEO_PATH.into(), 200)
+                .ffmpeg_path(path.to_string_lossy());
+            let messages = run_to_completion_with(settings, move |tx| {
+                std::thread::sleep(Duration::from_millis(STDIN_THREAD_SLEEP_DURATION_MS / 2));
+                send_command(&tx, Command::Cancel);
+            });
+            let terminal: Vec<_> = messages
+                .iter()
+                .filter(|m| matches!(m, Message::Success(_) | Message::Error(_)))
+                .collect();
+            assert_eq!(terminal.len(), 1, "{:?}", messages);
+            assert_eq!(
+                final_summary(&messages).error.is_some(),
+                matches!(terminal[0], Message::Error(_))
+            );
+            match terminal[0] {
+                Message::Success(bytes) => {
+                    assert_eq!(bytes, &gif);
+                    succeeded += 1;
+                }
+                Message::Error(Error::Cancelled { .. }) => cancelled += 1,
+                m => panic!("Unexpected terminal message: {:?}", m),
             }
         }
-        log::debug!(target: LOG_TARGET_MAIN, "{} Trying to join STDIN thread...", self.id());
-        match handle_stdin.join() {
-            Ok(_) => {
-                log::debug!(target: LOG_TARGET_MAIN, "{} Successfully joined STDIN thread", self.id());
-            }
-            Err(e) => {
-                log::error!(target: LOG_TARGET_MAIN, "{} Failed to join STDIN thread: {:?}", self.id(), e);
-                panic!();
-            }
+        log::info!("{} jobs succeeded, {} cancelled", succeeded, cancelled);
+    }
+
+    #[cfg(all(unix, feature = "tokio"))]
+    #[tokio::test(flavor = "multi_thread")]
+    async fn test_converter_blocking_in_async_context() {
+        init_logging();
+
+        let path = fake_ffmpeg(SAMPLE_STDERR, &sample_gif(2, Some(0)), 0);
+        let settings = Settings::with_standard_fps(SAMPLE_VIDEO_PATH.into(), 200)
+            .ffmpeg_path(path.to_string_lossy());
+
+        // NOTE: This is precisely what applications should not do.
+        let (converter, _tx, mut rx) = Converter::new_with_channels();
+        converter.convert(settings.clone());
+        assert!(matches!(
+            rx.recv().await,
+            Some(Message::Warning(Warning::BlockingInAsyncContext))
+        ));
+        let mut success = false;
+        while let Some(message) = rx.recv().await {
+            success |= matches!(message, Message::Success(_));
         }
+        assert!(success);
 
-        log::info!(target: LOG_TARGET_MAIN, "{} Trying to send 'done' message down channel...", self.id());
-        match self.tx.send(Message::Done) {
-            Ok(_) => {
-                log::info!(target: LOG_TARGET_MAIN, "{} Successfully sent 'done' message down channel.", self.id());
-            }
-            Err(e) => {
-                log::error!(target: LOG_TARGET_MAIN, "{} Failed to send 'done' message down channel: {:?}", self.id(), e);
-                panic!();
-            }
+        let (converter, _tx, mut rx) = Converter::new_with_channels();
+        converter.convert(settings.strict_async_context(true));
+        assert!(matches!(
+            rx.recv().await,
+            Some(Message::Error(Error::BlockingInAsyncContext))
+        ));
+        assert!(matches!(rx.recv().await, Some(Message::Summary(_))));
+        assert!(matches!(rx.recv().await, Some(Message::Done)));
+        assert!(rx.recv().await.is_none());
+    }
+
+    #[cfg(all(unix, feature = "tokio"))]
+    #[tokio::test(flavor = "multi_thread")]
+    async fn test_converter_spawn_blocking_no_warning() {
+        init_logging();
+
+        let path = fake_ffmpeg(SAMPLE_STDERR, &sample_gif(2, Some(0)), 0);
+        let settings = Settings::with_standard_fps(SAMPLE_VIDEO_PATH.into(), 200)
+            .ffmpeg_path(path.to_string_lossy())
+            .strict_async_context(true);
+
+        let (converter, _tx, mut rx) = Converter::new_with_channels();
+        tokio::task::spawn_blocking(move || converter.convert(settings))
+            .await
+            .unwrap();
+        let mut success = false;
+        while let Some(message) = rx.recv().await {
+            assert!(!matches!(
+                message,
+                Message::Warning(_) | Message::Error(Error::BlockingInAsyncContext)
+            ));
+            success |= matches!(message, Message::Success(_));
         }
+        assert!(success);
+    }
+
+    #[cfg(all(unix, not(any(feature = "tokio", feature = "async-channel"))))]
+    #[test]
+    fn test_converter_job_guard() {
+        init_logging();
 
-        log::info!(target: LOG_TARGET_MAIN, "{} End of 'convert' method reached.", self.id());
+        let path = fake_ffmpeg_recording_pid(LONG_JOB);
+        let settings = Settings::with_standard_fps(SAMPLE_VIDEO_PATH.into(), 200)
+            .ffmpeg_path(path.to_string_lossy());
+
+        // Dropping the guard while progress messages are being sent.
+        let (converter, _tx, rx) = Converter::new_with_channels();
+        let started = std::time::Instant::now();
+        let guard = converter.spawn(settings.clone());
+        while !matches!(rx.recv().unwrap(), Message::Progress(_)) {}
+        drop(guard);
+        let messages: Vec<_> = rx.iter().collect();
+        let elapsed = started.elapsed();
+        assert!(elapsed < Duration::from_secs(2), "{:?}", elapsed);
+        assert!(matches!(
+            final_summary(&messages).error,
+            Some(Error::Cancelled { .. })
+        ));
+        assert_child_reaped(&path);
+
+        // Detaching the guard, which lets the job complete.
+        let (converter, _tx, rx) = Converter::new_with_channels();
+        converter.spawn(settings).detach();
+        let messages: Vec<_> = rx.iter().collect();
+        assert!(success_bytes(&messages).is_some(), "{:?}", messages);
     }
-}
 
-#[cfg(test)]
-mod tests {
-    use super::*;
+    #[cfg(all(unix, feature = "tokio"))]
+    #[tokio::test(flavor = "multi_thread")]
+    async fn test_converter_job_guard_task_aborted() {
+        init_logging();
+
+        let path = fake_ffmpeg_recording_pid(LONG_JOB);
+        let settings = Settings::with_standard_fps(SAMPLE_VIDEO_PATH.into(), 200)
+            .ffmpeg_path(path.to_string_lossy());
 
-    fn init_logging() {
-        std::env::set_var("RUST_LOG", "debug");
-        // std::env::set_var("RUST_LOG", "info");
-        let _ = env_logger::builder().is_test(true).try_init();
+        // The task owning the guard gets aborted while progress messages are being sent.
+        let (converter, _tx, mut rx) = Converter::new_with_channels();
+        let started = std::time::Instant::now();
+        let task = tokio::spawn(async move {
+            let _guard = converter.spawn(settings);
+            std::future::pending::<()>().await;
+        });
+        while !matches!(rx.recv().await, Some(Message::Progress(_))) {}
+        task.abort();
+        let mut messages = vec![];
+        while let Some(message) = rx.recv().await {
+            messages.push(message);
+        }
+        let elapsed = started.elapsed();
+        assert!(elapsed < Duration::from_secs(2), "{:?}", elapsed);
+        assert!(matches!(
+            final_summary(&messages).error,
+            Some(Error::Cancelled { .. })
+        ));
+        assert_child_reaped(&path);
+    }
+
+    #[cfg(all(unix, feature = "tokio"))]
+    #[tokio::test(flavor = "multi_thread")]
+    async fn test_converter_job_guard_shutdown() {
+        init_logging();
+
+        let path = fake_ffmpeg_recording_pid(LONG_JOB);
+        let settings = Settings::with_standard_fps(SAMPLE_VIDEO_PATH.into(), 200)
+            .ffmpeg_path(path.to_string_lossy());
+
+        let (converter, _tx, mut rx) = Converter::new_with_channels();
+        let started = std::time::Instant::now();
+        let guard = converter.spawn(settings.clone());
+        while !matches!(rx.recv().await, Some(Message::Progress(_))) {}
+        guard.shutdown().await;
+        let elapsed = started.elapsed();
+        assert!(elapsed < Duration::from_secs(2), "{:?}", elapsed);
+        // NOTE: The job has been torn down, so everything was already sent.
+        let mut messages = vec![];
+        while let Ok(message) = rx.try_recv() {
+            messages.push(message);
+        }
+        assert!(matches!(messages.last(), Some(Message::Done)));
+        assert_child_reaped(&path);
+
+        // Detaching the guard, which lets the job complete.
+        let (converter, _tx, mut rx) = Converter::new_with_channels();
+        converter.spawn(settings).detach();
+        let mut messages = vec![];
+        while let Some(message) = rx.recv().await {
+            messages.push(message);
+        }
+        assert!(success_bytes(&messages).is_some(), "{:?}", messages);
     }
 
     #[cfg(not(feature = "tokio"))]
@@ -621,6 +7829,7 @@ mod tests {
 
     #[cfg(feature = "tokio")]
     #[test]
+    #[allow(clippy::while_let_loop)]
     fn test_converter_blocking() {
         init_logging();
 
@@ -654,12 +7863,73 @@ mod tests {
                     Message::Progress(progress) => {
                         log::info!("Progress received: {:.04}", progress);
                     }
+                    Message::InterpolatedProgress(progress) => {
+                        log::info!("Interpolated progress received: {:.04}", progress);
+                    }
+                    Message::FramesProcessed(n) => {
+                        log::info!("Frames processed received: {}", n);
+                    }
                     Message::VideoDuration(duration) => {
                         log::info!("Duration received: {:?}", duration);
                     }
+                    Message::OutputBytes(n) => {
+                        log::info!("Output bytes received: {}", n);
+                    }
+                    Message::BytesWritten(n) => {
+                        log::info!("Bytes written received: {}", n);
+                    }
                     Message::Success(data) => {
                         log::info!("Successfully parsed data. Byte-length = {}", data.len());
                     }
+                    Message::Warning(warning) => {
+                        log::warn!("Warning received: {:?}", warning);
+                    }
+                    Message::Preview(data) => {
+                        log::info!("Preview received. Byte-length = {}", data.len());
+                    }
+                    Message::ColorCountSelected(n) => {
+                        log::info!("Color count selected: {}", n);
+                    }
+                    Message::CodecSelection {
+                        decoder, encoder, ..
+                    } => {
+                        log::info!("Codec selection received: {} -> {}", decoder, encoder);
+                    }
+                    Message::OutputDimensions { width, height } => {
+                        log::info!("Output dimensions received: {}x{}", width, height);
+                    }
+                    Message::FrameMap(timestamps) => {
+                        log::info!("Frame map received: {} frames", timestamps.len());
+                    }
+                    Message::Summary(summary) => {
+                        log::info!("Summary received: {:?}", summary);
+                    }
+                    Message::Thumbnail {
+                        index, timestamp, ..
+                    } => {
+                        log::info!("Thumbnail {} received: {:?}", index, timestamp);
+                    }
+                    Message::Saved { path } => {
+                        log::info!("Output saved: {:?}", path);
+                    }
+                    Message::Skipped { existing_path } => {
+                        log::info!("Output file already exists: {:?}", existing_path);
+                    }
+                    Message::Internal(event) => {
+                        log::info!("Internal event received: {:?}", event);
+                    }
+                    Message::Attempt { n, settings } => {
+                        log::info!("Attempt {} started: {:?}", n, settings);
+                    }
+                    Message::SuccessVariant { width, bytes } => {
+                        log::info!("Variant received (width {}): {} bytes", width, bytes.len());
+                    }
+                    Message::SuccessFile(path) => {
+                        log::info!("Output file received: {:?}", path);
+                    }
+                    Message::SuccessWritten(n) => {
+                        log::info!("Output written to writer: {} bytes", n);
+                    }
                 },
                 None => {
                     break;
@@ -673,6 +7943,7 @@ mod tests {
     }
     #[cfg(feature = "tokio")]
     #[test]
+    #[allow(clippy::while_let_loop)]
     fn test_converter_blocking_cancelled_job() {
         init_logging();
 
@@ -721,12 +7992,73 @@ mod tests {
                     Message::Progress(progress) => {
                         log::info!("Progress received: {:.04}", progress);
                     }
+                    Message::InterpolatedProgress(progress) => {
+                        log::info!("Interpolated progress received: {:.04}", progress);
+                    }
+                    Message::FramesProcessed(n) => {
+                        log::info!("Frames processed received: {}", n);
+                    }
                     Message::VideoDuration(duration) => {
                         log::info!("Duration received: {:?}", duration);
                     }
+                    Message::OutputBytes(n) => {
+                        log::info!("Output bytes received: {}", n);
+                    }
+                    Message::BytesWritten(n) => {
+                        log::info!("Bytes written received: {}", n);
+                    }
                     Message::Success(data) => {
                         log::info!("Successfully parsed data. Byte-length = {}", data.len());
                     }
+                    Message::Warning(warning) => {
+                        log::warn!("Warning received: {:?}", warning);
+                    }
+                    Message::Preview(data) => {
+                        log::info!("Preview received. Byte-length = {}", data.len());
+                    }
+                    Message::ColorCountSelected(n) => {
+                        log::info!("Color count selected: {}", n);
+                    }
+                    Message::CodecSelection {
+                        decoder, encoder, ..
+                    } => {
+                        log::info!("Codec selection received: {} -> {}", decoder, encoder);
+                    }
+                    Message::OutputDimensions { width, height } => {
+                        log::info!("Output dimensions received: {}x{}", width, height);
+                    }
+                    Message::FrameMap(timestamps) => {
+                        log::info!("Frame map received: {} frames", timestamps.len());
+                    }
+                    Message::Summary(summary) => {
+                        log::info!("Summary received: {:?}", summary);
+                    }
+                    Message::Thumbnail {
+                        index, timestamp, ..
+                    } => {
+                        log::info!("Thumbnail {} received: {:?}", index, timestamp);
+                    }
+                    Message::Saved { path } => {
+                        log::info!("Output saved: {:?}", path);
+                    }
+                    Message::Skipped { existing_path } => {
+                        log::info!("Output file already exists: {:?}", existing_path);
+                    }
+                    Message::Internal(event) => {
+                        log::info!("Internal event received: {:?}", event);
+                    }
+                    Message::Attempt { n, settings } => {
+                        log::info!("Attempt {} started: {:?}", n, settings);
+                    }
+                    Message::SuccessVariant { width, bytes } => {
+                        log::info!("Variant received (width {}): {} bytes", width, bytes.len());
+                    }
+                    Message::SuccessFile(path) => {
+                        log::info!("Output file received: {:?}", path);
+                    }
+                    Message::SuccessWritten(n) => {
+                        log::info!("Output written to writer: {} bytes", n);
+                    }
                 },
                 None => {
                     break;
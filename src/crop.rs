@@ -0,0 +1,218 @@
+//! The generation of FFmpeg's `crop` filter for [`crate::Settings::crop_keyframes`],
+//! which makes the cropped region follow a window that moves at given timestamps.
+//!
+//! The window's position is a step function of time: it stays at the position of
+//! a keyframe until the timestamp of the next keyframe (and the position of the
+//! first keyframe is also used before its timestamp). FFmpeg's `crop` filter
+//! evaluates its `x` and `y` expressions for each frame, so the step function
+//! is written as nested `if(between(t, a, b), position, ...)` terms, the first
+//! matching segment winning at the boundaries (where `between` is inclusive).
+
+use std::time::Duration;
+
+use crate::SettingsError;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
+/// A rectangular region of the video's frames (in pixels), whose top left
+/// corner is at (`x`, `y`).
+pub struct CropRect {
+    /// The horizontal position of the region's left edge.
+    pub x: u32,
+    /// The vertical position of the region's top edge.
+    pub y: u32,
+    /// The region's width.
+    pub width: u32,
+    /// The region's height.
+    pub height: u32,
+}
+
+impl CropRect {
+    /// Creates a new region of size `width` x `height`, whose top left corner is at (`x`, `y`).
+    pub fn new(x: u32, y: u32, width: u32, height: u32) -> Self {
+        Self {
+            x,
+            y,
+            width,
+            height,
+        }
+    }
+}
+
+/// Makes sure that the keyframes are sorted (by strictly increasing timestamps)
+/// and that the size of the region is the same for all of them.
+pub(crate) fn validate_keyframes(keyframes: &[(Duration, CropRect)]) -> Result<(), SettingsError> {
+    let Some((_, first)) = keyframes.first() else {
+        return Ok(());
+    };
+    if first.width == 0 || first.height == 0 {
+        return Err(SettingsError::CropRectEmpty);
+    }
+    for (index, window) in keyframes.windows(2).enumerate() {
+        let ((previous, _), (timestamp, rect)) = (window[0], window[1]);
+        if timestamp <= previous {
+            return Err(SettingsError::CropKeyframesUnsorted(index + 1));
+        }
+        if (rect.width, rect.height) != (first.width, first.height) {
+            return Err(SettingsError::CropSizeMismatch(index + 1));
+        }
+    }
+    Ok(())
+}
+
+/// Whether `line`, written by FFmpeg to `stderr`, reports that the `crop` filter's region
+/// is larger than the video's frames (see [`crate::Error::CropOutOfBounds`]).
+pub(crate) fn is_out_of_bounds_error(line: &str) -> bool {
+    // NOTE: E.g. `[Parsed_crop_0 @ 0x5581c0a0c2c0] Invalid too big or non positive size
+    // for width '5000' or height '400'`.
+    line.contains("Invalid too big or non positive size for width")
+}
+
+/// Formats a timestamp (in seconds) for FFmpeg's expressions.
+fn seconds(timestamp: Duration) -> String {
+    format!("{:.3}", timestamp.as_secs_f64())
+}
+
+/// Generates the piecewise expression of one of the window's coordinates, given
+/// by `coordinate`, over the (sorted and non-empty) `keyframes`.
+pub(crate) fn piecewise_expression(
+    keyframes: &[(Duration, CropRect)],
+    coordinate: impl Fn(&CropRect) -> u32,
+) -> String {
+    let values: Vec<u32> = keyframes.iter().map(|(_, rect)| coordinate(rect)).collect();
+    let last = values[values.len() - 1];
+    if values.iter().all(|&v| v == last) {
+        return last.to_string();
+    }
+    let mut expression = String::new();
+    for (i, value) in values.iter().enumerate().take(values.len() - 1) {
+        // NOTE: The first segment also covers anything before the first keyframe.
+        let start = match i {
+            0 => Duration::ZERO,
+            _ => keyframes[i].0,
+        };
+        expression.push_str(&format!(
+            "if(between(t,{},{}),{},",
+            seconds(start),
+            seconds(keyframes[i + 1].0),
+            value
+        ));
+    }
+    expression.push_str(&last.to_string());
+    expression.push_str(&")".repeat(values.len() - 1));
+    expression
+}
+
+/// Generates the `crop` filter for the (sorted and non-empty) `keyframes`.
+pub(crate) fn crop_filter(keyframes: &[(Duration, CropRect)]) -> String {
+    let first = keyframes[0].1;
+    format!(
+        "crop=w={}:h={}:x='{}':y='{}'",
+        first.width,
+        first.height,
+        piecewise_expression(keyframes, |r| r.x),
+        piecewise_expression(keyframes, |r| r.y)
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn keyframe(seconds: u64, x: u32, y: u32) -> (Duration, CropRect) {
+        (Duration::from_secs(seconds), CropRect::new(x, y, 100, 50))
+    }
+
+    /// Evaluates a piecewise expression at time `t`, the way FFmpeg would.
+    fn evaluate(expression: &str, t: f64) -> u32 {
+        let Some(rest) = expression.strip_prefix("if(between(t,") else {
+            return expression.trim_end_matches(')').parse().unwrap();
+        };
+        let mut parts = rest.splitn(4, ',');
+        let start: f64 = parts.next().unwrap().parse().unwrap();
+        let end: f64 = parts.next().unwrap().trim_end_matches(')').parse().unwrap();
+        let value: u32 = parts.next().unwrap().parse().unwrap();
+        if t >= start && t <= end {
+            value
+        } else {
+            evaluate(parts.next().unwrap(), t)
+        }
+    }
+
+    #[test]
+    fn test_one_keyframe() {
+        let keyframes = [keyframe(0, 10, 20)];
+        assert_eq!(crop_filter(&keyframes), "crop=w=100:h=50:x='10':y='20'");
+        // The timestamp of a single keyframe doesn't matter.
+        let keyframes = [keyframe(3, 10, 20)];
+        assert_eq!(crop_filter(&keyframes), "crop=w=100:h=50:x='10':y='20'");
+    }
+
+    #[test]
+    fn test_two_keyframes() {
+        let keyframes = [keyframe(0, 10, 20), keyframe(2, 30, 20)];
+        assert_eq!(
+            crop_filter(&keyframes),
+            "crop=w=100:h=50:x='if(between(t,0.000,2.000),10,30)':y='20'"
+        );
+        let x = piecewise_expression(&keyframes, |r| r.x);
+        assert_eq!(evaluate(&x, 0.0), 10);
+        assert_eq!(evaluate(&x, 1.999), 10);
+        assert_eq!(evaluate(&x, 2.001), 30);
+        assert_eq!(evaluate(&x, 100.0), 30);
+    }
+
+    #[test]
+    fn test_ten_keyframes() {
+        let keyframes: Vec<_> = (0..10u32)
+            .map(|i| keyframe(1 + i as u64, i * 10, 100 - i * 10))
+            .collect();
+        let x = piecewise_expression(&keyframes, |r| r.x);
+        let y = piecewise_expression(&keyframes, |r| r.y);
+        assert_eq!(x.matches("between").count(), 9);
+        assert_eq!(x.matches('(').count(), x.matches(')').count());
+        assert!(x.starts_with("if(between(t,0.000,2.000),0,if(between(t,2.000,3.000),10,"));
+        assert!(x.ends_with(",90)))))))))"));
+        // Before the first keyframe, the first position is used.
+        assert_eq!((evaluate(&x, 0.5), evaluate(&y, 0.5)), (0, 100));
+        for i in 0..10u32 {
+            let t = 1.5 + i as f64;
+            assert_eq!((evaluate(&x, t), evaluate(&y, t)), (i * 10, 100 - i * 10));
+        }
+        assert!(crop_filter(&keyframes).starts_with("crop=w=100:h=50:x='if("));
+    }
+
+    #[test]
+    fn test_is_out_of_bounds_error() {
+        assert!(is_out_of_bounds_error(
+            "[Parsed_crop_0 @ 0x5581c0a0c2c0] Invalid too big or non positive size for width '5000' or height '400'"
+        ));
+        assert!(!is_out_of_bounds_error(
+            "[Parsed_crop_0 @ 0x5581c0a0c2c0] w:640 h:360 sar:1/1 -> w:100 h:50 sar:1/1"
+        ));
+    }
+
+    #[test]
+    fn test_validate_keyframes() {
+        assert_eq!(validate_keyframes(&[]), Ok(()));
+        assert_eq!(
+            validate_keyframes(&[keyframe(0, 0, 0), keyframe(1, 5, 5)]),
+            Ok(())
+        );
+        assert_eq!(
+            validate_keyframes(&[keyframe(0, 0, 0), keyframe(2, 5, 5), keyframe(2, 0, 0)]),
+            Err(SettingsError::CropKeyframesUnsorted(2))
+        );
+        assert_eq!(
+            validate_keyframes(&[
+                keyframe(0, 0, 0),
+                (Duration::from_secs(1), CropRect::new(0, 0, 100, 51))
+            ]),
+            Err(SettingsError::CropSizeMismatch(1))
+        );
+        assert_eq!(
+            validate_keyframes(&[(Duration::ZERO, CropRect::new(0, 0, 0, 10))]),
+            Err(SettingsError::CropRectEmpty)
+        );
+    }
+}
@@ -0,0 +1,86 @@
+//! The deadlines after which child processes get killed (e.g. the auxiliary child
+//! processes bounded by [`crate::Settings::auxiliary_timeout`]), so that a crafted or
+//! corrupt input cannot make them hang forever, and the absolute deadline by which a
+//! whole job must be done (see [`crate::Settings::deadline`]).
+
+use std::time::{Duration, Instant};
+
+#[derive(Debug, Clone, Copy)]
+/// A point in time, a given `timeout` after the deadline was set.
+pub(crate) struct Deadline {
+    /// When the deadline was set.
+    started: Instant,
+    /// How long after [`Deadline::started`] the deadline expires.
+    timeout: Duration,
+}
+
+impl Deadline {
+    /// A deadline that expires `timeout` from now.
+    pub(crate) fn after(timeout: Duration) -> Self {
+        Self {
+            started: Instant::now(),
+            timeout,
+        }
+    }
+
+    /// The duration after which the deadline expires (as passed to [`Deadline::after`]).
+    pub(crate) fn timeout(&self) -> Duration {
+        self.timeout
+    }
+
+    /// Whether the deadline has passed.
+    pub(crate) fn expired(&self) -> bool {
+        self.started.elapsed() > self.timeout
+    }
+}
+
+/// How long ago the absolute `deadline` passed, or `None` if it has not passed yet.
+pub(crate) fn overshoot(deadline: Instant) -> Option<Duration> {
+    Instant::now().checked_duration_since(deadline)
+}
+
+/// The `timeout` (if any), shortened so that it expires no later than the absolute
+/// `deadline` (if any), i.e. whichever of the two comes first.
+pub(crate) fn earliest(timeout: Option<Duration>, deadline: Option<Instant>) -> Option<Duration> {
+    let remaining = deadline.map(|d| d.saturating_duration_since(Instant::now()));
+    match (timeout, remaining) {
+        (Some(timeout), Some(remaining)) => Some(timeout.min(remaining)),
+        (timeout, remaining) => timeout.or(remaining),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_deadline() {
+        let deadline = Deadline::after(Duration::from_millis(50));
+        assert_eq!(deadline.timeout(), Duration::from_millis(50));
+        assert!(!deadline.expired());
+        std::thread::sleep(Duration::from_millis(100));
+        assert!(deadline.expired());
+    }
+
+    #[test]
+    fn test_overshoot() {
+        let now = Instant::now();
+        assert_eq!(overshoot(now + Duration::from_secs(60)), None);
+        let passed = overshoot(now - Duration::from_secs(1)).unwrap();
+        assert!(passed >= Duration::from_secs(1));
+    }
+
+    #[test]
+    fn test_earliest() {
+        let timeout = Some(Duration::from_secs(10));
+        assert_eq!(earliest(None, None), None);
+        assert_eq!(earliest(timeout, None), timeout);
+        let soon = earliest(timeout, Some(Instant::now() + Duration::from_secs(1))).unwrap();
+        assert!(soon <= Duration::from_secs(1));
+        let later = Some(Instant::now() + Duration::from_secs(60));
+        assert_eq!(earliest(timeout, later), timeout);
+        assert!(earliest(None, later).unwrap() > Duration::from_secs(50));
+        let passed = Some(Instant::now() - Duration::from_secs(1));
+        assert_eq!(earliest(timeout, passed), Some(Duration::ZERO));
+    }
+}
@@ -0,0 +1,182 @@
+//! The matching of FFmpeg's `stderr` output against the patterns provided using
+//! [`crate::Settings::deny_stderr_patterns`], which makes the conversion job fail
+//! when FFmpeg reports a warning that the application does not tolerate.
+//!
+//! The output is split into complete lines (see [`crate::stderr_lines`]), and each
+//! line is matched against each pattern. Since long lines are truncated, the cost of
+//! matching a line is bounded (and patterns are matched against the kept prefix).
+
+use crate::SettingsError;
+
+#[derive(Debug, Clone)]
+/// A pattern against which FFmpeg's `stderr` lines are matched.
+pub(crate) enum DenyPattern {
+    /// A plain substring.
+    Substring(String),
+    #[cfg(feature = "regex")]
+    /// A regular expression (whose matching time is linear in the line's length).
+    Regex(regex::Regex),
+}
+
+impl DenyPattern {
+    /// The pattern, as provided by the application.
+    fn as_str(&self) -> &str {
+        match self {
+            Self::Substring(s) => s,
+            #[cfg(feature = "regex")]
+            Self::Regex(r) => r.as_str(),
+        }
+    }
+
+    fn is_match(&self, line: &str) -> bool {
+        match self {
+            Self::Substring(s) => line.contains(s.as_str()),
+            #[cfg(feature = "regex")]
+            Self::Regex(r) => r.is_match(line),
+        }
+    }
+}
+
+/// Builds the list of patterns from the substrings and the regular expressions
+/// (only supported with the `regex` feature flag) provided by the application.
+pub(crate) fn build_patterns(
+    substrings: &[String],
+    #[cfg(feature = "regex")] regexes: &[String],
+) -> Result<Vec<DenyPattern>, SettingsError> {
+    let mut patterns = vec![];
+    for s in substrings {
+        // NOTE: An empty substring would match every single line.
+        if s.is_empty() {
+            return Err(SettingsError::InvalidDenyPattern(s.clone()));
+        }
+        patterns.push(DenyPattern::Substring(s.clone()));
+    }
+    #[cfg(feature = "regex")]
+    for r in regexes {
+        match regex::Regex::new(r) {
+            Ok(regex) => patterns.push(DenyPattern::Regex(regex)),
+            Err(_) => return Err(SettingsError::InvalidDenyPattern(r.clone())),
+        }
+    }
+    Ok(patterns)
+}
+
+/// Matches FFmpeg's `stderr` lines against the patterns.
+pub(crate) struct DenyList {
+    patterns: Vec<DenyPattern>,
+}
+
+impl DenyList {
+    pub(crate) fn new(patterns: Vec<DenyPattern>) -> Self {
+        Self { patterns }
+    }
+
+    /// Returns the first pattern matched by `line` (along with the trimmed line), if any.
+    pub(crate) fn check(&self, line: &str) -> Option<(String, String)> {
+        self.patterns
+            .iter()
+            .find(|p| p.is_match(line))
+            .map(|p| (p.as_str().to_string(), line.trim().to_string()))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::stderr_lines::{LineAssembler, MAX_LINE_LEN};
+
+    fn deny_list(patterns: &[&str]) -> DenyList {
+        let patterns: Vec<String> = patterns.iter().map(|p| p.to_string()).collect();
+        DenyList::new(
+            build_patterns(
+                &patterns,
+                #[cfg(feature = "regex")]
+                &[],
+            )
+            .unwrap(),
+        )
+    }
+
+    const TRANSCRIPT: &str = "Input #0, mov,mp4,m4a,3gp,3g2,mj2, from 'video.mp4':
+  Duration: 00:00:05.00, start: 0.000000, bitrate: 1785 kb/s
+[swscaler @ 0x7f8] deprecated pixel format used, make sure you did set range correctly
+frame=   10 fps=0.0 q=-0.0 size=       0kB time=00:00:01.00 bitrate=   0.0kbits/s speed=2x\r";
+
+    fn check_transcript(list: &DenyList) -> Vec<(String, String)> {
+        let mut assembler = LineAssembler::default();
+        assembler
+            .push(TRANSCRIPT.as_bytes())
+            .into_iter()
+            .chain(assembler.finish())
+            .filter_map(|line| list.check(&line.text))
+            .collect()
+    }
+
+    #[test]
+    fn test_match() {
+        let list = deny_list(&["non-monotonous DTS", "deprecated pixel format"]);
+        assert_eq!(
+            check_transcript(&list),
+            [(
+                "deprecated pixel format".to_string(),
+                "[swscaler @ 0x7f8] deprecated pixel format used, make sure you did set range correctly".to_string()
+            )]
+        );
+    }
+
+    #[test]
+    fn test_no_match() {
+        assert!(check_transcript(&deny_list(&["non-monotonous DTS"])).is_empty());
+        assert!(check_transcript(&deny_list(&[])).is_empty());
+    }
+
+    #[test]
+    fn test_long_lines_are_matched_on_their_prefix() {
+        let list = deny_list(&["needle"]);
+        let mut assembler = LineAssembler::default();
+        let mut bytes = b"needle ".to_vec();
+        bytes.extend(vec![b'x'; MAX_LINE_LEN * 4]);
+        bytes.extend_from_slice(b" haystack\n");
+        bytes.extend(vec![b'x'; MAX_LINE_LEN * 4]);
+        bytes.extend_from_slice(b" needle\n");
+        let lines = assembler.push(&bytes);
+        assert_eq!(lines.len(), 2);
+        assert_eq!(
+            list.check(&lines[0].text).map(|(p, _)| p),
+            Some("needle".into())
+        );
+        // The end of the line is dropped.
+        assert_eq!(list.check(&lines[1].text), None);
+    }
+
+    #[test]
+    fn test_empty_pattern() {
+        assert!(matches!(
+            build_patterns(
+                &["".into()],
+                #[cfg(feature = "regex")]
+                &[],
+            ),
+            Err(SettingsError::InvalidDenyPattern(_))
+        ));
+    }
+
+    #[cfg(feature = "regex")]
+    #[test]
+    fn test_regex() {
+        let patterns = build_patterns(&[], &[r"non-monotonous DTS.*\d+".into()]).unwrap();
+        let list = DenyList::new(patterns);
+        assert_eq!(list.check("non-monotonous DTS in output stream"), None);
+        assert_eq!(
+            list.check("[mp4] non-monotonous DTS; previous: 1024"),
+            Some((
+                r"non-monotonous DTS.*\d+".into(),
+                "[mp4] non-monotonous DTS; previous: 1024".into()
+            ))
+        );
+        assert!(matches!(
+            build_patterns(&[], &["(".into()]),
+            Err(SettingsError::InvalidDenyPattern(_))
+        ));
+    }
+}
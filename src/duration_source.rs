@@ -0,0 +1,278 @@
+//! The duration relative to which the progress is computed (see [`crate::Message::Progress`]),
+//! which is taken from the most reliable of the following sources, as they become available:
+//! * the duration probed before the job (only when a [`crate::ClipSelection`] needs it);
+//! * the `Duration: ...` line of FFmpeg's banner;
+//! * the `DURATION` tag of a stream's metadata (e.g. for Matroska files, whose container
+//!   duration may be missing);
+//! * an estimate based on the input's size and the bitrate announced on the `Duration: N/A`
+//!   line of FFmpeg's banner.
+//!
+//! A source that appears later in the job replaces the current one if it is more reliable
+//! (e.g. a `DURATION` tag found after the estimate), in which case [`crate::Message::VideoDuration`]
+//! is sent again. Without any of them, no progress is reported. The source in use at the end
+//! of the job is reported using [`crate::Summary::duration_source`].
+
+use std::time::Duration;
+
+use crate::time_parsing::{duration_from_ffmpeg_time_string, try_extract_duration_from_line};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
+/// Where the duration relative to which the progress is computed comes from (see
+/// [`crate::Summary::duration_source`]), the variants being ordered from the least
+/// to the most reliable.
+pub enum DurationSource {
+    /// Estimated from the input's size and the bitrate announced by FFmpeg, when the
+    /// container does not report a duration (e.g. for some streamed formats).
+    BitrateEstimate,
+    /// The `DURATION` tag of a stream's metadata.
+    StreamMetadata,
+    /// The `Duration: ...` line of FFmpeg's banner.
+    Banner,
+    /// The duration probed before the job (see [`crate::ClipSelection::needs_duration`]).
+    Probe,
+}
+
+/// Picks the most reliable duration found so far (see the module's documentation).
+pub(crate) struct DurationTracker {
+    /// The size of the input (in bytes), if known, for [`DurationSource::BitrateEstimate`].
+    input_bytes: Option<u64>,
+    current: Option<(DurationSource, Duration)>,
+}
+
+impl DurationTracker {
+    pub(crate) fn new(input_bytes: Option<u64>) -> Self {
+        Self {
+            input_bytes,
+            current: None,
+        }
+    }
+
+    /// The source of the duration currently in use, if any.
+    pub(crate) fn source(&self) -> Option<DurationSource> {
+        self.current.map(|(source, _)| source)
+    }
+
+    /// Offers the duration found by `source`, which is returned if it replaces the current one.
+    pub(crate) fn offer(&mut self, source: DurationSource, d: Duration) -> Option<Duration> {
+        if self.source().is_some_and(|current| current >= source) {
+            return None;
+        }
+        self.current = Some((source, d));
+        Some(d)
+    }
+
+    /// Offers the duration found in a `line` of FFmpeg's output, if any (see [`DurationTracker::offer`]).
+    pub(crate) fn push(
+        &mut self,
+        line: &str,
+        logging_identifier: Option<&str>,
+    ) -> Option<(DurationSource, Duration)> {
+        let (source, d) = if let Some(d) = try_extract_duration_from_line(line, logging_identifier)
+        {
+            (DurationSource::Banner, d)
+        } else if let Some(d) = try_extract_duration_tag(line, logging_identifier) {
+            (DurationSource::StreamMetadata, d)
+        } else {
+            let bits_per_second = try_extract_unknown_duration_bitrate(line)?;
+            let bits = self.input_bytes? * 8;
+            (
+                DurationSource::BitrateEstimate,
+                Duration::from_secs_f64(bits as f64 / bits_per_second as f64),
+            )
+        };
+        self.offer(source, d).map(|d| (source, d))
+    }
+}
+
+/// Extracts the duration from a stream's `DURATION` metadata tag
+/// (e.g. `      DURATION        : 00:00:05.013000000`).
+fn try_extract_duration_tag(line: &str, logging_identifier: Option<&str>) -> Option<Duration> {
+    let (key, value) = line.split_once(':')?;
+    if key.trim() != "DURATION" {
+        return None;
+    }
+    duration_from_ffmpeg_time_string(value.trim(), logging_identifier)
+}
+
+/// Extracts the bitrate (in bits per second) from a banner's `Duration` line that has
+/// no duration (e.g. `  Duration: N/A, start: 0.000000, bitrate: 1785 kb/s`).
+fn try_extract_unknown_duration_bitrate(line: &str) -> Option<u64> {
+    let rest = line.trim_start().strip_prefix("Duration: N/A,")?;
+    let (_, bitrate) = rest.split_once("bitrate: ")?;
+    let kbps: u64 = bitrate.trim_end().strip_suffix(" kb/s")?.parse().ok()?;
+    (kbps > 0).then_some(kbps * 1000)
+}
+
+/// The options that change the duration relative to which the progress is computed.
+pub(crate) struct DurationAdjustments {
+    /// The selected part of the video (start and length), if any, relative to which
+    /// FFmpeg's `time` values are computed.
+    pub(crate) clip_bounds: Option<(Duration, Option<Duration>)>,
+    /// The playback speed (see [`crate::Settings::speed`] and [`crate::Settings::frame_step`]),
+    /// by which FFmpeg's `time` values (i.e. those of the animated GIF) are divided.
+    pub(crate) speed: f32,
+    /// Whether the frames play twice (see [`crate::Settings::boomerang`]).
+    pub(crate) boomerang: bool,
+    /// The duration of the frames allowed by [`crate::Settings::output_frame_limit`], if any.
+    pub(crate) frame_limit: Option<Duration>,
+}
+
+#[derive(Debug, PartialEq, Eq)]
+/// A video's duration, once adjusted (see [`DurationAdjustments::apply`]).
+pub(crate) struct AdjustedDuration {
+    /// The duration sent using [`crate::Message::VideoDuration`].
+    pub(crate) reported: Duration,
+    /// The duration relative to which the progress is computed.
+    pub(crate) progress: Duration,
+    /// The start of the clip and the video's duration, if the former is past the latter.
+    pub(crate) clip_out_of_range: Option<(Duration, Duration)>,
+}
+
+impl DurationAdjustments {
+    /// Adjusts the video's duration `d` for the clip, speed, boomerang and frame limit.
+    pub(crate) fn apply(&self, d: Duration) -> AdjustedDuration {
+        let mut clip_out_of_range = None;
+        // NOTE: When only part of the video is converted, FFmpeg's
+        // `time` values are relative to the start of that part.
+        let clipped = match self.clip_bounds {
+            Some((start, length)) if d > start => length.map_or(d - start, |l| l.min(d - start)),
+            Some((start, _)) if !start.is_zero() => {
+                // NOTE: FFmpeg won't output any frames, which is then
+                // reported as `Error::ClipOutOfRange`.
+                clip_out_of_range = Some((start, d));
+                d
+            }
+            _ => d,
+        };
+        let clipped = match self.speed {
+            1.0 => clipped,
+            speed => clipped.div_f64(speed as f64),
+        };
+        let (reported, clipped) = match self.boomerang {
+            true => (d * 2, clipped * 2),
+            false => (d, clipped),
+        };
+        AdjustedDuration {
+            reported,
+            progress: match self.frame_limit {
+                Some(limit) => clipped.min(limit),
+                None => clipped,
+            },
+            clip_out_of_range,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn secs(n: u64) -> Duration {
+        Duration::from_secs(n)
+    }
+
+    #[test]
+    fn test_extract_from_lines() {
+        assert_eq!(
+            try_extract_duration_tag("      DURATION        : 00:00:05.013000000", None),
+            Some(Duration::from_millis(5013))
+        );
+        assert_eq!(
+            try_extract_duration_tag("    title           : DURATION", None),
+            None
+        );
+        assert_eq!(
+            try_extract_unknown_duration_bitrate(
+                "  Duration: N/A, start: 0.000000, bitrate: 1785 kb/s"
+            ),
+            Some(1_785_000)
+        );
+        assert_eq!(
+            try_extract_unknown_duration_bitrate("  Duration: N/A, start: 0.000000, bitrate: N/A"),
+            None
+        );
+        assert_eq!(
+            try_extract_unknown_duration_bitrate(
+                "  Duration: 00:00:05.00, start: 0.000000, bitrate: 1785 kb/s"
+            ),
+            None
+        );
+    }
+
+    #[test]
+    fn test_fallback_chain() {
+        // A banner without a duration, followed by a stream's metadata.
+        let lines = [
+            "  Duration: N/A, start: 0.000000, bitrate: 800 kb/s",
+            "  Stream #0:0: Video: h264 (High), yuv420p(progressive), 640x360, 24 fps",
+            "    Metadata:",
+            "      DURATION        : 00:00:04.500000000",
+            "  Stream #0:1: Audio: aac, 48000 Hz, stereo",
+            "    Metadata:",
+            "      DURATION        : 00:00:04.600000000",
+        ];
+        // NOTE: 500,000 bytes at 800 kb/s.
+        let mut tracker = DurationTracker::new(Some(500_000));
+        let found = Vec::from_iter(lines.iter().filter_map(|line| tracker.push(line, None)));
+        assert_eq!(
+            found,
+            [
+                (DurationSource::BitrateEstimate, secs(5)),
+                (DurationSource::StreamMetadata, Duration::from_millis(4500)),
+            ]
+        );
+        assert_eq!(tracker.source(), Some(DurationSource::StreamMetadata));
+
+        // Without the input's size, there is no estimate.
+        let mut tracker = DurationTracker::new(None);
+        assert_eq!(tracker.push(lines[0], None), None);
+        assert_eq!(tracker.source(), None);
+
+        // The banner's duration replaces the estimate, but not the probed duration.
+        let banner = "  Duration: 00:00:05.00, start: 0.000000, bitrate: 1785 kb/s";
+        let mut tracker = DurationTracker::new(Some(500_000));
+        tracker.offer(DurationSource::BitrateEstimate, secs(3));
+        assert_eq!(
+            tracker.push(banner, None),
+            Some((DurationSource::Banner, secs(5)))
+        );
+        let mut tracker = DurationTracker::new(None);
+        assert_eq!(tracker.offer(DurationSource::Probe, secs(5)), Some(secs(5)));
+        assert_eq!(tracker.push(banner, None), None);
+        assert_eq!(tracker.push(lines[3], None), None);
+        assert_eq!(tracker.source(), Some(DurationSource::Probe));
+    }
+
+    #[test]
+    fn test_adjustments() {
+        let adjustments = DurationAdjustments {
+            clip_bounds: Some((secs(2), Some(secs(10)))),
+            speed: 2.0,
+            boomerang: true,
+            frame_limit: None,
+        };
+        assert_eq!(
+            adjustments.apply(secs(6)),
+            AdjustedDuration {
+                reported: secs(12),
+                progress: secs(4),
+                clip_out_of_range: None,
+            }
+        );
+        let adjustments = DurationAdjustments {
+            clip_bounds: Some((secs(8), None)),
+            speed: 1.0,
+            boomerang: false,
+            frame_limit: Some(secs(1)),
+        };
+        assert_eq!(
+            adjustments.apply(secs(6)),
+            AdjustedDuration {
+                reported: secs(6),
+                progress: secs(1),
+                clip_out_of_range: Some((secs(8), secs(6))),
+            }
+        );
+    }
+}
@@ -0,0 +1,273 @@
+//! The generation of the value of FFmpeg's `-filter_complex` flag, i.e. the filter graph
+//! run by the conversion job (see [`crate::ConversionPlan::filter_complex`]).
+//!
+//! The graph is made of a chain of filters applied to the video's frames (e.g. `fps` and
+//! `scale`), whose output is split in two: one copy is used to generate the palette, which
+//! is then applied to the other copy (unless the output format has no palette, see
+//! [`crate::OutputFormat`], in which case the graph ends with the chain). The chain itself
+//! may be split and joined back along the way (e.g. by [`FilterGraph::boomerang`]), in
+//! which case it spans several statements.
+//!
+//! To generate several animated GIFs at once (see [`crate::Settings::additional_widths`]),
+//! the chain is split into branches (see [`FilterGraph::split`]), each of which ends with
+//! its own palette and labelled output, its labels being prefixed to keep them apart.
+//!
+//! With another input (e.g. a watermark, see [`crate::Settings::watermark`]), the chain
+//! starts from the video's explicit label (see [`FilterGraph::from_input`]), and the other
+//! input is prepared by its own statements, whose output is then overlaid onto the chain
+//! (see [`FilterGraph::overlay`]).
+
+#[derive(Debug, Default)]
+/// A filter graph, built one filter at a time.
+pub(crate) struct FilterGraph {
+    /// The statements (e.g. `[r]reverse[rr]`) that precede the current chain.
+    statements: Vec<String>,
+    /// The filters of the current chain, the first of which may be preceded by input labels.
+    chain: Vec<String>,
+    /// The prefix of the labels introduced by the graph (e.g. `o1_` for `[o1_s]`), if it is
+    /// one of the branches returned by [`FilterGraph::split`].
+    prefix: String,
+    /// The label of the input that precedes the chain's first filter (e.g. `[0:v]`), which
+    /// is only explicit when the graph has several inputs (or reads a specific video stream).
+    input: Option<String>,
+}
+
+impl FilterGraph {
+    /// A graph whose chain starts from the `input` label (e.g. `[0:v]`), and whose
+    /// `statements` (e.g. those preparing the other inputs) precede the chain.
+    pub(crate) fn from_input(input: &str, statements: Vec<String>) -> Self {
+        Self {
+            statements,
+            input: Some(input.into()),
+            ..Default::default()
+        }
+    }
+
+    /// Appends `filter` to the chain.
+    pub(crate) fn push(&mut self, filter: impl Into<String>) -> &mut Self {
+        let filter = filter.into();
+        match self.input.take() {
+            Some(input) => self.chain.push(format!("{}{}", input, filter)),
+            None => self.chain.push(filter),
+        }
+        self
+    }
+
+    /// Appends `filter` to the chain, if any.
+    pub(crate) fn push_some(&mut self, filter: Option<String>) -> &mut Self {
+        if let Some(filter) = filter {
+            self.push(filter);
+        }
+        self
+    }
+
+    /// The label called `name`, prefixed if needed (e.g. `[o1_s]`).
+    fn label(&self, name: &str) -> String {
+        format!("[{}{}]", self.prefix, name)
+    }
+
+    /// Makes the frames output so far play forward, then backward (see
+    /// [`crate::Settings::boomerang`]), by splitting the chain, reversing one copy,
+    /// and concatenating the other copy and the reversed one.
+    pub(crate) fn boomerang(&mut self) -> &mut Self {
+        let (f, r, rr) = (self.label("f"), self.label("r"), self.label("rr"));
+        self.push(format!("split{}{}", f, r));
+        self.statements.push(self.chain.join(","));
+        self.statements.push(format!("{}reverse{}", r, rr));
+        self.chain = vec![format!("{}{}concat=n=2", f, rr)];
+        self
+    }
+
+    /// Overlays the frames labelled `name` (prefixed if needed, e.g. `[o1_wm]`) onto the
+    /// frames output so far, using the `overlay` filter (e.g. `overlay=W-w:H-h`).
+    pub(crate) fn overlay(&mut self, name: &str, overlay: &str) -> &mut Self {
+        let (main, input) = (self.label("main"), self.label(name));
+        self.statements
+            .push(format!("{}{}", self.chain.join(","), main));
+        self.chain = vec![format!("{}{}{}", main, input, overlay)];
+        self
+    }
+
+    /// Splits the chain into one branch per prefix (e.g. `o1_`), each of which starts
+    /// with a copy of the frames output so far and uses that prefix for its own labels.
+    /// The graph then only holds the statements that precede the branches.
+    pub(crate) fn split(&mut self, prefixes: &[String]) -> Vec<FilterGraph> {
+        let branches = Vec::from_iter(prefixes.iter().map(|prefix| FilterGraph {
+            prefix: prefix.clone(),
+            ..Default::default()
+        }));
+        let labels = String::from_iter(branches.iter().map(|branch| branch.label("in")));
+        self.push(format!("split={}{}", prefixes.len(), labels));
+        self.statements.push(self.chain.join(","));
+        self.chain.clear();
+        Vec::from_iter(branches.into_iter().map(|mut branch| {
+            // NOTE: The input label precedes the branch's first filter.
+            branch.chain.push(format!("{}null", branch.label("in")));
+            branch
+        }))
+    }
+
+    /// The statements that precede the current chain (e.g. those preceding the
+    /// branches returned by [`FilterGraph::split`]).
+    pub(crate) fn into_statements(self) -> Vec<String> {
+        self.statements
+    }
+
+    /// The graph, ending with the generation (using the `palettegen` filter) and the
+    /// application (using the `paletteuse` filter) of the palette.
+    pub(crate) fn build(&self, palettegen: &str, paletteuse: &str) -> String {
+        self.build_to(palettegen, paletteuse, None)
+    }
+
+    /// Same as [`FilterGraph::build`], but ends with the `output` label, if any (e.g. `out1`).
+    pub(crate) fn build_to(
+        &self,
+        palettegen: &str,
+        paletteuse: &str,
+        output: Option<&str>,
+    ) -> String {
+        let (s, a, b, palette) = (
+            self.label("s"),
+            self.label("a"),
+            self.label("b"),
+            self.label("palette"),
+        );
+        let mut statements = self.statements.clone();
+        statements.push(format!("{}{}", self.chain.join(","), s));
+        statements.push(format!("{}split{}{}", s, a, b));
+        statements.push(format!("{}{}{}", a, palettegen, palette));
+        statements.push(format!(
+            "{}{}{}{}",
+            b,
+            palette,
+            paletteuse,
+            output.map(|o| format!("[{}]", o)).unwrap_or_default()
+        ));
+        statements.join("; ")
+    }
+
+    /// The graph, ending with the current chain (i.e. without any palette, e.g. for an
+    /// animated WebP) and the `output` label, if any (e.g. `out1`).
+    pub(crate) fn build_without_palette(&self, output: Option<&str>) -> String {
+        let mut statements = self.statements.clone();
+        statements.push(format!(
+            "{}{}",
+            self.chain.join(","),
+            output.map(|o| format!("[{}]", o)).unwrap_or_default()
+        ));
+        statements.join("; ")
+    }
+}
+
+/// The `filter`, followed by its `options` (e.g. `palettegen=max_colors=64:stats_mode=diff`).
+pub(crate) fn with_options(filter: &str, options: &[String]) -> String {
+    match options {
+        [] => filter.into(),
+        _ => format!("{}={}", filter, options.join(":")),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_filter_graph() {
+        let mut graph = FilterGraph::default();
+        graph.push("fps=10").push_some(None).push("scale=200:-1");
+        assert_eq!(
+            graph.build("palettegen", "paletteuse"),
+            "fps=10,scale=200:-1[s]; [s]split[a][b]; [a]palettegen[palette]; [b][palette]paletteuse"
+        );
+
+        let mut graph = FilterGraph::default();
+        graph
+            .push_some(Some("setpts=PTS/2".into()))
+            .push("fps=10")
+            .boomerang();
+        assert_eq!(
+            graph.build("palettegen=max_colors=64", "paletteuse"),
+            "setpts=PTS/2,fps=10,split[f][r]; [r]reverse[rr]; [f][rr]concat=n=2[s]; [s]split[a][b]; [a]palettegen=max_colors=64[palette]; [b][palette]paletteuse"
+        );
+        assert_eq!(
+            graph.build_without_palette(None),
+            "setpts=PTS/2,fps=10,split[f][r]; [r]reverse[rr]; [f][rr]concat=n=2"
+        );
+        assert_eq!(
+            graph.build_without_palette(Some("out1")),
+            "setpts=PTS/2,fps=10,split[f][r]; [r]reverse[rr]; [f][rr]concat=n=2[out1]"
+        );
+    }
+
+    #[test]
+    fn test_split() {
+        let mut graph = FilterGraph::default();
+        graph.push("fps=10");
+        let branches = graph.split(&["o0_".into(), "o1_".into()]);
+        let mut statements = graph.into_statements();
+        for (i, mut branch) in branches.into_iter().enumerate() {
+            branch
+                .push(format!("scale={}:-1", 100 * (i + 1)))
+                .boomerang();
+            statements.push(branch.build_to(
+                "palettegen",
+                "paletteuse",
+                Some(&format!("out{}", i)),
+            ));
+        }
+        assert_eq!(
+            statements,
+            [
+                "fps=10,split=2[o0_in][o1_in]",
+                "[o0_in]null,scale=100:-1,split[o0_f][o0_r]; [o0_r]reverse[o0_rr]; [o0_f][o0_rr]concat=n=2[o0_s]; [o0_s]split[o0_a][o0_b]; [o0_a]palettegen[o0_palette]; [o0_b][o0_palette]paletteuse[out0]",
+                "[o1_in]null,scale=200:-1,split[o1_f][o1_r]; [o1_r]reverse[o1_rr]; [o1_f][o1_rr]concat=n=2[o1_s]; [o1_s]split[o1_a][o1_b]; [o1_a]palettegen[o1_palette]; [o1_b][o1_palette]paletteuse[out1]",
+            ]
+        );
+    }
+
+    #[test]
+    fn test_overlay() {
+        let mut graph = FilterGraph::from_input("[0:v]", vec!["[1:v]format=rgba[wm]".into()]);
+        graph
+            .push_some(None)
+            .push("fps=10")
+            .overlay("wm", "overlay=0:0");
+        assert_eq!(
+            graph.build("palettegen", "paletteuse"),
+            "[1:v]format=rgba[wm]; [0:v]fps=10[main]; [main][wm]overlay=0:0[s]; [s]split[a][b]; [a]palettegen[palette]; [b][palette]paletteuse"
+        );
+
+        let mut graph = FilterGraph::from_input("[0:v]", vec![]);
+        graph.push("fps=10");
+        let branches = graph.split(&["o0_".into(), "o1_".into()]);
+        let mut statements = graph.into_statements();
+        for (i, mut branch) in branches.into_iter().enumerate() {
+            branch.overlay("wm", "overlay=0:0");
+            statements.push(branch.build_to(
+                "palettegen",
+                "paletteuse",
+                Some(&format!("out{}", i)),
+            ));
+        }
+        assert_eq!(
+            statements,
+            [
+                "[0:v]fps=10,split=2[o0_in][o1_in]",
+                "[o0_in]null[o0_main]; [o0_main][o0_wm]overlay=0:0[o0_s]; [o0_s]split[o0_a][o0_b]; [o0_a]palettegen[o0_palette]; [o0_b][o0_palette]paletteuse[out0]",
+                "[o1_in]null[o1_main]; [o1_main][o1_wm]overlay=0:0[o1_s]; [o1_s]split[o1_a][o1_b]; [o1_a]palettegen[o1_palette]; [o1_b][o1_palette]paletteuse[out1]",
+            ]
+        );
+    }
+
+    #[test]
+    fn test_with_options() {
+        assert_eq!(with_options("paletteuse", &[]), "paletteuse");
+        assert_eq!(
+            with_options(
+                "palettegen",
+                &["max_colors=64".into(), "stats_mode=diff".into()]
+            ),
+            "palettegen=max_colors=64:stats_mode=diff"
+        );
+    }
+}
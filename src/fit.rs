@@ -0,0 +1,370 @@
+//! The generation of FFmpeg's `scale` filter, which resizes the video's frames to the
+//! animated GIF's width (see [`crate::Settings::with_standard_fps`]) and, optionally,
+//! to its height (see [`crate::Settings::gif_height`]), in which case the source's aspect
+//! ratio is handled according to the [`FitMode`] (see [`crate::Settings::fit_mode`]), using
+//! the [`ScaleAlgorithm`] provided using [`crate::Settings::scale_algorithm`], if any.
+//!
+//! The dimensions that the filter produces are computed by [`compute_output_dimensions`],
+//! which applications can use to preview them (e.g. while the user picks a width), and which
+//! is the one place where FFmpeg's `-1` and `-2` semantics are reimplemented.
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
+/// How the video's frames are fitted into the animated GIF's dimensions, when both its
+/// width and its height are provided (see [`crate::Settings::gif_height`]).
+pub enum FitMode {
+    /// The frames are scaled to the exact dimensions, ignoring the video's aspect ratio.
+    Stretch,
+    /// The frames are scaled down to fit within the dimensions, keeping the video's
+    /// aspect ratio, and are then centered and padded with black bars.
+    #[default]
+    Letterbox,
+    /// The frames are scaled up to cover the dimensions, keeping the video's aspect
+    /// ratio, and are then cropped around their center.
+    Crop,
+}
+
+impl FitMode {
+    /// A short name for the fit mode (e.g. for [`crate::ConversionPlan`]'s summary).
+    pub(crate) fn name(&self) -> &'static str {
+        match self {
+            Self::Stretch => "stretch",
+            Self::Letterbox => "letterbox",
+            Self::Crop => "crop",
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
+/// The algorithm used by FFmpeg's `scale` filter to resize the video's frames (i.e. the
+/// filter's `flags` option, see [`crate::Settings::scale_algorithm`]).
+pub enum ScaleAlgorithm {
+    /// Bilinear interpolation (FFmpeg's default), which is fast, but blurs sharp edges.
+    Bilinear,
+    /// Bicubic interpolation, which is slightly sharper than bilinear.
+    Bicubic,
+    /// Lanczos resampling, which keeps downscaled details (e.g. screen text) the sharpest.
+    Lanczos,
+    /// Nearest neighbor, which keeps the pixels' own colors (e.g. for pixel art).
+    Neighbor,
+    /// Pixel area averaging, which suits large reductions.
+    Area,
+}
+
+impl ScaleAlgorithm {
+    /// The algorithm's name, as used by FFmpeg's `flags` option (e.g. `lanczos`).
+    pub(crate) fn name(&self) -> &'static str {
+        match self {
+            Self::Bilinear => "bilinear",
+            Self::Bicubic => "bicubic",
+            Self::Lanczos => "lanczos",
+            Self::Neighbor => "neighbor",
+            Self::Area => "area",
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
+/// How the animated GIF's dimensions are derived from the settings (see
+/// [`crate::ConversionPlan::size_mode`]).
+pub enum SizeMode {
+    /// The given width, the height following the frames' aspect ratio (rounded according
+    /// to the [`EvenDimensionPolicy`]).
+    Width(u16),
+    /// The given width and height, into which the frames are fitted using the [`FitMode`].
+    Fit {
+        width: u16,
+        height: u16,
+        fit: FitMode,
+    },
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
+/// How the height that follows the frames' aspect ratio (see [`SizeMode::Width`]) is rounded
+/// (see [`crate::Settings::even_dimension_policy`]).
+pub enum EvenDimensionPolicy {
+    /// Rounded to the nearest integer (i.e. FFmpeg's `-1`).
+    #[default]
+    Any,
+    /// Rounded to the nearest even integer (i.e. FFmpeg's `-2`), e.g. for the GIFs that
+    /// get converted to a video afterwards, which most encoders require.
+    Even,
+}
+
+impl EvenDimensionPolicy {
+    /// The value used by FFmpeg's `scale` filter for a dimension that follows the aspect ratio.
+    fn scale_value(&self) -> &'static str {
+        match self {
+            Self::Any => "-1",
+            Self::Even => "-2",
+        }
+    }
+
+    /// The number that a dimension following the aspect ratio is a multiple of.
+    fn factor(&self) -> u64 {
+        match self {
+            Self::Any => 1,
+            Self::Even => 2,
+        }
+    }
+}
+
+/// The dimensions of the animated GIF generated from frames of the given `source` dimensions
+/// (i.e. once rotated and cropped, if applicable) according to the `size_mode`, computed the
+/// way FFmpeg's `scale` filter does, i.e. as produced by the filter graph that the
+/// [`crate::Settings`] generate (see [`crate::ConversionPlan::size_mode`] and
+/// [`crate::ConversionPlan::even_dimension_policy`]).
+///
+/// NOTE: A derived height is at least 1 pixel (or 2, see [`EvenDimensionPolicy::Even`]), and
+/// is 0 if a `source` dimension is 0.
+pub fn compute_output_dimensions(
+    source: (u32, u32),
+    size_mode: &SizeMode,
+    even_policy: EvenDimensionPolicy,
+) -> (u32, u32) {
+    match *size_mode {
+        SizeMode::Width(width) => {
+            let (source_width, source_height) = source;
+            if source_width == 0 || source_height == 0 {
+                return (u32::from(width), 0);
+            }
+            // NOTE: Same rounding as FFmpeg's `av_rescale` (i.e. to the nearest, halves away
+            // from zero), the factor being applied to the divisor before the rounding.
+            let factor = even_policy.factor();
+            let divisor = u64::from(source_width) * factor;
+            let height = (u64::from(width) * u64::from(source_height) + divisor / 2) / divisor;
+            let height = (height * factor).max(factor);
+            (u32::from(width), u32::try_from(height).unwrap_or(u32::MAX))
+        }
+        SizeMode::Fit { width, height, .. } => (u32::from(width), u32::from(height)),
+    }
+}
+
+/// The filter(s) that resize the frames according to the `size_mode` (the height following
+/// the aspect ratio being rounded according to the `even_policy`), using `algorithm`
+/// (FFmpeg's default if `None`).
+pub(crate) fn scale_filter(
+    size_mode: &SizeMode,
+    even_policy: EvenDimensionPolicy,
+    algorithm: Option<ScaleAlgorithm>,
+) -> String {
+    let flags = algorithm
+        .map(|a| format!(":flags={}", a.name()))
+        .unwrap_or_default();
+    let (width, height, fit) = match *size_mode {
+        SizeMode::Width(width) => {
+            return format!("scale={}:{}{}", width, even_policy.scale_value(), flags)
+        }
+        SizeMode::Fit { width, height, fit } => (width, height, fit),
+    };
+    match fit {
+        FitMode::Stretch => format!("scale={}:{}{}", width, height, flags),
+        FitMode::Letterbox => format!(
+            "scale={w}:{h}:force_original_aspect_ratio=decrease{f},pad={w}:{h}:(ow-iw)/2:(oh-ih)/2",
+            w = width,
+            h = height,
+            f = flags
+        ),
+        FitMode::Crop => format!(
+            "scale={w}:{h}:force_original_aspect_ratio=increase{f},crop={w}:{h}",
+            w = width,
+            h = height,
+            f = flags
+        ),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn fit(width: u16, height: u16, fit: FitMode) -> SizeMode {
+        SizeMode::Fit { width, height, fit }
+    }
+
+    #[test]
+    fn test_scale_filter() {
+        assert_eq!(
+            scale_filter(&SizeMode::Width(480), EvenDimensionPolicy::Any, None),
+            "scale=480:-1"
+        );
+        assert_eq!(
+            scale_filter(
+                &fit(480, 270, FitMode::Stretch),
+                EvenDimensionPolicy::Any,
+                None
+            ),
+            "scale=480:270"
+        );
+        assert_eq!(
+            scale_filter(
+                &fit(480, 270, FitMode::Letterbox),
+                EvenDimensionPolicy::Any,
+                None
+            ),
+            "scale=480:270:force_original_aspect_ratio=decrease,pad=480:270:(ow-iw)/2:(oh-ih)/2"
+        );
+        assert_eq!(
+            scale_filter(
+                &fit(480, 270, FitMode::Crop),
+                EvenDimensionPolicy::Any,
+                None
+            ),
+            "scale=480:270:force_original_aspect_ratio=increase,crop=480:270"
+        );
+        // NOTE: Only the height that follows the aspect ratio is affected by the policy.
+        assert_eq!(
+            scale_filter(&SizeMode::Width(480), EvenDimensionPolicy::Even, None),
+            "scale=480:-2"
+        );
+        assert_eq!(
+            scale_filter(
+                &fit(481, 271, FitMode::Stretch),
+                EvenDimensionPolicy::Even,
+                None
+            ),
+            "scale=481:271"
+        );
+    }
+
+    #[test]
+    fn test_compute_output_dimensions_width() {
+        use EvenDimensionPolicy::{Any, Even};
+        // (source, width, height with `-1`, height with `-2`)
+        let cases = [
+            // Landscape sources.
+            ((1920, 1080), 480, 270, 270),
+            ((1920, 1080), 200, 113, 112),
+            ((1920, 1080), 201, 113, 114),
+            ((1280, 720), 100, 56, 56),
+            ((640, 360), 333, 187, 188),
+            ((1920, 800), 250, 104, 104),
+            // Portrait sources.
+            ((1080, 1920), 480, 853, 854),
+            ((1080, 1920), 200, 356, 356),
+            ((720, 1280), 99, 176, 176),
+            ((360, 640), 101, 180, 180),
+            // Square sources.
+            ((500, 500), 200, 200, 200),
+            ((500, 500), 201, 201, 202),
+            // Odd source dimensions.
+            ((1921, 1081), 480, 270, 270),
+            ((853, 481), 200, 113, 112),
+            ((479, 853), 120, 214, 214),
+            ((3, 5), 2, 3, 4),
+            // Halves are rounded away from zero (before the factor is applied).
+            ((4, 3), 2, 2, 2),
+            ((8, 3), 4, 2, 2),
+            ((4, 6), 1, 2, 2),
+            // Upscaling.
+            ((320, 240), 640, 480, 480),
+            ((321, 241), 640, 480, 480),
+            // Tiny results are at least 1 pixel (or 2).
+            ((1920, 10), 100, 1, 2),
+            ((10000, 1), 1, 1, 2),
+            // Extreme dimensions.
+            ((1, 10000), u16::MAX, 655350000, 655350000),
+        ];
+        for (source, width, any, even) in cases {
+            let size_mode = SizeMode::Width(width);
+            assert_eq!(
+                compute_output_dimensions(source, &size_mode, Any),
+                (u32::from(width), any),
+                "{:?} at {}px (-1)",
+                source,
+                width
+            );
+            assert_eq!(
+                compute_output_dimensions(source, &size_mode, Even),
+                (u32::from(width), even),
+                "{:?} at {}px (-2)",
+                source,
+                width
+            );
+        }
+    }
+
+    #[test]
+    fn test_compute_output_dimensions_fit() {
+        // NOTE: The frames are stretched, padded or cropped into the given dimensions, whatever
+        // the source's aspect ratio, and the policy only applies to a derived height.
+        let sources = [(1920, 1080), (1080, 1920), (1921, 1081), (481, 853), (1, 1)];
+        let policies = [EvenDimensionPolicy::Any, EvenDimensionPolicy::Even];
+        let fits = [FitMode::Stretch, FitMode::Letterbox, FitMode::Crop];
+        for source in sources {
+            for policy in policies {
+                for mode in fits {
+                    for (width, height) in [(480, 270), (201, 301), (1, 1)] {
+                        assert_eq!(
+                            compute_output_dimensions(source, &fit(width, height, mode), policy),
+                            (u32::from(width), u32::from(height)),
+                            "{:?} into {}x{} ({:?}, {:?})",
+                            source,
+                            width,
+                            height,
+                            mode,
+                            policy
+                        );
+                    }
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn test_compute_output_dimensions_empty_source() {
+        for policy in [EvenDimensionPolicy::Any, EvenDimensionPolicy::Even] {
+            for source in [(0, 1080), (1920, 0), (0, 0)] {
+                assert_eq!(
+                    compute_output_dimensions(source, &SizeMode::Width(480), policy),
+                    (480, 0)
+                );
+            }
+            assert_eq!(
+                compute_output_dimensions((0, 0), &fit(480, 270, FitMode::Crop), policy),
+                (480, 270)
+            );
+        }
+    }
+
+    #[test]
+    fn test_scale_filter_algorithm() {
+        let cases = [
+            (ScaleAlgorithm::Bilinear, "scale=480:-1:flags=bilinear"),
+            (ScaleAlgorithm::Bicubic, "scale=480:-1:flags=bicubic"),
+            (ScaleAlgorithm::Lanczos, "scale=480:-1:flags=lanczos"),
+            (ScaleAlgorithm::Neighbor, "scale=480:-1:flags=neighbor"),
+            (ScaleAlgorithm::Area, "scale=480:-1:flags=area"),
+        ];
+        for (algorithm, expected) in cases {
+            assert_eq!(
+                scale_filter(
+                    &SizeMode::Width(480),
+                    EvenDimensionPolicy::Any,
+                    Some(algorithm)
+                ),
+                expected
+            );
+        }
+        // NOTE: The flags belong to the `scale` filter, not to the padding or the cropping.
+        assert_eq!(
+            scale_filter(
+                &fit(480, 270, FitMode::Letterbox),
+                EvenDimensionPolicy::Any,
+                Some(ScaleAlgorithm::Lanczos)
+            ),
+            "scale=480:270:force_original_aspect_ratio=decrease:flags=lanczos,pad=480:270:(ow-iw)/2:(oh-ih)/2"
+        );
+        assert_eq!(
+            scale_filter(
+                &fit(480, 270, FitMode::Crop),
+                EvenDimensionPolicy::Even,
+                Some(ScaleAlgorithm::Neighbor)
+            ),
+            "scale=480:270:force_original_aspect_ratio=increase:flags=neighbor,crop=480:270"
+        );
+    }
+}
@@ -0,0 +1,121 @@
+//! The mapping from the animated GIF's frames to timestamps in the source video (see
+//! [`crate::Settings::emit_frame_map`]), e.g. for an application that lets its users
+//! click a frame to jump to the same point in the video.
+//!
+//! The mapping is derived from the settings, rather than from the GIF's frame delays,
+//! which are rounded to hundredths of a second (so that adding them up would drift):
+//! FFmpeg's `fps` filter outputs its `n`-th frame at `n / fps` seconds, counted from the
+//! start of the selected part of the video (since the input is seeked to it using `-ss`).
+//! The number of frames, however, is the one found in the animated GIF itself, the second
+//! half of which mirrors the first one for a boomerang (see [`crate::Settings::boomerang`]).
+
+use std::time::Duration;
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+/// What the timestamps of the animated GIF's frames are derived from.
+pub(crate) struct FrameMap {
+    /// The animated GIF's frame rate (in frames per second).
+    pub(crate) fps: u16,
+    /// The playback speed (see [`crate::Settings::speed`]), i.e. how much video time
+    /// separates two frames, relative to the GIF's frame interval.
+    pub(crate) speed: f32,
+    /// The start of the selected part of the video (see [`crate::Settings::clip`]).
+    pub(crate) start: Duration,
+    /// The end of the selected part of the video, if known, which caps the timestamps
+    /// (e.g. that of the frame added by [`crate::Settings::preserve_last_frame`]).
+    pub(crate) end: Option<Duration>,
+    /// Whether the animated GIF plays forward, then backward.
+    pub(crate) boomerang: bool,
+}
+
+impl FrameMap {
+    /// The timestamp (in the source video) of each of the animated GIF's `frame_count` frames.
+    pub(crate) fn timestamps(&self, frame_count: usize) -> Vec<Duration> {
+        (0..frame_count as u64)
+            .map(|n| match self.boomerang {
+                true => n.min(frame_count as u64 - 1 - n),
+                false => n,
+            })
+            .map(|n| {
+                let offset = Duration::from_secs(n) / self.fps.max(1) as u32;
+                let timestamp = match self.speed {
+                    1.0 => self.start + offset,
+                    speed => self.start + offset.mul_f64(speed as f64),
+                };
+                match self.end {
+                    Some(end) => timestamp.min(end),
+                    None => timestamp,
+                }
+            })
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_timestamps() {
+        let map = FrameMap {
+            fps: 10,
+            speed: 1.0,
+            start: Duration::ZERO,
+            end: None,
+            boomerang: false,
+        };
+        assert_eq!(map.timestamps(3), [0, 100, 200].map(Duration::from_millis));
+        assert!(map.timestamps(0).is_empty());
+
+        // No drift, even though 1/3 of a second cannot be represented exactly.
+        let map = FrameMap {
+            fps: 3,
+            speed: 1.0,
+            start: Duration::from_millis(1_500),
+            end: None,
+            boomerang: false,
+        };
+        let timestamps = map.timestamps(301);
+        assert_eq!(timestamps[1], Duration::from_nanos(1_833_333_333));
+        assert_eq!(timestamps[300], Duration::from_millis(101_500));
+
+        // Capped by the end of the clip.
+        let map = FrameMap {
+            fps: 10,
+            speed: 1.0,
+            start: Duration::from_secs(2),
+            end: Some(Duration::from_millis(2_150)),
+            boomerang: false,
+        };
+        assert_eq!(
+            map.timestamps(3),
+            [2_000, 2_100, 2_150].map(Duration::from_millis)
+        );
+
+        // Twice as much video time between two frames at twice the speed.
+        let map = FrameMap {
+            fps: 10,
+            speed: 2.0,
+            start: Duration::from_secs(2),
+            end: None,
+            boomerang: false,
+        };
+        assert_eq!(
+            map.timestamps(3),
+            [2_000, 2_200, 2_400].map(Duration::from_millis)
+        );
+
+        // The second half mirrors the first one.
+        let map = FrameMap {
+            fps: 10,
+            speed: 1.0,
+            start: Duration::ZERO,
+            end: None,
+            boomerang: true,
+        };
+        assert_eq!(
+            map.timestamps(5),
+            [0, 100, 200, 100, 0].map(Duration::from_millis)
+        );
+    }
+}
@@ -0,0 +1,730 @@
+//! A minimal GIF block parser, used by the [`crate::Converter`] to validate its
+//! output and exposed publicly so that applications can inspect the generated
+//! animated GIF (e.g. to display "N frames, M colors, loops forever"), and edit
+//! the settings that don't require encoding it again (see [`GifData`]).
+//!
+//! NOTE: The parser only walks the GIF's block structure; it does not decode
+//! the LZW-compressed image data.
+
+use std::ops::Range;
+
+pub(crate) const TRAILER: u8 = 0x3b;
+const EXTENSION_INTRODUCER: u8 = 0x21;
+const IMAGE_SEPARATOR: u8 = 0x2c;
+const GRAPHIC_CONTROL_LABEL: u8 = 0xf9;
+const APPLICATION_LABEL: u8 = 0xff;
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+/// The information extracted from an animated GIF by [`parse_gif_info`].
+pub struct GifInfo {
+    /// The logical screen width.
+    pub width: u16,
+    /// The logical screen height.
+    pub height: u16,
+    /// The number of frames (i.e. image descriptor blocks) in the GIF.
+    pub frame_count: usize,
+    /// The loop count found in the `NETSCAPE2.0` application extension, if any.
+    /// A value of `Some(0)` means that the animation loops forever, while `None`
+    /// means that the extension is absent (i.e. the animation plays once).
+    pub loop_count: Option<u16>,
+    /// The number of colors in the global color table, if there is one.
+    pub global_palette_size: Option<usize>,
+    /// The number of frames that have their own (i.e. local) color table, which
+    /// is zero when all the frames use the global color table.
+    pub local_palette_count: usize,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+/// An error returned by [`parse_gif_info`] when the bytes don't make up a valid GIF.
+pub enum GifParseError {
+    /// The data does not start with `GIF87a` or `GIF89a`.
+    InvalidSignature,
+    /// The data ended before the GIF trailer was reached.
+    UnexpectedEof {
+        /// The offset at which more data was expected.
+        offset: usize,
+    },
+    /// An unknown block introducer byte was found.
+    UnknownBlock {
+        /// The offset of the unexpected byte.
+        offset: usize,
+        /// The unexpected byte.
+        byte: u8,
+    },
+}
+
+impl std::error::Error for GifParseError {}
+
+impl std::fmt::Display for GifParseError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::InvalidSignature => write!(f, "invalid GIF signature"),
+            Self::UnexpectedEof { offset } => {
+                write!(f, "unexpected end of data at offset {}", offset)
+            }
+            Self::UnknownBlock { offset, byte } => {
+                write!(f, "unknown block 0x{:02x} at offset {}", byte, offset)
+            }
+        }
+    }
+}
+
+/// Parses the header and block structure of the GIF contained in `bytes`.
+///
+/// Any data found after the GIF trailer is ignored. This function never
+/// panics, whatever the input.
+pub fn parse_gif_info(bytes: &[u8]) -> Result<GifInfo, GifParseError> {
+    let layout = parse_layout(bytes)?;
+    let mut loop_count = None;
+    let mut frame_count = 0;
+    let mut local_palette_count = 0;
+    for block in layout.blocks.iter() {
+        match block.kind {
+            BlockKind::Image { local_color_table } => {
+                frame_count += 1;
+                if local_color_table {
+                    local_palette_count += 1;
+                }
+            }
+            BlockKind::Application {
+                loop_count: Some(n),
+            } => loop_count = Some(n),
+            _ => {}
+        }
+    }
+    Ok(GifInfo {
+        width: layout.width,
+        height: layout.height,
+        frame_count,
+        loop_count,
+        global_palette_size: layout.global_palette_size,
+        local_palette_count,
+    })
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+/// How many times an animated GIF is played (see [`GifData::set_loop_count`]).
+pub enum LoopCount {
+    /// The animation loops forever.
+    Forever,
+    /// The animation is played once (i.e. the GIF has no `NETSCAPE2.0` application extension).
+    Once,
+    /// The animation is repeated the given number of times after being played once (i.e.
+    /// the value stored in the `NETSCAPE2.0` application extension), `Repeat(0)` being the
+    /// same as [`LoopCount::Once`].
+    Repeat(u16),
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+/// The error returned by [`GifData::scale_frame_delays`] when the factor is not a
+/// positive (and finite) number.
+pub struct InvalidDelayFactor(pub f32);
+
+impl std::error::Error for InvalidDelayFactor {}
+
+impl std::fmt::Display for InvalidDelayFactor {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "invalid frame delay factor {}", self.0)
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+/// The error returned by [`GifData::set_frame_rate`] when the frame rate is not a positive
+/// (and finite) number of at most [`GifData::MAX_FRAME_RATE`] frames per second.
+pub struct InvalidFrameRate(pub f64);
+
+impl std::error::Error for InvalidFrameRate {}
+
+impl std::fmt::Display for InvalidFrameRate {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "invalid frame rate {}", self.0)
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+/// An animated GIF (e.g. the bytes sent using [`crate::Message::Success`]) whose block
+/// structure has been validated, and which can be edited in place without encoding it
+/// again, e.g. so that a UI can instantly offer "loop 3 times" or "play 2x faster" on an
+/// existing result. The edits only touch the bytes of the blocks they are about.
+pub struct GifData {
+    bytes: Vec<u8>,
+}
+
+impl GifData {
+    /// Validates the block structure of the GIF contained in `bytes` (see [`parse_gif_info`]).
+    pub fn parse(bytes: Vec<u8>) -> Result<Self, GifParseError> {
+        parse_layout(&bytes)?;
+        Ok(Self { bytes })
+    }
+
+    /// The information about the GIF (see [`parse_gif_info`]).
+    pub fn info(&self) -> GifInfo {
+        parse_gif_info(&self.bytes).expect("validated by GifData::parse")
+    }
+
+    /// The GIF's bytes.
+    pub fn as_bytes(&self) -> &[u8] {
+        &self.bytes
+    }
+
+    /// Returns the GIF's bytes.
+    pub fn into_bytes(self) -> Vec<u8> {
+        self.bytes
+    }
+
+    fn layout(&self) -> Layout {
+        parse_layout(&self.bytes).expect("validated by GifData::parse")
+    }
+
+    /// Changes how many times the animation is played, by rewriting the loop count of its
+    /// `NETSCAPE2.0` application extension, which is added (right before the first frame)
+    /// or removed as needed.
+    pub fn set_loop_count(&mut self, loop_count: LoopCount) {
+        let value = match loop_count {
+            LoopCount::Forever => Some(0),
+            LoopCount::Once | LoopCount::Repeat(0) => None,
+            LoopCount::Repeat(n) => Some(n),
+        };
+        let layout = self.layout();
+        let existing = layout.blocks.iter().find(|b| {
+            matches!(
+                b.kind,
+                BlockKind::Application {
+                    loop_count: Some(_)
+                }
+            )
+        });
+        match (existing, value) {
+            (Some(block), Some(value)) => {
+                // NOTE: Introducer, label, identifier (and its size), sub-block size and id.
+                let offset = block.range.start + 3 + self.bytes[block.range.start + 2] as usize + 2;
+                self.bytes[offset..offset + 2].copy_from_slice(&value.to_le_bytes());
+            }
+            (Some(block), None) => {
+                self.bytes.drain(block.range.clone());
+            }
+            (None, Some(value)) => {
+                let offset = layout
+                    .blocks
+                    .first()
+                    .map_or(layout.trailer, |b| b.range.start);
+                let mut block = vec![EXTENSION_INTRODUCER, APPLICATION_LABEL, 0x0b];
+                block.extend_from_slice(b"NETSCAPE2.0");
+                block.extend_from_slice(&[0x03, 0x01]);
+                block.extend_from_slice(&value.to_le_bytes());
+                block.push(0x00);
+                self.bytes.splice(offset..offset, block);
+            }
+            (None, None) => {}
+        }
+    }
+
+    /// Multiplies the delay of each frame by `factor` (e.g. 0.5 to play the animation twice
+    /// as fast), by rewriting its graphic control extension. The delays are rounded to the
+    /// nearest centisecond, a nonzero delay never becoming zero.
+    ///
+    /// NOTE: Many decoders (e.g. web browsers) play frames with a delay shorter than
+    /// 2 centiseconds much slower, so speeding up an animation whose delays are already
+    /// short can make it play slower instead.
+    pub fn scale_frame_delays(&mut self, factor: f32) -> Result<(), InvalidDelayFactor> {
+        if !(factor.is_finite() && factor > 0.0) {
+            return Err(InvalidDelayFactor(factor));
+        }
+        for block in self.layout().blocks {
+            let BlockKind::GraphicControl { delay } = block.kind else {
+                continue;
+            };
+            if delay == 0 {
+                continue;
+            }
+            let scaled = (delay as f64 * factor as f64)
+                .round()
+                .clamp(1.0, u16::MAX as f64);
+            // NOTE: Introducer, label, block size and packed field.
+            let offset = block.range.start + 4;
+            self.bytes[offset..offset + 2].copy_from_slice(&(scaled as u16).to_le_bytes());
+        }
+        Ok(())
+    }
+
+    /// The largest frame rate accepted by [`GifData::set_frame_rate`], i.e. one frame per
+    /// centisecond (the unit of the frames' delays).
+    pub const MAX_FRAME_RATE: f64 = 100.0;
+
+    /// Sets the delays of the frames so that the animation plays at `fps` frames per
+    /// second, by rewriting their graphic control extensions. Since the delays are whole
+    /// centiseconds, they alternate when `100 / fps` is not a whole number (e.g. 7, 7 and 6
+    /// centiseconds at 15 fps), each frame ending at the first centisecond at or after its
+    /// exact end, so that the total duration never drifts by more than a centisecond.
+    pub fn set_frame_rate(&mut self, fps: f64) -> Result<(), InvalidFrameRate> {
+        if !(fps.is_finite() && fps > 0.0 && fps <= Self::MAX_FRAME_RATE) {
+            return Err(InvalidFrameRate(fps));
+        }
+        // NOTE: The exact end of a frame is slightly lowered, so that rounding errors
+        // (e.g. `3.0 * 100.0 / 30.0` not being exactly 10) never push it to the next
+        // centisecond.
+        let end = |n: usize| (n as f64 * 100.0 / fps - 1e-6).ceil().min(u32::MAX as f64) as u32;
+        let graphic_controls = self
+            .layout()
+            .blocks
+            .into_iter()
+            .filter(|b| matches!(b.kind, BlockKind::GraphicControl { .. }));
+        for (n, block) in graphic_controls.enumerate() {
+            let delay = (end(n + 1) - end(n)).min(u16::MAX as u32) as u16;
+            // NOTE: Introducer, label, block size and packed field.
+            let offset = block.range.start + 4;
+            self.bytes[offset..offset + 2].copy_from_slice(&delay.to_le_bytes());
+        }
+        Ok(())
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+/// The kind of a block found by [`parse_layout`].
+pub(crate) enum BlockKind {
+    /// A graphic control extension, with its delay (in centiseconds).
+    GraphicControl { delay: u16 },
+    /// An application extension, with the loop count if it is a
+    /// `NETSCAPE2.0` (or `ANIMEXTS1.0`) extension.
+    Application { loop_count: Option<u16> },
+    /// Any other extension (e.g. comment or plain text).
+    OtherExtension,
+    /// An image descriptor, followed by its optional local color table and image data.
+    Image { local_color_table: bool },
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+/// A block found by [`parse_layout`], along with its location in the data.
+pub(crate) struct Block {
+    pub(crate) kind: BlockKind,
+    pub(crate) range: Range<usize>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+/// The block structure of a GIF.
+pub(crate) struct Layout {
+    pub(crate) width: u16,
+    pub(crate) height: u16,
+    pub(crate) global_palette_size: Option<usize>,
+    pub(crate) blocks: Vec<Block>,
+    /// The offset of the trailer byte.
+    pub(crate) trailer: usize,
+}
+
+/// A small cursor that never reads past the end of the data.
+struct Cursor<'a> {
+    bytes: &'a [u8],
+    offset: usize,
+}
+
+impl<'a> Cursor<'a> {
+    fn u8(&mut self) -> Result<u8, GifParseError> {
+        let byte = *self
+            .bytes
+            .get(self.offset)
+            .ok_or(GifParseError::UnexpectedEof {
+                offset: self.offset,
+            })?;
+        self.offset += 1;
+        Ok(byte)
+    }
+
+    fn u16(&mut self) -> Result<u16, GifParseError> {
+        Ok(u16::from_le_bytes([self.u8()?, self.u8()?]))
+    }
+
+    fn take(&mut self, n: usize) -> Result<&'a [u8], GifParseError> {
+        let end = self.offset.saturating_add(n);
+        let slice = self
+            .bytes
+            .get(self.offset..end)
+            .ok_or(GifParseError::UnexpectedEof { offset: end })?;
+        self.offset = end;
+        Ok(slice)
+    }
+
+    /// Skips a sequence of data sub-blocks, up to and including the block terminator.
+    fn skip_sub_blocks(&mut self) -> Result<(), GifParseError> {
+        loop {
+            let size = self.u8()? as usize;
+            if size == 0 {
+                return Ok(());
+            }
+            self.take(size)?;
+        }
+    }
+
+    /// Skips a color table, given the packed field that describes it.
+    fn skip_color_table(&mut self, packed: u8) -> Result<Option<usize>, GifParseError> {
+        if packed & 0x80 == 0 {
+            return Ok(None);
+        }
+        let size = 1usize << ((packed & 0x07) + 1);
+        self.take(3 * size)?;
+        Ok(Some(size))
+    }
+}
+
+/// Walks the block structure of the GIF contained in `bytes`, stopping at the trailer.
+pub(crate) fn parse_layout(bytes: &[u8]) -> Result<Layout, GifParseError> {
+    let mut cursor = Cursor { bytes, offset: 0 };
+    match cursor.take(6) {
+        Ok(b"GIF87a") | Ok(b"GIF89a") => {}
+        _ => return Err(GifParseError::InvalidSignature),
+    }
+    let width = cursor.u16()?;
+    let height = cursor.u16()?;
+    let packed = cursor.u8()?;
+    // Background color index and pixel aspect ratio.
+    cursor.take(2)?;
+    let global_palette_size = cursor.skip_color_table(packed)?;
+
+    let mut blocks = vec![];
+    loop {
+        let start = cursor.offset;
+        let kind = match cursor.u8()? {
+            TRAILER => {
+                return Ok(Layout {
+                    width,
+                    height,
+                    global_palette_size,
+                    blocks,
+                    trailer: start,
+                })
+            }
+            EXTENSION_INTRODUCER => match cursor.u8()? {
+                GRAPHIC_CONTROL_LABEL => {
+                    let size = cursor.u8()?;
+                    if size != 4 {
+                        return Err(GifParseError::UnknownBlock {
+                            offset: start + 2,
+                            byte: size,
+                        });
+                    }
+                    let _packed = cursor.u8()?;
+                    let delay = cursor.u16()?;
+                    // Transparent color index.
+                    cursor.u8()?;
+                    cursor.skip_sub_blocks()?;
+                    BlockKind::GraphicControl { delay }
+                }
+                APPLICATION_LABEL => {
+                    let size = cursor.u8()? as usize;
+                    let identifier = cursor.take(size)?;
+                    let mut loop_count = None;
+                    if identifier == b"NETSCAPE2.0" || identifier == b"ANIMEXTS1.0" {
+                        let sub_block_start = cursor.offset;
+                        if let (Ok(3), Ok(1)) = (cursor.u8(), cursor.u8()) {
+                            loop_count = Some(cursor.u16()?);
+                        } else {
+                            cursor.offset = sub_block_start;
+                        }
+                    }
+                    cursor.skip_sub_blocks()?;
+                    BlockKind::Application { loop_count }
+                }
+                _ => {
+                    cursor.skip_sub_blocks()?;
+                    BlockKind::OtherExtension
+                }
+            },
+            IMAGE_SEPARATOR => {
+                // Left, top, width and height.
+                cursor.take(8)?;
+                let packed = cursor.u8()?;
+                let local_color_table = cursor.skip_color_table(packed)?.is_some();
+                // LZW minimum code size.
+                cursor.u8()?;
+                cursor.skip_sub_blocks()?;
+                BlockKind::Image { local_color_table }
+            }
+            byte => {
+                return Err(GifParseError::UnknownBlock {
+                    offset: start,
+                    byte,
+                })
+            }
+        };
+        blocks.push(Block {
+            kind,
+            range: start..cursor.offset,
+        });
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_utils::sample_gif;
+
+    #[test]
+    fn test_parse_gif_info() {
+        let info = parse_gif_info(&sample_gif(3, Some(0))).unwrap();
+        assert_eq!(
+            info,
+            GifInfo {
+                width: 1,
+                height: 1,
+                frame_count: 3,
+                loop_count: Some(0),
+                global_palette_size: Some(2),
+                local_palette_count: 0,
+            }
+        );
+
+        let info = parse_gif_info(&sample_gif(1, None)).unwrap();
+        assert_eq!(info.frame_count, 1);
+        assert_eq!(info.loop_count, None);
+    }
+
+    #[test]
+    fn test_parse_gif_info_local_palettes() {
+        // NOTE: Giving the second of three frames its own (2 colors) color table.
+        let mut bytes = sample_gif(3, None);
+        let layout = parse_layout(&bytes).unwrap();
+        let image = layout
+            .blocks
+            .iter()
+            .filter(|b| matches!(b.kind, BlockKind::Image { .. }))
+            .nth(1)
+            .unwrap()
+            .range
+            .start;
+        bytes[image + 9] |= 0x80;
+        let table = [0x00, 0x00, 0x00, 0xff, 0xff, 0xff];
+        bytes.splice(image + 10..image + 10, table);
+        let info = parse_gif_info(&bytes).unwrap();
+        assert_eq!(info.frame_count, 3);
+        assert_eq!(info.local_palette_count, 1);
+    }
+
+    #[test]
+    fn test_parse_gif_info_trailing_garbage() {
+        let mut bytes = sample_gif(2, Some(3));
+        bytes.extend_from_slice(b"some trailing garbage \x00\x21\x2c");
+        let info = parse_gif_info(&bytes).unwrap();
+        assert_eq!(info.frame_count, 2);
+        assert_eq!(info.loop_count, Some(3));
+    }
+
+    #[test]
+    fn test_parse_gif_info_errors() {
+        assert_eq!(parse_gif_info(b""), Err(GifParseError::InvalidSignature));
+        assert_eq!(
+            parse_gif_info(b"PNG89a......"),
+            Err(GifParseError::InvalidSignature)
+        );
+
+        let bytes = sample_gif(2, Some(0));
+        let truncated = &bytes[..bytes.len() - 1];
+        assert_eq!(
+            parse_gif_info(truncated),
+            Err(GifParseError::UnexpectedEof {
+                offset: truncated.len()
+            })
+        );
+
+        let mut bytes = bytes.clone();
+        let trailer = bytes.len() - 1;
+        bytes[trailer] = 0x42;
+        assert_eq!(
+            parse_gif_info(&bytes),
+            Err(GifParseError::UnknownBlock {
+                offset: trailer,
+                byte: 0x42
+            })
+        );
+    }
+
+    #[test]
+    fn test_parse_layout_ranges() {
+        let bytes = sample_gif(2, Some(0));
+        let layout = parse_layout(&bytes).unwrap();
+        assert_eq!(layout.trailer, bytes.len() - 1);
+        // Application extension, then a graphic control extension and an image per frame.
+        assert_eq!(layout.blocks.len(), 5);
+        for pair in layout.blocks.windows(2) {
+            assert_eq!(pair[0].range.end, pair[1].range.start);
+        }
+        assert_eq!(layout.blocks.last().unwrap().range.end, layout.trailer);
+    }
+
+    /// The delays of the GIF's frames.
+    fn delays(bytes: &[u8]) -> Vec<u16> {
+        parse_layout(bytes)
+            .unwrap()
+            .blocks
+            .iter()
+            .filter_map(|b| match b.kind {
+                BlockKind::GraphicControl { delay } => Some(delay),
+                _ => None,
+            })
+            .collect()
+    }
+
+    #[test]
+    fn test_set_loop_count() {
+        let original = sample_gif(2, Some(0));
+        let mut gif = GifData::parse(original.clone()).unwrap();
+        gif.set_loop_count(LoopCount::Repeat(3));
+        assert_eq!(gif.info().loop_count, Some(3));
+        // Only the loop count's bytes changed.
+        let changed: Vec<_> = (0..original.len())
+            .filter(|i| original[*i] != gif.as_bytes()[*i])
+            .collect();
+        assert_eq!(changed.len(), 1);
+        assert_eq!(gif.as_bytes()[changed[0]], 3);
+
+        gif.set_loop_count(LoopCount::Once);
+        assert_eq!(gif.info().loop_count, None);
+        assert_eq!(gif.as_bytes(), sample_gif(2, None));
+
+        gif.set_loop_count(LoopCount::Forever);
+        assert_eq!(gif.as_bytes(), original);
+
+        let mut gif = GifData::parse(sample_gif(2, None)).unwrap();
+        gif.set_loop_count(LoopCount::Repeat(7));
+        assert_eq!(gif.into_bytes(), sample_gif(2, Some(7)));
+
+        let mut gif = GifData::parse(original.clone()).unwrap();
+        gif.set_loop_count(LoopCount::Repeat(0));
+        assert_eq!(gif.info().loop_count, None);
+    }
+
+    #[test]
+    fn test_scale_frame_delays() {
+        let original = sample_gif(3, Some(0));
+        let mut gif = GifData::parse(original.clone()).unwrap();
+        gif.scale_frame_delays(0.5).unwrap();
+        assert_eq!(delays(gif.as_bytes()), [5, 5, 5]);
+        gif.scale_frame_delays(4.0).unwrap();
+        assert_eq!(delays(gif.as_bytes()), [20, 20, 20]);
+        // A nonzero delay never becomes zero.
+        gif.scale_frame_delays(0.01).unwrap();
+        assert_eq!(delays(gif.as_bytes()), [1, 1, 1]);
+
+        // The structure is intact, and only the delays' bytes changed.
+        let info = gif.info();
+        assert_eq!(info, parse_gif_info(&original).unwrap());
+        let bytes = gif.into_bytes();
+        assert_eq!(bytes.len(), original.len());
+        assert_eq!(&bytes[..6], b"GIF89a");
+        assert_eq!(bytes.last(), Some(&TRAILER));
+        let layout = parse_layout(&original).unwrap();
+        for block in layout.blocks {
+            let range = block.range.clone();
+            match block.kind {
+                BlockKind::GraphicControl { .. } => {
+                    let (start, end) = (range.start, range.end);
+                    assert_eq!(bytes[start..start + 4], original[start..start + 4]);
+                    assert_eq!(bytes[start + 6..end], original[start + 6..end]);
+                }
+                _ => assert_eq!(bytes[range.clone()], original[range]),
+            }
+        }
+
+        let mut gif = GifData::parse(original).unwrap();
+        for factor in [0.0, -1.0, f32::NAN, f32::INFINITY] {
+            assert!(gif.scale_frame_delays(factor).is_err());
+        }
+    }
+
+    #[test]
+    fn test_set_frame_rate() {
+        let mut gif = GifData::parse(sample_gif(6, Some(0))).unwrap();
+        gif.set_frame_rate(15.0).unwrap();
+        assert_eq!(delays(gif.as_bytes()), [7, 7, 6, 7, 7, 6]);
+        gif.set_frame_rate(10.0).unwrap();
+        assert_eq!(delays(gif.as_bytes()), [10; 6]);
+        gif.set_frame_rate(100.0).unwrap();
+        assert_eq!(delays(gif.as_bytes()), [1; 6]);
+
+        // The total duration stays within a centisecond (i.e. less than a frame) of the
+        // exact one, however many frames there are.
+        for fps in [15.0, 24.0, 29.97, 30.0, 23.976, 7.0, 12.5, 60.0, 1.0, 0.3] {
+            for frames in [1, 2, 3, 10, 59, 250] {
+                let mut gif = GifData::parse(sample_gif(frames, Some(0))).unwrap();
+                gif.set_frame_rate(fps).unwrap();
+                let delays = delays(gif.as_bytes());
+                let total: f64 = delays.iter().map(|d| *d as f64).sum();
+                let exact = frames as f64 * 100.0 / fps;
+                assert!(
+                    total >= exact - 1e-6 && total < exact + 1.0,
+                    "{} frames at {} fps: {} cs instead of {} cs",
+                    frames,
+                    fps,
+                    total,
+                    exact
+                );
+                // NOTE: The delays are the exact one rounded either way.
+                let (min, max) = ((100.0 / fps).floor(), (100.0 / fps).ceil());
+                assert!(
+                    delays.iter().all(|d| (min..=max).contains(&(*d as f64))),
+                    "{} fps: {:?}",
+                    fps,
+                    delays
+                );
+            }
+        }
+
+        let original = sample_gif(3, Some(0));
+        let mut gif = GifData::parse(original.clone()).unwrap();
+        for fps in [0.0, -15.0, 100.5, f64::NAN, f64::INFINITY] {
+            assert!(gif.set_frame_rate(fps).is_err(), "{}", fps);
+        }
+        assert_eq!(gif.into_bytes(), original);
+    }
+
+    #[test]
+    fn test_gif_data_rejects_invalid_gifs() {
+        assert_eq!(
+            GifData::parse(b"PNG".to_vec()),
+            Err(GifParseError::InvalidSignature)
+        );
+        let mut bytes = sample_gif(2, Some(0));
+        bytes.pop();
+        assert!(GifData::parse(bytes).is_err());
+    }
+
+    /// A small xorshift generator, so that the "fuzz" tests below are deterministic.
+    fn xorshift(state: &mut u64) -> u64 {
+        *state ^= *state << 13;
+        *state ^= *state >> 7;
+        *state ^= *state << 17;
+        *state
+    }
+
+    #[test]
+    fn test_parse_gif_info_never_panics_on_truncated_input() {
+        let bytes = sample_gif(4, Some(0));
+        for n in 0..bytes.len() {
+            assert!(parse_gif_info(&bytes[..n]).is_err());
+        }
+    }
+
+    #[test]
+    fn test_parse_gif_info_never_panics_on_mutated_input() {
+        let bytes = sample_gif(4, Some(0));
+        let mut state = 0x2545_f491_4f6c_dd1d;
+        for _ in 0..20_000 {
+            let mut mutated = bytes.clone();
+            let mutations = 1 + xorshift(&mut state) % 4;
+            for _ in 0..mutations {
+                let i = (xorshift(&mut state) as usize) % mutated.len();
+                mutated[i] = xorshift(&mut state) as u8;
+            }
+            let _ = parse_gif_info(&mutated);
+        }
+    }
+
+    #[test]
+    fn test_parse_gif_info_never_panics_on_random_input() {
+        let mut state = 0x9e37_79b9_7f4a_7c15;
+        for _ in 0..20_000 {
+            let len = (xorshift(&mut state) % 64) as usize;
+            let mut bytes = b"GIF89a".to_vec();
+            bytes.extend((0..len).map(|_| xorshift(&mut state) as u8));
+            let _ = parse_gif_info(&bytes);
+        }
+    }
+}
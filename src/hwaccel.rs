@@ -0,0 +1,192 @@
+//! The hardware accelerated decoding of the video (see [`crate::Settings::hwaccel`]), which
+//! makes FFmpeg decode the frames on the GPU (e.g. for 4K screen recordings, whose decoding
+//! is otherwise the bottleneck of the conversion), using its `-hwaccel` input option.
+//!
+//! [`HwAccel::Auto`] picks the first method listed by `ffmpeg -hwaccels` that is one of the
+//! [`HwAccel`] variants, the list being probed once per binary path. A listed method may still
+//! fail to initialize (e.g. `cuda` on a machine without an NVIDIA GPU), in which case FFmpeg
+//! either falls back to software decoding or fails, which the [`Converter`](crate::Converter)
+//! detects from the lines that FFmpeg writes to `stderr` (e.g. `hwaccel initialisation
+//! returned error`), and handles as requested (see [`HwAccelFallback`]).
+
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+use std::sync::{Mutex, OnceLock};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
+/// The hardware acceleration method used to decode the video (see [`crate::Settings::hwaccel`]).
+pub enum HwAccel {
+    /// The first of the other methods listed by `ffmpeg -hwaccels` (in FFmpeg's order), or no
+    /// acceleration if none is listed. If the list cannot be probed, FFmpeg picks the method
+    /// by itself (i.e. `-hwaccel auto`).
+    Auto,
+    /// NVIDIA's CUDA (NVDEC).
+    Cuda,
+    /// Intel's Quick Sync Video.
+    Qsv,
+    /// The Video Acceleration API (on Linux).
+    Vaapi,
+    /// Apple's VideoToolbox (on macOS).
+    VideoToolbox,
+    /// Direct3D 11 (on Windows).
+    D3d11va,
+}
+
+impl HwAccel {
+    /// The methods that [`HwAccel::Auto`] picks from.
+    const METHODS: [Self; 5] = [
+        Self::Cuda,
+        Self::Qsv,
+        Self::Vaapi,
+        Self::VideoToolbox,
+        Self::D3d11va,
+    ];
+
+    /// The value of FFmpeg's `-hwaccel` input option.
+    pub(crate) fn name(&self) -> &'static str {
+        match self {
+            Self::Auto => "auto",
+            Self::Cuda => "cuda",
+            Self::Qsv => "qsv",
+            Self::Vaapi => "vaapi",
+            Self::VideoToolbox => "videotoolbox",
+            Self::D3d11va => "d3d11va",
+        }
+    }
+
+    /// The input options passed to FFmpeg (before the input's `-i` flag).
+    pub(crate) fn args(&self) -> [String; 2] {
+        ["-hwaccel".into(), self.name().into()]
+    }
+}
+
+impl std::fmt::Display for HwAccel {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", self.name())
+    }
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
+/// What happens when FFmpeg reports that the hardware acceleration method provided using
+/// [`crate::Settings::hwaccel`] could not be set up (e.g. `hwaccel initialisation returned
+/// error`), see [`crate::Settings::hwaccel_fallback`].
+pub enum HwAccelFallback {
+    /// The job is run again without acceleration, after a
+    /// [`crate::Warning::HardwareAccelerationFailed`] warning.
+    #[default]
+    Software,
+    /// The job fails with [`crate::Error::HardwareAcceleration`].
+    Fail,
+}
+
+/// The arguments that make FFmpeg list its hardware acceleration methods.
+pub(crate) const HWACCELS_ARGS: [&str; 2] = ["-hide_banner", "-hwaccels"];
+
+/// Parses the output of `ffmpeg -hwaccels`, whose lines list a method each (e.g. `cuda`)
+/// after a `Hardware acceleration methods:` line, returning `None` if it is not recognized.
+fn parse_hwaccels(output: &str) -> Option<Vec<&str>> {
+    let mut lines = output.lines();
+    lines.find(|line| line.trim() == "Hardware acceleration methods:")?;
+    Some(
+        lines
+            .map(str::trim)
+            .filter(|line| !line.is_empty())
+            .collect(),
+    )
+}
+
+/// The method picked by [`HwAccel::Auto`] given the output of `ffmpeg -hwaccels` (see
+/// [`parse_hwaccels`]), i.e. the first one that is known, `None` if there is none, or
+/// [`HwAccel::Auto`] (i.e. FFmpeg's own pick) if the output is not recognized.
+pub(crate) fn pick(output: &str) -> Option<HwAccel> {
+    let Some(methods) = parse_hwaccels(output) else {
+        return Some(HwAccel::Auto);
+    };
+    methods
+        .into_iter()
+        .find_map(|name| HwAccel::METHODS.into_iter().find(|m| m.name() == name))
+}
+
+/// The methods picked so far by [`HwAccel::Auto`], per binary path.
+static CACHE: OnceLock<Mutex<HashMap<PathBuf, Option<HwAccel>>>> = OnceLock::new();
+
+/// The method picked by [`HwAccel::Auto`] for the binary at `binary_path`, whose methods are
+/// listed using `probe` (which runs FFmpeg with the given arguments and returns its `stdout`)
+/// unless they were listed before. An error returned by `probe` is not cached, so that the
+/// next job probes again.
+pub(crate) fn cached<E>(
+    binary_path: &Path,
+    probe: impl FnOnce(&[&str]) -> Result<String, E>,
+) -> Result<Option<HwAccel>, E> {
+    let cache = CACHE.get_or_init(Default::default);
+    if let Some(hwaccel) = cache
+        .lock()
+        .unwrap_or_else(|e| e.into_inner())
+        .get(binary_path)
+    {
+        return Ok(*hwaccel);
+    }
+    let hwaccel = pick(&probe(&HWACCELS_ARGS)?);
+    cache
+        .lock()
+        .unwrap_or_else(|e| e.into_inner())
+        .insert(binary_path.to_path_buf(), hwaccel);
+    Ok(hwaccel)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const HWACCELS_LINUX: &str =
+        "Hardware acceleration methods:\nvdpau\ncuda\nvaapi\nqsv\ndrm\nopencl\nvulkan\n\n";
+    const HWACCELS_MACOS: &str = "Hardware acceleration methods:\nvideotoolbox\n\n";
+
+    #[test]
+    fn test_args() {
+        assert_eq!(HwAccel::Cuda.args(), ["-hwaccel", "cuda"]);
+        assert_eq!(HwAccel::VideoToolbox.args(), ["-hwaccel", "videotoolbox"]);
+        assert_eq!(HwAccel::Auto.to_string(), "auto");
+    }
+
+    #[test]
+    fn test_pick() {
+        assert_eq!(pick(HWACCELS_LINUX), Some(HwAccel::Cuda));
+        assert_eq!(pick(HWACCELS_MACOS), Some(HwAccel::VideoToolbox));
+        // The methods that are not known (e.g. `vdpau`) are skipped.
+        assert_eq!(
+            pick("Hardware acceleration methods:\nvdpau\r\nvaapi\r\n"),
+            Some(HwAccel::Vaapi)
+        );
+        assert_eq!(
+            pick("Hardware acceleration methods:\nvdpau\nvulkan\n"),
+            None
+        );
+        assert_eq!(pick("Hardware acceleration methods:\n"), None);
+        // NOTE: FFmpeg picks the method by itself if its output is not recognized.
+        assert_eq!(pick("Unrecognized option 'hwaccels'."), Some(HwAccel::Auto));
+    }
+
+    #[test]
+    fn test_cached() {
+        let path = Path::new("/hwaccel/test_cached/ffmpeg");
+        let mut probes = 0;
+        let mut probe = |args: &[&str]| -> Result<String, ()> {
+            assert_eq!(args, HWACCELS_ARGS);
+            probes += 1;
+            Ok(HWACCELS_MACOS.into())
+        };
+        assert_eq!(cached(path, &mut probe), Ok(Some(HwAccel::VideoToolbox)));
+        assert_eq!(cached(path, &mut probe), Ok(Some(HwAccel::VideoToolbox)));
+        assert_eq!(probes, 1);
+        // An error is not cached.
+        let other = Path::new("/hwaccel/test_cached/other");
+        assert_eq!(cached(other, |_| Err(())), Err(()));
+        assert_eq!(
+            cached(other, |_| Ok::<_, ()>(HWACCELS_LINUX.into())),
+            Ok(Some(HwAccel::Cuda))
+        );
+    }
+}
@@ -0,0 +1,761 @@
+use std::sync::{Arc, Mutex};
+
+use crate::RemoteUrl;
+
+/// The size of the chunks in which the input is written to the child process' `stdin`
+/// (see [`pump`]).
+const WRITE_CHUNK_LEN: usize = 64 * 1024;
+
+#[derive(Clone)]
+/// The source of the video to be converted into an animated GIF (see [`crate::Settings::with_input`]).
+pub enum InputSource {
+    /// The path of the video file.
+    Path(String),
+    /// An already opened video file, which is passed to the FFmpeg child process
+    /// as an inherited file descriptor (referenced as `/dev/fd/N`) on unix systems,
+    /// or as an inherited handle, which becomes the child process' `stdin` (referenced
+    /// as `pipe:0`), on Windows, instead of by path. This is useful for sandboxed
+    /// applications, which can be given an open file without being able to pass its
+    /// path to a child process, and it also avoids races between checking a path and
+    /// using it.
+    ///
+    /// NOTE: On Windows, just like with [`InputSource::Bytes`], a cancelled job kills the
+    /// child process, and the formats that require seeking fail to be read (since FFmpeg
+    /// does not seek within `pipe:0`). On other systems (neither unix nor Windows),
+    /// [`crate::Settings::validate`] returns [`crate::SettingsError::UnsupportedInput`].
+    /// The file is kept open (and is shared between clones of the settings) for
+    /// as long as the [`crate::Settings`] are alive, and its position is reset to
+    /// the start before each FFmpeg child process is spawned.
+    File(Arc<std::fs::File>),
+    /// The video's bytes, already in memory (e.g. an upload received by a server), which
+    /// are streamed to the FFmpeg child process' `stdin` (referenced as `pipe:0`) by a
+    /// dedicated thread, instead of being written to a temporary file first.
+    ///
+    /// NOTE: Since the child process' `stdin` then carries the video, a cancelled job (see
+    /// [`crate::Command::Cancel`]) kills the child process, instead of asking it to stop by
+    /// writing `q` to its `stdin`. FFmpeg cannot seek within a pipe, so the formats that
+    /// require seeking (e.g. an MP4 file whose `moov` atom comes after its media data, i.e.
+    /// one that was not written with `-movflags +faststart`) fail to be read. The bytes are
+    /// shared between clones of the settings, and are streamed again to each child process
+    /// that reads the video (e.g. to probe its duration).
+    Bytes(Arc<Vec<u8>>),
+    /// A reader of the video (e.g. a network body, or a decrypting reader), which is
+    /// copied to the FFmpeg child process' `stdin` (referenced as `pipe:0`) in chunks by a
+    /// dedicated thread, without the whole video being buffered first (see
+    /// [`InputSource::from_reader`]). An error returned by the reader fails the job with
+    /// [`crate::Error::InputRead`].
+    ///
+    /// NOTE: Just like with [`InputSource::Bytes`], a cancelled job kills the child process,
+    /// and the formats that require seeking fail to be read. Since the reader can only be
+    /// read once (i.e. it is shared between clones of the settings, and taken by the first
+    /// conversion job), the options that read the video more than once cannot be used (see
+    /// [`crate::SettingsConflict::ReadOnceInput`]), and the video's duration is usually
+    /// unknown, in which case the progress is reported using [`crate::Message::FramesProcessed`].
+    #[allow(clippy::type_complexity)]
+    Reader(Arc<Mutex<Option<Box<dyn std::io::Read + Send>>>>),
+    /// The URL of a remote video (e.g. `https://example.com/video.mp4`), which FFmpeg reads
+    /// directly over the network, using only the protocols allowed by [`RemoteUrl`] (see
+    /// [`crate::Settings::from_url`]). A URL whose scheme is not allowed makes
+    /// [`crate::Settings::validate`] fail with [`crate::SettingsError::InvalidUrl`].
+    ///
+    /// NOTE: The video is fetched again by each child process that reads it (e.g. to probe
+    /// its duration), and its duration may be slow to find, or unknown (e.g. for a live
+    /// stream), in which case the progress is reported using [`crate::Message::FramesProcessed`].
+    /// Since the video cannot be fingerprinted, the animated GIF is never cached (see
+    /// [`crate::Converter::cache`]).
+    Url(RemoteUrl),
+}
+
+impl std::fmt::Debug for InputSource {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::Path(path) => f.debug_tuple("Path").field(path).finish(),
+            Self::File(file) => f.debug_tuple("File").field(file).finish(),
+            // NOTE: The bytes themselves would flood the log lines.
+            Self::Bytes(bytes) => write!(f, "Bytes({} bytes)", bytes.len()),
+            Self::Reader(_) => write!(f, "Reader"),
+            Self::Url(url) => f.debug_tuple("Url").field(&url.as_str()).finish(),
+        }
+    }
+}
+
+impl From<String> for InputSource {
+    fn from(path: String) -> Self {
+        Self::Path(path)
+    }
+}
+
+impl From<&str> for InputSource {
+    fn from(path: &str) -> Self {
+        Self::Path(path.into())
+    }
+}
+
+impl From<std::fs::File> for InputSource {
+    fn from(file: std::fs::File) -> Self {
+        Self::File(Arc::new(file))
+    }
+}
+
+impl From<Vec<u8>> for InputSource {
+    fn from(bytes: Vec<u8>) -> Self {
+        Self::Bytes(Arc::new(bytes))
+    }
+}
+
+impl From<RemoteUrl> for InputSource {
+    fn from(url: RemoteUrl) -> Self {
+        Self::Url(url)
+    }
+}
+
+impl InputSource {
+    /// Creates an [`InputSource::Reader`] that reads the video from `reader`.
+    pub fn from_reader(reader: impl std::io::Read + Send + 'static) -> Self {
+        Self::Reader(Arc::new(Mutex::new(Some(Box::new(reader)))))
+    }
+
+    /// Whether the input source is supported on the current system.
+    pub(crate) fn is_supported(&self) -> bool {
+        match self {
+            Self::Path(_) | Self::Bytes(_) | Self::Reader(_) | Self::Url(_) => true,
+            Self::File(_) => cfg!(any(unix, windows)),
+        }
+    }
+
+    /// Whether the input is the current process' `stdin` (e.g. `/dev/stdin`), which
+    /// is then also the FFmpeg child process' `stdin` (see [`crate::SettingsConflict::StdinInput`]).
+    pub(crate) fn reads_stdin(&self) -> bool {
+        self.is_supported()
+            && matches!(
+                self.arg().as_str(),
+                "/dev/stdin" | "/dev/fd/0" | "/proc/self/fd/0"
+            )
+    }
+
+    /// The size (in bytes) of the input, if it can be found.
+    pub(crate) fn size(&self) -> Option<u64> {
+        let metadata = match self {
+            Self::Path(path) => std::fs::metadata(path),
+            Self::File(file) => file.metadata(),
+            Self::Bytes(bytes) => return Some(bytes.len() as u64),
+            Self::Reader(_) | Self::Url(_) => return None,
+        };
+        metadata.ok().filter(|m| m.is_file()).map(|m| m.len())
+    }
+
+    /// The value passed to FFmpeg's `-i` flag.
+    pub(crate) fn arg(&self) -> String {
+        match self {
+            Self::Path(path) => crate::argv::path_arg(path),
+            #[cfg(unix)]
+            Self::File(file) => {
+                use std::os::fd::AsRawFd;
+                format!("/dev/fd/{}", file.as_raw_fd())
+            }
+            #[cfg(windows)]
+            Self::File(_) => "pipe:0".into(),
+            #[cfg(not(any(unix, windows)))]
+            Self::File(_) => {
+                unreachable!("File input sources are only supported on unix and Windows")
+            }
+            Self::Bytes(_) | Self::Reader(_) => "pipe:0".into(),
+            Self::Url(url) => url.as_str().into(),
+        }
+    }
+
+    /// Whether the child process reads the input from its `stdin` (see [`InputSource::Bytes`]
+    /// and [`InputSource::Reader`]), which then cannot be used to ask it to stop.
+    pub(crate) fn pipes_stdin(&self) -> bool {
+        matches!(self, Self::Bytes(_) | Self::Reader(_)) || self.is_child_stdin()
+    }
+
+    /// Whether the input file itself is the child process' `stdin` (see [`InputSource::File`],
+    /// on Windows), which then is not a pipe.
+    pub(crate) fn is_child_stdin(&self) -> bool {
+        cfg!(windows) && matches!(self, Self::File(_))
+    }
+
+    /// Whether the input can only be read once (see [`InputSource::Reader`]).
+    pub(crate) fn is_read_once(&self) -> bool {
+        matches!(self, Self::Reader(_))
+    }
+
+    /// The bytes to be written to the child process' `stdin`, if the input is
+    /// [`InputSource::Bytes`].
+    pub(crate) fn bytes(&self) -> Option<&Arc<Vec<u8>>> {
+        match self {
+            Self::Bytes(bytes) => Some(bytes),
+            _ => None,
+        }
+    }
+
+    /// What is to be copied to the child process' `stdin` (see [`pump`]), if the input is
+    /// [`InputSource::Bytes`] or [`InputSource::Reader`], whose reader is taken (so that
+    /// `None` is returned the next time).
+    pub(crate) fn take_stdin_data(&self) -> Option<Box<dyn std::io::Read + Send>> {
+        match self {
+            Self::Bytes(bytes) => Some(Box::new(std::io::Cursor::new(SharedBytes(Arc::clone(
+                bytes,
+            ))))),
+            Self::Reader(reader) => reader.lock().unwrap_or_else(|e| e.into_inner()).take(),
+            _ => None,
+        }
+    }
+
+    /// Sets up `command` so that the child process can read the input source,
+    /// which is required for [`InputSource::File`], whose file descriptor (or handle)
+    /// must be inherited by the child process, and for [`InputSource::Bytes`] and
+    /// [`InputSource::Reader`], which are written to its `stdin` (see [`pump`]).
+    ///
+    /// NOTE: `command`'s `stdin` must not be set up after this call.
+    pub(crate) fn prepare(&self, command: &mut std::process::Command) -> std::io::Result<()> {
+        match self {
+            Self::Path(_) | Self::Url(_) => Ok(()),
+            Self::Bytes(_) => {
+                command.stdin(std::process::Stdio::piped());
+                Ok(())
+            }
+            Self::Reader(reader) => {
+                if reader.lock().unwrap_or_else(|e| e.into_inner()).is_none() {
+                    return Err(std::io::Error::other("the input reader was already read"));
+                }
+                command.stdin(std::process::Stdio::piped());
+                Ok(())
+            }
+            #[cfg(unix)]
+            Self::File(file) => {
+                use std::io::Seek;
+                use std::os::fd::AsRawFd;
+                use std::os::unix::process::CommandExt;
+
+                // NOTE: Depending on the system, `/dev/fd/N` either reopens the file
+                // or duplicates the file descriptor (in which case the position is
+                // shared with any previous child process, such as an analysis pass).
+                (&**file).seek(std::io::SeekFrom::Start(0))?;
+                let fd = file.as_raw_fd();
+                // SAFETY: The closure only calls `fcntl`, which is async-signal-safe,
+                // and does not allocate.
+                unsafe {
+                    command.pre_exec(move || {
+                        // NOTE: Rust opens files with `FD_CLOEXEC`, which needs to be
+                        // cleared (in the child process only) for the file descriptor
+                        // to be inherited.
+                        let flags = libc::fcntl(fd, libc::F_GETFD);
+                        if flags < 0
+                            || libc::fcntl(fd, libc::F_SETFD, flags & !libc::FD_CLOEXEC) < 0
+                        {
+                            return Err(std::io::Error::last_os_error());
+                        }
+                        Ok(())
+                    });
+                }
+                Ok(())
+            }
+            #[cfg(windows)]
+            Self::File(file) => {
+                use std::io::Seek;
+
+                // NOTE: The duplicated handle shares the file's position with the original
+                // one (and with any previous child process, such as an analysis pass), and
+                // is made inheritable by the standard library when the child is spawned.
+                (&**file).seek(std::io::SeekFrom::Start(0))?;
+                command.stdin(std::process::Stdio::from(file.try_clone()?));
+                Ok(())
+            }
+            #[cfg(not(any(unix, windows)))]
+            Self::File(_) => Err(std::io::Error::new(
+                std::io::ErrorKind::Unsupported,
+                "File input sources are only supported on unix and Windows",
+            )),
+        }
+    }
+}
+
+/// The bytes of an [`InputSource::Bytes`], which can be read (using a [`std::io::Cursor`])
+/// without being copied.
+struct SharedBytes(Arc<Vec<u8>>);
+
+impl AsRef<[u8]> for SharedBytes {
+    fn as_ref(&self) -> &[u8] {
+        &self.0
+    }
+}
+
+/// Why [`pump`] failed.
+#[derive(Debug)]
+pub(crate) enum PumpError {
+    /// The input could not be read (see [`crate::Error::InputRead`]).
+    Read(std::io::Error),
+    /// The child process' `stdin` could not be written to.
+    Write(std::io::Error),
+}
+
+/// Copies `input` (see [`InputSource::take_stdin_data`]) to the child process' `stdin` in
+/// chunks, until it has all been written or `stop` returns `true`, and then closes it, so
+/// that FFmpeg reaches the end of its input. Returns the number of bytes written, a
+/// child process that exits without reading all of them (e.g. once the clip has been
+/// read, or when killed) not being an error.
+pub(crate) fn pump(
+    mut stdin: std::process::ChildStdin,
+    mut input: impl std::io::Read,
+    mut stop: impl FnMut() -> bool,
+) -> Result<usize, PumpError> {
+    use std::io::Write;
+    let mut buf = vec![0u8; WRITE_CHUNK_LEN];
+    let mut written = 0;
+    while !stop() {
+        let n = match input.read(&mut buf) {
+            Ok(0) => break,
+            Ok(n) => n,
+            Err(e) if e.kind() == std::io::ErrorKind::Interrupted => continue,
+            Err(e) => return Err(PumpError::Read(e)),
+        };
+        match stdin.write_all(&buf[..n]) {
+            Ok(()) => written += n,
+            Err(e) if e.kind() == std::io::ErrorKind::BrokenPipe => break,
+            Err(e) => return Err(PumpError::Write(e)),
+        }
+    }
+    Ok(written)
+}
+
+#[cfg(all(test, unix))]
+mod tests {
+    use super::*;
+    use crate::test_utils::{
+        ffmpeg_available, final_summary, init_logging, run_to_completion, run_to_completion_with,
+        sample_gif, send_command, success_bytes, temp_dir, write_script, SAMPLE_STDERR,
+        SAMPLE_VIDEO_PATH,
+    };
+    use crate::{Command, Error, Message, Settings, SettingsError, UrlError};
+    use std::time::Duration;
+
+    #[test]
+    fn test_arg() {
+        assert_eq!(InputSource::from("-i.mp4").arg(), "./-i.mp4");
+        let file = std::fs::File::open(crate::test_utils::SAMPLE_VIDEO_PATH).unwrap();
+        let fd = std::os::fd::AsRawFd::as_raw_fd(&file);
+        assert_eq!(InputSource::from(file).arg(), format!("/dev/fd/{}", fd));
+        let bytes = InputSource::from(vec![0; 16]);
+        assert_eq!(bytes.arg(), "pipe:0");
+        assert_eq!(bytes.size(), Some(16));
+        assert_eq!(format!("{:?}", bytes), "Bytes(16 bytes)");
+        let reader = InputSource::from_reader(std::io::empty());
+        assert_eq!(reader.arg(), "pipe:0");
+        assert_eq!(reader.size(), None);
+        let url = InputSource::from(RemoteUrl::new("https://example.com/-video.mp4"));
+        assert_eq!(url.arg(), "https://example.com/-video.mp4");
+        assert_eq!(url.size(), None);
+        assert_eq!(
+            format!("{:?}", url),
+            r#"Url("https://example.com/-video.mp4")"#
+        );
+    }
+
+    #[test]
+    fn test_size() {
+        let expected = std::fs::metadata(SAMPLE_VIDEO_PATH).unwrap().len();
+        assert_eq!(InputSource::from(SAMPLE_VIDEO_PATH).size(), Some(expected));
+        let file = std::fs::File::open(SAMPLE_VIDEO_PATH).unwrap();
+        assert_eq!(InputSource::from(file).size(), Some(expected));
+        assert_eq!(InputSource::from("no-such-file.mp4").size(), None);
+    }
+
+    #[test]
+    fn test_prepare_inherits_file_descriptor() {
+        let path = temp_dir().join("input.txt");
+        std::fs::write(&path, "some content").unwrap();
+        let input = InputSource::from(std::fs::File::open(&path).unwrap());
+        // The file's path is not needed anymore.
+        std::fs::remove_file(&path).unwrap();
+
+        for _ in 0..2 {
+            let mut command = std::process::Command::new("cat");
+            command.arg(input.arg());
+            input.prepare(&mut command).unwrap();
+            let output = command.output().unwrap();
+            assert!(output.status.success());
+            assert_eq!(output.stdout, b"some content");
+        }
+
+        // Without the setup, the file descriptor is not inherited.
+        let output = std::process::Command::new("cat")
+            .arg(input.arg())
+            .stderr(std::process::Stdio::null())
+            .output()
+            .unwrap();
+        assert!(!output.status.success());
+    }
+
+    #[test]
+    fn test_convert_file_input_scripted() {
+        init_logging();
+
+        let dir = temp_dir();
+        let gif = sample_gif(2, Some(0));
+        std::fs::write(dir.join("expected.bin"), "video data").unwrap();
+        std::fs::write(dir.join("stderr.txt"), SAMPLE_STDERR).unwrap();
+        std::fs::write(dir.join("stdout.bin"), &gif).unwrap();
+        // A fake FFmpeg binary that only succeeds if its input has the expected content.
+        let path = write_script(
+            &dir,
+            &format!(
+                r#"args="$*"
+while [ $# -gt 0 ]; do
+  if [ "$1" = "-i" ]; then input="$2"; fi
+  shift
+done
+cmp -s "$input" '{dir}/expected.bin' || exit 3
+case "$args" in
+  *rawvideo*) exit 0;;
+esac
+cat '{dir}/stderr.txt' >&2
+cat '{dir}/stdout.bin'"#,
+                dir = dir.display()
+            ),
+        );
+
+        let video_path = temp_dir().join("video.mp4");
+        std::fs::write(&video_path, "video data").unwrap();
+        let file = std::fs::File::open(&video_path).unwrap();
+        std::fs::remove_file(&video_path).unwrap();
+
+        // NOTE: The analysis pass makes sure that the file can be read several times.
+        let settings = Settings::with_input(file, 200)
+            .ffmpeg_path(path.to_string_lossy())
+            .auto_colors(true);
+        let messages = run_to_completion(settings);
+        assert_eq!(success_bytes(&messages), Some(&gif[..]));
+        assert!(messages
+            .iter()
+            .any(|m| matches!(m, Message::ColorCountSelected(_))));
+        assert!(final_summary(&messages).error.is_none());
+    }
+
+    #[test]
+    fn test_convert_file_input_end_to_end() {
+        init_logging();
+        if !ffmpeg_available() {
+            return;
+        }
+
+        let file = std::fs::File::open(SAMPLE_VIDEO_PATH).unwrap();
+        let messages = run_to_completion(Settings::with_input(file, 100));
+        let bytes = success_bytes(&messages).expect("Expected a 'Success' message");
+        assert!(crate::gif_info::parse_gif_info(bytes).is_ok());
+    }
+
+    /// Some video data larger than a pipe's buffer (i.e. 64 KiB on Linux), so that writing
+    /// it blocks until the child process reads it.
+    fn video_data() -> Vec<u8> {
+        (0..1024 * 1024).map(|i| (i % 251) as u8).collect()
+    }
+
+    #[test]
+    fn test_convert_bytes_input_scripted() {
+        init_logging();
+
+        let dir = temp_dir();
+        let gif = sample_gif(2, Some(0));
+        std::fs::write(dir.join("expected.bin"), video_data()).unwrap();
+        std::fs::write(dir.join("stderr.txt"), SAMPLE_STDERR).unwrap();
+        std::fs::write(dir.join("stdout.bin"), &gif).unwrap();
+        // A fake FFmpeg binary that only succeeds if it reads the expected content from
+        // `stdin`, after writing to `stderr` (which must not block the writer).
+        let path = write_script(
+            &dir,
+            &format!(
+                r#"case "$*" in
+  *"-i pipe:0"*) ;;
+  *) exit 4;;
+esac
+case "$*" in
+  *rawvideo*) ;;
+  *) cat '{dir}/stderr.txt' >&2;;
+esac
+input="{dir}/input-$$.bin"
+cat > "$input"
+cmp -s "$input" '{dir}/expected.bin' || exit 3
+case "$*" in
+  *rawvideo*) exit 0;;
+esac
+cat '{dir}/stdout.bin'"#,
+                dir = dir.display()
+            ),
+        );
+
+        // NOTE: The analysis pass makes sure that the bytes can be read several times.
+        let settings = Settings::from_bytes(video_data(), 200)
+            .ffmpeg_path(path.to_string_lossy())
+            .auto_colors(true);
+        let messages = run_to_completion(settings);
+        assert_eq!(success_bytes(&messages), Some(&gif[..]));
+        assert!(messages
+            .iter()
+            .any(|m| matches!(m, Message::ColorCountSelected(_))));
+        assert!(final_summary(&messages).error.is_none());
+    }
+
+    #[test]
+    fn test_convert_bytes_input_cancelled() {
+        init_logging();
+
+        let dir = temp_dir();
+        std::fs::write(dir.join("stderr.txt"), SAMPLE_STDERR).unwrap();
+        // A fake FFmpeg binary that never reads its input, so that the writer blocks.
+        let path = write_script(
+            &dir,
+            &format!(
+                "cat '{dir}/stderr.txt' >&2\nexec sleep 30",
+                dir = dir.display()
+            ),
+        );
+
+        let settings = Settings::from_bytes(video_data(), 200).ffmpeg_path(path.to_string_lossy());
+        let started = std::time::Instant::now();
+        let messages = run_to_completion_with(settings, |tx| {
+            std::thread::sleep(Duration::from_millis(500));
+            assert!(send_command(&tx, Command::Cancel));
+        });
+        assert!(started.elapsed() < Duration::from_secs(10));
+        assert!(messages
+            .iter()
+            .any(|m| matches!(m, Message::Error(Error::Cancelled { .. }))));
+        assert_eq!(
+            final_summary(&messages).outcome(),
+            crate::Outcome::Cancelled
+        );
+    }
+
+    /// The sample video, remuxed (using FFmpeg) into a format that can be read from a pipe,
+    /// since the sample's `moov` atom comes after its media data.
+    fn streamable_sample() -> std::path::PathBuf {
+        let path = temp_dir().join("sample.mkv");
+        let status = std::process::Command::new("ffmpeg")
+            .args([
+                "-nostdin",
+                "-loglevel",
+                "error",
+                "-y",
+                "-i",
+                SAMPLE_VIDEO_PATH,
+            ])
+            .args(["-c", "copy"])
+            .arg(&path)
+            .status()
+            .unwrap();
+        assert!(status.success());
+        path
+    }
+
+    #[test]
+    fn test_convert_bytes_input_end_to_end() {
+        init_logging();
+        if !ffmpeg_available() {
+            return;
+        }
+
+        let bytes = std::fs::read(streamable_sample()).unwrap();
+        let messages = run_to_completion(Settings::from_bytes(bytes, 100));
+        let bytes = success_bytes(&messages).expect("Expected a 'Success' message");
+        assert!(crate::gif_info::parse_gif_info(bytes).is_ok());
+    }
+
+    /// Same as [`SAMPLE_STDERR`], but as written when reading a video whose duration is
+    /// unknown (e.g. from a pipe).
+    fn stderr_without_duration() -> String {
+        SAMPLE_STDERR.replace(
+            "Duration: 00:00:05.00, start: 0.000000, bitrate: 1785 kb/s",
+            "Duration: N/A, start: 0.000000, bitrate: N/A",
+        )
+    }
+
+    #[test]
+    fn test_convert_reader_input_scripted() {
+        init_logging();
+
+        let dir = temp_dir();
+        let gif = sample_gif(2, Some(0));
+        std::fs::write(dir.join("expected.bin"), video_data()).unwrap();
+        std::fs::write(dir.join("stderr.txt"), stderr_without_duration()).unwrap();
+        std::fs::write(dir.join("stdout.bin"), &gif).unwrap();
+        // A fake FFmpeg binary that only succeeds if it reads the expected content from `stdin`.
+        let path = write_script(
+            &dir,
+            &format!(
+                r#"case "$*" in
+  *"-i pipe:0"*) ;;
+  *) exit 4;;
+esac
+cat > '{dir}/input.bin'
+cmp -s '{dir}/input.bin' '{dir}/expected.bin' || exit 3
+cat '{dir}/stderr.txt' >&2
+cat '{dir}/stdout.bin'"#,
+                dir = dir.display()
+            ),
+        );
+
+        let video_path = dir.join("video.bin");
+        std::fs::write(&video_path, video_data()).unwrap();
+        let reader = std::io::BufReader::new(std::fs::File::open(&video_path).unwrap());
+        let settings = Settings::from_reader(reader, 200).ffmpeg_path(path.to_string_lossy());
+        let messages = run_to_completion(settings.clone());
+        assert_eq!(success_bytes(&messages), Some(&gif[..]));
+        // NOTE: Without a duration, the progress is reported as a number of frames.
+        let frames: Vec<_> = messages
+            .iter()
+            .filter_map(|m| match m {
+                Message::FramesProcessed(n) => Some(*n),
+                _ => None,
+            })
+            .collect();
+        assert_eq!(frames, [20, 50]);
+        assert!(!messages.iter().any(|m| matches!(m, Message::Progress(_))));
+
+        // NOTE: The reader was taken by the first job.
+        let messages = run_to_completion(settings);
+        assert!(final_summary(&messages).error.is_some());
+    }
+
+    /// A reader that returns an error after `ok` chunks of video data.
+    struct FailingReader {
+        ok: usize,
+    }
+
+    impl std::io::Read for FailingReader {
+        fn read(&mut self, buf: &mut [u8]) -> std::io::Result<usize> {
+            if self.ok == 0 {
+                return Err(std::io::Error::other("connection reset"));
+            }
+            self.ok -= 1;
+            buf.fill(0);
+            Ok(buf.len())
+        }
+    }
+
+    #[test]
+    fn test_convert_reader_input_read_error() {
+        init_logging();
+
+        let dir = temp_dir();
+        std::fs::write(dir.join("stderr.txt"), stderr_without_duration()).unwrap();
+        // A fake FFmpeg binary that reads its input to end, and then never exits by itself.
+        let path = write_script(
+            &dir,
+            &format!(
+                "cat '{dir}/stderr.txt' >&2\ncat > /dev/null\nexec sleep 30",
+                dir = dir.display()
+            ),
+        );
+
+        let settings =
+            Settings::from_reader(FailingReader { ok: 4 }, 200).ffmpeg_path(path.to_string_lossy());
+        let started = std::time::Instant::now();
+        let messages = run_to_completion(settings);
+        assert!(started.elapsed() < Duration::from_secs(10));
+        assert!(success_bytes(&messages).is_none());
+        assert!(matches!(
+            final_summary(&messages).error,
+            Some(Error::InputRead(ref e)) if e.to_string() == "connection reset"
+        ));
+    }
+
+    #[test]
+    fn test_convert_reader_input_end_to_end() {
+        init_logging();
+        if !ffmpeg_available() {
+            return;
+        }
+
+        let file = std::fs::File::open(streamable_sample()).unwrap();
+        let reader = std::io::BufReader::new(file);
+        let messages = run_to_completion(Settings::from_reader(reader, 100));
+        let bytes = success_bytes(&messages).expect("Expected a 'Success' message");
+        assert!(crate::gif_info::parse_gif_info(bytes).is_ok());
+    }
+
+    #[test]
+    fn test_convert_url_input_scripted() {
+        init_logging();
+
+        let dir = temp_dir();
+        let gif = sample_gif(2, Some(0));
+        // NOTE: Over the network, FFmpeg may not report any `Duration` line at all.
+        let stderr: String = SAMPLE_STDERR
+            .lines()
+            .filter(|line| !line.contains("Duration:"))
+            .map(|line| format!("{}\n", line))
+            .collect();
+        std::fs::write(dir.join("stderr.txt"), stderr).unwrap();
+        std::fs::write(dir.join("stdout.bin"), &gif).unwrap();
+        // A fake FFmpeg binary that only succeeds if it is only allowed the expected protocols.
+        let path = write_script(
+            &dir,
+            &format!(
+                r#"case "$*" in
+  *"-protocol_whitelist http,https,tcp,tls -i https://example.com/video.mp4 "*) ;;
+  *) exit 4;;
+esac
+cat '{dir}/stderr.txt' >&2
+cat '{dir}/stdout.bin'"#,
+                dir = dir.display()
+            ),
+        );
+
+        let settings = Settings::from_url("https://example.com/video.mp4", 200)
+            .ffmpeg_path(path.to_string_lossy());
+        let messages = run_to_completion(settings);
+        assert_eq!(success_bytes(&messages), Some(&gif[..]));
+        let frames: Vec<_> = messages
+            .iter()
+            .filter_map(|m| match m {
+                Message::FramesProcessed(n) => Some(*n),
+                _ => None,
+            })
+            .collect();
+        assert_eq!(frames, [20, 50]);
+        assert!(!messages.iter().any(|m| matches!(m, Message::Progress(_))));
+
+        // A URL whose scheme is not allowed never reaches FFmpeg.
+        let settings =
+            Settings::from_url("file:///etc/passwd", 200).ffmpeg_path(path.to_string_lossy());
+        assert_eq!(
+            settings.validate(),
+            Err(SettingsError::InvalidUrl(UrlError::SchemeNotAllowed(
+                "file".into()
+            )))
+        );
+        let messages = run_to_completion(settings);
+        assert!(matches!(
+            final_summary(&messages).error,
+            Some(Error::InvalidSettings(SettingsError::InvalidUrl(_)))
+        ));
+    }
+}
+
+#[cfg(all(test, windows))]
+mod windows_tests {
+    use super::*;
+    use crate::test_utils::temp_dir;
+
+    #[test]
+    fn test_prepare_hands_file_over_as_stdin() {
+        let path = temp_dir().join("input.txt");
+        std::fs::write(&path, "some content").unwrap();
+        let input = InputSource::from(std::fs::File::open(&path).unwrap());
+        assert_eq!(input.arg(), "pipe:0");
+        assert!(input.pipes_stdin());
+
+        for _ in 0..2 {
+            let mut command = std::process::Command::new("sort");
+            input.prepare(&mut command).unwrap();
+            let output = command.output().unwrap();
+            assert!(output.status.success());
+            assert_eq!(
+                String::from_utf8_lossy(&output.stdout).trim(),
+                "some content"
+            );
+        }
+    }
+}
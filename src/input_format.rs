@@ -0,0 +1,207 @@
+//! The hints passed to FFmpeg about the format of the input (see
+//! [`crate::Settings::input_format_hints`]), which are needed for inputs that have no
+//! header FFmpeg could read them from (e.g. raw frames), and which are written before
+//! the input's `-i` flag (i.e. as input options) of every FFmpeg child process.
+
+use crate::{InputSource, SettingsError};
+
+/// The name of FFmpeg's demuxer for raw (i.e. headerless) video frames.
+pub(crate) const RAW_VIDEO: &str = "rawvideo";
+
+#[derive(Debug, Clone, Default, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
+/// The format of the input, for inputs whose format FFmpeg cannot find by itself (see
+/// [`crate::Settings::input_format_hints`]). The hints that are `None` are not passed
+/// to FFmpeg, which then relies on its own detection (or defaults) for them.
+pub struct InputFormatHints {
+    /// The name of FFmpeg's demuxer (i.e. the value of the `-f` input option), e.g. `rawvideo`.
+    pub format: Option<String>,
+    /// The pixel format of the frames (i.e. the value of the `-pixel_format` input option),
+    /// e.g. `rgb24` or `yuv420p`.
+    pub pixel_format: Option<String>,
+    /// The width and height of the frames (i.e. the value of the `-video_size` input option).
+    pub video_size: Option<(u32, u32)>,
+    /// The frame rate (in frames per second) of the input (i.e. the value of the
+    /// `-framerate` input option).
+    pub framerate: Option<f64>,
+}
+
+impl InputFormatHints {
+    /// The hints needed to read a file of raw video frames (using FFmpeg's `rawvideo`
+    /// demuxer), of `width` x `height` pixels in `pixel_format` (e.g. `rgb24`), each
+    /// lasting `1 / framerate` seconds.
+    pub fn raw_video(
+        pixel_format: impl Into<String>,
+        width: u32,
+        height: u32,
+        framerate: f64,
+    ) -> Self {
+        Self {
+            format: Some(RAW_VIDEO.into()),
+            pixel_format: Some(pixel_format.into()),
+            video_size: Some((width, height)),
+            framerate: Some(framerate),
+        }
+    }
+
+    /// Makes sure that FFmpeg can make sense of the hints, which includes the size
+    /// and the pixel format of the frames being provided for the `rawvideo` demuxer.
+    pub(crate) fn validate(&self) -> Result<(), SettingsError> {
+        let empty = |value: &Option<String>| value.as_deref().is_some_and(str::is_empty);
+        if empty(&self.format) || empty(&self.pixel_format) {
+            return Err(SettingsError::InvalidInputFormatHints);
+        }
+        if let Some((width, height)) = self.video_size {
+            if width == 0 || height == 0 {
+                return Err(SettingsError::InvalidInputFormatHints);
+            }
+        }
+        if let Some(framerate) = self.framerate {
+            if !(framerate.is_finite() && framerate > 0.0) {
+                return Err(SettingsError::InvalidInputFormatHints);
+            }
+        }
+        if self.format.as_deref() == Some(RAW_VIDEO)
+            && (self.pixel_format.is_none() || self.video_size.is_none())
+        {
+            return Err(SettingsError::IncompleteRawVideoHints);
+        }
+        Ok(())
+    }
+
+    /// The input options passed to FFmpeg (before the input's `-i` flag).
+    fn args(&self) -> Vec<String> {
+        let mut args = vec![];
+        if let Some(format) = &self.format {
+            args.extend(["-f".into(), format.clone()]);
+        }
+        if let Some(pixel_format) = &self.pixel_format {
+            args.extend(["-pixel_format".into(), pixel_format.clone()]);
+        }
+        if let Some((width, height)) = self.video_size {
+            args.extend(["-video_size".into(), format!("{}x{}", width, height)]);
+        }
+        if let Some(framerate) = self.framerate {
+            args.extend(["-framerate".into(), framerate.to_string()]);
+        }
+        args
+    }
+}
+
+/// The arguments that make FFmpeg read `input`, i.e. its `-i` flag, preceded by the
+/// options given by `hints` (if any), and by the protocols FFmpeg may use for a remote
+/// input (see [`crate::RemoteUrl`]).
+pub(crate) fn input_args(hints: Option<&InputFormatHints>, input: &InputSource) -> Vec<String> {
+    let mut args = hints.map(InputFormatHints::args).unwrap_or_default();
+    if let InputSource::Url(url) = input {
+        args.extend(["-protocol_whitelist".into(), url.protocol_whitelist()]);
+    }
+    args.extend(["-i".into(), input.arg()]);
+    args
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_input_args() {
+        let input = InputSource::from("frames.rgb");
+        assert_eq!(input_args(None, &input), ["-i", "frames.rgb"]);
+        let hints = InputFormatHints::raw_video("rgb24", 1280, 720, 30.0);
+        assert_eq!(
+            input_args(Some(&hints), &input),
+            [
+                "-f",
+                "rawvideo",
+                "-pixel_format",
+                "rgb24",
+                "-video_size",
+                "1280x720",
+                "-framerate",
+                "30",
+                "-i",
+                "frames.rgb"
+            ]
+        );
+        let hints = InputFormatHints {
+            format: Some("h264".into()),
+            framerate: Some(29.97),
+            ..Default::default()
+        };
+        assert_eq!(
+            input_args(Some(&hints), &input),
+            ["-f", "h264", "-framerate", "29.97", "-i", "frames.rgb"]
+        );
+        let url = InputSource::from(crate::RemoteUrl::new("https://example.com/video.mp4"));
+        assert_eq!(
+            input_args(None, &url),
+            [
+                "-protocol_whitelist",
+                "http,https,tcp,tls",
+                "-i",
+                "https://example.com/video.mp4"
+            ]
+        );
+    }
+
+    #[test]
+    fn test_validate() {
+        let hints = InputFormatHints::raw_video("rgb24", 1280, 720, 30.0);
+        assert_eq!(hints.validate(), Ok(()));
+        // The frame rate is optional (FFmpeg defaults to 25 fps).
+        let no_framerate = InputFormatHints {
+            framerate: None,
+            ..hints.clone()
+        };
+        assert_eq!(no_framerate.validate(), Ok(()));
+        for incomplete in [
+            InputFormatHints {
+                pixel_format: None,
+                ..hints.clone()
+            },
+            InputFormatHints {
+                video_size: None,
+                ..hints.clone()
+            },
+        ] {
+            assert_eq!(
+                incomplete.validate(),
+                Err(SettingsError::IncompleteRawVideoHints)
+            );
+        }
+        for invalid in [
+            InputFormatHints {
+                format: Some("".into()),
+                ..hints.clone()
+            },
+            InputFormatHints {
+                pixel_format: Some("".into()),
+                ..hints.clone()
+            },
+            InputFormatHints {
+                video_size: Some((1280, 0)),
+                ..hints.clone()
+            },
+            InputFormatHints {
+                framerate: Some(0.0),
+                ..hints.clone()
+            },
+            InputFormatHints {
+                framerate: Some(f64::NAN),
+                ..hints.clone()
+            },
+        ] {
+            assert_eq!(
+                invalid.validate(),
+                Err(SettingsError::InvalidInputFormatHints)
+            );
+        }
+        // Other demuxers can find the size and pixel format by themselves.
+        let other = InputFormatHints {
+            format: Some("mjpeg".into()),
+            ..Default::default()
+        };
+        assert_eq!(other.validate(), Ok(()));
+    }
+}
@@ -0,0 +1,85 @@
+//! The events describing the lifecycle of the [`crate::Converter`]'s own threads and child
+//! process (see [`crate::Settings::emit_internal_events`]), which make the concurrency structure
+//! of a conversion job observable without enabling debug logging, e.g. for diagnostics.
+//!
+//! A conversion job runs the following threads, in addition to the one calling
+//! [`crate::Converter::convert`] (i.e. the main thread), which joins them before sending
+//! [`crate::Message::Summary`]:
+//! * [`ThreadKind::Stdin`] listens for the application's commands, and writes to the child process;
+//! * [`ThreadKind::Stdout`] reads the animated GIF, and settles the job's outcome;
+//! * [`ThreadKind::Stderr`] parses FFmpeg's log lines (e.g. for the progress);
+//! * [`ThreadKind::Smoother`] interpolates the progress (see [`crate::Settings::smooth_progress`]);
+//! * [`ThreadKind::Child`] waits for the child process to exit;
+//! * [`ThreadKind::Writer`] writes the video to the child process (see [`crate::InputSource::Bytes`]
+//!   and [`crate::InputSource::Reader`]).
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+/// One of the threads run by a conversion job (see the module's documentation).
+pub enum ThreadKind {
+    /// The thread that listens for the application's commands.
+    Stdin,
+    /// The thread that reads the animated GIF.
+    Stdout,
+    /// The thread that parses FFmpeg's log lines.
+    Stderr,
+    /// The thread that interpolates the progress.
+    Smoother,
+    /// The thread that waits for the child process to exit.
+    Child,
+    /// The thread that writes the video's bytes to the child process' `stdin`.
+    Writer,
+}
+
+impl ThreadKind {
+    /// The thread's name, as used in the log lines (e.g. `STDOUT`).
+    pub fn name(&self) -> &'static str {
+        match self {
+            Self::Stdin => "STDIN",
+            Self::Stdout => "STDOUT",
+            Self::Stderr => "STDERR",
+            Self::Smoother => "SMOOTHER",
+            Self::Child => "CHILD",
+            Self::Writer => "WRITER",
+        }
+    }
+}
+
+impl std::fmt::Display for ThreadKind {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(self.name())
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+/// How a thread exited (see [`InternalEvent::ThreadExited`]).
+pub enum ExitKind {
+    /// The thread returned.
+    Returned,
+    /// The thread panicked, in which case the main thread panics as well when joining it.
+    Panicked,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+/// An event of the lifecycle of the [`crate::Converter`]'s threads and child process, sent using
+/// [`crate::Message::Internal`] when [`crate::Settings::emit_internal_events`] is enabled.
+///
+/// NOTE: For a given thread, [`InternalEvent::ThreadStarted`] always comes first, followed by
+/// [`InternalEvent::ThreadExited`] and then by either [`InternalEvent::ThreadJoined`] or
+/// [`InternalEvent::ThreadAbandoned`], all of which come before [`crate::Message::Summary`].
+pub enum InternalEvent {
+    /// The thread has started.
+    ThreadStarted(ThreadKind),
+    /// The thread is exiting (i.e. this is the last message it sends).
+    ThreadExited(ThreadKind, ExitKind),
+    /// The thread has been joined by the main thread.
+    ThreadJoined(ThreadKind),
+    /// The thread did not exit in time, so the main thread gave up on it (see
+    /// [`crate::Warning::StdinThreadTimeout`]), after which no other internal event is sent
+    /// (since the thread may only exit after [`crate::Message::Done`]).
+    ThreadAbandoned(ThreadKind),
+    /// The child process has exited, and has been reaped by [`ThreadKind::Child`].
+    ChildReaped {
+        /// The child process' exit code (`None` if it was killed by a signal).
+        code: Option<i32>,
+    },
+}
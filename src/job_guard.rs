@@ -0,0 +1,65 @@
+//! The handle returned by [`crate::Converter::spawn`], which ties the conversion job to
+//! the application's scope: when the guard is dropped (e.g. because the task owning it was
+//! aborted), the job is cancelled just like with [`crate::Command::Cancel`], and the child
+//! process is killed rather than left running until completion, since nobody waits for it
+//! anymore. The application opts out using [`JobGuard::detach`].
+//!
+//! The guard only requests the cancellation, without waiting for the job's threads and child
+//! process to be gone, so that dropping it never blocks (e.g. an asynchronous runtime). With
+//! the `tokio` feature flag, [`JobGuard::shutdown`] also waits for the job to be torn down.
+
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+
+/// Cancels the conversion job it was returned for when dropped (see the module's documentation).
+#[must_use = "dropping the guard cancels the job (see `JobGuard::detach`)"]
+pub struct JobGuard {
+    /// Whether the guard was dropped, which the [`crate::Converter`] checks along with its
+    /// commands.
+    dropped: Arc<AtomicBool>,
+    /// Whether the job keeps running when the guard is dropped.
+    detached: bool,
+    #[cfg(feature = "tokio")]
+    /// Completed (or closed) once [`crate::Converter::convert`] has returned.
+    done: Option<tokio::sync::oneshot::Receiver<()>>,
+}
+
+impl JobGuard {
+    pub(crate) fn new(
+        dropped: Arc<AtomicBool>,
+        #[cfg(feature = "tokio")] done: tokio::sync::oneshot::Receiver<()>,
+    ) -> Self {
+        Self {
+            dropped,
+            detached: false,
+            #[cfg(feature = "tokio")]
+            done: Some(done),
+        }
+    }
+
+    /// Lets the job run to completion, whatever happens to the application's scope (the
+    /// application then relies on the [`crate::Message`] channel, or on the commands, alone).
+    pub fn detach(mut self) {
+        self.detached = true;
+    }
+
+    #[cfg(feature = "tokio")]
+    /// Cancels the job (see [`crate::Command::Cancel`]), and waits (without blocking the
+    /// runtime) until it has been torn down, i.e. until its threads have been joined and its
+    /// child process reaped, which may be after [`crate::Message::Done`] was received.
+    pub async fn shutdown(mut self) {
+        self.dropped.store(true, Ordering::SeqCst);
+        if let Some(done) = self.done.take() {
+            // NOTE: Closed without a value if the job's thread panicked, which also tears it down.
+            let _ = done.await;
+        }
+    }
+}
+
+impl Drop for JobGuard {
+    fn drop(&mut self) {
+        if !self.detached {
+            self.dropped.store(true, Ordering::SeqCst);
+        }
+    }
+}
@@ -0,0 +1,85 @@
+//! The identifier of a conversion job (see [`crate::Converter::id`]), which is either
+//! generated by the [`crate::Converter`] (i.e. a random UUID), or supplied by the
+//! application (see [`crate::Converter::with_id`]), e.g. the primary key of the job's
+//! row in the application's database, so that no mapping between the two is needed.
+
+#[derive(Debug, Clone, PartialEq, Eq, Hash, PartialOrd, Ord)]
+/// The unique identifier of a conversion job (see [`crate::Converter::id`]), which tags
+/// its log lines (as the `job_id` key), its [`crate::Summary`] and its [`crate::BatchEvent`]'s.
+pub enum JobId {
+    /// A UUID (e.g. the random one generated by default).
+    Uuid(uuid::Uuid),
+    /// An integer supplied by the application.
+    U64(u64),
+    /// A string supplied by the application.
+    String(String),
+}
+
+impl JobId {
+    /// A new random identifier, as used by default.
+    pub(crate) fn generate() -> Self {
+        Self::Uuid(uuid::Uuid::new_v4())
+    }
+}
+
+impl From<uuid::Uuid> for JobId {
+    fn from(id: uuid::Uuid) -> Self {
+        Self::Uuid(id)
+    }
+}
+
+impl From<u64> for JobId {
+    fn from(id: u64) -> Self {
+        Self::U64(id)
+    }
+}
+
+impl From<String> for JobId {
+    fn from(id: String) -> Self {
+        Self::String(id)
+    }
+}
+
+impl From<&str> for JobId {
+    fn from(id: &str) -> Self {
+        Self::String(id.into())
+    }
+}
+
+impl std::fmt::Display for JobId {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::Uuid(id) => id.fmt(f),
+            Self::U64(id) => id.fmt(f),
+            Self::String(id) => f.write_str(id),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_job_id_display() {
+        let uuid = uuid::Uuid::parse_str("67e55044-10b1-426f-9247-bb680e5fe0c8").unwrap();
+        assert_eq!(
+            JobId::from(uuid).to_string(),
+            "67e55044-10b1-426f-9247-bb680e5fe0c8"
+        );
+        assert_eq!(JobId::from(42).to_string(), "42");
+        assert_eq!(JobId::from("upload-42").to_string(), "upload-42");
+        assert!(matches!(JobId::generate(), JobId::Uuid(_)));
+        assert_ne!(JobId::generate(), JobId::generate());
+    }
+
+    #[test]
+    fn test_job_id_map_key() {
+        let mut jobs = std::collections::HashMap::new();
+        jobs.insert(JobId::from(42), "integer");
+        jobs.insert(JobId::from("42"), "string");
+        assert_eq!(jobs.len(), 2);
+        assert_eq!(jobs.get(&JobId::U64(42)), Some(&"integer"));
+        assert_eq!(jobs.get(&JobId::String("42".into())), Some(&"string"));
+    }
+}
@@ -0,0 +1,184 @@
+//! The counters and histograms recorded (using the [`metrics`](https://docs.rs/metrics)
+//! crate) when the `metrics` feature flag is enabled. The functions in this module
+//! are no-ops otherwise.
+//!
+//! The following metrics are recorded:
+//! * `ffmpeg_gif_maker_jobs_started_total` (counter): the number of jobs started.
+//! * `ffmpeg_gif_maker_jobs_total{outcome}` (counter): the number of jobs that ended,
+//!   where `outcome` is one of `succeeded`, `failed` or `cancelled`.
+//! * `ffmpeg_gif_maker_job_errors_total{kind}` (counter): the number of failed jobs,
+//!   where `kind` is the value returned by [`crate::Error::kind`].
+//! * `ffmpeg_gif_maker_output_bytes_total` (counter): the number of bytes produced
+//!   by successful jobs.
+//! * `ffmpeg_gif_maker_job_duration_seconds{outcome}` (histogram): the duration of
+//!   the jobs that ended.
+
+use crate::Summary;
+
+#[cfg(feature = "metrics")]
+use crate::Outcome;
+
+/// Records that a job was started.
+pub(crate) fn record_started() {
+    #[cfg(feature = "metrics")]
+    metrics::counter!("ffmpeg_gif_maker_jobs_started_total").increment(1);
+}
+
+/// Records the outcome of a job that ended, based on its [`Summary`].
+pub(crate) fn record_summary(summary: &Summary) {
+    #[cfg(feature = "metrics")]
+    {
+        let outcome = summary.outcome();
+        let label = match outcome {
+            Outcome::Succeeded => "succeeded",
+            Outcome::Failed => "failed",
+            Outcome::Cancelled => "cancelled",
+            Outcome::Skipped => "skipped",
+        };
+        metrics::counter!("ffmpeg_gif_maker_jobs_total", "outcome" => label).increment(1);
+        metrics::histogram!("ffmpeg_gif_maker_job_duration_seconds", "outcome" => label)
+            .record(summary.elapsed.as_secs_f64());
+        if let (Outcome::Failed, Some(e)) = (outcome, &summary.error) {
+            metrics::counter!("ffmpeg_gif_maker_job_errors_total", "kind" => e.kind()).increment(1);
+        }
+        if let Some(n) = summary.output_bytes {
+            metrics::counter!("ffmpeg_gif_maker_output_bytes_total").increment(n as u64);
+        }
+    }
+    #[cfg(not(feature = "metrics"))]
+    let _ = summary;
+}
+
+#[cfg(all(test, unix, feature = "metrics"))]
+mod tests {
+    use metrics_util::debugging::{DebugValue, DebuggingRecorder, Snapshotter};
+    use metrics_util::MetricKind;
+
+    use crate::test_utils::{
+        fake_ffmpeg, init_logging, sample_gif, SAMPLE_STDERR, SAMPLE_VIDEO_PATH,
+    };
+    use crate::{Converter, Settings};
+
+    /// Runs a job on the current thread (so that the thread-local recorder sees its metrics).
+    fn run_job(stdout: &[u8], exit_code: i32) {
+        let settings = Settings::with_standard_fps(SAMPLE_VIDEO_PATH.into(), 200)
+            .ffmpeg_path(fake_ffmpeg(SAMPLE_STDERR, stdout, exit_code).to_string_lossy());
+        let (converter, _tx, _rx) = Converter::new_with_channels();
+        converter.convert(settings);
+    }
+
+    /// The values of all the metrics recorded so far.
+    type Values = Vec<(MetricKind, String, Vec<(String, String)>, DebugValue)>;
+
+    fn values(snapshotter: &Snapshotter) -> Values {
+        snapshotter
+            .snapshot()
+            .into_vec()
+            .into_iter()
+            .map(|(key, _, _, value)| {
+                let labels = key
+                    .key()
+                    .labels()
+                    .map(|l| (l.key().to_string(), l.value().to_string()))
+                    .collect();
+                (key.kind(), key.key().name().to_string(), labels, value)
+            })
+            .collect()
+    }
+
+    fn value<'a>(
+        values: &'a Values,
+        kind: MetricKind,
+        name: &str,
+        labels: &[(&str, &str)],
+    ) -> Option<&'a DebugValue> {
+        values
+            .iter()
+            .find(|(k, n, l, _)| {
+                *k == kind
+                    && n == name
+                    && l.len() == labels.len()
+                    && labels
+                        .iter()
+                        .all(|(lk, lv)| l.iter().any(|(k, v)| k == lk && v == lv))
+            })
+            .map(|(_, _, _, value)| value)
+    }
+
+    fn counter(values: &Values, name: &str, labels: &[(&str, &str)]) -> u64 {
+        match value(values, MetricKind::Counter, name, labels) {
+            Some(DebugValue::Counter(n)) => *n,
+            _ => 0,
+        }
+    }
+
+    #[test]
+    fn test_metrics_success() {
+        init_logging();
+        let recorder = DebuggingRecorder::new();
+        let snapshotter = recorder.snapshotter();
+        let gif = sample_gif(3, Some(0));
+        metrics::with_local_recorder(&recorder, || run_job(&gif, 0));
+        let values = values(&snapshotter);
+
+        assert_eq!(
+            counter(&values, "ffmpeg_gif_maker_jobs_started_total", &[]),
+            1
+        );
+        assert_eq!(
+            counter(
+                &values,
+                "ffmpeg_gif_maker_jobs_total",
+                &[("outcome", "succeeded")]
+            ),
+            1
+        );
+        assert_eq!(
+            counter(&values, "ffmpeg_gif_maker_output_bytes_total", &[]),
+            gif.len() as u64
+        );
+        assert!(matches!(
+            value(
+                &values,
+                MetricKind::Histogram,
+                "ffmpeg_gif_maker_job_duration_seconds",
+                &[("outcome", "succeeded")]
+            ),
+            Some(DebugValue::Histogram(durations)) if durations.len() == 1
+        ));
+    }
+
+    #[test]
+    fn test_metrics_failure() {
+        init_logging();
+        let recorder = DebuggingRecorder::new();
+        let snapshotter = recorder.snapshotter();
+        metrics::with_local_recorder(&recorder, || run_job(b"", 0));
+        let values = values(&snapshotter);
+
+        assert_eq!(
+            counter(&values, "ffmpeg_gif_maker_jobs_started_total", &[]),
+            1
+        );
+        assert_eq!(
+            counter(
+                &values,
+                "ffmpeg_gif_maker_jobs_total",
+                &[("outcome", "failed")]
+            ),
+            1
+        );
+        assert_eq!(
+            counter(
+                &values,
+                "ffmpeg_gif_maker_job_errors_total",
+                &[("kind", "empty_stdout")]
+            ),
+            1
+        );
+        assert_eq!(
+            counter(&values, "ffmpeg_gif_maker_output_bytes_total", &[]),
+            0
+        );
+    }
+}
@@ -0,0 +1,105 @@
+//! The identification of the log lines written on behalf of a conversion job, which
+//! are prefixed with a short, human-readable tag (e.g. `#12 [request-42]`), and carry
+//! the job's identifiers as structured key-values (see [`job_log`]), so that the lines
+//! of concurrent jobs can be told apart (and filtered) by both humans and tools.
+
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::Arc;
+
+use crate::JobId;
+
+/// The number of the next [`crate::Converter`] instance created by the process.
+static NEXT_JOB_NUMBER: AtomicU64 = AtomicU64::new(1);
+
+/// Returns a new sequential job number (starting at 1, for the whole process).
+pub(crate) fn next_job_number() -> u64 {
+    NEXT_JOB_NUMBER.fetch_add(1, Ordering::Relaxed)
+}
+
+#[derive(Debug)]
+struct Inner {
+    id: JobId,
+    number: u64,
+    label: Option<String>,
+    prefix: String,
+}
+
+#[derive(Debug, Clone)]
+/// The (cheaply cloned) identifiers of a job, as included in its log lines.
+pub(crate) struct JobTag(Arc<Inner>);
+
+impl JobTag {
+    pub(crate) fn new(id: JobId, number: u64, label: Option<String>) -> Self {
+        let prefix = match &label {
+            Some(label) => format!("#{} [{}]", number, label),
+            None => format!("#{}", number),
+        };
+        Self(Arc::new(Inner {
+            id,
+            number,
+            label,
+            prefix,
+        }))
+    }
+
+    /// The job's unique identifier (logged as the `job_id` key).
+    pub(crate) fn id(&self) -> &JobId {
+        &self.0.id
+    }
+
+    /// The job's sequential number (logged as the `job` key).
+    pub(crate) fn number(&self) -> u64 {
+        self.0.number
+    }
+
+    /// The job's label, if any (logged as the `job_label` key, empty if `None`).
+    pub(crate) fn label(&self) -> Option<&str> {
+        self.0.label.as_deref()
+    }
+}
+
+impl std::fmt::Display for JobTag {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(&self.0.prefix)
+    }
+}
+
+/// Logs a message on behalf of a job (identified by a [`JobTag`]), i.e. with the job's
+/// tag as a prefix and its identifiers as key-values: `job_log!(info, TARGET, tag, "...", args)`.
+macro_rules! job_log {
+    ($level:ident, $target:expr, $tag:expr, $($arg:tt)+) => {{
+        let tag: &$crate::job_tag::JobTag = &$tag;
+        log::$level!(
+            target: $target,
+            job_id:% = tag.id(),
+            job = tag.number(),
+            job_label = tag.label().unwrap_or("");
+            "{} {}", tag, format_args!($($arg)+)
+        )
+    }};
+}
+pub(crate) use job_log;
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_job_tag() {
+        let id = JobId::generate();
+        let tag = JobTag::new(id.clone(), 12, None);
+        assert_eq!(tag.to_string(), "#12");
+        assert_eq!((tag.id(), tag.number(), tag.label()), (&id, 12, None));
+        let tag = JobTag::new(JobId::U64(42), 13, Some("request-42".into()));
+        assert_eq!(tag.id().to_string(), "42");
+        assert_eq!(tag.to_string(), "#13 [request-42]");
+        assert_eq!(tag.label(), Some("request-42"));
+    }
+
+    #[test]
+    fn test_job_numbers() {
+        let first = next_job_number();
+        let second = next_job_number();
+        assert!(second > first);
+    }
+}
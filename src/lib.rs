@@ -1,75 +1,2084 @@
 #![doc = include_str!("../docs/lib.md")]
 
-pub use converter::{CommandReceiver, CommandSender, Converter, MessageReceiver, MessageSender};
+#[cfg(all(feature = "tokio", feature = "async-channel"))]
+compile_error!("The `tokio` and `async-channel` feature flags are mutually exclusive, since both select the flavor of the channels.");
 
+pub use batch::{Batch, BatchEvent, BatchHandle, BatchReport, UpdateError};
+pub use bounded_channel::{BoundedMessageReceiver, BoundedMessageSender};
+pub use cache::CacheConfig;
+pub use capabilities::Capability;
+pub use clip::{ClipSelection, SeekMode};
+pub use conflicts::{ConflictSeverity, SettingsConflict};
+pub use converter::{
+    CommandReceiver, CommandSender, Converter, MessageReceiver, MessageSender, ProgressReceiver,
+    ProgressSender,
+};
+pub use crop::CropRect;
+pub use duration_source::DurationSource;
+pub use fit::{compute_output_dimensions, EvenDimensionPolicy, FitMode, ScaleAlgorithm, SizeMode};
+pub use hwaccel::{HwAccel, HwAccelFallback};
+pub use input::InputSource;
+pub use input_format::InputFormatHints;
+pub use internal_events::{ExitKind, InternalEvent, ThreadKind};
+pub use job_guard::JobGuard;
+pub use job_id::JobId;
+pub use location::{FfmpegLocation, FfmpegLocationError};
+pub use memory_budget::BudgetMeasure;
+#[cfg(not(any(feature = "tokio", feature = "async-channel")))]
+pub use messages::{convert_with_progress, wait_for_result, Messages};
+pub use output_format::OutputFormat;
+pub use output_naming::{CollisionPolicy, NamingError, OutputNaming};
+pub use palette_stats::PaletteStatsMode;
+pub use plan::ConversionPlan;
+pub use preview::Text;
+pub use probe::{quick_validate, ProbeError, VideoInfo};
+pub use progress::ProgressCurve;
+pub use provenance::{ParseProvenance, ParsedEvent, PROVENANCE_LOG_TARGET};
+pub use receiver_ext::MessageReceiverExt;
+pub use remote_url::{RemoteUrl, UrlError};
+pub use resource_usage::ResourceUsage;
+pub use rotation::Rotation;
+pub use sniff::InputKind;
+pub use stats_anomaly::StatsAnomaly;
+pub use strict_parsing::ParseContext;
+pub use thumbnails::{StripSettings, ThumbnailFormat};
+pub use time_parsing::{parse_time_spec, TimeSpec, TimeSpecError};
+pub use watermark::Position;
+
+mod argv;
+#[cfg(feature = "tokio")]
+mod async_context;
+mod auxiliary;
+mod batch;
+mod bounded_channel;
+mod cache;
+mod capabilities;
+mod cleanup_guard;
+mod clip;
+mod codec_selection;
+mod conflicts;
 mod converter;
+mod crop;
+mod deadline;
+mod deny;
+mod duration_source;
+mod filter_graph;
+mod fit;
+mod frame_map;
+pub mod gif_info;
+mod hwaccel;
+mod input;
+mod input_format;
+mod internal_events;
+mod job_guard;
+mod job_id;
+mod job_metrics;
+mod job_tag;
+mod location;
+mod memory_budget;
+#[cfg(not(any(feature = "tokio", feature = "async-channel")))]
+mod messages;
+mod outbox;
+mod output_format;
+mod output_naming;
+mod output_path;
+mod output_stream;
+mod output_writer;
+mod palette;
+mod palette_stats;
+mod plan;
+mod preview;
+mod probe;
+mod progress;
+mod provenance;
+mod receiver_ext;
+mod remote_url;
+mod resource_usage;
+mod rotation;
+mod size_target;
+mod sniff;
+mod stats_anomaly;
+mod stderr_lines;
+mod strict_parsing;
+mod subtitles;
+#[cfg(test)]
+mod test_utils;
+mod thumbnails;
 mod time_parsing;
+#[cfg(feature = "v2-messages")]
+pub mod v2;
+#[cfg(not(feature = "v2-messages"))]
+mod v2;
+mod variants;
+mod video_stream;
+mod watermark;
 
 #[derive(Clone, Debug)]
 /// The structure that contains the settings for the [`Converter`].
 pub struct Settings {
-    /// The absolute path of the FFmpeg binary on the system.
-    ffmpeg_path: Option<String>,
-    /// The path of the video to be converted into an animated GIF.
-    video_path: String,
+    /// The location of the FFmpeg binary on the system.
+    ffmpeg_location: Option<FfmpegLocation>,
+    /// The command (i.e. program and arguments) through which FFmpeg is run, if any.
+    command_wrapper: Vec<std::ffi::OsString>,
+    /// The callback that can edit the FFmpeg arguments right before the job is spawned.
+    argv_hook: Option<argv::ArgvHook>,
+    /// Whether the FFmpeg binary's filters and encoders are checked before the job.
+    check_capabilities: bool,
+    /// The source of the video to be converted into an animated GIF.
+    input: InputSource,
+    /// The format of the input, for inputs whose format FFmpeg cannot find by itself.
+    input_format_hints: Option<InputFormatHints>,
+    /// The index of the video stream to convert, among the input's video streams.
+    video_stream_index: Option<u32>,
+    /// The hardware acceleration method used to decode the video, if any.
+    hwaccel: Option<HwAccel>,
+    /// What happens when the hardware acceleration method cannot be set up.
+    hwaccel_fallback: HwAccelFallback,
+    /// The number of threads used by FFmpeg to decode the video and run the filter graph
+    /// (or 0 to let FFmpeg decide).
+    threads: u16,
     /// The frame rate (in frames per second) to use for animated GIF.
     gif_fps: u16,
     /// The animated GIF's width.
     gif_width: u16,
+    /// The animated GIF's height (following the video's aspect ratio if `None`).
+    gif_height: Option<u16>,
+    /// How the video's frames are fitted into the animated GIF's width and height.
+    fit_mode: FitMode,
+    /// The algorithm used to resize the video's frames (FFmpeg's default if `None`).
+    scale_algorithm: Option<ScaleAlgorithm>,
+    /// How the height that follows the video's aspect ratio is rounded.
+    even_dimension_policy: EvenDimensionPolicy,
+    /// How the video's frames are rotated (not at all if `None`).
+    rotation: Option<Rotation>,
+    /// Whether the video's frames are mirrored horizontally (i.e. left to right).
+    hflip: bool,
+    /// Whether the video's frames are flipped vertically (i.e. upside down).
+    vflip: bool,
+    /// The saturation of the video's frames, relative to the source video's.
+    saturation: f32,
+    /// The brightness added to the video's frames.
+    brightness: f32,
+    /// The contrast of the video's frames, relative to the source video's.
+    contrast: f32,
+    /// The image overlaid onto the animated GIF's frames, if any.
+    watermark: Option<watermark::Watermark>,
+    /// The path of the subtitles file burned into the animated GIF's frames, if any.
+    subtitles: Option<std::path::PathBuf>,
+    /// The width of the preview, converted before the animated GIF itself, if any.
+    preview_width: Option<u16>,
+    /// The badge stamped onto the preview's frames (but not onto the animated GIF's), if any.
+    badge_overlay: Option<Text>,
+    /// The pass of the job described by these settings (see [`Settings::preview_settings`]).
+    pass: preview::Pass,
+    /// Whether the last frame of the source video should always be
+    /// included in the animated GIF.
+    preserve_last_frame: bool,
+    /// Whether the delays of the animated GIF's frames are rewritten to match its frame rate.
+    interpolate_frame_delays: bool,
+    /// The maximum number of colors in the animated GIF's palette.
+    max_colors: Option<u16>,
+    /// Whether the number of colors should be picked automatically.
+    auto_colors: bool,
+    /// The time after which each auxiliary child process (run before the job) gets killed.
+    auxiliary_timeout: Option<std::time::Duration>,
+    /// The point in time by which the whole job must be done.
+    deadline: Option<std::time::Instant>,
+    /// The upper bound (in bytes) on the memory used by the job's growing buffers.
+    memory_budget: Option<usize>,
+    /// The format of the animation written to `stdout` (an animated GIF by default).
+    output_format: OutputFormat,
+    /// Whether the animated GIF should only have a global color table.
+    global_palette_only: bool,
+    /// The maximum number of bits per color index in the animated GIF's palette.
+    max_palette_bit_depth: Option<u8>,
+    /// Which pixels the animated GIF's palette is computed from.
+    palette_stats_mode: PaletteStatsMode,
+    /// Whether interpolated progress values should be emitted between
+    /// FFmpeg's stats updates.
+    smooth_progress: bool,
+    /// How the progress values are derived from FFmpeg's stats updates.
+    progress_curve: ProgressCurve,
+    /// The region of the video's frames to convert, over time.
+    crop_keyframes: Vec<(std::time::Duration, CropRect)>,
+    /// The substrings that make the job fail when found in FFmpeg's `stderr` output.
+    deny_stderr_patterns: Vec<String>,
+    #[cfg(feature = "regex")]
+    /// The regular expressions that make the job fail when matched by FFmpeg's `stderr` output.
+    deny_stderr_regexes: Vec<String>,
+    /// The output to input size ratio above which [`Warning::OutputLargerThanInput`] is emitted.
+    output_size_warning_ratio: Option<f64>,
+    /// The size (in bytes) above which the animated GIF is re-encoded with reduced settings.
+    max_output_bytes: Option<u64>,
+    /// The maximum number of attempts made to fit within `max_output_bytes`.
+    max_output_attempts: u32,
+    /// The part of the source video to convert.
+    clip: Option<ClipSelection>,
+    /// How FFmpeg seeks to the start of the clip.
+    seek_mode: SeekMode,
+    /// The exact number of frames of the animated GIF (at most).
+    output_frame_limit: Option<u32>,
+    /// Whether [`Message::FrameMap`] is emitted.
+    emit_frame_map: bool,
+    /// The filter graph passed to FFmpeg instead of the generated one.
+    custom_filter_complex: Option<String>,
+    /// The widths of the other animated GIFs generated from the same decoded frames.
+    additional_widths: Vec<u16>,
+    /// The arguments passed to FFmpeg right before `-i`.
+    extra_input_args: Vec<std::ffi::OsString>,
+    /// The arguments passed to FFmpeg right before each output.
+    extra_output_args: Vec<std::ffi::OsString>,
+    /// Whether [`Message::Internal`] is emitted.
+    emit_internal_events: bool,
+    /// The number of parsed events whose source line is kept for [`Summary::provenance`].
+    parse_provenance: Option<usize>,
+    /// Whether the problems met while parsing FFmpeg's `stderr` output fail the job.
+    strict_parsing: bool,
+    /// The playback speed of the animated GIF, relative to the source video's.
+    speed: f32,
+    /// The interval at which the video's frames are kept, before the frame rate is applied.
+    frame_step: Option<u32>,
+    /// Whether the animated GIF plays forward, then backward.
+    boomerang: bool,
+    /// The directory to which the animated GIF is also written, and the naming of the file.
+    output_file: Option<(std::path::PathBuf, OutputNaming)>,
+    /// The path of the file to which FFmpeg writes the animated GIF, instead of `stdout`.
+    output_path: Option<std::path::PathBuf>,
+    /// The first time spec passed to a setter (e.g. [`Settings::trim`]) that could not be parsed.
+    invalid_time_spec: Option<TimeSpecError>,
+    #[cfg(feature = "tokio")]
+    /// Whether the [`Converter`] should refuse to run when called from
+    /// a thread that is driving asynchronous tasks.
+    strict_async_context: bool,
 }
 
-impl Settings {
-    /// The default frame rate used for the generated animated GIF.
+impl Settings {
+    /// The default frame rate used for the generated animated GIF.
+    ///
+    /// NOTE: This is the only allowed value for now; i.e. the API does
+    /// not allow modifying this value (except for [`Settings::auto`], which
+    /// picks the frame rate based on the source video's).
+    pub const STANDARD_FPS: u16 = 10;
+
+    /// A factory method that probes the source video (using a short-lived FFmpeg child
+    /// process, FFmpeg being expected on the system path) and picks sensible settings
+    /// based on its properties (see [`Settings::from_video_info`]).
+    pub fn auto(video_path: String) -> Result<Self, ProbeError> {
+        let info = VideoInfo::probe(video_path.as_str(), None)?;
+        Ok(Self::from_video_info(video_path, &info))
+    }
+
+    /// Same as [`Settings::auto`], given the properties of the source video (e.g. as
+    /// returned by [`VideoInfo::probe`]), using the following (predictable) heuristics:
+    ///
+    /// | Setting | Value |
+    /// | ------- | ----- |
+    /// | Width | The video's width, capped at 480 pixels |
+    /// | Frame rate | The video's frame rate (rounded), capped at 15 fps, or at 10 fps for videos longer than 30 seconds ([`Settings::STANDARD_FPS`] if unknown) |
+    /// | [`Settings::max_colors`] | 256 (i.e. the default) for videos up to 10 seconds long (or of unknown duration), 128 up to 30 seconds, and 64 beyond |
+    /// | [`Settings::preserve_last_frame`] | Enabled for videos up to 10 seconds long (which are typically loops) |
+    ///
+    /// The other settings keep their default values.
+    pub fn from_video_info(input: impl Into<InputSource>, info: &VideoInfo) -> Self {
+        let params = probe::auto_params(info);
+        Self {
+            gif_fps: params.fps,
+            max_colors: params.max_colors,
+            preserve_last_frame: params.preserve_last_frame,
+            ..Self::with_input(input, params.width)
+        }
+    }
+
+    /// A factory method that takes in the source `video_path` and the
+    /// target `width` for the animated GIF.
+    pub fn with_standard_fps(video_path: String, width: u16) -> Self {
+        Self::with_input(InputSource::Path(video_path), width)
+    }
+
+    /// Same as [`Settings::with_standard_fps`], except that the video is provided as its
+    /// bytes, already in memory (see [`InputSource::Bytes`]), e.g. an upload received by a
+    /// server, which is streamed to FFmpeg instead of being written to a temporary file.
+    pub fn from_bytes(bytes: Vec<u8>, width: u16) -> Self {
+        Self::with_input(bytes, width)
+    }
+
+    /// Same as [`Settings::with_standard_fps`], except that the video is read from `reader`
+    /// (see [`InputSource::Reader`]), e.g. a network body, which is streamed to FFmpeg without
+    /// the whole video being buffered first.
+    pub fn from_reader(reader: impl std::io::Read + Send + 'static, width: u16) -> Self {
+        Self::with_input(InputSource::from_reader(reader), width)
+    }
+
+    /// Same as [`Settings::with_standard_fps`], except that FFmpeg reads the video directly
+    /// from `url` (see [`InputSource::Url`]), e.g. `https://example.com/video.mp4`, which may
+    /// only be an `http` or `https` URL (see [`RemoteUrl::allowed_schemes`] to change that,
+    /// using [`Settings::with_input`]), any other URL making [`Settings::validate`] fail
+    /// with [`SettingsError::InvalidUrl`].
+    pub fn from_url(url: impl Into<String>, width: u16) -> Self {
+        Self::with_input(RemoteUrl::new(url), width)
+    }
+
+    /// Same as [`Settings::with_standard_fps`], except that the video can be
+    /// provided using any [`InputSource`] (e.g. an already opened [`std::fs::File`]).
+    pub fn with_input(input: impl Into<InputSource>, width: u16) -> Self {
+        Self {
+            ffmpeg_location: None,
+            command_wrapper: vec![],
+            argv_hook: None,
+            check_capabilities: false,
+            input: input.into(),
+            input_format_hints: None,
+            video_stream_index: None,
+            hwaccel: None,
+            hwaccel_fallback: HwAccelFallback::Software,
+            threads: 0,
+            gif_fps: Self::STANDARD_FPS,
+            gif_width: width,
+            gif_height: None,
+            fit_mode: FitMode::default(),
+            scale_algorithm: None,
+            even_dimension_policy: EvenDimensionPolicy::default(),
+            rotation: None,
+            hflip: false,
+            vflip: false,
+            saturation: 1.0,
+            brightness: 0.0,
+            contrast: 1.0,
+            watermark: None,
+            subtitles: None,
+            preview_width: None,
+            badge_overlay: None,
+            pass: preview::Pass::default(),
+            preserve_last_frame: false,
+            interpolate_frame_delays: false,
+            max_colors: None,
+            auto_colors: false,
+            auxiliary_timeout: Some(Self::DEFAULT_AUXILIARY_TIMEOUT),
+            deadline: None,
+            memory_budget: None,
+            output_format: OutputFormat::default(),
+            global_palette_only: false,
+            max_palette_bit_depth: None,
+            palette_stats_mode: PaletteStatsMode::default(),
+            smooth_progress: false,
+            progress_curve: ProgressCurve::default(),
+            crop_keyframes: vec![],
+            deny_stderr_patterns: vec![],
+            #[cfg(feature = "regex")]
+            deny_stderr_regexes: vec![],
+            output_size_warning_ratio: Some(Self::DEFAULT_OUTPUT_SIZE_WARNING_RATIO),
+            max_output_bytes: None,
+            max_output_attempts: Self::DEFAULT_MAX_OUTPUT_ATTEMPTS,
+            clip: None,
+            seek_mode: SeekMode::default(),
+            output_frame_limit: None,
+            emit_frame_map: false,
+            custom_filter_complex: None,
+            additional_widths: vec![],
+            extra_input_args: vec![],
+            extra_output_args: vec![],
+            emit_internal_events: false,
+            parse_provenance: None,
+            strict_parsing: false,
+            speed: 1.0,
+            frame_step: None,
+            boomerang: false,
+            output_file: None,
+            output_path: None,
+            invalid_time_spec: None,
+            #[cfg(feature = "tokio")]
+            strict_async_context: false,
+        }
+    }
+
+    /// A setter method that allows specifying the path to be used
+    /// for the ffmpeg binary.
+    pub fn ffmpeg_path(self, ffmpeg_path: impl Into<String>) -> Self {
+        self.ffmpeg_location(FfmpegLocation::Path(ffmpeg_path.into()))
+    }
+
+    /// A setter method that allows specifying where to find the ffmpeg
+    /// binary, e.g. relative to the application's own executable (see
+    /// [`FfmpegLocation::RelativeToExe`]). When no location is provided,
+    /// `ffmpeg` is expected to be found on the system path.
+    pub fn ffmpeg_location(self, ffmpeg_location: impl Into<FfmpegLocation>) -> Self {
+        Self {
+            ffmpeg_location: Some(ffmpeg_location.into()),
+            ..self
+        }
+    }
+
+    /// A setter method that allows running FFmpeg through a wrapper command (e.g. `nice -n 19`,
+    /// `firejail --profile=ffmpeg`, or `wsl.exe` on Windows), whose first element is the
+    /// program that gets spawned, followed by its own arguments, the FFmpeg binary's path then
+    /// being passed as its next argument (followed by FFmpeg's arguments). The FFmpeg binary is
+    /// still resolved beforehand (see [`Settings::ffmpeg_location`]). By default (or if the
+    /// wrapper is empty), FFmpeg is run directly.
+    ///
+    /// NOTE: The wrapper must run FFmpeg in the foreground, and forward its `stdout` and
+    /// `stderr` output, which the [`Converter`] reads, as well as its exit code.
+    pub fn command_wrapper(self, command_wrapper: Vec<std::ffi::OsString>) -> Self {
+        Self {
+            command_wrapper,
+            ..self
+        }
+    }
+
+    /// A setter method that allows registering a callback, as an escape hatch for what the
+    /// other options will never cover, which is given the fully built FFmpeg arguments of the
+    /// conversion job (i.e. without the program and the [`Settings::command_wrapper`]) right
+    /// before FFmpeg is spawned, and which can reorder, insert or remove arguments. The edited
+    /// arguments are the ones FFmpeg gets, and the ones the job logs. The hook is called once
+    /// per FFmpeg run of the conversion (i.e. once per attempt with [`Settings::max_output_bytes`]),
+    /// but not for the auxiliary child processes (e.g. probing the video's duration). A hook that
+    /// panics fails the job with [`SettingsError::ArgvHookPanicked`].
+    ///
+    /// NOTE: Editing the arguments voids some of the [`Converter`]'s guarantees, e.g. the
+    /// progress is no longer reported if `-stats` is removed, the animated GIF is only read
+    /// from `stdout` (i.e. the `-` output), and [`Settings::plan`] does not reflect the edits.
+    /// The jobs using a hook also bypass the cache (see [`Converter::cache`]).
+    pub fn argv_hook(self, hook: impl Fn(&mut Vec<std::ffi::OsString>) + Send + 'static) -> Self {
+        Self {
+            argv_hook: Some(argv::ArgvHook::new(hook)),
+            ..self
+        }
+    }
+
+    /// A setter method that allows checking, before the job starts, that the FFmpeg binary
+    /// has the filters and encoders required by the settings (e.g. the `subtitles` filter,
+    /// which FFmpeg only has when built with libass, see [`Settings::subtitles`]), so that
+    /// a missing one is reported using [`Error::MissingCapability`], along with an actionable
+    /// hint, instead of an opaque error. The binary's filters and encoders are listed (using
+    /// `ffmpeg -filters` and `ffmpeg -encoders`) once per binary path, for the whole process.
+    /// By default, the binary's capabilities are not checked.
+    ///
+    /// NOTE: If the lists cannot be obtained (or are not recognized), the job runs anyway.
+    pub fn check_capabilities(self, check_capabilities: bool) -> Self {
+        Self {
+            check_capabilities,
+            ..self
+        }
+    }
+
+    /// A setter method that allows telling FFmpeg the format of the input (e.g. its
+    /// demuxer, pixel format, frame size and frame rate), for inputs whose format it
+    /// cannot find by itself, such as a file of raw frames (see [`InputFormatHints::raw_video`]).
+    /// The hints are passed to every FFmpeg child process that reads the input.
+    ///
+    /// NOTE: With the `rawvideo` demuxer, both the pixel format and the frame size must
+    /// be provided (see [`SettingsError::IncompleteRawVideoHints`]).
+    pub fn input_format_hints(self, input_format_hints: InputFormatHints) -> Self {
+        Self {
+            input_format_hints: Some(input_format_hints),
+            ..self
+        }
+    }
+
+    /// A setter method that allows converting the video stream at the given (zero-based)
+    /// `index` among the input's video streams (e.g. the camera's, in a screen recording
+    /// holding both the screen and the camera), instead of the one FFmpeg considers the best
+    /// (usually the one with the highest resolution). The stream is selected by every FFmpeg
+    /// child process that decodes the frames (i.e. the conversion and the palette analysis).
+    ///
+    /// NOTE: If the input has no such stream, the job fails with [`Error::VideoStreamNotFound`].
+    pub fn video_stream_index(self, index: u32) -> Self {
+        Self {
+            video_stream_index: Some(index),
+            ..self
+        }
+    }
+
+    /// A setter method that makes FFmpeg decode the video using the given hardware
+    /// acceleration method (see [`HwAccel`]), e.g. for high resolution screen recordings,
+    /// whose decoding is otherwise the bottleneck of the conversion. Only the conversion's
+    /// FFmpeg child process uses it (i.e. not the probes). With [`HwAccel::Auto`], the
+    /// method is picked from those listed by `ffmpeg -hwaccels`, which is run once per
+    /// FFmpeg binary.
+    ///
+    /// NOTE: If the method cannot be set up (e.g. the machine has no such GPU), the job
+    /// is handled according to [`Settings::hwaccel_fallback`].
+    pub fn hwaccel(self, hwaccel: HwAccel) -> Self {
+        Self {
+            hwaccel: Some(hwaccel),
+            ..self
+        }
+    }
+
+    /// A setter method that allows choosing what happens when FFmpeg reports that the
+    /// method provided using [`Settings::hwaccel`] could not be set up, i.e. whether the
+    /// job is run again without acceleration (the default) or fails with
+    /// [`Error::HardwareAcceleration`].
+    ///
+    /// NOTE: Running the job again reads the input again, so an input that can only be read
+    /// once (see [`InputSource::from_reader`]) requires [`HwAccelFallback::Fail`] (see
+    /// [`SettingsConflict::ReadOnceInput`]).
+    pub fn hwaccel_fallback(self, hwaccel_fallback: HwAccelFallback) -> Self {
+        Self {
+            hwaccel_fallback,
+            ..self
+        }
+    }
+
+    /// The largest allowed value for [`Settings::threads`].
+    pub const MAX_THREADS: u16 = 512;
+
+    /// A setter method that allows specifying the number of threads (at most
+    /// [`Settings::MAX_THREADS`]) used by FFmpeg to decode the video and to run the filter
+    /// graph, e.g. 1 or 2 to keep the conversions from taking over a shared server. By
+    /// default (or with 0), FFmpeg decides, usually using every CPU core.
+    pub fn threads(self, threads: u16) -> Self {
+        Self { threads, ..self }
+    }
+
+    /// The arguments that make FFmpeg read the input (i.e. its `-i` flag, preceded by
+    /// the options given by [`Settings::input_format_hints`], if any).
+    fn input_args(&self) -> Vec<String> {
+        input_format::input_args(self.input_format_hints.as_ref(), &self.input)
+    }
+
+    /// A setter method that allows specifying the animated GIF's height (e.g. so that
+    /// all the GIFs of a gallery have the same dimensions, whatever the aspect ratio of
+    /// their source video), in which case the video's frames are fitted into the GIF's
+    /// width and height according to [`Settings::fit_mode`]. By default, the height
+    /// follows the video's aspect ratio.
+    pub fn gif_height(self, gif_height: u16) -> Self {
+        Self {
+            gif_height: Some(gif_height),
+            ..self
+        }
+    }
+
+    /// A setter method that allows specifying how the video's frames are fitted into
+    /// the animated GIF's dimensions when its height is provided (see [`Settings::gif_height`]),
+    /// i.e. stretched, letterboxed (the default) or cropped (see [`FitMode`]). It has no
+    /// effect otherwise.
+    pub fn fit_mode(self, fit_mode: FitMode) -> Self {
+        Self { fit_mode, ..self }
+    }
+
+    /// A setter method that allows selecting the algorithm used to resize the video's frames
+    /// (see [`ScaleAlgorithm`]), e.g. [`ScaleAlgorithm::Lanczos`] to keep downscaled screen
+    /// text legible. By default, FFmpeg's own default (i.e. bilinear) is used.
+    pub fn scale_algorithm(self, scale_algorithm: ScaleAlgorithm) -> Self {
+        Self {
+            scale_algorithm: Some(scale_algorithm),
+            ..self
+        }
+    }
+
+    /// A setter method that allows rounding the height that follows the video's aspect ratio
+    /// to an even number (see [`EvenDimensionPolicy`]). It has no effect when the height is
+    /// provided (see [`Settings::gif_height`]). By default, the height is rounded to the
+    /// nearest integer.
+    pub fn even_dimension_policy(self, even_dimension_policy: EvenDimensionPolicy) -> Self {
+        Self {
+            even_dimension_policy,
+            ..self
+        }
+    }
+
+    /// A setter method that allows rotating the video's frames (e.g. for a video recorded
+    /// by a phone held upright), which is done before they are resized, so that the width
+    /// provided using [`Settings::with_standard_fps`] (and the height provided using
+    /// [`Settings::gif_height`]) is still that of the animated GIF. By default, the frames
+    /// are not rotated.
+    ///
+    /// NOTE: The regions of [`Settings::crop_keyframes`] are cropped before the rotation,
+    /// so they are still expressed in the video's own coordinates.
+    pub fn rotation(self, rotation: Rotation) -> Self {
+        Self {
+            rotation: Some(rotation),
+            ..self
+        }
+    }
+
+    /// A setter method that allows mirroring the video's frames horizontally (e.g. for a
+    /// video recorded by a phone's front camera, which comes out reversed). Disabled by default.
+    ///
+    /// NOTE: The frames are mirrored after being rotated (see [`Settings::rotation`]), i.e. the
+    /// mirroring is relative to the animated GIF's own orientation.
+    pub fn hflip(self, hflip: bool) -> Self {
+        Self { hflip, ..self }
+    }
+
+    /// A setter method that allows flipping the video's frames vertically (i.e. upside down),
+    /// after [`Settings::hflip`], if enabled. Disabled by default.
+    pub fn vflip(self, vflip: bool) -> Self {
+        Self { vflip, ..self }
+    }
+
+    /// The largest allowed value for [`Settings::saturation`].
+    pub const MAX_SATURATION: f32 = 3.0;
+
+    /// A setter method that allows changing the saturation of the video's frames, e.g. 0.0
+    /// for a grayscale animated GIF, or 0.5 for a muted one, which must be between 0.0 and
+    /// [`Settings::MAX_SATURATION`] (see [`SettingsError::InvalidSaturation`]). The default
+    /// value is 1.0 (i.e. the video's saturation), in which case the frames are left untouched.
+    ///
+    /// NOTE: The colors are adjusted before the palette is generated, so a grayscale animated
+    /// GIF only spends its palette on shades of gray.
+    pub fn saturation(self, saturation: f32) -> Self {
+        Self { saturation, ..self }
+    }
+
+    /// A setter method that allows brightening (or darkening) the video's frames, e.g. 0.2 for
+    /// dark footage, which must be between -1.0 and 1.0 (see [`SettingsError::InvalidBrightness`]).
+    /// The default value is 0.0, in which case the brightness is left untouched.
+    ///
+    /// NOTE: Rendered along with [`Settings::contrast`] and [`Settings::saturation`] as a single
+    /// `eq` filter.
+    pub fn brightness(self, brightness: f32) -> Self {
+        Self { brightness, ..self }
+    }
+
+    /// The largest allowed value for [`Settings::contrast`].
+    pub const MAX_CONTRAST: f32 = 4.0;
+
+    /// A setter method that allows changing the contrast of the video's frames, which must be
+    /// between 0.0 and [`Settings::MAX_CONTRAST`] (see [`SettingsError::InvalidContrast`]). The
+    /// default value is 1.0 (i.e. the video's contrast), in which case it is left untouched.
+    pub fn contrast(self, contrast: f32) -> Self {
+        Self { contrast, ..self }
+    }
+
+    /// A setter method that allows overlaying an image (e.g. a small PNG logo) at the given
+    /// `position` of the animated GIF's frames, with the given `opacity`, which must be between
+    /// 0.0 (i.e. invisible) and 1.0 (i.e. opaque, see [`SettingsError::InvalidWatermarkOpacity`]).
+    /// The image is overlaid once the frames have been scaled, at its own size, and FFmpeg
+    /// reads it as a second input. By default, no image is overlaid.
+    ///
+    /// NOTE: A missing image is reported using [`Error::WatermarkFile`], before the job starts.
+    pub fn watermark(self, path: std::path::PathBuf, position: Position, opacity: f32) -> Self {
+        Self {
+            watermark: Some(watermark::Watermark {
+                path,
+                position,
+                opacity,
+            }),
+            ..self
+        }
+    }
+
+    /// A setter method that allows burning the subtitles of an external file (e.g. an SRT
+    /// file) into the animated GIF's frames, using FFmpeg's `subtitles` filter, before the
+    /// frames are scaled (but after they are cropped). The subtitles' timestamps are those of
+    /// the source video, even when only part of it is converted (see [`Settings::clip`]). By
+    /// default, no subtitles are burned in.
+    ///
+    /// NOTE: A subtitles file that FFmpeg cannot open (or parse) makes the job fail with
+    /// [`Error::SubtitlesFile`].
+    pub fn subtitles(self, path: std::path::PathBuf) -> Self {
+        Self {
+            subtitles: Some(path),
+            ..self
+        }
+    }
+
+    /// A setter method that allows running a quick preview pass before the job itself, which
+    /// converts the same part of the video at the given (smaller) width, and whose animated
+    /// GIF is sent using [`Message::Preview`], e.g. so that the application has something to
+    /// show during a long conversion. The preview has the animated GIF's options (e.g. its
+    /// frame rate and colors), except for those that produce other outputs (e.g.
+    /// [`Settings::additional_widths`] or [`Settings::output_file`]), and its height follows
+    /// the video's aspect ratio. By default, no preview is generated.
+    ///
+    /// NOTE: A failed (or cancelled) preview pass fails the job, whose error is sent using
+    /// [`Message::Error`] as usual.
+    pub fn preview_width(self, preview_width: u16) -> Self {
+        Self {
+            preview_width: Some(preview_width),
+            ..self
+        }
+    }
+
+    /// A setter method that allows stamping a badge (e.g. `rendering…`) onto the frames of
+    /// the preview (see [`Settings::preview_width`]), using FFmpeg's `drawtext` filter once the
+    /// frames have been scaled, while the animated GIF itself is left untouched. By default,
+    /// no badge is stamped.
+    pub fn badge_overlay(self, badge_overlay: Text) -> Self {
+        Self {
+            badge_overlay: Some(badge_overlay),
+            ..self
+        }
+    }
+
+    /// The settings of the preview pass (see [`Settings::preview_width`]), if any, i.e. the
+    /// same conversion at the preview's width, whose only output is sent using
+    /// [`Message::Preview`], and which gets its own filters (see [`preview::Pass`]).
+    pub(crate) fn preview_settings(&self) -> Option<Settings> {
+        let width = self.preview_width?;
+        Some(Settings {
+            gif_width: width,
+            gif_height: None,
+            fit_mode: FitMode::default(),
+            auto_colors: false,
+            additional_widths: vec![],
+            max_output_bytes: None,
+            emit_frame_map: false,
+            output_file: None,
+            output_path: None,
+            preview_width: None,
+            pass: preview::Pass::Preview,
+            ..self.clone()
+        })
+    }
+
+    /// A setter method that allows making sure that the last frame of
+    /// the source video always appears in the animated GIF.
+    ///
+    /// NOTE: Depending on how the video's duration lines up with the
+    /// GIF's frame interval, FFmpeg's `fps` filter can drop the source's
+    /// final frame when resampling. When this option is enabled, the
+    /// last frame is cloned for one output frame interval (using the
+    /// `tpad` filter) before resampling, so that it always gets picked.
+    pub fn preserve_last_frame(self, preserve_last_frame: bool) -> Self {
+        Self {
+            preserve_last_frame,
+            ..self
+        }
+    }
+
+    /// A setter method that allows making the animated GIF's duration match the video's,
+    /// when the frame interval (i.e. `100 / gif_fps` centiseconds, the unit of the GIF's
+    /// frame delays) is not a whole number of centiseconds. Disabled by default.
+    ///
+    /// NOTE: When this option is enabled, the delays of the output's frames are rewritten
+    /// (see [`gif_info::GifData::set_frame_rate`]) so that they alternate (e.g. 7, 7 and 6
+    /// centiseconds at 15 fps), the total duration then being within a centisecond of the
+    /// exact one, instead of drifting by up to a centisecond per frame (e.g. when all the
+    /// delays are rounded the same way).
+    pub fn interpolate_frame_delays(self, interpolate_frame_delays: bool) -> Self {
+        Self {
+            interpolate_frame_delays,
+            ..self
+        }
+    }
+
+    /// The smallest allowed value for [`Settings::max_colors`].
+    pub const MIN_COLORS: u16 = 2;
+    /// The largest allowed value for [`Settings::max_colors`] (i.e. FFmpeg's default).
+    pub const MAX_COLORS: u16 = 256;
+
+    /// A setter method that allows specifying the maximum number of colors
+    /// (between [`Settings::MIN_COLORS`] and [`Settings::MAX_COLORS`]) in the
+    /// animated GIF's palette. Fewer colors usually result in a smaller file.
+    pub fn max_colors(self, max_colors: u16) -> Self {
+        Self {
+            max_colors: Some(max_colors),
+            ..self
+        }
+    }
+
+    /// A setter method that allows letting the [`Converter`] pick the number
+    /// of colors in the animated GIF's palette, based on a cheap analysis of
+    /// a few sampled frames run before the conversion itself. The selected value
+    /// is sent to the application using [`Message::ColorCountSelected`], and
+    /// overrides any value provided using [`Settings::max_colors`].
+    ///
+    /// NOTE: The analysis decodes at most the first 8 seconds of the video
+    /// (one frame per second, downscaled to 64 pixels wide), so its overhead
+    /// is bounded and usually small compared to the conversion itself. If the
+    /// analysis fails, FFmpeg's default palette size (i.e. 256 colors) is used.
+    pub fn auto_colors(self, auto_colors: bool) -> Self {
+        Self {
+            auto_colors,
+            ..self
+        }
+    }
+
+    /// The default value for [`Settings::auxiliary_timeout`].
+    pub const DEFAULT_AUXILIARY_TIMEOUT: std::time::Duration = std::time::Duration::from_secs(10);
+
+    /// A setter method that allows limiting how long each of the short-lived FFmpeg child
+    /// processes that run before the job (i.e. the probing of the video's duration required
+    /// by [`Settings::clip`], and the palette analysis pass used by [`Settings::auto_colors`])
+    /// may run, after which it gets killed, so that a crafted or corrupt input cannot make
+    /// the job hang before it even starts. The default value is
+    /// [`Settings::DEFAULT_AUXILIARY_TIMEOUT`], and `None` disables the timeout.
+    ///
+    /// When the palette analysis pass times out, FFmpeg's default palette size is used,
+    /// while the job fails with [`Error::Probe`] (containing [`ProbeError::Timeout`]) when
+    /// probing the video's duration times out.
+    pub fn auxiliary_timeout(self, auxiliary_timeout: Option<std::time::Duration>) -> Self {
+        Self {
+            auxiliary_timeout,
+            ..self
+        }
+    }
+
+    /// A setter method that allows providing the point in time by which the whole job
+    /// must be done (e.g. when the user's upload slot expires), after which the job is
+    /// stopped (just like when cancelled) and fails with [`Error::DeadlineExceeded`].
+    /// A deadline that has already passed when the job starts makes it fail before any
+    /// FFmpeg child process gets spawned.
+    ///
+    /// The deadline also bounds the short-lived child processes that run before the job,
+    /// along with [`Settings::auxiliary_timeout`], whichever comes first winning: the job
+    /// fails with [`Error::DeadlineExceeded`] if the deadline passes first (even during
+    /// the palette analysis pass, which otherwise falls back to the default palette size
+    /// when it times out), and as described by [`Settings::auxiliary_timeout`] otherwise.
+    pub fn deadline(self, deadline: std::time::Instant) -> Self {
+        Self {
+            deadline: Some(deadline),
+            ..self
+        }
+    }
+
+    /// A setter method that allows bounding the memory used by the job (e.g. in a
+    /// multi-tenant service), i.e. by the buffers that grow with it, to `bytes`:
+    ///
+    /// * the animated GIF being read from FFmpeg's `stdout` is moved to a temporary file
+    ///   once keeping it in memory would exceed the budget, and read back (in one go)
+    ///   once complete, to be sent using [`Message::Success`];
+    /// * the lines written by FFmpeg to `stderr` that are kept for [`Warning::DirtyExit`]
+    ///   may use at most an eighth of the budget;
+    /// * at most one progress message (see [`Message::Progress`] and
+    ///   [`Message::InterpolatedProgress`]), and one [`Message::OutputBytes`] message, is
+    ///   sent per step of progress, the number of steps being such that these messages take
+    ///   up at most a sixteenth of the budget, however slowly the application reads the channel.
+    ///
+    /// Each of these measures is reported (once) using [`Warning::MemoryBudgetSpill`]
+    /// as soon as it kicks in. There is no budget by default.
+    pub fn memory_budget(self, bytes: usize) -> Self {
+        Self {
+            memory_budget: Some(bytes),
+            ..self
+        }
+    }
+
+    /// A setter method that allows producing an animated WebP or APNG image instead of an
+    /// animated GIF (see [`OutputFormat`]), e.g. for full color frames, since neither format
+    /// is limited to a palette of 256 colors. The filter graph then skips the generation of
+    /// the palette (so that the palette options are ignored), while the frame rate and the
+    /// scaling still apply, and the bytes sent using [`Message::Success`] are in that format.
+    ///
+    /// NOTE: The animated WebP encoder is only part of FFmpeg builds with libwebp
+    /// (`--enable-libwebp`, see [`Settings::check_capabilities`]). Unlike an animated GIF,
+    /// whose frames are counted, an output in another format is only checked for its
+    /// signature, so that a nonzero exit code always fails the job with [`Error::ExitCode`].
+    pub fn output_format(self, output_format: OutputFormat) -> Self {
+        Self {
+            output_format,
+            ..self
+        }
+    }
+
+    /// A setter method that allows making sure that none of the animated GIF's frames
+    /// has its own (i.e. local) color table, which some decoders (e.g. on older embedded
+    /// devices or e-ink displays) don't support, so that all the frames use the GIF's
+    /// global color table.
+    ///
+    /// NOTE: A single palette is generated for the whole video, which FFmpeg's GIF encoder
+    /// is then told to write to the GIF's header only (using its `global_palette` option).
+    /// That option was added in FFmpeg 5.1, so older versions fail with [`Error::ExitCode`].
+    pub fn global_palette_only(self, global_palette_only: bool) -> Self {
+        Self {
+            global_palette_only,
+            ..self
+        }
+    }
+
+    /// The largest allowed value for [`Settings::max_palette_bit_depth`].
+    pub const MAX_PALETTE_BIT_DEPTH: u8 = 8;
+
+    /// A setter method that allows specifying the maximum number of bits (between 1 and
+    /// [`Settings::MAX_PALETTE_BIT_DEPTH`]) per color index in the animated GIF's palette
+    /// (e.g. 7 for decoders that only handle up to 128 colors), which caps the number of
+    /// colors to `2^max_palette_bit_depth`, whatever the value provided using
+    /// [`Settings::max_colors`] (or selected using [`Settings::auto_colors`]).
+    pub fn max_palette_bit_depth(self, max_palette_bit_depth: u8) -> Self {
+        Self {
+            max_palette_bit_depth: Some(max_palette_bit_depth),
+            ..self
+        }
+    }
+
+    /// A setter method that allows specifying which pixels the animated GIF's palette is
+    /// computed from (see [`PaletteStatsMode`]), e.g. only those that change between frames
+    /// ([`PaletteStatsMode::Diff`]), which gives better colors to a small moving element in
+    /// front of a static background. By default, all the pixels are used.
+    ///
+    /// NOTE: With [`PaletteStatsMode::Single`], each frame gets its own palette, which
+    /// [`Settings::global_palette_only`] does not allow (see
+    /// [`SettingsConflict::PerFramePalettesGlobalPaletteOnly`]).
+    pub fn palette_stats_mode(self, palette_stats_mode: PaletteStatsMode) -> Self {
+        Self {
+            palette_stats_mode,
+            ..self
+        }
+    }
+
+    /// The maximum number of colors in the animated GIF's palette, once capped by
+    /// [`Settings::max_palette_bit_depth`], if it differs from FFmpeg's default.
+    fn effective_max_colors(&self) -> Option<u16> {
+        let cap = self.max_palette_bit_depth.map(|depth| 1u16 << depth);
+        match (self.max_colors, cap) {
+            (Some(n), Some(cap)) => Some(n.min(cap)),
+            (n, None) => n,
+            (None, cap) => cap.filter(|cap| *cap < Self::MAX_COLORS),
+        }
+    }
+
+    /// A setter method that allows making the [`Converter`] emit estimated progress
+    /// values (using [`Message::InterpolatedProgress`]) between the real ones (i.e.
+    /// [`Message::Progress`]), which FFmpeg only reports about twice per second.
+    ///
+    /// NOTE: The estimates are based on the progress rate observed between the last
+    /// two real values, and are bounded so that they stay below the next expected
+    /// real value (assuming that the encode speed does not drop by more than 10%)
+    /// and below 1.0. They are never decreasing, although a real value can be lower
+    /// than the estimate that preceded it if the encode speed drops suddenly.
+    pub fn smooth_progress(self, smooth_progress: bool) -> Self {
+        Self {
+            smooth_progress,
+            ..self
+        }
+    }
+
+    /// A setter method that allows remapping the progress values (see [`Message::Progress`])
+    /// so that they are roughly linear in wall time, using a [`ProgressCurve`] (e.g.
+    /// [`ProgressCurve::CALIBRATED`]). By default ([`ProgressCurve::Linear`]), the progress
+    /// is the ratio of the time reached by FFmpeg to the video's duration, which tends to
+    /// advance quickly while the palette is generated, and then to stall while it gets applied.
+    ///
+    /// NOTE: The share of a [`ProgressCurve::TwoPhase`] curve must be strictly between 0.0
+    /// and 1.0 (see [`SettingsError::InvalidProgressCurve`]). The estimates enabled by
+    /// [`Settings::smooth_progress`] are based on the remapped values.
+    pub fn progress_curve(self, progress_curve: ProgressCurve) -> Self {
+        Self {
+            progress_curve,
+            ..self
+        }
+    }
+
+    /// A setter method that allows converting only a region of the video's frames,
+    /// whose position can change over time (e.g. to follow a moving subject). Each
+    /// keyframe gives the region to use from its timestamp until the timestamp of
+    /// the next one (the first region is also used before its timestamp), and the
+    /// animated GIF's width (see [`Settings::with_standard_fps`]) applies to the
+    /// cropped frames. An empty list (the default) disables cropping.
+    ///
+    /// NOTE: The keyframes must be sorted by strictly increasing timestamps, and the
+    /// region's size must be the same for all of them, since FFmpeg's `crop` filter
+    /// can only move the region (see [`Settings::validate`]). The region is not
+    /// interpolated between keyframes; for smoother motion, more keyframes can be used.
+    pub fn crop_keyframes(self, crop_keyframes: Vec<(std::time::Duration, CropRect)>) -> Self {
+        Self {
+            crop_keyframes,
+            ..self
+        }
+    }
+
+    /// A setter method that allows converting only a still region of the video's frames
+    /// (e.g. a 600x400 window at offset 100,200 in a screen recording), whose top left
+    /// corner is at (`x`, `y`), using FFmpeg's `crop` filter before the frames are scaled
+    /// to the animated GIF's width. This is a shorthand for [`Settings::crop_keyframes`]
+    /// with a single keyframe, and replaces any keyframes provided beforehand.
+    ///
+    /// NOTE: The region's width and height must not be zero (see [`SettingsError::CropRectEmpty`]).
+    /// A region larger than the video's frames makes the job fail with [`Error::CropOutOfBounds`],
+    /// while a region that is only partly outside of them is moved back inside by FFmpeg.
+    pub fn crop(self, x: u32, y: u32, width: u32, height: u32) -> Self {
+        self.crop_keyframes(vec![(
+            std::time::Duration::ZERO,
+            CropRect::new(x, y, width, height),
+        )])
+    }
+
+    /// A setter method that allows making the job fail (with an [`Error::DeniedWarning`]
+    /// error) when one of the lines written by FFmpeg to `stderr` contains one of the
+    /// `patterns` (e.g. `"deprecated pixel format"` or `"non-monotonous DTS"`), in which
+    /// case the job is stopped (just like when cancelled) and the output is thrown away.
+    ///
+    /// NOTE: Only the first 64 KiB of each line are matched against the patterns,
+    /// and an empty pattern is not allowed (see [`SettingsError::InvalidDenyPattern`]).
+    /// With the `regex` feature flag, regular expressions can also be provided using
+    /// [`Settings::deny_stderr_regexes`].
+    pub fn deny_stderr_patterns(self, deny_stderr_patterns: Vec<String>) -> Self {
+        Self {
+            deny_stderr_patterns,
+            ..self
+        }
+    }
+
+    #[cfg(feature = "regex")]
+    /// Same as [`Settings::deny_stderr_patterns`], except that the patterns are regular
+    /// expressions (using the `regex` crate's syntax, whose matching time is linear in
+    /// the length of the line). Only available with the `regex` feature flag.
+    pub fn deny_stderr_regexes(self, deny_stderr_regexes: Vec<String>) -> Self {
+        Self {
+            deny_stderr_regexes,
+            ..self
+        }
+    }
+
+    /// The default value for [`Settings::output_size_warning_ratio`], i.e. the
+    /// warning is emitted as soon as the animated GIF is larger than the video.
+    pub const DEFAULT_OUTPUT_SIZE_WARNING_RATIO: f64 = 1.0;
+
+    /// A setter method that allows specifying the ratio between the animated GIF's size
+    /// and the source video's size (as found before starting the job) above which a
+    /// [`Warning::OutputLargerThanInput`] warning is emitted (right before [`Message::Success`]),
+    /// e.g. so that the application can suggest smaller settings. The default value is
+    /// [`Settings::DEFAULT_OUTPUT_SIZE_WARNING_RATIO`], and `None` disables the warning.
+    ///
+    /// NOTE: The ratio must be positive (see [`SettingsError::InvalidOutputSizeWarningRatio`]).
+    pub fn output_size_warning_ratio(self, output_size_warning_ratio: Option<f64>) -> Self {
+        Self {
+            output_size_warning_ratio,
+            ..self
+        }
+    }
+
+    /// A setter method that allows capping the size of the animated GIF (e.g. to the upload
+    /// limit of a chat application) to `max_output_bytes`: when the animated GIF is larger, it
+    /// is automatically re-encoded with reduced settings (i.e. a smaller width, then a lower
+    /// frame rate, and then fewer colors), up to [`Settings::max_output_attempts`] times in total.
+    /// Each attempt is announced using [`Message::Attempt`], and the smallest animated GIF is
+    /// sent using [`Message::Success`], preceded by [`Warning::OutputSizeTargetMissed`] if it is
+    /// still too large.
+    ///
+    /// NOTE: Each attempt fills its share of the progress bar (e.g. the first quarter, with 4
+    /// attempts), and the progress jumps to 1.0 once the job is done. The other messages (e.g.
+    /// [`Message::VideoDuration`] and [`Message::OutputBytes`]) are sent for each attempt.
+    pub fn max_output_bytes(self, max_output_bytes: u64) -> Self {
+        Self {
+            max_output_bytes: Some(max_output_bytes),
+            ..self
+        }
+    }
+
+    /// The default value for [`Settings::max_output_attempts`].
+    pub const DEFAULT_MAX_OUTPUT_ATTEMPTS: u32 = 4;
+
+    /// A setter method that allows specifying the number of attempts (including the first
+    /// one, and which must not be zero, see [`SettingsError::MaxOutputAttemptsZero`]) made to
+    /// fit within [`Settings::max_output_bytes`]. The default value is
+    /// [`Settings::DEFAULT_MAX_OUTPUT_ATTEMPTS`].
+    pub fn max_output_attempts(self, max_output_attempts: u32) -> Self {
+        Self {
+            max_output_attempts,
+            ..self
+        }
+    }
+
+    /// A setter method that allows converting only part of the source video (e.g. its
+    /// first or last few seconds), instead of the whole video. The progress values
+    /// (see [`Message::Progress`]) are then relative to the selected part.
+    ///
+    /// NOTE: For [`ClipSelection::FromEnd`] and [`ClipSelection::Middle`], the video's
+    /// duration is probed (using a short-lived FFmpeg child process) before starting
+    /// the job, which fails with [`SettingsError::ClipRequiresDuration`] if the duration
+    /// cannot be found.
+    pub fn clip(self, clip: ClipSelection) -> Self {
+        Self {
+            clip: Some(clip),
+            ..self
+        }
+    }
+
+    /// A setter method that allows choosing how FFmpeg seeks to the start of the part of the
+    /// source video selected using [`Settings::clip`], trading speed for accuracy (see
+    /// [`SeekMode`]). Defaults to [`SeekMode::FastThenAccurate`].
+    pub fn seek_mode(self, seek_mode: SeekMode) -> Self {
+        Self { seek_mode, ..self }
+    }
+
+    /// A setter method that allows converting only the part of the source video between
+    /// `start` and `end` (i.e. a [`ClipSelection::Range`], see [`Settings::clip`]), which
+    /// can be given either as [`std::time::Duration`]'s or as time specs typed by a user
+    /// (e.g. `"90"`, `"1:30"` or `"00:01:30.5"`, see [`parse_time_spec`]).
+    ///
+    /// NOTE: A time spec that cannot be parsed is reported by [`Settings::validate`]
+    /// (see [`SettingsError::InvalidTimeSpec`]).
+    pub fn trim<S, E>(self, start: S, end: E) -> Self
+    where
+        S: TryInto<TimeSpec>,
+        S::Error: Into<TimeSpecError>,
+        E: TryInto<TimeSpec>,
+        E::Error: Into<TimeSpecError>,
+    {
+        match (time_parsing::time_spec(start), time_parsing::time_spec(end)) {
+            (Ok(start), Ok(end)) => self.clip(ClipSelection::Range(start, end)),
+            (Err(e), _) | (_, Err(e)) => self.with_invalid_time_spec(e),
+        }
+    }
+
+    /// Records `e`, unless a time spec was already found to be invalid (see
+    /// [`SettingsError::InvalidTimeSpec`]).
+    fn with_invalid_time_spec(self, e: TimeSpecError) -> Self {
+        Self {
+            invalid_time_spec: self.invalid_time_spec.or(Some(e)),
+            ..self
+        }
+    }
+
+    /// A setter method that allows converting only the part of the source video that
+    /// starts at `start_time` (i.e. a [`ClipSelection::StartingAt`], see [`Settings::clip`]),
+    /// until the end of the video (e.g. to skip a screen recording's first few seconds).
+    /// The progress values (see [`Message::Progress`]) are then relative to the remaining
+    /// duration of the video, from `start_time` onward.
+    ///
+    /// When combined with [`Settings::clip_duration`] or [`Settings::end_time`] (in any order),
+    /// the part starts at `start_time` and keeps the given length or end, respectively.
+    ///
+    /// Like [`Settings::trim`], `start_time` can be given either as a [`std::time::Duration`]
+    /// or as a time spec (e.g. `"1:30"`).
+    ///
+    /// NOTE: When `start_time` is past the end of the video, the job fails with
+    /// [`Error::ClipOutOfRange`]. A time spec that cannot be parsed (or an end that overflows)
+    /// is reported by [`Settings::validate`] (see [`SettingsError::InvalidTimeSpec`]).
+    pub fn start_time<T>(self, start_time: T) -> Self
+    where
+        T: TryInto<TimeSpec>,
+        T::Error: Into<TimeSpecError>,
+    {
+        let start_time = match time_parsing::time_spec(start_time) {
+            Ok(start_time) => start_time,
+            Err(e) => return self.with_invalid_time_spec(e),
+        };
+        let clip = match self.clip {
+            Some(ClipSelection::FromStart(length)) => match start_time.checked_add(length) {
+                Some(end) => ClipSelection::Range(start_time, end),
+                None => return self.with_invalid_time_spec(TimeSpecError::Overflow),
+            },
+            Some(ClipSelection::Range(_, end)) => ClipSelection::Range(start_time, end),
+            _ => ClipSelection::StartingAt(start_time),
+        };
+        self.clip(clip)
+    }
+
+    /// A setter method that allows converting only `clip_duration` of the source video,
+    /// starting at the beginning of the video, or at the value provided using
+    /// [`Settings::start_time`] (e.g. 5 seconds starting at 1:30), see [`Settings::clip`].
+    /// The progress values (see [`Message::Progress`]) are then relative to `clip_duration`
+    /// (while [`Message::VideoDuration`] still reports the duration of the whole video).
+    ///
+    /// Like [`Settings::trim`], `clip_duration` can be given either as a
+    /// [`std::time::Duration`] or as a time spec (e.g. `"5"`).
+    ///
+    /// NOTE: The duration must not be zero (see [`SettingsError::EmptyClip`]). A time spec
+    /// that cannot be parsed (or an end that overflows) is reported by [`Settings::validate`]
+    /// (see [`SettingsError::InvalidTimeSpec`]).
+    pub fn clip_duration<T>(self, clip_duration: T) -> Self
+    where
+        T: TryInto<TimeSpec>,
+        T::Error: Into<TimeSpecError>,
+    {
+        let clip_duration = match time_parsing::time_spec(clip_duration) {
+            Ok(clip_duration) => clip_duration,
+            Err(e) => return self.with_invalid_time_spec(e),
+        };
+        let clip = match self.clip_start() {
+            Some(start) => match start.checked_add(clip_duration) {
+                Some(end) => ClipSelection::Range(start, end),
+                None => return self.with_invalid_time_spec(TimeSpecError::Overflow),
+            },
+            None => ClipSelection::FromStart(clip_duration),
+        };
+        self.clip(clip)
+    }
+
+    /// A setter method that allows converting only the part of the source video that ends
+    /// at `end_time`, starting at the beginning of the video, or at the value provided using
+    /// [`Settings::start_time`], see [`Settings::clip`]. The progress values (see
+    /// [`Message::Progress`]) are then relative to the duration of that part.
+    ///
+    /// Like [`Settings::trim`], `end_time` can be given either as a [`std::time::Duration`]
+    /// or as a time spec (e.g. `"1:35"`).
+    ///
+    /// NOTE: The end time must be later than the start time (see [`SettingsError::EmptyClip`]).
+    /// A time spec that cannot be parsed is reported by [`Settings::validate`] (see
+    /// [`SettingsError::InvalidTimeSpec`]).
+    pub fn end_time<T>(self, end_time: T) -> Self
+    where
+        T: TryInto<TimeSpec>,
+        T::Error: Into<TimeSpecError>,
+    {
+        let end_time = match time_parsing::time_spec(end_time) {
+            Ok(end_time) => end_time,
+            Err(e) => return self.with_invalid_time_spec(e),
+        };
+        let start = self.clip_start().unwrap_or_default();
+        self.clip(ClipSelection::Range(start, end_time))
+    }
+
+    /// The start of the part of the video selected using [`Settings::start_time`]
+    /// (or [`Settings::trim`]), if any.
+    fn clip_start(&self) -> Option<std::time::Duration> {
+        match self.clip? {
+            ClipSelection::StartingAt(start) | ClipSelection::Range(start, _) => Some(start),
+            _ => None,
+        }
+    }
+
+    /// A setter method that allows limiting the animated GIF to (at most) `output_frame_limit`
+    /// frames (using FFmpeg's `-frames:v` output option), which, unlike [`Settings::clip`]
+    /// (whose timestamps can be off by a frame), gives the exact number of frames (e.g. for
+    /// a seamless loop). The limit counts the animated GIF's frames, i.e. after the video
+    /// is resampled to the GIF's frame rate (so 48 frames are 4.8 seconds of video at
+    /// [`Settings::STANDARD_FPS`]), starting at the beginning of the [`Settings::clip`], if any.
+    /// The progress values (see [`Message::Progress`]) are then relative to the duration
+    /// of those frames, when shorter than the video.
+    ///
+    /// NOTE: The limit must not be zero (see [`SettingsError::OutputFrameLimitZero`]).
+    pub fn output_frame_limit(self, output_frame_limit: u32) -> Self {
+        Self {
+            output_frame_limit: Some(output_frame_limit),
+            ..self
+        }
+    }
+
+    /// A setter method that allows receiving the timestamp (in the source video) of each
+    /// of the animated GIF's frames, using [`Message::FrameMap`] (emitted right before
+    /// [`Message::Success`]), e.g. to let the user click a frame to jump to the same
+    /// point in the video. The timestamps account for [`Settings::clip`]. Disabled by default.
+    pub fn emit_frame_map(self, emit_frame_map: bool) -> Self {
+        Self {
+            emit_frame_map,
+            ..self
+        }
+    }
+
+    /// A setter method that allows passing a bespoke filter graph (e.g. with FFmpeg's `zoompan`
+    /// and `drawbox` filters) verbatim to FFmpeg's `-filter_complex` option, instead of the
+    /// one generated from the settings, while still getting the [`Converter`]'s process
+    /// management, cancellation and progress reporting. The graph must still end in an output
+    /// that the `gif` muxer accepts (e.g. using the `palettegen` and `paletteuse` filters).
+    ///
+    /// NOTE: The options that feed the generated graph (e.g. [`Settings::gif_height`],
+    /// [`Settings::crop`] or [`Settings::speed`]) cannot be combined with this one (see
+    /// [`SettingsConflict::CustomFilterComplexOverrides`]), while the width and the frame rate
+    /// passed to the constructor are then only used by the placeholders of [`Settings::output_file`].
+    pub fn custom_filter_complex(self, custom_filter_complex: impl Into<String>) -> Self {
+        Self {
+            custom_filter_complex: Some(custom_filter_complex.into()),
+            ..self
+        }
+    }
+
+    /// A setter method that allows generating the same animated GIF at other widths (e.g.
+    /// 400 and 800 pixels, along with the width passed to the constructor) in the same job,
+    /// so that the video is only decoded once: the filter graph is split after the frame rate
+    /// is reduced (and after the rotation and the flips), and each width gets its own scaling
+    /// and palette. The height of each one follows [`Settings::gif_height`] proportionally, if
+    /// provided, and the video's aspect ratio otherwise.
+    ///
+    /// Since `stdout` can only carry one of them, FFmpeg writes the others to temporary files
+    /// (removed once read, or when the job fails), each of which is then sent, once FFmpeg
+    /// has exited, using [`Message::SuccessVariant`] (or [`Error::VariantOutput`] if it turns
+    /// out to be missing or invalid), right after [`Message::Success`], which still carries
+    /// the animated GIF at the constructor's width (and the only one written to
+    /// [`Settings::output_file`], if provided).
+    ///
+    /// NOTE: The progress reports the decoding of the video, which all the widths share, and
+    /// the jobs using this option bypass the cache (see [`Converter::cache`]). The widths must
+    /// differ from each other, and from the constructor's (see [`SettingsError::DuplicateWidth`]).
+    pub fn additional_widths(self, additional_widths: Vec<u16>) -> Self {
+        Self {
+            additional_widths,
+            ..self
+        }
+    }
+
+    /// A setter method that allows passing arguments that the settings don't model (e.g.
+    /// `-noautorotate`, or an unusual input option) verbatim to FFmpeg, right before its
+    /// `-i` flag (i.e. after the options of [`Settings::input_format_hints`], if any),
+    /// so that they apply to the input.
+    ///
+    /// NOTE: The arguments are neither checked nor escaped, so they must not come from
+    /// untrusted sources, and FFmpeg rejects (see [`Error::ExitCode`]) those it doesn't know.
+    pub fn extra_input_args(self, extra_input_args: Vec<std::ffi::OsString>) -> Self {
+        Self {
+            extra_input_args,
+            ..self
+        }
+    }
+
+    /// A setter method that allows passing arguments that the settings don't model (e.g.
+    /// `-copyts`, or a muxer option) verbatim to FFmpeg, right before the output (i.e. after
+    /// `-f gif`, and repeated for each of the [`Settings::additional_widths`], if any), so
+    /// that they apply to the output.
+    ///
+    /// NOTE: As with [`Settings::extra_input_args`], the arguments are neither checked nor
+    /// escaped, and they must not redirect the output, which the [`Converter`] reads from `stdout`.
+    pub fn extra_output_args(self, extra_output_args: Vec<std::ffi::OsString>) -> Self {
+        Self {
+            extra_output_args,
+            ..self
+        }
+    }
+
+    /// A setter method that allows receiving the lifecycle events of the [`Converter`]'s
+    /// own threads and child process (e.g. when a thread starts, exits or is joined), using
+    /// [`Message::Internal`], e.g. for diagnostics, without enabling debug logging. Disabled
+    /// by default.
+    pub fn emit_internal_events(self, emit_internal_events: bool) -> Self {
+        Self {
+            emit_internal_events,
+            ..self
+        }
+    }
+
+    /// A setter method that allows tracing the duration and the progress back to the exact
+    /// lines of FFmpeg's `stderr` output from which they were parsed (e.g. to debug a wrong
+    /// progress reported in the field): each parsed value is logged at the debug level, along
+    /// with its line (see [`PROVENANCE_LOG_TARGET`]), and the last `records` of them are
+    /// reported using [`Summary::provenance`]. Disabled by default.
+    pub fn parse_provenance(self, records: usize) -> Self {
+        Self {
+            parse_provenance: Some(records),
+            ..self
+        }
+    }
+
+    /// A setter method that allows making the job fail (with an [`Error::ParseFailure`]
+    /// error) when FFmpeg's `stderr` output cannot be parsed, i.e. when no duration is found
+    /// in the first few KiB of output (or before the first stats line), or when the time of
+    /// a `frame=` stats line cannot be parsed, e.g. so that a CI job catches a new FFmpeg
+    /// version that changed the format of its output. It also makes the job fail (with an
+    /// [`Error::SuspectOutput`] error) when the stats lines betray a broken output.
+    ///
+    /// By default, such problems are only logged (as warnings), and the job runs anyway,
+    /// without reporting its progress (a suspect output being delivered after a
+    /// [`Warning::SuspectOutput`] warning).
+    pub fn strict_parsing(self, strict_parsing: bool) -> Self {
+        Self {
+            strict_parsing,
+            ..self
+        }
+    }
+
+    /// The smallest allowed value for [`Settings::speed`].
+    pub const MIN_SPEED: f32 = 0.1;
+    /// The largest allowed value for [`Settings::speed`].
+    pub const MAX_SPEED: f32 = 10.0;
+
+    /// A setter method that allows changing the playback speed of the animated GIF (e.g. 2.0
+    /// for a sped-up timelapse of a long screen capture, or 0.5 for a slow motion one), which
+    /// must be between [`Settings::MIN_SPEED`] and [`Settings::MAX_SPEED`] (see
+    /// [`SettingsError::InvalidSpeed`]). The default value is 1.0 (i.e. the video's speed).
+    ///
+    /// NOTE: The frame rate of the animated GIF does not change, so at twice the speed, half
+    /// as many of the video's frames are kept. The timestamps of [`Settings::crop_keyframes`]
+    /// and [`Settings::clip`] are still those of the source video.
+    pub fn speed(self, speed: f32) -> Self {
+        Self { speed, ..self }
+    }
+
+    /// A setter method that allows keeping only one of every `frame_step` frames of the video
+    /// (e.g. every 30th frame, for a compact summary of an hour-long recording), which must
+    /// not be zero (see [`SettingsError::FrameStepZero`]). By default, all the frames are kept.
+    ///
+    /// NOTE: The kept frames are retimed as if they were consecutive frames of the video (i.e.
+    /// the video plays `frame_step` times as fast, like with [`Settings::speed`]), after which
+    /// the GIF's frame rate applies as usual. Every kept frame therefore ends up in the animated
+    /// GIF only if its frame rate is at least that of the video (e.g. a 30 fps video, with a
+    /// `frame_step` of 30 and a 10 fps GIF, ends up with one of every 90 frames). The timestamps
+    /// (e.g. of [`Settings::crop_keyframes`]) are still those of the source video, and those of
+    /// [`Message::FrameMap`] assume a constant frame rate.
+    pub fn frame_step(self, frame_step: u32) -> Self {
+        Self {
+            frame_step: Some(frame_step),
+            ..self
+        }
+    }
+
+    /// The playback speed of the animated GIF, relative to the source video's, once the
+    /// frames have been decimated (see [`Settings::frame_step`]).
+    fn effective_speed(&self) -> f32 {
+        self.speed * self.frame_step.unwrap_or(1) as f32
+    }
+
+    /// A setter method that allows making the animated GIF play forward, then backward
+    /// (i.e. a "boomerang"), so that it loops seamlessly. The animated GIF is then twice as
+    /// long, which is reflected by [`Message::VideoDuration`] (whose value is doubled) and
+    /// by the progress values. Disabled by default.
+    ///
+    /// NOTE: FFmpeg buffers all the frames to reverse them, which is done once they have
+    /// been resized (see [`Settings::with_standard_fps`]) and their rate reduced, so that
+    /// the memory used stays proportional to the size of the animated GIF itself.
+    pub fn boomerang(self, boomerang: bool) -> Self {
+        Self { boomerang, ..self }
+    }
+
+    /// A setter method that allows writing the animated GIF to a file in `dir` (in addition
+    /// to sending it using [`Message::Success`]), whose name is expanded from the `naming`
+    /// template (e.g. `{stem}_{width}px.gif`, see [`OutputNaming`]). The file's path is sent
+    /// using [`Message::Saved`], right before [`Message::Success`].
+    ///
+    /// NOTE: The template is expanded by [`Settings::validate`] (see [`SettingsError::OutputNaming`]),
+    /// and the collisions are checked before the job starts, so that a job skipped by
+    /// [`CollisionPolicy::Skip`] completes right away with [`Message::Skipped`]. The job fails
+    /// with [`Error::OutputFile`] if the file cannot be written (e.g. if `dir` does not exist).
+    pub fn output_file(self, dir: impl Into<std::path::PathBuf>, naming: OutputNaming) -> Self {
+        Self {
+            output_file: Some((dir.into(), naming)),
+            ..self
+        }
+    }
+
+    /// A setter method that allows FFmpeg to write the animated GIF directly to the file at
+    /// `path` (which it overwrites, if any), instead of sending it to `stdout`, e.g. for
+    /// animated GIFs too large to be kept in memory: the output is then not buffered at all,
+    /// and the job completes with [`Message::SuccessFile`] instead of [`Message::Success`].
+    /// By default, the animated GIF is kept in memory.
+    ///
+    /// NOTE: The file is checked (i.e. created) before FFmpeg is spawned, so that a missing
+    /// directory or a lack of permission fails the job right away (see
+    /// [`Error::OutputDirectoryNotFound`] and [`Error::OutputPermissionDenied`]), and a file
+    /// that is missing or empty once FFmpeg exits fails it with [`Error::EmptyOutputFile`].
+    /// The file is removed when the job fails (unless it existed before). Only the animated
+    /// GIF's signature and trailer are checked (so that the file is never read in full), and
+    /// the options that need its bytes (e.g. [`Settings::emit_frame_map`]) cannot be used
+    /// (see [`SettingsConflict::OutputPathOverrides`]). Since the job sends no bytes,
+    /// [`wait_for_result`] is not meant for it.
+    pub fn output_path(self, path: impl Into<std::path::PathBuf>) -> Self {
+        Self {
+            output_path: Some(path.into()),
+            ..self
+        }
+    }
+
+    /// The file to which the animated GIF is written (see [`Settings::output_file`]), once
+    /// its name has been expanded (and validated).
+    fn resolved_output_file(&self) -> Option<Result<output_naming::OutputFile, NamingError>> {
+        let (dir, naming) = self.output_file.as_ref()?;
+        Some(
+            naming
+                .file_name(self)
+                .map(|file_name| output_naming::OutputFile {
+                    dir: dir.clone(),
+                    naming: naming.clone(),
+                    file_name,
+                }),
+        )
+    }
+
+    /// What the timestamps reported by [`Message::FrameMap`] are derived from, if
+    /// enabled (see [`Settings::emit_frame_map`]), and if the clip (if any) does not
+    /// depend on the video's duration (i.e. once resolved).
+    fn frame_map(&self) -> Option<frame_map::FrameMap> {
+        if !self.emit_frame_map {
+            return None;
+        }
+        let (start, end) = match self.clip {
+            Some(clip) => {
+                let (start, length) = clip.bounds(None)?;
+                (start, length.map(|length| start + length))
+            }
+            None => (std::time::Duration::ZERO, None),
+        };
+        Some(frame_map::FrameMap {
+            fps: self.gif_fps,
+            speed: self.effective_speed(),
+            start,
+            end,
+            boomerang: self.boomerang,
+        })
+    }
+
+    /// The duration of the frames allowed by [`Settings::output_frame_limit`], if any.
+    fn output_frame_limit_duration(&self) -> Option<std::time::Duration> {
+        let frames = self.output_frame_limit?;
+        Some(std::time::Duration::from_secs_f64(
+            frames as f64 / self.gif_fps as f64,
+        ))
+    }
+
+    /// Builds the patterns provided using [`Settings::deny_stderr_patterns`]
+    /// (and [`Settings::deny_stderr_regexes`]).
+    fn deny_patterns(&self) -> Result<Vec<deny::DenyPattern>, SettingsError> {
+        deny::build_patterns(
+            &self.deny_stderr_patterns,
+            #[cfg(feature = "regex")]
+            &self.deny_stderr_regexes,
+        )
+    }
+
+    /// Makes sure that the settings are valid, returning the first problem
+    /// found otherwise. This method is called by [`Converter::convert`] before
+    /// starting the job, but can also be called by the application beforehand.
+    pub fn validate(&self) -> Result<(), SettingsError> {
+        if !self.input.is_supported() {
+            return Err(SettingsError::UnsupportedInput);
+        }
+        if let InputSource::Url(url) = &self.input {
+            url.validate().map_err(SettingsError::InvalidUrl)?;
+        }
+        if let Some(e) = self.invalid_time_spec {
+            return Err(SettingsError::InvalidTimeSpec(e));
+        }
+        if let Some(hints) = &self.input_format_hints {
+            hints.validate()?;
+        }
+        if let Some(n) = self.max_colors {
+            if !(Self::MIN_COLORS..=Self::MAX_COLORS).contains(&n) {
+                return Err(SettingsError::MaxColorsOutOfRange(n));
+            }
+        }
+        if let Some(depth) = self.max_palette_bit_depth {
+            if !(1..=Self::MAX_PALETTE_BIT_DEPTH).contains(&depth) {
+                return Err(SettingsError::PaletteBitDepthOutOfRange(depth));
+            }
+        }
+        if self.threads > Self::MAX_THREADS {
+            return Err(SettingsError::ThreadsOutOfRange(self.threads));
+        }
+        crop::validate_keyframes(&self.crop_keyframes)?;
+        self.deny_patterns()?;
+        if let Some(clip) = &self.clip {
+            clip.validate()?;
+        }
+        if self.output_frame_limit == Some(0) {
+            return Err(SettingsError::OutputFrameLimitZero);
+        }
+        if self.frame_step == Some(0) {
+            return Err(SettingsError::FrameStepZero);
+        }
+        if self.max_output_attempts == 0 {
+            return Err(SettingsError::MaxOutputAttemptsZero);
+        }
+        for (i, width) in self.additional_widths.iter().enumerate() {
+            if *width == self.gif_width || self.additional_widths[..i].contains(width) {
+                return Err(SettingsError::DuplicateWidth(*width));
+            }
+        }
+        if !(Self::MIN_SPEED..=Self::MAX_SPEED).contains(&self.speed) {
+            return Err(SettingsError::InvalidSpeed);
+        }
+        if !(0.0..=Self::MAX_SATURATION).contains(&self.saturation) {
+            return Err(SettingsError::InvalidSaturation);
+        }
+        if !(-1.0..=1.0).contains(&self.brightness) {
+            return Err(SettingsError::InvalidBrightness);
+        }
+        if !(0.0..=Self::MAX_CONTRAST).contains(&self.contrast) {
+            return Err(SettingsError::InvalidContrast);
+        }
+        if let Some(watermark) = self.watermark.as_ref() {
+            if !(0.0..=1.0).contains(&watermark.opacity) {
+                return Err(SettingsError::InvalidWatermarkOpacity);
+            }
+        }
+        if !self.progress_curve.is_valid() {
+            return Err(SettingsError::InvalidProgressCurve);
+        }
+        if let OutputFormat::WebP { quality, .. } = self.output_format {
+            if quality > OutputFormat::MAX_WEBP_QUALITY {
+                return Err(SettingsError::WebPQualityOutOfRange(quality));
+            }
+        }
+        if let Some(Err(e)) = self.resolved_output_file() {
+            return Err(SettingsError::OutputNaming(e));
+        }
+        if let Some(ratio) = self.output_size_warning_ratio {
+            if !(ratio.is_finite() && ratio > 0.0) {
+                return Err(SettingsError::InvalidOutputSizeWarningRatio);
+            }
+        }
+        if let Some(conflict) = self
+            .conflicts()
+            .into_iter()
+            .find(|c| c.severity() == ConflictSeverity::Hard)
+        {
+            return Err(SettingsError::Conflict(conflict));
+        }
+        Ok(())
+    }
+
+    /// Returns the conflicts between the options of these settings (e.g. both
+    /// [`Settings::auto_colors`] and [`Settings::max_colors`] being provided), i.e.
+    /// the combinations in which one option overrides, or breaks, another one, so
+    /// that the application can report them (or fix them) before starting the job.
     ///
-    /// NOTE: This is the only allowed value for now; i.e. the API does
-    /// not allow modifying this value.
-    pub const STANDARD_FPS: u16 = 10;
+    /// NOTE: Soft conflicts (see [`ConflictSeverity`]) are resolved as described by
+    /// [`SettingsConflict`]'s variants, and are also reported by the [`Converter`] using
+    /// [`Warning::SettingsConflict`], while hard conflicts make [`Settings::validate`] fail.
+    pub fn conflicts(&self) -> Vec<SettingsConflict> {
+        conflicts::find_conflicts(self)
+    }
 
-    /// A factory method that takes in the source `video_path` and the
-    /// target `width` for the animated GIF.
-    pub fn with_standard_fps(video_path: String, width: u16) -> Self {
+    #[cfg(feature = "tokio")]
+    /// A setter method that allows making the [`Converter`] refuse to run
+    /// (with an [`Error::BlockingInAsyncContext`] error) when [`Converter::convert`]
+    /// is called from a tokio runtime worker thread (e.g. directly inside an
+    /// asynchronous task) instead of from a blocking thread (e.g. inside
+    /// `tokio::task::spawn_blocking`).
+    ///
+    /// By default, the misuse is only reported using a [`Warning::BlockingInAsyncContext`]
+    /// warning, and the job runs anyway (starving the runtime while it does).
+    pub fn strict_async_context(self, strict_async_context: bool) -> Self {
         Self {
-            ffmpeg_path: None,
-            video_path,
-            gif_fps: Self::STANDARD_FPS,
-            gif_width: width,
+            strict_async_context,
+            ..self
         }
     }
 
-    /// A setter method that allows specifying the path to be used
-    /// for the ffmpeg binary.
-    pub fn ffmpeg_path(self, ffmpeg_path: impl Into<String>) -> Self {
-        Self {
-            ffmpeg_path: Some(ffmpeg_path.into()),
-            ..self
+    /// Returns the [`ConversionPlan`] describing what a conversion job started with
+    /// these settings is going to do (i.e. the effective values, after the defaults
+    /// are applied, and the arguments passed to FFmpeg), which can be displayed to
+    /// the user, logged, or compared between two sets of settings.
+    ///
+    /// NOTE: The [`Converter`] builds the FFmpeg command from this same plan, once
+    /// the clip (see [`Settings::clip`]) and the number of colors (see
+    /// [`Settings::auto_colors`]) have been resolved, and with the animated GIFs of
+    /// [`Settings::additional_widths`] (named after their width, e.g. `400px.gif`)
+    /// written to a temporary directory.
+    pub fn plan(&self) -> ConversionPlan {
+        self.plan_in(None)
+    }
+
+    /// Same as [`Settings::plan`], with the animated GIFs of [`Settings::additional_widths`]
+    /// written to `variant_dir`, if provided.
+    pub(crate) fn plan_in(&self, variant_dir: Option<&std::path::Path>) -> ConversionPlan {
+        ConversionPlan {
+            input: self.input.arg(),
+            fps: self.gif_fps,
+            width: self.gif_width,
+            additional_widths: self.additional_widths.clone(),
+            height: self.gif_height,
+            fit_mode: self.gif_height.map(|_| self.fit_mode),
+            scale_algorithm: self.scale_algorithm,
+            even_dimension_policy: self.even_dimension_policy,
+            rotation: self.rotation,
+            hflip: self.hflip,
+            vflip: self.vflip,
+            saturation: self.saturation,
+            brightness: self.brightness,
+            contrast: self.contrast,
+            watermark: self.watermark.as_ref().map(|w| w.path.clone()),
+            subtitles: self.subtitles.clone(),
+            output_format: self.output_format,
+            max_colors: self.effective_max_colors().unwrap_or(Self::MAX_COLORS),
+            auto_colors: self.auto_colors,
+            global_palette_only: self.global_palette_only,
+            palette_stats_mode: self.palette_stats_mode,
+            preserve_last_frame: self.preserve_last_frame,
+            interpolate_frame_delays: self.interpolate_frame_delays,
+            clip: self.clip,
+            output_frame_limit: self.output_frame_limit,
+            speed: self.speed,
+            frame_step: self.frame_step,
+            boomerang: self.boomerang,
+            crop_keyframes: self.crop_keyframes.clone(),
+            dither: plan::DITHER.into(),
+            filter_complex: self.filter_complex(),
+            args: self.generate_args(variant_dir),
+        }
+    }
+
+    /// The arguments passed to the FFmpeg child process (see [`Settings::command_args`]),
+    /// as shown by [`ConversionPlan::args`], i.e. with the arguments that are not valid
+    /// unicode (if any) converted lossily.
+    fn generate_args(&self, variant_dir: Option<&std::path::Path>) -> Vec<String> {
+        Vec::from_iter(
+            self.command_args(variant_dir)
+                .iter()
+                .map(|arg| arg.to_string_lossy().into_owned()),
+        )
+    }
+
+    /// Assembles the arguments passed to the FFmpeg child process that runs
+    /// the conversion job, without spawning it (i.e. `-stats`, the input options and `-i`,
+    /// `-filter_complex`, and the options of each output, followed by the output itself).
+    /// The arguments provided using [`Settings::extra_input_args`] come right before `-i`,
+    /// and those provided using [`Settings::extra_output_args`] right before each output
+    /// (e.g. `-`, i.e. `stdout`, or [`Settings::output_path`], along with `-y`).
+    ///
+    /// NOTE: A [`Settings::clip`] that needs the video's duration must have been
+    /// resolved (see [`ClipSelection::resolve`]) beforehand, or it is ignored. The
+    /// animated GIFs of [`Settings::additional_widths`] are written to `variant_dir`, if
+    /// provided (see [`Settings::plan_in`]), each output (the first one being `stdout`)
+    /// getting its own mapping and options.
+    pub(crate) fn command_args(
+        &self,
+        variant_dir: Option<&std::path::Path>,
+    ) -> Vec<std::ffi::OsString> {
+        let mut args: Vec<std::ffi::OsString> = vec!["-stats".into()];
+        // NOTE: The output file is created before the job (see `Settings::output_path`), so
+        // FFmpeg would otherwise ask whether to overwrite it.
+        if self.output_path.is_some() {
+            args.push("-y".into());
+        }
+        // NOTE: Since the graph is given using `-filter_complex`, FFmpeg only applies the
+        // latter (the former being for the simple graphs, e.g. those given using `-vf`).
+        if self.threads > 0 {
+            for option in ["-filter_threads", "-filter_complex_threads"] {
+                args.extend([option.into(), self.threads.to_string().into()]);
+            }
+        }
+        args.extend(self.seek_args().into_iter().map(std::ffi::OsString::from));
+        if let Some(hwaccel) = self.hwaccel {
+            args.extend(hwaccel.args().map(std::ffi::OsString::from));
+        }
+        if self.threads > 0 {
+            args.extend(["-threads".into(), self.threads.to_string().into()]);
+        }
+        // NOTE: The input arguments end with `-i` and the input itself.
+        let mut input_args = self.input_args();
+        let input = input_args.split_off(input_args.len() - 2);
+        args.extend(input_args.into_iter().map(std::ffi::OsString::from));
+        args.extend(self.extra_input_args.iter().cloned());
+        args.extend(input.into_iter().map(std::ffi::OsString::from));
+        if let Some(watermark) = self.watermark.as_ref() {
+            args.extend(watermark.input_args().map(std::ffi::OsString::from));
+        }
+        args.extend(["-filter_complex".into(), self.filter_complex().into()]);
+        let output = match self
+            .output_path
+            .as_deref()
+            .map(|path| (path, path.to_str()))
+        {
+            None => "-".into(),
+            Some((_, Some(path))) => argv::path_arg(path).into(),
+            Some((path, None)) => path.as_os_str().to_owned(),
+        };
+        let targets = std::iter::once(output).chain(
+            self.additional_widths
+                .iter()
+                .map(|width| variants::path(variant_dir, *width).into_os_string()),
+        );
+        for (i, target) in targets.enumerate() {
+            if !self.additional_widths.is_empty() {
+                args.extend(["-map".into(), format!("[out{}]", i).into()]);
+            }
+            if let Some(frames) = self.output_frame_limit {
+                args.extend(["-frames:v".into(), frames.to_string().into()]);
+            }
+            if self.global_palette_only && self.output_format.uses_palette() {
+                args.extend(["-global_palette".into(), "1".into()]);
+            }
+            args.extend(self.output_format.output_args().into_iter().map(Into::into));
+            args.extend(self.extra_output_args.iter().cloned());
+            args.push(target);
+        }
+        args
+    }
+
+    /// The graph provided using [`Settings::custom_filter_complex`], if any, or the generated one.
+    fn filter_complex(&self) -> String {
+        match &self.custom_filter_complex {
+            Some(graph) => graph.clone(),
+            None => self.generate_filter_complex(),
         }
     }
 
     /// A convenience method that can be used to generate the
     /// value of FFmpeg's `-filter_complex` flag.
     fn generate_filter_complex(&self) -> String {
-        format!(
-            "fps={},scale={}:-1[s]; [s]split[a][b]; [a]palettegen[palette]; [b][palette]paletteuse",
-            self.gif_fps, self.gif_width
+        // NOTE: The labels of the outputs' branches (see below), if there are several outputs.
+        let prefixes = match self.additional_widths.is_empty() {
+            true => vec![String::new()],
+            false => Vec::from_iter((0..=self.additional_widths.len()).map(|i| format!("o{}_", i))),
+        };
+        // NOTE: With a watermark, the graph has two inputs, the image getting one copy per output,
+        // and the video's label is explicit as well when one of its streams is selected.
+        let mut graph = match (self.watermark.as_ref(), self.video_stream_index) {
+            (None, None) => filter_graph::FilterGraph::default(),
+            (watermark, index) => filter_graph::FilterGraph::from_input(
+                &video_stream::input_label(index),
+                Vec::from_iter(watermark.map(|watermark| watermark.input_statement(&prefixes))),
+            ),
+        };
+        // NOTE: First, so that the other filters only see the clip, as with the input seeking.
+        graph.push_some(self.trim_filter());
+        // NOTE: The padding comes before the decimation and the speed change (see below), so
+        // its duration is that of one of the GIF's frames in the source video's time.
+        graph.push_some(self.preserve_last_frame.then(|| {
+            format!(
+                "tpad=stop_mode=clone:stop_duration={}",
+                self.effective_speed() as f64 / self.gif_fps as f64
+            )
+        }));
+        graph.push_some(
+            (!self.crop_keyframes.is_empty()).then(|| crop::crop_filter(&self.crop_keyframes)),
+        );
+        // NOTE: After the crop, so that the subtitles stay within the frames, and before the
+        // retiming (see below), the frames being shifted to the source video's timestamps
+        // meanwhile, which the clip's input seeking resets.
+        if let Some(path) = self.subtitles.as_ref() {
+            match self
+                .clip
+                .and_then(|c| c.bounds(None))
+                .map(|(start, _)| start)
+            {
+                Some(start) if !start.is_zero() => {
+                    graph
+                        .push(format!("setpts=PTS+{}/TB", clip::seconds(start)))
+                        .push(subtitles::subtitles_filter(path))
+                        .push("setpts=PTS-STARTPTS");
+                }
+                _ => {
+                    graph.push(subtitles::subtitles_filter(path));
+                }
+            }
+        }
+        // NOTE: After the crop as well, and the kept frames are then retimed as consecutive ones.
+        graph.push_some(
+            self.frame_step
+                .filter(|step| *step > 1)
+                .map(|step| format!("select='not(mod(n,{}))',setpts=N/FRAME_RATE/TB", step)),
+        );
+        // NOTE: After the crop, whose keyframes' timestamps are those of the source video.
+        graph.push_some((self.speed != 1.0).then(|| format!("setpts=PTS/{}", self.speed)));
+        // NOTE: The rotation (and the flips) come before the scaling, which then applies to the
+        // rotated frames (and after the frame rate reduction, so that fewer frames are rotated).
+        graph
+            .push(format!("fps={}", self.gif_fps))
+            .push_some(self.rotation.map(|r| r.transpose_filter().into()))
+            .push_some(self.hflip.then(|| "hflip".into()))
+            .push_some(self.vflip.then(|| "vflip".into()));
+        // NOTE: Before the palette is generated, so that it only holds the adjusted colors.
+        graph.push_some(self.eq_filter());
+        // NOTE: With one palette per frame, `paletteuse` must pick up each new palette,
+        // so both filters get their options from the same mode.
+        let palettegen_options = Vec::from_iter(
+            self.effective_max_colors()
+                .map(|n| format!("max_colors={}", n))
+                .into_iter()
+                .chain(self.palette_stats_mode.palettegen_option()),
+        );
+        let palettegen = filter_graph::with_options("palettegen", &palettegen_options);
+        let paletteuse = filter_graph::with_options(
+            "paletteuse",
+            &Vec::from_iter(self.palette_stats_mode.paletteuse_option()),
+        );
+        // NOTE: Without a palette (see `OutputFormat`), the graph ends with the scaling.
+        let uses_palette = self.output_format.uses_palette();
+        if self.additional_widths.is_empty() {
+            self.push_output_filters(&mut graph, self.gif_width);
+            return match uses_palette {
+                true => graph.build(&palettegen, &paletteuse),
+                false => graph.build_without_palette(None),
+            };
+        }
+        // NOTE: The branches (one per width, the constructor's coming first) share everything
+        // up to the flips, and each one then gets its own scaling, boomerang and palette.
+        let widths = Vec::from_iter(
+            std::iter::once(self.gif_width).chain(self.additional_widths.iter().copied()),
+        );
+        let branches = graph.split(&prefixes);
+        let mut statements = graph.into_statements();
+        for (i, (mut branch, width)) in branches.into_iter().zip(widths).enumerate() {
+            self.push_output_filters(&mut branch, width);
+            let output = format!("out{}", i);
+            statements.push(match uses_palette {
+                true => branch.build_to(&palettegen, &paletteuse, Some(&output)),
+                false => branch.build_without_palette(Some(&output)),
+            });
+        }
+        statements.join("; ")
+    }
+
+    /// The input options (placed before `-i`) that select the clip (see [`Settings::clip`]),
+    /// if it does not need the video's duration, or has been resolved.
+    fn seek_args(&self) -> Vec<String> {
+        self.clip
+            .and_then(|c| c.bounds(None))
+            .map(|bounds| self.seek_mode.input_args(bounds))
+            .unwrap_or_default()
+    }
+
+    /// The filter that drops the frames before the clip's start (see [`SeekMode::trim_filter`]).
+    fn trim_filter(&self) -> Option<String> {
+        self.clip
+            .and_then(|c| c.bounds(None))
+            .and_then(|(start, _)| self.seek_mode.trim_filter(start))
+    }
+
+    /// The filter that adjusts the frames' colors (see [`Settings::brightness`],
+    /// [`Settings::contrast`] and [`Settings::saturation`]), if any, as a single `eq` filter,
+    /// which would otherwise convert the frames once per option.
+    fn eq_filter(&self) -> Option<String> {
+        let eq_options = Vec::from_iter(
+            [
+                (self.brightness != 0.0).then(|| format!("brightness={}", self.brightness)),
+                (self.contrast != 1.0).then(|| format!("contrast={}", self.contrast)),
+                (self.saturation != 1.0).then(|| format!("saturation={}", self.saturation)),
+            ]
+            .into_iter()
+            .flatten(),
+        );
+        (!eq_options.is_empty()).then(|| format!("eq={}", eq_options.join(":")))
+    }
+
+    /// The arguments passed to FFmpeg for the palette analysis pass (see
+    /// [`Settings::auto_colors`]), which samples the same clip as the job, once cropped
+    /// and with its colors adjusted.
+    pub(crate) fn palette_analysis_args(&self) -> Vec<String> {
+        let crop =
+            (!self.crop_keyframes.is_empty()).then(|| crop::crop_filter(&self.crop_keyframes));
+        palette::analysis_args(
+            self.seek_args(),
+            self.input_args(),
+            self.video_stream_index,
+            Vec::from_iter(
+                [self.trim_filter(), crop, self.eq_filter()]
+                    .into_iter()
+                    .flatten(),
+            ),
         )
     }
+
+    /// Appends the filters that depend on the animated GIF's `width` (i.e. the scaling, with
+    /// the height following [`Settings::gif_height`] proportionally, and the boomerang).
+    fn push_output_filters(&self, graph: &mut filter_graph::FilterGraph, width: u16) {
+        let size_mode = match self.gif_height {
+            None => SizeMode::Width(width),
+            Some(height) => {
+                // NOTE: The box of the main animated GIF, scaled to the given width.
+                let source = (u32::from(self.gif_width), u32::from(height));
+                let size_mode = SizeMode::Width(width);
+                let (_, height) =
+                    compute_output_dimensions(source, &size_mode, EvenDimensionPolicy::Any);
+                SizeMode::Fit {
+                    width,
+                    height: height.clamp(1, u32::from(u16::MAX)) as u16,
+                    fit: self.fit_mode,
+                }
+            }
+        };
+        graph.push(fit::scale_filter(
+            &size_mode,
+            self.even_dimension_policy,
+            self.scale_algorithm,
+        ));
+        // NOTE: After the scaling, so that the image keeps its own size, and before the
+        // boomerang, so that fewer frames get overlaid.
+        if let Some(watermark) = self.watermark.as_ref() {
+            graph.overlay("wm", &watermark.overlay_filter());
+        }
+        // NOTE: After the watermark as well, so that the badge is drawn over it.
+        graph.push_some(self.pass.filter(self.badge_overlay.as_ref()));
+        if self.boomerang {
+            graph.boomerang();
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+/// A problem found by [`Settings::validate`].
+pub enum SettingsError {
+    /// The value provided using [`Settings::max_colors`] is not between
+    /// [`Settings::MIN_COLORS`] and [`Settings::MAX_COLORS`].
+    MaxColorsOutOfRange(u16),
+    /// The value provided using [`Settings::max_palette_bit_depth`] is not between
+    /// 1 and [`Settings::MAX_PALETTE_BIT_DEPTH`].
+    PaletteBitDepthOutOfRange(u8),
+    /// The value provided using [`Settings::threads`] is larger than [`Settings::MAX_THREADS`].
+    ThreadsOutOfRange(u16),
+    /// The number of thumbnails passed to [`StripSettings::new`] is zero.
+    ThumbnailCountZero,
+    /// The [`InputSource`] is not supported on the current system (see [`InputSource::File`]).
+    UnsupportedInput,
+    /// The URL of the remote video (see [`Settings::from_url`]) is malformed, or its scheme
+    /// is not allowed.
+    InvalidUrl(UrlError),
+    /// The keyframe at the given index (see [`Settings::crop_keyframes`]) does not
+    /// have a later timestamp than the previous one.
+    CropKeyframesUnsorted(usize),
+    /// The region of the keyframe at the given index (see [`Settings::crop_keyframes`])
+    /// does not have the same size as the first one.
+    CropSizeMismatch(usize),
+    /// The regions passed to [`Settings::crop_keyframes`] have a zero width or height.
+    CropRectEmpty,
+    /// The pattern passed to [`Settings::deny_stderr_patterns`] is empty, or the
+    /// regular expression passed to `Settings::deny_stderr_regexes` (only available
+    /// with the `regex` feature flag) is invalid.
+    InvalidDenyPattern(String),
+    /// The value provided using [`Settings::output_size_warning_ratio`] is not a
+    /// positive (and finite) number.
+    InvalidOutputSizeWarningRatio,
+    /// The part of the video selected using [`Settings::clip`] is empty (e.g. a zero
+    /// [`Settings::clip_duration`], or an [`Settings::end_time`] that is not later than
+    /// the [`Settings::start_time`]).
+    EmptyClip,
+    /// The part of the video selected using [`Settings::clip`] depends on the
+    /// video's duration, which could not be found.
+    ClipRequiresDuration,
+    /// The value provided using [`Settings::output_frame_limit`] is zero.
+    OutputFrameLimitZero,
+    /// The value provided using [`Settings::frame_step`] is zero.
+    FrameStepZero,
+    /// The value provided using [`Settings::max_output_attempts`] is zero.
+    MaxOutputAttemptsZero,
+    /// The given width appears more than once among the constructor's and those provided
+    /// using [`Settings::additional_widths`].
+    DuplicateWidth(u16),
+    /// The value provided using [`Settings::speed`] is not between [`Settings::MIN_SPEED`]
+    /// and [`Settings::MAX_SPEED`].
+    InvalidSpeed,
+    /// The value provided using [`Settings::saturation`] is not between 0.0 and
+    /// [`Settings::MAX_SATURATION`].
+    InvalidSaturation,
+    /// The value provided using [`Settings::brightness`] is not between -1.0 and 1.0.
+    InvalidBrightness,
+    /// The value provided using [`Settings::contrast`] is not between 0.0 and
+    /// [`Settings::MAX_CONTRAST`].
+    InvalidContrast,
+    /// The opacity provided using [`Settings::watermark`] is not between 0.0 and 1.0.
+    InvalidWatermarkOpacity,
+    /// The share provided using [`ProgressCurve::TwoPhase`] (see [`Settings::progress_curve`])
+    /// is not strictly between 0.0 and 1.0.
+    InvalidProgressCurve,
+    /// The quality of the [`OutputFormat::WebP`] provided using [`Settings::output_format`]
+    /// is larger than [`OutputFormat::MAX_WEBP_QUALITY`].
+    WebPQualityOutOfRange(u8),
+    /// A time spec passed to a setter (e.g. [`Settings::trim`]) could not be parsed.
+    InvalidTimeSpec(TimeSpecError),
+    /// The [`InputFormatHints`] select the `rawvideo` demuxer, but are missing either
+    /// the pixel format or the frame size, which FFmpeg cannot guess for raw frames.
+    IncompleteRawVideoHints,
+    /// The [`InputFormatHints`] contain an empty name, a zero frame width or height, or
+    /// a frame rate that is not a positive (and finite) number.
+    InvalidInputFormatHints,
+    /// Some of the options conflict with each other, in a way that cannot be resolved
+    /// (see [`Settings::conflicts`] and [`ConflictSeverity::Hard`]).
+    Conflict(SettingsConflict),
+    /// The template provided using [`Settings::output_file`] cannot be expanded into a
+    /// valid file name.
+    OutputNaming(NamingError),
+    /// The callback provided using [`Settings::argv_hook`] panicked, with the given message.
+    ArgvHookPanicked(String),
+}
+
+impl std::error::Error for SettingsError {}
+
+impl std::fmt::Display for SettingsError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{:?}", self)
+    }
 }
 
 #[derive(Debug, Clone)]
 /// An error generated by the [`Converter`].
 pub enum Error {
     /// Contains an error code returned by the FFmpeg child process.
-    /// 
+    ///
     /// NOTE: I am not sure at this point whether this variant will ever get emitted.
     /// For instance, deliberately inputting an invalid file format (e.g. png)
     /// will still return 0 (i.e. success) as an exit code, but we will simply
     /// get an empty `stdout`, which we signal here using the [`Error::EmptyStdout`]
     /// variant instead.
+    ///
+    /// When FFmpeg exits with a nonzero code after having written a complete GIF, the
+    /// GIF is delivered with a [`Warning::DirtyExit`] warning instead. Conversely, this
+    /// variant takes precedence over [`Error::EmptyStdout`] and [`Error::InvalidOutput`].
     ExitCode(i32),
-    /// A confirmation that signals that the conversion job has been cancelled.
-    Cancelled,
+    /// A confirmation that signals that the conversion job has been cancelled,
+    /// along with how far the job had gone when the cancellation was processed
+    /// (e.g. to decide whether it's worth offering to resume the job later).
+    Cancelled {
+        /// The last progress value sent to the application (see [`Message::Progress`]
+        /// and [`Message::InterpolatedProgress`]) before the cancellation, if any.
+        progress_at_cancel: Option<f64>,
+        /// The number of bytes of output that had already been produced (and that
+        /// were thrown away) when the cancellation was processed.
+        bytes_discarded: usize,
+    },
     /// Contains the [`std::io::Error`] returned by calling the `wait` method
-    /// on the [`std::process::Child`] process.
+    /// on the [`std::process::Child`] process (or by reading its `stdout` or `stderr`).
     ChildProcess(std::sync::Arc<std::io::Error>),
     /// Emitted by the [`Converter`] when the child process' `stdout` is
     /// empty at the end of the job. This is likely because an invalid file
@@ -77,6 +2086,218 @@ pub enum Error {
     /// for error messages, we simply assume that an empty `stdout` means an
     /// unsupported file format.
     EmptyStdout,
+    /// Emitted by the [`Converter`] when the child process' `stdout` is not
+    /// empty at the end of the job, but does not contain a valid GIF (e.g.
+    /// because the data was truncated).
+    InvalidOutput(gif_info::GifParseError),
+    /// Emitted by the [`Converter`] when the child process' `stdout` is not empty at the
+    /// end of the job, but does not start with the signature of the format provided using
+    /// [`Settings::output_format`] (other than [`OutputFormat::Gif`], see
+    /// [`Error::InvalidOutput`]).
+    InvalidOutputSignature(OutputFormat),
+    /// Emitted by the [`Converter`] when [`Converter::convert`] was called from
+    /// a thread that is driving asynchronous tasks, and the [`Settings::strict_async_context`]
+    /// option was enabled (only available with the `tokio` feature flag).
+    BlockingInAsyncContext,
+    /// Emitted by the [`Converter`] when the [`Settings`] are invalid (see
+    /// [`Settings::validate`]), in which case the job is not started.
+    InvalidSettings(SettingsError),
+    /// Emitted by the [`Converter`] when the video's duration could not be
+    /// determined, for jobs that require it (e.g. [`Converter::extract_thumbnail_strip`]).
+    UnknownDuration,
+    /// Emitted by the [`Converter`] when the FFmpeg binary's location (see
+    /// [`Settings::ffmpeg_location`]) could not be resolved, in which case
+    /// the job is not started.
+    FfmpegLocation(FfmpegLocationError),
+    /// Returned by [`wait_for_result`] (only available without the `tokio` and
+    /// `async-channel` feature flags) when the job ended, or the channel was closed,
+    /// without sending either [`Message::Success`] or [`Message::Error`].
+    MissingResult,
+    /// Emitted by the [`Converter`] when one of the lines written by FFmpeg to `stderr`
+    /// matches one of the patterns provided using [`Settings::deny_stderr_patterns`],
+    /// in which case the job is stopped and its output is thrown away.
+    DeniedWarning {
+        /// The pattern that was matched.
+        pattern: String,
+        /// The line that matched the pattern.
+        line: String,
+    },
+    /// Emitted by the [`Converter`] when probing the video, for jobs that require it
+    /// (e.g. [`Converter::extract_thumbnail_strip`]), did not finish in time (see
+    /// [`Settings::auxiliary_timeout`]), in which case it contains [`ProbeError::Timeout`].
+    Probe(ProbeError),
+    /// Emitted by the [`Converter`] when the part of the video selected using
+    /// [`Settings::clip`] (e.g. with [`Settings::start_time`]) starts past the end of
+    /// the video, so that FFmpeg had no frames to convert.
+    ClipOutOfRange {
+        /// The start of the selected part of the video.
+        start: std::time::Duration,
+        /// The video's duration, as reported by FFmpeg.
+        duration: std::time::Duration,
+    },
+    /// Emitted by the [`Converter`] when the job was not done by the deadline provided
+    /// using [`Settings::deadline`], in which case it was stopped (or not started at all)
+    /// and its output, if any, was thrown away.
+    DeadlineExceeded {
+        /// How long after the deadline the job was stopped.
+        overshoot: std::time::Duration,
+    },
+    /// Emitted by the [`Converter`] when the region provided using [`Settings::crop`]
+    /// (or [`Settings::crop_keyframes`]) is larger than the video's frames, so that FFmpeg
+    /// could not set up its `crop` filter and had no frames to convert.
+    CropOutOfBounds {
+        /// The line written by FFmpeg to `stderr` that reports the problem (e.g. `Invalid too
+        /// big or non positive size for width '5000' or height '400'`).
+        line: String,
+    },
+    /// Emitted by the [`Converter`] when the animated GIF could not be written to the file
+    /// provided using [`Settings::output_file`] (e.g. because its directory does not exist),
+    /// in which case [`Message::Success`] is not sent.
+    OutputFile {
+        /// The path of the file (or of the first candidate, when no file name was available).
+        path: std::path::PathBuf,
+        /// The error returned when writing the file.
+        error: std::sync::Arc<std::io::Error>,
+    },
+    /// Emitted by the [`Converter`] (right after [`Message::Success`]) when the animated GIF
+    /// of one of the widths provided using [`Settings::additional_widths`] turned out to be
+    /// missing or empty (in which case it contains [`Error::EmptyStdout`]), invalid (see
+    /// [`Error::InvalidOutput`]), or unreadable (see [`Error::OutputFile`]), the other widths
+    /// being sent anyway.
+    VariantOutput {
+        /// The width of the animated GIF.
+        width: u16,
+        /// The problem with the animated GIF.
+        error: Box<Error>,
+    },
+    /// Emitted by the [`Converter`] when the image provided using [`Settings::watermark`]
+    /// could not be found (or read), in which case FFmpeg is not run.
+    WatermarkFile {
+        /// The path of the image.
+        path: std::path::PathBuf,
+        /// The error returned when looking the image up.
+        error: std::sync::Arc<std::io::Error>,
+    },
+    /// Emitted by the [`Converter`] when [`Settings::check_capabilities`] is enabled and the
+    /// FFmpeg binary lacks a filter (or an encoder) required by the settings, in which case
+    /// FFmpeg is not run.
+    MissingCapability {
+        /// The missing filter (or encoder).
+        needed: Capability,
+        /// An actionable message for the user (e.g. `your FFmpeg lacks libass (--enable-libass),
+        /// needed by the subtitles setting; install a full build`).
+        hint: String,
+    },
+    /// Emitted by the [`Converter`] when FFmpeg reports that the hardware acceleration method
+    /// provided using [`Settings::hwaccel`] could not be set up, and the job was set to fail
+    /// in that case (see [`Settings::hwaccel_fallback`]).
+    HardwareAcceleration {
+        /// The method that could not be set up (as resolved, for [`HwAccel::Auto`]).
+        hwaccel: HwAccel,
+        /// The line written by FFmpeg to `stderr` that reports the problem (e.g. `Failed setup
+        /// for format cuda: hwaccel initialisation returned error.`).
+        line: String,
+    },
+    /// Emitted by the [`Converter`] when the subtitles file provided using
+    /// [`Settings::subtitles`] could not be opened (or parsed) by FFmpeg, so that it could
+    /// not set up its `subtitles` filter and had no frames to convert.
+    SubtitlesFile {
+        /// The path of the subtitles file.
+        path: std::path::PathBuf,
+        /// The line written by FFmpeg to `stderr` that reports the problem (e.g. `Unable to
+        /// open captions.srt`).
+        line: String,
+    },
+    /// Emitted by the [`Converter`] when the input has no video stream at the index provided
+    /// using [`Settings::video_stream_index`], so that FFmpeg had no frames to convert.
+    VideoStreamNotFound {
+        /// The index provided using [`Settings::video_stream_index`].
+        index: u32,
+        /// The line written by FFmpeg to `stderr` that reports the problem (e.g. `Stream
+        /// specifier ':v:1' in filtergraph description [0:v:1]fps=10[s] matches no streams.`).
+        line: String,
+    },
+    /// Emitted by the [`Converter`] when FFmpeg's `stderr` output could not be parsed and
+    /// [`Settings::strict_parsing`] is enabled, in which case the job is stopped (just
+    /// like when cancelled) and its output is thrown away.
+    ParseFailure {
+        /// What was being parsed.
+        context: ParseContext,
+        /// The beginning of the line that could not be parsed (e.g. the banner's
+        /// `Duration` line, or a `frame=` stats line).
+        sample: String,
+    },
+    /// Emitted by the [`Converter`] instead of [`Warning::SuspectOutput`] when
+    /// [`Settings::strict_parsing`] is enabled, in which case the output is thrown away.
+    SuspectOutput {
+        /// The anomaly found in the stats lines.
+        reason: StatsAnomaly,
+    },
+    /// Emitted by the [`Converter`] when the reader provided using [`InputSource::from_reader`]
+    /// returned an error, in which case the job is stopped (just like when cancelled) and
+    /// its output is thrown away.
+    InputRead(std::sync::Arc<std::io::Error>),
+    /// Emitted by the [`Converter`] when the directory of the file provided using
+    /// [`Settings::output_path`] does not exist, in which case FFmpeg is not run.
+    OutputDirectoryNotFound(std::path::PathBuf),
+    /// Emitted by the [`Converter`] when the file provided using [`Settings::output_path`]
+    /// cannot be written for lack of permission, in which case FFmpeg is not run.
+    OutputPermissionDenied(std::path::PathBuf),
+    /// Same as [`Error::EmptyStdout`], when the file provided using [`Settings::output_path`]
+    /// is missing or empty at the end of the job.
+    EmptyOutputFile(std::path::PathBuf),
+    /// Emitted by the [`Converter`] when the writer provided to [`Converter::convert_to_writer`]
+    /// returned an error, in which case the job is stopped (just like when cancelled).
+    OutputWrite(std::sync::Arc<std::io::Error>),
+    /// Emitted by the [`Converter`] when the output could not be moved to (or read back from)
+    /// the temporary file it spilled over to (see [`Settings::memory_budget`]), e.g. because
+    /// the disk is full, in which case the job is stopped (just like when cancelled).
+    OutputSpill(std::sync::Arc<std::io::Error>),
+    /// Emitted by the [`Converter`] when the state it shares between its threads is broken
+    /// (e.g. because one of them panicked while holding a lock), with a description of what
+    /// is broken, in which case the job is stopped (just like when cancelled).
+    Internal(&'static str),
+}
+
+impl Error {
+    /// A short, stable, machine-friendly name for the kind of error (e.g. to
+    /// be used as a metrics label).
+    pub fn kind(&self) -> &'static str {
+        match self {
+            Self::ExitCode(_) => "exit_code",
+            Self::Cancelled { .. } => "cancelled",
+            Self::ChildProcess(_) => "child_process",
+            Self::EmptyStdout => "empty_stdout",
+            Self::InvalidOutput(_) => "invalid_output",
+            Self::InvalidOutputSignature(_) => "invalid_output_signature",
+            Self::BlockingInAsyncContext => "blocking_in_async_context",
+            Self::InvalidSettings(_) => "invalid_settings",
+            Self::UnknownDuration => "unknown_duration",
+            Self::FfmpegLocation(_) => "ffmpeg_location",
+            Self::MissingResult => "missing_result",
+            Self::DeniedWarning { .. } => "denied_warning",
+            Self::Probe(_) => "probe",
+            Self::ClipOutOfRange { .. } => "clip_out_of_range",
+            Self::DeadlineExceeded { .. } => "deadline_exceeded",
+            Self::CropOutOfBounds { .. } => "crop_out_of_bounds",
+            Self::OutputFile { .. } => "output_file",
+            Self::VariantOutput { .. } => "variant_output",
+            Self::ParseFailure { .. } => "parse_failure",
+            Self::WatermarkFile { .. } => "watermark_file",
+            Self::SubtitlesFile { .. } => "subtitles_file",
+            Self::VideoStreamNotFound { .. } => "video_stream_not_found",
+            Self::MissingCapability { .. } => "missing_capability",
+            Self::HardwareAcceleration { .. } => "hardware_acceleration",
+            Self::SuspectOutput { .. } => "suspect_output",
+            Self::InputRead(_) => "input_read",
+            Self::OutputDirectoryNotFound(_) => "output_directory_not_found",
+            Self::OutputPermissionDenied(_) => "output_permission_denied",
+            Self::EmptyOutputFile(_) => "empty_output_file",
+            Self::OutputWrite(_) => "output_write",
+            Self::OutputSpill(_) => "output_spill",
+            Self::Internal(_) => "internal",
+        }
+    }
 }
 
 impl std::error::Error for Error {}
@@ -89,9 +2310,28 @@ impl std::fmt::Display for Error {
 
 #[derive(Debug, Clone)]
 /// A message (i.e. an event) sent to the application by the [`Converter`].
+// NOTE: The largest variant is `Message::Summary`, which is sent only once per job, so
+// boxing it would not make much difference, while making it less convenient to match.
+#[allow(clippy::large_enum_variant)]
 pub enum Message {
     /// The raw bytes that make up the successfully generated animated GIF.
     Success(Vec<u8>),
+    /// The raw bytes that make up the animated GIF generated at one of the widths provided
+    /// using [`Settings::additional_widths`], emitted (in the same order as the widths) after
+    /// [`Message::Success`], once FFmpeg has exited.
+    SuccessVariant {
+        /// The width of the animated GIF.
+        width: u16,
+        /// The raw bytes of the animated GIF.
+        bytes: Vec<u8>,
+    },
+    /// The path of the file to which FFmpeg wrote the successfully generated animated GIF
+    /// (see [`Settings::output_path`]), sent instead of [`Message::Success`].
+    SuccessFile(std::path::PathBuf),
+    /// The number of bytes of the successfully generated animated GIF written to the writer
+    /// provided to [`Converter::convert_to_writer`], sent instead of [`Message::Success`]
+    /// once the writer has been flushed.
+    SuccessWritten(u64),
     /// An error message, containing the [`Error`].
     Error(Error),
     /// The progress (a value between 0.0 and 1.0) made by the converter, estimated
@@ -101,15 +2341,278 @@ pub enum Message {
     /// NOTE: Progress messages don't start being emitted right away.
     /// The [`Message::VideoDuration`] will (should) be emitted first.
     Progress(f64),
+    /// An estimated progress value (between 0.0 and 1.0, excluded), emitted between
+    /// [`Message::Progress`] messages when the [`Settings::smooth_progress`] option
+    /// is enabled. Applications that don't care whether the value was measured or
+    /// estimated can handle it just like [`Message::Progress`].
+    InterpolatedProgress(f64),
+    /// The number of frames processed so far, emitted (for each of FFmpeg's stats lines)
+    /// instead of [`Message::Progress`] while the video's duration is unknown (e.g. when
+    /// it is streamed from a non-seekable [`InputSource::Reader`]), so that the application
+    /// can still tell that the job is moving.
+    FramesProcessed(u64),
     /// The video duration, determined by FFmpeg as a first step in creating
     /// the animated GIF. Note that this event will (should) be emitted before
-    /// the [`Message::Progress`] event.
+    /// the [`Message::Progress`] event. The duration is doubled when the
+    /// [`Settings::boomerang`] option is enabled, since the frames play twice.
+    ///
+    /// NOTE: When FFmpeg does not report the video's duration, it may be estimated, and
+    /// then sent again if a more reliable source turns up later in the job (see
+    /// [`Summary::duration_source`]).
     VideoDuration(std::time::Duration),
+    /// The number of bytes written by FFmpeg to `stdout` so far (i.e. the size of the
+    /// output, as it grows), emitted at most every 100 milliseconds while the output is
+    /// being read (and once more when done), independently of [`Message::Progress`].
+    /// This can be combined with an estimate of the final size when no time-based
+    /// progress is available.
+    OutputBytes(u64),
+    /// The number of bytes written so far to the writer provided to [`Converter::convert_to_writer`],
+    /// emitted (instead of [`Message::OutputBytes`]) at most every 100 milliseconds while the
+    /// output is being copied (and once more when done).
+    BytesWritten(u64),
+    /// The number of colors selected for the animated GIF's palette when the
+    /// [`Settings::auto_colors`] option is enabled, emitted before the conversion
+    /// starts (i.e. before [`Message::VideoDuration`]).
+    ColorCountSelected(u16),
+    /// The decoder and encoder selected by FFmpeg for the video stream (as announced in
+    /// the `Stream mapping` section of its `stderr` output), emitted once, after
+    /// [`Message::VideoDuration`], e.g. so that the application can explain a slow job
+    /// by the lack of hardware acceleration (see also [`Warning::HardwareAccelerationFailed`]).
+    CodecSelection {
+        /// The name of the decoder (e.g. `h264` for FFmpeg's own, or `h264_cuvid`).
+        decoder: String,
+        /// The name of the encoder (i.e. `gif`, for the conversion job).
+        encoder: String,
+        /// Whether the decoder or the encoder is a hardware one (e.g. `h264_cuvid`).
+        hw_accelerated: bool,
+    },
+    /// The animated GIF's dimensions, as announced by FFmpeg (in the `Output #0` section of
+    /// its `stderr` output) as soon as it has configured its filter graph, i.e. long before
+    /// the GIF is finished, e.g. so that the application can reserve space for it. This is
+    /// emitted once, after [`Message::VideoDuration`] (or right before [`Message::Success`]
+    /// when the GIF was found in the cache, see [`Converter::cache`]).
+    OutputDimensions {
+        /// The animated GIF's width.
+        width: u32,
+        /// The animated GIF's height (e.g. following the video's aspect ratio).
+        height: u32,
+    },
+    /// The timestamp (in the source video) of each of the animated GIF's frames, emitted
+    /// right before [`Message::Success`] when the [`Settings::emit_frame_map`] option is
+    /// enabled. The `n`-th frame is shown from `n / fps` seconds into the selected part of
+    /// the video (see [`Settings::clip`]), which the frame delays stored in the GIF (in
+    /// hundredths of a second) only approximate.
+    FrameMap(Vec<std::time::Duration>),
+    /// A warning about something that did not prevent the job from running,
+    /// but that the application should probably know about.
+    Warning(Warning),
+    /// The raw bytes of the preview (see [`Settings::preview_width`]), emitted once the
+    /// preview pass is done, before the job itself starts.
+    Preview(Vec<u8>),
+    /// A thumbnail extracted by [`Converter::extract_thumbnail_strip`], emitted
+    /// (in order) as soon as FFmpeg has written it.
+    Thumbnail {
+        /// The position of the thumbnail in the strip, starting at 0.
+        index: usize,
+        /// The (approximate) timestamp of the thumbnail in the source video.
+        timestamp: std::time::Duration,
+        /// The encoded image (see [`ThumbnailFormat`]).
+        bytes: Vec<u8>,
+    },
+    /// The path of the file to which the animated GIF was written (see [`Settings::output_file`]),
+    /// emitted right before [`Message::Success`].
+    Saved {
+        /// The path of the file.
+        path: std::path::PathBuf,
+    },
+    /// The job was not run, because the file to which the animated GIF would have been
+    /// written already exists, and [`CollisionPolicy::Skip`] was used (see [`Settings::output_file`]).
+    /// This message is sent instead of [`Message::Success`] (and of [`Message::Error`]).
+    Skipped {
+        /// The path of the existing file.
+        existing_path: std::path::PathBuf,
+    },
+    /// A lifecycle event of the [`Converter`]'s own threads and child process (see
+    /// [`InternalEvent`]), emitted when the [`Settings::emit_internal_events`] option is enabled.
+    Internal(InternalEvent),
+    /// The start of the `n`-th attempt (starting at 1) at fitting the animated GIF within
+    /// [`Settings::max_output_bytes`], along with the settings used by the attempt (e.g. its
+    /// reduced width), emitted before each attempt (including the first one).
+    Attempt {
+        /// The attempt's number, starting at 1.
+        n: u32,
+        /// The settings used by the attempt.
+        settings: Box<Settings>,
+    },
+    /// A summary of the job, emitted right before [`Message::Done`].
+    Summary(Summary),
     /// A message that signals that the job is done and that no other messages
     /// will be emitted.
     Done,
 }
 
+impl Message {
+    /// Whether the message only reports the job's progress, in which case it is sent
+    /// down the progress channel when using [`Converter::new_with_split_channels`].
+    pub fn is_progress(&self) -> bool {
+        matches!(
+            self,
+            Self::Progress(_)
+                | Self::InterpolatedProgress(_)
+                | Self::FramesProcessed(_)
+                | Self::OutputBytes(_)
+                | Self::BytesWritten(_)
+        )
+    }
+}
+
+#[derive(Debug, Clone)]
+/// A summary of a conversion job, sent to the application using [`Message::Summary`].
+pub struct Summary {
+    /// The unique identifier of the [`Converter`] that ran the job (see [`Converter::id`]).
+    pub id: JobId,
+    /// The label attached to the job using [`Converter::job_label`], if any.
+    pub label: Option<String>,
+    /// The size (in bytes) of the generated animated GIF (or the total size of
+    /// the extracted thumbnails), if the job succeeded.
+    pub output_bytes: Option<usize>,
+    /// The first error emitted during the job, if any.
+    pub error: Option<Error>,
+    /// The warnings emitted during the job.
+    pub warnings: Vec<Warning>,
+    /// The time it took to run the job.
+    pub elapsed: std::time::Duration,
+    /// The resources used by the FFmpeg child process of the conversion job, once it
+    /// has exited, if reported by the system (i.e. on unix and Windows), which does not
+    /// include the short-lived child processes run before the job (e.g. to probe the video's
+    /// duration, or for [`Settings::auto_colors`]), nor those of [`Converter::extract_thumbnail_strip`].
+    pub resource_usage: Option<ResourceUsage>,
+    /// The path of the file to which the animated GIF was written (see [`Message::Saved`]),
+    /// or of the existing file because of which the job was skipped (see [`Message::Skipped`]).
+    pub output_path: Option<std::path::PathBuf>,
+    /// Where the duration relative to which the progress is computed came from (see
+    /// [`DurationSource`]), or `None` if it was unknown, in which case no progress was reported.
+    pub duration_source: Option<DurationSource>,
+    /// The last lines of FFmpeg's `stderr` output from which the duration and the progress
+    /// were parsed, from the oldest to the most recent, if [`Settings::parse_provenance`] was
+    /// provided (empty otherwise).
+    pub provenance: Vec<ParseProvenance>,
+}
+
+impl Summary {
+    /// The outcome of the job.
+    pub fn outcome(&self) -> Outcome {
+        match (&self.error, self.output_bytes, &self.output_path) {
+            (Some(Error::Cancelled { .. }), _, _) => Outcome::Cancelled,
+            (None, None, Some(_)) => Outcome::Skipped,
+            (Some(_), _, _) | (None, None, None) => Outcome::Failed,
+            (None, Some(_), _) => Outcome::Succeeded,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+/// The outcome of a conversion job, as reported by [`Summary::outcome`].
+pub enum Outcome {
+    /// The animated GIF was successfully generated.
+    Succeeded,
+    /// The job failed (see [`Summary::error`]).
+    Failed,
+    /// The job was cancelled by the application.
+    Cancelled,
+    /// The job was not run, because its output file already exists (see [`Message::Skipped`]).
+    Skipped,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+/// A warning sent to the application by the [`Converter`], using [`Message::Warning`].
+pub enum Warning {
+    /// [`Converter::convert`] was called from a tokio runtime worker thread, which
+    /// it blocks until the job is done. The method should instead be called from a
+    /// blocking thread (e.g. using `tokio::task::spawn_blocking`).
+    ///
+    /// NOTE: This warning is only emitted with the `tokio` feature flag. See also
+    /// [`Settings::strict_async_context`].
+    BlockingInAsyncContext,
+    /// The animated GIF is larger than the source video, by more than the ratio provided
+    /// using [`Settings::output_size_warning_ratio`]. This warning is emitted right before
+    /// [`Message::Success`], e.g. so that the application can suggest smaller settings
+    /// (such as a smaller width or fewer colors) or another format.
+    OutputLargerThanInput {
+        /// The size (in bytes) of the source video.
+        input_bytes: u64,
+        /// The size (in bytes) of the animated GIF.
+        output_bytes: u64,
+    },
+    /// FFmpeg exited with a nonzero code (e.g. while cleaning up, which can happen with
+    /// network filesystems or antivirus software), but only after having written a complete
+    /// GIF (i.e. with a valid trailer, and at least as many frames as reported by FFmpeg),
+    /// which is delivered anyway. This warning is emitted right before [`Message::Success`]
+    /// (instead of [`Error::ExitCode`]).
+    DirtyExit {
+        /// FFmpeg's exit code.
+        code: i32,
+        /// The last few lines written by FFmpeg to `stderr`.
+        stderr_tail: String,
+    },
+    /// FFmpeg could not set up the hardware accelerated decoder it was asked to use, and
+    /// fell back to a software one, which is usually much slower. This warning is emitted
+    /// (at most once) as soon as FFmpeg reports the failure (see also [`Message::CodecSelection`]).
+    /// When the method was provided using [`Settings::hwaccel`], it is emitted instead before
+    /// the job is run again without acceleration (see [`HwAccelFallback::Software`]).
+    HardwareAccelerationFailed {
+        /// The line written by FFmpeg to `stderr` that reports the failure.
+        line: String,
+    },
+    /// The thread that forwards the application's commands (see [`Command`]) to the FFmpeg
+    /// child process did not exit in time after the job ended (e.g. because it was stuck on a
+    /// pipe that was kept open), so the [`Converter`] closed the child process' `stdin` and
+    /// left the thread behind, rather than blocking forever. This warning is emitted right
+    /// before [`Message::Summary`].
+    StdinThreadTimeout,
+    /// A measure was taken to keep the job within the memory budget provided using
+    /// [`Settings::memory_budget`]. This warning is emitted (at most once per measure)
+    /// as soon as the measure kicks in.
+    MemoryBudgetSpill {
+        /// The measure that was taken.
+        measure: BudgetMeasure,
+    },
+    /// Some of the options of the [`Settings`] conflict with each other, and the conflict
+    /// was resolved as described by the [`SettingsConflict`] (see [`Settings::conflicts`]).
+    /// This warning is emitted (once per conflict) right after the settings are validated.
+    SettingsConflict(SettingsConflict),
+    /// The animated GIF is still larger than [`Settings::max_output_bytes`] after the last
+    /// attempt, in which case the smallest one is sent (using [`Message::Success`]) anyway.
+    /// This warning is emitted right before [`Message::Success`].
+    OutputSizeTargetMissed {
+        /// The size (in bytes) provided using [`Settings::max_output_bytes`].
+        max_bytes: u64,
+        /// The size (in bytes) of the smallest animated GIF.
+        output_bytes: u64,
+        /// The number of attempts made.
+        attempts: u32,
+    },
+    /// FFmpeg's stats lines betray a broken output (e.g. a negative bitrate), even though
+    /// the animated GIF looks valid and FFmpeg exited successfully (see [`StatsAnomaly`]).
+    /// This warning is emitted right before [`Message::Success`], unless
+    /// [`Settings::strict_parsing`] is enabled, in which case [`Error::SuspectOutput`] is
+    /// emitted instead.
+    SuspectOutput {
+        /// The anomaly found in the stats lines.
+        reason: StatsAnomaly,
+    },
+    /// The first line written by FFmpeg to `stderr` is not its banner (i.e. `ffmpeg version
+    /// ...`), which suggests that [`Settings::ffmpeg_path`] points to a wrapper script (e.g. a
+    /// `.bat` or `.cmd` shim on Windows) that alters FFmpeg's output, in which case the video's
+    /// duration and the progress may not be found. This warning is emitted (at most once) as
+    /// soon as the first line is read, unless the banner is hidden on purpose (e.g. using
+    /// `-hide_banner` or `-loglevel`).
+    MissingBanner {
+        /// The first line written to `stderr`.
+        first_line: String,
+    },
+}
+
 #[derive(Debug, Clone)]
 /// A command sent to the [`Converter`] by the application.
 pub enum Command {
@@ -118,3 +2621,1508 @@ pub enum Command {
     /// as a [`Message::Error`].
     Cancel,
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_utils::{
+        ffmpeg_available, init_logging, run_to_completion, success_bytes, SAMPLE_VIDEO_PATH,
+    };
+
+    #[test]
+    fn test_generate_filter_complex() {
+        let settings = Settings::with_standard_fps(SAMPLE_VIDEO_PATH.into(), 200);
+        assert_eq!(
+            settings.generate_filter_complex(),
+            "fps=10,scale=200:-1[s]; [s]split[a][b]; [a]palettegen[palette]; [b][palette]paletteuse"
+        );
+    }
+
+    #[test]
+    fn test_generate_filter_complex_preserve_last_frame() {
+        let settings =
+            Settings::with_standard_fps(SAMPLE_VIDEO_PATH.into(), 200).preserve_last_frame(true);
+        assert_eq!(
+            settings.generate_filter_complex(),
+            "tpad=stop_mode=clone:stop_duration=0.1,fps=10,scale=200:-1[s]; [s]split[a][b]; [a]palettegen[palette]; [b][palette]paletteuse"
+        );
+    }
+
+    #[test]
+    fn test_generate_filter_complex_max_colors() {
+        let settings = Settings::with_standard_fps(SAMPLE_VIDEO_PATH.into(), 200).max_colors(64);
+        assert_eq!(
+            settings.generate_filter_complex(),
+            "fps=10,scale=200:-1[s]; [s]split[a][b]; [a]palettegen=max_colors=64[palette]; [b][palette]paletteuse"
+        );
+    }
+
+    #[test]
+    fn test_generate_filter_complex_gif_height() {
+        let settings = Settings::with_standard_fps(SAMPLE_VIDEO_PATH.into(), 480).gif_height(270);
+        let cases = [
+            (
+                FitMode::Stretch,
+                "fps=10,scale=480:270[s]; [s]split[a][b]; [a]palettegen[palette]; [b][palette]paletteuse",
+            ),
+            (
+                FitMode::Letterbox,
+                "fps=10,scale=480:270:force_original_aspect_ratio=decrease,pad=480:270:(ow-iw)/2:(oh-ih)/2[s]; [s]split[a][b]; [a]palettegen[palette]; [b][palette]paletteuse",
+            ),
+            (
+                FitMode::Crop,
+                "fps=10,scale=480:270:force_original_aspect_ratio=increase,crop=480:270[s]; [s]split[a][b]; [a]palettegen[palette]; [b][palette]paletteuse",
+            ),
+        ];
+        for (fit_mode, expected) in cases {
+            let settings = settings.clone().fit_mode(fit_mode);
+            assert_eq!(
+                settings.generate_filter_complex(),
+                expected,
+                "{:?}",
+                fit_mode
+            );
+        }
+        // NOTE: The fit mode has no effect without a height.
+        assert_eq!(
+            Settings::with_standard_fps(SAMPLE_VIDEO_PATH.into(), 480)
+                .fit_mode(FitMode::Crop)
+                .generate_filter_complex(),
+            "fps=10,scale=480:-1[s]; [s]split[a][b]; [a]palettegen[palette]; [b][palette]paletteuse"
+        );
+    }
+
+    #[test]
+    fn test_generate_filter_complex_scale_algorithm() {
+        let settings = Settings::with_standard_fps(SAMPLE_VIDEO_PATH.into(), 200);
+        // NOTE: The default leaves the `scale` filter untouched.
+        assert_eq!(
+            settings.generate_filter_complex(),
+            "fps=10,scale=200:-1[s]; [s]split[a][b]; [a]palettegen[palette]; [b][palette]paletteuse"
+        );
+        let cases = [
+            (ScaleAlgorithm::Bilinear, "scale=200:-1:flags=bilinear"),
+            (ScaleAlgorithm::Bicubic, "scale=200:-1:flags=bicubic"),
+            (ScaleAlgorithm::Lanczos, "scale=200:-1:flags=lanczos"),
+            (ScaleAlgorithm::Neighbor, "scale=200:-1:flags=neighbor"),
+            (ScaleAlgorithm::Area, "scale=200:-1:flags=area"),
+        ];
+        for (algorithm, expected) in cases {
+            assert_eq!(
+                settings
+                    .clone()
+                    .scale_algorithm(algorithm)
+                    .generate_filter_complex(),
+                format!(
+                    "fps=10,{}[s]; [s]split[a][b]; [a]palettegen[palette]; [b][palette]paletteuse",
+                    expected
+                ),
+                "{:?}",
+                algorithm
+            );
+        }
+    }
+
+    #[test]
+    fn test_generate_filter_complex_rotation() {
+        let settings = Settings::with_standard_fps(SAMPLE_VIDEO_PATH.into(), 200);
+        let cases = [
+            (
+                Rotation::Cw90,
+                "fps=10,transpose=1,scale=200:-1[s]; [s]split[a][b]; [a]palettegen[palette]; [b][palette]paletteuse",
+            ),
+            (
+                Rotation::Ccw90,
+                "fps=10,transpose=2,scale=200:-1[s]; [s]split[a][b]; [a]palettegen[palette]; [b][palette]paletteuse",
+            ),
+            (
+                Rotation::Rotate180,
+                "fps=10,transpose=2,transpose=2,scale=200:-1[s]; [s]split[a][b]; [a]palettegen[palette]; [b][palette]paletteuse",
+            ),
+        ];
+        for (rotation, expected) in cases {
+            let settings = settings.clone().rotation(rotation);
+            assert_eq!(
+                settings.generate_filter_complex(),
+                expected,
+                "{:?}",
+                rotation
+            );
+        }
+        // NOTE: The crop comes first (in the video's coordinates), and the scaling last.
+        assert_eq!(
+            settings
+                .crop(0, 0, 360, 640)
+                .gif_height(150)
+                .fit_mode(FitMode::Stretch)
+                .rotation(Rotation::Cw90)
+                .generate_filter_complex(),
+            "crop=w=360:h=640:x='0':y='0',fps=10,transpose=1,scale=200:150[s]; [s]split[a][b]; [a]palettegen[palette]; [b][palette]paletteuse"
+        );
+    }
+
+    #[test]
+    fn test_generate_filter_complex_flips() {
+        let settings = Settings::with_standard_fps(SAMPLE_VIDEO_PATH.into(), 200);
+        let cases = [
+            (false, false, "fps=10,scale=200:-1"),
+            (true, false, "fps=10,hflip,scale=200:-1"),
+            (false, true, "fps=10,vflip,scale=200:-1"),
+            (true, true, "fps=10,hflip,vflip,scale=200:-1"),
+        ];
+        for (hflip, vflip, expected) in cases {
+            let settings = settings.clone().hflip(hflip).vflip(vflip);
+            assert_eq!(
+                settings.generate_filter_complex(),
+                format!(
+                    "{}[s]; [s]split[a][b]; [a]palettegen[palette]; [b][palette]paletteuse",
+                    expected
+                ),
+                "hflip: {}, vflip: {}",
+                hflip,
+                vflip
+            );
+        }
+        // NOTE: Composed with the other transforms, the flips always come right after the rotation.
+        assert_eq!(
+            settings
+                .speed(2.0)
+                .rotation(Rotation::Ccw90)
+                .vflip(true)
+                .hflip(true)
+                .boomerang(true)
+                .generate_filter_complex(),
+            "setpts=PTS/2,fps=10,transpose=2,hflip,vflip,scale=200:-1,split[f][r]; [r]reverse[rr]; [f][rr]concat=n=2[s]; [s]split[a][b]; [a]palettegen[palette]; [b][palette]paletteuse"
+        );
+    }
+
+    #[test]
+    fn test_generate_filter_complex_saturation() {
+        let settings = Settings::with_standard_fps(SAMPLE_VIDEO_PATH.into(), 200);
+        // NOTE: The default saturation leaves the frames untouched.
+        assert!(!settings.generate_filter_complex().contains("eq="));
+        assert!(!settings
+            .clone()
+            .saturation(1.0)
+            .generate_filter_complex()
+            .contains("eq="));
+        assert_eq!(
+            settings
+                .clone()
+                .saturation(0.0)
+                .rotation(Rotation::Cw90)
+                .generate_filter_complex(),
+            "fps=10,transpose=1,eq=saturation=0,scale=200:-1[s]; [s]split[a][b]; [a]palettegen[palette]; [b][palette]paletteuse"
+        );
+        // NOTE: Shared by the branches of the additional widths.
+        assert_eq!(
+            settings
+                .clone()
+                .saturation(2.5)
+                .additional_widths(vec![100])
+                .generate_filter_complex(),
+            "fps=10,eq=saturation=2.5,split=2[o0_in][o1_in]; [o0_in]null,scale=200:-1[o0_s]; [o0_s]split[o0_a][o0_b]; [o0_a]palettegen[o0_palette]; [o0_b][o0_palette]paletteuse[out0]; [o1_in]null,scale=100:-1[o1_s]; [o1_s]split[o1_a][o1_b]; [o1_a]palettegen[o1_palette]; [o1_b][o1_palette]paletteuse[out1]"
+        );
+        for saturation in [0.0, 1.0, Settings::MAX_SATURATION] {
+            assert_eq!(settings.clone().saturation(saturation).validate(), Ok(()));
+        }
+        for saturation in [-0.1, 3.5, f32::NAN] {
+            assert_eq!(
+                settings.clone().saturation(saturation).validate(),
+                Err(SettingsError::InvalidSaturation)
+            );
+        }
+    }
+
+    #[test]
+    fn test_generate_filter_complex_brightness_contrast() {
+        let settings = Settings::with_standard_fps(SAMPLE_VIDEO_PATH.into(), 200);
+        assert!(!settings
+            .clone()
+            .brightness(0.0)
+            .contrast(1.0)
+            .generate_filter_complex()
+            .contains("eq="));
+        assert_eq!(
+            settings
+                .clone()
+                .brightness(0.25)
+                .generate_filter_complex(),
+            "fps=10,eq=brightness=0.25,scale=200:-1[s]; [s]split[a][b]; [a]palettegen[palette]; [b][palette]paletteuse"
+        );
+        assert_eq!(
+            settings.clone().contrast(1.5).generate_filter_complex(),
+            "fps=10,eq=contrast=1.5,scale=200:-1[s]; [s]split[a][b]; [a]palettegen[palette]; [b][palette]paletteuse"
+        );
+        // NOTE: Merged with the saturation into a single filter.
+        let filter_complex = settings
+            .clone()
+            .saturation(0.5)
+            .contrast(1.5)
+            .brightness(-0.1)
+            .generate_filter_complex();
+        assert_eq!(
+            filter_complex,
+            "fps=10,eq=brightness=-0.1:contrast=1.5:saturation=0.5,scale=200:-1[s]; [s]split[a][b]; [a]palettegen[palette]; [b][palette]paletteuse"
+        );
+        assert_eq!(filter_complex.matches("eq=").count(), 1);
+        for brightness in [-1.0, 0.0, 1.0] {
+            assert_eq!(settings.clone().brightness(brightness).validate(), Ok(()));
+        }
+        for brightness in [-1.1, 1.5, f32::NAN] {
+            assert_eq!(
+                settings.clone().brightness(brightness).validate(),
+                Err(SettingsError::InvalidBrightness)
+            );
+        }
+        for contrast in [0.0, 1.0, Settings::MAX_CONTRAST] {
+            assert_eq!(settings.clone().contrast(contrast).validate(), Ok(()));
+        }
+        for contrast in [-0.5, 4.5, f32::NAN] {
+            assert_eq!(
+                settings.clone().contrast(contrast).validate(),
+                Err(SettingsError::InvalidContrast)
+            );
+        }
+    }
+
+    #[test]
+    fn test_generate_filter_complex_subtitles() {
+        let settings = Settings::with_standard_fps(SAMPLE_VIDEO_PATH.into(), 200)
+            .subtitles("C:\\My Videos\\captions 1: intro.srt".into());
+        assert_eq!(
+            settings.generate_filter_complex(),
+            r"subtitles='C\:\\My Videos\\captions 1\: intro.srt',fps=10,scale=200:-1[s]; [s]split[a][b]; [a]palettegen[palette]; [b][palette]paletteuse"
+        );
+        // NOTE: Burned in after the crop, and before the speed change.
+        let settings = Settings::with_standard_fps(SAMPLE_VIDEO_PATH.into(), 200)
+            .subtitles("my captions.srt".into())
+            .crop(0, 0, 320, 180)
+            .speed(2.0);
+        assert_eq!(
+            settings.generate_filter_complex(),
+            "crop=w=320:h=180:x='0':y='0',subtitles='my captions.srt',setpts=PTS/2,fps=10,scale=200:-1[s]; [s]split[a][b]; [a]palettegen[palette]; [b][palette]paletteuse"
+        );
+        // NOTE: Shifted to the source video's timestamps, which the input seeking resets.
+        let settings = Settings::with_standard_fps(SAMPLE_VIDEO_PATH.into(), 200)
+            .subtitles("captions.srt".into())
+            .trim("0:01.5", "0:03");
+        assert_eq!(
+            settings.generate_filter_complex(),
+            "setpts=PTS+1.500/TB,subtitles='captions.srt',setpts=PTS-STARTPTS,fps=10,scale=200:-1[s]; [s]split[a][b]; [a]palettegen[palette]; [b][palette]paletteuse"
+        );
+        assert_eq!(settings.plan().subtitles, Some("captions.srt".into()));
+    }
+
+    #[test]
+    fn test_output_format() {
+        let settings = Settings::with_standard_fps(SAMPLE_VIDEO_PATH.into(), 200).hflip(true);
+        let webp = OutputFormat::WebP {
+            quality: 90,
+            lossless: true,
+        };
+        // NOTE: The palette stages are skipped, while the frame rate and the scaling are kept.
+        for format in [webp, OutputFormat::Apng] {
+            let settings = settings.clone().output_format(format);
+            assert_eq!(
+                settings.generate_filter_complex(),
+                "fps=10,hflip,scale=200:-1"
+            );
+            assert_eq!(settings.validate(), Ok(()));
+        }
+        let args = settings.clone().output_format(webp).generate_args(None);
+        assert_eq!(
+            args[args.len() - 11..],
+            [
+                "-c:v",
+                "libwebp_anim",
+                "-quality",
+                "90",
+                "-lossless",
+                "1",
+                "-loop",
+                "0",
+                "-f",
+                "webp",
+                "-"
+            ]
+        );
+        let args = settings
+            .clone()
+            .output_format(OutputFormat::Apng)
+            .generate_args(None);
+        assert_eq!(args[args.len() - 5..], ["-plays", "0", "-f", "apng", "-"]);
+        assert_eq!(
+            settings
+                .output_format(OutputFormat::WebP {
+                    quality: 101,
+                    lossless: false,
+                })
+                .validate(),
+            Err(SettingsError::WebPQualityOutOfRange(101))
+        );
+    }
+
+    #[test]
+    fn test_generate_filter_complex_watermark() {
+        let settings = Settings::with_standard_fps(SAMPLE_VIDEO_PATH.into(), 200).watermark(
+            "logo.png".into(),
+            Position::BottomRight,
+            0.5,
+        );
+        assert_eq!(
+            settings.generate_filter_complex(),
+            "[1:v]format=rgba,colorchannelmixer=aa=0.5[wm]; [0:v]fps=10,scale=200:-1[main]; [main][wm]overlay=W-w:H-h[s]; [s]split[a][b]; [a]palettegen[palette]; [b][palette]paletteuse"
+        );
+        // NOTE: Overlaid before the boomerang.
+        assert_eq!(
+            settings.clone().boomerang(true).generate_filter_complex(),
+            "[1:v]format=rgba,colorchannelmixer=aa=0.5[wm]; [0:v]fps=10,scale=200:-1[main]; [main][wm]overlay=W-w:H-h,split[f][r]; [r]reverse[rr]; [f][rr]concat=n=2[s]; [s]split[a][b]; [a]palettegen[palette]; [b][palette]paletteuse"
+        );
+        // NOTE: One copy of the image per output.
+        assert_eq!(
+            settings
+                .clone()
+                .additional_widths(vec![100])
+                .generate_filter_complex(),
+            "[1:v]format=rgba,colorchannelmixer=aa=0.5,split=2[o0_wm][o1_wm]; [0:v]fps=10,split=2[o0_in][o1_in]; [o0_in]null,scale=200:-1[o0_main]; [o0_main][o0_wm]overlay=W-w:H-h[o0_s]; [o0_s]split[o0_a][o0_b]; [o0_a]palettegen[o0_palette]; [o0_b][o0_palette]paletteuse[out0]; [o1_in]null,scale=100:-1[o1_main]; [o1_main][o1_wm]overlay=W-w:H-h[o1_s]; [o1_s]split[o1_a][o1_b]; [o1_a]palettegen[o1_palette]; [o1_b][o1_palette]paletteuse[out1]"
+        );
+        // NOTE: The image is read as the second input.
+        let args = settings.command_args(None);
+        let i = args
+            .iter()
+            .position(|arg| arg == "-filter_complex")
+            .unwrap();
+        assert_eq!(args[i - 4..i], ["-i", SAMPLE_VIDEO_PATH, "-i", "logo.png"]);
+
+        for opacity in [0.0, 1.0] {
+            let settings = settings
+                .clone()
+                .watermark("logo.png".into(), Position::Center, opacity);
+            assert_eq!(settings.validate(), Ok(()));
+        }
+        for opacity in [-0.1, 1.5, f32::NAN] {
+            let settings = settings
+                .clone()
+                .watermark("logo.png".into(), Position::Center, opacity);
+            assert_eq!(
+                settings.validate(),
+                Err(SettingsError::InvalidWatermarkOpacity)
+            );
+        }
+    }
+
+    #[test]
+    fn test_generate_filter_complex_palette_stats_mode() {
+        let settings = Settings::with_standard_fps(SAMPLE_VIDEO_PATH.into(), 200);
+        // NOTE: The default mode leaves both filters untouched.
+        assert_eq!(
+            settings
+                .clone()
+                .palette_stats_mode(PaletteStatsMode::Full)
+                .generate_filter_complex(),
+            settings.generate_filter_complex()
+        );
+        let cases = [
+            (
+                PaletteStatsMode::Full,
+                "[a]palettegen[palette]; [b][palette]paletteuse",
+            ),
+            (
+                PaletteStatsMode::Diff,
+                "[a]palettegen=stats_mode=diff[palette]; [b][palette]paletteuse",
+            ),
+            (
+                PaletteStatsMode::Single,
+                "[a]palettegen=stats_mode=single[palette]; [b][palette]paletteuse=new=1",
+            ),
+        ];
+        for (mode, expected) in cases {
+            let filter_complex = settings
+                .clone()
+                .palette_stats_mode(mode)
+                .generate_filter_complex();
+            assert_eq!(
+                filter_complex,
+                format!("fps=10,scale=200:-1[s]; [s]split[a][b]; {}", expected),
+                "{:?}",
+                mode
+            );
+        }
+        // NOTE: Composed with the number of colors.
+        assert!(settings
+            .max_colors(64)
+            .palette_stats_mode(PaletteStatsMode::Diff)
+            .generate_filter_complex()
+            .contains("[a]palettegen=max_colors=64:stats_mode=diff[palette]"));
+    }
+
+    #[test]
+    fn test_generate_filter_complex_frame_step() {
+        let settings = Settings::with_standard_fps(SAMPLE_VIDEO_PATH.into(), 200);
+        assert_eq!(
+            settings.clone().frame_step(30).generate_filter_complex(),
+            "select='not(mod(n,30))',setpts=N/FRAME_RATE/TB,fps=10,scale=200:-1[s]; [s]split[a][b]; [a]palettegen[palette]; [b][palette]paletteuse"
+        );
+        // NOTE: Keeping every frame leaves the graph untouched.
+        assert_eq!(
+            settings.clone().frame_step(1).generate_filter_complex(),
+            settings.generate_filter_complex()
+        );
+        // After the crop (whose timestamps are the source video's), and before the speed change,
+        // the padding lasting one GIF frame once both are applied.
+        let settings = settings
+            .frame_step(3)
+            .speed(2.0)
+            .preserve_last_frame(true)
+            .crop_keyframes(vec![(
+                std::time::Duration::ZERO,
+                CropRect::new(10, 20, 320, 180),
+            )]);
+        assert_eq!(settings.validate(), Ok(()));
+        assert_eq!(
+            settings.generate_filter_complex(),
+            "tpad=stop_mode=clone:stop_duration=0.6,crop=w=320:h=180:x='10':y='20',select='not(mod(n,3))',setpts=N/FRAME_RATE/TB,setpts=PTS/2,fps=10,scale=200:-1[s]; [s]split[a][b]; [a]palettegen[palette]; [b][palette]paletteuse"
+        );
+        // The GIF's frames are 0.6 seconds of video apart.
+        assert_eq!(
+            settings
+                .clone()
+                .emit_frame_map(true)
+                .frame_map()
+                .map(|m| m.speed),
+            Some(6.0)
+        );
+        assert_eq!(
+            settings.frame_step(0).validate(),
+            Err(SettingsError::FrameStepZero)
+        );
+    }
+
+    #[test]
+    fn test_max_output_attempts() {
+        let settings = Settings::with_standard_fps(SAMPLE_VIDEO_PATH.into(), 200);
+        assert!(settings.clone().max_output_bytes(1_000).validate().is_ok());
+        assert_eq!(
+            settings.max_output_attempts(0).validate(),
+            Err(SettingsError::MaxOutputAttemptsZero)
+        );
+    }
+
+    #[test]
+    fn test_generate_filter_complex_speed() {
+        let settings = Settings::with_standard_fps(SAMPLE_VIDEO_PATH.into(), 200);
+        for (speed, expected) in [
+            (
+                2.0,
+                "setpts=PTS/2,fps=10,scale=200:-1[s]; [s]split[a][b]; [a]palettegen[palette]; [b][palette]paletteuse",
+            ),
+            (
+                0.5,
+                "setpts=PTS/0.5,fps=10,scale=200:-1[s]; [s]split[a][b]; [a]palettegen[palette]; [b][palette]paletteuse",
+            ),
+        ] {
+            let settings = settings.clone().speed(speed);
+            assert_eq!(settings.validate(), Ok(()));
+            assert_eq!(settings.generate_filter_complex(), expected);
+        }
+        let settings = settings
+            .speed(4.0)
+            .preserve_last_frame(true)
+            .crop_keyframes(vec![(
+                std::time::Duration::ZERO,
+                CropRect::new(10, 20, 320, 180),
+            )]);
+        assert_eq!(
+            settings.generate_filter_complex(),
+            "tpad=stop_mode=clone:stop_duration=0.4,crop=w=320:h=180:x='10':y='20',setpts=PTS/4,fps=10,scale=200:-1[s]; [s]split[a][b]; [a]palettegen[palette]; [b][palette]paletteuse"
+        );
+        for speed in [0.0, 0.05, 10.5, f32::NAN] {
+            assert_eq!(
+                settings.clone().speed(speed).validate(),
+                Err(SettingsError::InvalidSpeed)
+            );
+        }
+    }
+
+    #[test]
+    fn test_generate_filter_complex_crop_keyframes() {
+        let keyframes = vec![
+            (std::time::Duration::ZERO, CropRect::new(0, 0, 320, 180)),
+            (
+                std::time::Duration::from_secs(2),
+                CropRect::new(320, 0, 320, 180),
+            ),
+        ];
+        let settings =
+            Settings::with_standard_fps(SAMPLE_VIDEO_PATH.into(), 200).crop_keyframes(keyframes);
+        assert_eq!(settings.validate(), Ok(()));
+        assert_eq!(
+            settings.generate_filter_complex(),
+            "crop=w=320:h=180:x='if(between(t,0.000,2.000),0,320)':y='0',fps=10,scale=200:-1[s]; [s]split[a][b]; [a]palettegen[palette]; [b][palette]paletteuse"
+        );
+    }
+
+    #[test]
+    fn test_generate_filter_complex_crop() {
+        let settings = Settings::with_standard_fps(SAMPLE_VIDEO_PATH.into(), 200)
+            .gif_height(100)
+            .crop(100, 200, 600, 400);
+        assert_eq!(settings.validate(), Ok(()));
+        assert_eq!(
+            settings.generate_filter_complex(),
+            "crop=w=600:h=400:x='100':y='200',fps=10,scale=200:100:force_original_aspect_ratio=decrease,pad=200:100:(ow-iw)/2:(oh-ih)/2[s]; [s]split[a][b]; [a]palettegen[palette]; [b][palette]paletteuse"
+        );
+        // Replacing any keyframes.
+        let settings = Settings::with_standard_fps(SAMPLE_VIDEO_PATH.into(), 200)
+            .crop_keyframes(vec![
+                (std::time::Duration::ZERO, CropRect::new(0, 0, 320, 180)),
+                (
+                    std::time::Duration::from_secs(2),
+                    CropRect::new(320, 0, 320, 180),
+                ),
+            ])
+            .crop(10, 20, 64, 48);
+        assert_eq!(
+            settings.generate_filter_complex(),
+            "crop=w=64:h=48:x='10':y='20',fps=10,scale=200:-1[s]; [s]split[a][b]; [a]palettegen[palette]; [b][palette]paletteuse"
+        );
+        assert_eq!(
+            Settings::with_standard_fps(SAMPLE_VIDEO_PATH.into(), 200)
+                .crop(10, 20, 0, 48)
+                .validate(),
+            Err(SettingsError::CropRectEmpty)
+        );
+    }
+
+    #[test]
+    fn test_crop_end_to_end() {
+        init_logging();
+        if !ffmpeg_available() {
+            return;
+        }
+
+        // NOTE: The sample video's frames are 640x360.
+        let settings =
+            Settings::with_standard_fps(SAMPLE_VIDEO_PATH.into(), 320).crop(100, 200, 320, 120);
+        let messages = run_to_completion(settings);
+        let bytes = success_bytes(&messages).expect("Expected a 'Success' message");
+        let info = gif_info::parse_gif_info(bytes).unwrap();
+        assert_eq!((info.width, info.height), (320, 120));
+
+        let settings =
+            Settings::with_standard_fps(SAMPLE_VIDEO_PATH.into(), 320).crop(0, 0, 5000, 400);
+        let messages = run_to_completion(settings);
+        assert!(
+            messages.iter().any(|m| matches!(
+                m,
+                Message::Error(Error::CropOutOfBounds { line }) if line.contains("'5000'")
+            )),
+            "{:?}",
+            messages
+        );
+    }
+
+    #[test]
+    fn test_crop_keyframes_end_to_end() {
+        init_logging();
+        if !ffmpeg_available() {
+            return;
+        }
+
+        // A window moving across the frame, one second at a time.
+        let keyframes = (0..5u32)
+            .map(|i| {
+                let rect = CropRect::new(i * 40, i * 20, 160, 90);
+                (std::time::Duration::from_secs(i as u64), rect)
+            })
+            .collect();
+        let settings =
+            Settings::with_standard_fps(SAMPLE_VIDEO_PATH.into(), 80).crop_keyframes(keyframes);
+        let messages = run_to_completion(settings);
+        let bytes = success_bytes(&messages).expect("Expected a 'Success' message");
+        let info = gif_info::parse_gif_info(bytes).unwrap();
+        assert_eq!(info.width, 80);
+    }
+
+    #[test]
+    fn test_generate_args() {
+        let settings = Settings::with_standard_fps(SAMPLE_VIDEO_PATH.into(), 200);
+        let args = settings.generate_args(None);
+        assert_eq!(args[..3], ["-stats", "-i", SAMPLE_VIDEO_PATH]);
+        assert_eq!(args[3], "-filter_complex");
+        assert_eq!(args[5..], ["-f", "gif", "-"]);
+    }
+
+    #[test]
+    fn test_generate_args_clip() {
+        let settings = Settings::with_standard_fps(SAMPLE_VIDEO_PATH.into(), 200);
+        let clip = ClipSelection::Range(
+            std::time::Duration::from_millis(1500),
+            std::time::Duration::from_secs(4),
+        );
+        let args = settings.clone().clip(clip).generate_args(None);
+        assert_eq!(
+            args[..7],
+            [
+                "-stats",
+                "-ss",
+                "1.500",
+                "-t",
+                "2.500",
+                "-i",
+                SAMPLE_VIDEO_PATH
+            ]
+        );
+        let clip = ClipSelection::FromStart(std::time::Duration::from_secs(2));
+        let args = settings.clip(clip).generate_args(None);
+        assert_eq!(args[1..5], ["-ss", "0.000", "-t", "2.000"]);
+    }
+
+    #[test]
+    fn test_generate_args_leading_dash() {
+        for (path, expected) in [("-i.mp4", "./-i.mp4"), ("-y", "./-y"), ("-", "./-")] {
+            let args = Settings::with_standard_fps(path.into(), 200).generate_args(None);
+            // The path is the only argument following `-i`, and no argument
+            // other than the flags themselves starts with a dash.
+            assert_eq!(args[1..3], ["-i", expected]);
+            let flags: Vec<_> = args.iter().filter(|a| a.starts_with('-')).collect();
+            assert_eq!(flags, ["-stats", "-i", "-filter_complex", "-f", "-"]);
+        }
+    }
+
+    #[test]
+    fn test_leading_dash_path_end_to_end() {
+        init_logging();
+        if !ffmpeg_available() {
+            return;
+        }
+
+        // NOTE: The copy needs to live in the current directory, since only
+        // relative paths can start with a dash.
+        let path = format!("-{}.mp4", uuid::Uuid::new_v4());
+        std::fs::copy(SAMPLE_VIDEO_PATH, &path).unwrap();
+        let messages = run_to_completion(Settings::with_standard_fps(path.clone(), 100));
+        std::fs::remove_file(&path).unwrap();
+        let bytes = success_bytes(&messages).expect("Expected a 'Success' message");
+        assert!(gif_info::parse_gif_info(bytes).is_ok());
+    }
+
+    #[test]
+    fn test_generate_args_input_format_hints() {
+        let settings = Settings::with_standard_fps("frames.rgb".into(), 200)
+            .input_format_hints(InputFormatHints::raw_video("rgb24", 1280, 720, 30.0));
+        assert_eq!(settings.validate(), Ok(()));
+        let args = settings.clone().trim("1", "3").generate_args(None);
+        let i = args.iter().position(|a| a == "-i").unwrap();
+        assert_eq!(
+            args[i - 8..i + 2],
+            [
+                "-f",
+                "rawvideo",
+                "-pixel_format",
+                "rgb24",
+                "-video_size",
+                "1280x720",
+                "-framerate",
+                "30",
+                "-i",
+                "frames.rgb"
+            ]
+        );
+        // The analysis pass reads the input the same way.
+        let analysis = settings.palette_analysis_args();
+        assert_eq!(analysis[1..11], args[i - 8..i + 2]);
+
+        let incomplete = InputFormatHints {
+            video_size: None,
+            ..InputFormatHints::raw_video("rgb24", 1280, 720, 30.0)
+        };
+        assert_eq!(
+            settings.input_format_hints(incomplete).validate(),
+            Err(SettingsError::IncompleteRawVideoHints)
+        );
+    }
+
+    #[test]
+    fn test_input_format_hints_end_to_end() {
+        init_logging();
+        if !ffmpeg_available() {
+            return;
+        }
+
+        // Two seconds of raw rgb24 frames (64x48 pixels at 10 fps), fading from black to red.
+        let frames: Vec<u8> = (0..20u8)
+            .flat_map(|i| [i * 12, 0, 0].repeat(64 * 48))
+            .collect();
+        let path = crate::test_utils::temp_dir().join("frames.rgb");
+        std::fs::write(&path, frames).unwrap();
+        let settings = Settings::with_standard_fps(path.to_string_lossy().into(), 32)
+            .input_format_hints(InputFormatHints::raw_video("rgb24", 64, 48, 10.0));
+        let messages = run_to_completion(settings);
+        let bytes = success_bytes(&messages).expect("Expected a 'Success' message");
+        let info = gif_info::parse_gif_info(bytes).unwrap();
+        assert_eq!((info.width, info.height), (32, 24));
+        assert_eq!(info.frame_count, 20);
+    }
+
+    #[test]
+    fn test_validate_max_colors() {
+        let settings = Settings::with_standard_fps(SAMPLE_VIDEO_PATH.into(), 200);
+        assert_eq!(settings.clone().validate(), Ok(()));
+        assert_eq!(settings.clone().max_colors(2).validate(), Ok(()));
+        assert_eq!(settings.clone().max_colors(256).validate(), Ok(()));
+        assert_eq!(
+            settings.clone().max_colors(1).validate(),
+            Err(SettingsError::MaxColorsOutOfRange(1))
+        );
+        assert_eq!(
+            settings.max_colors(257).validate(),
+            Err(SettingsError::MaxColorsOutOfRange(257))
+        );
+    }
+
+    #[test]
+    fn test_max_palette_bit_depth() {
+        let settings = Settings::with_standard_fps(SAMPLE_VIDEO_PATH.into(), 200);
+        for (max_colors, depth, expected) in [
+            (None, 8, None),
+            (None, 7, Some(128)),
+            (Some(200), 7, Some(128)),
+            (Some(64), 7, Some(64)),
+            (Some(2), 1, Some(2)),
+        ] {
+            let mut settings = settings.clone().max_palette_bit_depth(depth);
+            if let Some(n) = max_colors {
+                settings = settings.max_colors(n);
+            }
+            assert_eq!(settings.validate(), Ok(()));
+            assert_eq!(settings.effective_max_colors(), expected, "{:?}", settings);
+        }
+        assert!(settings
+            .clone()
+            .max_palette_bit_depth(4)
+            .generate_filter_complex()
+            .contains("[a]palettegen=max_colors=16[palette]"));
+        for depth in [0, 9] {
+            assert_eq!(
+                settings.clone().max_palette_bit_depth(depth).validate(),
+                Err(SettingsError::PaletteBitDepthOutOfRange(depth))
+            );
+        }
+    }
+
+    #[test]
+    fn test_generate_args_global_palette_only() {
+        let settings =
+            Settings::with_standard_fps(SAMPLE_VIDEO_PATH.into(), 200).global_palette_only(true);
+        let args = settings.generate_args(None);
+        assert_eq!(args[5..], ["-global_palette", "1", "-f", "gif", "-"]);
+    }
+
+    #[test]
+    fn test_trim() {
+        let secs = std::time::Duration::from_secs;
+        let settings = Settings::with_standard_fps(SAMPLE_VIDEO_PATH.into(), 200);
+        for settings in [
+            settings.clone().trim(secs(90), secs(95)),
+            settings.clone().trim("90", "1:35"),
+            settings.clone().trim("00:01:30", &String::from("95.0")),
+        ] {
+            assert_eq!(settings.validate(), Ok(()));
+            assert_eq!(
+                settings.clip,
+                Some(ClipSelection::Range(secs(90), secs(95)))
+            );
+        }
+        assert_eq!(
+            settings.clone().trim("1:30", "1:90").validate(),
+            Err(SettingsError::InvalidTimeSpec(
+                TimeSpecError::FieldOutOfRange
+            ))
+        );
+        // NOTE: The first error is kept, even if a later call succeeds.
+        let settings = settings.trim("-5", secs(5)).trim("0", "5");
+        assert_eq!(
+            settings.validate(),
+            Err(SettingsError::InvalidTimeSpec(TimeSpecError::Negative))
+        );
+    }
+
+    #[test]
+    fn test_clip_duration_and_end_time() {
+        let secs = std::time::Duration::from_secs;
+        let settings = Settings::with_standard_fps(SAMPLE_VIDEO_PATH.into(), 200);
+        let cases = [
+            (
+                settings.clone().clip_duration(secs(5)),
+                ClipSelection::FromStart(secs(5)),
+            ),
+            (
+                settings.clone().end_time(secs(5)),
+                ClipSelection::Range(secs(0), secs(5)),
+            ),
+            (
+                settings.clone().start_time(secs(90)).clip_duration(secs(5)),
+                ClipSelection::Range(secs(90), secs(95)),
+            ),
+            (
+                settings.clone().clip_duration(secs(5)).start_time(secs(90)),
+                ClipSelection::Range(secs(90), secs(95)),
+            ),
+            (
+                settings.clone().start_time(secs(90)).end_time(secs(100)),
+                ClipSelection::Range(secs(90), secs(100)),
+            ),
+            (
+                settings.clone().end_time(secs(100)).start_time(secs(90)),
+                ClipSelection::Range(secs(90), secs(100)),
+            ),
+        ];
+        for (settings, expected) in cases {
+            assert_eq!(settings.validate(), Ok(()));
+            assert_eq!(settings.clip, Some(expected));
+        }
+        let args = settings
+            .clone()
+            .start_time(secs(90))
+            .clip_duration(secs(5))
+            .generate_args(None);
+        assert_eq!(args[1..5], ["-ss", "90.000", "-t", "5.000"]);
+
+        assert_eq!(
+            settings.clone().clip_duration(secs(0)).validate(),
+            Err(SettingsError::EmptyClip)
+        );
+        assert_eq!(
+            settings
+                .clone()
+                .start_time(secs(90))
+                .end_time(secs(80))
+                .validate(),
+            Err(SettingsError::EmptyClip)
+        );
+
+        // The same values, as time specs.
+        let specs = settings.clone().start_time("1:30").clip_duration("5");
+        assert_eq!(specs.clip, Some(ClipSelection::Range(secs(90), secs(95))));
+        let specs = settings.clone().end_time("00:01:40").start_time("90");
+        assert_eq!(specs.clip, Some(ClipSelection::Range(secs(90), secs(100))));
+        assert_eq!(
+            settings
+                .clone()
+                .start_time("1:30")
+                .end_time("1:2x")
+                .validate(),
+            Err(SettingsError::InvalidTimeSpec(TimeSpecError::Malformed))
+        );
+        // An end that cannot be represented is reported instead of panicking.
+        let max = std::time::Duration::MAX;
+        for settings in [
+            settings.clone().start_time(max).clip_duration(secs(5)),
+            settings.clone().clip_duration(secs(5)).start_time(max),
+        ] {
+            assert_eq!(
+                settings.validate(),
+                Err(SettingsError::InvalidTimeSpec(TimeSpecError::Overflow))
+            );
+        }
+    }
+
+    #[test]
+    fn test_custom_filter_complex() {
+        let graph = "[0:v]zoompan=z='min(zoom+0.01,1.5)':d=1:s=320x180,drawbox=x=10:y=10:w=100:h=50:color=red,split[a][b]; [a]palettegen[p]; [b][p]paletteuse";
+        let settings =
+            Settings::with_standard_fps(SAMPLE_VIDEO_PATH.into(), 200).custom_filter_complex(graph);
+        let args = settings.generate_args(None);
+        assert_eq!(args[3..], ["-filter_complex", graph, "-f", "gif", "-"]);
+        assert_eq!(settings.plan().filter_complex, graph);
+        assert!(settings.validate().is_ok());
+    }
+
+    #[test]
+    fn test_additional_widths() {
+        let settings = Settings::with_standard_fps(SAMPLE_VIDEO_PATH.into(), 200)
+            .additional_widths(vec![400, 800])
+            .global_palette_only(true);
+        let dir = std::path::Path::new("/tmp/variants");
+        let args = settings.plan_in(Some(dir)).args;
+        let outputs = &args[args.iter().position(|a| a == "-map").unwrap()..];
+        assert_eq!(
+            outputs.join(" "),
+            "-map [out0] -global_palette 1 -f gif - -map [out1] -global_palette 1 -f gif /tmp/variants/400px.gif -map [out2] -global_palette 1 -f gif /tmp/variants/800px.gif"
+        );
+        assert!(settings
+            .generate_filter_complex()
+            .starts_with("fps=10,split=3[o0_in][o1_in][o2_in]; [o0_in]null,scale=200:-1[o0_s]"));
+        assert!(settings.validate().is_ok());
+
+        for widths in [vec![400, 400], vec![200]] {
+            assert!(matches!(
+                settings.clone().additional_widths(widths).validate(),
+                Err(SettingsError::DuplicateWidth(_))
+            ));
+        }
+    }
+
+    #[test]
+    fn test_extra_args() {
+        let settings = Settings::with_standard_fps(SAMPLE_VIDEO_PATH.into(), 200)
+            .trim("1", "3")
+            .input_format_hints(InputFormatHints {
+                format: Some("mp4".into()),
+                ..Default::default()
+            })
+            .extra_input_args(vec!["-noautorotate".into(), "-copyts".into()])
+            .extra_output_args(vec!["-loop".into(), "0".into()]);
+        let args = settings.command_args(None);
+        let filter_complex = settings.filter_complex();
+        assert_eq!(
+            args,
+            [
+                "-stats",
+                "-ss",
+                "1.000",
+                "-t",
+                "2.000",
+                "-f",
+                "mp4",
+                "-noautorotate",
+                "-copyts",
+                "-i",
+                SAMPLE_VIDEO_PATH,
+                "-filter_complex",
+                &filter_complex,
+                "-f",
+                "gif",
+                "-loop",
+                "0",
+                "-",
+            ]
+        );
+        assert_eq!(
+            settings.plan().args,
+            Vec::from_iter(args.iter().map(|arg| arg.to_str().unwrap()))
+        );
+
+        // Repeated for each output.
+        let args = settings.additional_widths(vec![400]).command_args(None);
+        let outputs = &args[args.iter().position(|a| a == "-map").unwrap()..];
+        assert_eq!(
+            outputs,
+            [
+                "-map",
+                "[out0]",
+                "-f",
+                "gif",
+                "-loop",
+                "0",
+                "-",
+                "-map",
+                "[out1]",
+                "-f",
+                "gif",
+                "-loop",
+                "0",
+                "400px.gif",
+            ]
+        );
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_extra_args_not_unicode() {
+        use std::os::unix::ffi::OsStringExt;
+
+        let arg = std::ffi::OsString::from_vec(vec![b'x', 0xff]);
+        let settings = Settings::with_standard_fps(SAMPLE_VIDEO_PATH.into(), 200)
+            .extra_output_args(vec!["-metadata".into(), arg.clone()]);
+        // Passed as is (right before the output), and only converted for the plan.
+        let args = settings.command_args(None);
+        assert_eq!(args[args.len() - 2], arg);
+        let args = settings.plan().args;
+        assert_eq!(args[args.len() - 2], "x\u{fffd}");
+    }
+
+    #[test]
+    fn test_hwaccel_args() {
+        let settings = Settings::with_standard_fps(SAMPLE_VIDEO_PATH.into(), 200)
+            .extra_input_args(vec!["-threads".into(), "2".into()]);
+        let args = settings.clone().generate_args(None);
+        assert!(!args.contains(&"-hwaccel".to_string()));
+        // An input option, so before the other input options (and the input itself).
+        let args = settings
+            .clone()
+            .hwaccel(HwAccel::Cuda)
+            .clip(ClipSelection::Range(
+                std::time::Duration::from_secs(1),
+                std::time::Duration::from_secs(3),
+            ))
+            .generate_args(None);
+        assert_eq!(
+            args[..10],
+            ["-stats", "-ss", "1.000", "-t", "2.000", "-hwaccel", "cuda", "-threads", "2", "-i"]
+        );
+        // NOTE: Left to FFmpeg when it could not be resolved (see `HwAccel::Auto`).
+        let args = settings.hwaccel(HwAccel::Auto).generate_args(None);
+        assert_eq!(args[1..4], ["-hwaccel", "auto", "-threads"]);
+    }
+
+    #[test]
+    fn test_threads() {
+        let settings = Settings::with_standard_fps(SAMPLE_VIDEO_PATH.into(), 200);
+        assert_eq!(
+            settings.clone().threads(0).generate_args(None)[..3],
+            ["-stats", "-i", SAMPLE_VIDEO_PATH]
+        );
+        let args = settings.clone().threads(2).generate_args(None);
+        assert_eq!(
+            args[..9],
+            [
+                "-stats",
+                "-filter_threads",
+                "2",
+                "-filter_complex_threads",
+                "2",
+                "-threads",
+                "2",
+                "-i",
+                SAMPLE_VIDEO_PATH
+            ]
+        );
+        // NOTE: The number of threads is an input option, so it comes after the global ones.
+        let args = settings
+            .clone()
+            .threads(1)
+            .hwaccel(HwAccel::Cuda)
+            .output_path("output.gif")
+            .generate_args(None);
+        assert_eq!(
+            args[..10],
+            [
+                "-stats",
+                "-y",
+                "-filter_threads",
+                "1",
+                "-filter_complex_threads",
+                "1",
+                "-hwaccel",
+                "cuda",
+                "-threads",
+                "1"
+            ]
+        );
+
+        assert_eq!(
+            settings.clone().threads(Settings::MAX_THREADS).validate(),
+            Ok(())
+        );
+        assert_eq!(
+            settings.threads(Settings::MAX_THREADS + 1).validate(),
+            Err(SettingsError::ThreadsOutOfRange(513))
+        );
+    }
+
+    #[test]
+    fn test_video_stream_index() {
+        let settings = Settings::with_standard_fps(SAMPLE_VIDEO_PATH.into(), 200);
+        assert!(settings
+            .generate_filter_complex()
+            .starts_with("fps=10,scale=200:-1[s]"));
+
+        let second = settings.clone().video_stream_index(1);
+        assert_eq!(
+            second.generate_filter_complex(),
+            "[0:v:1]fps=10,scale=200:-1[s]; [s]split[a][b]; [a]palettegen[palette]; [b][palette]paletteuse"
+        );
+        assert!(second
+            .clone()
+            .watermark("logo.png".into(), Position::TopLeft, 1.0)
+            .generate_filter_complex()
+            .contains("; [0:v:1]fps=10,scale=200:-1[main]; "));
+        // NOTE: The palette analysis pass uses a simple graph, so the stream is mapped instead.
+        let analysis = second.palette_analysis_args();
+        assert_eq!(
+            analysis[..6],
+            ["-nostdin", "-i", SAMPLE_VIDEO_PATH, "-map", "0:v:1", "-vf"]
+        );
+        assert!(!settings.generate_args(None).iter().any(|arg| arg == "-map"));
+    }
+
+    #[test]
+    fn test_seek_mode() {
+        let settings = Settings::with_standard_fps(SAMPLE_VIDEO_PATH.into(), 200)
+            .clip(ClipSelection::Range(
+                std::time::Duration::from_secs(3),
+                std::time::Duration::from_secs(5),
+            ))
+            .subtitles("captions.srt".into());
+        let graph = |settings: &Settings| settings.generate_filter_complex();
+
+        let args = settings.clone().generate_args(None);
+        assert_eq!(args[..6], ["-stats", "-ss", "3.000", "-t", "2.000", "-i"]);
+        assert!(!graph(&settings).contains("trim="));
+
+        let fast = settings.clone().seek_mode(SeekMode::Fast);
+        assert_eq!(
+            fast.generate_args(None)[..7],
+            [
+                "-stats",
+                "-ss",
+                "3.000",
+                "-noaccurate_seek",
+                "-t",
+                "2.000",
+                "-i"
+            ]
+        );
+        assert_eq!(graph(&fast), graph(&settings));
+
+        // NOTE: The frames before the start are dropped first, so that the subtitles are still
+        // shifted by the clip's start.
+        let accurate = settings.seek_mode(SeekMode::Accurate);
+        assert_eq!(
+            accurate.generate_args(None)[..4],
+            ["-stats", "-t", "5.000", "-i"]
+        );
+        assert!(
+            graph(&accurate)
+                .starts_with("trim=start=3.000,setpts=PTS-STARTPTS,setpts=PTS+3.000/TB,subtitles="),
+            "{}",
+            graph(&accurate)
+        );
+    }
+
+    #[test]
+    fn test_preview_settings() {
+        let settings = Settings::with_standard_fps(SAMPLE_VIDEO_PATH.into(), 400)
+            .gif_height(300)
+            .additional_widths(vec![800])
+            .badge_overlay(Text::new("rendering…"));
+        assert!(settings.preview_settings().is_none());
+
+        let settings = settings.preview_width(100);
+        let preview = settings.preview_settings().unwrap();
+        assert!(preview.preview_settings().is_none());
+        assert!(preview.additional_widths.is_empty());
+        // The preview pass stamps the badge, once scaled, while the job itself does not.
+        let has_drawtext = |settings: &Settings| {
+            let args = settings.generate_args(None);
+            let i = args
+                .iter()
+                .position(|arg| arg == "-filter_complex")
+                .unwrap();
+            args[i + 1].contains("drawtext=text='rendering…'")
+        };
+        assert!(has_drawtext(&preview));
+        assert!(preview
+            .generate_filter_complex()
+            .starts_with("fps=10,scale=100:-1,drawtext="));
+        assert!(!has_drawtext(&settings));
+    }
+
+    #[test]
+    fn test_palette_analysis_args() {
+        let settings = Settings::with_standard_fps(SAMPLE_VIDEO_PATH.into(), 200);
+        let analysis = settings.palette_analysis_args();
+        assert_eq!(
+            analysis[..5],
+            [
+                "-nostdin",
+                "-i",
+                SAMPLE_VIDEO_PATH,
+                "-vf",
+                "fps=1,scale=64:-2"
+            ]
+        );
+
+        // The same clip is sampled, once cropped and with its colors adjusted.
+        let settings = settings
+            .clip(ClipSelection::Range(
+                std::time::Duration::from_secs(3),
+                std::time::Duration::from_secs(5),
+            ))
+            .crop(10, 20, 100, 50)
+            .saturation(0.5)
+            .brightness(0.1);
+        let analysis = settings.palette_analysis_args();
+        assert_eq!(
+            analysis[..9],
+            [
+                "-nostdin",
+                "-ss",
+                "3.000",
+                "-t",
+                "2.000",
+                "-i",
+                SAMPLE_VIDEO_PATH,
+                "-vf",
+                "crop=w=100:h=50:x='10':y='20',eq=brightness=0.1:saturation=0.5,fps=1,scale=64:-2"
+            ]
+        );
+        let accurate = settings
+            .seek_mode(SeekMode::Accurate)
+            .palette_analysis_args();
+        assert_eq!(accurate[1..3], ["-t", "5.000"]);
+        assert!(accurate[6].starts_with("trim=start=3.000,setpts=PTS-STARTPTS,crop="));
+    }
+
+    #[test]
+    fn test_output_frame_limit() {
+        let settings = Settings::with_standard_fps(SAMPLE_VIDEO_PATH.into(), 200);
+        let args = settings.clone().output_frame_limit(48).generate_args(None);
+        assert_eq!(args[5..], ["-frames:v", "48", "-f", "gif", "-"]);
+        assert_eq!(
+            settings
+                .clone()
+                .output_frame_limit(48)
+                .output_frame_limit_duration(),
+            Some(std::time::Duration::from_millis(4800))
+        );
+        assert_eq!(settings.output_frame_limit_duration(), None);
+        assert_eq!(
+            Settings::with_standard_fps(SAMPLE_VIDEO_PATH.into(), 200)
+                .output_frame_limit(0)
+                .validate(),
+            Err(SettingsError::OutputFrameLimitZero)
+        );
+    }
+
+    #[test]
+    fn test_output_frame_limit_end_to_end() {
+        init_logging();
+        if !ffmpeg_available() {
+            return;
+        }
+
+        for frames in [1, 12] {
+            let settings = Settings::with_standard_fps(SAMPLE_VIDEO_PATH.into(), 100)
+                .output_frame_limit(frames);
+            let messages = run_to_completion(settings);
+            let bytes = success_bytes(&messages).expect("Expected a 'Success' message");
+            let info = gif_info::parse_gif_info(bytes).unwrap();
+            assert_eq!(info.frame_count, frames as usize);
+        }
+    }
+
+    #[test]
+    fn test_auto_end_to_end() {
+        init_logging();
+        if !ffmpeg_available() {
+            return;
+        }
+
+        let settings = Settings::auto(SAMPLE_VIDEO_PATH.into()).unwrap();
+        let info = VideoInfo::probe(SAMPLE_VIDEO_PATH, None).unwrap();
+        let plan = settings.plan();
+        assert_eq!(u32::from(plan.width), info.width.min(480));
+        assert!(plan.fps <= 15);
+        let messages = run_to_completion(settings);
+        let bytes = success_bytes(&messages).expect("Expected a 'Success' message");
+        let gif = gif_info::parse_gif_info(bytes).unwrap();
+        assert_eq!(gif.width, plan.width);
+        assert!(gif.frame_count > 1);
+
+        assert!(matches!(
+            Settings::auto("./assets/missing.mp4".into()),
+            Err(ProbeError::NoVideoStream)
+        ));
+    }
+
+    #[test]
+    fn test_global_palette_only_end_to_end() {
+        init_logging();
+        if !ffmpeg_available() {
+            return;
+        }
+
+        let settings = Settings::with_standard_fps(SAMPLE_VIDEO_PATH.into(), 100)
+            .global_palette_only(true)
+            .max_palette_bit_depth(7);
+        let messages = run_to_completion(settings);
+        let bytes = success_bytes(&messages).expect("Expected a 'Success' message");
+        let info = gif_info::parse_gif_info(bytes).unwrap();
+        assert!(info.frame_count > 1);
+        assert_eq!(info.local_palette_count, 0);
+        assert!(
+            info.global_palette_size.is_some_and(|n| n <= 128),
+            "{:?}",
+            info
+        );
+    }
+
+    #[test]
+    fn test_preserve_last_frame_end_to_end() {
+        init_logging();
+        if !ffmpeg_available() {
+            return;
+        }
+
+        // NOTE: Only the video's last frame (at 1.96 s, i.e. closer to the end of the GIF's last
+        // frame interval than to its start, so that the `fps` filter drops it) is white.
+        let video = crate::test_utils::generate_lavfi_video(
+            "color=c=black:s=32x32:r=25:d=2,drawbox=c=white:t=fill:enable='gte(t,1.95)'",
+        );
+        let convert = |preserve_last_frame: bool| {
+            let settings = Settings::with_standard_fps(video.to_string_lossy().into(), 32)
+                .preserve_last_frame(preserve_last_frame);
+            let messages = run_to_completion(settings);
+            success_bytes(&messages)
+                .expect("Expected a 'Success' message")
+                .to_vec()
+        };
+        // NOTE: The frames only hold the pixels that changed, so they are drawn onto a canvas.
+        let last_image = |bytes: &[u8]| {
+            let mut options = gif::DecodeOptions::new();
+            options.set_color_output(gif::ColorOutput::RGBA);
+            let mut decoder = options.read_info(bytes).expect("Expected a valid GIF");
+            let width = usize::from(decoder.width());
+            let mut canvas = vec![0u8; width * usize::from(decoder.height()) * 4];
+            while let Some(frame) = decoder.read_next_frame().unwrap() {
+                let (left, top) = (usize::from(frame.left), usize::from(frame.top));
+                for (n, pixel) in frame.buffer.chunks_exact(4).enumerate() {
+                    if pixel[3] == 0 {
+                        continue;
+                    }
+                    let (x, y) = (n % usize::from(frame.width), n / usize::from(frame.width));
+                    let i = ((top + y) * width + left + x) * 4;
+                    canvas[i..i + 4].copy_from_slice(pixel);
+                }
+            }
+            canvas
+        };
+
+        let without = convert(false);
+        let with = convert(true);
+        let frame_count = |bytes: &[u8]| gif_info::parse_gif_info(bytes).unwrap().frame_count;
+        log::info!(
+            "Frame count: {} (without padding), {} (with padding)",
+            frame_count(&without),
+            frame_count(&with)
+        );
+        assert!(frame_count(&with) >= frame_count(&without));
+        // The animated GIF ends with the video's last (i.e. white) frame.
+        let last = last_image(&with);
+        assert!(
+            last.chunks_exact(4)
+                .all(|pixel| pixel[..3].iter().all(|c| *c > 200)),
+            "The last frame is not white: {:?}",
+            &last[..4]
+        );
+    }
+}
+
+#[cfg(all(test, unix))]
+mod auto_colors_tests {
+    use super::*;
+    use crate::test_utils::{
+        ffmpeg_available, generate_lavfi_video, init_logging, run_to_completion,
+        run_to_completion_with, sample_gif, send_command, success_bytes, temp_dir, write_script,
+        SAMPLE_STDERR, SAMPLE_VIDEO_PATH,
+    };
+
+    /// A fake FFmpeg binary that writes `rgb` during the analysis pass (after sleeping
+    /// for `analysis_sleep` seconds), and a valid GIF during the conversion itself.
+    fn fake_ffmpeg_with_analysis(rgb: &[u8], analysis_sleep: u32) -> std::path::PathBuf {
+        let dir = temp_dir();
+        std::fs::write(dir.join("rgb.bin"), rgb).unwrap();
+        std::fs::write(dir.join("stderr.txt"), SAMPLE_STDERR).unwrap();
+        std::fs::write(dir.join("stdout.bin"), sample_gif(2, Some(0))).unwrap();
+        write_script(
+            &dir,
+            &format!(
+                r#"case "$*" in
+  *rawvideo*) sleep {sleep}; cat '{dir}/rgb.bin'; exit 0;;
+esac
+cat '{dir}/stderr.txt' >&2
+cat '{dir}/stdout.bin'"#,
+                sleep = analysis_sleep,
+                dir = dir.display()
+            ),
+        )
+    }
+
+    #[test]
+    fn test_auto_colors_scripted() {
+        init_logging();
+        let rgb: Vec<u8> = [[255u8, 0, 0], [0, 0, 255]].repeat(100).concat();
+        let settings = Settings::with_standard_fps(SAMPLE_VIDEO_PATH.into(), 200)
+            .ffmpeg_path(fake_ffmpeg_with_analysis(&rgb, 0).to_string_lossy())
+            .auto_colors(true);
+        let messages = run_to_completion(settings);
+        let selected = messages
+            .iter()
+            .position(|m| matches!(m, Message::ColorCountSelected(16)));
+        let duration = messages
+            .iter()
+            .position(|m| matches!(m, Message::VideoDuration(_)));
+        assert!(selected.is_some());
+        assert!(selected < duration);
+        assert!(success_bytes(&messages).is_some());
+    }
+
+    #[test]
+    fn test_auto_colors_cancelled_during_analysis() {
+        init_logging();
+        let settings = Settings::with_standard_fps(SAMPLE_VIDEO_PATH.into(), 200)
+            .ffmpeg_path(fake_ffmpeg_with_analysis(&[], 30).to_string_lossy())
+            .auto_colors(true);
+        let started = std::time::Instant::now();
+        let messages = run_to_completion_with(settings, |tx| {
+            std::thread::sleep(std::time::Duration::from_millis(200));
+            assert!(send_command(&tx, Command::Cancel));
+        });
+        assert!(started.elapsed() < std::time::Duration::from_secs(10));
+        assert!(matches!(
+            &messages[..],
+            [
+                Message::Error(Error::Cancelled {
+                    progress_at_cancel: None,
+                    bytes_discarded: 0
+                }),
+                Message::Summary(_),
+                Message::Done
+            ]
+        ));
+    }
+
+    #[test]
+    fn test_auto_colors_analysis_timed_out() {
+        init_logging();
+        let settings = Settings::with_standard_fps(SAMPLE_VIDEO_PATH.into(), 200)
+            .ffmpeg_path(fake_ffmpeg_with_analysis(&[], 30).to_string_lossy())
+            .auto_colors(true)
+            .auxiliary_timeout(Some(std::time::Duration::from_millis(200)));
+        let started = std::time::Instant::now();
+        let messages = run_to_completion(settings);
+        assert!(started.elapsed() < std::time::Duration::from_secs(10));
+        // The job proceeds with FFmpeg's default palette size.
+        assert!(!messages
+            .iter()
+            .any(|m| matches!(m, Message::ColorCountSelected(_))));
+        assert!(success_bytes(&messages).is_some());
+    }
+
+    #[test]
+    fn test_auto_colors_end_to_end() {
+        init_logging();
+        if !ffmpeg_available() {
+            return;
+        }
+        let path = generate_lavfi_video("color=c=red:s=64x64:d=2");
+        let settings =
+            Settings::with_standard_fps(path.to_string_lossy().into(), 64).auto_colors(true);
+        let messages = run_to_completion(settings);
+        let selected = messages
+            .iter()
+            .find_map(|m| match m {
+                Message::ColorCountSelected(n) => Some(*n),
+                _ => None,
+            })
+            .expect("Expected a 'ColorCountSelected' message");
+        assert!(selected < 256);
+        let bytes = success_bytes(&messages).expect("Expected a 'Success' message");
+        let info = gif_info::parse_gif_info(bytes).unwrap();
+        assert!(info.global_palette_size.unwrap_or(256) <= selected as usize);
+    }
+}
@@ -0,0 +1,174 @@
+//! The resolution of the FFmpeg binary's location (see [`crate::Settings::ffmpeg_location`]).
+//!
+//! The binary is looked for, in order of precedence, at the location provided by the
+//! application (either an explicit path, or a path relative to the application's own
+//! executable), and then as `ffmpeg` on the system path.
+
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+/// The location of the FFmpeg binary (see [`crate::Settings::ffmpeg_location`]).
+pub enum FfmpegLocation {
+    /// The path of the FFmpeg binary, which is used as is (i.e. it is looked up on
+    /// the system path if it does not contain any directory separator).
+    Path(String),
+    /// The path of the FFmpeg binary, relative to the directory containing the
+    /// application's executable (as returned by [`std::env::current_exe`]), which
+    /// is useful for desktop applications that ship FFmpeg next to their executable
+    /// (e.g. `ffmpeg` for `Contents/MacOS/ffmpeg` in a macOS application bundle,
+    /// `ffmpeg.exe` on Windows, or `../lib/myapp/ffmpeg` on Linux).
+    ///
+    /// NOTE: The path is resolved when the job starts, and the job fails with an
+    /// [`crate::Error::FfmpegLocation`] error if the resolved file does not exist
+    /// or is not executable.
+    RelativeToExe(PathBuf),
+}
+
+impl From<String> for FfmpegLocation {
+    fn from(path: String) -> Self {
+        Self::Path(path)
+    }
+}
+
+impl From<&str> for FfmpegLocation {
+    fn from(path: &str) -> Self {
+        Self::Path(path.into())
+    }
+}
+
+#[derive(Debug, Clone)]
+/// A problem found while resolving an [`FfmpegLocation`], which is emitted
+/// using [`crate::Error::FfmpegLocation`].
+pub enum FfmpegLocationError {
+    /// The path of the application's executable could not be found.
+    CurrentExe(Arc<std::io::Error>),
+    /// The resolved path does not exist (or is not a file).
+    Missing(PathBuf),
+    /// The resolved path is not executable.
+    NotExecutable(PathBuf),
+}
+
+impl std::error::Error for FfmpegLocationError {}
+
+impl std::fmt::Display for FfmpegLocationError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::CurrentExe(e) => write!(f, "unable to find the application's executable: {}", e),
+            Self::Missing(path) => write!(f, "FFmpeg binary not found at '{}'", path.display()),
+            Self::NotExecutable(path) => {
+                write!(f, "FFmpeg binary at '{}' is not executable", path.display())
+            }
+        }
+    }
+}
+
+impl FfmpegLocation {
+    /// Resolves the location into the path passed to [`std::process::Command::new`].
+    pub(crate) fn resolve(&self) -> Result<PathBuf, FfmpegLocationError> {
+        self.resolve_with(std::env::current_exe)
+    }
+
+    /// Same as [`FfmpegLocation::resolve`], using `current_exe` to find the
+    /// application's executable.
+    fn resolve_with(
+        &self,
+        current_exe: impl FnOnce() -> std::io::Result<PathBuf>,
+    ) -> Result<PathBuf, FfmpegLocationError> {
+        match self {
+            Self::Path(path) => Ok(PathBuf::from(path)),
+            Self::RelativeToExe(relative) => {
+                let exe =
+                    current_exe().map_err(|e| FfmpegLocationError::CurrentExe(Arc::new(e)))?;
+                let dir = exe.parent().unwrap_or(Path::new(""));
+                let path = dir.join(relative);
+                check_executable(&path)?;
+                Ok(path)
+            }
+        }
+    }
+}
+
+/// Makes sure that `path` is an existing and executable file.
+fn check_executable(path: &Path) -> Result<(), FfmpegLocationError> {
+    let metadata = match std::fs::metadata(path) {
+        Ok(m) if m.is_file() => m,
+        _ => return Err(FfmpegLocationError::Missing(path.into())),
+    };
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::PermissionsExt;
+        if metadata.permissions().mode() & 0o111 == 0 {
+            return Err(FfmpegLocationError::NotExecutable(path.into()));
+        }
+    }
+    #[cfg(not(unix))]
+    let _ = metadata;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_utils::temp_dir;
+
+    #[test]
+    fn test_path_is_used_as_is() {
+        let location = FfmpegLocation::from("ffmpeg");
+        let resolved = location.resolve_with(|| unreachable!()).unwrap();
+        assert_eq!(resolved, PathBuf::from("ffmpeg"));
+    }
+
+    #[test]
+    fn test_relative_to_exe() {
+        let dir = temp_dir();
+        let exe = dir.join("myapp");
+        std::fs::create_dir_all(dir.join("lib/myapp")).unwrap();
+        let ffmpeg = dir.join("lib/myapp/ffmpeg");
+        std::fs::write(&ffmpeg, "").unwrap();
+        #[cfg(unix)]
+        {
+            use std::os::unix::fs::PermissionsExt;
+            std::fs::set_permissions(&ffmpeg, std::fs::Permissions::from_mode(0o755)).unwrap();
+        }
+
+        let location = FfmpegLocation::RelativeToExe("lib/myapp/ffmpeg".into());
+        let resolved = location.resolve_with(|| Ok(exe.clone())).unwrap();
+        assert_eq!(resolved, ffmpeg);
+    }
+
+    #[test]
+    fn test_relative_to_exe_missing() {
+        let dir = temp_dir();
+        let location = FfmpegLocation::RelativeToExe("ffmpeg".into());
+        match location.resolve_with(|| Ok(dir.join("myapp"))) {
+            Err(FfmpegLocationError::Missing(path)) => assert_eq!(path, dir.join("ffmpeg")),
+            other => panic!("Unexpected result: {:?}", other),
+        }
+        // A directory is not a binary.
+        std::fs::create_dir(dir.join("ffmpeg")).unwrap();
+        assert!(matches!(
+            location.resolve_with(|| Ok(dir.join("myapp"))),
+            Err(FfmpegLocationError::Missing(_))
+        ));
+        assert!(matches!(
+            location.resolve_with(|| Err(std::io::Error::other("no exe"))),
+            Err(FfmpegLocationError::CurrentExe(_))
+        ));
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_relative_to_exe_not_executable() {
+        let dir = temp_dir();
+        std::fs::write(dir.join("ffmpeg"), "").unwrap();
+        let location = FfmpegLocation::RelativeToExe("ffmpeg".into());
+        let result = location.resolve_with(|| Ok(dir.join("myapp")));
+        match result {
+            Err(e @ FfmpegLocationError::NotExecutable(_)) => {
+                assert!(e.to_string().contains("is not executable"))
+            }
+            other => panic!("Unexpected result: {:?}", other),
+        }
+    }
+}
@@ -0,0 +1,416 @@
+//! The upper bound on the memory used by a conversion job (see [`crate::Settings::memory_budget`]),
+//! which matters when many jobs run side by side (e.g. in a multi-tenant service).
+//!
+//! The [`MemoryBudget`] tracker, shared by the job's threads, accounts for the buffers
+//! that grow with the job: the output read from FFmpeg's `stdout` (see [`OutputBuffer`]),
+//! which spills over to a temporary file once keeping it in memory would exceed the budget,
+//! and the lines written by FFmpeg to `stderr` that are kept for [`crate::Warning::DirtyExit`],
+//! which may only use a share of the budget. It also thins out the progress messages (and
+//! the output size reports), so that the number of them that can pile up in the channel
+//! (when the application reads it slowly, or not at all until the end) is bounded, whatever
+//! the channel.
+
+use std::io::Write;
+use std::sync::atomic::{AtomicU8, AtomicUsize, Ordering};
+use std::sync::Arc;
+
+use crate::{Error, Message};
+
+/// The share of the budget (as a divisor) that the kept `stderr` lines may use.
+const STDERR_SHARE: usize = 8;
+/// The share of the budget (as a divisor) that the progress messages may use.
+const PROGRESS_SHARE: usize = 16;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+/// A measure taken to keep a conversion job within its memory budget (see
+/// [`crate::Settings::memory_budget`] and [`crate::Warning::MemoryBudgetSpill`]).
+pub enum BudgetMeasure {
+    /// The output read from FFmpeg's `stdout` was moved to a temporary file, from which
+    /// it gets read back (in one go) once complete.
+    StdoutSpilledToDisk,
+    /// Some of the lines written by FFmpeg to `stderr` were not kept, so that fewer
+    /// lines are included in [`crate::Warning::DirtyExit`].
+    StderrTruncated,
+    /// Some of the progress messages (i.e. [`crate::Message::Progress`] and
+    /// [`crate::Message::InterpolatedProgress`]) were not sent.
+    ProgressThinned,
+}
+
+impl BudgetMeasure {
+    fn bit(self) -> u8 {
+        1 << self as u8
+    }
+}
+
+#[derive(Debug)]
+/// Keeps track of the memory used by a job's buffers, relative to its budget.
+pub(crate) struct MemoryBudget {
+    /// The budget, in bytes.
+    limit: usize,
+    /// The number of bytes currently reserved, `stderr_used` included.
+    used: AtomicUsize,
+    /// The number of bytes currently reserved for the kept `stderr` lines.
+    stderr_used: AtomicUsize,
+    /// The measures taken so far (one bit per [`BudgetMeasure`]).
+    engaged: AtomicU8,
+    /// The last progress step admitted, plus one (zero meaning none yet).
+    progress_step: AtomicUsize,
+    /// The value of `progress_step` when the last output size report was admitted.
+    output_bytes_step: AtomicUsize,
+}
+
+impl MemoryBudget {
+    pub(crate) fn new(limit: usize) -> Self {
+        Self {
+            limit,
+            used: AtomicUsize::new(0),
+            stderr_used: AtomicUsize::new(0),
+            engaged: AtomicU8::new(0),
+            progress_step: AtomicUsize::new(0),
+            output_bytes_step: AtomicUsize::new(0),
+        }
+    }
+
+    /// The budget, in bytes.
+    pub(crate) fn limit(&self) -> usize {
+        self.limit
+    }
+
+    #[cfg(test)]
+    /// The number of bytes currently reserved.
+    pub(crate) fn used(&self) -> usize {
+        self.used.load(Ordering::SeqCst)
+    }
+
+    /// Reserves `n` bytes, returning `false` (without reserving anything) if that
+    /// would exceed the budget.
+    pub(crate) fn try_reserve(&self, n: usize) -> bool {
+        reserve(&self.used, n, self.limit)
+    }
+
+    /// Releases `n` bytes, previously reserved using [`MemoryBudget::try_reserve`].
+    pub(crate) fn release(&self, n: usize) {
+        release(&self.used, n);
+    }
+
+    /// Same as [`MemoryBudget::try_reserve`], for the kept `stderr` lines, which may
+    /// only use a share of the budget.
+    pub(crate) fn try_reserve_stderr(&self, n: usize) -> bool {
+        if !reserve(&self.stderr_used, n, self.limit / STDERR_SHARE) {
+            return false;
+        }
+        if !self.try_reserve(n) {
+            release(&self.stderr_used, n);
+            return false;
+        }
+        true
+    }
+
+    /// Releases `n` bytes, previously reserved using [`MemoryBudget::try_reserve_stderr`].
+    pub(crate) fn release_stderr(&self, n: usize) {
+        release(&self.stderr_used, n);
+        self.release(n);
+    }
+
+    /// The number of steps into which the progress (from 0.0 to 1.0) is divided, at most
+    /// one progress message (and one output size report) being sent per step, so that no
+    /// more than the budget's share of them can ever pile up in the channel.
+    pub(crate) fn progress_steps(&self) -> usize {
+        (self.limit / PROGRESS_SHARE / (2 * std::mem::size_of::<Message>())).max(1)
+    }
+
+    /// Whether a progress message for `progress` may be sent, i.e. whether it reaches
+    /// a step that no previous progress message reached.
+    pub(crate) fn admit_progress(&self, progress: f64) -> bool {
+        let steps = self.progress_steps();
+        let step = (progress.clamp(0.0, 1.0) * steps as f64).floor() as usize + 1;
+        self.progress_step.fetch_max(step, Ordering::SeqCst) < step
+    }
+
+    /// Whether an output size report (i.e. [`crate::Message::OutputBytes`]) may be sent,
+    /// i.e. whether a new progress step was reached since the last one.
+    pub(crate) fn admit_output_bytes(&self) -> bool {
+        let step = self.progress_step.load(Ordering::SeqCst);
+        self.output_bytes_step.swap(step, Ordering::SeqCst) < step
+    }
+
+    /// Records that `measure` was taken, returning `true` the first time only (i.e.
+    /// when [`crate::Warning::MemoryBudgetSpill`] should be emitted).
+    pub(crate) fn engage(&self, measure: BudgetMeasure) -> bool {
+        self.engaged.fetch_or(measure.bit(), Ordering::SeqCst) & measure.bit() == 0
+    }
+}
+
+/// Adds `n` to `counter`, unless that would make it exceed `limit`.
+fn reserve(counter: &AtomicUsize, n: usize, limit: usize) -> bool {
+    counter
+        .fetch_update(Ordering::SeqCst, Ordering::SeqCst, |used| {
+            used.checked_add(n).filter(|used| *used <= limit)
+        })
+        .is_ok()
+}
+
+/// Subtracts `n` from `counter`.
+fn release(counter: &AtomicUsize, n: usize) {
+    let _ = counter.fetch_update(Ordering::SeqCst, Ordering::SeqCst, |used| {
+        Some(used.saturating_sub(n))
+    });
+}
+
+#[derive(Debug)]
+/// The temporary file to which an [`OutputBuffer`] spilled over.
+struct Spill {
+    path: std::path::PathBuf,
+    file: std::fs::File,
+}
+
+#[derive(Debug, Default)]
+/// The buffer into which the output read from FFmpeg's `stdout` is collected, which moves
+/// it to a temporary file once keeping it in memory would exceed the [`MemoryBudget`]
+/// (if any).
+pub(crate) struct OutputBuffer {
+    budget: Option<Arc<MemoryBudget>>,
+    memory: Vec<u8>,
+    /// The number of bytes reserved from the budget for `memory`.
+    reserved: usize,
+    spill: Option<Spill>,
+    len: usize,
+}
+
+impl OutputBuffer {
+    pub(crate) fn new(budget: Option<Arc<MemoryBudget>>) -> Self {
+        Self {
+            budget,
+            memory: vec![],
+            reserved: 0,
+            spill: None,
+            len: 0,
+        }
+    }
+
+    /// The number of bytes collected so far.
+    pub(crate) fn len(&self) -> usize {
+        self.len
+    }
+
+    /// Appends `chunk`, moving the data to a temporary file first if keeping it in memory
+    /// would exceed the budget, in which case `true` is returned (the first time only).
+    /// Fails with [`Error::OutputSpill`] if the temporary file cannot be written.
+    pub(crate) fn extend(&mut self, chunk: &[u8]) -> Result<bool, Error> {
+        self.len += chunk.len();
+        if let Some(spill) = self.spill.as_mut() {
+            spill.file.write_all(chunk).map_err(spill_error)?;
+            return Ok(false);
+        }
+        let Some(budget) = self.budget.as_ref() else {
+            self.memory.extend_from_slice(chunk);
+            return Ok(false);
+        };
+        let needed = self.memory.len() + chunk.len();
+        if needed > self.reserved {
+            // NOTE: Growing like `Vec` would (i.e. doubling the capacity), if the budget allows.
+            let capacity = [needed.max(self.reserved * 2), needed]
+                .into_iter()
+                .find(|capacity| budget.try_reserve(capacity - self.reserved));
+            let Some(capacity) = capacity else {
+                self.spill(chunk).map_err(spill_error)?;
+                return Ok(true);
+            };
+            self.memory.reserve_exact(capacity - self.memory.len());
+            self.reserved = capacity;
+        }
+        self.memory.extend_from_slice(chunk);
+        Ok(false)
+    }
+
+    /// Moves the data collected so far, followed by `chunk`, to a new temporary file.
+    fn spill(&mut self, chunk: &[u8]) -> std::io::Result<()> {
+        let path =
+            std::env::temp_dir().join(format!("ffmpeg_gif_maker-{}.spill", uuid::Uuid::new_v4()));
+        let mut file = std::fs::File::options()
+            .write(true)
+            .create_new(true)
+            .open(&path)?;
+        // NOTE: Set right away, so that the file gets removed even if writing to it fails.
+        let memory = std::mem::take(&mut self.memory);
+        self.spill = Some(Spill {
+            path,
+            file: file.try_clone()?,
+        });
+        self.release();
+        file.write_all(&memory)?;
+        file.write_all(chunk)
+    }
+
+    /// Releases the bytes reserved for `memory`.
+    fn release(&mut self) {
+        if let Some(budget) = self.budget.as_ref() {
+            budget.release(self.reserved);
+        }
+        self.reserved = 0;
+    }
+
+    /// The bytes collected, read back from the temporary file (which is then removed)
+    /// if the data was spilled over, which fails with [`Error::OutputSpill`] if it cannot be.
+    pub(crate) fn into_bytes(mut self) -> Result<Vec<u8>, Error> {
+        match self.spill.as_mut() {
+            Some(spill) => spill
+                .file
+                .flush()
+                .and_then(|_| std::fs::read(&spill.path))
+                .map_err(spill_error),
+            None => Ok(std::mem::take(&mut self.memory)),
+        }
+    }
+}
+
+fn spill_error(e: std::io::Error) -> Error {
+    Error::OutputSpill(Arc::new(e))
+}
+
+impl Drop for OutputBuffer {
+    fn drop(&mut self) {
+        self.release();
+        if let Some(spill) = self.spill.take() {
+            drop(spill.file);
+            let _ = std::fs::remove_file(spill.path);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_memory_budget_reserve() {
+        let budget = MemoryBudget::new(1000);
+        assert!(budget.try_reserve(600));
+        assert!(!budget.try_reserve(500));
+        assert_eq!(budget.used(), 600);
+        budget.release(200);
+        assert!(budget.try_reserve(600));
+        assert_eq!(budget.used(), 1000);
+        assert!(!budget.try_reserve(1));
+        budget.release(2000);
+        assert_eq!(budget.used(), 0);
+        assert_eq!(budget.limit(), 1000);
+    }
+
+    #[test]
+    fn test_memory_budget_stderr_share() {
+        let budget = MemoryBudget::new(8000);
+        assert!(budget.try_reserve_stderr(600));
+        assert!(!budget.try_reserve_stderr(500));
+        assert!(budget.try_reserve_stderr(400));
+        assert_eq!(budget.used(), 1000);
+        budget.release_stderr(600);
+        assert!(budget.try_reserve(7400));
+        // NOTE: Within the share, but not within what is left of the budget.
+        assert!(!budget.try_reserve_stderr(500));
+        assert!(budget.try_reserve_stderr(200));
+        assert_eq!(budget.used(), 8000);
+    }
+
+    #[test]
+    fn test_memory_budget_progress() {
+        let size = std::mem::size_of::<Message>();
+        assert_eq!(MemoryBudget::new(0).progress_steps(), 1);
+        let budget = MemoryBudget::new(PROGRESS_SHARE * 2 * size * 10);
+        assert_eq!(budget.progress_steps(), 10);
+        assert!(budget.admit_progress(0.0));
+        assert!(!budget.admit_progress(0.05));
+        assert!(budget.admit_progress(0.1));
+        assert!(!budget.admit_progress(0.05));
+        assert!(budget.admit_progress(0.55));
+        assert!(!budget.admit_progress(0.59));
+        assert!(budget.admit_progress(1.0));
+        assert!(!budget.admit_progress(1.0));
+        // NOTE: So at most `steps + 1` progress messages are ever sent.
+        let admitted = (0..=1000)
+            .filter(|i| budget.admit_progress(*i as f64 / 1000.0))
+            .count();
+        assert_eq!(admitted, 0);
+    }
+
+    #[test]
+    fn test_memory_budget_output_bytes() {
+        let budget = MemoryBudget::new(PROGRESS_SHARE * 2 * std::mem::size_of::<Message>() * 10);
+        assert!(!budget.admit_output_bytes());
+        assert!(budget.admit_progress(0.0));
+        assert!(budget.admit_output_bytes());
+        assert!(!budget.admit_output_bytes());
+        assert!(budget.admit_progress(0.5));
+        assert!(budget.admit_output_bytes());
+        assert!(!budget.admit_output_bytes());
+    }
+
+    #[test]
+    fn test_memory_budget_engage() {
+        let budget = MemoryBudget::new(0);
+        assert!(budget.engage(BudgetMeasure::StderrTruncated));
+        assert!(!budget.engage(BudgetMeasure::StderrTruncated));
+        assert!(budget.engage(BudgetMeasure::StdoutSpilledToDisk));
+        assert!(budget.engage(BudgetMeasure::ProgressThinned));
+        assert!(!budget.engage(BudgetMeasure::StdoutSpilledToDisk));
+    }
+
+    #[test]
+    fn test_output_buffer_without_budget() {
+        let mut buf = OutputBuffer::default();
+        assert!(!buf.extend(&[1; 100_000]).unwrap());
+        assert!(!buf.extend(&[2; 10]).unwrap());
+        assert_eq!(buf.len(), 100_010);
+        let bytes = buf.into_bytes().unwrap();
+        assert_eq!(bytes.len(), 100_010);
+        assert_eq!(bytes[100_000..], [2; 10]);
+    }
+
+    #[test]
+    fn test_output_buffer_within_budget() {
+        let budget = Arc::new(MemoryBudget::new(1000));
+        let mut buf = OutputBuffer::new(Some(Arc::clone(&budget)));
+        for i in 0..10 {
+            assert!(!buf.extend(&[i; 50]).unwrap());
+        }
+        assert!(budget.used() >= 500);
+        assert!(buf.spill.is_none());
+        let bytes = buf.into_bytes().unwrap();
+        assert_eq!(bytes.len(), 500);
+        assert_eq!(budget.used(), 0);
+    }
+
+    #[test]
+    fn test_output_buffer_spill() {
+        let budget = Arc::new(MemoryBudget::new(1000));
+        assert!(budget.try_reserve_stderr(100));
+        let mut buf = OutputBuffer::new(Some(Arc::clone(&budget)));
+        let expected: Vec<u8> = (0..5000).map(|i| (i % 251) as u8).collect();
+        let spilled = expected
+            .chunks(300)
+            .map(|chunk| buf.extend(chunk).unwrap())
+            .collect::<Vec<_>>();
+        assert_eq!(spilled.iter().filter(|s| **s).count(), 1);
+        assert!(spilled[0..3].iter().all(|s| !s));
+        // NOTE: The memory reserved for the output is released once spilled over.
+        assert_eq!(budget.used(), 100);
+        assert_eq!(buf.len(), 5000);
+        let path = buf.spill.as_ref().unwrap().path.clone();
+        assert!(path.exists());
+        assert_eq!(buf.into_bytes().unwrap(), expected);
+        assert!(!path.exists());
+        assert_eq!(budget.used(), 100);
+    }
+
+    #[test]
+    fn test_output_buffer_spill_read_back_failure() {
+        let mut buf = OutputBuffer::new(Some(Arc::new(MemoryBudget::new(100))));
+        assert!(buf.extend(&[1; 500]).unwrap());
+        // NOTE: E.g. removed by a temporary files cleaner while the job was running.
+        std::fs::remove_file(&buf.spill.as_ref().unwrap().path).unwrap();
+        assert!(matches!(
+            buf.into_bytes(),
+            Err(Error::OutputSpill(e)) if e.kind() == std::io::ErrorKind::NotFound
+        ));
+    }
+}
@@ -0,0 +1,311 @@
+//! Blocking helpers for consuming the [`Message`]'s sent by the [`crate::Converter`]
+//! (only available without the `tokio` and `async-channel` feature flags), which take
+//! care of the usual receive loop and of its termination condition (i.e. [`Message::Done`]).
+
+use std::ops::ControlFlow;
+
+use crate::{Command, Converter, Error, Message, MessageReceiver, Settings};
+
+/// A blocking iterator over the [`Message`]'s received from a [`MessageReceiver`],
+/// which yields the messages until (and including) [`Message::Done`], and then
+/// returns `None` (without waiting for the sender to be dropped). It also returns
+/// `None` if the channel is closed before [`Message::Done`] is received.
+///
+/// ```no_run
+/// use ffmpeg_gif_maker::{Converter, Message, Messages, Settings};
+///
+/// let (converter, _tx, rx) = Converter::new_with_channels();
+/// let settings = Settings::with_standard_fps("video.mp4".into(), 200);
+/// std::thread::spawn(move || converter.convert(settings));
+/// for message in Messages::new(rx) {
+///     if let Message::Progress(p) = message {
+///         println!("Progress: {:.1}%", p * 100.0);
+///     }
+/// }
+/// ```
+pub struct Messages {
+    receiver: MessageReceiver,
+    done: bool,
+}
+
+impl Messages {
+    /// Wraps the `receiver` returned by [`crate::Converter::new_with_channels`]
+    /// (or by [`crate::Converter::new_with_split_channels`]).
+    pub fn new(receiver: MessageReceiver) -> Self {
+        Self {
+            receiver,
+            done: false,
+        }
+    }
+}
+
+impl From<MessageReceiver> for Messages {
+    fn from(receiver: MessageReceiver) -> Self {
+        Self::new(receiver)
+    }
+}
+
+impl Iterator for Messages {
+    type Item = Message;
+
+    fn next(&mut self) -> Option<Message> {
+        if self.done {
+            return None;
+        }
+        match self.receiver.recv() {
+            Ok(message) => {
+                self.done = matches!(message, Message::Done);
+                Some(message)
+            }
+            Err(_) => {
+                self.done = true;
+                None
+            }
+        }
+    }
+}
+
+impl std::iter::FusedIterator for Messages {}
+
+/// Blocks until the end of the conversion job whose messages are received from
+/// `receiver`, and returns the animated GIF's bytes (i.e. [`Message::Success`]) or
+/// the first error sent by the [`crate::Converter`] (e.g. [`Error::Cancelled`]).
+/// [`Error::MissingResult`] is returned if the job ended (or the channel was closed)
+/// without sending either of them.
+pub fn wait_for_result(receiver: MessageReceiver) -> Result<Vec<u8>, Error> {
+    collect_result(Messages::new(receiver), |_| {})
+}
+
+/// Runs the conversion job described by `settings` on a new thread, and blocks until its
+/// end, like [`wait_for_result`], while calling `on_progress` with each progress value
+/// (see [`Message::Progress`] and [`Message::InterpolatedProgress`]).
+///
+/// Returning [`ControlFlow::Break`] from `on_progress` cancels the job (i.e. sends
+/// [`Command::Cancel`]), in which case [`Error::Cancelled`] is returned as soon as the
+/// [`crate::Converter`] has stopped FFmpeg. The callback is not called again after that.
+///
+/// ```no_run
+/// use std::ops::ControlFlow;
+///
+/// use ffmpeg_gif_maker::{convert_with_progress, Settings};
+///
+/// let settings = Settings::with_standard_fps("video.mp4".into(), 200);
+/// let result = convert_with_progress(settings, |p| {
+///     println!("Progress: {:.1}%", p * 100.0);
+///     ControlFlow::Continue(())
+/// });
+/// ```
+pub fn convert_with_progress(
+    settings: Settings,
+    mut on_progress: impl FnMut(f64) -> ControlFlow<()>,
+) -> Result<Vec<u8>, Error> {
+    let (converter, tx, rx) = Converter::new_with_channels();
+    let handle = std::thread::spawn(move || converter.convert(settings));
+    let mut cancelled = false;
+    let result = collect_result(Messages::new(rx), |message| match message {
+        Message::Progress(p) | Message::InterpolatedProgress(p)
+            if !cancelled && on_progress(*p).is_break() =>
+        {
+            cancelled = true;
+            // NOTE: The job may have ended in the meantime, in which case the
+            // command is simply never received.
+            let _ = tx.send(Command::Cancel);
+        }
+        _ => {}
+    });
+    if handle.join().is_err() {
+        log::error!("The conversion thread panicked");
+    }
+    result
+}
+
+/// The result of the conversion job whose `messages` are consumed (see [`wait_for_result`]),
+/// after each of them has been passed to `inspect`.
+fn collect_result(messages: Messages, mut inspect: impl FnMut(&Message)) -> Result<Vec<u8>, Error> {
+    let mut bytes = None;
+    let mut error = None;
+    for message in messages {
+        inspect(&message);
+        match message {
+            Message::Success(b) => bytes = Some(b),
+            Message::Error(e) => {
+                error.get_or_insert(e);
+            }
+            _ => {}
+        }
+    }
+    match (error, bytes) {
+        (Some(e), _) => Err(e),
+        (None, Some(bytes)) => Ok(bytes),
+        (None, None) => Err(Error::MissingResult),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_utils::{init_logging, SAMPLE_VIDEO_PATH};
+
+    #[test]
+    fn test_stops_after_done() {
+        let (tx, rx) = std::sync::mpsc::channel();
+        tx.send(Message::Progress(0.5)).unwrap();
+        tx.send(Message::Done).unwrap();
+        tx.send(Message::Progress(1.0)).unwrap();
+        let mut messages = Messages::new(rx);
+        assert!(matches!(messages.next(), Some(Message::Progress(_))));
+        assert!(matches!(messages.next(), Some(Message::Done)));
+        // The sender is still alive, so this would block if the iterator tried to receive.
+        assert!(messages.next().is_none());
+        assert!(messages.next().is_none());
+        drop(tx);
+    }
+
+    #[test]
+    fn test_stops_when_disconnected() {
+        let (tx, rx) = std::sync::mpsc::channel();
+        tx.send(Message::Progress(0.5)).unwrap();
+        drop(tx);
+        let messages: Vec<_> = Messages::from(rx).collect();
+        assert_eq!(messages.len(), 1);
+
+        let (tx, rx) = std::sync::mpsc::channel::<Message>();
+        drop(tx);
+        assert!(matches!(wait_for_result(rx), Err(Error::MissingResult)));
+    }
+
+    #[test]
+    fn test_missing_result() {
+        let (tx, rx) = std::sync::mpsc::channel();
+        tx.send(Message::Progress(1.0)).unwrap();
+        tx.send(Message::Done).unwrap();
+        assert!(matches!(wait_for_result(rx), Err(Error::MissingResult)));
+    }
+
+    #[cfg(unix)]
+    mod scripted {
+        use super::*;
+        use crate::test_utils::{
+            fake_ffmpeg, fake_ffmpeg_with_progress, fake_ffmpeg_with_progress_script, sample_gif,
+            SAMPLE_STDERR,
+        };
+
+        fn scripted_settings(path: std::path::PathBuf) -> Settings {
+            Settings::with_standard_fps(SAMPLE_VIDEO_PATH.into(), 200)
+                .ffmpeg_path(path.to_string_lossy())
+        }
+
+        #[test]
+        fn test_success() {
+            init_logging();
+
+            let gif = sample_gif(3, Some(0));
+            let (converter, tx, rx) = Converter::new_with_channels();
+            let settings = scripted_settings(fake_ffmpeg(SAMPLE_STDERR, &gif, 0));
+            let handle = std::thread::spawn(move || converter.convert(settings));
+            let messages: Vec<_> = Messages::new(rx).collect();
+            handle.join().unwrap();
+            drop(tx);
+            assert!(matches!(messages.last(), Some(Message::Done)));
+            assert_eq!(
+                messages
+                    .iter()
+                    .filter(|m| matches!(m, Message::Done))
+                    .count(),
+                1
+            );
+            assert!(messages.iter().any(|m| matches!(m, Message::Success(_))));
+
+            let (converter, _tx, rx) = Converter::new_with_channels();
+            let settings = scripted_settings(fake_ffmpeg(SAMPLE_STDERR, &gif, 0));
+            std::thread::spawn(move || converter.convert(settings));
+            assert_eq!(wait_for_result(rx).unwrap(), gif);
+        }
+
+        #[test]
+        fn test_error() {
+            init_logging();
+
+            let (converter, _tx, rx) = Converter::new_with_channels();
+            let settings = scripted_settings(fake_ffmpeg(SAMPLE_STDERR, b"", 0));
+            std::thread::spawn(move || converter.convert(settings));
+            assert!(matches!(wait_for_result(rx), Err(Error::EmptyStdout)));
+
+            let (converter, _tx, rx) = Converter::new_with_channels();
+            let settings = scripted_settings(fake_ffmpeg(SAMPLE_STDERR, b"", 0)).max_colors(1);
+            std::thread::spawn(move || converter.convert(settings));
+            assert!(matches!(
+                wait_for_result(rx),
+                Err(Error::InvalidSettings(_))
+            ));
+        }
+
+        #[test]
+        fn test_cancelled() {
+            init_logging();
+
+            let path = fake_ffmpeg_with_progress_script(4, 0.2, "", "sleep 3");
+            let (converter, tx, rx) = Converter::new_with_channels();
+            let settings = scripted_settings(path);
+            let handle = std::thread::spawn(move || converter.convert(settings));
+            let mut messages = Messages::new(rx);
+            // Cancelling as soon as the first progress value is received.
+            for message in messages.by_ref() {
+                if let Message::Progress(_) = message {
+                    tx.send(Command::Cancel).unwrap();
+                    break;
+                }
+            }
+            let rest: Vec<_> = messages.collect();
+            handle.join().unwrap();
+            assert!(rest
+                .iter()
+                .any(|m| matches!(m, Message::Error(Error::Cancelled { .. }))));
+            assert!(matches!(rest.last(), Some(Message::Done)));
+
+            let (converter, tx, rx) = Converter::new_with_channels();
+            let path = fake_ffmpeg_with_progress_script(4, 0.2, "", "sleep 3");
+            let settings = scripted_settings(path);
+            std::thread::spawn(move || converter.convert(settings));
+            tx.send(Command::Cancel).unwrap();
+            assert!(matches!(wait_for_result(rx), Err(Error::Cancelled { .. })));
+        }
+
+        #[test]
+        fn test_convert_with_progress() {
+            init_logging();
+
+            let path = fake_ffmpeg_with_progress(4, 0.05);
+            let mut values = Vec::new();
+            let result = convert_with_progress(scripted_settings(path), |p| {
+                values.push(p);
+                ControlFlow::Continue(())
+            });
+            assert!(result.is_ok());
+            assert!(!values.is_empty());
+            assert!(values.windows(2).all(|w| w[0] <= w[1]));
+
+            // Cancelling from the callback, once the job is halfway through.
+            let path = fake_ffmpeg_with_progress_script(4, 0.2, "", "sleep 5");
+            let mut broke = false;
+            let started = std::time::Instant::now();
+            let result = convert_with_progress(scripted_settings(path), |p| {
+                // The callback is not called again once it has cancelled the job.
+                assert!(!broke);
+                broke = p >= 0.5;
+                if broke {
+                    ControlFlow::Break(())
+                } else {
+                    ControlFlow::Continue(())
+                }
+            });
+            assert!(matches!(
+                result,
+                Err(Error::Cancelled { progress_at_cancel: Some(p), .. }) if p >= 0.5
+            ));
+            // Well before the (at least 5 seconds long) job would have ended.
+            assert!(started.elapsed() < std::time::Duration::from_secs(3));
+            assert!(broke);
+        }
+    }
+}
@@ -0,0 +1,282 @@
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+
+use crate::bounded_channel::BoundedMessageSender;
+use crate::converter::{MessageSender, ProgressSender};
+use crate::{
+    v2, DurationSource, Error, JobId, Message, ParseProvenance, ResourceUsage, Summary, Warning,
+};
+
+const LOG_TARGET: &str = "ffmpeg_gif_maker::outbox";
+
+#[cfg(not(any(feature = "tokio", feature = "async-channel")))]
+/// The error returned when a [`Message`] cannot be sent down the channel.
+pub(crate) type SendError = std::sync::mpsc::SendError<Message>;
+#[cfg(feature = "tokio")]
+/// The error returned when a [`Message`] cannot be sent down the channel.
+pub(crate) type SendError = tokio::sync::mpsc::error::SendError<Message>;
+#[cfg(feature = "async-channel")]
+/// The error returned when a [`Message`] cannot be sent down the channel.
+pub(crate) type SendError = async_channel::SendError<Message>;
+
+#[derive(Debug, Default)]
+/// What has been sent down the channel so far, as far as the [`Summary`] is concerned.
+struct Record {
+    output_bytes: Option<usize>,
+    error: Option<Error>,
+    warnings: Vec<Warning>,
+    last_progress: Option<f64>,
+    resource_usage: Option<ResourceUsage>,
+    output_path: Option<std::path::PathBuf>,
+    duration_source: Option<DurationSource>,
+    provenance: Vec<ParseProvenance>,
+    /// What the messages' new data (see [`v2::Message`]) comes from.
+    context: v2::Context,
+}
+
+#[derive(Debug, Clone)]
+/// The channel down which the messages end up.
+enum Sink {
+    /// A legacy channel, which receives the messages' legacy shape (see [`Message`]).
+    Legacy(MessageSender),
+    /// A bounded legacy channel (see [`Outbox::bounded`]).
+    Bounded(BoundedMessageSender),
+    #[cfg(feature = "v2-messages")]
+    /// A channel that receives the messages' new shape (see [`v2::Message`]).
+    V2(v2::MessageSender),
+}
+
+#[derive(Debug, Clone)]
+/// The wrapper around the [`MessageSender`] through which all the converter's
+/// threads send their messages, so that the data needed for the job's [`Summary`]
+/// gets gathered in a single place, and so that each message gets routed to the
+/// right channel.
+///
+/// NOTE: Every message is lifted to its new shape (see [`v2::Message`]), from which the
+/// legacy one is derived when it is sent down a legacy channel.
+pub(crate) struct Outbox {
+    tx: Sink,
+    /// The identifier of the job, which tags the messages' new shape.
+    job_id: JobId,
+    /// The (lossy) channel down which progress messages are sent instead, if any.
+    progress: Option<ProgressSender>,
+    record: Arc<Mutex<Record>>,
+}
+
+impl Outbox {
+    pub(crate) fn new(tx: MessageSender) -> Self {
+        Self::with_sink(Sink::Legacy(tx))
+    }
+
+    #[cfg(feature = "v2-messages")]
+    /// Creates an outbox whose messages are sent down `tx` in their new shape.
+    pub(crate) fn v2(tx: v2::MessageSender) -> Self {
+        Self::with_sink(Sink::V2(tx))
+    }
+
+    fn with_sink(tx: Sink) -> Self {
+        Self {
+            tx,
+            job_id: JobId::generate(),
+            progress: None,
+            record: Arc::new(Mutex::new(Record::default())),
+        }
+    }
+
+    /// Tags the messages' new shape with `job_id` (see [`crate::Converter::id`]).
+    pub(crate) fn with_job_id(self, job_id: JobId) -> Self {
+        Self { job_id, ..self }
+    }
+
+    pub(crate) fn with_progress(tx: MessageSender, progress: ProgressSender) -> Self {
+        Self {
+            progress: Some(progress),
+            ..Self::new(tx)
+        }
+    }
+
+    /// Creates an outbox whose messages are sent down the bounded channel `bounded`, which
+    /// never blocks the converter's threads: progress messages are dropped when the channel
+    /// is full, while the other messages take the slots reserved for them.
+    pub(crate) fn bounded(bounded: BoundedMessageSender) -> Self {
+        Self::with_sink(Sink::Bounded(bounded))
+    }
+
+    /// Records what the [`Summary`] needs to know about `message`, and sends it down the channel.
+    // NOTE: The error type is the channel's own, which hands the (unsent) message back.
+    #[allow(clippy::result_large_err)]
+    pub(crate) fn send(&self, message: Message) -> Result<(), SendError> {
+        let is_progress = message.is_progress();
+        let message = {
+            let mut record = self.record.lock().unwrap_or_else(|e| e.into_inner());
+            match &message {
+                Message::Success(bytes) => record.output_bytes = Some(bytes.len()),
+                // NOTE: The file is complete by then (see `Settings::output_path`).
+                Message::SuccessFile(path) => {
+                    let len = std::fs::metadata(path).map_or(0, |m| m.len() as usize);
+                    record.output_bytes = Some(len);
+                    record.output_path = Some(path.clone());
+                }
+                Message::SuccessWritten(n) => record.output_bytes = Some(*n as usize),
+                Message::Thumbnail { bytes, .. } => {
+                    record.output_bytes = Some(record.output_bytes.unwrap_or(0) + bytes.len())
+                }
+                Message::Error(e) if record.error.is_none() => record.error = Some(e.clone()),
+                Message::Warning(w) => record.warnings.push(w.clone()),
+                Message::Saved { path: p } | Message::Skipped { existing_path: p } => {
+                    record.output_path = Some(p.clone())
+                }
+                Message::Progress(p) | Message::InterpolatedProgress(p) => {
+                    record.last_progress = Some(*p)
+                }
+                _ => {}
+            }
+            match &message {
+                Message::Saved { path: p }
+                | Message::Skipped { existing_path: p }
+                | Message::SuccessFile(p) => record.context.output_path = Some(p.clone()),
+                Message::OutputDimensions { width, height } => {
+                    record.context.dimensions = Some((*width, *height))
+                }
+                Message::VideoDuration(d) => record.context.video_duration = Some(*d),
+                _ => {}
+            }
+            v2::Message::lift(self.job_id.clone(), message, &record.context)
+        };
+        let tx = match &self.tx {
+            Sink::Legacy(tx) => tx,
+            Sink::Bounded(tx) => return tx.send(Message::from(message)),
+            #[cfg(feature = "v2-messages")]
+            Sink::V2(tx) => {
+                // NOTE: The error hands the message back, in its legacy shape.
+                #[cfg(not(any(feature = "tokio", feature = "async-channel")))]
+                return tx
+                    .send(message)
+                    .map_err(|e| std::sync::mpsc::SendError(e.0.into()));
+                #[cfg(feature = "tokio")]
+                return tx
+                    .send(message)
+                    .map_err(|e| tokio::sync::mpsc::error::SendError(e.0.into()));
+                #[cfg(feature = "async-channel")]
+                return tx
+                    .send_blocking(message)
+                    .map_err(|e| async_channel::SendError(e.0.into()));
+            }
+        };
+        let message = Message::from(message);
+        if let (true, Some(progress)) = (is_progress, &self.progress) {
+            // NOTE: Progress messages are expendable, so they are simply dropped when
+            // the channel is full (or closed), instead of blocking the sending thread.
+            if let Err(e) = progress.try_send(message) {
+                log::trace!(target: LOG_TARGET, "Progress message dropped: {:?}", e);
+            }
+            return Ok(());
+        }
+        #[cfg(not(feature = "async-channel"))]
+        return tx.send(message);
+        #[cfg(feature = "async-channel")]
+        return tx.send_blocking(message);
+    }
+
+    /// Records the resources used by the FFmpeg child process, for the [`Summary`].
+    pub(crate) fn record_resource_usage(&self, resource_usage: Option<ResourceUsage>) {
+        self.record
+            .lock()
+            .unwrap_or_else(|e| e.into_inner())
+            .resource_usage = resource_usage;
+    }
+
+    /// Records where the duration relative to which the progress is computed comes from,
+    /// for the [`Summary`].
+    pub(crate) fn record_duration_source(&self, duration_source: DurationSource) {
+        self.record
+            .lock()
+            .unwrap_or_else(|e| e.into_inner())
+            .duration_source = Some(duration_source);
+    }
+
+    /// Records the last lines from which the duration and the progress were parsed (see
+    /// [`crate::Settings::parse_provenance`]), for the [`Summary`].
+    pub(crate) fn record_provenance(&self, provenance: Vec<ParseProvenance>) {
+        self.record
+            .lock()
+            .unwrap_or_else(|e| e.into_inner())
+            .provenance = provenance;
+    }
+
+    /// Whether the animated GIF has been sent down the channel (using [`Message::Success`],
+    /// or written to a file or to a writer, see [`Message::SuccessFile`] and
+    /// [`Message::SuccessWritten`]), and no error so far.
+    pub(crate) fn succeeded(&self) -> bool {
+        let record = self.record.lock().unwrap_or_else(|e| e.into_inner());
+        record.error.is_none() && record.output_bytes.is_some()
+    }
+
+    /// The last progress value sent down the channel, if any.
+    pub(crate) fn last_progress(&self) -> Option<f64> {
+        self.record
+            .lock()
+            .unwrap_or_else(|e| e.into_inner())
+            .last_progress
+    }
+
+    /// Builds the [`Summary`] of the job, based on the messages sent so far.
+    pub(crate) fn summary(&self, id: JobId, label: Option<String>, elapsed: Duration) -> Summary {
+        let record = self.record.lock().unwrap_or_else(|e| e.into_inner());
+        Summary {
+            id,
+            label,
+            output_bytes: record.output_bytes,
+            error: record.error.clone(),
+            warnings: record.warnings.clone(),
+            elapsed,
+            resource_usage: record.resource_usage,
+            output_path: record.output_path.clone(),
+            duration_source: record.duration_source,
+            provenance: record.provenance.clone(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_progress_routing() {
+        #[cfg(not(any(feature = "tokio", feature = "async-channel")))]
+        let ((tx, rx), (progress_tx, progress_rx)) =
+            (std::sync::mpsc::channel(), std::sync::mpsc::sync_channel(2));
+        #[cfg(feature = "async-channel")]
+        let ((tx, rx), (progress_tx, progress_rx)) =
+            (async_channel::unbounded(), async_channel::bounded(2));
+        #[cfg(feature = "tokio")]
+        let ((tx, mut rx), (progress_tx, mut progress_rx)) = (
+            tokio::sync::mpsc::unbounded_channel(),
+            tokio::sync::mpsc::channel(2),
+        );
+        let outbox = Outbox::with_progress(tx, progress_tx);
+
+        outbox
+            .send(Message::VideoDuration(Duration::from_secs(1)))
+            .unwrap();
+        // The channel is full after two messages, but sending never blocks nor fails.
+        for i in 0..5 {
+            outbox.send(Message::Progress(i as f64 / 4.0)).unwrap();
+        }
+        outbox.send(Message::Done).unwrap();
+
+        assert!(matches!(rx.try_recv(), Ok(Message::VideoDuration(_))));
+        assert!(matches!(rx.try_recv(), Ok(Message::Done)));
+        assert!(rx.try_recv().is_err());
+        assert!(matches!(progress_rx.try_recv(), Ok(Message::Progress(p)) if p == 0.0));
+        assert!(matches!(progress_rx.try_recv(), Ok(Message::Progress(p)) if p == 0.25));
+        assert!(progress_rx.try_recv().is_err());
+
+        // Dropping the progress receiver does not affect the other channel.
+        drop(progress_rx);
+        outbox.send(Message::Progress(1.0)).unwrap();
+        outbox.send(Message::Done).unwrap();
+        assert!(matches!(rx.try_recv(), Ok(Message::Done)));
+    }
+}
@@ -0,0 +1,147 @@
+//! The container and codec of the animation written by FFmpeg to `stdout` (see
+//! [`crate::Settings::output_format`]), which is an animated GIF by default.
+//!
+//! Unlike the GIF muxer, the animated WebP and APNG encoders are not limited to a
+//! palette of 256 colors, so their filter graph skips the generation (and the application)
+//! of the palette, and keeps the rest of the chain (e.g. `fps` and `scale`).
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
+/// The format of the animation produced by the conversion job (see
+/// [`crate::Settings::output_format`]).
+pub enum OutputFormat {
+    /// An animated GIF, whose colors are reduced to a palette.
+    #[default]
+    Gif,
+    /// An animated WebP image, encoded using `libwebp_anim` (which requires FFmpeg to be
+    /// built with `--enable-libwebp`).
+    WebP {
+        /// The compression quality, between 0 and [`OutputFormat::MAX_WEBP_QUALITY`] (i.e.
+        /// the image quality when lossy, and the compression effort when lossless).
+        quality: u8,
+        /// Whether the frames are encoded losslessly.
+        lossless: bool,
+    },
+    /// An animated PNG (APNG) image, which is lossless.
+    Apng,
+}
+
+impl OutputFormat {
+    /// The largest value of [`OutputFormat::WebP`]'s `quality`.
+    pub const MAX_WEBP_QUALITY: u8 = 100;
+
+    /// The usual extension of the files in this format, without the dot (e.g. `webp`).
+    pub fn extension(&self) -> &'static str {
+        match self {
+            Self::Gif => "gif",
+            Self::WebP { .. } => "webp",
+            Self::Apng => "png",
+        }
+    }
+
+    /// The media type of the files in this format (e.g. `image/webp`).
+    pub fn mime_type(&self) -> &'static str {
+        match self {
+            Self::Gif => "image/gif",
+            Self::WebP { .. } => "image/webp",
+            Self::Apng => "image/apng",
+        }
+    }
+
+    /// A short name for the format (e.g. for [`crate::ConversionPlan`]'s summary).
+    pub(crate) fn name(&self) -> &'static str {
+        match self {
+            Self::Gif => "GIF",
+            Self::WebP { lossless: true, .. } => "lossless WebP",
+            Self::WebP { .. } => "WebP",
+            Self::Apng => "APNG",
+        }
+    }
+
+    /// Whether the frames' colors are reduced to a palette, i.e. whether the filter graph
+    /// ends with the `palettegen` and `paletteuse` filters.
+    pub(crate) fn uses_palette(&self) -> bool {
+        matches!(self, Self::Gif)
+    }
+
+    /// The arguments that select the muxer (and encoder) of the output, which precede
+    /// [`crate::Settings::extra_output_args`].
+    pub(crate) fn output_args(&self) -> Vec<String> {
+        let args: &[&str] = match self {
+            Self::Gif => &["-f", "gif"],
+            Self::WebP { .. } => &["-c:v", "libwebp_anim"],
+            // NOTE: Loops forever, like the animated GIF (whose muxer does by default).
+            Self::Apng => &["-plays", "0", "-f", "apng"],
+        };
+        let mut args = Vec::from_iter(args.iter().map(|arg| arg.to_string()));
+        if let Self::WebP { quality, lossless } = self {
+            args.extend([
+                "-quality".into(),
+                quality.to_string(),
+                "-lossless".into(),
+                u8::from(*lossless).to_string(),
+                "-loop".into(),
+                "0".into(),
+                "-f".into(),
+                "webp".into(),
+            ]);
+        }
+        args
+    }
+
+    /// Whether `bytes` start with the signature of this format (e.g. `RIFF....WEBP`).
+    pub(crate) fn has_signature(&self, bytes: &[u8]) -> bool {
+        match self {
+            Self::Gif => bytes.starts_with(b"GIF87a") || bytes.starts_with(b"GIF89a"),
+            Self::WebP { .. } => bytes.starts_with(b"RIFF") && bytes.get(8..12) == Some(b"WEBP"),
+            Self::Apng => bytes.starts_with(b"\x89PNG\r\n\x1a\n"),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const WEBP: OutputFormat = OutputFormat::WebP {
+        quality: 75,
+        lossless: false,
+    };
+
+    #[test]
+    fn test_output_args() {
+        assert_eq!(OutputFormat::Gif.output_args(), ["-f", "gif"]);
+        assert_eq!(
+            WEBP.output_args(),
+            [
+                "-c:v",
+                "libwebp_anim",
+                "-quality",
+                "75",
+                "-lossless",
+                "0",
+                "-loop",
+                "0",
+                "-f",
+                "webp"
+            ]
+        );
+        assert_eq!(
+            OutputFormat::Apng.output_args(),
+            ["-plays", "0", "-f", "apng"]
+        );
+    }
+
+    #[test]
+    fn test_has_signature() {
+        let webp = b"RIFF\x24\x00\x00\x00WEBPVP8X";
+        let png = b"\x89PNG\r\n\x1a\n\x00\x00\x00\x0dIHDR";
+        assert!(OutputFormat::Gif.has_signature(b"GIF89a\x01\x00"));
+        assert!(WEBP.has_signature(webp));
+        assert!(OutputFormat::Apng.has_signature(png));
+        assert!(!OutputFormat::Gif.has_signature(webp));
+        assert!(!WEBP.has_signature(b"RIFF\x24\x00\x00\x00WAVEfmt "));
+        assert!(!WEBP.has_signature(b"RIFF"));
+        assert!(!OutputFormat::Apng.has_signature(b"GIF89a"));
+    }
+}
@@ -0,0 +1,485 @@
+//! The file-output mode (see [`crate::Settings::output_file`]), in which the animated GIF
+//! is also written to a file, whose name is expanded from a template (see [`OutputNaming`])
+//! and validated before the job starts, and which is handled according to a
+//! [`CollisionPolicy`] when a file with that name already exists.
+//!
+//! The collisions are checked twice: before the job starts (so that a skipped job does not
+//! run FFmpeg at all), and when the file gets created (without replacing any file, except
+//! with [`CollisionPolicy::Overwrite`]), in case another job (e.g. of the same
+//! [`crate::Batch`]) created it in the meantime.
+
+use std::io::Write;
+use std::path::{Path, PathBuf};
+
+use crate::{InputSource, Settings};
+
+/// The largest length (in bytes) of a file name on most filesystems (e.g. ext4, btrfs or APFS).
+pub(crate) const MAX_FILE_NAME_BYTES: usize = 255;
+/// The largest number appended to a file name by [`CollisionPolicy::AutoNumber`].
+pub(crate) const MAX_AUTO_NUMBER: u32 = 9999;
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+/// What happens when the file named by an [`OutputNaming`] template already exists.
+pub enum CollisionPolicy {
+    /// The existing file is replaced.
+    Overwrite,
+    /// The job is not run, and completes right away with [`crate::Message::Skipped`].
+    Skip,
+    /// A number (starting at 2, up to 9999) is appended to the file's stem (e.g.
+    /// `clip_200px-2.gif`), the first one that is not taken being used.
+    #[default]
+    AutoNumber,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+/// The naming of the file to which the animated GIF is written (see [`crate::Settings::output_file`]).
+///
+/// The template is the file's name, in which the following placeholders get expanded:
+///
+/// * `{stem}`: the input's file name, without its extension (e.g. `clip` for `videos/clip.mp4`);
+/// * `{ext}`: the input's extension (e.g. `mp4`), or nothing if it has none;
+/// * `{width}`: the animated GIF's width;
+/// * `{height}`: the animated GIF's height (see [`crate::Settings::gif_height`]), or `auto`
+///   when it follows the video's aspect ratio;
+/// * `{fps}`: the animated GIF's frame rate.
+///
+/// E.g. `{stem}_{width}px.gif` gives `clip_200px.gif`.
+pub struct OutputNaming {
+    template: String,
+    collision: CollisionPolicy,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+/// A problem found when expanding an [`OutputNaming`] template (see
+/// [`crate::SettingsError::OutputNaming`]).
+pub enum NamingError {
+    /// The template contains a placeholder that is not supported.
+    UnknownPlaceholder(String),
+    /// The template contains a `{` that is not closed.
+    UnclosedPlaceholder,
+    /// The template uses the input's file name (i.e. `{stem}` or `{ext}`), but the
+    /// input is not a path (e.g. [`InputSource::File`] or [`InputSource::Bytes`]).
+    MissingInputPath,
+    /// The expanded file name is empty, is `.` or `..`, or contains a path separator
+    /// (or a NUL character).
+    InvalidFileName(String),
+    /// The expanded file name is longer than the filesystems allow, once the room needed
+    /// by [`CollisionPolicy::AutoNumber`] (if used) is taken into account.
+    FileNameTooLong {
+        /// The length (in bytes) of the expanded file name.
+        len: usize,
+        /// The largest length (in bytes) allowed.
+        max: usize,
+    },
+}
+
+#[derive(Debug, Clone)]
+/// The file to which the animated GIF is written (see [`crate::Settings::output_file`]).
+pub(crate) struct OutputFile {
+    pub(crate) dir: PathBuf,
+    pub(crate) naming: OutputNaming,
+    /// The expansion of the naming's template.
+    pub(crate) file_name: String,
+}
+
+impl OutputFile {
+    /// The path of the file, before any collision is handled.
+    pub(crate) fn path(&self) -> PathBuf {
+        self.dir.join(&self.file_name)
+    }
+
+    /// See [`OutputNaming::resolve`].
+    pub(crate) fn resolve(&self) -> std::io::Result<OutputTarget> {
+        self.naming.resolve(&self.dir, &self.file_name)
+    }
+
+    /// See [`OutputNaming::save`].
+    pub(crate) fn save(&self, bytes: &[u8]) -> std::io::Result<OutputTarget> {
+        self.naming.save(&self.dir, &self.file_name, bytes)
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+/// Where the animated GIF goes.
+pub(crate) enum OutputTarget {
+    /// The file to write.
+    Write(PathBuf),
+    /// The existing file, because of which the job is skipped.
+    Skip(PathBuf),
+}
+
+impl OutputNaming {
+    /// Creates a new naming, given the `template` (see [`OutputNaming`]), whose collisions
+    /// are handled using [`CollisionPolicy::AutoNumber`].
+    pub fn new(template: impl Into<String>) -> Self {
+        Self {
+            template: template.into(),
+            collision: CollisionPolicy::default(),
+        }
+    }
+
+    /// A setter method that allows specifying what happens when the file already exists.
+    pub fn collision(self, collision: CollisionPolicy) -> Self {
+        Self { collision, ..self }
+    }
+
+    /// Expands the template for `settings`, returning the (validated) file name.
+    pub(crate) fn file_name(&self, settings: &Settings) -> Result<String, NamingError> {
+        let mut name = String::new();
+        let mut rest = self.template.as_str();
+        while let Some(start) = rest.find('{') {
+            name.push_str(&rest[..start]);
+            let (placeholder, after) = rest[start + 1..]
+                .split_once('}')
+                .ok_or(NamingError::UnclosedPlaceholder)?;
+            name.push_str(&expand_placeholder(placeholder, settings)?);
+            rest = after;
+        }
+        name.push_str(rest);
+
+        if name.is_empty()
+            || name == "."
+            || name == ".."
+            || name.contains(|c| std::path::is_separator(c) || c == '\0')
+        {
+            return Err(NamingError::InvalidFileName(name));
+        }
+        let max = match self.collision {
+            CollisionPolicy::AutoNumber => {
+                MAX_FILE_NAME_BYTES - format!("-{}", MAX_AUTO_NUMBER).len()
+            }
+            _ => MAX_FILE_NAME_BYTES,
+        };
+        if name.len() > max {
+            return Err(NamingError::FileNameTooLong {
+                len: name.len(),
+                max,
+            });
+        }
+        Ok(name)
+    }
+
+    /// Where the animated GIF named `file_name` (see [`OutputNaming::file_name`]) goes in
+    /// `dir`, given the files that exist at the moment. It fails with an `AlreadyExists`
+    /// error when all the numbers of [`CollisionPolicy::AutoNumber`] are taken.
+    pub(crate) fn resolve(&self, dir: &Path, file_name: &str) -> std::io::Result<OutputTarget> {
+        // NOTE: Not following symbolic links, so that a dangling one counts as taken
+        // (as it does when creating the file).
+        let taken = |path: &Path| path.symlink_metadata().is_ok();
+        let path = dir.join(file_name);
+        match self.collision {
+            CollisionPolicy::Overwrite => Ok(OutputTarget::Write(path)),
+            CollisionPolicy::Skip if taken(&path) => Ok(OutputTarget::Skip(path)),
+            CollisionPolicy::Skip => Ok(OutputTarget::Write(path)),
+            CollisionPolicy::AutoNumber => std::iter::once(path)
+                .chain((2..=MAX_AUTO_NUMBER).map(|n| dir.join(numbered(file_name, n))))
+                .find(|path| !taken(path))
+                .map(OutputTarget::Write)
+                .ok_or_else(|| {
+                    std::io::Error::new(
+                        std::io::ErrorKind::AlreadyExists,
+                        format!("all the numbered variants of {:?} are taken", file_name),
+                    )
+                }),
+        }
+    }
+
+    /// Writes `bytes` to the file named `file_name` in `dir` (see [`OutputNaming::resolve`]),
+    /// returning where they went (i.e. nowhere if the job turns out to be skipped).
+    pub(crate) fn save(
+        &self,
+        dir: &Path,
+        file_name: &str,
+        bytes: &[u8],
+    ) -> std::io::Result<OutputTarget> {
+        loop {
+            let path = match self.resolve(dir, file_name)? {
+                OutputTarget::Write(path) => path,
+                target => return Ok(target),
+            };
+            let mut options = std::fs::OpenOptions::new();
+            match self.collision {
+                CollisionPolicy::Overwrite => options.write(true).create(true).truncate(true),
+                _ => options.write(true).create_new(true),
+            };
+            let mut file = match options.open(&path) {
+                Ok(file) => file,
+                // NOTE: Created since it was resolved, so resolving it again.
+                Err(e) if e.kind() == std::io::ErrorKind::AlreadyExists => continue,
+                Err(e) => return Err(e),
+            };
+            if let Err(e) = file.write_all(bytes) {
+                let _ = std::fs::remove_file(&path);
+                return Err(e);
+            }
+            return Ok(OutputTarget::Write(path));
+        }
+    }
+}
+
+/// The value of the template's `placeholder` (i.e. its name, without the braces) for `settings`.
+fn expand_placeholder(placeholder: &str, settings: &Settings) -> Result<String, NamingError> {
+    let input_path = || match &settings.input {
+        InputSource::Path(path) => Ok(Path::new(path)),
+        InputSource::File(_)
+        | InputSource::Bytes(_)
+        | InputSource::Reader(_)
+        | InputSource::Url(_) => Err(NamingError::MissingInputPath),
+    };
+    Ok(match placeholder {
+        "stem" => input_path()?
+            .file_stem()
+            .map(|s| s.to_string_lossy().into_owned())
+            .unwrap_or_default(),
+        "ext" => input_path()?
+            .extension()
+            .map(|s| s.to_string_lossy().into_owned())
+            .unwrap_or_default(),
+        "width" => settings.gif_width.to_string(),
+        "height" => settings
+            .gif_height
+            .map_or("auto".into(), |height| height.to_string()),
+        "fps" => settings.gif_fps.to_string(),
+        _ => return Err(NamingError::UnknownPlaceholder(placeholder.into())),
+    })
+}
+
+/// The `file_name` numbered `n` (e.g. `clip-2.gif` for `clip.gif`), the number going
+/// before the extension, if any.
+fn numbered(file_name: &str, n: u32) -> String {
+    match file_name.rfind('.') {
+        Some(dot) if dot > 0 => format!("{}-{}{}", &file_name[..dot], n, &file_name[dot..]),
+        _ => format!("{}-{}", file_name, n),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_utils::temp_dir;
+
+    fn settings(path: &str) -> Settings {
+        Settings::with_standard_fps(path.into(), 200)
+    }
+
+    #[test]
+    fn test_file_name() {
+        let long_stem = "a".repeat(251);
+        let cases = [
+            (
+                "{stem}_{width}px.gif",
+                "videos/clip.mp4",
+                Ok("clip_200px.gif".to_string()),
+            ),
+            (
+                "{stem}.{ext}.gif",
+                "clip.tar.mp4",
+                Ok("clip.tar.mp4.gif".into()),
+            ),
+            ("{stem}{ext}.gif", "Makefile", Ok("Makefile.gif".into())),
+            (
+                "{width}x{height}@{fps}.gif",
+                "clip.mp4",
+                Ok("200xauto@10.gif".into()),
+            ),
+            (
+                "vidéo {stem}.gif",
+                "/tmp/日本語の動画.mov",
+                Ok("vidéo 日本語の動画.gif".into()),
+            ),
+            ("{stem}-🎞️.gif", "🐰.mp4", Ok("🐰-🎞️.gif".into())),
+            ("{stem}.gif", "{width}.mp4", Ok("{width}.gif".into())),
+            ("output.gif", "clip.mp4", Ok("output.gif".into())),
+            (
+                "{stem}_{size}.gif",
+                "clip.mp4",
+                Err(NamingError::UnknownPlaceholder("size".into())),
+            ),
+            (
+                "{stem.gif",
+                "clip.mp4",
+                Err(NamingError::UnclosedPlaceholder),
+            ),
+            (
+                "{}.gif",
+                "clip.mp4",
+                Err(NamingError::UnknownPlaceholder("".into())),
+            ),
+            ("{stem}", "", Err(NamingError::InvalidFileName("".into()))),
+            (
+                "..",
+                "clip.mp4",
+                Err(NamingError::InvalidFileName("..".into())),
+            ),
+            (
+                "gifs/{stem}.gif",
+                "clip.mp4",
+                Err(NamingError::InvalidFileName("gifs/clip.gif".into())),
+            ),
+            // NOTE: 250 bytes, which leaves room for the largest number.
+            (
+                "{stem}.gif",
+                &long_stem[5..],
+                Ok(format!("{}.gif", &long_stem[5..])),
+            ),
+            (
+                "{stem}.gif",
+                &long_stem[4..],
+                Err(NamingError::FileNameTooLong { len: 251, max: 250 }),
+            ),
+            // NOTE: 83 characters of 3 bytes each, i.e. 249 bytes.
+            (
+                "{stem}.",
+                &"動".repeat(83),
+                Ok(format!("{}.", "動".repeat(83))),
+            ),
+            (
+                "{stem}.g",
+                &"動".repeat(83),
+                Err(NamingError::FileNameTooLong { len: 251, max: 250 }),
+            ),
+        ];
+        for (template, input, expected) in cases {
+            assert_eq!(
+                OutputNaming::new(template).file_name(&settings(input)),
+                expected,
+                "{:?} {:?}",
+                template,
+                input
+            );
+        }
+
+        let settings = settings("clip.mp4").gif_height(120);
+        let naming = OutputNaming::new("{width}x{height}.gif");
+        assert_eq!(naming.file_name(&settings), Ok("200x120.gif".into()));
+
+        // The whole length is available when not numbering.
+        let settings = Settings::with_standard_fps(format!("{}.mp4", long_stem), 200);
+        let naming = OutputNaming::new("{stem}.gif");
+        assert_eq!(
+            naming
+                .clone()
+                .collision(CollisionPolicy::Overwrite)
+                .file_name(&settings),
+            Ok(format!("{}.gif", long_stem))
+        );
+        assert_eq!(
+            naming
+                .collision(CollisionPolicy::Skip)
+                .file_name(&Settings::with_standard_fps(
+                    format!("a{}.mp4", long_stem),
+                    200
+                )),
+            Err(NamingError::FileNameTooLong { len: 256, max: 255 })
+        );
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_file_name_file_input() {
+        let file = std::fs::File::open(crate::test_utils::SAMPLE_VIDEO_PATH).unwrap();
+        let settings = Settings::with_input(file, 200);
+        assert_eq!(
+            OutputNaming::new("{stem}.gif").file_name(&settings),
+            Err(NamingError::MissingInputPath)
+        );
+        assert_eq!(
+            OutputNaming::new("{width}px.gif").file_name(&settings),
+            Ok("200px.gif".into())
+        );
+    }
+
+    #[test]
+    fn test_numbered() {
+        let cases = [
+            ("clip.gif", 2, "clip-2.gif"),
+            ("clip.tar.gif", 3, "clip.tar-3.gif"),
+            ("clip", 2, "clip-2"),
+            (".gif", 2, ".gif-2"),
+            ("日本語.gif", 9999, "日本語-9999.gif"),
+        ];
+        for (file_name, n, expected) in cases {
+            assert_eq!(numbered(file_name, n), expected);
+        }
+    }
+
+    #[test]
+    fn test_resolve_and_save() {
+        let dir = temp_dir();
+        let taken = ["clip.gif", "clip-2.gif", "動画.gif"];
+        for name in taken {
+            std::fs::write(dir.join(name), b"existing").unwrap();
+        }
+        let naming = |collision| OutputNaming::new("").collision(collision);
+        let cases = [
+            (
+                CollisionPolicy::Skip,
+                "clip.gif",
+                OutputTarget::Skip(dir.join("clip.gif")),
+            ),
+            (
+                CollisionPolicy::Skip,
+                "new.gif",
+                OutputTarget::Write(dir.join("new.gif")),
+            ),
+            (
+                CollisionPolicy::AutoNumber,
+                "clip.gif",
+                OutputTarget::Write(dir.join("clip-3.gif")),
+            ),
+            (
+                CollisionPolicy::AutoNumber,
+                "動画.gif",
+                OutputTarget::Write(dir.join("動画-2.gif")),
+            ),
+            (
+                CollisionPolicy::AutoNumber,
+                "new.gif",
+                OutputTarget::Write(dir.join("new.gif")),
+            ),
+            // NOTE: Last, since it replaces the existing file.
+            (
+                CollisionPolicy::Overwrite,
+                "clip.gif",
+                OutputTarget::Write(dir.join("clip.gif")),
+            ),
+        ];
+        for (collision, file_name, expected) in cases {
+            let naming = naming(collision);
+            assert_eq!(naming.resolve(&dir, file_name).unwrap(), expected);
+            assert_eq!(naming.save(&dir, file_name, b"gif").unwrap(), expected);
+            match expected {
+                OutputTarget::Write(path) => {
+                    assert_eq!(std::fs::read(&path).unwrap(), b"gif");
+                    if !taken.contains(&file_name) {
+                        std::fs::remove_file(path).unwrap();
+                    }
+                }
+                OutputTarget::Skip(path) => {
+                    assert_eq!(std::fs::read(path).unwrap(), b"existing")
+                }
+            }
+        }
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_resolve_dangling_symlink() {
+        let dir = temp_dir();
+        std::os::unix::fs::symlink(dir.join("missing"), dir.join("clip.gif")).unwrap();
+        let naming = OutputNaming::new("").collision(CollisionPolicy::AutoNumber);
+        assert_eq!(
+            naming.save(&dir, "clip.gif", b"gif").unwrap(),
+            OutputTarget::Write(dir.join("clip-2.gif"))
+        );
+        assert!(!dir.join("missing").exists());
+    }
+
+    #[test]
+    fn test_save_errors() {
+        let dir = temp_dir().join("missing");
+        let naming = OutputNaming::new("").collision(CollisionPolicy::Overwrite);
+        let e = naming.save(&dir, "clip.gif", b"gif").unwrap_err();
+        assert_eq!(e.kind(), std::io::ErrorKind::NotFound);
+    }
+}
@@ -0,0 +1,233 @@
+//! The direct-to-file mode (see [`crate::Settings::output_path`]), in which FFmpeg writes the
+//! animated GIF to a file instead of `stdout`, so that it is never kept in memory.
+//!
+//! The file is opened (and created, if needed) before FFmpeg is spawned, so that the
+//! problems that would otherwise only show in FFmpeg's `stderr` output (e.g. a missing
+//! directory) are reported as distinct errors. Once FFmpeg has exited, only the beginning
+//! and the end of the file are read to validate it (see [`validate`]).
+
+use std::io::{Read, Seek};
+use std::path::{Path, PathBuf};
+
+use crate::gif_info::{GifInfo, GifParseError, TRAILER};
+use crate::{Error, OutputFormat};
+
+/// The number of bytes read to check the output's signature (i.e. that of a WebP image,
+/// which is the longest one, see [`OutputFormat::has_signature`]).
+pub(crate) const SIGNATURE_LEN: u64 = 12;
+
+#[derive(Debug)]
+/// The file to which FFmpeg writes the animated GIF, which is removed when dropped (unless
+/// kept, see [`OutputPath::keep`]) if it was created for the job.
+pub(crate) struct OutputPath {
+    path: PathBuf,
+    created: bool,
+    keep: bool,
+}
+
+impl OutputPath {
+    /// Makes sure that the file at `path` can be written, by opening it for writing (without
+    /// truncating it, in case FFmpeg is never spawned), and creating it if needed.
+    pub(crate) fn prepare(path: &Path) -> Result<Self, Error> {
+        let created = !path.exists();
+        match std::fs::OpenOptions::new()
+            .write(true)
+            .create(true)
+            .truncate(false)
+            .open(path)
+        {
+            Ok(_) => Ok(Self {
+                path: path.to_path_buf(),
+                created,
+                keep: false,
+            }),
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => {
+                let dir = path.parent().filter(|dir| !dir.as_os_str().is_empty());
+                Err(Error::OutputDirectoryNotFound(
+                    dir.unwrap_or(path).to_path_buf(),
+                ))
+            }
+            Err(e) if e.kind() == std::io::ErrorKind::PermissionDenied => {
+                Err(Error::OutputPermissionDenied(path.to_path_buf()))
+            }
+            Err(e) => Err(Error::OutputFile {
+                path: path.to_path_buf(),
+                error: std::sync::Arc::new(e),
+            }),
+        }
+    }
+
+    /// The path of the file.
+    pub(crate) fn path(&self) -> &Path {
+        &self.path
+    }
+
+    /// Keeps the file when dropped (i.e. once the job has succeeded).
+    pub(crate) fn keep(&mut self) {
+        self.keep = true;
+    }
+}
+
+impl Drop for OutputPath {
+    fn drop(&mut self) {
+        if self.keep || !self.created {
+            return;
+        }
+        if let Err(e) = std::fs::remove_file(&self.path) {
+            if e.kind() != std::io::ErrorKind::NotFound {
+                log::warn!("Unable to remove output file {:?}: {:?}", self.path, e);
+            }
+        }
+    }
+}
+
+/// Same as validating the bytes written by FFmpeg to `stdout`, for the file at `path`,
+/// without reading it in full: the file must not be empty, and must start with the
+/// format's signature (and, for an animated GIF, end with its trailer). Since the GIF is
+/// not parsed, its metadata is never returned.
+pub(crate) fn validate(path: &Path, format: OutputFormat) -> Result<Option<GifInfo>, Error> {
+    let output_file_error = |e: std::io::Error| Error::OutputFile {
+        path: path.to_path_buf(),
+        error: std::sync::Arc::new(e),
+    };
+    let mut file = match std::fs::File::open(path) {
+        Ok(file) => file,
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => {
+            return Err(Error::EmptyOutputFile(path.to_path_buf()))
+        }
+        Err(e) => return Err(output_file_error(e)),
+    };
+    let len = file.metadata().map_err(output_file_error)?.len();
+    if len == 0 {
+        return Err(Error::EmptyOutputFile(path.to_path_buf()));
+    }
+    let mut signature = Vec::new();
+    (&mut file)
+        .take(SIGNATURE_LEN)
+        .read_to_end(&mut signature)
+        .map_err(output_file_error)?;
+    let mut last = [0u8];
+    file.seek(std::io::SeekFrom::End(-1))
+        .and_then(|_| file.read_exact(&mut last))
+        .map_err(output_file_error)?;
+    validate_ends(&signature, last[0], len, format)
+}
+
+/// Validates an output of `len` bytes (which is not empty) given only its beginning (i.e. its
+/// first [`SIGNATURE_LEN`] bytes, or fewer if shorter) and its `last` byte, as [`validate`] does.
+pub(crate) fn validate_ends(
+    signature: &[u8],
+    last: u8,
+    len: u64,
+    format: OutputFormat,
+) -> Result<Option<GifInfo>, Error> {
+    match format {
+        OutputFormat::Gif if !format.has_signature(signature) => {
+            Err(Error::InvalidOutput(GifParseError::InvalidSignature))
+        }
+        OutputFormat::Gif if last != TRAILER => {
+            Err(Error::InvalidOutput(GifParseError::UnexpectedEof {
+                offset: len as usize,
+            }))
+        }
+        _ if format.has_signature(signature) => Ok(None),
+        _ => Err(Error::InvalidOutputSignature(format)),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_utils::{sample_gif, temp_dir};
+
+    #[test]
+    fn test_prepare() {
+        let dir = temp_dir();
+        let path = dir.join("out.gif");
+        let output = OutputPath::prepare(&path).unwrap();
+        assert_eq!(output.path(), path);
+        assert_eq!(std::fs::metadata(&path).unwrap().len(), 0);
+        // Removed when dropped, unless kept.
+        drop(output);
+        assert!(!path.exists());
+        let mut output = OutputPath::prepare(&path).unwrap();
+        output.keep();
+        drop(output);
+        assert!(path.exists());
+
+        // An existing file is neither truncated nor removed.
+        std::fs::write(&path, b"existing").unwrap();
+        drop(OutputPath::prepare(&path).unwrap());
+        assert_eq!(std::fs::read(&path).unwrap(), b"existing");
+
+        let missing = dir.join("missing");
+        assert!(matches!(
+            OutputPath::prepare(&missing.join("out.gif")),
+            Err(Error::OutputDirectoryNotFound(d)) if d == missing
+        ));
+        assert!(matches!(
+            OutputPath::prepare(&dir),
+            Err(Error::OutputFile { path, .. }) if path == dir
+        ));
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_prepare_permission_denied() {
+        use std::os::unix::fs::PermissionsExt;
+
+        let dir = temp_dir().join("read-only");
+        std::fs::create_dir(&dir).unwrap();
+        std::fs::set_permissions(&dir, std::fs::Permissions::from_mode(0o555)).unwrap();
+        // NOTE: The permissions do not apply to a privileged user (e.g. in a container).
+        if std::fs::write(dir.join("probe"), b"").is_ok() {
+            log::warn!("Skipping test, since the permissions are not enforced.");
+            return;
+        }
+        let path = dir.join("out.gif");
+        assert!(matches!(
+            OutputPath::prepare(&path),
+            Err(Error::OutputPermissionDenied(p)) if p == path
+        ));
+    }
+
+    #[test]
+    fn test_validate() {
+        let path = temp_dir().join("out.gif");
+        let validate = |bytes: Option<&[u8]>, format| {
+            if let Some(bytes) = bytes {
+                std::fs::write(&path, bytes).unwrap();
+            }
+            validate(&path, format)
+        };
+        let gif = sample_gif(2, Some(0));
+        let webp = b"RIFF\x24\x00\x00\x00WEBPVP8X";
+
+        assert!(matches!(
+            validate(None, OutputFormat::Gif),
+            Err(Error::EmptyOutputFile(p)) if p == path
+        ));
+        assert!(matches!(
+            validate(Some(b""), OutputFormat::Gif),
+            Err(Error::EmptyOutputFile(p)) if p == path
+        ));
+        assert!(matches!(validate(Some(&gif), OutputFormat::Gif), Ok(None)));
+        assert!(matches!(
+            validate(Some(&gif[..gif.len() - 1]), OutputFormat::Gif),
+            Err(Error::InvalidOutput(GifParseError::UnexpectedEof { .. }))
+        ));
+        assert!(matches!(
+            validate(Some(webp), OutputFormat::Gif),
+            Err(Error::InvalidOutput(GifParseError::InvalidSignature))
+        ));
+        assert!(matches!(
+            validate(Some(webp), OutputFormat::Apng),
+            Err(Error::InvalidOutputSignature(OutputFormat::Apng))
+        ));
+        let webp_format = OutputFormat::WebP {
+            quality: 75,
+            lossless: false,
+        };
+        assert!(matches!(validate(Some(webp), webp_format), Ok(None)));
+    }
+}
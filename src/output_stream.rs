@@ -0,0 +1,156 @@
+//! The parsing of the `Output #0` section written by FFmpeg to `stderr` as soon as the
+//! filter graph is configured, which gives the animated GIF's dimensions (see
+//! [`crate::Message::OutputDimensions`]) long before the GIF itself is finished, e.g. when
+//! only its width was specified (in which case the height follows the video's aspect ratio).
+//!
+//! The video stream's line lists its properties, separated by commas, the dimensions
+//! following the pixel format (e.g. `Stream #0:0: Video: gif, pal8(pc, gbr/unknown/unknown,
+//! progressive), 200x112 [SAR 1:1 DAR 25:14], q=2-31, 200 kb/s, 10 fps, 100 tbn`). Since the
+//! pixel format's own details (in parentheses) contain commas as well, and vary from one
+//! version of FFmpeg to another, the dimensions are looked for among the top level properties.
+
+#[derive(Debug, Default)]
+/// Finds the dimensions of the output's video stream in FFmpeg's `stderr` output, fed line by line.
+pub(crate) struct OutputStreamParser {
+    in_section: bool,
+    done: bool,
+}
+
+impl OutputStreamParser {
+    /// Parses the next `line`, returning the output's `(width, height)` once found
+    /// (which happens at most once).
+    pub(crate) fn push(&mut self, line: &str) -> Option<(u32, u32)> {
+        if self.done {
+            return None;
+        }
+        let line = line.trim();
+        if line.starts_with("Output #") {
+            self.in_section = true;
+            return None;
+        }
+        if line.starts_with("Input #") || line == "Stream mapping:" {
+            self.in_section = false;
+            return None;
+        }
+        if !self.in_section || !line.starts_with("Stream #") {
+            return None;
+        }
+        // NOTE: E.g. `Stream #0:0: Video: gif, pal8, 320x180, q=2-31, 200 kb/s, 10 fps`.
+        let (_, properties) = line.split_once(": Video: ")?;
+        let dimensions = top_level_properties(properties)
+            .into_iter()
+            .find_map(dimensions)?;
+        self.done = true;
+        Some(dimensions)
+    }
+}
+
+/// The comma separated properties of `s`, not counting the commas found between parentheses
+/// (e.g. `pal8(pc, progressive)` is a single property).
+fn top_level_properties(s: &str) -> Vec<&str> {
+    let mut properties = vec![];
+    let mut depth = 0usize;
+    let mut start = 0;
+    for (i, c) in s.char_indices() {
+        match c {
+            '(' | '[' => depth += 1,
+            ')' | ']' => depth = depth.saturating_sub(1),
+            ',' if depth == 0 => {
+                properties.push(s[start..i].trim());
+                start = i + 1;
+            }
+            _ => {}
+        }
+    }
+    properties.push(s[start..].trim());
+    properties
+}
+
+/// The `(width, height)` given by `property`, if it starts with them (e.g. `200x112`,
+/// possibly followed by the aspect ratios, such as `[SAR 1:1 DAR 25:14]`).
+fn dimensions(property: &str) -> Option<(u32, u32)> {
+    let size = property.split_whitespace().next()?;
+    let (width, height) = size.split_once('x')?;
+    match (width.parse(), height.parse()) {
+        (Ok(width), Ok(height)) if width > 0 && height > 0 => Some((width, height)),
+        _ => None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn parse(stderr: &str) -> Option<(u32, u32)> {
+        let mut parser = OutputStreamParser::default();
+        stderr.lines().find_map(|line| parser.push(line))
+    }
+
+    #[test]
+    fn test_output_stream_fixtures() {
+        let cases = [
+            ("sample", crate::test_utils::SAMPLE_STDERR, Some((200, 112))),
+            (
+                "ffmpeg 5",
+                "Output #0, gif, to 'pipe:':\n  Metadata:\n    encoder         : Lavf59.27.100\n  Stream #0:0: Video: gif, pal8(pc), 480x270 [SAR 1:1 DAR 16:9], q=2-31, 200 kb/s, 10 fps, 100 tbn\n    Metadata:\n      encoder         : Lavc59.37.100 gif\n",
+                Some((480, 270)),
+            ),
+            (
+                "ffmpeg 4, without SAR",
+                "Output #0, gif, to 'out.gif':\n    Stream #0:0: Video: gif, pal8, 320x180, q=2-31, 200 kb/s, 10 fps, 100 tbn, 100 tbc\n",
+                Some((320, 180)),
+            ),
+            (
+                "non-square pixels",
+                "Output #0, gif, to 'pipe:':\n  Stream #0:0: Video: gif, pal8(pc, gbr/unknown/unknown, progressive), 200x113 [SAR 339:340 DAR 600:339], q=2-31, 200 kb/s, 10 fps, 100 tbn\n",
+                Some((200, 113)),
+            ),
+            (
+                "stream index and language",
+                "Output #0, gif, to 'pipe:':\n  Stream #0:0(und): Video: gif, bgr8, 64x36, q=2-31, 200 kb/s, 10 fps, 100 tbn (default)\n",
+                Some((64, 36)),
+            ),
+            (
+                "input only",
+                "Input #0, mov,mp4,m4a,3gp,3g2,mj2, from 'in.mp4':\n  Stream #0:0[0x1](und): Video: h264 (High) (avc1 / 0x31637661), yuv420p(progressive), 640x360 [SAR 1:1 DAR 16:9], 1538 kb/s, 24 fps\nStream mapping:\n  Stream #0:0 (h264) -> fps:default\n",
+                None,
+            ),
+            (
+                "no dimensions",
+                "Output #0, gif, to 'pipe:':\n  Stream #0:0: Video: gif, pal8, q=2-31, 200 kb/s, 10 fps\n",
+                None,
+            ),
+        ];
+        for (name, stderr, expected) in cases {
+            assert_eq!(parse(stderr), expected, "{}", name);
+        }
+    }
+
+    #[test]
+    fn test_output_stream_reported_once() {
+        let mut parser = OutputStreamParser::default();
+        let line = "  Stream #0:0: Video: gif, pal8, 320x180, q=2-31";
+        assert_eq!(parser.push(line), None);
+        assert_eq!(parser.push("Output #0, gif, to 'pipe:':"), None);
+        assert_eq!(parser.push(line), Some((320, 180)));
+        assert_eq!(parser.push(line), None);
+    }
+
+    #[test]
+    fn test_top_level_properties() {
+        assert_eq!(
+            top_level_properties(
+                "gif, pal8(pc, progressive), 200x112 [SAR 1:1, DAR 25:14], q=2-31"
+            ),
+            [
+                "gif",
+                "pal8(pc, progressive)",
+                "200x112 [SAR 1:1, DAR 25:14]",
+                "q=2-31"
+            ]
+        );
+        assert_eq!(dimensions("0x1"), None);
+        assert_eq!(dimensions("0x1a"), None);
+        assert_eq!(dimensions("10 fps"), None);
+    }
+}
@@ -0,0 +1,151 @@
+//! The streaming mode (see [`crate::Converter::convert_to_writer`]), in which the animated GIF
+//! is copied to a writer provided by the application as FFmpeg produces it (e.g. to upload it
+//! while it is being generated), instead of being kept in memory.
+//!
+//! Since the bytes are gone once written, only the beginning and the end of the output are
+//! kept along the way, to validate it once complete, just like a file written by FFmpeg
+//! (see [`crate::output_path::validate`]).
+
+use std::io::Write;
+use std::sync::{Arc, Mutex};
+
+use crate::gif_info::GifInfo;
+use crate::output_path::{validate_ends, SIGNATURE_LEN};
+use crate::{Error, OutputFormat};
+
+/// The writer to which the animated GIF is copied, which keeps track of what it was given.
+pub(crate) struct OutputWriter {
+    writer: Box<dyn Write + Send>,
+    /// The first bytes written (see [`SIGNATURE_LEN`]).
+    signature: Vec<u8>,
+    /// The last byte written, if any.
+    last: Option<u8>,
+    /// The number of bytes accepted by the writer so far.
+    written: u64,
+}
+
+impl OutputWriter {
+    pub(crate) fn new(writer: impl Write + Send + 'static) -> Self {
+        Self {
+            writer: Box::new(writer),
+            signature: Vec::new(),
+            last: None,
+            written: 0,
+        }
+    }
+
+    /// Writes all of `bytes`, only counting those actually accepted by the writer when
+    /// it fails part of the way (unlike [`Write::write_all`]).
+    pub(crate) fn write_all(&mut self, mut bytes: &[u8]) -> std::io::Result<()> {
+        while !bytes.is_empty() {
+            match self.writer.write(bytes) {
+                Ok(0) => return Err(std::io::ErrorKind::WriteZero.into()),
+                Ok(n) => {
+                    self.record(&bytes[..n]);
+                    bytes = &bytes[n..];
+                }
+                Err(e) if e.kind() == std::io::ErrorKind::Interrupted => {}
+                Err(e) => return Err(e),
+            }
+        }
+        Ok(())
+    }
+
+    pub(crate) fn flush(&mut self) -> std::io::Result<()> {
+        self.writer.flush()
+    }
+
+    /// The number of bytes written so far.
+    pub(crate) fn written(&self) -> u64 {
+        self.written
+    }
+
+    /// Same as validating the bytes written by FFmpeg to `stdout`, for the bytes written so
+    /// far: they must not be empty, and must start with the format's signature (and, for an
+    /// animated GIF, end with its trailer). Since the GIF is not parsed, its metadata is
+    /// never returned.
+    pub(crate) fn validate(&self, format: OutputFormat) -> Result<Option<GifInfo>, Error> {
+        match self.last {
+            None => Err(Error::EmptyStdout),
+            Some(last) => validate_ends(&self.signature, last, self.written, format),
+        }
+    }
+
+    /// Gives up the writer, so that it can be lent to the jobs run on the application's
+    /// behalf (see [`SharedSink`]), forgetting what it was given so far.
+    pub(crate) fn into_shared(self) -> SharedSink {
+        SharedSink(Arc::new(Mutex::new(self.writer)))
+    }
+
+    fn record(&mut self, bytes: &[u8]) {
+        let missing = (SIGNATURE_LEN as usize).saturating_sub(self.signature.len());
+        self.signature
+            .extend_from_slice(&bytes[..missing.min(bytes.len())]);
+        self.last = bytes.last().copied().or(self.last);
+        self.written += bytes.len() as u64;
+    }
+}
+
+/// The application's writer, shared by the [`OutputWriter`]s of the jobs that are run one
+/// after the other on its behalf (see [`crate::HwAccelFallback::Software`]).
+#[derive(Clone)]
+pub(crate) struct SharedSink(Arc<Mutex<Box<dyn Write + Send>>>);
+
+impl Write for SharedSink {
+    fn write(&mut self, bytes: &[u8]) -> std::io::Result<usize> {
+        self.0
+            .lock()
+            .unwrap_or_else(|e| e.into_inner())
+            .write(bytes)
+    }
+
+    fn flush(&mut self) -> std::io::Result<()> {
+        self.0.lock().unwrap_or_else(|e| e.into_inner()).flush()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::gif_info::GifParseError;
+    use crate::test_utils::{sample_gif, FailingWriter, SharedWriter};
+
+    #[test]
+    fn test_write_and_validate() {
+        let gif = sample_gif(3, Some(0));
+        let shared = SharedWriter::default();
+        let mut writer = OutputWriter::new(shared.clone());
+        assert!(matches!(
+            writer.validate(OutputFormat::Gif),
+            Err(Error::EmptyStdout)
+        ));
+        // NOTE: Split so that the signature spans two writes.
+        for chunk in gif.chunks(4) {
+            writer.write_all(chunk).unwrap();
+        }
+        writer.flush().unwrap();
+        assert_eq!(writer.written(), gif.len() as u64);
+        assert_eq!(shared.bytes(), gif);
+        assert!(matches!(writer.validate(OutputFormat::Gif), Ok(None)));
+        assert!(matches!(
+            writer.validate(OutputFormat::Apng),
+            Err(Error::InvalidOutputSignature(OutputFormat::Apng))
+        ));
+
+        let mut writer = OutputWriter::new(SharedWriter::default());
+        writer.write_all(&gif[..gif.len() - 1]).unwrap();
+        assert!(matches!(
+            writer.validate(OutputFormat::Gif),
+            Err(Error::InvalidOutput(GifParseError::UnexpectedEof { .. }))
+        ));
+    }
+
+    #[test]
+    fn test_write_failure() {
+        let mut writer = OutputWriter::new(FailingWriter::new(10));
+        writer.write_all(&[0; 4]).unwrap();
+        assert!(writer.write_all(&[0; 8]).is_err());
+        // Only the bytes accepted before the failure are counted.
+        assert_eq!(writer.written(), 10);
+    }
+}
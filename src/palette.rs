@@ -0,0 +1,182 @@
+//! The content analysis used by [`crate::Settings::auto_colors`] to pick the
+//! number of colors of the animated GIF's palette.
+//!
+//! The analysis pass decodes at most [`SAMPLE_FRAMES`] frames, sampled one second
+//! apart from the beginning of the video (i.e. it decodes at most the first
+//! `SAMPLE_FRAMES` seconds of the source) and downscaled to [`SAMPLE_WIDTH`] pixels
+//! wide, which FFmpeg writes to `stdout` as raw RGB data. The pixels are then
+//! counted in a coarse color histogram (5 bits per channel), and the smallest
+//! candidate palette size that covers all but [`MAX_UNCOVERED_RATIO`] of the
+//! sampled pixels is selected. The overhead is therefore bounded by the time
+//! it takes FFmpeg to decode a few seconds of video, which is typically a small
+//! fraction of the conversion itself.
+
+/// The palette sizes the heuristic can choose from, in increasing order.
+pub(crate) const CANDIDATE_COLOR_COUNTS: [u16; 5] = [16, 32, 64, 128, 256];
+
+/// The largest fraction of sampled pixels that may fall outside the most
+/// frequent histogram bins for a palette size to be selected.
+pub(crate) const MAX_UNCOVERED_RATIO: f64 = 0.01;
+
+/// The maximum number of frames sampled by the analysis pass.
+pub(crate) const SAMPLE_FRAMES: u32 = 8;
+
+/// The width (in pixels) to which the sampled frames are downscaled.
+pub(crate) const SAMPLE_WIDTH: u32 = 64;
+
+/// The number of bits kept per color channel in the histogram.
+const BITS_PER_CHANNEL: u32 = 5;
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+/// Statistics about the colors found in the sampled frames.
+pub(crate) struct ColorStats {
+    /// The pixel counts of the non-empty histogram bins, in descending order.
+    pub(crate) bin_counts: Vec<u64>,
+    /// The total number of sampled pixels.
+    pub(crate) total: u64,
+}
+
+impl ColorStats {
+    /// Builds the color statistics from raw `rgb24` pixel data.
+    pub(crate) fn from_rgb24(data: &[u8]) -> Self {
+        let shift = 8 - BITS_PER_CHANNEL;
+        let mut histogram = vec![0u64; 1 << (3 * BITS_PER_CHANNEL)];
+        for pixel in data.chunks_exact(3) {
+            let index = ((pixel[0] as usize >> shift) << (2 * BITS_PER_CHANNEL))
+                | ((pixel[1] as usize >> shift) << BITS_PER_CHANNEL)
+                | (pixel[2] as usize >> shift);
+            histogram[index] += 1;
+        }
+        let mut bin_counts: Vec<u64> = histogram.into_iter().filter(|&n| n > 0).collect();
+        bin_counts.sort_unstable_by(|a, b| b.cmp(a));
+        let total = bin_counts.iter().sum();
+        Self { bin_counts, total }
+    }
+
+    /// The fraction of the sampled pixels that fall outside the `n` most frequent bins.
+    pub(crate) fn uncovered_ratio(&self, n: usize) -> f64 {
+        if self.total == 0 {
+            return 0.0;
+        }
+        let covered: u64 = self.bin_counts.iter().take(n).sum();
+        (self.total - covered) as f64 / self.total as f64
+    }
+}
+
+/// Picks the smallest palette size (among [`CANDIDATE_COLOR_COUNTS`]) whose
+/// estimated error is below [`MAX_UNCOVERED_RATIO`], falling back to the
+/// largest candidate when no pixels were sampled.
+pub(crate) fn choose_color_count(stats: &ColorStats) -> u16 {
+    let largest = CANDIDATE_COLOR_COUNTS[CANDIDATE_COLOR_COUNTS.len() - 1];
+    if stats.total == 0 {
+        return largest;
+    }
+    CANDIDATE_COLOR_COUNTS
+        .into_iter()
+        .find(|&n| stats.uncovered_ratio(n as usize) <= MAX_UNCOVERED_RATIO)
+        .unwrap_or(largest)
+}
+
+/// The arguments passed to FFmpeg for the analysis pass, given the input options that
+/// select the clip (see [`crate::Settings::clip`]), the arguments that make FFmpeg read the
+/// input (i.e. its `-i` flag, see [`crate::InputSource`], preceded by any input option), the
+/// index of the selected video stream, if any (see [`crate::Settings::video_stream_index`]),
+/// and the filters that come before the palette's generation in the conversion job's graph
+/// and change the frames' colors (e.g. the crop), so that the same pixels are sampled.
+pub(crate) fn analysis_args(
+    seek_args: Vec<String>,
+    input_args: Vec<String>,
+    video_stream_index: Option<u32>,
+    filters: Vec<String>,
+) -> Vec<String> {
+    let mut args = vec!["-nostdin".into()];
+    args.extend(seek_args);
+    args.extend(input_args);
+    args.extend(crate::video_stream::map_args(video_stream_index));
+    let filters = filters
+        .into_iter()
+        .chain([format!("fps=1,scale={}:-2", SAMPLE_WIDTH)]);
+    args.extend([
+        "-vf".into(),
+        Vec::from_iter(filters).join(","),
+        "-frames:v".into(),
+        SAMPLE_FRAMES.to_string(),
+        "-f".into(),
+        "rawvideo".into(),
+        "-pix_fmt".into(),
+        "rgb24".into(),
+        "-".into(),
+    ]);
+    args
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Builds `rgb24` data where each of the `colors` appears `pixels_per_color` times.
+    fn rgb24(colors: &[[u8; 3]], pixels_per_color: usize) -> Vec<u8> {
+        colors
+            .iter()
+            .flat_map(|c| std::iter::repeat_n(c, pixels_per_color).flatten().copied())
+            .collect()
+    }
+
+    #[test]
+    fn test_color_stats_from_rgb24() {
+        let stats = ColorStats::from_rgb24(&rgb24(&[[255, 0, 0], [0, 0, 255]], 3));
+        assert_eq!(stats.total, 6);
+        assert_eq!(stats.bin_counts, vec![3, 3]);
+
+        // Colors that only differ in their least significant bits share a bin.
+        let stats = ColorStats::from_rgb24(&rgb24(&[[255, 0, 0], [250, 3, 1]], 2));
+        assert_eq!(stats.bin_counts, vec![4]);
+
+        // Incomplete trailing pixels are ignored.
+        let stats = ColorStats::from_rgb24(&[1, 2, 3, 4]);
+        assert_eq!(stats.total, 1);
+    }
+
+    #[test]
+    fn test_choose_color_count_no_samples() {
+        let stats = ColorStats::from_rgb24(&[]);
+        assert_eq!(choose_color_count(&stats), 256);
+    }
+
+    #[test]
+    fn test_choose_color_count_few_colors() {
+        let stats = ColorStats::from_rgb24(&rgb24(&[[255, 0, 0], [0, 255, 0], [0, 0, 255]], 10));
+        assert_eq!(choose_color_count(&stats), 16);
+    }
+
+    #[test]
+    fn test_choose_color_count_ignores_rare_colors() {
+        // 16 dominant colors, plus a few isolated pixels of other colors.
+        let dominant: Vec<[u8; 3]> = (0..16).map(|i| [i * 16, 0, 0]).collect();
+        let rare: Vec<[u8; 3]> = (0..16).map(|i| [0, i * 16, 255]).collect();
+        let mut data = rgb24(&dominant, 1000);
+        data.extend(rgb24(&rare, 1));
+        let stats = ColorStats::from_rgb24(&data);
+        assert_eq!(choose_color_count(&stats), 16);
+    }
+
+    #[test]
+    fn test_choose_color_count_intermediate() {
+        let colors: Vec<[u8; 3]> = (0..100)
+            .map(|i| [(i % 10) * 25, (i / 10) * 25, 0])
+            .collect();
+        let stats = ColorStats::from_rgb24(&rgb24(&colors, 10));
+        // 100 distinct bins: 64 colors leave too many pixels uncovered.
+        assert_eq!(stats.bin_counts.len(), 100);
+        assert_eq!(choose_color_count(&stats), 128);
+    }
+
+    #[test]
+    fn test_choose_color_count_many_colors() {
+        let data: Vec<u8> = (0..=255u8)
+            .flat_map(|r| (0..=255u8).step_by(8).flat_map(move |g| [r, g, 255 - r]))
+            .collect();
+        let stats = ColorStats::from_rgb24(&data);
+        assert_eq!(choose_color_count(&stats), 256);
+    }
+}
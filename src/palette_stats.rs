@@ -0,0 +1,78 @@
+//! The options of FFmpeg's `palettegen` and `paletteuse` filters that control which
+//! pixels the palette is computed from (see [`crate::Settings::palette_stats_mode`]).
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
+/// Which pixels the animated GIF's palette is computed from (i.e. the `stats_mode`
+/// option of FFmpeg's `palettegen` filter).
+pub enum PaletteStatsMode {
+    /// A single palette is computed from all the pixels of all the frames.
+    #[default]
+    Full,
+    /// A single palette is computed from the pixels that differ from the previous frame,
+    /// which favors the moving parts over a static background.
+    Diff,
+    /// A palette is computed for each frame, and applied to that frame only, which gives
+    /// each frame its own (i.e. local) color table.
+    Single,
+}
+
+impl PaletteStatsMode {
+    /// A short name for the mode (e.g. for [`crate::ConversionPlan`]'s summary).
+    pub(crate) fn name(&self) -> &'static str {
+        match self {
+            Self::Full => "full",
+            Self::Diff => "diff",
+            Self::Single => "single",
+        }
+    }
+
+    /// The option appended to the `palettegen` filter (none for FFmpeg's default).
+    pub(crate) fn palettegen_option(&self) -> Option<String> {
+        match self {
+            Self::Full => None,
+            Self::Diff | Self::Single => Some(format!("stats_mode={}", self.name())),
+        }
+    }
+
+    /// The option appended to the `paletteuse` filter, which must pick up each new
+    /// palette when one is generated per frame.
+    pub(crate) fn paletteuse_option(&self) -> Option<String> {
+        match self {
+            Self::Full | Self::Diff => None,
+            Self::Single => Some("new=1".into()),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_filter_options() {
+        let cases = [
+            (PaletteStatsMode::Full, None, None),
+            (PaletteStatsMode::Diff, Some("stats_mode=diff"), None),
+            (
+                PaletteStatsMode::Single,
+                Some("stats_mode=single"),
+                Some("new=1"),
+            ),
+        ];
+        for (mode, palettegen, paletteuse) in cases {
+            assert_eq!(
+                mode.palettegen_option().as_deref(),
+                palettegen,
+                "{:?}",
+                mode
+            );
+            assert_eq!(
+                mode.paletteuse_option().as_deref(),
+                paletteuse,
+                "{:?}",
+                mode
+            );
+        }
+    }
+}
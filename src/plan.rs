@@ -0,0 +1,470 @@
+//! The conversion plan (see [`crate::Settings::plan`]), which describes what the
+//! conversion job is going to do, using the same logic as the job itself.
+
+use std::time::Duration;
+
+use crate::{
+    ClipSelection, CropRect, EvenDimensionPolicy, FitMode, OutputFormat, PaletteStatsMode,
+    Rotation, ScaleAlgorithm, SizeMode,
+};
+
+/// FFmpeg's default dithering algorithm for the `paletteuse` filter, which is
+/// the one used by the conversion job.
+pub(crate) const DITHER: &str = "sierra2_4a";
+
+#[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
+/// The effective values used by the conversion job (i.e. after the defaults are
+/// applied), along with the arguments passed to FFmpeg, as returned by [`crate::Settings::plan`].
+/// The [`std::fmt::Display`] implementation renders a short, human-readable summary
+/// (e.g. `200px wide, 10 fps, 64 colors, first 0:05, sierra2_4a dither`).
+///
+/// NOTE: With [`crate::Settings::auto_colors`], the number of colors is only picked
+/// once the job starts (see [`crate::Message::ColorCountSelected`]), and the clips that
+/// depend on the video's duration (see [`ClipSelection::FromEnd`] and [`ClipSelection::Middle`])
+/// are only resolved once it has been probed, so the plan reports them as is (and the
+/// FFmpeg arguments don't include them yet).
+pub struct ConversionPlan {
+    /// The value passed to FFmpeg's `-i` flag.
+    pub input: String,
+    /// The animated GIF's frame rate (in frames per second).
+    pub fps: u16,
+    /// The animated GIF's width.
+    pub width: u16,
+    /// The widths of the other animated GIFs generated by the same job (see
+    /// [`crate::Settings::additional_widths`]).
+    pub additional_widths: Vec<u16>,
+    /// The animated GIF's height (following the video's aspect ratio if `None`).
+    pub height: Option<u16>,
+    /// How the video's frames are fitted into the animated GIF's width and height
+    /// (`None` if its height follows the video's aspect ratio).
+    pub fit_mode: Option<FitMode>,
+    /// The algorithm used to resize the video's frames (FFmpeg's default if `None`).
+    pub scale_algorithm: Option<ScaleAlgorithm>,
+    /// How the height that follows the video's aspect ratio is rounded.
+    pub even_dimension_policy: EvenDimensionPolicy,
+    /// How the video's frames are rotated (not at all if `None`).
+    pub rotation: Option<Rotation>,
+    /// Whether the video's frames are mirrored horizontally.
+    pub hflip: bool,
+    /// Whether the video's frames are flipped vertically.
+    pub vflip: bool,
+    /// The saturation of the video's frames, relative to the source video's.
+    pub saturation: f32,
+    /// The brightness added to the video's frames.
+    pub brightness: f32,
+    /// The contrast of the video's frames, relative to the source video's.
+    pub contrast: f32,
+    /// The path of the image overlaid onto the animated GIF's frames, if any.
+    pub watermark: Option<std::path::PathBuf>,
+    /// The path of the subtitles file burned into the animated GIF's frames, if any.
+    pub subtitles: Option<std::path::PathBuf>,
+    /// The format of the animation (see [`crate::Settings::output_format`]), whose
+    /// palette options (e.g. the number of colors) only apply to an animated GIF.
+    pub output_format: OutputFormat,
+    /// The maximum number of colors in the animated GIF's palette (see also
+    /// [`crate::Settings::max_palette_bit_depth`]).
+    pub max_colors: u16,
+    /// Whether the number of colors is picked automatically when the job starts.
+    pub auto_colors: bool,
+    /// Whether the animated GIF only has a global color table.
+    pub global_palette_only: bool,
+    /// Which pixels the palette is computed from.
+    pub palette_stats_mode: PaletteStatsMode,
+    /// Whether the source video's last frame is always included.
+    pub preserve_last_frame: bool,
+    /// Whether the delays of the animated GIF's frames are rewritten to match its frame rate.
+    pub interpolate_frame_delays: bool,
+    /// The part of the video that gets converted (the whole video if `None`).
+    pub clip: Option<ClipSelection>,
+    /// The maximum number of frames of the animated GIF (no limit if `None`).
+    pub output_frame_limit: Option<u32>,
+    /// The playback speed of the animated GIF, relative to the source video's.
+    pub speed: f32,
+    /// The interval at which the video's frames are kept (all of them if `None`).
+    pub frame_step: Option<u32>,
+    /// Whether the animated GIF plays forward, then backward.
+    pub boomerang: bool,
+    /// The region of the video's frames that gets converted, over time (the whole
+    /// frames if empty).
+    pub crop_keyframes: Vec<(Duration, CropRect)>,
+    /// The dithering algorithm used when applying the palette.
+    pub dither: String,
+    /// The value of FFmpeg's `-filter_complex` flag.
+    pub filter_complex: String,
+    /// The arguments passed to FFmpeg (the extra ones, see [`crate::Settings::extra_input_args`],
+    /// being converted lossily if they are not valid unicode).
+    pub args: Vec<String>,
+}
+
+impl ConversionPlan {
+    /// How the main animated GIF's dimensions are derived, e.g. for previewing them using
+    /// [`crate::compute_output_dimensions`] (along with [`ConversionPlan::even_dimension_policy`]).
+    pub fn size_mode(&self) -> SizeMode {
+        match (self.height, self.fit_mode) {
+            (Some(height), Some(fit)) => SizeMode::Fit {
+                width: self.width,
+                height,
+                fit,
+            },
+            _ => SizeMode::Width(self.width),
+        }
+    }
+}
+
+/// Formats a timestamp as `m:ss` (with milliseconds, if any).
+fn timestamp(t: Duration) -> String {
+    let millis = t.as_millis();
+    let (minutes, seconds, millis) = (millis / 60_000, millis / 1000 % 60, millis % 1000);
+    match millis {
+        0 => format!("{}:{:02}", minutes, seconds),
+        _ => format!("{}:{:02}.{:03}", minutes, seconds, millis),
+    }
+}
+
+impl std::fmt::Display for ConversionPlan {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match (self.height, self.fit_mode) {
+            (Some(height), Some(fit_mode)) => write!(
+                f,
+                "{}x{} ({}), {} fps, ",
+                self.width,
+                height,
+                fit_mode.name(),
+                self.fps
+            )?,
+            _ => write!(f, "{}px wide, {} fps, ", self.width, self.fps)?,
+        }
+        if self.height.is_none() && self.even_dimension_policy == EvenDimensionPolicy::Even {
+            write!(f, "even height, ")?;
+        }
+        if !self.additional_widths.is_empty() {
+            let widths = Vec::from_iter(self.additional_widths.iter().map(|w| format!("{}px", w)));
+            write!(f, "also {}, ", widths.join(" and "))?;
+        }
+        match self.auto_colors {
+            true => write!(f, "auto colors, ")?,
+            false => write!(f, "{} colors, ", self.max_colors)?,
+        }
+        match self.clip {
+            None => write!(f, "whole video, ")?,
+            Some(ClipSelection::FromStart(d)) => write!(f, "first {}, ", timestamp(d))?,
+            Some(ClipSelection::FromEnd(d)) => write!(f, "last {}, ", timestamp(d))?,
+            Some(ClipSelection::Middle(d)) => write!(f, "middle {}, ", timestamp(d))?,
+            Some(ClipSelection::StartingAt(d)) => write!(f, "from {}, ", timestamp(d))?,
+            Some(ClipSelection::Range(start, end)) => write!(
+                f,
+                "trimmed {}\u{2013}{}, ",
+                timestamp(start),
+                timestamp(end)
+            )?,
+        }
+        if let Some(frames) = self.output_frame_limit {
+            write!(f, "{} frames max, ", frames)?;
+        }
+        if let Some(step) = self.frame_step.filter(|step| *step > 1) {
+            write!(f, "1 frame in {}, ", step)?;
+        }
+        if self.speed != 1.0 {
+            write!(f, "{}x speed, ", self.speed)?;
+        }
+        if self.boomerang {
+            write!(f, "boomerang, ")?;
+        }
+        if let Some(algorithm) = self.scale_algorithm {
+            write!(f, "{} scaling, ", algorithm.name())?;
+        }
+        if let Some(rotation) = self.rotation {
+            write!(f, "rotated {}, ", rotation.name())?;
+        }
+        if self.hflip {
+            write!(f, "flipped horizontally, ")?;
+        }
+        if self.vflip {
+            write!(f, "flipped vertically, ")?;
+        }
+        if self.saturation == 0.0 {
+            write!(f, "grayscale, ")?;
+        } else if self.saturation != 1.0 {
+            write!(f, "{}x saturation, ", self.saturation)?;
+        }
+        if self.brightness != 0.0 {
+            write!(f, "{:+} brightness, ", self.brightness)?;
+        }
+        if self.contrast != 1.0 {
+            write!(f, "{}x contrast, ", self.contrast)?;
+        }
+        if self.watermark.is_some() {
+            write!(f, "watermarked, ")?;
+        }
+        if self.subtitles.is_some() {
+            write!(f, "subtitled, ")?;
+        }
+        if self.output_format != OutputFormat::Gif {
+            write!(f, "{} output, ", self.output_format.name())?;
+        }
+        if let Some((_, rect)) = self.crop_keyframes.first() {
+            write!(f, "cropped to {}x{}, ", rect.width, rect.height)?;
+        }
+        if self.preserve_last_frame {
+            write!(f, "last frame preserved, ")?;
+        }
+        if self.interpolate_frame_delays {
+            write!(f, "interpolated delays, ")?;
+        }
+        if self.global_palette_only {
+            write!(f, "global palette only, ")?;
+        }
+        match self.palette_stats_mode {
+            PaletteStatsMode::Full => {}
+            PaletteStatsMode::Diff => write!(f, "diff palette, ")?,
+            PaletteStatsMode::Single => write!(f, "per-frame palettes, ")?,
+        }
+        write!(f, "{} dither", self.dither)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_utils::SAMPLE_VIDEO_PATH;
+    use crate::Settings;
+
+    fn settings() -> Settings {
+        Settings::with_standard_fps(SAMPLE_VIDEO_PATH.into(), 200)
+    }
+
+    #[test]
+    fn test_timestamp() {
+        assert_eq!(timestamp(Duration::from_secs(5)), "0:05");
+        assert_eq!(timestamp(Duration::from_secs(72)), "1:12");
+        assert_eq!(timestamp(Duration::from_millis(4500)), "0:04.500");
+    }
+
+    #[test]
+    fn test_plan_defaults() {
+        let plan = settings().plan();
+        assert_eq!(
+            plan,
+            ConversionPlan {
+                input: SAMPLE_VIDEO_PATH.into(),
+                fps: 10,
+                width: 200,
+                additional_widths: vec![],
+                height: None,
+                fit_mode: None,
+                scale_algorithm: None,
+                even_dimension_policy: EvenDimensionPolicy::Any,
+                rotation: None,
+                hflip: false,
+                vflip: false,
+                saturation: 1.0,
+                brightness: 0.0,
+                contrast: 1.0,
+                watermark: None,
+                subtitles: None,
+                output_format: OutputFormat::Gif,
+                max_colors: 256,
+                auto_colors: false,
+                global_palette_only: false,
+                palette_stats_mode: PaletteStatsMode::Full,
+                preserve_last_frame: false,
+                interpolate_frame_delays: false,
+                clip: None,
+                output_frame_limit: None,
+                speed: 1.0,
+                frame_step: None,
+                boomerang: false,
+                crop_keyframes: vec![],
+                dither: "sierra2_4a".into(),
+                filter_complex: "fps=10,scale=200:-1[s]; [s]split[a][b]; [a]palettegen[palette]; [b][palette]paletteuse".into(),
+                args: settings().generate_args(None),
+            }
+        );
+        assert_eq!(
+            plan.to_string(),
+            "200px wide, 10 fps, 256 colors, whole video, sierra2_4a dither"
+        );
+    }
+
+    #[test]
+    fn test_plan_snapshots() {
+        let secs = Duration::from_secs;
+        let cases = [
+            (
+                settings().max_colors(64).clip(ClipSelection::Range(secs(5), secs(12))),
+                "200px wide, 10 fps, 64 colors, trimmed 0:05\u{2013}0:12, sierra2_4a dither",
+                "-stats -ss 5.000 -t 7.000 -i ./assets/big-buck-bunny-clip.mp4 -filter_complex fps=10,scale=200:-1[s]; [s]split[a][b]; [a]palettegen=max_colors=64[palette]; [b][palette]paletteuse -f gif -",
+            ),
+            (
+                settings().auto_colors(true).preserve_last_frame(true).interpolate_frame_delays(true),
+                "200px wide, 10 fps, auto colors, whole video, last frame preserved, interpolated delays, sierra2_4a dither",
+                "-stats -i ./assets/big-buck-bunny-clip.mp4 -filter_complex tpad=stop_mode=clone:stop_duration=0.1,fps=10,scale=200:-1[s]; [s]split[a][b]; [a]palettegen[palette]; [b][palette]paletteuse -f gif -",
+            ),
+            (
+                settings().max_colors(200).max_palette_bit_depth(7).global_palette_only(true),
+                "200px wide, 10 fps, 128 colors, whole video, global palette only, sierra2_4a dither",
+                "-stats -i ./assets/big-buck-bunny-clip.mp4 -filter_complex fps=10,scale=200:-1[s]; [s]split[a][b]; [a]palettegen=max_colors=128[palette]; [b][palette]paletteuse -global_palette 1 -f gif -",
+            ),
+            (
+                settings().clip(ClipSelection::FromStart(secs(10))).output_frame_limit(48),
+                "200px wide, 10 fps, 256 colors, first 0:10, 48 frames max, sierra2_4a dither",
+                "-stats -ss 0.000 -t 10.000 -i ./assets/big-buck-bunny-clip.mp4 -filter_complex fps=10,scale=200:-1[s]; [s]split[a][b]; [a]palettegen[palette]; [b][palette]paletteuse -frames:v 48 -f gif -",
+            ),
+            (
+                settings().max_colors(64).palette_stats_mode(PaletteStatsMode::Single),
+                "200px wide, 10 fps, 64 colors, whole video, per-frame palettes, sierra2_4a dither",
+                "-stats -i ./assets/big-buck-bunny-clip.mp4 -filter_complex fps=10,scale=200:-1[s]; [s]split[a][b]; [a]palettegen=max_colors=64:stats_mode=single[palette]; [b][palette]paletteuse=new=1 -f gif -",
+            ),
+            (
+                settings().scale_algorithm(ScaleAlgorithm::Lanczos),
+                "200px wide, 10 fps, 256 colors, whole video, lanczos scaling, sierra2_4a dither",
+                "-stats -i ./assets/big-buck-bunny-clip.mp4 -filter_complex fps=10,scale=200:-1:flags=lanczos[s]; [s]split[a][b]; [a]palettegen[palette]; [b][palette]paletteuse -f gif -",
+            ),
+            (
+                settings().frame_step(30),
+                "200px wide, 10 fps, 256 colors, whole video, 1 frame in 30, sierra2_4a dither",
+                "-stats -i ./assets/big-buck-bunny-clip.mp4 -filter_complex select='not(mod(n,30))',setpts=N/FRAME_RATE/TB,fps=10,scale=200:-1[s]; [s]split[a][b]; [a]palettegen[palette]; [b][palette]paletteuse -f gif -",
+            ),
+            (
+                settings().speed(2.0),
+                "200px wide, 10 fps, 256 colors, whole video, 2x speed, sierra2_4a dither",
+                "-stats -i ./assets/big-buck-bunny-clip.mp4 -filter_complex setpts=PTS/2,fps=10,scale=200:-1[s]; [s]split[a][b]; [a]palettegen[palette]; [b][palette]paletteuse -f gif -",
+            ),
+            (
+                settings().boomerang(true),
+                "200px wide, 10 fps, 256 colors, whole video, boomerang, sierra2_4a dither",
+                "-stats -i ./assets/big-buck-bunny-clip.mp4 -filter_complex fps=10,scale=200:-1,split[f][r]; [r]reverse[rr]; [f][rr]concat=n=2[s]; [s]split[a][b]; [a]palettegen[palette]; [b][palette]paletteuse -f gif -",
+            ),
+            (
+                settings().rotation(Rotation::Cw90),
+                "200px wide, 10 fps, 256 colors, whole video, rotated 90\u{b0} clockwise, sierra2_4a dither",
+                "-stats -i ./assets/big-buck-bunny-clip.mp4 -filter_complex fps=10,transpose=1,scale=200:-1[s]; [s]split[a][b]; [a]palettegen[palette]; [b][palette]paletteuse -f gif -",
+            ),
+            (
+                settings().hflip(true).vflip(true),
+                "200px wide, 10 fps, 256 colors, whole video, flipped horizontally, flipped vertically, sierra2_4a dither",
+                "-stats -i ./assets/big-buck-bunny-clip.mp4 -filter_complex fps=10,hflip,vflip,scale=200:-1[s]; [s]split[a][b]; [a]palettegen[palette]; [b][palette]paletteuse -f gif -",
+            ),
+            (
+                settings().saturation(0.0),
+                "200px wide, 10 fps, 256 colors, whole video, grayscale, sierra2_4a dither",
+                "-stats -i ./assets/big-buck-bunny-clip.mp4 -filter_complex fps=10,eq=saturation=0,scale=200:-1[s]; [s]split[a][b]; [a]palettegen[palette]; [b][palette]paletteuse -f gif -",
+            ),
+            (
+                settings().saturation(0.5),
+                "200px wide, 10 fps, 256 colors, whole video, 0.5x saturation, sierra2_4a dither",
+                "-stats -i ./assets/big-buck-bunny-clip.mp4 -filter_complex fps=10,eq=saturation=0.5,scale=200:-1[s]; [s]split[a][b]; [a]palettegen[palette]; [b][palette]paletteuse -f gif -",
+            ),
+            (
+                settings().brightness(0.2).contrast(1.5),
+                "200px wide, 10 fps, 256 colors, whole video, +0.2 brightness, 1.5x contrast, sierra2_4a dither",
+                "-stats -i ./assets/big-buck-bunny-clip.mp4 -filter_complex fps=10,eq=brightness=0.2:contrast=1.5,scale=200:-1[s]; [s]split[a][b]; [a]palettegen[palette]; [b][palette]paletteuse -f gif -",
+            ),
+            (
+                settings().start_time(secs(42)),
+                "200px wide, 10 fps, 256 colors, from 0:42, sierra2_4a dither",
+                "-stats -ss 42.000 -i ./assets/big-buck-bunny-clip.mp4 -filter_complex fps=10,scale=200:-1[s]; [s]split[a][b]; [a]palettegen[palette]; [b][palette]paletteuse -f gif -",
+            ),
+            (
+                settings().gif_height(150).fit_mode(FitMode::Crop),
+                "200x150 (crop), 10 fps, 256 colors, whole video, sierra2_4a dither",
+                "-stats -i ./assets/big-buck-bunny-clip.mp4 -filter_complex fps=10,scale=200:150:force_original_aspect_ratio=increase,crop=200:150[s]; [s]split[a][b]; [a]palettegen[palette]; [b][palette]paletteuse -f gif -",
+            ),
+            (
+                settings().even_dimension_policy(EvenDimensionPolicy::Even),
+                "200px wide, 10 fps, even height, 256 colors, whole video, sierra2_4a dither",
+                "-stats -i ./assets/big-buck-bunny-clip.mp4 -filter_complex fps=10,scale=200:-2[s]; [s]split[a][b]; [a]palettegen[palette]; [b][palette]paletteuse -f gif -",
+            ),
+            (
+                // NOTE: Not resolved until the video's duration is probed.
+                settings().clip(ClipSelection::FromEnd(secs(2))).crop_keyframes(vec![(
+                    Duration::ZERO,
+                    CropRect::new(10, 20, 320, 180),
+                )]),
+                "200px wide, 10 fps, 256 colors, last 0:02, cropped to 320x180, sierra2_4a dither",
+                "-stats -i ./assets/big-buck-bunny-clip.mp4 -filter_complex crop=w=320:h=180:x='10':y='20',fps=10,scale=200:-1[s]; [s]split[a][b]; [a]palettegen[palette]; [b][palette]paletteuse -f gif -",
+            ),
+            (
+                settings().additional_widths(vec![400]).gif_height(100).output_frame_limit(8),
+                "200x100 (letterbox), 10 fps, also 400px, 256 colors, whole video, 8 frames max, sierra2_4a dither",
+                "-stats -i ./assets/big-buck-bunny-clip.mp4 -filter_complex fps=10,split=2[o0_in][o1_in]; [o0_in]null,scale=200:100:force_original_aspect_ratio=decrease,pad=200:100:(ow-iw)/2:(oh-ih)/2[o0_s]; [o0_s]split[o0_a][o0_b]; [o0_a]palettegen[o0_palette]; [o0_b][o0_palette]paletteuse[out0]; [o1_in]null,scale=400:200:force_original_aspect_ratio=decrease,pad=400:200:(ow-iw)/2:(oh-ih)/2[o1_s]; [o1_s]split[o1_a][o1_b]; [o1_a]palettegen[o1_palette]; [o1_b][o1_palette]paletteuse[out1] -map [out0] -frames:v 8 -f gif - -map [out1] -frames:v 8 -f gif 400px.gif",
+            ),
+            (
+                settings().output_format(OutputFormat::WebP { quality: 80, lossless: false }),
+                "200px wide, 10 fps, 256 colors, whole video, WebP output, sierra2_4a dither",
+                "-stats -i ./assets/big-buck-bunny-clip.mp4 -filter_complex fps=10,scale=200:-1 -c:v libwebp_anim -quality 80 -lossless 0 -loop 0 -f webp -",
+            ),
+            (
+                settings().output_format(OutputFormat::Apng).boomerang(true),
+                "200px wide, 10 fps, 256 colors, whole video, boomerang, APNG output, sierra2_4a dither",
+                "-stats -i ./assets/big-buck-bunny-clip.mp4 -filter_complex fps=10,scale=200:-1,split[f][r]; [r]reverse[rr]; [f][rr]concat=n=2 -plays 0 -f apng -",
+            ),
+        ];
+        for (settings, summary, args) in cases {
+            let plan = settings.plan();
+            assert_eq!(plan.to_string(), summary);
+            assert_eq!(plan.args.join(" "), args);
+            let position = plan.args.iter().position(|a| a == "-filter_complex");
+            assert_eq!(plan.args[position.unwrap() + 1], plan.filter_complex);
+        }
+    }
+
+    #[test]
+    fn test_plan_size_mode() {
+        assert_eq!(settings().plan().size_mode(), SizeMode::Width(200));
+        // NOTE: A fit mode without a height is ignored.
+        assert_eq!(
+            settings().fit_mode(FitMode::Crop).plan().size_mode(),
+            SizeMode::Width(200)
+        );
+        assert_eq!(
+            settings().gif_height(150).plan().size_mode(),
+            SizeMode::Fit {
+                width: 200,
+                height: 150,
+                fit: FitMode::Letterbox
+            }
+        );
+        let plan = settings()
+            .even_dimension_policy(EvenDimensionPolicy::Even)
+            .plan();
+        assert_eq!(
+            crate::compute_output_dimensions(
+                (1920, 1080),
+                &plan.size_mode(),
+                plan.even_dimension_policy
+            ),
+            (200, 112)
+        );
+    }
+
+    #[test]
+    fn test_plan_diff() {
+        let before = settings().plan();
+        let after = settings().max_colors(32).plan();
+        assert_ne!(before, after);
+        assert_eq!(
+            ConversionPlan {
+                max_colors: 32,
+                filter_complex: after.filter_complex.clone(),
+                args: after.args.clone(),
+                ..before
+            },
+            after
+        );
+    }
+
+    #[cfg(feature = "serde")]
+    #[test]
+    fn test_plan_serialize() {
+        let plan = settings()
+            .clip(ClipSelection::FromStart(Duration::from_secs(5)))
+            .plan();
+        let json = serde_json::to_value(&plan).unwrap();
+        assert_eq!(json["width"], 200);
+        assert_eq!(json["max_colors"], 256);
+        assert_eq!(json["clip"]["FromStart"]["secs"], 5);
+        assert_eq!(json["filter_complex"], plan.filter_complex.as_str());
+    }
+}
@@ -0,0 +1,108 @@
+//! The preview pass (see [`crate::Settings::preview_width`]), i.e. a quick conversion at a
+//! smaller width, run before the job itself (see [`crate::Converter::convert`]), whose
+//! animated GIF is sent using [`crate::Message::Preview`].
+//!
+//! Each pass of the job is described by its own [`crate::Settings`], which is built from
+//! the application's (see [`crate::Settings::preview_settings`]), and which tells which
+//! pass it belongs to (see [`Pass`]), so that a pass can add its own filters to the
+//! generated graph (e.g. the badge stamped onto the preview only, see [`Text`]).
+
+use crate::Position;
+
+/// The space (in pixels) between the badge and the edges of the frames, which is also the
+/// padding of the box drawn behind the badge's text.
+const BADGE_MARGIN: u16 = 4;
+
+#[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
+/// The badge stamped onto the preview's frames (see [`crate::Settings::badge_overlay`]),
+/// e.g. `rendering…`, drawn using FFmpeg's `drawtext` filter (which requires FFmpeg to be
+/// built with `--enable-libfreetype`), in white over a translucent black box.
+pub struct Text {
+    /// The text itself, drawn as is (e.g. `%` is not expanded).
+    pub content: String,
+    /// Where the badge is placed on the frames.
+    pub position: Position,
+    /// The height of the text (in pixels).
+    pub font_size: u16,
+}
+
+impl Text {
+    /// The font size used by [`Text::new`].
+    pub const DEFAULT_FONT_SIZE: u16 = 16;
+
+    /// Creates a new badge showing `content`, placed against the top and right edges, and
+    /// using [`Text::DEFAULT_FONT_SIZE`].
+    pub fn new(content: impl Into<String>) -> Self {
+        Self {
+            content: content.into(),
+            position: Position::TopRight,
+            font_size: Self::DEFAULT_FONT_SIZE,
+        }
+    }
+
+    /// The `drawtext` filter, which stamps the badge onto the frames.
+    fn drawtext_filter(&self) -> String {
+        format!(
+            "drawtext=text={}:expansion=none:fontsize={}:fontcolor=white:box=1:boxcolor=black@0.5:boxborderw={}:{}",
+            crate::subtitles::escape_value(&self.content),
+            self.font_size,
+            BADGE_MARGIN,
+            self.position.drawtext_options(BADGE_MARGIN)
+        )
+    }
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+/// The pass of the job described by the [`crate::Settings`].
+pub(crate) enum Pass {
+    /// The job itself, whose animated GIF is sent using [`crate::Message::Success`].
+    #[default]
+    Final,
+    /// The preview, whose animated GIF is sent using [`crate::Message::Preview`].
+    Preview,
+}
+
+impl Pass {
+    /// The filter added by this pass to the generated graph, once the frames have been
+    /// scaled (so that the badge's size does not depend on the width), if any.
+    pub(crate) fn filter(&self, badge: Option<&Text>) -> Option<String> {
+        match self {
+            Self::Final => None,
+            Self::Preview => badge.map(Text::drawtext_filter),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_drawtext_filter() {
+        assert_eq!(
+            Text::new("rendering…").drawtext_filter(),
+            "drawtext=text='rendering…':expansion=none:fontsize=16:fontcolor=white:box=1:boxcolor=black@0.5:boxborderw=4:x=w-tw-4:y=4"
+        );
+        let text = Text {
+            content: "50%: it's, [almost] done".into(),
+            position: Position::BottomLeft,
+            font_size: 12,
+        };
+        assert_eq!(
+            text.drawtext_filter(),
+            r"drawtext=text='50%\: it\'\''s, [almost] done':expansion=none:fontsize=12:fontcolor=white:box=1:boxcolor=black@0.5:boxborderw=4:x=4:y=h-th-4"
+        );
+    }
+
+    #[test]
+    fn test_pass_filter() {
+        let badge = Text::new("rendering…");
+        assert_eq!(Pass::Final.filter(Some(&badge)), None);
+        assert_eq!(Pass::Preview.filter(None), None);
+        assert_eq!(
+            Pass::Preview.filter(Some(&badge)),
+            Some(badge.drawtext_filter())
+        );
+    }
+}
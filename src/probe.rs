@@ -0,0 +1,497 @@
+//! The probing of the source video's properties (see [`VideoInfo::probe`]), and the
+//! heuristics used by [`crate::Settings::auto`] to pick sensible settings from them, along
+//! with the quick check of a file's content (see [`quick_validate`]).
+
+use std::path::Path;
+use std::sync::Arc;
+use std::time::Duration;
+
+use crate::auxiliary::{run_auxiliary, run_auxiliary_reading, AuxiliaryError};
+use crate::sniff::{sniff, InputKind, SNIFF_LEN};
+use crate::time_parsing::try_extract_duration;
+use crate::{FfmpegLocation, FfmpegLocationError, InputSource};
+
+const LOG_TARGET: &str = "ffmpeg_gif_maker::probe";
+
+/// The maximum width picked by [`crate::Settings::auto`].
+pub(crate) const AUTO_MAX_WIDTH: u32 = 480;
+/// The maximum frame rate picked by [`crate::Settings::auto`].
+pub(crate) const AUTO_MAX_FPS: u16 = 15;
+/// The maximum frame rate picked by [`crate::Settings::auto`] for long videos.
+pub(crate) const AUTO_MAX_FPS_LONG: u16 = 10;
+/// The duration up to which a video is considered short (i.e. a loop).
+pub(crate) const AUTO_SHORT_DURATION: Duration = Duration::from_secs(10);
+/// The duration above which a video is considered long.
+pub(crate) const AUTO_LONG_DURATION: Duration = Duration::from_secs(30);
+/// The maximum duration of the FFprobe child process run by [`quick_validate`].
+pub(crate) const QUICK_VALIDATE_TIMEOUT: Duration = Duration::from_secs(2);
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+/// The properties of the source video's (first) video stream, as reported by FFmpeg.
+pub struct VideoInfo {
+    /// The width of the video's frames.
+    pub width: u32,
+    /// The height of the video's frames.
+    pub height: u32,
+    /// The video's frame rate, if reported.
+    pub fps: Option<f64>,
+    /// The video's duration, if reported.
+    pub duration: Option<Duration>,
+}
+
+#[derive(Debug, Clone)]
+/// An error returned by [`VideoInfo::probe`] (and [`crate::Settings::auto`]).
+pub enum ProbeError {
+    /// The location of the FFmpeg binary could not be resolved.
+    FfmpegLocation(FfmpegLocationError),
+    /// The FFmpeg child process could not be spawned, or its output could not be read.
+    ChildProcess(Arc<std::io::Error>),
+    /// FFmpeg did not report any video stream (e.g. the file is missing, or is
+    /// not a video).
+    NoVideoStream,
+    /// The file could not be read (see [`quick_validate`]).
+    Read(Arc<std::io::Error>),
+    /// The child process did not finish in time, so it was killed (see [`quick_validate`]
+    /// and [`VideoInfo::probe_with_timeout`]).
+    Timeout(Duration),
+}
+
+impl std::error::Error for ProbeError {}
+
+impl std::fmt::Display for ProbeError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::FfmpegLocation(e) => write!(f, "{}", e),
+            Self::ChildProcess(e) => write!(f, "FFmpeg child process error: {}", e),
+            Self::NoVideoStream => write!(f, "no video stream found"),
+            Self::Read(e) => write!(f, "unable to read file: {}", e),
+            Self::Timeout(d) => write!(f, "child process did not finish within {:?}", d),
+        }
+    }
+}
+
+impl VideoInfo {
+    /// Runs a short-lived FFmpeg child process that only reads the header of the video
+    /// provided by `input`, to find its properties. FFmpeg is expected to be found on
+    /// the system path if `ffmpeg_location` is `None`. The child process is killed if it
+    /// does not finish within [`crate::Settings::DEFAULT_AUXILIARY_TIMEOUT`] (see
+    /// [`VideoInfo::probe_with_timeout`]).
+    pub fn probe(
+        input: impl Into<InputSource>,
+        ffmpeg_location: Option<&FfmpegLocation>,
+    ) -> Result<Self, ProbeError> {
+        Self::probe_with_timeout(
+            input,
+            ffmpeg_location,
+            Some(crate::Settings::DEFAULT_AUXILIARY_TIMEOUT),
+        )
+    }
+
+    /// Same as [`VideoInfo::probe`], with a custom `timeout` for the FFmpeg child process
+    /// (`None` meaning no timeout), after which [`ProbeError::Timeout`] is returned.
+    pub fn probe_with_timeout(
+        input: impl Into<InputSource>,
+        ffmpeg_location: Option<&FfmpegLocation>,
+        timeout: Option<Duration>,
+    ) -> Result<Self, ProbeError> {
+        let binary_path = match ffmpeg_location {
+            Some(location) => location.resolve().map_err(ProbeError::FfmpegLocation)?,
+            None => "ffmpeg".into(),
+        };
+        let input = input.into();
+        let mut command = std::process::Command::new(binary_path);
+        command.args(crate::thumbnails::probe_args(
+            crate::input_format::input_args(None, &input),
+        ));
+        // NOTE: FFmpeg exits with an error when no output file is specified, so
+        // the exit code is ignored here: only the `stderr` output matters.
+        let output = run_auxiliary_reading(command, &input, timeout, || false)
+            .map_err(child_process_error)?;
+        let info = parse_video_info(&crate::stderr_lines::decode(&output.stderr));
+        log::info!(target: LOG_TARGET, "Video probed: {:?}", info);
+        info.ok_or(ProbeError::NoVideoStream)
+    }
+}
+
+/// Converts the error returned by [`run_auxiliary`] into a [`ProbeError::ChildProcess`]
+/// (or a [`ProbeError::Timeout`]).
+fn child_process_error(e: AuxiliaryError) -> ProbeError {
+    match e {
+        AuxiliaryError::Spawn(e) | AuxiliaryError::Io(e) => ProbeError::ChildProcess(Arc::new(e)),
+        AuxiliaryError::TimedOut(d) => ProbeError::Timeout(d),
+        e => ProbeError::ChildProcess(Arc::new(std::io::Error::other(e.to_string()))),
+    }
+}
+
+/// Quickly tells what the file at `path` contains (e.g. before starting a job for a file
+/// dropped onto an application's window), by looking at its first few bytes, which are
+/// compared against the signatures of common formats (i.e. MP4, MOV, MKV, WebM, AVI and
+/// MPEG for videos, GIF, PNG, JPEG and WebP for images, and MP3, WAV, FLAC and M4A for audio
+/// files), without spawning any child process.
+///
+/// If `ffprobe_location` (i.e. the location of the FFprobe binary, which is shipped along
+/// with FFmpeg) is provided, the result is then confirmed by FFprobe, which reads the file's
+/// header to list its streams (a file that FFprobe cannot read being [`InputKind::Unknown`],
+/// while a file that is not recognized, but that FFprobe can read, gets the kind of its
+/// streams). FFprobe is killed if it does not finish within 2 seconds (which can happen with
+/// pathological files), in which case [`ProbeError::Timeout`] is returned.
+pub fn quick_validate(
+    path: impl AsRef<Path>,
+    ffprobe_location: Option<&FfmpegLocation>,
+) -> Result<InputKind, ProbeError> {
+    quick_validate_with_timeout(path.as_ref(), ffprobe_location, QUICK_VALIDATE_TIMEOUT)
+}
+
+/// Same as [`quick_validate`], with a custom `timeout` for the FFprobe child process.
+fn quick_validate_with_timeout(
+    path: &Path,
+    ffprobe_location: Option<&FfmpegLocation>,
+    timeout: Duration,
+) -> Result<InputKind, ProbeError> {
+    use std::io::Read;
+
+    let mut header = Vec::with_capacity(SNIFF_LEN);
+    std::fs::File::open(path)
+        .and_then(|file| file.take(SNIFF_LEN as u64).read_to_end(&mut header))
+        .map_err(|e| ProbeError::Read(Arc::new(e)))?;
+    let sniffed = sniff(&header);
+    log::debug!(target: LOG_TARGET, "File {:?} sniffed as {:?}.", path, sniffed);
+    let Some(location) = ffprobe_location else {
+        return Ok(sniffed);
+    };
+
+    let binary_path = location.resolve().map_err(ProbeError::FfmpegLocation)?;
+    let mut command = std::process::Command::new(binary_path);
+    command.args(stream_types_args(path));
+    let output = match run_auxiliary(command, Some(timeout), || false) {
+        Ok(output) => output,
+        Err(AuxiliaryError::TimedOut(_)) => {
+            log::warn!(target: LOG_TARGET, "FFprobe did not finish within {:?} for {:?}.", timeout, path);
+            return Err(ProbeError::Timeout(timeout));
+        }
+        Err(e) => return Err(child_process_error(e)),
+    };
+    if !output.status.success() {
+        log::info!(
+            target: LOG_TARGET,
+            "FFprobe unable to read {:?}: {}",
+            path,
+            String::from_utf8_lossy(&output.stderr).trim()
+        );
+        return Ok(InputKind::Unknown);
+    }
+    let kind = confirm_kind(sniffed, &String::from_utf8_lossy(&output.stdout));
+    log::debug!(target: LOG_TARGET, "File {:?} confirmed as {:?}.", path, kind);
+    Ok(kind)
+}
+
+/// The FFprobe arguments used to list the types of the streams of the file at `path`
+/// (one per line, e.g. `video` then `audio`).
+fn stream_types_args(path: &Path) -> Vec<String> {
+    [
+        "-v",
+        "error",
+        "-show_entries",
+        "stream=codec_type",
+        "-of",
+        "csv=p=0",
+        "-i",
+    ]
+    .into_iter()
+    .map(String::from)
+    .chain([path.to_string_lossy().into_owned()])
+    .collect()
+}
+
+/// Combines the `sniffed` kind of a file with the types of its streams, as listed by FFprobe.
+fn confirm_kind(sniffed: InputKind, stream_types: &str) -> InputKind {
+    // NOTE: Depending on the version, FFprobe may add a trailing comma (for side data).
+    let mut types = stream_types
+        .lines()
+        .map(|line| line.trim().trim_end_matches(','));
+    if types.clone().any(|t| t == "video") {
+        // NOTE: FFprobe reports images (including animated GIFs) as video streams.
+        match sniffed {
+            InputKind::Image => InputKind::Image,
+            _ => InputKind::Video,
+        }
+    } else if types.any(|t| t == "audio") {
+        InputKind::Audio
+    } else {
+        InputKind::Unknown
+    }
+}
+
+/// Finds the properties of the first video stream described in FFmpeg's `stderr`
+/// output, e.g. `Stream #0:0: Video: h264 (High) (avc1 / 0x31637661), yuv420p, 640x360
+/// [SAR 1:1 DAR 16:9], 1538 kb/s, 24 fps, 24 tbr, 12288 tbn (default)`.
+pub(crate) fn parse_video_info(stderr: &str) -> Option<VideoInfo> {
+    let (_, stream) = stderr.lines().find_map(|line| {
+        line.trim_start()
+            .strip_prefix("Stream #")?
+            .split_once("Video:")
+    })?;
+    // NOTE: The first part is the codec, whose tag (e.g. `0x31637661`) looks like a size.
+    let mut parts = stream.split(',').skip(1).map(str::trim);
+    let (width, height) = parts.clone().find_map(|part| {
+        let (width, height) = part.split_whitespace().next()?.split_once('x')?;
+        if width.starts_with('0') {
+            return None;
+        }
+        Some((width.parse().ok()?, height.parse().ok()?))
+    })?;
+    let fps = parts.find_map(|part| part.strip_suffix(" fps")?.parse().ok());
+    Some(VideoInfo {
+        width,
+        height,
+        fps,
+        duration: try_extract_duration(stderr, None),
+    })
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+/// The settings picked by [`auto_params`].
+pub(crate) struct AutoParams {
+    pub(crate) width: u16,
+    pub(crate) fps: u16,
+    pub(crate) max_colors: Option<u16>,
+    pub(crate) preserve_last_frame: bool,
+}
+
+/// The heuristics used by [`crate::Settings::auto`] (see its documentation).
+pub(crate) fn auto_params(info: &VideoInfo) -> AutoParams {
+    let long = info.duration.is_some_and(|d| d > AUTO_LONG_DURATION);
+    let short = info.duration.is_some_and(|d| d <= AUTO_SHORT_DURATION);
+    let max_fps = if long {
+        AUTO_MAX_FPS_LONG
+    } else {
+        AUTO_MAX_FPS
+    };
+    let fps = match info.fps {
+        Some(fps) if fps.is_finite() && fps >= 1.0 => (fps.round() as u16).min(max_fps),
+        _ => crate::Settings::STANDARD_FPS.min(max_fps),
+    };
+    let max_colors = match info.duration {
+        Some(_) if long => Some(64),
+        Some(_) if !short => Some(128),
+        _ => None,
+    };
+    AutoParams {
+        // NOTE: `AUTO_MAX_WIDTH` fits in a `u16`.
+        width: info.width.clamp(1, AUTO_MAX_WIDTH) as u16,
+        fps,
+        max_colors,
+        preserve_last_frame: short,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_utils::SAMPLE_STDERR;
+
+    fn info(width: u32, fps: Option<f64>, duration: Option<u64>) -> VideoInfo {
+        VideoInfo {
+            width,
+            height: width * 9 / 16,
+            fps,
+            duration: duration.map(Duration::from_secs),
+        }
+    }
+
+    #[test]
+    fn test_parse_video_info() {
+        assert_eq!(
+            parse_video_info(SAMPLE_STDERR),
+            Some(VideoInfo {
+                width: 640,
+                height: 360,
+                fps: Some(24.0),
+                duration: Some(Duration::from_secs(5)),
+            })
+        );
+        let stderr = "Input #0, matroska,webm, from 'in.mkv':
+  Duration: N/A, start: 0.000000, bitrate: N/A
+  Stream #0:0: Audio: opus, 48000 Hz, stereo, fltp (default)
+  Stream #0:1(eng): Video: vp9 (Profile 0), yuv420p(tv, bt709), 1920x1080, SAR 1:1 DAR 16:9, 29.97 fps, 29.97 tbr, 1k tbn (default)";
+        assert_eq!(
+            parse_video_info(stderr),
+            Some(VideoInfo {
+                width: 1920,
+                height: 1080,
+                fps: Some(29.97),
+                duration: None,
+            })
+        );
+        let stderr = "  Stream #0:0: Video: png, rgb24(pc), 320x240, 25 tbr, 25 tbn";
+        assert_eq!(parse_video_info(stderr).map(|i| i.fps), Some(None));
+        assert_eq!(
+            parse_video_info("  Stream #0:0: Audio: mp3, 44100 Hz"),
+            None
+        );
+        assert_eq!(parse_video_info("in.txt: Invalid data found"), None);
+    }
+
+    #[test]
+    fn test_auto_params() {
+        let params = |width, fps, duration| auto_params(&info(width, fps, duration));
+        // Width: the source's, capped at 480.
+        assert_eq!(params(320, Some(24.0), Some(5)).width, 320);
+        assert_eq!(params(480, Some(24.0), Some(5)).width, 480);
+        assert_eq!(params(1920, Some(24.0), Some(5)).width, 480);
+        assert_eq!(params(0, Some(24.0), Some(5)).width, 1);
+        // Frame rate: the source's (rounded), capped at 15 (or 10 for long videos).
+        assert_eq!(params(640, Some(8.0), Some(5)).fps, 8);
+        assert_eq!(params(640, Some(12.4), Some(5)).fps, 12);
+        assert_eq!(params(640, Some(29.97), Some(5)).fps, 15);
+        assert_eq!(params(640, Some(29.97), Some(30)).fps, 15);
+        assert_eq!(params(640, Some(29.97), Some(31)).fps, 10);
+        assert_eq!(params(640, Some(8.0), Some(31)).fps, 8);
+        // ... or the standard frame rate, when not reported (or invalid).
+        assert_eq!(params(640, None, Some(5)).fps, 10);
+        assert_eq!(params(640, Some(0.5), Some(5)).fps, 10);
+        assert_eq!(params(640, Some(f64::NAN), Some(5)).fps, 10);
+        // Palette and last frame: depending on the duration.
+        for (duration, max_colors, preserve_last_frame) in [
+            (Some(0), None, true),
+            (Some(10), None, true),
+            (Some(11), Some(128), false),
+            (Some(30), Some(128), false),
+            (Some(31), Some(64), false),
+            (None, None, false),
+        ] {
+            let params = params(640, Some(24.0), duration);
+            assert_eq!(params.max_colors, max_colors, "{:?}", duration);
+            assert_eq!(
+                params.preserve_last_frame, preserve_last_frame,
+                "{:?}",
+                duration
+            );
+        }
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_probe() {
+        use crate::test_utils::{fake_ffmpeg, fake_ffmpeg_with_script};
+
+        let path = fake_ffmpeg(SAMPLE_STDERR, b"", 1);
+        let location = FfmpegLocation::Path(path.to_string_lossy().into());
+        let info = VideoInfo::probe("video.mp4", Some(&location)).unwrap();
+        assert_eq!((info.width, info.height, info.fps), (640, 360, Some(24.0)));
+
+        let path = fake_ffmpeg("video.mp4: No such file or directory", b"", 1);
+        let location = FfmpegLocation::Path(path.to_string_lossy().into());
+        assert!(matches!(
+            VideoInfo::probe("video.mp4", Some(&location)),
+            Err(ProbeError::NoVideoStream)
+        ));
+        let location = FfmpegLocation::Path("/nonexistent/ffmpeg".into());
+        assert!(matches!(
+            VideoInfo::probe("video.mp4", Some(&location)),
+            Err(ProbeError::ChildProcess(_))
+        ));
+
+        // A malformed file, which FFmpeg never finishes reading.
+        let path = fake_ffmpeg_with_script("", b"", "sleep 10");
+        let location = FfmpegLocation::Path(path.to_string_lossy().into());
+        let timeout = Duration::from_millis(200);
+        assert!(matches!(
+            VideoInfo::probe_with_timeout("video.mp4", Some(&location), Some(timeout)),
+            Err(ProbeError::Timeout(t)) if t == timeout
+        ));
+    }
+
+    #[test]
+    fn test_confirm_kind() {
+        assert_eq!(
+            confirm_kind(InputKind::Video, "video\naudio\n"),
+            InputKind::Video
+        );
+        assert_eq!(
+            confirm_kind(InputKind::Unknown, "audio\nvideo,\n"),
+            InputKind::Video
+        );
+        assert_eq!(confirm_kind(InputKind::Image, "video\n"), InputKind::Image);
+        assert_eq!(confirm_kind(InputKind::Video, "audio\n"), InputKind::Audio);
+        assert_eq!(confirm_kind(InputKind::Video, "data\n"), InputKind::Unknown);
+        assert_eq!(confirm_kind(InputKind::Video, ""), InputKind::Unknown);
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_quick_validate() {
+        use crate::test_utils::{fake_ffmpeg, sample_gif, temp_dir, write_script};
+
+        let dir = temp_dir();
+        let mp4 = dir.join("video.mp4");
+        std::fs::write(
+            &mp4,
+            b"\x00\x00\x00\x20ftypisom\x00\x00\x02\x00isomiso2avc1mp41",
+        )
+        .unwrap();
+        let gif = dir.join("image.gif");
+        std::fs::write(&gif, sample_gif(2, Some(0))).unwrap();
+        let txt = dir.join("notes.txt");
+        std::fs::write(&txt, "Hello, world!\n").unwrap();
+
+        // Sniffing only.
+        assert_eq!(quick_validate(&mp4, None).unwrap(), InputKind::Video);
+        assert_eq!(quick_validate(&gif, None).unwrap(), InputKind::Image);
+        assert_eq!(quick_validate(&txt, None).unwrap(), InputKind::Unknown);
+        assert!(matches!(
+            quick_validate(dir.join("missing.mp4"), None),
+            Err(ProbeError::Read(_))
+        ));
+
+        // Confirmed by FFprobe.
+        let location =
+            |path: std::path::PathBuf| FfmpegLocation::Path(path.to_string_lossy().into());
+        let ffprobe = location(fake_ffmpeg("", b"video\naudio\n", 0));
+        assert_eq!(
+            quick_validate(&mp4, Some(&ffprobe)).unwrap(),
+            InputKind::Video
+        );
+        assert_eq!(
+            quick_validate(&gif, Some(&ffprobe)).unwrap(),
+            InputKind::Image
+        );
+        assert_eq!(
+            quick_validate(&txt, Some(&ffprobe)).unwrap(),
+            InputKind::Video
+        );
+        let ffprobe = location(fake_ffmpeg("", b"audio\n", 0));
+        assert_eq!(
+            quick_validate(&mp4, Some(&ffprobe)).unwrap(),
+            InputKind::Audio
+        );
+        let ffprobe = location(fake_ffmpeg(
+            "video.mp4: Invalid data found when processing input",
+            b"",
+            1,
+        ));
+        assert_eq!(
+            quick_validate(&mp4, Some(&ffprobe)).unwrap(),
+            InputKind::Unknown
+        );
+        let ffprobe = location("/nonexistent/ffprobe".into());
+        assert!(matches!(
+            quick_validate(&mp4, Some(&ffprobe)),
+            Err(ProbeError::ChildProcess(_))
+        ));
+
+        // A pathological file, which FFprobe never finishes reading.
+        let ffprobe = location(write_script(&temp_dir(), "sleep 10"));
+        let started = std::time::Instant::now();
+        let timeout = Duration::from_millis(200);
+        assert!(matches!(
+            quick_validate_with_timeout(&mp4, Some(&ffprobe), timeout),
+            Err(ProbeError::Timeout(t)) if t == timeout
+        ));
+        assert!(
+            started.elapsed() < Duration::from_secs(2),
+            "{:?}",
+            started.elapsed()
+        );
+    }
+}
@@ -0,0 +1,363 @@
+//! The model behind [`crate::Settings::smooth_progress`], which estimates the job's
+//! progress between two of FFmpeg's (sparse) stats updates.
+//!
+//! The estimate extrapolates the last real measurement using the progress rate
+//! observed between the last two measurements (i.e. the last observed encode speed),
+//! and is bounded so that it stays below the next expected real measurement (see
+//! [`MAX_GAP_FRACTION`]) and below 1.0. It is also monotonic, and no estimate is
+//! produced once the job has finished.
+//!
+//! It also holds the remapping behind [`crate::Settings::progress_curve`]. With the single
+//! pass filter graph (whose output is split between `palettegen` and `paletteuse`), the job
+//! goes through two regimes: FFmpeg first analyzes the frames to generate the palette,
+//! during which nothing gets written to `stdout`, and then applies the palette, writing the
+//! GIF as it goes. FFmpeg's `time` values do not advance at the same pace (relative to the
+//! wall time) in both regimes, so the raw ratio is remapped (see [`remap`]) such that each
+//! regime covers its own share of the progress, the switch between them being detected
+//! when the first bytes of output are read.
+
+use std::time::Duration;
+
+/// The largest fraction of the expected increment (i.e. the increment observed
+/// between the last two real measurements) that the estimate may cover, which
+/// keeps it below the next real measurement unless the encode speed drops by
+/// more than that.
+pub(crate) const MAX_GAP_FRACTION: f64 = 0.9;
+
+/// The largest value that can be estimated, since only real measurements
+/// may report that the job is complete.
+pub(crate) const MAX_ESTIMATE: f64 = 0.99;
+
+/// The smallest increment for which a new estimate gets produced.
+pub(crate) const MIN_STEP: f64 = 0.001;
+
+#[derive(Debug, Default)]
+/// Interpolates the progress between real measurements (see the module's documentation).
+pub(crate) struct ProgressInterpolator {
+    /// The time and value of the last real measurement.
+    last: Option<(Duration, f64)>,
+    /// The progress rate (per second) and the interval between the last two real measurements.
+    rate: Option<(f64, Duration)>,
+    /// The largest value produced or observed so far.
+    floor: f64,
+    /// Whether the job has finished.
+    finished: bool,
+}
+
+impl ProgressInterpolator {
+    /// Records the real `progress` measured at time `at` (relative to any fixed origin).
+    /// The first call, which only serves as a reference, would typically record a
+    /// progress of 0.0 at the time the processing starts.
+    pub(crate) fn observe(&mut self, at: Duration, progress: f64) {
+        if let Some((last_at, last_progress)) = self.last {
+            if at > last_at {
+                let interval = at - last_at;
+                let rate = (progress - last_progress) / interval.as_secs_f64();
+                self.rate = Some((rate, interval));
+            }
+        }
+        self.last = Some((at, progress));
+        self.floor = self.floor.max(progress);
+    }
+
+    /// Signals that the job has finished, after which no estimate gets produced.
+    pub(crate) fn finish(&mut self) {
+        self.finished = true;
+    }
+
+    /// Whether [`ProgressInterpolator::finish`] has been called.
+    pub(crate) fn finished(&self) -> bool {
+        self.finished
+    }
+
+    /// Estimates the progress at time `at`, returning `None` if no (new) estimate
+    /// can be made, i.e. if the job has finished, if fewer than two real measurements
+    /// were observed, if the progress is not increasing, or if the estimate has not
+    /// advanced by at least [`MIN_STEP`] since the last value produced or observed.
+    pub(crate) fn estimate(&mut self, at: Duration) -> Option<f64> {
+        if self.finished {
+            return None;
+        }
+        let (last_at, last_progress) = self.last?;
+        let (rate, interval) = self.rate?;
+        if rate <= 0.0 || at <= last_at {
+            return None;
+        }
+        let bound =
+            (last_progress + MAX_GAP_FRACTION * rate * interval.as_secs_f64()).min(MAX_ESTIMATE);
+        let estimate = (last_progress + rate * (at - last_at).as_secs_f64()).min(bound);
+        if estimate < self.floor + MIN_STEP {
+            return None;
+        }
+        self.floor = estimate;
+        Some(estimate)
+    }
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+/// How the progress values (see [`crate::Message::Progress`]) are derived from the ratio
+/// of the time reached by FFmpeg to the video's duration (see [`crate::Settings::progress_curve`]).
+pub enum ProgressCurve {
+    /// The ratio is used as is.
+    #[default]
+    Linear,
+    /// The ratio is remapped so that the palette analysis regime (until the first bytes
+    /// of output are written) covers the first `analysis_share` of the progress, and the
+    /// palette application regime covers the rest.
+    TwoPhase {
+        /// The share (strictly between 0.0 and 1.0) of the progress covered by the palette
+        /// analysis regime.
+        analysis_share: f64,
+    },
+}
+
+impl ProgressCurve {
+    /// The curve calibrated against the bundled clip (`assets/big-buck-bunny-clip.mp4`,
+    /// converted at 10 fps, 200 pixels wide), for which the palette analysis regime takes
+    /// about half of the wall time (the raw ratio then sitting at about 95% for the other
+    /// half of the job). The share is measured by the `test_progress_curve_calibration`
+    /// test (which requires FFmpeg), which fails if it drifts too far from this one.
+    ///
+    /// NOTE: The share depends on the machine, on FFmpeg's version and on the settings (e.g.
+    /// larger GIFs spend relatively more time applying the palette), which is why it can
+    /// be provided using [`ProgressCurve::TwoPhase`] instead.
+    pub const CALIBRATED: Self = Self::TwoPhase {
+        analysis_share: 0.5,
+    };
+
+    /// Whether the curve's parameters are valid.
+    pub(crate) fn is_valid(&self) -> bool {
+        match *self {
+            Self::Linear => true,
+            Self::TwoPhase { analysis_share } => analysis_share > 0.0 && analysis_share < 1.0,
+        }
+    }
+}
+
+/// Remaps the `raw` progress ratio to the `analysis_share` of the progress covered by the
+/// palette analysis regime, given the raw ratio at which the first bytes of output were
+/// written (`None` while in the palette analysis regime). The result is continuous and
+/// non-decreasing, provided that `raw` itself is non-decreasing, and is 1.0 once `raw` is.
+pub(crate) fn remap(raw: f64, output_started_at: Option<f64>, analysis_share: f64) -> f64 {
+    let raw = raw.clamp(0.0, 1.0);
+    let Some(switch) = output_started_at else {
+        return raw * analysis_share;
+    };
+    let switch = switch.clamp(0.0, 1.0);
+    let from = switch * analysis_share;
+    // NOTE: Whatever is left of the ratio after the switch is stretched over the
+    // remaining share of the progress.
+    let fraction = match 1.0 - switch {
+        rest if rest > 0.0 => ((raw - switch) / rest).max(0.0),
+        _ => 1.0,
+    };
+    from + (1.0 - from) * fraction
+}
+
+#[derive(Debug)]
+/// Applies a [`ProgressCurve`] to the successive raw ratios of a job.
+pub(crate) struct ProgressRemapper {
+    curve: ProgressCurve,
+    /// The raw ratio at which the first bytes of output were written, if they were.
+    output_started_at: Option<f64>,
+    /// The last raw ratio.
+    last_raw: f64,
+}
+
+impl ProgressRemapper {
+    pub(crate) fn new(curve: ProgressCurve) -> Self {
+        Self {
+            curve,
+            output_started_at: None,
+            last_raw: 0.0,
+        }
+    }
+
+    /// The progress value for the `raw` ratio, given whether any output was written so far.
+    pub(crate) fn remap(&mut self, raw: f64, output_started: bool) -> f64 {
+        let ProgressCurve::TwoPhase { analysis_share } = self.curve else {
+            return raw;
+        };
+        if output_started && self.output_started_at.is_none() {
+            // NOTE: The output started somewhere after the last ratio was observed.
+            self.output_started_at = Some(self.last_raw);
+        }
+        self.last_raw = raw;
+        remap(raw, self.output_started_at, analysis_share)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn ms(n: u64) -> Duration {
+        Duration::from_millis(n)
+    }
+
+    #[test]
+    fn test_no_estimate_without_rate() {
+        let mut interpolator = ProgressInterpolator::default();
+        assert_eq!(interpolator.estimate(ms(100)), None);
+        interpolator.observe(ms(0), 0.0);
+        assert_eq!(interpolator.estimate(ms(100)), None);
+    }
+
+    #[test]
+    fn test_linear_interpolation() {
+        let mut interpolator = ProgressInterpolator::default();
+        interpolator.observe(ms(0), 0.0);
+        interpolator.observe(ms(500), 0.2);
+        let estimate = interpolator.estimate(ms(625)).unwrap();
+        assert!((estimate - 0.25).abs() < 1e-9);
+        // Not advanced enough since the last estimate.
+        assert_eq!(interpolator.estimate(ms(625)), None);
+        // Bounded by the expected next measurement.
+        let estimate = interpolator.estimate(ms(5000)).unwrap();
+        assert!((estimate - (0.2 + MAX_GAP_FRACTION * 0.2)).abs() < 1e-9);
+        assert_eq!(interpolator.estimate(ms(6000)), None);
+    }
+
+    #[test]
+    fn test_bounded_below_one() {
+        let mut interpolator = ProgressInterpolator::default();
+        interpolator.observe(ms(0), 0.0);
+        interpolator.observe(ms(500), 0.9);
+        assert_eq!(interpolator.estimate(ms(2000)), Some(MAX_ESTIMATE));
+        interpolator.observe(ms(600), 1.0);
+        assert_eq!(interpolator.estimate(ms(2000)), None);
+    }
+
+    #[test]
+    fn test_stalled_progress() {
+        let mut interpolator = ProgressInterpolator::default();
+        interpolator.observe(ms(0), 0.0);
+        interpolator.observe(ms(500), 0.3);
+        interpolator.observe(ms(1000), 0.3);
+        assert_eq!(interpolator.estimate(ms(1200)), None);
+    }
+
+    #[test]
+    fn test_finished() {
+        let mut interpolator = ProgressInterpolator::default();
+        interpolator.observe(ms(0), 0.0);
+        interpolator.observe(ms(500), 0.2);
+        interpolator.finish();
+        assert_eq!(interpolator.estimate(ms(600)), None);
+    }
+
+    /// Replays recorded `(time in ms, progress)` measurements, querying an estimate
+    /// every 50 milliseconds, and makes sure that the estimates always lie between
+    /// the surrounding real measurements (at constant or increasing encode speed).
+    fn replay(recorded: &[(u64, f64)]) -> usize {
+        let mut interpolator = ProgressInterpolator::default();
+        let mut estimates = 0;
+        let mut previous = 0.0;
+        for window in recorded.windows(2) {
+            let ((at, progress), (next_at, next_progress)) = (window[0], window[1]);
+            interpolator.observe(ms(at), progress);
+            for t in (at + 50..next_at).step_by(50) {
+                if let Some(estimate) = interpolator.estimate(ms(t)) {
+                    assert!(estimate >= previous, "{} < {}", estimate, previous);
+                    assert!(estimate >= progress && estimate <= next_progress);
+                    assert!(estimate < 1.0);
+                    previous = estimate;
+                    estimates += 1;
+                }
+            }
+            previous = previous.max(progress);
+        }
+        estimates
+    }
+
+    #[test]
+    fn test_remap() {
+        // The palette analysis regime.
+        assert_eq!(remap(0.0, None, 0.5), 0.0);
+        assert_eq!(remap(0.5, None, 0.5), 0.25);
+        assert_eq!(remap(0.96, None, 0.5), 0.48);
+        // The palette application regime, which started at 0.96 (i.e. at 0.48).
+        assert_eq!(remap(0.96, Some(0.96), 0.5), 0.48);
+        assert!((remap(0.98, Some(0.96), 0.5) - 0.74).abs() < 1e-9);
+        assert_eq!(remap(1.0, Some(0.96), 0.5), 1.0);
+        // The palette application regime, which started right away.
+        assert_eq!(remap(0.0, Some(0.0), 0.25), 0.0);
+        assert_eq!(remap(0.5, Some(0.0), 0.25), 0.5);
+        // The output started once FFmpeg's `time` had reached the duration.
+        assert_eq!(remap(1.0, None, 0.5), 0.5);
+        assert_eq!(remap(1.0, Some(1.0), 0.5), 1.0);
+        // Out of range ratios.
+        assert_eq!(remap(1.5, Some(0.5), 0.5), 1.0);
+        assert_eq!(remap(-0.5, None, 0.5), 0.0);
+    }
+
+    #[test]
+    fn test_remap_monotonic() {
+        for switch in [0.0, 0.3, 0.95, 0.999, 1.0] {
+            for share in [0.1, 0.5, 0.9] {
+                let mut previous = 0.0;
+                for i in 0..=1000 {
+                    let raw = i as f64 / 1000.0;
+                    let started = (raw >= switch).then_some(switch);
+                    let progress = remap(raw, started, share);
+                    assert!(progress >= previous, "{} {} {}", switch, share, raw);
+                    assert!((0.0..=1.0).contains(&progress));
+                    previous = progress;
+                }
+                assert_eq!(previous, 1.0);
+            }
+        }
+    }
+
+    #[test]
+    fn test_progress_remapper() {
+        let mut remapper = ProgressRemapper::new(ProgressCurve::Linear);
+        assert_eq!(remapper.remap(0.5, false), 0.5);
+        assert_eq!(remapper.remap(0.9, true), 0.9);
+
+        // The stats line reaches 96% of the duration while the palette is generated,
+        // and then stalls while it gets applied.
+        let mut remapper = ProgressRemapper::new(ProgressCurve::CALIBRATED);
+        let progress: Vec<f64> = [
+            (0.4, false),
+            (0.96, false),
+            (0.97, true),
+            (0.98, true),
+            (0.99, true),
+            (1.0, true),
+        ]
+        .into_iter()
+        .map(|(raw, started)| remapper.remap(raw, started))
+        .collect();
+        let expected = [0.2, 0.48, 0.61, 0.74, 0.87, 1.0];
+        for (progress, expected) in progress.iter().zip(expected) {
+            assert!((progress - expected).abs() < 1e-9, "{:?}", progress);
+        }
+
+        assert!(ProgressCurve::CALIBRATED.is_valid());
+        for analysis_share in [0.0, 1.0, f64::NAN] {
+            assert!(!ProgressCurve::TwoPhase { analysis_share }.is_valid());
+        }
+    }
+
+    #[test]
+    fn test_replay_recorded_sequences() {
+        // A short clip, with stats updates every 500 ms.
+        assert!(replay(&[(0, 0.0), (500, 0.4), (1000, 0.8), (1250, 1.0)]) > 0);
+        // A longer clip, with an encode speed that increases over time.
+        assert!(
+            replay(&[
+                (0, 0.0),
+                (480, 0.08),
+                (990, 0.18),
+                (1500, 0.3),
+                (2010, 0.45),
+                (2500, 0.61),
+                (3000, 0.8),
+                (3420, 1.0)
+            ]) > 10
+        );
+        // Irregular updates (e.g. due to `-stats_period`).
+        assert!(replay(&[(0, 0.0), (200, 0.1), (1200, 0.6), (1400, 0.7), (2400, 1.0)]) > 0);
+    }
+}
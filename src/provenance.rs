@@ -0,0 +1,120 @@
+//! The record of the exact lines of FFmpeg's `stderr` output from which the duration and
+//! the progress were parsed (see [`crate::Settings::parse_provenance`]), so that a wrong
+//! value reported in the field can be traced back to the line that produced it.
+//!
+//! Each record is logged (at the debug level, using the [`PROVENANCE_LOG_TARGET`] target and the
+//! `provenance_event` and `provenance_line` keys), and only the last few are kept for
+//! [`crate::Summary::provenance`], so that the memory used does not grow with the length
+//! of the video. Only the values parsed from a line are recorded, i.e. not the duration
+//! probed before the job (see [`crate::DurationSource::Probe`]).
+
+use std::collections::VecDeque;
+use std::time::Duration;
+
+use crate::job_tag::JobTag;
+use crate::DurationSource;
+
+/// The target of the log lines describing the parsed events (see the module's documentation).
+pub const PROVENANCE_LOG_TARGET: &str = "ffmpeg_gif_maker::converter::provenance";
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
+/// An event parsed from a line of FFmpeg's `stderr` output.
+pub enum ParsedEvent {
+    /// The video's duration was found (or replaced, see [`crate::DurationSource`]), as sent
+    /// using [`crate::Message::VideoDuration`].
+    VideoDuration {
+        /// The duration sent using [`crate::Message::VideoDuration`].
+        duration: Duration,
+        /// Where the duration was found.
+        source: DurationSource,
+    },
+    /// The progress was computed, as sent using [`crate::Message::Progress`].
+    Progress(f64),
+}
+
+#[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
+/// A [`ParsedEvent`], along with the line of FFmpeg's `stderr` output it was derived from.
+pub struct ParseProvenance {
+    /// The parsed event.
+    pub event: ParsedEvent,
+    /// The exact line (without its line terminator).
+    pub line: String,
+}
+
+/// Keeps the last `capacity` records (see the module's documentation).
+#[derive(Debug)]
+pub(crate) struct ProvenanceLog {
+    capacity: usize,
+    records: VecDeque<ParseProvenance>,
+}
+
+impl ProvenanceLog {
+    pub(crate) fn new(capacity: usize) -> Self {
+        Self {
+            capacity,
+            records: VecDeque::with_capacity(capacity),
+        }
+    }
+
+    /// Logs the `event` parsed from `line`, and keeps it (dropping the oldest record if needed).
+    pub(crate) fn record(&mut self, tag: &JobTag, event: ParsedEvent, line: &str) {
+        log::debug!(
+            target: PROVENANCE_LOG_TARGET,
+            job_id:% = tag.id(),
+            job = tag.number(),
+            provenance_event:? = event,
+            provenance_line = line;
+            "{} {:?} parsed from line: {:?}", tag, event, line
+        );
+        if self.capacity == 0 {
+            return;
+        }
+        if self.records.len() == self.capacity {
+            self.records.pop_front();
+        }
+        self.records.push_back(ParseProvenance {
+            event,
+            line: line.to_string(),
+        });
+    }
+
+    /// The records kept, from the oldest to the most recent.
+    pub(crate) fn records(&self) -> Vec<ParseProvenance> {
+        self.records.iter().cloned().collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::JobId;
+
+    #[test]
+    fn test_bounded_retention() {
+        let tag = JobTag::new(JobId::U64(1), 1, None);
+        let mut log = ProvenanceLog::new(2);
+        for (i, line) in ["a", "b", "c"].into_iter().enumerate() {
+            log.record(&tag, ParsedEvent::Progress(i as f64 / 2.0), line);
+        }
+        assert_eq!(
+            log.records(),
+            [
+                ParseProvenance {
+                    event: ParsedEvent::Progress(0.5),
+                    line: "b".into(),
+                },
+                ParseProvenance {
+                    event: ParsedEvent::Progress(1.0),
+                    line: "c".into(),
+                },
+            ]
+        );
+
+        // Only logged.
+        let mut log = ProvenanceLog::new(0);
+        log.record(&tag, ParsedEvent::Progress(0.0), "a");
+        assert!(log.records().is_empty());
+    }
+}
@@ -0,0 +1,189 @@
+//! A common interface to the receiving ends of the [`Message`] channels, whichever kind of
+//! channel is used (see the `tokio` and `async-channel` feature flags), so that code built on
+//! top of the library (e.g. another library that passes its feature flags through) can write
+//! its receive loop once, against [`MessageReceiverExt`].
+//!
+//! NOTE: The tests below are compiled (and run) once per kind of channel, so the whole test
+//! suite must be run with each of them (see `README.md`).
+
+use crate::Message;
+
+/// Receives the [`Message`]'s sent by the [`crate::Converter`], whichever kind of channel
+/// is used (i.e. implemented for [`crate::MessageReceiver`], [`crate::BoundedMessageReceiver`]
+/// and [`crate::ProgressReceiver`]).
+///
+/// ```no_run
+/// use ffmpeg_gif_maker::{Converter, Message, MessageReceiverExt, Settings};
+///
+/// let (converter, _tx, mut rx) = Converter::new_with_channels();
+/// let settings = Settings::with_standard_fps("video.mp4".into(), 200);
+/// std::thread::spawn(move || converter.convert(settings));
+/// while let Some(message) = rx.next_blocking() {
+///     if let Message::Done = message {
+///         break;
+///     }
+/// }
+/// ```
+pub trait MessageReceiverExt {
+    /// Blocks until the next message is received, or returns `None` once the channel is closed.
+    ///
+    /// NOTE: With the `tokio` feature flag, this must not be called from an asynchronous
+    /// context (see [`tokio::sync::mpsc::UnboundedReceiver::blocking_recv`]), in which
+    /// case [`MessageReceiverExt::next`] should be used instead.
+    fn next_blocking(&mut self) -> Option<Message>;
+
+    #[cfg(any(feature = "tokio", feature = "async-channel"))]
+    /// Waits for the next message, or returns `None` once the channel is closed.
+    fn next(&mut self) -> impl std::future::Future<Output = Option<Message>> + Send + '_;
+}
+
+#[cfg(not(any(feature = "tokio", feature = "async-channel")))]
+impl MessageReceiverExt for std::sync::mpsc::Receiver<Message> {
+    fn next_blocking(&mut self) -> Option<Message> {
+        self.recv().ok()
+    }
+}
+
+#[cfg(feature = "tokio")]
+impl MessageReceiverExt for tokio::sync::mpsc::UnboundedReceiver<Message> {
+    fn next_blocking(&mut self) -> Option<Message> {
+        self.blocking_recv()
+    }
+
+    fn next(&mut self) -> impl std::future::Future<Output = Option<Message>> + Send + '_ {
+        self.recv()
+    }
+}
+
+#[cfg(feature = "tokio")]
+impl MessageReceiverExt for tokio::sync::mpsc::Receiver<Message> {
+    fn next_blocking(&mut self) -> Option<Message> {
+        self.blocking_recv()
+    }
+
+    fn next(&mut self) -> impl std::future::Future<Output = Option<Message>> + Send + '_ {
+        self.recv()
+    }
+}
+
+#[cfg(feature = "async-channel")]
+impl MessageReceiverExt for async_channel::Receiver<Message> {
+    fn next_blocking(&mut self) -> Option<Message> {
+        self.recv_blocking().ok()
+    }
+
+    async fn next(&mut self) -> Option<Message> {
+        self.recv().await.ok()
+    }
+}
+
+impl MessageReceiverExt for crate::BoundedMessageReceiver {
+    fn next_blocking(&mut self) -> Option<Message> {
+        #[cfg(not(any(feature = "tokio", feature = "async-channel")))]
+        return self.recv().ok();
+        #[cfg(feature = "tokio")]
+        return self.blocking_recv();
+        #[cfg(feature = "async-channel")]
+        return self.recv_blocking().ok();
+    }
+
+    #[cfg(any(feature = "tokio", feature = "async-channel"))]
+    async fn next(&mut self) -> Option<Message> {
+        #[cfg(feature = "tokio")]
+        return self.recv().await;
+        #[cfg(feature = "async-channel")]
+        return self.recv().await.ok();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::Converter;
+
+    /// Receives the messages until the channel is closed, written once for all the kinds
+    /// of channels.
+    fn drain(rx: &mut impl MessageReceiverExt) -> Vec<Message> {
+        std::iter::from_fn(|| rx.next_blocking()).collect()
+    }
+
+    /// Sends a few messages down the channel from another thread, then closes it.
+    fn send_all(tx: crate::MessageSender) {
+        std::thread::spawn(move || {
+            for message in [Message::OutputBytes(42), Message::Done] {
+                #[cfg(not(feature = "async-channel"))]
+                tx.send(message).unwrap();
+                #[cfg(feature = "async-channel")]
+                tx.send_blocking(message).unwrap();
+            }
+        })
+        .join()
+        .unwrap();
+    }
+
+    fn assert_drained(messages: &[Message]) {
+        assert!(matches!(
+            messages,
+            [Message::OutputBytes(42), Message::Done]
+        ));
+    }
+
+    #[cfg(not(any(feature = "tokio", feature = "async-channel")))]
+    #[test]
+    fn test_next_blocking_std() {
+        let (tx, mut rx) = std::sync::mpsc::channel();
+        send_all(tx);
+        assert_drained(&drain(&mut rx));
+    }
+
+    #[cfg(feature = "tokio")]
+    #[test]
+    fn test_next_blocking_tokio() {
+        let (tx, mut rx) = tokio::sync::mpsc::unbounded_channel();
+        send_all(tx);
+        assert_drained(&drain(&mut rx));
+
+        let (tx, mut rx) = tokio::sync::mpsc::channel(4);
+        tx.try_send(Message::Done).unwrap();
+        drop(tx);
+        assert!(matches!(drain(&mut rx)[..], [Message::Done]));
+    }
+
+    #[cfg(feature = "tokio")]
+    #[tokio::test]
+    async fn test_next_tokio() {
+        let (tx, mut rx) = tokio::sync::mpsc::unbounded_channel();
+        send_all(tx);
+        assert!(matches!(rx.next().await, Some(Message::OutputBytes(42))));
+        assert!(matches!(rx.next().await, Some(Message::Done)));
+        assert!(rx.next().await.is_none());
+    }
+
+    #[cfg(feature = "async-channel")]
+    #[test]
+    fn test_next_blocking_async_channel() {
+        let (tx, mut rx) = async_channel::unbounded();
+        send_all(tx);
+        assert_drained(&drain(&mut rx));
+    }
+
+    #[cfg(feature = "async-channel")]
+    #[test]
+    fn test_next_async_channel() {
+        let (tx, mut rx) = async_channel::unbounded();
+        send_all(tx);
+        smol::block_on(async {
+            assert!(matches!(rx.next().await, Some(Message::OutputBytes(42))));
+            assert!(matches!(rx.next().await, Some(Message::Done)));
+            assert!(rx.next().await.is_none());
+        });
+    }
+
+    /// The converter's own channels, whichever kind they are.
+    #[test]
+    fn test_converter_channels() {
+        let (converter, _tx, mut rx) = Converter::new_with_channels();
+        drop(converter);
+        assert!(rx.next_blocking().is_none());
+    }
+}
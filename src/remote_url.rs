@@ -0,0 +1,230 @@
+//! The remote inputs (see [`crate::InputSource::Url`]), which FFmpeg reads directly over the
+//! network (e.g. `https://example.com/video.mp4`), without the video being downloaded first.
+//!
+//! Since the URL may come from the application's users, FFmpeg must not be tricked into
+//! reading anything else (e.g. a local file, or a service only reachable from the server):
+//! the URL's scheme must be one of the allowed ones (`http` and `https`, by default), and
+//! FFmpeg is given the same list (using its `-protocol_whitelist` input option), so that a
+//! playlist (e.g. HLS) cannot reference other kinds of resources either. The protocols that
+//! read local resources (e.g. `file:`), or that wrap other URLs (e.g. `concat:`), are never
+//! allowed.
+
+/// The protocols that are never allowed, since they read local files or file descriptors
+/// (e.g. `file`), or wrap other URLs (e.g. `concat`, whose parts could be any of them).
+const FORBIDDEN_SCHEMES: [&str; 9] = [
+    "file", "pipe", "fd", "concat", "concatf", "subfile", "cache", "async", "crypto",
+];
+
+/// The protocols on top of which the allowed ones run (e.g. `https` over `tls` over `tcp`),
+/// which FFmpeg's `-protocol_whitelist` must list as well.
+const TRANSPORT_PROTOCOLS: [&str; 2] = ["tcp", "tls"];
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+/// The URL of a remote video, along with the schemes it may use (see [`RemoteUrl::allowed_schemes`]).
+pub struct RemoteUrl {
+    url: String,
+    allowed_schemes: Vec<String>,
+}
+
+impl RemoteUrl {
+    /// The schemes allowed by default.
+    pub const DEFAULT_SCHEMES: [&'static str; 2] = ["http", "https"];
+
+    /// Creates a [`RemoteUrl`] that only allows the [`RemoteUrl::DEFAULT_SCHEMES`].
+    pub fn new(url: impl Into<String>) -> Self {
+        Self {
+            url: url.into(),
+            allowed_schemes: Self::DEFAULT_SCHEMES.map(String::from).to_vec(),
+        }
+    }
+
+    /// A setter method that replaces the schemes that the URL may use (compared
+    /// case-insensitively), e.g. to only allow `https`. The schemes that are never allowed
+    /// (e.g. `file` and `concat`, see the `remote_url` module) are ignored.
+    pub fn allowed_schemes<S: Into<String>>(self, schemes: impl IntoIterator<Item = S>) -> Self {
+        Self {
+            allowed_schemes: schemes
+                .into_iter()
+                .map(|s| s.into().to_ascii_lowercase())
+                .collect(),
+            ..self
+        }
+    }
+
+    /// The URL, as provided.
+    pub fn as_str(&self) -> &str {
+        &self.url
+    }
+
+    /// Checks that the URL is made of a scheme, followed by `://` and the rest of the URL
+    /// (without whitespace or control characters), and that its scheme is allowed.
+    pub fn validate(&self) -> Result<(), UrlError> {
+        let (scheme, rest) = self.url.split_once(':').ok_or(UrlError::Malformed)?;
+        let mut chars = scheme.chars();
+        let valid_scheme = chars.next().is_some_and(|c| c.is_ascii_alphabetic())
+            && chars.all(|c| c.is_ascii_alphanumeric() || matches!(c, '+' | '-' | '.'));
+        if !valid_scheme {
+            return Err(UrlError::Malformed);
+        }
+        let scheme = scheme.to_ascii_lowercase();
+        if !self.schemes().any(|s| s == scheme) {
+            return Err(UrlError::SchemeNotAllowed(scheme));
+        }
+        match rest.strip_prefix("//") {
+            Some(rest)
+                if !rest.is_empty()
+                    && !rest.chars().any(|c| c.is_whitespace() || c.is_control()) =>
+            {
+                Ok(())
+            }
+            _ => Err(UrlError::Malformed),
+        }
+    }
+
+    /// The value of FFmpeg's `-protocol_whitelist` input option, i.e. the allowed schemes,
+    /// along with the transport protocols they rely on (e.g. `http,https,tcp,tls`).
+    pub(crate) fn protocol_whitelist(&self) -> String {
+        let mut protocols: Vec<&str> = vec![];
+        for protocol in self.schemes().chain(TRANSPORT_PROTOCOLS) {
+            if !protocols.contains(&protocol) {
+                protocols.push(protocol);
+            }
+        }
+        protocols.join(",")
+    }
+
+    /// The allowed schemes, without those that are never allowed.
+    fn schemes(&self) -> impl Iterator<Item = &str> {
+        self.allowed_schemes
+            .iter()
+            .map(String::as_str)
+            .filter(|s| !FORBIDDEN_SCHEMES.contains(s))
+    }
+}
+
+impl From<String> for RemoteUrl {
+    fn from(url: String) -> Self {
+        Self::new(url)
+    }
+}
+
+impl From<&str> for RemoteUrl {
+    fn from(url: &str) -> Self {
+        Self::new(url)
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+/// A problem found by [`RemoteUrl::validate`].
+pub enum UrlError {
+    /// The URL is not made of a scheme followed by `://` and the rest of the URL (e.g.
+    /// `https://example.com/video.mp4`), or it contains whitespace or control characters.
+    Malformed,
+    /// The URL's scheme (lowercased) is not one of the allowed ones (see
+    /// [`RemoteUrl::allowed_schemes`]), or is never allowed (e.g. `file`).
+    SchemeNotAllowed(String),
+}
+
+impl std::error::Error for UrlError {}
+
+impl std::fmt::Display for UrlError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::Malformed => write!(f, "expected a URL such as https://example.com/video.mp4"),
+            Self::SchemeNotAllowed(scheme) => write!(f, "URL scheme not allowed: {}", scheme),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_validate() {
+        let valid = [
+            "http://example.com/video.mp4",
+            "https://example.com/video.mp4?token=abc",
+            "HTTPS://example.com/video.mp4",
+            "https://user:pass@[::1]:8443/live/index.m3u8",
+        ];
+        for url in valid {
+            assert_eq!(RemoteUrl::new(url).validate(), Ok(()), "{}", url);
+        }
+
+        let malformed = [
+            "",
+            "example.com/video.mp4",
+            "https:example.com",
+            "https://",
+            "://example.com",
+            "1http://example.com",
+            "ht tp://example.com",
+            "https://example.com/video 1.mp4",
+            "https://example.com/\nvideo.mp4",
+            "subfile,,start,0,end,0,,:https://example.com",
+        ];
+        for url in malformed {
+            assert_eq!(
+                RemoteUrl::new(url).validate(),
+                Err(UrlError::Malformed),
+                "{}",
+                url
+            );
+        }
+
+        let not_allowed = [
+            ("file:///etc/passwd", "file"),
+            ("FILE:///etc/passwd", "file"),
+            ("ftp://example.com/video.mp4", "ftp"),
+            ("rtmp://example.com/live", "rtmp"),
+            ("tcp://127.0.0.1:6379", "tcp"),
+        ];
+        for (url, scheme) in not_allowed {
+            assert_eq!(
+                RemoteUrl::new(url).validate(),
+                Err(UrlError::SchemeNotAllowed(scheme.into())),
+                "{}",
+                url
+            );
+        }
+        // NOTE: FFmpeg's protocols do not need `://` (e.g. `file:video.mp4`).
+        for (url, scheme) in [
+            ("file:/etc/passwd", "file"),
+            ("concat:https://example.com/a.mp4|/etc/passwd", "concat"),
+            ("pipe:0", "pipe"),
+        ] {
+            assert_eq!(
+                RemoteUrl::new(url).validate(),
+                Err(UrlError::SchemeNotAllowed(scheme.into())),
+                "{}",
+                url
+            );
+        }
+    }
+
+    #[test]
+    fn test_allowed_schemes() {
+        let url = RemoteUrl::new("http://example.com/video.mp4");
+        assert_eq!(url.protocol_whitelist(), "http,https,tcp,tls");
+
+        let url = url.allowed_schemes(["HTTPS"]);
+        assert_eq!(
+            url.validate(),
+            Err(UrlError::SchemeNotAllowed("http".into()))
+        );
+        assert_eq!(url.protocol_whitelist(), "https,tcp,tls");
+
+        let rtmp = RemoteUrl::new("rtmp://example.com/live").allowed_schemes(["rtmp", "tcp"]);
+        assert_eq!(rtmp.validate(), Ok(()));
+        assert_eq!(rtmp.protocol_whitelist(), "rtmp,tcp,tls");
+
+        // The schemes that are never allowed are ignored.
+        let file = RemoteUrl::new("file:///etc/passwd").allowed_schemes(["file", "https"]);
+        assert_eq!(
+            file.validate(),
+            Err(UrlError::SchemeNotAllowed("file".into()))
+        );
+        assert_eq!(file.protocol_whitelist(), "https,tcp,tls");
+    }
+}
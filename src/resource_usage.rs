@@ -0,0 +1,234 @@
+//! The measurement of the resources used by the FFmpeg child process (see
+//! [`crate::Summary::resource_usage`]), which is taken when the child process is reaped:
+//! on unix, the child process is reaped using `wait4`, which returns its resource usage
+//! (instead of [`std::process::Child::try_wait`]), and on Windows, the process' times and
+//! memory counters are queried once it has exited, before its handle gets closed.
+
+use std::process::{Child, ExitStatus};
+use std::time::Duration;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+/// The resources used by the FFmpeg child process of a conversion job (see
+/// [`crate::Summary::resource_usage`]).
+pub struct ResourceUsage {
+    /// The CPU time spent in user mode.
+    pub user_time: Duration,
+    /// The CPU time spent in kernel mode (i.e. by the system on behalf of the process).
+    pub system_time: Duration,
+    /// The peak resident set size (in bytes), i.e. the maximum amount of physical
+    /// memory used, if reported by the system.
+    pub max_rss_bytes: Option<u64>,
+}
+
+impl ResourceUsage {
+    /// The total CPU time (i.e. in both user and kernel modes).
+    pub fn cpu_time(&self) -> Duration {
+        self.user_time + self.system_time
+    }
+}
+
+/// Same as [`std::process::Child::try_wait`], but also returns the resources used by
+/// the child process (if available) once it has exited.
+///
+/// NOTE: On unix, the child process is reaped without [`Child`] knowing about it, so
+/// [`Child::kill`] must not be called once this function has returned an exit status.
+pub(crate) fn try_wait(
+    child: &mut Child,
+) -> std::io::Result<Option<(ExitStatus, Option<ResourceUsage>)>> {
+    #[cfg(unix)]
+    {
+        unix::wait4(child, libc::WNOHANG)
+    }
+    #[cfg(not(unix))]
+    {
+        Ok(child.try_wait()?.map(|status| (status, usage(child))))
+    }
+}
+
+/// Same as [`std::process::Child::wait`], but also returns the resources used by
+/// the child process (if available).
+pub(crate) fn wait(child: &mut Child) -> std::io::Result<(ExitStatus, Option<ResourceUsage>)> {
+    #[cfg(unix)]
+    {
+        unix::wait4(child, 0).map(|exited| exited.expect("blocking wait4 returned no status"))
+    }
+    #[cfg(not(unix))]
+    {
+        let status = child.wait()?;
+        Ok((status, usage(child)))
+    }
+}
+
+#[cfg(unix)]
+mod unix {
+    use super::*;
+
+    /// Calls `wait4` for `child`, with the given `options`.
+    pub(super) fn wait4(
+        child: &mut Child,
+        options: libc::c_int,
+    ) -> std::io::Result<Option<(ExitStatus, Option<ResourceUsage>)>> {
+        use std::os::unix::process::ExitStatusExt;
+
+        let pid = child.id() as libc::pid_t;
+        let mut status: libc::c_int = 0;
+        // SAFETY: `rusage` is a plain C structure, for which all zeros is a valid value.
+        let mut rusage: libc::rusage = unsafe { std::mem::zeroed() };
+        loop {
+            // SAFETY: Both pointers are valid for the duration of the call.
+            match unsafe { libc::wait4(pid, &mut status, options, &mut rusage) } {
+                0 => return Ok(None),
+                -1 => {
+                    let e = std::io::Error::last_os_error();
+                    if e.kind() != std::io::ErrorKind::Interrupted {
+                        return Err(e);
+                    }
+                }
+                _ => {
+                    return Ok(Some((
+                        ExitStatus::from_raw(status),
+                        Some(from_rusage(&rusage)),
+                    )))
+                }
+            }
+        }
+    }
+
+    fn from_timeval(t: libc::timeval) -> Duration {
+        Duration::from_secs(t.tv_sec.max(0) as u64) + Duration::from_micros(t.tv_usec.max(0) as u64)
+    }
+
+    fn from_rusage(rusage: &libc::rusage) -> ResourceUsage {
+        // NOTE: Reported in bytes on Apple systems, and in kilobytes elsewhere.
+        #[cfg(any(target_os = "macos", target_os = "ios"))]
+        const MAX_RSS_UNIT: u64 = 1;
+        #[cfg(not(any(target_os = "macos", target_os = "ios")))]
+        const MAX_RSS_UNIT: u64 = 1024;
+        ResourceUsage {
+            user_time: from_timeval(rusage.ru_utime),
+            system_time: from_timeval(rusage.ru_stime),
+            max_rss_bytes: match rusage.ru_maxrss {
+                n if n > 0 => Some(n as u64 * MAX_RSS_UNIT),
+                _ => None,
+            },
+        }
+    }
+}
+
+#[cfg(windows)]
+/// Queries the resources used by `child`, which has exited (but whose handle is still open).
+fn usage(child: &Child) -> Option<ResourceUsage> {
+    use std::os::windows::io::AsRawHandle;
+
+    #[repr(C)]
+    #[derive(Default)]
+    struct FileTime {
+        low: u32,
+        high: u32,
+    }
+
+    #[repr(C)]
+    #[derive(Default)]
+    // NOTE: Only the peak working set size is read.
+    #[allow(dead_code)]
+    struct ProcessMemoryCounters {
+        cb: u32,
+        page_fault_count: u32,
+        peak_working_set_size: usize,
+        working_set_size: usize,
+        quota_peak_paged_pool_usage: usize,
+        quota_paged_pool_usage: usize,
+        quota_peak_non_paged_pool_usage: usize,
+        quota_non_paged_pool_usage: usize,
+        pagefile_usage: usize,
+        peak_pagefile_usage: usize,
+    }
+
+    #[link(name = "kernel32")]
+    extern "system" {
+        fn GetProcessTimes(
+            process: *mut std::ffi::c_void,
+            creation_time: *mut FileTime,
+            exit_time: *mut FileTime,
+            kernel_time: *mut FileTime,
+            user_time: *mut FileTime,
+        ) -> i32;
+        fn K32GetProcessMemoryInfo(
+            process: *mut std::ffi::c_void,
+            counters: *mut ProcessMemoryCounters,
+            cb: u32,
+        ) -> i32;
+    }
+
+    // NOTE: `FILETIME`'s are expressed in 100-nanosecond intervals.
+    let duration =
+        |t: &FileTime| Duration::from_nanos((((t.high as u64) << 32) | t.low as u64) * 100);
+    let handle = child.as_raw_handle();
+    let (mut creation, mut exit, mut kernel, mut user) = Default::default();
+    // SAFETY: The handle is valid until `child` is dropped, and the pointers are valid
+    // for the duration of the call.
+    if unsafe { GetProcessTimes(handle, &mut creation, &mut exit, &mut kernel, &mut user) } == 0 {
+        return None;
+    }
+    let cb = std::mem::size_of::<ProcessMemoryCounters>() as u32;
+    let mut counters = ProcessMemoryCounters {
+        cb,
+        ..Default::default()
+    };
+    // SAFETY: Same as above, `cb` being the size of the structure.
+    let max_rss_bytes = match unsafe { K32GetProcessMemoryInfo(handle, &mut counters, cb) } {
+        0 => None,
+        _ => Some(counters.peak_working_set_size as u64),
+    };
+    Some(ResourceUsage {
+        user_time: duration(&user),
+        system_time: duration(&kernel),
+        max_rss_bytes,
+    })
+}
+
+#[cfg(not(any(unix, windows)))]
+fn usage(_child: &Child) -> Option<ResourceUsage> {
+    None
+}
+
+#[cfg(all(test, unix))]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_wait() {
+        // NOTE: Busy enough for the CPU time to be measurable.
+        let mut child = std::process::Command::new("sh")
+            .args([
+                "-c",
+                "i=0; while [ $i -lt 20000 ]; do i=$((i + 1)); done; exit 3",
+            ])
+            .spawn()
+            .unwrap();
+        let (status, usage) = wait(&mut child).unwrap();
+        assert_eq!(status.code(), Some(3));
+        let usage = usage.unwrap();
+        assert!(usage.cpu_time() > Duration::ZERO, "{:?}", usage);
+        assert!(usage.max_rss_bytes.unwrap() > 0);
+    }
+
+    #[test]
+    fn test_try_wait() {
+        let mut child = std::process::Command::new("sleep")
+            .arg("0.2")
+            .spawn()
+            .unwrap();
+        assert!(try_wait(&mut child).unwrap().is_none());
+        let exited = loop {
+            if let Some(exited) = try_wait(&mut child).unwrap() {
+                break exited;
+            }
+            std::thread::sleep(Duration::from_millis(10));
+        };
+        assert!(exited.0.success());
+        assert!(exited.1.is_some());
+        // The child process has been reaped.
+        assert!(try_wait(&mut child).is_err());
+    }
+}
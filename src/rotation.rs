@@ -0,0 +1,50 @@
+//! The generation of FFmpeg's `transpose` filter(s), which rotate the video's frames
+//! (see [`crate::Settings::rotation`]), e.g. for videos recorded by phones, whose
+//! orientation is not always applied by FFmpeg.
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
+/// How the video's frames are rotated (see [`crate::Settings::rotation`]).
+pub enum Rotation {
+    /// The frames are rotated by 90 degrees, clockwise.
+    Cw90,
+    /// The frames are rotated by 90 degrees, counterclockwise.
+    Ccw90,
+    /// The frames are rotated by 180 degrees (i.e. upside down).
+    Rotate180,
+}
+
+impl Rotation {
+    /// A short name for the rotation (e.g. for [`crate::ConversionPlan`]'s summary).
+    pub(crate) fn name(&self) -> &'static str {
+        match self {
+            Self::Cw90 => "90\u{b0} clockwise",
+            Self::Ccw90 => "90\u{b0} counterclockwise",
+            Self::Rotate180 => "180\u{b0}",
+        }
+    }
+
+    /// The filter(s) that rotate the frames.
+    pub(crate) fn transpose_filter(&self) -> &'static str {
+        match self {
+            Self::Cw90 => "transpose=1",
+            Self::Ccw90 => "transpose=2",
+            Self::Rotate180 => "transpose=2,transpose=2",
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_transpose_filter() {
+        assert_eq!(Rotation::Cw90.transpose_filter(), "transpose=1");
+        assert_eq!(Rotation::Ccw90.transpose_filter(), "transpose=2");
+        assert_eq!(
+            Rotation::Rotate180.transpose_filter(),
+            "transpose=2,transpose=2"
+        );
+    }
+}
@@ -0,0 +1,113 @@
+//! The automatic re-encoding of the animated GIF when it is larger than
+//! [`crate::Settings::max_output_bytes`], i.e. the settings of each new attempt
+//! and how the progress of the attempts adds up to that of the whole job.
+//!
+//! The size of an animated GIF grows roughly with its number of pixels per second, so each
+//! new attempt reduces, in this order (moving on to the next one once a minimum is reached):
+//! 1. the width (and the height, if provided), by the square root of the shrink factor,
+//!    down to [`MIN_WIDTH`];
+//! 2. the frame rate, by the shrink factor, down to [`MIN_FPS`];
+//! 3. the number of colors, by half, down to [`MIN_COLORS`].
+//!
+//! The shrink factor is the ratio between the target and the size of the last attempt,
+//! with a margin (see [`MARGIN`]), bounded so that each attempt makes a difference without
+//! overshooting by too much (see [`MIN_SHRINK_FACTOR`] and [`MAX_SHRINK_FACTOR`]).
+
+use crate::Settings;
+
+/// The smallest width to which the animated GIF gets reduced.
+pub(crate) const MIN_WIDTH: u16 = 64;
+/// The smallest frame rate to which the animated GIF gets reduced.
+pub(crate) const MIN_FPS: u16 = 5;
+/// The smallest number of colors to which the animated GIF's palette gets reduced.
+pub(crate) const MIN_COLORS: u16 = 16;
+/// The fraction of the target aimed for, since the size is only roughly proportional.
+const MARGIN: f64 = 0.9;
+/// The smallest shrink factor, so that a far too large GIF doesn't become tiny at once.
+const MIN_SHRINK_FACTOR: f64 = 0.25;
+/// The largest shrink factor, so that a GIF that is barely too large still shrinks enough.
+const MAX_SHRINK_FACTOR: f64 = 0.9;
+
+/// The settings of the attempt that follows one that produced `output_bytes` with
+/// `settings`, or `None` if there is nothing left to reduce (see the module's documentation).
+pub(crate) fn next_attempt(
+    settings: &Settings,
+    output_bytes: usize,
+    max_bytes: u64,
+) -> Option<Settings> {
+    let factor = (max_bytes as f64 / output_bytes.max(1) as f64 * MARGIN)
+        .clamp(MIN_SHRINK_FACTOR, MAX_SHRINK_FACTOR);
+    let scale = |value: u16, factor: f64, min: u16| ((value as f64 * factor) as u16).max(min);
+    if settings.gif_width > MIN_WIDTH {
+        let factor = factor.sqrt();
+        let gif_width = scale(settings.gif_width, factor, MIN_WIDTH);
+        return Some(Settings {
+            gif_width,
+            gif_height: settings
+                .gif_height
+                .map(|height| scale(height, gif_width as f64 / settings.gif_width as f64, 1)),
+            ..settings.clone()
+        });
+    }
+    if settings.gif_fps > MIN_FPS {
+        return Some(Settings {
+            gif_fps: scale(settings.gif_fps, factor, MIN_FPS),
+            ..settings.clone()
+        });
+    }
+    let colors = settings
+        .effective_max_colors()
+        .unwrap_or(Settings::MAX_COLORS);
+    // NOTE: The number of colors selected by `Settings::auto_colors` is not known here.
+    (colors > MIN_COLORS).then(|| Settings {
+        max_colors: Some((colors / 2).max(MIN_COLORS)),
+        auto_colors: false,
+        ..settings.clone()
+    })
+}
+
+/// The progress of the whole job, given the `progress` of the `attempt`-th attempt
+/// (starting at 1) out of at most `attempts`, i.e. each attempt fills its share of
+/// the progress bar, which jumps to the end once the job is done.
+pub(crate) fn overall_progress(attempt: u32, attempts: u32, progress: f64) -> f64 {
+    ((attempt - 1) as f64 + progress) / attempts as f64
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_utils::SAMPLE_VIDEO_PATH;
+
+    #[test]
+    fn test_next_attempt() {
+        let settings = Settings::with_standard_fps(SAMPLE_VIDEO_PATH.into(), 400).gif_height(300);
+        // 4 times too large: the width is halved (the shrink factor being 0.25).
+        let next = next_attempt(&settings, 4_000, 1_000).unwrap();
+        assert_eq!((next.gif_width, next.gif_height), (200, Some(150)));
+        // Barely too large: the shrink factor is bounded.
+        let next = next_attempt(&next, 1_010, 1_050).unwrap();
+        assert_eq!(next.gif_width, 189);
+
+        // Then the frame rate, and then the number of colors.
+        let settings = Settings::with_standard_fps(SAMPLE_VIDEO_PATH.into(), 100).auto_colors(true);
+        let next = next_attempt(&settings, 4_000, 1_000).unwrap();
+        assert_eq!(next.gif_width, MIN_WIDTH);
+        let next = next_attempt(&next, 4_000, 1_000).unwrap();
+        assert_eq!(next.gif_fps, MIN_FPS);
+        let mut colors = vec![];
+        let mut next = next_attempt(&next, 4_000, 1_000);
+        while let Some(settings) = next {
+            assert!(!settings.auto_colors);
+            colors.extend(settings.max_colors);
+            next = next_attempt(&settings, 4_000, 1_000);
+        }
+        assert_eq!(colors, [128, 64, 32, 16]);
+    }
+
+    #[test]
+    fn test_overall_progress() {
+        assert_eq!(overall_progress(1, 4, 0.5), 0.125);
+        assert_eq!(overall_progress(2, 4, 1.0), 0.5);
+        assert_eq!(overall_progress(4, 4, 1.0), 1.0);
+    }
+}
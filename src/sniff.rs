@@ -0,0 +1,181 @@
+//! The detection of the kind of a file from its first few bytes (i.e. its "magic bytes"),
+//! which is used by [`crate::quick_validate`] to tell (without spawning any child process)
+//! whether a file dropped by the user looks like a video before starting a job.
+
+/// The number of bytes needed by [`sniff`] (the MPEG transport stream's second sync byte
+/// being the farthest one).
+pub(crate) const SNIFF_LEN: usize = 512;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+/// The kind of content found in a file (see [`crate::quick_validate`]).
+pub enum InputKind {
+    /// A video (e.g. MP4, MOV, MKV, WebM or AVI).
+    Video,
+    /// An image, which includes animated GIFs (e.g. GIF, PNG, JPEG or WebP).
+    Image,
+    /// An audio file without any video stream (e.g. MP3, WAV, FLAC or M4A).
+    Audio,
+    /// Not recognized.
+    Unknown,
+}
+
+/// The MPEG transport stream's packet size (each packet starting with a sync byte).
+const TS_PACKET_LEN: usize = 188;
+
+/// Finds the kind of the file whose first (at most [`SNIFF_LEN`]) bytes are `header`.
+pub(crate) fn sniff(header: &[u8]) -> InputKind {
+    let at = |offset: usize, magic: &[u8]| header.get(offset..offset + magic.len()) == Some(magic);
+    if at(4, b"ftyp") {
+        // NOTE: The ISO base media file format (MP4, MOV, M4A, HEIF, ...), whose
+        // "major brand" tells what the file contains.
+        return match header.get(8..12) {
+            Some(b"M4A " | b"M4B " | b"M4P " | b"F4A ") => InputKind::Audio,
+            Some(b"avif" | b"heic" | b"heix" | b"mif1") => InputKind::Image,
+            _ => InputKind::Video,
+        };
+    }
+    // NOTE: Old QuickTime files don't start with an `ftyp` atom.
+    if [b"moov", b"mdat", b"wide", b"free", b"pnot"]
+        .iter()
+        .any(|atom| at(4, *atom))
+    {
+        return InputKind::Video;
+    }
+    if at(0, b"RIFF") {
+        return match header.get(8..12) {
+            Some(b"AVI ") => InputKind::Video,
+            Some(b"WAVE") => InputKind::Audio,
+            Some(b"WEBP") => InputKind::Image,
+            _ => InputKind::Unknown,
+        };
+    }
+    if at(0, &[0x1a, 0x45, 0xdf, 0xa3]) {
+        // NOTE: The EBML header used by both Matroska and WebM.
+        return InputKind::Video;
+    }
+    if at(0, &[0x00, 0x00, 0x01, 0xba]) || (at(0, &[0x47]) && at(TS_PACKET_LEN, &[0x47])) {
+        // NOTE: MPEG program and transport streams.
+        return InputKind::Video;
+    }
+    if at(0, b"GIF87a")
+        || at(0, b"GIF89a")
+        || at(0, b"\x89PNG\r\n\x1a\n")
+        || at(0, &[0xff, 0xd8, 0xff])
+    {
+        return InputKind::Image;
+    }
+    if at(0, b"ID3")
+        || at(0, b"fLaC")
+        || (at(0, &[0xff]) && header.get(1).is_some_and(|b| b & 0xe0 == 0xe0))
+    {
+        // NOTE: MP3 files start with either an ID3 tag or a frame sync.
+        return InputKind::Audio;
+    }
+    InputKind::Unknown
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Pads `magic` with zeros, as found at the beginning of a file.
+    fn header(magic: &[u8]) -> Vec<u8> {
+        let mut header = magic.to_vec();
+        header.resize(SNIFF_LEN, 0);
+        header
+    }
+
+    #[test]
+    fn test_sniff_fixtures() {
+        let ts = {
+            let mut ts = header(&[0x47, 0x40, 0x00, 0x10]);
+            ts[TS_PACKET_LEN] = 0x47;
+            ts
+        };
+        let cases: [(&str, Vec<u8>, InputKind); 17] = [
+            (
+                "mp4",
+                header(b"\x00\x00\x00\x20ftypisom\x00\x00\x02\x00isomiso2avc1mp41"),
+                InputKind::Video,
+            ),
+            (
+                "mov",
+                header(b"\x00\x00\x00\x14ftypqt  \x00\x00\x02\x00qt  "),
+                InputKind::Video,
+            ),
+            (
+                "old mov",
+                header(b"\x00\x00\x00\x08wide\x00\x1b\xd3\xe5mdat"),
+                InputKind::Video,
+            ),
+            (
+                "m4a",
+                header(b"\x00\x00\x00\x1cftypM4A \x00\x00\x02\x00M4A isom"),
+                InputKind::Audio,
+            ),
+            (
+                "avif",
+                header(b"\x00\x00\x00\x1cftypavif\x00\x00\x00\x00avifmif1"),
+                InputKind::Image,
+            ),
+            (
+                "mkv",
+                header(b"\x1a\x45\xdf\xa3\xa3\x42\x86\x81\x01\x42\xf7\x81\x01\x42\x82\x88matroska"),
+                InputKind::Video,
+            ),
+            (
+                "webm",
+                header(b"\x1a\x45\xdf\xa3\x9f\x42\x86\x81\x01\x42\xf7\x81\x01\x42\x82\x84webm"),
+                InputKind::Video,
+            ),
+            (
+                "avi",
+                header(b"RIFF\x24\x10\x00\x00AVI LIST"),
+                InputKind::Video,
+            ),
+            (
+                "mpeg-ps",
+                header(b"\x00\x00\x01\xba\x44\x00\x04\x00"),
+                InputKind::Video,
+            ),
+            ("mpeg-ts", ts, InputKind::Video),
+            ("gif", header(b"GIF89a\x40\x01\xf0\x00"), InputKind::Image),
+            (
+                "png",
+                header(b"\x89PNG\r\n\x1a\n\x00\x00\x00\x0dIHDR"),
+                InputKind::Image,
+            ),
+            (
+                "jpeg",
+                header(b"\xff\xd8\xff\xe0\x00\x10JFIF"),
+                InputKind::Image,
+            ),
+            (
+                "wav",
+                header(b"RIFF\x24\x08\x00\x00WAVEfmt "),
+                InputKind::Audio,
+            ),
+            (
+                "mp3",
+                header(b"ID3\x04\x00\x00\x00\x00\x00\x23"),
+                InputKind::Audio,
+            ),
+            ("flac", header(b"fLaC\x00\x00\x00\x22"), InputKind::Audio),
+            ("text", header(b"Hello, world!\n"), InputKind::Unknown),
+        ];
+        for (name, header, kind) in cases {
+            assert_eq!(sniff(&header), kind, "{}", name);
+        }
+    }
+
+    #[test]
+    fn test_sniff_short_headers() {
+        assert_eq!(sniff(b""), InputKind::Unknown);
+        assert_eq!(sniff(b"\x00\x00\x00"), InputKind::Unknown);
+        assert_eq!(sniff(b"GIF8"), InputKind::Unknown);
+        assert_eq!(sniff(b"RIFF\x24\x10\x00\x00"), InputKind::Unknown);
+        // NOTE: A single sync byte is not enough to tell a transport stream.
+        assert_eq!(sniff(b"\x47\x40\x00\x10"), InputKind::Unknown);
+        assert_eq!(sniff(b"\x00\x00\x00\x18ftyp"), InputKind::Video);
+    }
+}
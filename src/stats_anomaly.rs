@@ -0,0 +1,235 @@
+//! The detection of the anomalies found in FFmpeg's stats lines (e.g. `frame=   50 fps=3.9
+//! q=-0.0 Lsize=   23430kB time=00:00:04.91 bitrate=39091.3kbits/s speed=0.379x`), which
+//! betray a broken output even when FFmpeg exits successfully (see [`crate::Warning::SuspectOutput`]).
+//!
+//! The stats values are parsed once (see [`StatsValues`]), and the anomalies are then
+//! detected by the rules of the [`RULES`] table, each of which looks at the values of the
+//! last stats line (i.e. FFmpeg's final report), and at whether the quality was stuck at 0.0
+//! on every stats line:
+//!
+//! | Anomaly | Rule |
+//! |---------|------|
+//! | [`StatsAnomaly::NoFramesEncoded`] | The last line's `frame=` value is 0. |
+//! | [`StatsAnomaly::NegativeTime`] | The last line's `time=` value is negative. |
+//! | [`StatsAnomaly::NegativeBitrate`] | The last line's `bitrate=` value is negative (including `-0.0`). |
+//! | [`StatsAnomaly::QualityStuckAtZero`] | Every line's `q=` value is `0.0` (the GIF encoder, which has no quantizer, reports `-0.0`). |
+//!
+//! All of them show up, for instance, in the line that FFmpeg writes when it fails to write
+//! the output's trailer: `frame=    0 fps=0.0 q=0.0 Lsize=       0kB time=-577014:32:22.77
+//! bitrate=  -0.0kbits/s speed=N/A`.
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
+/// An anomaly found in FFmpeg's stats lines (see [`crate::Warning::SuspectOutput`]).
+pub enum StatsAnomaly {
+    /// FFmpeg's final report says that no frame was encoded.
+    NoFramesEncoded,
+    /// FFmpeg's final report has a negative time.
+    NegativeTime,
+    /// FFmpeg's final report has a negative bitrate.
+    NegativeBitrate,
+    /// The quality (i.e. the `q=` value) was stuck at 0.0, which the GIF encoder never reports.
+    QualityStuckAtZero,
+}
+
+#[derive(Debug, Clone, Default, PartialEq)]
+/// The values of a stats line that the [`RULES`] look at (`None` when missing or `N/A`).
+pub(crate) struct StatsValues {
+    /// The `frame=` value.
+    frame: Option<u64>,
+    /// The `q=` value.
+    q: Option<f64>,
+    /// Whether the `time=` value is negative.
+    negative_time: bool,
+    /// The `bitrate=` value (in kbits/s).
+    bitrate: Option<f64>,
+}
+
+impl StatsValues {
+    /// Parses the values of a stats `line`.
+    pub(crate) fn parse(line: &str) -> Self {
+        Self {
+            frame: value(line, "frame").and_then(|v| v.parse().ok()),
+            q: value(line, "q").and_then(|v| v.parse().ok()),
+            negative_time: value(line, "time").is_some_and(|v| v.starts_with('-')),
+            bitrate: value(line, "bitrate")
+                .and_then(|v| v.strip_suffix("kbits/s"))
+                .and_then(|v| v.parse().ok()),
+        }
+    }
+}
+
+/// The values that the [`RULES`] look at.
+pub(crate) struct Observation<'a> {
+    /// The values of the last stats line.
+    last: &'a StatsValues,
+    /// Whether the quality was 0.0 on every stats line.
+    quality_stuck_at_zero: bool,
+}
+
+/// The rules that detect the anomalies, in order of precedence (see the module's documentation).
+#[allow(clippy::type_complexity)]
+pub(crate) static RULES: [(StatsAnomaly, fn(&Observation) -> bool); 4] = [
+    (StatsAnomaly::NoFramesEncoded, |o| o.last.frame == Some(0)),
+    (StatsAnomaly::NegativeTime, |o| o.last.negative_time),
+    (StatsAnomaly::NegativeBitrate, |o| {
+        o.last.bitrate.is_some_and(f64::is_sign_negative)
+    }),
+    (StatsAnomaly::QualityStuckAtZero, |o| {
+        o.quality_stuck_at_zero
+    }),
+];
+
+#[derive(Debug, Default)]
+/// Follows the stats lines of a job, to detect their anomalies once it is done.
+pub(crate) struct AnomalyDetector {
+    /// The values of the last stats line, if any.
+    last: Option<StatsValues>,
+    /// Whether the quality was 0.0 on every stats line so far.
+    quality_stuck_at_zero: bool,
+}
+
+impl AnomalyDetector {
+    /// Takes a stats `line` into account.
+    pub(crate) fn push(&mut self, line: &str) {
+        let values = StatsValues::parse(line);
+        let zero = values.q.is_some_and(|q| q == 0.0 && q.is_sign_positive());
+        self.quality_stuck_at_zero = zero && (self.last.is_none() || self.quality_stuck_at_zero);
+        self.last = Some(values);
+    }
+
+    /// The first anomaly detected by the [`RULES`], if any (and if there was any stats line).
+    pub(crate) fn detect(&self) -> Option<StatsAnomaly> {
+        let observation = Observation {
+            last: self.last.as_ref()?,
+            quality_stuck_at_zero: self.quality_stuck_at_zero,
+        };
+        RULES
+            .iter()
+            .find(|(_, rule)| rule(&observation))
+            .map(|(anomaly, _)| *anomaly)
+    }
+}
+
+/// The value following `key=` in `line` (e.g. `-0.0kbits/s` for `bitrate`, the value
+/// being separated from the key by spaces at times), if any, and if not `N/A`.
+fn value<'a>(line: &'a str, key: &str) -> Option<&'a str> {
+    let pattern = format!("{}=", key);
+    let start = line
+        .match_indices(&pattern)
+        // NOTE: Not part of another key (e.g. `q=` in `freq=`).
+        .find(|(i, _)| !line[..*i].ends_with(|c: char| c.is_ascii_alphanumeric()))
+        .map(|(i, _)| i + pattern.len())?;
+    let value = line[start..].split_whitespace().next()?;
+    (value != "N/A").then_some(value)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Runs the `lines` through the detector.
+    fn detect(lines: &[&str]) -> Option<StatsAnomaly> {
+        let mut detector = AnomalyDetector::default();
+        for line in lines {
+            detector.push(line);
+        }
+        detector.detect()
+    }
+
+    #[test]
+    fn test_parse() {
+        assert_eq!(
+            StatsValues::parse("frame=   50 fps=3.9 q=-0.0 Lsize=   23430kB time=00:00:04.91 bitrate=39091.3kbits/s speed=0.379x"),
+            StatsValues {
+                frame: Some(50),
+                q: Some(-0.0),
+                negative_time: false,
+                bitrate: Some(39091.3),
+            }
+        );
+        let values = StatsValues::parse("frame=    0 fps=0.0 q=0.0 Lsize=       0kB time=-577014:32:22.77 bitrate=  -0.0kbits/s speed=N/A");
+        assert_eq!(values.frame, Some(0));
+        assert!(values.q.is_some_and(f64::is_sign_positive));
+        assert!(values.negative_time);
+        assert!(values.bitrate.is_some_and(f64::is_sign_negative));
+        assert_eq!(
+            StatsValues::parse(
+                "frame=    0 fps=0.0 q=0.0 size=       0kB time=N/A bitrate=N/A speed=N/A"
+            ),
+            StatsValues {
+                frame: Some(0),
+                q: Some(0.0),
+                negative_time: false,
+                bitrate: None,
+            }
+        );
+    }
+
+    #[test]
+    fn test_valid_stats() {
+        assert_eq!(detect(&[]), None);
+        assert_eq!(
+            detect(&[
+                "frame=    0 fps=0.0 q=0.0 size=       0kB time=N/A bitrate=N/A speed=N/A",
+                "frame=   10 fps=0.0 q=-0.0 size=       0kB time=00:00:01.00 bitrate=   0.0kbits/s speed=2x",
+                "frame=   50 fps=3.9 q=-0.0 Lsize=   23430kB time=00:00:04.91 bitrate=39091.3kbits/s speed=0.379x",
+            ]),
+            None
+        );
+    }
+
+    #[test]
+    fn test_rules() {
+        let cases = [
+            (
+                "frame=    0 fps=0.0 q=-0.0 Lsize=       0kB time=00:00:00.00 bitrate=   0.0kbits/s speed=N/A",
+                StatsAnomaly::NoFramesEncoded,
+            ),
+            (
+                "frame=   50 fps=3.9 q=-0.0 Lsize=   23430kB time=-00:00:04.91 bitrate=39091.3kbits/s speed=0.379x",
+                StatsAnomaly::NegativeTime,
+            ),
+            (
+                "frame=   50 fps=3.9 q=-0.0 Lsize=   23430kB time=00:00:04.91 bitrate=  -0.0kbits/s speed=0.379x",
+                StatsAnomaly::NegativeBitrate,
+            ),
+            (
+                "frame=   50 fps=3.9 q=0.0 Lsize=   23430kB time=00:00:04.91 bitrate=39091.3kbits/s speed=0.379x",
+                StatsAnomaly::QualityStuckAtZero,
+            ),
+            // NOTE: The first matching rule wins.
+            (
+                "frame=    0 fps=0.0 q=0.0 Lsize=       0kB time=-577014:32:22.77 bitrate=  -0.0kbits/s speed=N/A",
+                StatsAnomaly::NoFramesEncoded,
+            ),
+        ];
+        for (line, expected) in cases {
+            assert_eq!(detect(&[line]), Some(expected), "{}", line);
+        }
+    }
+
+    #[test]
+    fn test_quality_stuck_at_zero() {
+        let zero = "frame=   10 fps=0.0 q=0.0 size=       0kB time=00:00:01.00 bitrate=   0.0kbits/s speed=2x";
+        let valid = "frame=   20 fps=0.0 q=-0.0 size=       0kB time=00:00:02.00 bitrate=   0.0kbits/s speed=2x";
+        assert_eq!(
+            detect(&[zero, zero]),
+            Some(StatsAnomaly::QualityStuckAtZero)
+        );
+        assert_eq!(detect(&[zero, valid]), None);
+        assert_eq!(detect(&[valid, zero]), None);
+    }
+
+    #[test]
+    fn test_value() {
+        let line = "frame=   10 fps=0.0 q=-0.0 size=       0kB time=N/A bitrate=   0.0kbits/s";
+        assert_eq!(value(line, "frame"), Some("10"));
+        assert_eq!(value(line, "q"), Some("-0.0"));
+        assert_eq!(value(line, "size"), Some("0kB"));
+        assert_eq!(value(line, "time"), None);
+        assert_eq!(value(line, "bitrate"), Some("0.0kbits/s"));
+        assert_eq!(value(line, "speed"), None);
+        assert_eq!(value("frame=   10 freq=5", "q"), None);
+    }
+}
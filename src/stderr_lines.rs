@@ -0,0 +1,371 @@
+//! The assembly of FFmpeg's `stderr` output (read in arbitrary chunks) into complete
+//! lines, ended by either `\n` or `\r` (the latter being used by FFmpeg's stats updates).
+//!
+//! Some FFmpeg builds print huge lines (e.g. the whole filter graph, or metadata dumps),
+//! so only the first [`MAX_LINE_LEN`] bytes of each line are kept, which bounds both the
+//! memory used by the assembler and the cost of parsing (or matching) a line. Truncated
+//! lines are flagged as such, so that they are not parsed for timestamps (a token cut in
+//! the middle, such as `time=00:00:04.9` instead of `time=00:00:04.95`, would be parsed
+//! into the wrong value), but only matched against the deny patterns.
+//!
+//! When FFmpeg is run through a wrapper script (e.g. a `.bat` or `.cmd` shim on Windows),
+//! the output may come with CRLF line endings (which are handled like any other line
+//! break, since empty lines are dropped), and may even be encoded in UTF-16, which is
+//! detected using its BOM (or, without one, using the NUL byte that follows the first
+//! ASCII character), and decoded into UTF-8 before being split into lines.
+
+/// The number of bytes of each line that are kept.
+pub(crate) const MAX_LINE_LEN: usize = 64 * 1024;
+
+/// The beginning of FFmpeg's banner, i.e. of its first line (unless hidden, e.g. using
+/// `-hide_banner`).
+const BANNER_PREFIX: &str = "ffmpeg version";
+
+/// The number of bytes needed to detect the encoding (i.e. the length of UTF-8's BOM).
+const DETECTION_LEN: usize = 3;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+/// The encoding of the output, as detected from its first bytes.
+enum Encoding {
+    Utf8,
+    Utf16Le,
+    Utf16Be,
+}
+
+impl Encoding {
+    /// Detects the encoding from the first bytes of the output, and returns it along
+    /// with the length of the BOM (if any), which is to be skipped.
+    fn detect(head: &[u8]) -> (Self, usize) {
+        match head {
+            [0xef, 0xbb, 0xbf, ..] => (Self::Utf8, 3),
+            [0xff, 0xfe, ..] => (Self::Utf16Le, 2),
+            [0xfe, 0xff, ..] => (Self::Utf16Be, 2),
+            // NOTE: FFmpeg never writes NUL bytes, so ASCII text with NUL bytes in
+            // between is UTF-16 without a BOM (e.g. as written by `cmd /U`).
+            [c, 0, ..] if *c != 0 && c.is_ascii() => (Self::Utf16Le, 0),
+            [0, c, ..] if *c != 0 && c.is_ascii() => (Self::Utf16Be, 0),
+            _ => (Self::Utf8, 0),
+        }
+    }
+}
+
+/// Whether `line` is the first line of FFmpeg's banner (e.g. `ffmpeg version 6.0 ...`).
+pub(crate) fn is_banner(line: &str) -> bool {
+    line.starts_with(BANNER_PREFIX)
+}
+
+/// Decodes the whole output of an FFmpeg child process (e.g. that of a probe), as the
+/// [`LineAssembler`] does, into lines ended by `\n` (i.e. with CRLF and CR normalized to LF).
+pub(crate) fn decode(bytes: &[u8]) -> String {
+    let mut assembler = LineAssembler::default();
+    let mut lines = assembler.push(bytes);
+    lines.extend(assembler.finish());
+    lines
+        .iter()
+        .map(|line| format!("{}\n", line.text))
+        .collect()
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+/// A complete line written by FFmpeg to `stderr` (without its line break).
+pub(crate) struct StderrLine {
+    /// The (possibly truncated) line.
+    pub(crate) text: String,
+    /// Whether the line was longer than [`MAX_LINE_LEN`] bytes.
+    pub(crate) truncated: bool,
+}
+
+#[derive(Debug, Default)]
+/// Assembles the chunks read from FFmpeg's `stderr` into [`StderrLine`]'s.
+pub(crate) struct LineAssembler {
+    /// The beginning of the current (incomplete) line.
+    line: Vec<u8>,
+    /// Whether bytes of the current line have been dropped.
+    truncated: bool,
+    /// The encoding of the output, once detected.
+    encoding: Option<Encoding>,
+    /// The bytes that are not decoded yet, i.e. the first bytes of the output (until the
+    /// encoding is detected), or the end of an incomplete UTF-16 character.
+    pending: Vec<u8>,
+}
+
+impl LineAssembler {
+    /// Appends `chunk` to the output read so far, and returns the (non-empty) lines
+    /// that are now complete.
+    pub(crate) fn push(&mut self, chunk: &[u8]) -> Vec<StderrLine> {
+        let mut lines = vec![];
+        match self.encoding {
+            Some(Encoding::Utf8) => self.push_utf8(chunk, &mut lines),
+            Some(encoding) => {
+                let mut bytes = std::mem::take(&mut self.pending);
+                bytes.extend_from_slice(chunk);
+                self.push_utf16(encoding, &bytes, &mut lines);
+            }
+            None => {
+                self.pending.extend_from_slice(chunk);
+                if self.pending.len() >= DETECTION_LEN {
+                    self.push_pending(&mut lines);
+                }
+            }
+        }
+        lines
+    }
+
+    /// Returns the last line (if it was not ended by a line break), once the
+    /// whole output has been read.
+    pub(crate) fn finish(&mut self) -> Option<StderrLine> {
+        let mut lines = vec![];
+        if self.encoding.is_none() {
+            self.push_pending(&mut lines);
+        }
+        if !std::mem::take(&mut self.pending).is_empty() {
+            // NOTE: The last UTF-16 character is incomplete.
+            let mut utf8 = [0u8; 4];
+            let replacement = char::REPLACEMENT_CHARACTER.encode_utf8(&mut utf8);
+            self.push_utf8(replacement.as_bytes(), &mut lines);
+        }
+        // NOTE: An output too short for its encoding to be detected holds at most one line.
+        lines.pop().or_else(|| self.take_line())
+    }
+
+    /// Detects the encoding from the first bytes of the output (which are pending), whose
+    /// BOM (if any) is dropped, and then decodes them.
+    fn push_pending(&mut self, lines: &mut Vec<StderrLine>) {
+        let (encoding, bom_len) = Encoding::detect(&self.pending);
+        self.encoding = Some(encoding);
+        let mut bytes = std::mem::take(&mut self.pending);
+        bytes.drain(..bom_len);
+        match encoding {
+            Encoding::Utf8 => self.push_utf8(&bytes, lines),
+            _ => self.push_utf16(encoding, &bytes, lines),
+        }
+    }
+
+    fn push_utf8(&mut self, bytes: &[u8], lines: &mut Vec<StderrLine>) {
+        for &byte in bytes {
+            if byte == b'\n' || byte == b'\r' {
+                lines.extend(self.take_line());
+            } else if self.line.len() < MAX_LINE_LEN {
+                self.line.push(byte);
+            } else {
+                self.truncated = true;
+            }
+        }
+    }
+
+    /// Decodes `bytes` from UTF-16, keeping the end of an incomplete character (i.e. an odd
+    /// byte, or a high surrogate) pending until the next chunk.
+    fn push_utf16(&mut self, encoding: Encoding, bytes: &[u8], lines: &mut Vec<StderrLine>) {
+        let units: Vec<u16> = bytes
+            .chunks_exact(2)
+            .map(|pair| match encoding {
+                Encoding::Utf16Be => u16::from_be_bytes([pair[0], pair[1]]),
+                _ => u16::from_le_bytes([pair[0], pair[1]]),
+            })
+            .collect();
+        let mut complete = units.len();
+        if units.last().is_some_and(|u| (0xd800..0xdc00).contains(u)) {
+            complete -= 1;
+        }
+        self.pending = bytes[complete * 2..].to_vec();
+        let mut utf8 = [0u8; 4];
+        for c in char::decode_utf16(units[..complete].iter().copied()) {
+            let c = c.unwrap_or(char::REPLACEMENT_CHARACTER);
+            self.push_utf8(c.encode_utf8(&mut utf8).as_bytes(), lines);
+        }
+    }
+
+    fn take_line(&mut self) -> Option<StderrLine> {
+        let truncated = std::mem::take(&mut self.truncated);
+        if self.line.is_empty() {
+            return None;
+        }
+        let line = StderrLine {
+            text: String::from_utf8_lossy(&self.line).into_owned(),
+            truncated,
+        };
+        self.line.clear();
+        Some(line)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const TRANSCRIPT: &str = "Input #0, mov,mp4,m4a,3gp,3g2,mj2, from 'video.mp4':
+  Duration: 00:00:05.00, start: 0.000000, bitrate: 1785 kb/s
+frame=   10 fps=0.0 q=-0.0 size=       0kB time=00:00:01.00 bitrate=   0.0kbits/s speed=2x\r\
+frame=   20 fps=0.0 q=-0.0 size=       0kB time=00:00:02.00 bitrate=   0.0kbits/s speed=2x\r\n";
+
+    fn texts(lines: &[StderrLine]) -> Vec<&str> {
+        lines.iter().map(|l| l.text.as_str()).collect()
+    }
+
+    #[test]
+    fn test_lines_in_chunks() {
+        for chunk in [1, 7, 100, TRANSCRIPT.len()] {
+            let mut assembler = LineAssembler::default();
+            let lines: Vec<_> = TRANSCRIPT
+                .as_bytes()
+                .chunks(chunk)
+                .flat_map(|c| assembler.push(c))
+                .collect();
+            assert_eq!(
+                texts(&lines),
+                [
+                    "Input #0, mov,mp4,m4a,3gp,3g2,mj2, from 'video.mp4':",
+                    "  Duration: 00:00:05.00, start: 0.000000, bitrate: 1785 kb/s",
+                    "frame=   10 fps=0.0 q=-0.0 size=       0kB time=00:00:01.00 bitrate=   0.0kbits/s speed=2x",
+                    "frame=   20 fps=0.0 q=-0.0 size=       0kB time=00:00:02.00 bitrate=   0.0kbits/s speed=2x",
+                ]
+            );
+            assert!(lines.iter().all(|l| !l.truncated));
+            assert_eq!(assembler.finish(), None);
+        }
+    }
+
+    #[test]
+    fn test_last_line_without_line_break() {
+        let mut assembler = LineAssembler::default();
+        assert_eq!(
+            texts(&assembler.push(b"some line\nConversion fail")),
+            ["some line"]
+        );
+        assert!(assembler.push(b"ed!").is_empty());
+        assert_eq!(
+            assembler.finish().map(|l| l.text),
+            Some("Conversion failed!".into())
+        );
+    }
+
+    #[test]
+    fn test_long_lines_are_truncated() {
+        let mut assembler = LineAssembler::default();
+        let mut lines = vec![];
+        // A multi-megabyte line, read in small chunks.
+        let prefix = b"[Parsed_graph] ";
+        lines.extend(assembler.push(prefix));
+        for _ in 0..(4 * 1024) {
+            lines.extend(assembler.push(&[b'x'; 1024]));
+            assert!(assembler.line.capacity() <= 2 * MAX_LINE_LEN);
+        }
+        lines.extend(assembler.push(b"\nframe=    1 time=00:00:01.00\r"));
+        assert_eq!(lines.len(), 2);
+        assert!(lines[0].truncated);
+        assert_eq!(lines[0].text.len(), MAX_LINE_LEN);
+        assert!(lines[0].text.starts_with("[Parsed_graph] xxx"));
+        assert_eq!(
+            lines[1],
+            StderrLine {
+                text: "frame=    1 time=00:00:01.00".into(),
+                truncated: false
+            }
+        );
+    }
+
+    #[test]
+    fn test_invalid_utf8() {
+        let mut assembler = LineAssembler::default();
+        // NOTE: A multi-byte character split across two chunks.
+        let bytes = "size=10kB é\n".as_bytes();
+        let (a, b) = bytes.split_at(bytes.len() - 2);
+        assert!(assembler.push(a).is_empty());
+        assert_eq!(texts(&assembler.push(b)), ["size=10kB é"]);
+        assert_eq!(
+            texts(&assembler.push(b"bad \xff byte\n")),
+            ["bad \u{fffd} byte"]
+        );
+    }
+
+    /// Transcripts captured from FFmpeg run through a `.cmd` shim on Windows.
+    const CMD_SHIM_CRLF: &[u8] = include_bytes!("../tests/fixtures/stderr_cmd_shim_crlf.txt");
+    const CMD_SHIM_UTF16: &[u8] = include_bytes!("../tests/fixtures/stderr_cmd_shim_utf16.txt");
+
+    /// Splits `bytes` into chunks of `chunk` bytes, and returns the assembled lines.
+    fn assemble(bytes: &[u8], chunk: usize) -> Vec<String> {
+        let mut assembler = LineAssembler::default();
+        let mut lines: Vec<_> = bytes
+            .chunks(chunk)
+            .flat_map(|c| assembler.push(c))
+            .collect();
+        lines.extend(assembler.finish());
+        lines.into_iter().map(|l| l.text).collect()
+    }
+
+    #[test]
+    fn test_cmd_shim_fixtures() {
+        let text = std::str::from_utf8(CMD_SHIM_CRLF).unwrap();
+        let expected: Vec<_> = text.split(['\r', '\n']).filter(|l| !l.is_empty()).collect();
+        assert!(is_banner(expected[0]));
+        let utf16be: Vec<u8> = [0xfe, 0xff]
+            .into_iter()
+            .chain(text.encode_utf16().flat_map(u16::to_be_bytes))
+            .collect();
+        let utf16le_without_bom = &CMD_SHIM_UTF16[2..];
+        let utf8_bom = [&[0xef, 0xbb, 0xbf], CMD_SHIM_CRLF].concat();
+        for bytes in [
+            CMD_SHIM_CRLF,
+            CMD_SHIM_UTF16,
+            &utf16be,
+            utf16le_without_bom,
+            &utf8_bom,
+        ] {
+            for chunk in [1, 2, 3, 5, 1000, bytes.len()] {
+                assert_eq!(assemble(bytes, chunk), expected, "chunk: {}", chunk);
+            }
+        }
+
+        // The whole output, as read from a probe.
+        let decoded = decode(CMD_SHIM_UTF16);
+        assert_eq!(decoded, format!("{}\n", expected.join("\n")));
+        assert_eq!(
+            crate::time_parsing::try_extract_duration(&decoded, None),
+            Some(std::time::Duration::from_secs(5))
+        );
+        let times: Vec<_> = decoded
+            .lines()
+            .filter_map(|l| crate::time_parsing::try_extract_frame_time(l, None))
+            .collect();
+        assert_eq!(times.len(), 2);
+    }
+
+    #[test]
+    fn test_utf16_characters_across_chunks() {
+        // NOTE: A character outside the BMP is encoded as a surrogate pair.
+        let text = "size=10kB 🎞 é\r\n";
+        let bytes: Vec<u8> = [0xff, 0xfe]
+            .into_iter()
+            .chain(text.encode_utf16().flat_map(u16::to_le_bytes))
+            .collect();
+        for chunk in 1..=bytes.len() {
+            assert_eq!(
+                assemble(&bytes, chunk),
+                ["size=10kB 🎞 é"],
+                "chunk: {}",
+                chunk
+            );
+        }
+        // An unpaired surrogate, and an odd byte at the end.
+        let mut assembler = LineAssembler::default();
+        assert!(assembler
+            .push(&[0xff, 0xfe, b'a', 0, 0x00, 0xd8])
+            .is_empty());
+        assert!(assembler.push(&[b'b', 0]).is_empty());
+        assert!(assembler.push(b"c").is_empty());
+        assert_eq!(
+            assembler.finish().map(|l| l.text),
+            Some("a\u{fffd}b\u{fffd}".into())
+        );
+    }
+
+    #[test]
+    fn test_short_outputs() {
+        assert_eq!(assemble(b"", 1), Vec::<String>::new());
+        assert_eq!(assemble(b"ok", 1), ["ok"]);
+        assert_eq!(assemble(b"a\n", 1), ["a"]);
+        assert_eq!(assemble(&[0xff, 0xfe], 1), Vec::<String>::new());
+        assert_eq!(assemble(&[b'a', 0], 1), ["a"]);
+        assert_eq!(decode(b"a\r\nb\rc"), "a\nb\nc\n");
+    }
+}
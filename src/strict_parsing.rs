@@ -0,0 +1,171 @@
+//! The checks behind [`crate::Settings::strict_parsing`], which turn the problems met
+//! while parsing FFmpeg's `stderr` output (e.g. after a new FFmpeg version changed its
+//! format) into an [`crate::Error::ParseFailure`] error, instead of a warning logged
+//! while the job carries on without progress reporting.
+//!
+//! Two problems are detected:
+//! * no duration was found (from any of the sources of the `duration_source` module) in
+//!   the first [`BANNER_LIMIT`] bytes of output, or before the first `frame=` stats line;
+//! * the `time=` value of a `frame=` stats line could not be parsed (`time=N/A`, which
+//!   FFmpeg writes before the first frame is processed, being a valid value).
+//!
+//! Only the lines that are parsed count (i.e. not the truncated ones, see the
+//! `stderr_lines` module).
+
+use crate::Error;
+
+/// The number of bytes of output within which the duration must have been found.
+pub(crate) const BANNER_LIMIT: usize = 16 * 1024;
+
+/// The number of characters of the offending line kept in [`crate::Error::ParseFailure`].
+const MAX_SAMPLE_LEN: usize = 256;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+/// What was being parsed when FFmpeg's `stderr` output could not be made sense of
+/// (see [`crate::Error::ParseFailure`]).
+pub enum ParseContext {
+    /// The video's duration, which was not found in the first few KiB of output (i.e.
+    /// in FFmpeg's banner), or before the first stats line.
+    Duration,
+    /// The `time=` value of a `frame=` stats line, from which the progress is computed.
+    FrameTime,
+}
+
+#[derive(Debug)]
+/// Checks the lines of FFmpeg's `stderr` output (see the module's documentation).
+pub(crate) struct ParseChecker {
+    /// Whether the problems fail the job (see [`crate::Settings::strict_parsing`]).
+    strict: bool,
+    /// The number of bytes of output checked so far.
+    bytes: usize,
+    /// The first `Duration: ...` line, if any, which is the most telling sample when
+    /// no duration could be parsed from it.
+    duration_line: Option<String>,
+}
+
+impl ParseChecker {
+    pub(crate) fn new(strict: bool) -> Self {
+        Self {
+            strict,
+            bytes: 0,
+            duration_line: None,
+        }
+    }
+
+    /// Checks a `line` of output, given whether a duration has been found so far.
+    pub(crate) fn check_line(&mut self, line: &str, duration_found: bool) -> Result<(), Error> {
+        if self.duration_line.is_none() && line.trim_start().starts_with("Duration:") {
+            self.duration_line = Some(line.to_string());
+        }
+        // NOTE: Counting the line break.
+        self.bytes += line.len() + 1;
+        if !self.strict || duration_found {
+            return Ok(());
+        }
+        if self.bytes > BANNER_LIMIT || is_stats_line(line) {
+            let sample = self.duration_line.as_deref().unwrap_or(line);
+            return Err(failure(ParseContext::Duration, sample));
+        }
+        Ok(())
+    }
+
+    /// Checks a `frame=` stats `line`, given whether its time could be parsed.
+    pub(crate) fn check_frame_time(&self, line: &str, parsed: bool) -> Result<(), Error> {
+        if !self.strict || parsed || has_unknown_time(line) {
+            return Ok(());
+        }
+        Err(failure(ParseContext::FrameTime, line))
+    }
+}
+
+/// Whether `line` is one of FFmpeg's stats lines.
+pub(crate) fn is_stats_line(line: &str) -> bool {
+    line.trim_start().starts_with("frame=")
+}
+
+/// Whether the `time=` value of `line` is `N/A` (i.e. no frame has been processed yet).
+fn has_unknown_time(line: &str) -> bool {
+    line.split_ascii_whitespace()
+        .any(|token| token == "time=N/A")
+}
+
+fn failure(context: ParseContext, line: &str) -> Error {
+    Error::ParseFailure {
+        context,
+        sample: line.chars().take(MAX_SAMPLE_LEN).collect(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::duration_source::DurationTracker;
+    use crate::test_utils::{SAMPLE_STDERR, STDERR_RENAMED_DURATION, STDERR_RENAMED_TIME};
+    use crate::time_parsing::try_extract_frame_time;
+
+    /// Runs the lines of `transcript` through the checks, the way the STDERR thread does,
+    /// returning the first failure, if any.
+    fn run(transcript: &str, strict: bool) -> Result<(), Error> {
+        let mut checker = ParseChecker::new(strict);
+        let mut tracker = DurationTracker::new(None);
+        let mut duration_found = false;
+        for line in transcript.lines() {
+            duration_found |= tracker.push(line, None).is_some();
+            checker.check_line(line, duration_found)?;
+            if is_stats_line(line) {
+                let parsed = try_extract_frame_time(line, None).is_some();
+                checker.check_frame_time(line, parsed)?;
+            }
+        }
+        Ok(())
+    }
+
+    #[test]
+    fn test_valid_transcript() {
+        let transcript = SAMPLE_STDERR;
+        assert!(run(transcript, false).is_ok());
+        assert!(run(transcript, true).is_ok());
+    }
+
+    #[test]
+    fn test_missing_duration() {
+        assert!(run(STDERR_RENAMED_DURATION, false).is_ok());
+        match run(STDERR_RENAMED_DURATION, true) {
+            Err(Error::ParseFailure { context, sample }) => {
+                assert_eq!(context, ParseContext::Duration);
+                assert_eq!(
+                    sample,
+                    "  Duration: 5.00 s, start: 0.000000, bitrate: 1785 kb/s"
+                );
+            }
+            other => panic!("{:?}", other),
+        }
+    }
+
+    #[test]
+    fn test_missing_duration_long_banner() {
+        // No stats line, but too much output without a duration.
+        let line = format!("    comment         : {}", "x".repeat(1000));
+        let transcript = vec![line.as_str(); BANNER_LIMIT / line.len() + 1].join("\n");
+        assert!(run(&transcript, false).is_ok());
+        match run(&transcript, true) {
+            Err(Error::ParseFailure { context, sample }) => {
+                assert_eq!(context, ParseContext::Duration);
+                assert_eq!(sample.len(), MAX_SAMPLE_LEN);
+            }
+            other => panic!("{:?}", other),
+        }
+    }
+
+    #[test]
+    fn test_unparsable_frame_time() {
+        assert!(run(STDERR_RENAMED_TIME, false).is_ok());
+        match run(STDERR_RENAMED_TIME, true) {
+            Err(Error::ParseFailure { context, sample }) => {
+                assert_eq!(context, ParseContext::FrameTime);
+                assert!(sample.contains("time=2000ms"), "{}", sample);
+            }
+            other => panic!("{:?}", other),
+        }
+    }
+}
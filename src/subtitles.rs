@@ -0,0 +1,100 @@
+//! The subtitles burned into the animated GIF's frames (see [`crate::Settings::subtitles`]),
+//! which FFmpeg's `subtitles` filter reads from an external file (e.g. an SRT file).
+//!
+//! The file's path is escaped twice: once for the filter's options (in which `:` separates
+//! the options, e.g. after a Windows drive letter), and once for the filter graph (in which
+//! `,`, `;`, `[` and `]` separate the filters), the latter by quoting the whole value.
+
+use std::path::Path;
+
+/// The `subtitles` filter, which burns the subtitles of the file at `path` into the frames.
+pub(crate) fn subtitles_filter(path: &Path) -> String {
+    format!("subtitles={}", escape_value(&path.to_string_lossy()))
+}
+
+/// Escapes `value` (e.g. a path) for both levels of FFmpeg's filter graph syntax (see the
+/// module's documentation), e.g. `C:\it's.srt` becoming `'C\:\\it\'\''s.srt'`.
+pub(crate) fn escape_value(value: &str) -> String {
+    let mut option = String::with_capacity(value.len());
+    for c in value.chars() {
+        if matches!(c, '\\' | '\'' | ':') {
+            option.push('\\');
+        }
+        option.push(c);
+    }
+    // NOTE: A quote cannot be escaped within a quoted value, which is therefore closed
+    // before the (escaped) quote, and then reopened.
+    format!("'{}'", option.replace('\'', r"'\''"))
+}
+
+/// Whether `line`, written by FFmpeg to `stderr`, reports that the subtitles file could
+/// not be opened or parsed (see [`crate::Error::SubtitlesFile`]).
+pub(crate) fn is_subtitles_error(line: &str) -> bool {
+    // NOTE: E.g. `[Parsed_subtitles_1 @ 0x5581c0a0c2c0] Unable to open captions.srt`, which
+    // is followed by `Error initializing filter 'subtitles' with args 'captions.srt'`, while
+    // the other lines of the filter (e.g. `libass API version: 0x1701000`) are informational.
+    (line.contains("[Parsed_subtitles_")
+        && ["Unable to", "Failed to", "Could not"]
+            .iter()
+            .any(|pattern| line.contains(pattern)))
+        || line.contains("Error initializing filter 'subtitles'")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Undoes one level of escaping, the way FFmpeg's `av_get_token` does (i.e. removing
+    /// the quotes and the backslashes outside of them).
+    fn unescape(value: &str) -> String {
+        let (mut unescaped, mut chars, mut quoted) = (String::new(), value.chars(), false);
+        while let Some(c) = chars.next() {
+            match c {
+                '\'' => quoted = !quoted,
+                '\\' if !quoted => unescaped.extend(chars.next()),
+                c => unescaped.push(c),
+            }
+        }
+        unescaped
+    }
+
+    #[test]
+    fn test_subtitles_filter() {
+        let cases = [
+            ("captions.srt", "subtitles='captions.srt'"),
+            ("my captions.srt", "subtitles='my captions.srt'"),
+            ("clip 1: intro.srt", r"subtitles='clip 1\: intro.srt'"),
+            (
+                r"C:\Users\me\captions.srt",
+                r"subtitles='C\:\\Users\\me\\captions.srt'",
+            ),
+            ("it's.srt", r"subtitles='it\'\''s.srt'"),
+            ("a,b;[c].srt", "subtitles='a,b;[c].srt'"),
+        ];
+        for (path, expected) in cases {
+            let filter = subtitles_filter(Path::new(path));
+            assert_eq!(filter, expected);
+            // NOTE: The filter graph's level, and then the filter options' level.
+            let value = filter.strip_prefix("subtitles=").unwrap();
+            assert_eq!(unescape(&unescape(value)), path);
+        }
+    }
+
+    #[test]
+    fn test_is_subtitles_error() {
+        for line in [
+            "[Parsed_subtitles_1 @ 0x5581c0a0c2c0] Unable to open captions.srt",
+            "[Parsed_subtitles_1 @ 0x5581c0a0c2c0] Unable to locate subtitle stream in captions.srt",
+            "[AVFilterGraph @ 0x5581c0a0c2c0] Error initializing filter 'subtitles' with args 'captions.srt'",
+        ] {
+            assert!(is_subtitles_error(line), "{}", line);
+        }
+        for line in [
+            "[Parsed_subtitles_1 @ 0x5581c0a0c2c0] libass API version: 0x1701000",
+            "[Parsed_subtitles_1 @ 0x5581c0a0c2c0] Using font provider fontconfig",
+            "[Parsed_crop_0 @ 0x5581c0a0c2c0] Unable to parse option value",
+        ] {
+            assert!(!is_subtitles_error(line), "{}", line);
+        }
+    }
+}
@@ -0,0 +1,468 @@
+//! Helpers shared by the unit tests of the different modules.
+
+use crate::{Command, CommandSender, Converter, Message, Settings, Summary};
+
+/// The path of the sample video bundled with the repository.
+pub(crate) const SAMPLE_VIDEO_PATH: &str = "./assets/big-buck-bunny-clip.mp4";
+
+/// A logger that forwards the records to `env_logger`, and also keeps them
+/// for the tests that need to inspect them (see [`captured_logs`]).
+struct CapturingLogger {
+    inner: env_logger::Logger,
+    /// The target, message and key-values of each record.
+    records: std::sync::Mutex<Vec<CapturedLog>>,
+}
+
+#[derive(Debug, Clone)]
+/// A log record captured by the [`CapturingLogger`].
+pub(crate) struct CapturedLog {
+    pub(crate) target: String,
+    pub(crate) message: String,
+    /// The record's key-values, formatted using [`std::fmt::Display`].
+    pub(crate) fields: Vec<(String, String)>,
+}
+
+/// Collects the key-values of a log record.
+struct FieldVisitor(Vec<(String, String)>);
+
+impl<'kvs> log::kv::VisitSource<'kvs> for FieldVisitor {
+    fn visit_pair(
+        &mut self,
+        key: log::kv::Key<'kvs>,
+        value: log::kv::Value<'kvs>,
+    ) -> Result<(), log::kv::Error> {
+        self.0.push((key.to_string(), value.to_string()));
+        Ok(())
+    }
+}
+
+impl log::Log for CapturingLogger {
+    fn enabled(&self, metadata: &log::Metadata) -> bool {
+        self.inner.enabled(metadata)
+    }
+
+    fn log(&self, record: &log::Record) {
+        if !self.inner.enabled(record.metadata()) {
+            return;
+        }
+        let mut fields = FieldVisitor(vec![]);
+        let _ = record.key_values().visit(&mut fields);
+        self.records
+            .lock()
+            .unwrap_or_else(|e| e.into_inner())
+            .push(CapturedLog {
+                target: record.target().into(),
+                message: record.args().to_string(),
+                fields: fields.0,
+            });
+        self.inner.log(record);
+    }
+
+    fn flush(&self) {
+        self.inner.flush();
+    }
+}
+
+static LOGGER: std::sync::OnceLock<CapturingLogger> = std::sync::OnceLock::new();
+
+pub(crate) fn init_logging() {
+    std::env::set_var("RUST_LOG", "debug");
+    let logger = LOGGER.get_or_init(|| CapturingLogger {
+        inner: env_logger::builder().is_test(true).build(),
+        records: Default::default(),
+    });
+    if log::set_logger(logger).is_ok() {
+        log::set_max_level(logger.inner.filter());
+    }
+}
+
+/// Returns the log records captured so far (by all the tests, which run
+/// concurrently) for which `filter` returns `true`.
+pub(crate) fn captured_logs(filter: impl Fn(&CapturedLog) -> bool) -> Vec<CapturedLog> {
+    let Some(logger) = LOGGER.get() else {
+        return vec![];
+    };
+    logger
+        .records
+        .lock()
+        .unwrap_or_else(|e| e.into_inner())
+        .iter()
+        .filter(|log| filter(log))
+        .cloned()
+        .collect()
+}
+
+/// Whether an `ffmpeg` binary can be found on the system path. Tests
+/// that need to run a real conversion return early (after logging a
+/// warning) when this is not the case.
+pub(crate) fn ffmpeg_available() -> bool {
+    let available = std::process::Command::new("ffmpeg")
+        .arg("-version")
+        .stdin(std::process::Stdio::null())
+        .stdout(std::process::Stdio::null())
+        .stderr(std::process::Stdio::null())
+        .status()
+        .map(|s| s.success())
+        .unwrap_or(false);
+    if !available {
+        log::warn!("FFmpeg not found on system path, so skipping end-to-end test.");
+    }
+    available
+}
+
+/// Runs the conversion job described by `settings` on a separate thread and
+/// collects all the messages emitted by the [`Converter`] until (and including)
+/// [`Message::Done`].
+pub(crate) fn run_to_completion(settings: Settings) -> Vec<Message> {
+    run_to_completion_with(settings, |tx| {
+        // NOTE: Keeping the command sender alive until the end of the job.
+        std::thread::sleep(std::time::Duration::from_secs(60));
+        drop(tx);
+    })
+}
+
+/// Sends `command` down the [`CommandSender`], returning `false` if the channel is closed,
+/// whichever kind of channel is used (see the `tokio` and `async-channel` feature flags).
+pub(crate) fn send_command(tx: &CommandSender, command: Command) -> bool {
+    #[cfg(not(feature = "async-channel"))]
+    return tx.send(command).is_ok();
+    #[cfg(feature = "async-channel")]
+    return tx.send_blocking(command).is_ok();
+}
+
+/// Same as [`run_to_completion`], but also calls `commands` (on yet another thread)
+/// with the [`CommandSender`], which allows sending commands to the [`Converter`].
+pub(crate) fn run_to_completion_with(
+    settings: Settings,
+    commands: impl FnOnce(CommandSender) + Send + 'static,
+) -> Vec<Message> {
+    run_job_to_completion_with(move |converter| converter.convert(settings), commands)
+}
+
+/// Same as [`run_to_completion_with`], but runs an arbitrary `job` (e.g.
+/// [`Converter::extract_thumbnail_strip`]) instead of [`Converter::convert`].
+pub(crate) fn run_job_to_completion_with(
+    job: impl FnOnce(Converter) + Send + 'static,
+    commands: impl FnOnce(CommandSender) + Send + 'static,
+) -> Vec<Message> {
+    run_job_to_completion_timed(job, commands)
+        .into_iter()
+        .map(|(_, message)| message)
+        .collect()
+}
+
+/// Same as [`run_to_completion`], but also returns the time at which each message
+/// was received (relative to the start of the job).
+pub(crate) fn run_to_completion_timed(settings: Settings) -> Vec<(std::time::Duration, Message)> {
+    run_job_to_completion_timed(
+        move |converter| converter.convert(settings),
+        |tx| {
+            std::thread::sleep(std::time::Duration::from_secs(60));
+            drop(tx);
+        },
+    )
+}
+
+/// Same as [`run_job_to_completion_with`], but also returns the time at which each
+/// message was received (relative to the start of the job).
+fn run_job_to_completion_timed(
+    job: impl FnOnce(Converter) + Send + 'static,
+    commands: impl FnOnce(CommandSender) + Send + 'static,
+) -> Vec<(std::time::Duration, Message)> {
+    #[cfg(not(feature = "tokio"))]
+    let (converter, tx, rx) = Converter::new_with_channels();
+    #[cfg(feature = "tokio")]
+    let (converter, tx, mut rx) = Converter::new_with_channels();
+
+    let started = std::time::Instant::now();
+    let handle = std::thread::spawn(move || job(converter));
+    std::thread::spawn(move || commands(tx));
+
+    let mut messages = vec![];
+    loop {
+        #[cfg(not(any(feature = "tokio", feature = "async-channel")))]
+        let message = rx.recv().ok();
+        #[cfg(feature = "tokio")]
+        let message = rx.blocking_recv();
+        #[cfg(feature = "async-channel")]
+        let message = rx.recv_blocking().ok();
+        match message {
+            Some(Message::Done) => {
+                messages.push((started.elapsed(), Message::Done));
+                break;
+            }
+            Some(message) => messages.push((started.elapsed(), message)),
+            None => break,
+        }
+    }
+
+    handle.join().expect("Failed to join converter thread");
+    messages
+}
+
+/// Returns the GIF bytes contained in the [`Message::Success`] message, if any.
+pub(crate) fn success_bytes(messages: &[Message]) -> Option<&[u8]> {
+    messages.iter().find_map(|m| match m {
+        Message::Success(bytes) => Some(&bytes[..]),
+        _ => None,
+    })
+}
+
+/// Returns the [`Summary`] found right before the final [`Message::Done`] message,
+/// making sure that the messages end that way.
+pub(crate) fn final_summary(messages: &[Message]) -> Summary {
+    match messages {
+        [.., Message::Summary(summary), Message::Done] => summary.clone(),
+        _ => panic!(
+            "Expected messages to end with 'Summary' and 'Done': {:?}",
+            messages
+        ),
+    }
+}
+
+/// Builds a small (1x1 pixel) but structurally valid animated GIF with `frames`
+/// frames and, if `loop_count` is provided, a `NETSCAPE2.0` application extension.
+pub(crate) fn sample_gif(frames: usize, loop_count: Option<u16>) -> Vec<u8> {
+    let mut bytes = b"GIF89a".to_vec();
+    // Logical screen descriptor (1x1, global color table with 2 colors).
+    bytes.extend_from_slice(&[0x01, 0x00, 0x01, 0x00, 0x80, 0x00, 0x00]);
+    bytes.extend_from_slice(&[0xff, 0xff, 0xff, 0x00, 0x00, 0x00]);
+    if let Some(n) = loop_count {
+        bytes.extend_from_slice(&[0x21, 0xff, 0x0b]);
+        bytes.extend_from_slice(b"NETSCAPE2.0");
+        bytes.extend_from_slice(&[0x03, 0x01]);
+        bytes.extend_from_slice(&n.to_le_bytes());
+        bytes.push(0x00);
+    }
+    for _ in 0..frames {
+        // Graphic control extension (10 centiseconds delay).
+        bytes.extend_from_slice(&[0x21, 0xf9, 0x04, 0x00, 0x0a, 0x00, 0x00, 0x00]);
+        // Image descriptor and image data.
+        bytes.extend_from_slice(&[0x2c, 0x00, 0x00, 0x00, 0x00, 0x01, 0x00, 0x01, 0x00, 0x00]);
+        bytes.extend_from_slice(&[0x02, 0x02, 0x44, 0x01, 0x00]);
+    }
+    bytes.push(0x3b);
+    bytes
+}
+
+/// Builds a structurally valid (but not decodable) PNG image.
+pub(crate) fn sample_png(payload: &[u8]) -> Vec<u8> {
+    let mut bytes = b"\x89PNG\r\n\x1a\n".to_vec();
+    for (kind, data) in [
+        (&b"IHDR"[..], &[0u8; 13][..]),
+        (b"IDAT", payload),
+        (b"IEND", &[]),
+    ] {
+        bytes.extend_from_slice(&(data.len() as u32).to_be_bytes());
+        bytes.extend_from_slice(kind);
+        bytes.extend_from_slice(data);
+        bytes.extend_from_slice(&[0, 0, 0, 0]);
+    }
+    bytes
+}
+
+/// A transcript similar to what FFmpeg writes to `stderr` when converting the sample video.
+pub(crate) const SAMPLE_STDERR: &str = "ffmpeg version 6.0-tessus  https://evermeet.cx/ffmpeg/  Copyright (c) 2000-2023 the FFmpeg developers
+Input #0, mov,mp4,m4a,3gp,3g2,mj2, from './assets/big-buck-bunny-clip.mp4':
+  Metadata:
+    major_brand     : isom
+  Duration: 00:00:05.00, start: 0.000000, bitrate: 1785 kb/s
+  Stream #0:0[0x1](und): Video: h264 (High) (avc1 / 0x31637661), yuv420p(progressive), 640x360 [SAR 1:1 DAR 16:9], 1538 kb/s, 24 fps, 24 tbr, 12288 tbn (default)
+Stream mapping:
+  Stream #0:0 (h264) -> fps:default
+  paletteuse:default -> Stream #0:0 (gif)
+Output #0, gif, to 'pipe:':
+  Stream #0:0: Video: gif, pal8(pc, gbr/unknown/unknown, progressive), 200x112 [SAR 1:1 DAR 25:14], q=2-31, 200 kb/s, 10 fps, 100 tbn
+frame=   20 fps=0.0 q=-0.0 size=       0kB time=00:00:02.00 bitrate=   0.0kbits/s speed=3.91x
+frame=   50 fps= 48 q=-0.0 Lsize=     310kB time=00:00:04.90 bitrate= 518.2kbits/s speed=4.71x
+";
+
+/// Same as [`SAMPLE_STDERR`], but with a `Duration` line in a format that is not understood
+/// (e.g. as written by a future FFmpeg version).
+pub(crate) const STDERR_RENAMED_DURATION: &str =
+    "ffmpeg version 9.0  Copyright (c) 2000-2030 the FFmpeg developers
+Input #0, mov,mp4,m4a,3gp,3g2,mj2, from './assets/big-buck-bunny-clip.mp4':
+  Duration: 5.00 s, start: 0.000000, bitrate: 1785 kb/s
+frame=   20 fps=0.0 q=-0.0 size=       0kB time=00:00:02.00 bitrate=   0.0kbits/s speed=3.91x
+frame=   50 fps= 48 q=-0.0 Lsize=     310kB time=00:00:04.90 bitrate= 518.2kbits/s speed=4.71x
+";
+
+/// Same as [`SAMPLE_STDERR`], but with stats lines whose `time=` value is in a format that
+/// is not understood (the first one, written before any frame is processed, being valid).
+pub(crate) const STDERR_RENAMED_TIME: &str =
+    "ffmpeg version 9.0  Copyright (c) 2000-2030 the FFmpeg developers
+Input #0, mov,mp4,m4a,3gp,3g2,mj2, from './assets/big-buck-bunny-clip.mp4':
+  Duration: 00:00:05.00, start: 0.000000, bitrate: 1785 kb/s
+frame=    0 fps=0.0 q=0.0 size=       0kB time=N/A bitrate=N/A speed=N/A
+frame=   20 fps=0.0 q=-0.0 size=       0kB time=2000ms bitrate=   0.0kbits/s speed=3.91x
+frame=   50 fps= 48 q=-0.0 Lsize=     310kB time=4900ms bitrate= 518.2kbits/s speed=4.71x
+";
+
+#[cfg(unix)]
+/// Writes a shell script that stands in for the FFmpeg binary: it writes `stderr`
+/// to its `stderr`, then `stdout` to its `stdout`, and exits with `exit_code`. The
+/// returned path can be passed to [`Settings::ffmpeg_path`].
+pub(crate) fn fake_ffmpeg(stderr: &str, stdout: &[u8], exit_code: i32) -> std::path::PathBuf {
+    fake_ffmpeg_with_script(stderr, stdout, &format!("exit {}", exit_code))
+}
+
+#[cfg(unix)]
+/// Same as [`fake_ffmpeg`], but runs `script` (instead of simply exiting) after
+/// having written the `stderr` and `stdout` data.
+pub(crate) fn fake_ffmpeg_with_script(
+    stderr: &str,
+    stdout: &[u8],
+    script: &str,
+) -> std::path::PathBuf {
+    let dir = temp_dir();
+    std::fs::write(dir.join("stderr.txt"), stderr).unwrap();
+    std::fs::write(dir.join("stdout.bin"), stdout).unwrap();
+    write_script(
+        &dir,
+        &format!(
+            "cat '{dir}/stderr.txt' >&2\ncat '{dir}/stdout.bin'\n{script}",
+            dir = dir.display(),
+            script = script
+        ),
+    )
+}
+
+#[cfg(unix)]
+/// Writes a shell script that stands in for the FFmpeg binary: it writes the header
+/// of [`SAMPLE_STDERR`] (i.e. a 5 seconds long video), then `steps` evenly spaced
+/// progress lines, `interval` seconds apart, and finally a valid GIF to `stdout`.
+pub(crate) fn fake_ffmpeg_with_progress(steps: u32, interval: f64) -> std::path::PathBuf {
+    fake_ffmpeg_with_progress_script(steps, interval, "", "")
+}
+
+#[cfg(unix)]
+/// Same as [`fake_ffmpeg_with_progress`], but also runs `step_script` after each progress
+/// line (e.g. to write partial output), and `script` after all of them (but before the GIF).
+pub(crate) fn fake_ffmpeg_with_progress_script(
+    steps: u32,
+    interval: f64,
+    step_script: &str,
+    script: &str,
+) -> std::path::PathBuf {
+    // NOTE: The progress lines need to be written separately, since the STDERR
+    // thread expects each read to start with them.
+    let (header, _) = SAMPLE_STDERR.split_once("frame=").unwrap();
+    let dir = temp_dir();
+    std::fs::write(dir.join("header.txt"), header).unwrap();
+    std::fs::write(dir.join("stdout.bin"), sample_gif(2, Some(0))).unwrap();
+    let progress_lines: String = (1..=steps)
+        .map(|i| {
+            format!(
+                "sleep {}; printf 'frame= {} fps=0.0 q=-0.0 size= 0kB time=00:00:{:05.2} bitrate= 0.0kbits/s speed=1x\\r' >&2\n{}\n",
+                interval,
+                i,
+                5.0 * i as f64 / steps as f64,
+                step_script
+            )
+        })
+        .collect();
+    write_script(
+        &dir,
+        &format!(
+            "cat '{dir}/header.txt' >&2\n{progress_lines}{script}\ncat '{dir}/stdout.bin'",
+            dir = dir.display()
+        ),
+    )
+}
+
+#[cfg(unix)]
+/// Writes an executable shell script named `ffmpeg`, whose body is `body`, in `dir`.
+/// Any file the script relies on can be written to `dir` beforehand.
+pub(crate) fn write_script(dir: &std::path::Path, body: &str) -> std::path::PathBuf {
+    use std::os::unix::fs::PermissionsExt;
+
+    let path = dir.join("ffmpeg");
+    std::fs::write(&path, format!("#!/bin/sh\n{}\n", body)).unwrap();
+    std::fs::set_permissions(&path, std::fs::Permissions::from_mode(0o755)).unwrap();
+    path
+}
+
+/// Uses the real FFmpeg binary to generate a (lossless) video file from the
+/// `lavfi` `source` (e.g. `color=c=red:s=64x64:d=2`), returning its path.
+pub(crate) fn generate_lavfi_video(source: &str) -> std::path::PathBuf {
+    let path = temp_dir().join("input.mkv");
+    let status = std::process::Command::new("ffmpeg")
+        .args([
+            "-nostdin",
+            "-loglevel",
+            "error",
+            "-f",
+            "lavfi",
+            "-i",
+            source,
+        ])
+        .args(["-c:v", "ffv1"])
+        .arg(&path)
+        .status()
+        .expect("Failed to run FFmpeg");
+    assert!(
+        status.success(),
+        "Failed to generate video from '{}'",
+        source
+    );
+    path
+}
+
+/// Creates a new, empty, uniquely named directory inside the system's temporary directory.
+pub(crate) fn temp_dir() -> std::path::PathBuf {
+    let dir = std::env::temp_dir()
+        .join("ffmpeg_gif_maker_tests")
+        .join(uuid::Uuid::new_v4().to_string());
+    std::fs::create_dir_all(&dir).unwrap();
+    dir
+}
+
+#[derive(Debug, Clone, Default)]
+/// A writer whose bytes can be read back through any of its clones (e.g. once moved
+/// into [`crate::Converter::convert_to_writer`]).
+pub(crate) struct SharedWriter(std::sync::Arc<std::sync::Mutex<Vec<u8>>>);
+
+impl SharedWriter {
+    /// The bytes written so far.
+    pub(crate) fn bytes(&self) -> Vec<u8> {
+        self.0.lock().unwrap().clone()
+    }
+}
+
+impl std::io::Write for SharedWriter {
+    fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> {
+        self.0.lock().unwrap().extend_from_slice(buf);
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> std::io::Result<()> {
+        Ok(())
+    }
+}
+
+#[derive(Debug)]
+/// A writer that accepts `remaining` bytes, and then fails (e.g. like a dropped connection).
+pub(crate) struct FailingWriter {
+    remaining: usize,
+}
+
+impl FailingWriter {
+    pub(crate) fn new(remaining: usize) -> Self {
+        Self { remaining }
+    }
+}
+
+impl std::io::Write for FailingWriter {
+    fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> {
+        if self.remaining == 0 {
+            return Err(std::io::ErrorKind::ConnectionReset.into());
+        }
+        let n = buf.len().min(self.remaining);
+        self.remaining -= n;
+        Ok(n)
+    }
+
+    fn flush(&mut self) -> std::io::Result<()> {
+        Ok(())
+    }
+}
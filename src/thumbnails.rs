@@ -0,0 +1,354 @@
+//! The thumbnail strip extraction job (see [`crate::Converter::extract_thumbnail_strip`]),
+//! which lets applications show a few evenly spaced frames of the source video (e.g. to
+//! help users pick a trim range) before running the conversion itself.
+//!
+//! The video's duration is first probed using a short-lived FFmpeg child process (the
+//! same `Duration: ...` line that the conversion job parses), after which a single FFmpeg
+//! child process resamples the video to `count` frames over its duration and writes
+//! them to `stdout` as a sequence of images. The images are split apart as they arrive,
+//! so that each of them can be sent to the application as soon as it is complete.
+
+use std::time::Duration;
+
+use crate::{FfmpegLocation, SettingsError};
+
+const LOG_TARGET: &str = "ffmpeg_gif_maker::thumbnails";
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+/// The image format of the thumbnails extracted by [`crate::Converter::extract_thumbnail_strip`].
+pub enum ThumbnailFormat {
+    /// Baseline JPEG images (the smallest option).
+    #[default]
+    Jpeg,
+    /// Lossless PNG images.
+    Png,
+}
+
+#[derive(Clone, Debug)]
+/// The settings for [`crate::Converter::extract_thumbnail_strip`].
+pub struct StripSettings {
+    /// The location of the FFmpeg binary on the system.
+    pub(crate) ffmpeg_location: Option<FfmpegLocation>,
+    /// The path of the video from which the thumbnails are extracted.
+    pub(crate) video_path: String,
+    /// The number of thumbnails to extract.
+    pub(crate) count: u16,
+    /// The thumbnails' width.
+    pub(crate) width: u16,
+    /// The thumbnails' image format.
+    pub(crate) format: ThumbnailFormat,
+}
+
+impl StripSettings {
+    /// A factory method that takes in the source `video_path`, the number of
+    /// thumbnails to extract (`count`), and their `width`.
+    pub fn new(video_path: String, count: u16, width: u16) -> Self {
+        Self {
+            ffmpeg_location: None,
+            video_path,
+            count,
+            width,
+            format: ThumbnailFormat::default(),
+        }
+    }
+
+    /// A setter method that allows specifying the path to be used
+    /// for the ffmpeg binary.
+    pub fn ffmpeg_path(self, ffmpeg_path: impl Into<String>) -> Self {
+        self.ffmpeg_location(FfmpegLocation::Path(ffmpeg_path.into()))
+    }
+
+    /// A setter method that allows specifying where to find the ffmpeg
+    /// binary (see [`crate::Settings::ffmpeg_location`]).
+    pub fn ffmpeg_location(self, ffmpeg_location: impl Into<FfmpegLocation>) -> Self {
+        Self {
+            ffmpeg_location: Some(ffmpeg_location.into()),
+            ..self
+        }
+    }
+
+    /// A setter method that allows specifying the thumbnails' image format
+    /// (JPEG by default).
+    pub fn format(self, format: ThumbnailFormat) -> Self {
+        Self { format, ..self }
+    }
+
+    /// Makes sure that the settings are valid, returning the first problem
+    /// found otherwise.
+    pub fn validate(&self) -> Result<(), SettingsError> {
+        if self.count == 0 {
+            return Err(SettingsError::ThumbnailCountZero);
+        }
+        Ok(())
+    }
+}
+
+/// The arguments passed to FFmpeg to probe the video's duration (without decoding it),
+/// where `input_args` are the arguments that make FFmpeg read the input (i.e. its `-i`
+/// flag, see [`crate::InputSource`], preceded by any input option).
+pub(crate) fn probe_args(input_args: Vec<String>) -> Vec<String> {
+    let mut args = vec!["-hide_banner".into(), "-nostdin".into()];
+    args.extend(input_args);
+    args
+}
+
+/// The arguments passed to FFmpeg to extract the thumbnail strip from a video
+/// lasting `duration`.
+pub(crate) fn strip_args(settings: &StripSettings, duration: Duration) -> Vec<String> {
+    let codec = match settings.format {
+        ThumbnailFormat::Jpeg => "mjpeg",
+        ThumbnailFormat::Png => "png",
+    };
+    vec![
+        "-nostdin".into(),
+        "-i".into(),
+        crate::argv::path_arg(&settings.video_path),
+        "-vf".into(),
+        format!(
+            "fps={}/{:.6},scale={}:-2",
+            settings.count,
+            duration.as_secs_f64(),
+            settings.width
+        ),
+        "-frames:v".into(),
+        settings.count.to_string(),
+        "-c:v".into(),
+        codec.into(),
+        "-f".into(),
+        "image2pipe".into(),
+        "-".into(),
+    ]
+}
+
+/// The timestamp of the `index`-th thumbnail (out of `count`) of a video lasting `duration`.
+pub(crate) fn thumbnail_timestamp(duration: Duration, count: u16, index: usize) -> Duration {
+    duration.mul_f64(index as f64 / count as f64)
+}
+
+const PNG_SIGNATURE: &[u8] = b"\x89PNG\r\n\x1a\n";
+const JPEG_SIGNATURE: &[u8] = &[0xff, 0xd8];
+
+/// Splits the sequence of images written by FFmpeg to `stdout` back into separate images.
+pub(crate) struct ImageSplitter {
+    format: ThumbnailFormat,
+    buffer: Vec<u8>,
+}
+
+impl ImageSplitter {
+    pub(crate) fn new(format: ThumbnailFormat) -> Self {
+        Self {
+            format,
+            buffer: vec![],
+        }
+    }
+
+    fn signature(&self) -> &'static [u8] {
+        match self.format {
+            ThumbnailFormat::Jpeg => JPEG_SIGNATURE,
+            ThumbnailFormat::Png => PNG_SIGNATURE,
+        }
+    }
+
+    /// Appends `chunk` to the internal buffer, and returns the images that are now complete.
+    pub(crate) fn push(&mut self, chunk: &[u8]) -> Vec<Vec<u8>> {
+        self.buffer.extend_from_slice(chunk);
+        let mut images = vec![];
+        loop {
+            let signature = self.signature();
+            if self.buffer.len() < signature.len() {
+                break;
+            }
+            if !self.buffer.starts_with(signature) {
+                // NOTE: This should not happen with FFmpeg's output, but in case it
+                // does, the data is skipped until the beginning of the next image.
+                let skip = find(&self.buffer[1..], signature)
+                    .map(|i| i + 1)
+                    .unwrap_or(self.buffer.len() + 1 - signature.len());
+                log::warn!(target: LOG_TARGET, "Skipping {} bytes of unexpected data.", skip);
+                self.buffer.drain(..skip);
+                continue;
+            }
+            let len = match self.format {
+                ThumbnailFormat::Jpeg => jpeg_len(&self.buffer),
+                ThumbnailFormat::Png => png_len(&self.buffer),
+            };
+            match len {
+                Some(len) => images.push(self.buffer.drain(..len).collect()),
+                None => break,
+            }
+        }
+        images
+    }
+
+    /// The number of buffered bytes that do not (yet) make up a complete image.
+    pub(crate) fn pending(&self) -> usize {
+        self.buffer.len()
+    }
+}
+
+fn find(haystack: &[u8], needle: &[u8]) -> Option<usize> {
+    haystack.windows(needle.len()).position(|w| w == needle)
+}
+
+/// The length of the complete PNG image at the beginning of `bytes`, if any.
+fn png_len(bytes: &[u8]) -> Option<usize> {
+    let mut offset = PNG_SIGNATURE.len();
+    loop {
+        let header = bytes.get(offset..offset + 8)?;
+        let len = u32::from_be_bytes([header[0], header[1], header[2], header[3]]) as usize;
+        // Length, type, data and CRC.
+        let end = offset.checked_add(12)?.checked_add(len)?;
+        if bytes.len() < end {
+            return None;
+        }
+        if &header[4..] == b"IEND" {
+            return Some(end);
+        }
+        offset = end;
+    }
+}
+
+/// The length of the complete JPEG image at the beginning of `bytes`, if any.
+///
+/// NOTE: The marker segments are walked one by one (instead of simply looking
+/// for the end-of-image marker), since their payload (e.g. the quantization
+/// tables) could contain the end-of-image marker's bytes.
+fn jpeg_len(bytes: &[u8]) -> Option<usize> {
+    let mut offset = JPEG_SIGNATURE.len();
+    loop {
+        if *bytes.get(offset)? != 0xff {
+            // NOTE: Not a marker, so the image is malformed; treating what has
+            // been read so far as the image, so that the splitter can move on.
+            log::warn!(target: LOG_TARGET, "Malformed JPEG data found at offset {}.", offset);
+            return Some(offset);
+        }
+        let marker = *bytes.get(offset + 1)?;
+        match marker {
+            // Fill byte.
+            0xff => offset += 1,
+            // End of image.
+            0xd9 => return Some(offset + 2),
+            // Markers without a payload.
+            0x01 | 0xd0..=0xd8 => offset += 2,
+            _ => {
+                let len = u16::from_be_bytes([*bytes.get(offset + 2)?, *bytes.get(offset + 3)?]);
+                offset += 2 + len as usize;
+                if marker == 0xda {
+                    // Start of scan, which is followed by the entropy-coded data, in which
+                    // `0xff` bytes are either stuffed (i.e. followed by `0x00`) or part of
+                    // a restart marker.
+                    loop {
+                        let byte = *bytes.get(offset)?;
+                        let next = *bytes.get(offset + 1)?;
+                        if byte == 0xff && next != 0x00 && !(0xd0..=0xd7).contains(&next) {
+                            break;
+                        }
+                        offset += 1;
+                    }
+                }
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_utils::sample_png;
+
+    /// Builds a structurally valid (but not decodable) JPEG image, whose
+    /// quantization table contains the end-of-image marker's bytes.
+    fn sample_jpeg(scan: &[u8]) -> Vec<u8> {
+        let mut bytes = JPEG_SIGNATURE.to_vec();
+        bytes.extend_from_slice(&[0xff, 0xdb, 0x00, 0x06, 0x00, 0xff, 0xd9, 0x01]);
+        bytes.extend_from_slice(&[0xff, 0xda, 0x00, 0x03, 0x01]);
+        bytes.extend_from_slice(scan);
+        bytes.extend_from_slice(&[0xff, 0xd9]);
+        bytes
+    }
+
+    fn split_in_chunks(format: ThumbnailFormat, data: &[u8], chunk: usize) -> Vec<Vec<u8>> {
+        let mut splitter = ImageSplitter::new(format);
+        let images = data.chunks(chunk).flat_map(|c| splitter.push(c)).collect();
+        assert_eq!(splitter.pending(), 0);
+        images
+    }
+
+    #[test]
+    fn test_split_png() {
+        let images = [sample_png(b"first"), sample_png(b"second"), sample_png(b"")];
+        let data = images.concat();
+        for chunk in [1, 7, 64, data.len()] {
+            assert_eq!(split_in_chunks(ThumbnailFormat::Png, &data, chunk), images);
+        }
+    }
+
+    #[test]
+    fn test_split_jpeg() {
+        let images = [
+            sample_jpeg(&[1, 2, 3]),
+            // Stuffed `0xff` bytes and a restart marker.
+            sample_jpeg(&[0xff, 0x00, 4, 0xff, 0xd0, 5, 0xff, 0x00]),
+            sample_jpeg(&[]),
+        ];
+        let data = images.concat();
+        for chunk in [1, 5, 64, data.len()] {
+            assert_eq!(split_in_chunks(ThumbnailFormat::Jpeg, &data, chunk), images);
+        }
+    }
+
+    #[test]
+    fn test_split_incomplete() {
+        let image = sample_png(b"data");
+        let mut splitter = ImageSplitter::new(ThumbnailFormat::Png);
+        assert!(splitter.push(&image[..image.len() - 1]).is_empty());
+        assert_eq!(splitter.pending(), image.len() - 1);
+        assert_eq!(splitter.push(&image[image.len() - 1..]), vec![image]);
+    }
+
+    #[test]
+    fn test_split_skips_garbage() {
+        let image = sample_png(b"data");
+        let mut data = b"garbage".to_vec();
+        data.extend_from_slice(&image);
+        assert_eq!(split_in_chunks(ThumbnailFormat::Png, &data, 3), vec![image]);
+    }
+
+    #[test]
+    fn test_strip_args() {
+        let settings = StripSettings::new("video.mp4".into(), 5, 120);
+        let args = strip_args(&settings, Duration::from_millis(4900));
+        assert_eq!(args[4], "fps=5/4.900000,scale=120:-2");
+        assert_eq!(args[6], "5");
+        assert_eq!(args[8], "mjpeg");
+        let args = strip_args(
+            &settings.format(ThumbnailFormat::Png),
+            Duration::from_secs(2),
+        );
+        assert_eq!(args[8], "png");
+    }
+
+    #[test]
+    fn test_thumbnail_timestamp() {
+        let duration = Duration::from_secs(10);
+        let timestamps: Vec<_> = (0..4)
+            .map(|i| thumbnail_timestamp(duration, 4, i))
+            .collect();
+        assert_eq!(
+            timestamps,
+            [0, 2500, 5000, 7500].map(Duration::from_millis).to_vec()
+        );
+    }
+
+    #[test]
+    fn test_validate() {
+        assert_eq!(
+            StripSettings::new("video.mp4".into(), 1, 100).validate(),
+            Ok(())
+        );
+        assert_eq!(
+            StripSettings::new("video.mp4".into(), 0, 100).validate(),
+            Err(SettingsError::ThumbnailCountZero)
+        );
+    }
+}
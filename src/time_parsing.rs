@@ -4,12 +4,18 @@ use std::time::Duration;
 // parsing the output of `ffmpeg version 5.0-tessus`, but they
 // could definitely use more testing.
 
+#[allow(clippy::redundant_static_lifetimes)]
 const LOG_TARGET_FN_DURATION: &'static str = "ffmpeg_gif_maker::time_parser::fn_duration";
+#[allow(clippy::redundant_static_lifetimes)]
 const LOG_TARGET_FN_TRY_TIME: &'static str = "ffmpeg_gif_maker::time_parser::fn_try_extract_time";
+#[allow(clippy::redundant_static_lifetimes)]
 const LOG_TARGET_FN_TRY_DURATION: &'static str =
     "ffmpeg_gif_maker::time_parser::fn_try_extract_duration";
 
-fn duration_from_ffmpeg_time_string(s: &str, logging_identifier: Option<&str>) -> Option<Duration> {
+pub(crate) fn duration_from_ffmpeg_time_string(
+    s: &str,
+    logging_identifier: Option<&str>,
+) -> Option<Duration> {
     // Expected format:  HH:mm:ss.ms (e.g. 00:00:04.91)
 
     let id = logging_identifier
@@ -31,15 +37,12 @@ fn duration_from_ffmpeg_time_string(s: &str, logging_identifier: Option<&str>) -
         return None;
     }
 
-    let Ok(milliseconds) = dot_splitted[1].parse::<u64>() else {
-        log::debug!(target: LOG_TARGET_FN_DURATION, "{}Failed parse milliseconds.", id);
+    // NOTE: The digits are a decimal fraction (e.g. `.91` is 910 milliseconds).
+    let Some(fraction) = parse_fraction(&dot_splitted[1]) else {
+        log::debug!(target: LOG_TARGET_FN_DURATION, "{}Failed parse fraction of second.", id);
         return None;
     };
-    if milliseconds >= 1000 {
-        log::debug!(target: LOG_TARGET_FN_DURATION, "{}Milliseconds greater than 1000? (value = {}).", id, milliseconds);
-        return None;
-    }
-    log::debug!(target: LOG_TARGET_FN_DURATION, "{}Milliseconds successfully parsed: {}", id, milliseconds);
+    log::debug!(target: LOG_TARGET_FN_DURATION, "{}Fraction of second successfully parsed: {:?}", id, fraction);
 
     let Ok(seconds) = colon_splitted[2].parse::<u64>() else {
         log::debug!(target: LOG_TARGET_FN_DURATION, "{}Failed parse seconds.", id);
@@ -67,15 +70,173 @@ fn duration_from_ffmpeg_time_string(s: &str, logging_identifier: Option<&str>) -
     };
     log::debug!(target: LOG_TARGET_FN_DURATION, "{}Hours successfully parsed: {}", id, hours);
 
-    let total = milliseconds + (seconds * 1000) + (minutes * 60 * 1000) + (hours * 60 * 60 * 1000);
-    log::debug!(target: LOG_TARGET_FN_DURATION, "{}Total duration in milliseconds: {}", id, total);
-
-    let duration = Duration::from_millis(total);
+    let Some(duration) = hours
+        .checked_mul(60 * 60)
+        .and_then(|h| h.checked_add(minutes * 60 + seconds))
+        .map(|s| Duration::from_secs(s) + fraction)
+    else {
+        log::debug!(target: LOG_TARGET_FN_DURATION, "{}Duration too large.", id);
+        return None;
+    };
     log::debug!(target: LOG_TARGET_FN_DURATION, "{}Duration instance: {:?}", id, duration);
 
     Some(duration)
 }
 
+/// Parses the digits following the decimal point of a number of seconds (e.g. `91` in
+/// `04.91`, which is 910 milliseconds) into a fraction of a second, ignoring the digits
+/// beyond the nanosecond.
+fn parse_fraction(digits: &str) -> Option<Duration> {
+    if digits.is_empty() || !digits.bytes().all(|b| b.is_ascii_digit()) {
+        return None;
+    }
+    let nanos = digits
+        .bytes()
+        .chain(std::iter::repeat(b'0'))
+        .take(9)
+        .fold(0u32, |nanos, digit| nanos * 10 + (digit - b'0') as u32);
+    Some(Duration::from_nanos(nanos as u64))
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+/// A problem found by [`parse_time_spec`].
+pub enum TimeSpecError {
+    /// The time spec is empty (or only made of whitespace).
+    Empty,
+    /// The time spec is negative (e.g. `-5`).
+    Negative,
+    /// The time spec does not match any of the supported formats (see [`parse_time_spec`]).
+    Malformed,
+    /// A field following the first one (i.e. the minutes or the seconds) is 60 or more.
+    FieldOutOfRange,
+    /// The time spec is too large to be represented as a [`Duration`].
+    Overflow,
+}
+
+impl std::error::Error for TimeSpecError {}
+
+impl std::fmt::Display for TimeSpecError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::Empty => write!(f, "empty time"),
+            Self::Negative => write!(f, "negative time"),
+            Self::Malformed => write!(
+                f,
+                "expected seconds, MM:SS or HH:MM:SS (e.g. 90, 1:30 or 00:01:30.5)"
+            ),
+            Self::FieldOutOfRange => write!(f, "minutes and seconds must be less than 60"),
+            Self::Overflow => write!(f, "time too large"),
+        }
+    }
+}
+
+impl From<std::convert::Infallible> for TimeSpecError {
+    fn from(e: std::convert::Infallible) -> Self {
+        match e {}
+    }
+}
+
+/// Parses a time spec typed by a user (leading and trailing whitespace being ignored), in
+/// one of the formats supported by FFmpeg for durations, i.e. either plain seconds (e.g.
+/// `90` or `90.5`), `MM:SS` (e.g. `1:30` or `01:30.5`) or `HH:MM:SS` (e.g. `00:01:30.5`).
+/// Only the seconds can have a decimal part, and the fields following the first one
+/// must be less than 60 (e.g. `90:00` is 90 minutes, but `1:90` is invalid).
+pub fn parse_time_spec(s: &str) -> Result<Duration, TimeSpecError> {
+    let s = s.trim();
+    if s.is_empty() {
+        return Err(TimeSpecError::Empty);
+    }
+    if s.starts_with('-') {
+        return Err(TimeSpecError::Negative);
+    }
+    let (whole, fraction) = match s.split_once('.') {
+        Some((whole, fraction)) => (
+            whole,
+            parse_fraction(fraction).ok_or(TimeSpecError::Malformed)?,
+        ),
+        None => (s, Duration::ZERO),
+    };
+    let fields: Vec<&str> = whole.split(':').collect();
+    if fields.len() > 3 {
+        return Err(TimeSpecError::Malformed);
+    }
+    let mut seconds: u64 = 0;
+    for (i, field) in fields.iter().enumerate() {
+        if field.is_empty() || !field.bytes().all(|b| b.is_ascii_digit()) {
+            return Err(TimeSpecError::Malformed);
+        }
+        let value: u64 = field.parse().map_err(|_| TimeSpecError::Overflow)?;
+        if i > 0 && value >= 60 {
+            return Err(TimeSpecError::FieldOutOfRange);
+        }
+        seconds = seconds
+            .checked_mul(if i > 0 { 60 } else { 1 })
+            .and_then(|s| s.checked_add(value))
+            .ok_or(TimeSpecError::Overflow)?;
+    }
+    // NOTE: Cannot overflow, since `Duration` can hold (almost) another second.
+    Ok(Duration::from_secs(seconds) + fraction)
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+/// A point in time (or a length) of the source video, as accepted by the setters that
+/// take a time (e.g. [`crate::Settings::trim`]), which can be created from either a
+/// [`Duration`] or a time spec typed by a user (see [`parse_time_spec`]), e.g. `"1:30"`.
+pub struct TimeSpec(Duration);
+
+impl TimeSpec {
+    /// The time, as a [`Duration`].
+    pub fn duration(&self) -> Duration {
+        self.0
+    }
+}
+
+impl From<Duration> for TimeSpec {
+    fn from(duration: Duration) -> Self {
+        Self(duration)
+    }
+}
+
+impl From<TimeSpec> for Duration {
+    fn from(spec: TimeSpec) -> Self {
+        spec.0
+    }
+}
+
+impl std::str::FromStr for TimeSpec {
+    type Err = TimeSpecError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        parse_time_spec(s).map(Self)
+    }
+}
+
+impl TryFrom<&str> for TimeSpec {
+    type Error = TimeSpecError;
+
+    fn try_from(s: &str) -> Result<Self, Self::Error> {
+        s.parse()
+    }
+}
+
+impl TryFrom<&String> for TimeSpec {
+    type Error = TimeSpecError;
+
+    fn try_from(s: &String) -> Result<Self, Self::Error> {
+        s.parse()
+    }
+}
+
+/// Converts a value accepted by the setters that take a time (see [`TimeSpec`]).
+pub(crate) fn time_spec<T>(value: T) -> Result<Duration, TimeSpecError>
+where
+    T: TryInto<TimeSpec>,
+    T::Error: Into<TimeSpecError>,
+{
+    value.try_into().map(Duration::from).map_err(Into::into)
+}
+
+#[allow(clippy::redundant_static_lifetimes)]
 pub(crate) fn try_extract_frame_time(
     s: &str,
     logging_identifier: Option<&str>,
@@ -106,6 +267,20 @@ pub(crate) fn try_extract_frame_time(
     duration_from_ffmpeg_time_string(&time, logging_identifier)
 }
 
+/// Whether the `time=` value of the last stats line found in `s` is negative (e.g.
+/// `time=-00:00:00.48`), which FFmpeg reports for the frames decoded before the start of the
+/// output when the input is seeked to a keyframe (see [`crate::SeekMode::Fast`]).
+pub(crate) fn has_negative_frame_time(s: &str) -> bool {
+    s.rsplit("\nframe=")
+        .next()
+        .and_then(|last| {
+            last.split_ascii_whitespace()
+                .find_map(|s| s.strip_prefix("time=-"))
+        })
+        .is_some_and(|time| duration_from_ffmpeg_time_string(time, None).is_some())
+}
+
+#[allow(clippy::redundant_static_lifetimes, clippy::needless_splitn)]
 pub(crate) fn try_extract_duration(s: &str, logging_identifier: Option<&str>) -> Option<Duration> {
     let id = logging_identifier
         .map(|s| format!("{} ", s))
@@ -129,6 +304,29 @@ pub(crate) fn try_extract_duration(s: &str, logging_identifier: Option<&str>) ->
     None
 }
 
+/// Same as [`try_extract_duration`], but for a single line of FFmpeg's output
+/// (e.g. `  Duration: 00:00:05.06, start: 0.000000, bitrate: 1785 kb/s`).
+pub(crate) fn try_extract_duration_from_line(
+    line: &str,
+    logging_identifier: Option<&str>,
+) -> Option<Duration> {
+    let rest = line.trim_start().strip_prefix("Duration: ")?;
+    let (time, _) = rest.split_once(", start: ")?;
+    duration_from_ffmpeg_time_string(time, logging_identifier)
+}
+
+/// Extracts the number of frames from the last `frame=` stats line found in `s`
+/// (e.g. `frame=   50 fps= 48 q=-0.0 Lsize=     310kB time=00:00:04.90 ...`).
+pub(crate) fn try_extract_frame_count(s: &str) -> Option<usize> {
+    let (_, last) = s.rsplit_once("frame=")?;
+    let digits: String = last
+        .trim_start()
+        .chars()
+        .take_while(|c| c.is_ascii_digit())
+        .collect();
+    digits.parse().ok()
+}
+
 pub(crate) fn progress_from_durations(total: Duration, processed: Duration) -> f64 {
     let total = total.as_millis() as f64;
     let processed = processed.as_millis() as f64;
@@ -139,11 +337,7 @@ pub(crate) fn progress_from_durations(total: Duration, processed: Duration) -> f
 #[cfg(test)]
 mod tests {
     use super::*;
-
-    fn init_logging() {
-        std::env::set_var("RUST_LOG", "debug");
-        let _ = env_logger::builder().is_test(true).try_init();
-    }
+    use crate::test_utils::init_logging;
 
     #[test]
     fn test_try_extract_duration() {
@@ -165,6 +359,7 @@ Input #0, mov,mp4,m4a,3gp,3g2,mj2, from 'assets/flower.mp4':
     }
 
     #[test]
+    #[allow(clippy::redundant_static_lifetimes)]
     fn test_try_extract_frame_time() {
         const FRAME_LINE: &'static str = r#"""
 frame=   50 fps=3.9 q=-0.0 Lsize=   23430kB time=00:00:04.91 bitrate=39091.3kbits/s speed=0.379x    
@@ -173,10 +368,151 @@ frame=   50 fps=3.9 q=-0.0 Lsize=   23430kB time=00:00:04.91 bitrate=39091.3kbit
         println!("{:?}", try_extract_frame_time(FRAME_LINE, None));
     }
 
+    #[test]
+    fn test_has_negative_frame_time() {
+        const NEGATIVE: &str =
+            "frame=    3 fps=0.0 q=-0.0 size=       0kB time=-00:00:00.48 bitrate=N/A speed=N/A";
+        assert!(has_negative_frame_time(NEGATIVE));
+        assert_eq!(try_extract_frame_time(NEGATIVE, None), None);
+        assert!(!has_negative_frame_time(
+            "frame=   50 fps=3.9 q=-0.0 Lsize=   23430kB time=00:00:04.91 bitrate=39091.3kbits/s"
+        ));
+        assert!(!has_negative_frame_time(
+            "frame=    0 fps=0.0 q=0.0 size=       0kB time=N/A bitrate=N/A speed=N/A"
+        ));
+        assert!(!has_negative_frame_time("time=-garbage"));
+    }
+
+    #[test]
+    fn test_try_extract_duration_from_line() {
+        assert_eq!(
+            try_extract_duration_from_line(
+                "  Duration: 00:00:05.00, start: 0.000000, bitrate: 1785 kb/s",
+                None
+            ),
+            Some(Duration::from_secs(5))
+        );
+        assert_eq!(
+            try_extract_duration_from_line("  Duration: N/A, start: 0.000000, bitrate: N/A", None),
+            None
+        );
+        assert_eq!(
+            try_extract_duration_from_line("Stream #0:0 -> Duration: 00:00:05.06", None),
+            None
+        );
+    }
+
+    #[test]
+    fn test_try_extract_frame_count() {
+        let s = "frame=   20 fps=0.0 q=-0.0 size=       0kB time=00:00:02.00\rframe=   50 fps= 48 q=-0.0 Lsize=     310kB time=00:00:04.90\n";
+        assert_eq!(try_extract_frame_count(s), Some(50));
+        assert_eq!(try_extract_frame_count("frame=7"), Some(7));
+        assert_eq!(try_extract_frame_count("no stats here"), None);
+        assert_eq!(try_extract_frame_count("frame=  "), None);
+    }
+
     #[test]
     fn test_duration_from_ffmpeg_time_string() {
-        let expected = Duration::from_millis(4 * 1000 + 91);
+        let expected = Duration::from_millis(4 * 1000 + 910);
         let calulcated = duration_from_ffmpeg_time_string("00:00:04.91", None).unwrap();
         assert_eq!(expected, calulcated);
+        assert_eq!(
+            duration_from_ffmpeg_time_string("01:02:03.5", None),
+            Some(Duration::from_millis(3_723_500))
+        );
+        assert_eq!(
+            duration_from_ffmpeg_time_string("00:00:00.05", None),
+            Some(Duration::from_millis(50))
+        );
+        assert_eq!(duration_from_ffmpeg_time_string("00:00:04.x1", None), None);
+        assert_eq!(
+            duration_from_ffmpeg_time_string("-577014:32:22.77", None),
+            None
+        );
+    }
+
+    #[test]
+    fn test_parse_fraction() {
+        let ms = Duration::from_millis;
+        assert_eq!(parse_fraction("5"), Some(ms(500)));
+        assert_eq!(parse_fraction("91"), Some(ms(910)));
+        assert_eq!(parse_fraction("05"), Some(ms(50)));
+        assert_eq!(parse_fraction("123"), Some(ms(123)));
+        assert_eq!(parse_fraction("0000001"), Some(Duration::from_nanos(100)));
+        assert_eq!(
+            parse_fraction("1234567891"),
+            Some(Duration::from_nanos(123_456_789))
+        );
+        assert_eq!(parse_fraction(""), None);
+        assert_eq!(parse_fraction("5s"), None);
+        assert_eq!(parse_fraction("+5"), None);
+    }
+
+    #[test]
+    fn test_parse_time_spec() {
+        let ms = Duration::from_millis;
+        let cases: &[(&str, Result<Duration, TimeSpecError>)] = &[
+            // Plain seconds.
+            ("0", Ok(Duration::ZERO)),
+            ("90", Ok(ms(90_000))),
+            ("90.5", Ok(ms(90_500))),
+            ("1.05", Ok(ms(1_050))),
+            ("0.125", Ok(ms(125))),
+            ("  90 ", Ok(ms(90_000))),
+            ("3600", Ok(ms(3_600_000))),
+            // MM:SS.
+            ("1:30", Ok(ms(90_000))),
+            ("01:30.5", Ok(ms(90_500))),
+            ("0:05", Ok(ms(5_000))),
+            ("90:00", Ok(ms(5_400_000))),
+            // HH:MM:SS.
+            ("00:01:30.5", Ok(ms(90_500))),
+            ("1:02:03", Ok(ms(3_723_000))),
+            ("00:00:04.91", Ok(ms(4_910))),
+            ("100:00:00", Ok(ms(360_000_000))),
+            // Errors.
+            ("", Err(TimeSpecError::Empty)),
+            ("   ", Err(TimeSpecError::Empty)),
+            ("-5", Err(TimeSpecError::Negative)),
+            ("-0:05", Err(TimeSpecError::Negative)),
+            ("+5", Err(TimeSpecError::Malformed)),
+            ("5s", Err(TimeSpecError::Malformed)),
+            ("1.5.5", Err(TimeSpecError::Malformed)),
+            (".5", Err(TimeSpecError::Malformed)),
+            ("5.", Err(TimeSpecError::Malformed)),
+            ("1:", Err(TimeSpecError::Malformed)),
+            (":30", Err(TimeSpecError::Malformed)),
+            ("1::30", Err(TimeSpecError::Malformed)),
+            ("1:2:3:4", Err(TimeSpecError::Malformed)),
+            ("1.5:30", Err(TimeSpecError::Malformed)),
+            ("1 30", Err(TimeSpecError::Malformed)),
+            ("1:60", Err(TimeSpecError::FieldOutOfRange)),
+            ("1:60:00", Err(TimeSpecError::FieldOutOfRange)),
+            ("1:00:60.5", Err(TimeSpecError::FieldOutOfRange)),
+            ("18446744073709551616", Err(TimeSpecError::Overflow)),
+            ("18446744073709551615:00", Err(TimeSpecError::Overflow)),
+            ("307445734561825861:00:00", Err(TimeSpecError::Overflow)),
+            (
+                "18446744073709551615.5",
+                Ok(Duration::from_secs(u64::MAX) + ms(500)),
+            ),
+        ];
+        for (spec, expected) in cases {
+            assert_eq!(parse_time_spec(spec), *expected, "{:?}", spec);
+        }
+    }
+
+    #[test]
+    fn test_time_spec_conversions() {
+        let secs = Duration::from_secs;
+        assert_eq!(time_spec(secs(5)), Ok(secs(5)));
+        assert_eq!(time_spec("1:30"), Ok(secs(90)));
+        assert_eq!(time_spec(&String::from("90")), Ok(secs(90)));
+        assert_eq!(time_spec(TimeSpec::from(secs(1))), Ok(secs(1)));
+        assert_eq!(time_spec("soon"), Err(TimeSpecError::Malformed));
+        assert_eq!(
+            "1:30".parse::<TimeSpec>().map(|t| t.duration()),
+            Ok(secs(90))
+        );
     }
 }
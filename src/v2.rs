@@ -0,0 +1,445 @@
+//! The next shape of the [`crate::Message`]'s sent by the [`crate::Converter`] (only public
+//! with the `v2-messages` feature flag), which brings the breaking changes to the messages
+//! together, so that applications can port their receive loop once:
+//!
+//! * Every message is tagged with the identifier of the job that sent it (see [`Message::job_id`]).
+//! * The progress is a [`Progress`] structure, which tells a measured value from an
+//!   interpolated one (instead of [`crate::Message::Progress`] and
+//!   [`crate::Message::InterpolatedProgress`]).
+//! * The cancellation is an [`Event::Cancelled`] event of its own (instead of an
+//!   [`crate::Error::Cancelled`] error), carrying a [`Cancelled`] payload.
+//! * The animated GIF comes with its metadata (see [`Success`]).
+//!
+//! Internally, the converter always builds its messages in this shape, from which the
+//! legacy ones are derived (using the lossy `From<v2::Message>` conversion) when they reach
+//! a legacy channel, i.e. the one returned by [`crate::Converter::new_with_channels`]. The
+//! messages in this shape are received from the channel returned by
+//! `Converter::new_with_v2_channels` (with the `v2-messages` feature flag).
+
+use std::path::PathBuf;
+use std::time::Duration;
+
+use crate::{Error, InternalEvent, JobId, Settings, Summary, Warning};
+
+#[cfg(all(
+    feature = "v2-messages",
+    not(any(feature = "tokio", feature = "async-channel"))
+))]
+/// The sender's end of an mpsc [`Message`] channel.
+pub type MessageSender = std::sync::mpsc::Sender<Message>;
+#[cfg(all(
+    feature = "v2-messages",
+    not(any(feature = "tokio", feature = "async-channel"))
+))]
+/// The reciever's end of an mpsc [`Message`] channel.
+pub type MessageReceiver = std::sync::mpsc::Receiver<Message>;
+#[cfg(all(feature = "v2-messages", feature = "tokio"))]
+/// The sender's end of an mpsc [`Message`] channel.
+pub type MessageSender = tokio::sync::mpsc::UnboundedSender<Message>;
+#[cfg(all(feature = "v2-messages", feature = "tokio"))]
+/// The reciever's end of an mpsc [`Message`] channel.
+pub type MessageReceiver = tokio::sync::mpsc::UnboundedReceiver<Message>;
+#[cfg(all(feature = "v2-messages", feature = "async-channel"))]
+/// The sender's end of an mpsc [`Message`] channel.
+pub type MessageSender = async_channel::Sender<Message>;
+#[cfg(all(feature = "v2-messages", feature = "async-channel"))]
+/// The reciever's end of an mpsc [`Message`] channel.
+pub type MessageReceiver = async_channel::Receiver<Message>;
+
+#[derive(Debug, Clone)]
+#[non_exhaustive]
+/// A message sent to the application by the [`crate::Converter`], tagged with its job.
+pub struct Message {
+    /// The identifier of the job that sent the message (see [`crate::Converter::id`]).
+    pub job_id: JobId,
+    /// What happened.
+    pub event: Event,
+}
+
+#[derive(Debug, Clone)]
+#[non_exhaustive]
+// NOTE: Same as `crate::Message`, whose largest variant is `Summary`.
+#[allow(clippy::large_enum_variant)]
+/// An event of a conversion job (see [`crate::Message`] for the variants left unchanged).
+pub enum Event {
+    /// The successfully generated animated GIF, along with its metadata.
+    Success(Success),
+    /// Same as [`crate::Message::SuccessVariant`].
+    SuccessVariant {
+        /// The width of the animated GIF.
+        width: u16,
+        /// The raw bytes of the animated GIF.
+        bytes: Vec<u8>,
+    },
+    /// Same as [`crate::Message::SuccessFile`].
+    SuccessFile(PathBuf),
+    /// Same as [`crate::Message::SuccessWritten`].
+    SuccessWritten(u64),
+    /// Same as [`crate::Message::Error`], except that the cancellation is reported
+    /// using [`Event::Cancelled`] instead.
+    Error(Error),
+    /// A confirmation that signals that the conversion job has been cancelled.
+    Cancelled(Cancelled),
+    /// The progress made by the converter, whether measured or interpolated.
+    Progress(Progress),
+    /// Same as [`crate::Message::FramesProcessed`].
+    FramesProcessed(u64),
+    /// Same as [`crate::Message::VideoDuration`].
+    VideoDuration(Duration),
+    /// Same as [`crate::Message::OutputBytes`].
+    OutputBytes(u64),
+    /// Same as [`crate::Message::BytesWritten`].
+    BytesWritten(u64),
+    /// Same as [`crate::Message::ColorCountSelected`].
+    ColorCountSelected(u16),
+    /// Same as [`crate::Message::CodecSelection`].
+    CodecSelection {
+        /// The name of the decoder (e.g. `h264` for FFmpeg's own, or `h264_cuvid`).
+        decoder: String,
+        /// The name of the encoder (i.e. `gif`, for the conversion job).
+        encoder: String,
+        /// Whether the decoder or the encoder is a hardware one (e.g. `h264_cuvid`).
+        hw_accelerated: bool,
+    },
+    /// Same as [`crate::Message::OutputDimensions`].
+    OutputDimensions {
+        /// The animated GIF's width.
+        width: u32,
+        /// The animated GIF's height.
+        height: u32,
+    },
+    /// Same as [`crate::Message::FrameMap`].
+    FrameMap(Vec<Duration>),
+    /// Same as [`crate::Message::Warning`].
+    Warning(Warning),
+    /// Same as [`crate::Message::Preview`].
+    Preview(Vec<u8>),
+    /// Same as [`crate::Message::Thumbnail`].
+    Thumbnail {
+        /// The position of the thumbnail in the strip, starting at 0.
+        index: usize,
+        /// The (approximate) timestamp of the thumbnail in the source video.
+        timestamp: Duration,
+        /// The encoded image.
+        bytes: Vec<u8>,
+    },
+    /// Same as [`crate::Message::Saved`].
+    Saved {
+        /// The path of the file.
+        path: PathBuf,
+    },
+    /// Same as [`crate::Message::Skipped`].
+    Skipped {
+        /// The path of the existing file.
+        existing_path: PathBuf,
+    },
+    /// Same as [`crate::Message::Internal`].
+    Internal(InternalEvent),
+    /// Same as [`crate::Message::Attempt`].
+    Attempt {
+        /// The attempt's number, starting at 1.
+        n: u32,
+        /// The settings used by the attempt.
+        settings: Box<Settings>,
+    },
+    /// Same as [`crate::Message::Summary`].
+    Summary(Summary),
+    /// Same as [`crate::Message::Done`].
+    Done,
+}
+
+#[derive(Debug, Clone)]
+#[non_exhaustive]
+/// The animated GIF sent using [`Event::Success`].
+pub struct Success {
+    /// The raw bytes that make up the animated GIF.
+    pub bytes: Vec<u8>,
+    /// The animated GIF's width and height, as announced by FFmpeg (see
+    /// [`Event::OutputDimensions`]), if known.
+    pub dimensions: Option<(u32, u32)>,
+    /// The path of the file to which the animated GIF was written (see
+    /// [`crate::Settings::output_file`]), if any.
+    pub output_path: Option<PathBuf>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+#[non_exhaustive]
+/// How far the job had gone when it was cancelled (see [`Event::Cancelled`]).
+pub struct Cancelled {
+    /// The last progress value sent to the application before the cancellation, if any.
+    pub progress_at_cancel: Option<f64>,
+    /// The number of bytes of output that had already been produced (and that were
+    /// thrown away) when the cancellation was processed.
+    pub bytes_discarded: usize,
+    /// The video's duration (see [`Event::VideoDuration`]), if known, e.g. to tell where
+    /// in the video the job was cancelled.
+    pub video_duration: Option<Duration>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+#[non_exhaustive]
+/// The progress sent using [`Event::Progress`].
+pub struct Progress {
+    /// A value between 0.0 and 1.0 (see [`crate::Message::Progress`]).
+    pub value: f64,
+    /// Whether the value was estimated between two measured ones (see
+    /// [`crate::Message::InterpolatedProgress`]).
+    pub interpolated: bool,
+    /// The position in the video that the value corresponds to, if its duration is known.
+    pub position: Option<Duration>,
+}
+
+/// What the [`crate::Converter`] has sent so far that the messages' new data comes from.
+#[derive(Debug, Clone, Default)]
+pub(crate) struct Context {
+    /// The last [`Event::OutputDimensions`], if any.
+    pub(crate) dimensions: Option<(u32, u32)>,
+    /// The path of the last [`Event::Saved`] (or [`Event::Skipped`]), if any.
+    pub(crate) output_path: Option<PathBuf>,
+    /// The last [`Event::VideoDuration`], if any.
+    pub(crate) video_duration: Option<Duration>,
+}
+
+impl Message {
+    /// Builds the message sent by the job `job_id` from its legacy shape, taking the new
+    /// data from `context`.
+    pub(crate) fn lift(job_id: JobId, message: crate::Message, context: &Context) -> Self {
+        use crate::Message as M;
+        let progress = |value, interpolated| {
+            Event::Progress(Progress {
+                value,
+                interpolated,
+                position: context.video_duration.map(|d| d.mul_f64(value)),
+            })
+        };
+        let event = match message {
+            M::Success(bytes) => Event::Success(Success {
+                bytes,
+                dimensions: context.dimensions,
+                output_path: context.output_path.clone(),
+            }),
+            M::SuccessVariant { width, bytes } => Event::SuccessVariant { width, bytes },
+            M::SuccessFile(path) => Event::SuccessFile(path),
+            M::SuccessWritten(n) => Event::SuccessWritten(n),
+            M::Error(Error::Cancelled {
+                progress_at_cancel,
+                bytes_discarded,
+            }) => Event::Cancelled(Cancelled {
+                progress_at_cancel,
+                bytes_discarded,
+                video_duration: context.video_duration,
+            }),
+            M::Error(e) => Event::Error(e),
+            M::Progress(p) => progress(p, false),
+            M::InterpolatedProgress(p) => progress(p, true),
+            M::FramesProcessed(n) => Event::FramesProcessed(n),
+            M::VideoDuration(d) => Event::VideoDuration(d),
+            M::OutputBytes(n) => Event::OutputBytes(n),
+            M::BytesWritten(n) => Event::BytesWritten(n),
+            M::ColorCountSelected(n) => Event::ColorCountSelected(n),
+            M::CodecSelection {
+                decoder,
+                encoder,
+                hw_accelerated,
+            } => Event::CodecSelection {
+                decoder,
+                encoder,
+                hw_accelerated,
+            },
+            M::OutputDimensions { width, height } => Event::OutputDimensions { width, height },
+            M::FrameMap(timestamps) => Event::FrameMap(timestamps),
+            M::Warning(w) => Event::Warning(w),
+            M::Preview(bytes) => Event::Preview(bytes),
+            M::Thumbnail {
+                index,
+                timestamp,
+                bytes,
+            } => Event::Thumbnail {
+                index,
+                timestamp,
+                bytes,
+            },
+            M::Saved { path } => Event::Saved { path },
+            M::Skipped { existing_path } => Event::Skipped { existing_path },
+            M::Internal(e) => Event::Internal(e),
+            M::Attempt { n, settings } => Event::Attempt { n, settings },
+            M::Summary(s) => Event::Summary(s),
+            M::Done => Event::Done,
+        };
+        Self { job_id, event }
+    }
+}
+
+/// The legacy shape of a message, which drops the data that it cannot hold (e.g. the job's
+/// identifier, or [`Success::dimensions`]).
+impl From<Message> for crate::Message {
+    fn from(message: Message) -> Self {
+        use crate::Message as M;
+        match message.event {
+            Event::Success(success) => M::Success(success.bytes),
+            Event::SuccessVariant { width, bytes } => M::SuccessVariant { width, bytes },
+            Event::SuccessFile(path) => M::SuccessFile(path),
+            Event::SuccessWritten(n) => M::SuccessWritten(n),
+            Event::Error(e) => M::Error(e),
+            Event::Cancelled(cancelled) => M::Error(Error::Cancelled {
+                progress_at_cancel: cancelled.progress_at_cancel,
+                bytes_discarded: cancelled.bytes_discarded,
+            }),
+            Event::Progress(p) if p.interpolated => M::InterpolatedProgress(p.value),
+            Event::Progress(p) => M::Progress(p.value),
+            Event::FramesProcessed(n) => M::FramesProcessed(n),
+            Event::VideoDuration(d) => M::VideoDuration(d),
+            Event::OutputBytes(n) => M::OutputBytes(n),
+            Event::BytesWritten(n) => M::BytesWritten(n),
+            Event::ColorCountSelected(n) => M::ColorCountSelected(n),
+            Event::CodecSelection {
+                decoder,
+                encoder,
+                hw_accelerated,
+            } => M::CodecSelection {
+                decoder,
+                encoder,
+                hw_accelerated,
+            },
+            Event::OutputDimensions { width, height } => M::OutputDimensions { width, height },
+            Event::FrameMap(timestamps) => M::FrameMap(timestamps),
+            Event::Warning(w) => M::Warning(w),
+            Event::Preview(bytes) => M::Preview(bytes),
+            Event::Thumbnail {
+                index,
+                timestamp,
+                bytes,
+            } => M::Thumbnail {
+                index,
+                timestamp,
+                bytes,
+            },
+            Event::Saved { path } => M::Saved { path },
+            Event::Skipped { existing_path } => M::Skipped { existing_path },
+            Event::Internal(e) => M::Internal(e),
+            Event::Attempt { n, settings } => M::Attempt { n, settings },
+            Event::Summary(s) => M::Summary(s),
+            Event::Done => M::Done,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::Message as M;
+
+    fn context() -> Context {
+        Context {
+            dimensions: Some((200, 112)),
+            output_path: Some("out.gif".into()),
+            video_duration: Some(Duration::from_secs(10)),
+        }
+    }
+
+    #[test]
+    fn test_legacy_round_trip() {
+        let messages = [
+            M::Success(vec![1, 2, 3]),
+            M::SuccessVariant {
+                width: 100,
+                bytes: vec![4],
+            },
+            M::SuccessFile("out.gif".into()),
+            M::SuccessWritten(2048),
+            M::Error(Error::EmptyStdout),
+            M::Error(Error::Cancelled {
+                progress_at_cancel: Some(0.5),
+                bytes_discarded: 42,
+            }),
+            M::Progress(0.25),
+            M::InterpolatedProgress(0.3),
+            M::FramesProcessed(12),
+            M::VideoDuration(Duration::from_secs(10)),
+            M::OutputBytes(1024),
+            M::BytesWritten(512),
+            M::ColorCountSelected(64),
+            M::CodecSelection {
+                decoder: "h264".into(),
+                encoder: "gif".into(),
+                hw_accelerated: false,
+            },
+            M::OutputDimensions {
+                width: 200,
+                height: 112,
+            },
+            M::FrameMap(vec![Duration::ZERO, Duration::from_millis(100)]),
+            M::Warning(Warning::OutputLargerThanInput {
+                input_bytes: 1,
+                output_bytes: 2,
+            }),
+            M::Preview(vec![6]),
+            M::Thumbnail {
+                index: 1,
+                timestamp: Duration::from_secs(1),
+                bytes: vec![5],
+            },
+            M::Saved {
+                path: "out.gif".into(),
+            },
+            M::Skipped {
+                existing_path: "out.gif".into(),
+            },
+            M::Internal(InternalEvent::ThreadStarted(crate::ThreadKind::Stdout)),
+            M::Attempt {
+                n: 2,
+                settings: Box::new(Settings::with_standard_fps("video.mp4".into(), 100)),
+            },
+            M::Done,
+        ];
+        for message in messages {
+            let expected = format!("{:?}", message);
+            let lifted = Message::lift(JobId::U64(7), message, &context());
+            assert_eq!(lifted.job_id, JobId::U64(7));
+            assert_eq!(format!("{:?}", M::from(lifted)), expected);
+        }
+    }
+
+    #[test]
+    fn test_lift() {
+        let lift = |message| Message::lift(JobId::U64(7), message, &context()).event;
+        match lift(M::Success(vec![1, 2, 3])) {
+            Event::Success(success) => {
+                assert_eq!(success.bytes, [1, 2, 3]);
+                assert_eq!(success.dimensions, Some((200, 112)));
+                assert_eq!(success.output_path, Some("out.gif".into()));
+            }
+            e => panic!("{:?}", e),
+        }
+        match lift(M::InterpolatedProgress(0.25)) {
+            Event::Progress(p) => assert_eq!(
+                p,
+                Progress {
+                    value: 0.25,
+                    interpolated: true,
+                    position: Some(Duration::from_millis(2500)),
+                }
+            ),
+            e => panic!("{:?}", e),
+        }
+        match lift(M::Error(Error::Cancelled {
+            progress_at_cancel: None,
+            bytes_discarded: 0,
+        })) {
+            Event::Cancelled(c) => assert_eq!(
+                c,
+                Cancelled {
+                    progress_at_cancel: None,
+                    bytes_discarded: 0,
+                    video_duration: Some(Duration::from_secs(10)),
+                }
+            ),
+            e => panic!("{:?}", e),
+        }
+        // NOTE: Without any context, the new data is simply missing.
+        match Message::lift(JobId::U64(7), M::Progress(0.5), &Context::default()).event {
+            Event::Progress(p) => assert_eq!(p.position, None),
+            e => panic!("{:?}", e),
+        }
+    }
+}
@@ -0,0 +1,100 @@
+//! The animated GIFs generated at the other widths provided using
+//! [`crate::Settings::additional_widths`], which FFmpeg writes to temporary files (since
+//! `stdout` only carries the one at the constructor's width), named after their width and
+//! gathered in a directory of their own, which is removed once the job is done (whether or
+//! not the files were read).
+//!
+//! The files are only read once FFmpeg has exited, since FFmpeg finishes its outputs one
+//! after the other (i.e. `stdout` may be closed before the other files are complete).
+
+use std::path::{Path, PathBuf};
+
+use crate::gif_info::parse_gif_info;
+use crate::Error;
+
+const LOG_TARGET: &str = "ffmpeg_gif_maker::variants";
+
+/// The path of the file to which the animated GIF of the given `width` is written, inside
+/// `dir` (or relative to the working directory, for [`crate::Settings::plan`]).
+pub(crate) fn path(dir: Option<&Path>, width: u16) -> PathBuf {
+    let name = format!("{}px.gif", width);
+    match dir {
+        Some(dir) => dir.join(name),
+        None => name.into(),
+    }
+}
+
+/// The temporary directory holding the files (see the module's documentation), which is
+/// removed when dropped.
+#[derive(Debug)]
+pub(crate) struct VariantDir {
+    path: PathBuf,
+}
+
+impl VariantDir {
+    /// Creates a new, empty, uniquely named directory inside the system's temporary directory.
+    pub(crate) fn create() -> std::io::Result<Self> {
+        let path = std::env::temp_dir().join(format!("ffmpeg_gif_maker-{}", uuid::Uuid::new_v4()));
+        std::fs::create_dir(&path)?;
+        Ok(Self { path })
+    }
+
+    pub(crate) fn path(&self) -> &Path {
+        &self.path
+    }
+
+    /// Reads the animated GIF of the given `width`, making sure that it is valid, or
+    /// returns the error describing the problem (see [`Error::VariantOutput`]).
+    pub(crate) fn read(&self, width: u16) -> Result<Vec<u8>, Error> {
+        let path = path(Some(&self.path), width);
+        let bytes = match std::fs::read(&path) {
+            Ok(bytes) => bytes,
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => vec![],
+            Err(e) => {
+                return Err(Error::OutputFile {
+                    path,
+                    error: std::sync::Arc::new(e),
+                })
+            }
+        };
+        if bytes.is_empty() {
+            return Err(Error::EmptyStdout);
+        }
+        parse_gif_info(&bytes).map_err(Error::InvalidOutput)?;
+        Ok(bytes)
+    }
+}
+
+impl Drop for VariantDir {
+    fn drop(&mut self) {
+        if let Err(e) = std::fs::remove_dir_all(&self.path) {
+            log::warn!(
+                target: LOG_TARGET,
+                "Unable to remove temporary directory {:?}: {:?}",
+                self.path,
+                e
+            );
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_utils::sample_gif;
+
+    #[test]
+    fn test_variant_dir() {
+        let dir = VariantDir::create().unwrap();
+        let path = dir.path().to_path_buf();
+        std::fs::write(super::path(Some(&path), 400), sample_gif(2, None)).unwrap();
+        std::fs::write(super::path(Some(&path), 800), b"GIF89a").unwrap();
+        assert_eq!(dir.read(400).unwrap(), sample_gif(2, None));
+        assert!(matches!(dir.read(800), Err(Error::InvalidOutput(_))));
+        assert!(matches!(dir.read(200), Err(Error::EmptyStdout)));
+
+        // Removed along with the files.
+        drop(dir);
+        assert!(!path.exists());
+    }
+}
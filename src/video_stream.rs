@@ -0,0 +1,67 @@
+//! The selection of one of the source video's video streams (see
+//! [`crate::Settings::video_stream_index`]), e.g. the screen or the camera of a recording
+//! holding both, instead of the one FFmpeg considers the best (usually the one with the
+//! highest resolution).
+//!
+//! The generated filter graph reads the selected stream using an explicit input label (e.g.
+//! `[0:v:1]`), while the passes that use a simple filter graph (e.g. the palette analysis,
+//! see [`crate::Settings::auto_colors`]) select it using the `-map` output option.
+
+/// The label of the video stream read by the generated filter graph (e.g. `[0:v:1]`),
+/// given the index of the selected stream, if any.
+pub(crate) fn input_label(index: Option<u32>) -> String {
+    match index {
+        Some(index) => format!("[0:v:{}]", index),
+        None => "[0:v]".into(),
+    }
+}
+
+/// The `-map` option that selects the stream, if any, for a simple filter graph.
+pub(crate) fn map_args(index: Option<u32>) -> Vec<String> {
+    index
+        .map(|index| vec!["-map".into(), format!("0:v:{}", index)])
+        .unwrap_or_default()
+}
+
+/// Whether `line`, written by FFmpeg to `stderr`, reports that the selected stream does not
+/// exist (see [`crate::Error::VideoStreamNotFound`]).
+pub(crate) fn is_missing_stream_error(line: &str) -> bool {
+    // NOTE: E.g. `Stream specifier ':v:1' in filtergraph description [0:v:1]fps=10[s] matches
+    // no streams.` (prefixed with `[fc#0 @ 0x55d4c8f0c2c0]` since FFmpeg 7), or `Stream map
+    // '0:v:1' matches no streams.` with `-map`.
+    line.contains("matches no streams")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_input_label() {
+        assert_eq!(input_label(None), "[0:v]");
+        assert_eq!(input_label(Some(1)), "[0:v:1]");
+    }
+
+    #[test]
+    fn test_map_args() {
+        assert!(map_args(None).is_empty());
+        assert_eq!(map_args(Some(2)), ["-map", "0:v:2"]);
+    }
+
+    #[test]
+    fn test_is_missing_stream_error() {
+        for line in [
+            "Stream specifier ':v:1' in filtergraph description [0:v:1]fps=10[s] matches no streams.",
+            "[fc#0 @ 0x55d4c8f0c2c0] Stream specifier ':v:1' in filtergraph description [0:v:1]fps=10[s] matches no streams.",
+            "Stream map '0:v:1' matches no streams.",
+        ] {
+            assert!(is_missing_stream_error(line), "{}", line);
+        }
+        for line in [
+            "  Stream #0:1[0x2](und): Video: h264 (High) (avc1 / 0x31637661), yuv420p, 1280x720",
+            "To ignore this, add a trailing '?' to the map.",
+        ] {
+            assert!(!is_missing_stream_error(line), "{}", line);
+        }
+    }
+}
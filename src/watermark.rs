@@ -0,0 +1,151 @@
+//! The image overlaid onto the animated GIF's frames (see [`crate::Settings::watermark`]),
+//! e.g. a small PNG logo, which FFmpeg reads as a second input (i.e. `[1:v]`). Its opacity
+//! is applied using the `colorchannelmixer` filter (on an RGBA copy of the image), and it
+//! is then overlaid using the `overlay` filter, once the frames have been scaled, so that its
+//! size does not depend on the animated GIF's width.
+
+use std::path::PathBuf;
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
+/// Where the watermark is placed on the animated GIF's frames (see
+/// [`crate::Settings::watermark`]), or the badge on the preview's (see [`crate::Text`]).
+pub enum Position {
+    /// Against the top and left edges.
+    TopLeft,
+    /// Against the top and right edges.
+    TopRight,
+    /// Against the bottom and left edges.
+    BottomLeft,
+    /// Against the bottom and right edges.
+    #[default]
+    BottomRight,
+    /// Centered on the frames.
+    Center,
+}
+
+impl Position {
+    /// The `overlay` filter's `x` and `y` options placing the watermark (whose width and
+    /// height are `w` and `h`) on the frames (whose width and height are `W` and `H`).
+    fn overlay_options(&self) -> &'static str {
+        match self {
+            Self::TopLeft => "0:0",
+            Self::TopRight => "W-w:0",
+            Self::BottomLeft => "0:H-h",
+            Self::BottomRight => "W-w:H-h",
+            Self::Center => "(W-w)/2:(H-h)/2",
+        }
+    }
+
+    /// The `drawtext` filter's `x` and `y` options placing the text (whose width and height
+    /// are `tw` and `th`) on the frames (whose width and height are `w` and `h`), `margin`
+    /// pixels away from the edges.
+    pub(crate) fn drawtext_options(&self, margin: u16) -> String {
+        let m = margin;
+        match self {
+            Self::TopLeft => format!("x={}:y={}", m, m),
+            Self::TopRight => format!("x=w-tw-{}:y={}", m, m),
+            Self::BottomLeft => format!("x={}:y=h-th-{}", m, m),
+            Self::BottomRight => format!("x=w-tw-{}:y=h-th-{}", m, m),
+            Self::Center => "x=(w-tw)/2:y=(h-th)/2".into(),
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+/// The watermark provided using [`crate::Settings::watermark`].
+pub(crate) struct Watermark {
+    /// The path of the image file.
+    pub(crate) path: PathBuf,
+    pub(crate) position: Position,
+    /// Between 0.0 (i.e. invisible) and 1.0 (i.e. opaque).
+    pub(crate) opacity: f32,
+}
+
+impl Watermark {
+    /// The arguments that make FFmpeg read the image, as its second input.
+    pub(crate) fn input_args(&self) -> [String; 2] {
+        [
+            "-i".into(),
+            crate::argv::path_arg(&self.path.to_string_lossy()),
+        ]
+    }
+
+    /// The statement that prepares the image for each of the outputs whose label `prefixes`
+    /// are given (e.g. `o1_`, see [`crate::filter_graph::FilterGraph::split`]), each copy
+    /// being labelled `wm` (e.g. `[o1_wm]`).
+    pub(crate) fn input_statement(&self, prefixes: &[String]) -> String {
+        let copies = match prefixes {
+            [prefix] => format!("[{}wm]", prefix),
+            _ => format!(
+                ",split={}{}",
+                prefixes.len(),
+                String::from_iter(prefixes.iter().map(|prefix| format!("[{}wm]", prefix)))
+            ),
+        };
+        format!(
+            "[1:v]format=rgba,colorchannelmixer=aa={}{}",
+            self.opacity, copies
+        )
+    }
+
+    /// The `overlay` filter, which places the image (see [`Watermark::input_statement`]).
+    pub(crate) fn overlay_filter(&self) -> String {
+        format!("overlay={}", self.position.overlay_options())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn watermark(path: &str, position: Position, opacity: f32) -> Watermark {
+        Watermark {
+            path: path.into(),
+            position,
+            opacity,
+        }
+    }
+
+    #[test]
+    fn test_input_args() {
+        assert_eq!(
+            watermark("logo.png", Position::default(), 1.0).input_args(),
+            ["-i", "logo.png"]
+        );
+        assert_eq!(
+            watermark("-logo.png", Position::default(), 1.0).input_args(),
+            ["-i", "./-logo.png"]
+        );
+    }
+
+    #[test]
+    fn test_input_statement() {
+        let watermark = watermark("logo.png", Position::default(), 0.5);
+        assert_eq!(
+            watermark.input_statement(&["".into()]),
+            "[1:v]format=rgba,colorchannelmixer=aa=0.5[wm]"
+        );
+        assert_eq!(
+            watermark.input_statement(&["o0_".into(), "o1_".into()]),
+            "[1:v]format=rgba,colorchannelmixer=aa=0.5,split=2[o0_wm][o1_wm]"
+        );
+    }
+
+    #[test]
+    fn test_overlay_filter() {
+        let cases = [
+            (Position::TopLeft, "overlay=0:0"),
+            (Position::TopRight, "overlay=W-w:0"),
+            (Position::BottomLeft, "overlay=0:H-h"),
+            (Position::BottomRight, "overlay=W-w:H-h"),
+            (Position::Center, "overlay=(W-w)/2:(H-h)/2"),
+        ];
+        for (position, expected) in cases {
+            assert_eq!(
+                watermark("logo.png", position, 1.0).overlay_filter(),
+                expected
+            );
+        }
+    }
+}
@@ -0,0 +1,95 @@
+//! Runs conversion jobs from a `smol` executor with the `async-channel` feature flag,
+//! using a fake FFmpeg binary (i.e. a shell script), to make sure that the channels can
+//! be used from asynchronous tasks without any tokio runtime.
+#![cfg(all(unix, feature = "async-channel"))]
+
+use std::time::Duration;
+
+use ffmpeg_gif_maker::{Command, Converter, Error, Message, Settings};
+
+/// A structurally valid animated GIF (1x1, 2 frames).
+fn sample_gif() -> Vec<u8> {
+    let mut bytes = b"GIF89a".to_vec();
+    bytes.extend_from_slice(&[0x01, 0x00, 0x01, 0x00, 0x80, 0x00, 0x00]);
+    bytes.extend_from_slice(&[0xff, 0xff, 0xff, 0x00, 0x00, 0x00]);
+    for _ in 0..2 {
+        bytes.extend_from_slice(&[0x21, 0xf9, 0x04, 0x00, 0x0a, 0x00, 0x00, 0x00]);
+        bytes.extend_from_slice(&[0x2c, 0x00, 0x00, 0x00, 0x00, 0x01, 0x00, 0x01, 0x00, 0x00]);
+        bytes.extend_from_slice(&[0x02, 0x02, 0x44, 0x01, 0x00]);
+    }
+    bytes.push(0x3b);
+    bytes
+}
+
+/// A fake FFmpeg binary that reports a 5 seconds long video and then runs `script`,
+/// which can write a valid GIF to `stdout` using `cat "$GIF"`.
+fn fake_ffmpeg(script: &str) -> std::path::PathBuf {
+    use std::os::unix::fs::PermissionsExt;
+
+    let dir = std::env::temp_dir()
+        .join("ffmpeg_gif_maker_async_channel_tests")
+        .join(uuid::Uuid::new_v4().to_string());
+    std::fs::create_dir_all(&dir).unwrap();
+    std::fs::write(dir.join("stdout.bin"), sample_gif()).unwrap();
+    let path = dir.join("ffmpeg");
+    std::fs::write(
+        &path,
+        format!(
+            "#!/bin/sh\nGIF='{}'\necho '  Duration: 00:00:05.00, start: 0.000000, bitrate: 1785 kb/s' >&2\n{}\n",
+            dir.join("stdout.bin").display(),
+            script
+        ),
+    )
+    .unwrap();
+    std::fs::set_permissions(&path, std::fs::Permissions::from_mode(0o755)).unwrap();
+    path
+}
+
+#[test]
+fn test_smol_conversion() {
+    let settings = Settings::with_standard_fps("video.mp4".into(), 200)
+        .ffmpeg_path(fake_ffmpeg("sleep 0.1; cat \"$GIF\"").to_string_lossy());
+    let messages = smol::block_on(async {
+        let (converter, _tx, rx) = Converter::new_with_channels();
+        let job = smol::unblock(move || converter.convert(settings));
+        let mut messages = vec![];
+        while let Ok(message) = rx.recv().await {
+            messages.push(message);
+        }
+        job.await;
+        messages
+    });
+    assert!(messages
+        .iter()
+        .any(|m| matches!(m, Message::Success(bytes) if *bytes == sample_gif())));
+    assert!(matches!(messages.last(), Some(Message::Done)));
+}
+
+#[test]
+fn test_smol_cancellation() {
+    let settings = Settings::with_standard_fps("video.mp4".into(), 200).ffmpeg_path(
+        fake_ffmpeg(
+            // NOTE: Like FFmpeg, stopping (without any output) once `q` is written to `stdin`.
+            "timeout 30 head -c 1 > /dev/null",
+        )
+        .to_string_lossy(),
+    );
+    let started = std::time::Instant::now();
+    let messages = smol::block_on(async {
+        let (converter, tx, rx) = Converter::new_with_channels();
+        let job = smol::unblock(move || converter.convert(settings));
+        smol::Timer::after(Duration::from_millis(500)).await;
+        tx.send(Command::Cancel).await.unwrap();
+        let mut messages = vec![];
+        while let Ok(message) = rx.recv().await {
+            messages.push(message);
+        }
+        job.await;
+        messages
+    });
+    assert!(started.elapsed() < Duration::from_secs(20));
+    assert!(messages
+        .iter()
+        .any(|m| matches!(m, Message::Error(Error::Cancelled { .. }))));
+    assert!(!messages.iter().any(|m| matches!(m, Message::Success(_))));
+}
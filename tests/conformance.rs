@@ -0,0 +1,186 @@
+//! Decodes the animated GIFs generated from the sample video (when FFmpeg is available
+//! on the system path), to make sure that they are sound, and not merely non-empty: the
+//! dimensions match the settings, the frames hold an actual picture, and the animation
+//! lasts as long as the (trimmed) video.
+
+#[path = "../examples/common/mod.rs"]
+mod common;
+
+use std::time::Duration;
+
+use ffmpeg_gif_maker::{
+    compute_output_dimensions, Converter, EvenDimensionPolicy, FitMode, Message, Settings, SizeMode,
+};
+
+fn ffmpeg_available() -> bool {
+    std::process::Command::new("ffmpeg")
+        .arg("-version")
+        .stdin(std::process::Stdio::null())
+        .stdout(std::process::Stdio::null())
+        .stderr(std::process::Stdio::null())
+        .status()
+        .map(|s| s.success())
+        .unwrap_or(false)
+}
+
+/// What a conversion job reported, along with the animated GIF's bytes.
+struct Output {
+    bytes: Vec<u8>,
+    video_duration: Duration,
+    dimensions: (u32, u32),
+}
+
+/// Runs a conversion job with `settings`.
+fn convert(settings: Settings) -> Output {
+    let (converter, _tx, mut rx) = Converter::new_with_channels();
+    let handle = std::thread::spawn(move || converter.convert(settings));
+    let (mut bytes, mut video_duration, mut dimensions) = (None, None, None);
+    while let Some(message) = common::recv(&mut rx) {
+        match message {
+            Message::VideoDuration(d) => video_duration = Some(d),
+            Message::OutputDimensions { width, height } => dimensions = Some((width, height)),
+            Message::Success(b) => bytes = Some(b),
+            Message::Error(e) => panic!("Conversion failed: {:?}", e),
+            Message::Done => break,
+            _ => {}
+        }
+    }
+    handle.join().expect("Failed to join");
+    Output {
+        bytes: bytes.expect("No output"),
+        video_duration: video_duration.expect("No video duration"),
+        dimensions: dimensions.expect("No output dimensions"),
+    }
+}
+
+/// The decoded animated GIF (the frames being converted to RGBA).
+struct DecodedGif {
+    width: u32,
+    height: u32,
+    /// The RGBA pixels of each frame (which may only cover part of the screen).
+    frames: Vec<Vec<u8>>,
+    duration: Duration,
+}
+
+fn decode(bytes: &[u8]) -> DecodedGif {
+    let mut options = gif::DecodeOptions::new();
+    options.set_color_output(gif::ColorOutput::RGBA);
+    let mut decoder = options.read_info(bytes).expect("Invalid GIF header");
+    let (width, height) = (u32::from(decoder.width()), u32::from(decoder.height()));
+    let (mut frames, mut centiseconds) = (vec![], 0u64);
+    while let Some(frame) = decoder.read_next_frame().expect("Invalid GIF frame") {
+        assert!(u32::from(frame.left) + u32::from(frame.width) <= width);
+        assert!(u32::from(frame.top) + u32::from(frame.height) <= height);
+        centiseconds += u64::from(frame.delay);
+        frames.push(frame.buffer.to_vec());
+    }
+    DecodedGif {
+        width,
+        height,
+        frames,
+        duration: Duration::from_millis(centiseconds * 10),
+    }
+}
+
+/// The variance of the luma of the (RGBA) pixels.
+fn luma_variance(rgba: &[u8]) -> f64 {
+    let lumas = Vec::from_iter(
+        rgba.chunks_exact(4)
+            .map(|p| 0.299 * f64::from(p[0]) + 0.587 * f64::from(p[1]) + 0.114 * f64::from(p[2])),
+    );
+    let mean = lumas.iter().sum::<f64>() / lumas.len().max(1) as f64;
+    lumas.iter().map(|l| (l - mean).powi(2)).sum::<f64>() / lumas.len().max(1) as f64
+}
+
+/// Checks the animated GIF decoded from `output`, given the frame rate and the duration of
+/// the (trimmed) video.
+fn assert_sound(output: &Output, fps: u16, duration: Duration) -> DecodedGif {
+    let gif = decode(&output.bytes);
+    assert_eq!((gif.width, gif.height), output.dimensions);
+
+    let expected_frames = duration.as_secs_f64() * f64::from(fps);
+    let frames = gif.frames.len() as f64;
+    assert!(
+        (expected_frames * 0.8..=expected_frames * 1.2 + 1.0).contains(&frames),
+        "{} frames, expected about {}",
+        frames,
+        expected_frames
+    );
+
+    // NOTE: A filter-ordering mistake tends to produce blank (or single color) frames.
+    let variance = gif
+        .frames
+        .iter()
+        .map(|f| luma_variance(f))
+        .fold(0.0, f64::max);
+    assert!(variance > 100.0, "Frames too uniform ({})", variance);
+
+    let (actual, expected) = (gif.duration.as_secs_f64(), duration.as_secs_f64());
+    assert!(
+        (actual - expected).abs() <= expected * 0.1,
+        "Animation lasts {:?}, expected {:?}",
+        gif.duration,
+        duration
+    );
+    gif
+}
+
+#[test]
+fn test_whole_video() {
+    if !ffmpeg_available() {
+        eprintln!("FFmpeg not found on system path, so skipping conformance test.");
+        return;
+    }
+    let settings = Settings::with_standard_fps(common::input_video(), 200);
+    let output = convert(settings);
+    let gif = assert_sound(&output, Settings::STANDARD_FPS, output.video_duration);
+    // NOTE: The sample video (or the generated one) is 640x360.
+    assert_eq!(
+        (gif.width, gif.height),
+        compute_output_dimensions((640, 360), &SizeMode::Width(200), EvenDimensionPolicy::Any)
+    );
+}
+
+#[test]
+fn test_trimmed_video() {
+    if !ffmpeg_available() {
+        eprintln!("FFmpeg not found on system path, so skipping conformance test.");
+        return;
+    }
+    let settings = Settings::with_standard_fps(common::input_video(), 160).trim("0:01", "0:03");
+    let output = convert(settings);
+    let gif = assert_sound(&output, Settings::STANDARD_FPS, Duration::from_secs(2));
+    assert_eq!(gif.width, 160);
+}
+
+#[test]
+fn test_fitted_dimensions() {
+    if !ffmpeg_available() {
+        eprintln!("FFmpeg not found on system path, so skipping conformance test.");
+        return;
+    }
+    for fit_mode in [FitMode::Stretch, FitMode::Letterbox, FitMode::Crop] {
+        let settings = Settings::with_standard_fps(common::input_video(), 120)
+            .gif_height(120)
+            .fit_mode(fit_mode)
+            .trim("0:00", "0:02");
+        let output = convert(settings);
+        let gif = assert_sound(&output, Settings::STANDARD_FPS, Duration::from_secs(2));
+        assert_eq!((gif.width, gif.height), (120, 120), "{:?}", fit_mode);
+    }
+}
+
+#[test]
+fn test_single_thread() {
+    if !ffmpeg_available() {
+        eprintln!("FFmpeg not found on system path, so skipping conformance test.");
+        return;
+    }
+    // NOTE: The filter graph (e.g. `palettegen` and `paletteuse`) must still complete.
+    let settings = Settings::with_standard_fps(common::input_video(), 160)
+        .trim("0:01", "0:03")
+        .threads(1);
+    let output = convert(settings);
+    let gif = assert_sound(&output, Settings::STANDARD_FPS, Duration::from_secs(2));
+    assert_eq!(gif.width, 160);
+}
@@ -0,0 +1,91 @@
+//! Runs the examples' code (when FFmpeg is available on the system path), to make
+//! sure that they keep working as the library evolves.
+
+// NOTE: Each example declares the `common` module of its own.
+#![allow(clippy::duplicate_mod)]
+
+#[allow(dead_code)]
+#[path = "../examples/multi_progress.rs"]
+mod multi_progress;
+#[allow(dead_code)]
+#[path = "../examples/to_file.rs"]
+mod to_file;
+
+fn ffmpeg_available() -> bool {
+    std::process::Command::new("ffmpeg")
+        .arg("-version")
+        .stdin(std::process::Stdio::null())
+        .stdout(std::process::Stdio::null())
+        .stderr(std::process::Stdio::null())
+        .status()
+        .map(|s| s.success())
+        .unwrap_or(false)
+}
+
+#[test]
+fn test_to_file_example() {
+    if !ffmpeg_available() {
+        eprintln!("FFmpeg not found on system path, so skipping example test.");
+        return;
+    }
+    let dir =
+        std::env::temp_dir().join(format!("ffmpeg_gif_maker_examples_{}", std::process::id()));
+    std::fs::create_dir_all(&dir).unwrap();
+    let output = dir.join("output.gif");
+    let written = to_file::convert_to_file(&to_file::common::input_video(), &output).unwrap();
+    let bytes = std::fs::read(&output).unwrap();
+    assert_eq!(bytes.len(), written);
+    let info = ffmpeg_gif_maker::gif_info::parse_gif_info(&bytes).unwrap();
+    assert_eq!(info.width, 200);
+    assert!(info.frame_count > 0);
+    std::fs::remove_dir_all(&dir).unwrap();
+}
+
+#[test]
+fn test_multi_progress_example() {
+    if !ffmpeg_available() {
+        eprintln!("FFmpeg not found on system path, so skipping example test.");
+        return;
+    }
+    let dir = std::env::temp_dir().join(format!(
+        "ffmpeg_gif_maker_multi_progress_{}",
+        std::process::id()
+    ));
+    std::fs::create_dir_all(&dir).unwrap();
+    // NOTE: More jobs than `MAX_CONCURRENT_JOBS`, so that one of them is queued.
+    let sources = [
+        "testsrc2=duration=2:size=320x240:rate=10",
+        "smptebars=duration=2:size=320x240:rate=10",
+        "mandelbrot=size=320x240:rate=10,trim=duration=2",
+    ];
+    let jobs = sources
+        .iter()
+        .enumerate()
+        .map(|(i, source)| {
+            let path = dir.join(format!("input-{}.mkv", i));
+            let status = std::process::Command::new("ffmpeg")
+                .args(["-nostdin", "-loglevel", "error", "-y", "-f", "lavfi"])
+                .args(["-i", source, "-c:v", "ffv1"])
+                .arg(&path)
+                .status()
+                .unwrap();
+            assert!(status.success(), "Failed to generate {:?}", source);
+            let settings =
+                ffmpeg_gif_maker::Settings::with_input(path.to_string_lossy().into_owned(), 100);
+            (format!("job-{}", i), settings)
+        })
+        .collect();
+
+    let summaries = multi_progress::run_jobs(jobs, &mut multi_progress::NoProgress);
+    assert_eq!(summaries.len(), sources.len());
+    for summary in &summaries {
+        assert_eq!(
+            summary.outcome(),
+            ffmpeg_gif_maker::Outcome::Succeeded,
+            "{}",
+            multi_progress::summary_line(summary)
+        );
+        assert!(summary.output_bytes.unwrap() > 0);
+    }
+    std::fs::remove_dir_all(&dir).unwrap();
+}
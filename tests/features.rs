@@ -0,0 +1,163 @@
+//! Compiles a minimal application (see `tests/fixtures/features_consumer.rs`) against the
+//! library once per supported combination of feature flags, since the channels (and the code
+//! paths built around them) differ from one flag to another, so that a combination that no
+//! longer compiles (or that lacks part of the public API the examples rely on) is caught,
+//! whichever flags the test suite itself is run with. The combinations that are not supported
+//! must fail to compile.
+//!
+//! Each application is checked (using `cargo check`) in its own crate, generated in Cargo's
+//! temporary directory for the tests, sharing a target directory there. Since the dependencies
+//! are resolved using the repository's `Cargo.lock`, no network access is needed once they have
+//! been downloaded.
+
+use std::path::{Path, PathBuf};
+
+/// The combinations of feature flags that must compile.
+const SUPPORTED: &[&[&str]] = &[
+    &[],
+    &["tokio"],
+    &["async-channel"],
+    &["serde"],
+    &["tokio", "serde"],
+    &["async-channel", "serde"],
+    &["metrics"],
+    &["regex"],
+    &["v2-messages"],
+    &["tokio", "v2-messages"],
+    &["async-channel", "v2-messages"],
+    &["metrics", "regex", "serde", "v2-messages"],
+    &["tokio", "metrics", "regex", "serde", "v2-messages"],
+    &["async-channel", "metrics", "regex", "serde", "v2-messages"],
+];
+
+/// The combinations of feature flags that must not compile, along with (part of) the reason.
+const UNSUPPORTED: &[(&[&str], &str)] = &[(
+    &["tokio", "async-channel"],
+    "feature flags are mutually exclusive",
+)];
+
+/// The library's feature flags, which the application forwards (see [`consumer_manifest`]).
+const FEATURES: [&str; 6] = [
+    "async-channel",
+    "metrics",
+    "regex",
+    "serde",
+    "tokio",
+    "v2-messages",
+];
+
+/// The name of a combination of feature flags, as found in the failure messages.
+fn name(features: &[&str]) -> String {
+    if features.is_empty() {
+        "no feature flags".into()
+    } else {
+        format!("[{}]", features.join(", "))
+    }
+}
+
+/// The application's `Cargo.toml`, whose feature flags enable the library's ones.
+fn consumer_manifest() -> String {
+    let root = env!("CARGO_MANIFEST_DIR").replace('\\', "/");
+    let features: String = FEATURES
+        .iter()
+        .map(|feature| match *feature {
+            "serde" => "serde = [\"ffmpeg_gif_maker/serde\", \"dep:serde\"]\n".to_string(),
+            _ => format!("{0} = [\"ffmpeg_gif_maker/{0}\"]\n", feature),
+        })
+        .collect();
+    format!(
+        "[package]\n\
+        name = \"ffmpeg_gif_maker_features_consumer\"\n\
+        version = \"0.0.0\"\n\
+        edition = \"2021\"\n\
+        publish = false\n\
+        \n\
+        [dependencies]\n\
+        ffmpeg_gif_maker = {{path = \"{root}\"}}\n\
+        serde = {{version = \"1.0\", optional = true}}\n\
+        \n\
+        [features]\n\
+        {features}\n\
+        [workspace]\n"
+    )
+}
+
+/// Generates the application's crate (unless already there), returning its directory.
+fn consumer_crate(dir: &Path) -> PathBuf {
+    let root = Path::new(env!("CARGO_MANIFEST_DIR"));
+    let dir = dir.join("consumer");
+    std::fs::create_dir_all(dir.join("src/common")).unwrap();
+    std::fs::write(dir.join("Cargo.toml"), consumer_manifest()).unwrap();
+    std::fs::copy(
+        root.join("tests/fixtures/features_consumer.rs"),
+        dir.join("src/main.rs"),
+    )
+    .unwrap();
+    std::fs::copy(
+        root.join("examples/common/mod.rs"),
+        dir.join("src/common/mod.rs"),
+    )
+    .unwrap();
+    // NOTE: So that the same versions of the dependencies are used (without resolving them).
+    if !dir.join("Cargo.lock").exists() {
+        std::fs::copy(root.join("Cargo.lock"), dir.join("Cargo.lock")).unwrap();
+    }
+    dir
+}
+
+/// Checks the application with the given `features`, returning the compiler's output if
+/// it does not compile.
+fn check(consumer: &Path, target_dir: &Path, features: &[&str]) -> Result<(), String> {
+    let output = std::process::Command::new(env!("CARGO"))
+        .arg("check")
+        .arg("--quiet")
+        .arg("--manifest-path")
+        .arg(consumer.join("Cargo.toml"))
+        .arg("--target-dir")
+        .arg(target_dir)
+        .arg("--features")
+        .arg(features.join(","))
+        // NOTE: The application's warnings (e.g. its unused helpers) do not matter.
+        .env_remove("RUSTFLAGS")
+        .output()
+        .unwrap_or_else(|e| panic!("Failed to run cargo: {}", e));
+    if output.status.success() {
+        Ok(())
+    } else {
+        Err(String::from_utf8_lossy(&output.stderr).into_owned())
+    }
+}
+
+#[test]
+fn test_feature_combinations() {
+    let dir = Path::new(env!("CARGO_TARGET_TMPDIR")).join("features");
+    let consumer = consumer_crate(&dir);
+    let target_dir = dir.join("target");
+
+    let mut failures = vec![];
+    for features in SUPPORTED {
+        if let Err(output) = check(&consumer, &target_dir, features) {
+            failures.push(format!(
+                "The application does not compile with {}:\n{}",
+                name(features),
+                output
+            ));
+        }
+    }
+    for (features, reason) in UNSUPPORTED {
+        match check(&consumer, &target_dir, features) {
+            Ok(()) => failures.push(format!(
+                "The application compiles with {}, which is not supported.",
+                name(features)
+            )),
+            Err(output) if !output.contains(reason) => failures.push(format!(
+                "The application does not compile with {} (which is not supported), but not because {:?}:\n{}",
+                name(features),
+                reason,
+                output
+            )),
+            Err(_) => {}
+        }
+    }
+    assert!(failures.is_empty(), "{}", failures.join("\n\n"));
+}
@@ -0,0 +1,268 @@
+//! Runs conversion jobs whose filter graph combines several transforms (when FFmpeg is
+//! available on the system path), to make sure that FFmpeg accepts the generated graph.
+
+#[path = "../examples/common/mod.rs"]
+mod common;
+
+use ffmpeg_gif_maker::{
+    Converter, FitMode, Message, OutputFormat, Position, Rotation, ScaleAlgorithm, Settings,
+};
+
+fn ffmpeg_available() -> bool {
+    std::process::Command::new("ffmpeg")
+        .arg("-version")
+        .stdin(std::process::Stdio::null())
+        .stdout(std::process::Stdio::null())
+        .stderr(std::process::Stdio::null())
+        .status()
+        .map(|s| s.success())
+        .unwrap_or(false)
+}
+
+/// Runs a conversion job with `settings`, and returns the animated GIF's bytes.
+fn convert(settings: Settings) -> Vec<u8> {
+    convert_all(settings).remove(0)
+}
+
+/// Same as [`convert`], but also returns the bytes of the animated GIFs generated at
+/// the other widths (see [`Settings::additional_widths`]), in order.
+fn convert_all(settings: Settings) -> Vec<Vec<u8>> {
+    let (converter, _tx, mut rx) = Converter::new_with_channels();
+    let handle = std::thread::spawn(move || converter.convert(settings));
+    let mut outputs = vec![];
+    while let Some(message) = common::recv(&mut rx) {
+        match message {
+            Message::Success(b) | Message::SuccessVariant { bytes: b, .. } => outputs.push(b),
+            Message::Error(e) => panic!("Conversion failed: {:?}", e),
+            Message::Done => break,
+            _ => {}
+        }
+    }
+    handle.join().expect("Failed to join");
+    assert!(!outputs.is_empty(), "No output");
+    outputs
+}
+
+#[test]
+fn test_flips_and_rotation() {
+    if !ffmpeg_available() {
+        eprintln!("FFmpeg not found on system path, so skipping filter test.");
+        return;
+    }
+    let settings = Settings::with_standard_fps(common::input_video(), 120)
+        .rotation(Rotation::Cw90)
+        .hflip(true)
+        .vflip(true)
+        .trim("0:00", "0:01");
+    let bytes = convert(settings);
+    let info = ffmpeg_gif_maker::gif_info::parse_gif_info(&bytes).unwrap();
+    // NOTE: The width applies to the rotated frames.
+    assert_eq!(info.width, 120);
+    assert!(info.height > info.width);
+    assert!(info.frame_count > 0);
+}
+
+#[test]
+fn test_grayscale() {
+    if !ffmpeg_available() {
+        eprintln!("FFmpeg not found on system path, so skipping filter test.");
+        return;
+    }
+    let settings = Settings::with_standard_fps(common::input_video(), 120)
+        .saturation(0.0)
+        .trim("0:00", "0:01");
+    let bytes = convert(settings);
+    let info = ffmpeg_gif_maker::gif_info::parse_gif_info(&bytes).unwrap();
+    assert_eq!(info.width, 120);
+    assert!(info.frame_count > 0);
+}
+
+#[test]
+fn test_watermark() {
+    if !ffmpeg_available() {
+        eprintln!("FFmpeg not found on system path, so skipping filter test.");
+        return;
+    }
+    // A tiny white logo, overlaid (opaque) on the bottom right corner.
+    let dir = std::env::temp_dir().join(format!("ffmpeg_gif_maker_filters_{}", std::process::id()));
+    std::fs::create_dir_all(&dir).unwrap();
+    let logo = dir.join("logo.png");
+    let status = std::process::Command::new("ffmpeg")
+        .args(["-nostdin", "-loglevel", "error", "-y", "-f", "lavfi"])
+        .args(["-i", "color=c=white:s=16x16", "-frames:v", "1"])
+        .arg(&logo)
+        .status()
+        .unwrap();
+    assert!(status.success(), "Failed to generate logo");
+
+    let settings = Settings::with_standard_fps(common::input_video(), 120)
+        .watermark(logo, Position::BottomRight, 1.0)
+        .trim("0:00", "0:01");
+    let bytes = convert(settings);
+    let info = ffmpeg_gif_maker::gif_info::parse_gif_info(&bytes).unwrap();
+    assert_eq!(info.width, 120);
+    assert!(info.frame_count > 0);
+
+    let mut options = gif::DecodeOptions::new();
+    options.set_color_output(gif::ColorOutput::RGBA);
+    let mut decoder = options.read_info(&bytes[..]).unwrap();
+    let (width, height) = (usize::from(decoder.width()), usize::from(decoder.height()));
+    let frame = decoder.read_next_frame().unwrap().unwrap();
+    assert_eq!((frame.width, frame.height), (width as u16, height as u16));
+    let pixel = |x: usize, y: usize| &frame.buffer[(y * width + x) * 4..][..3];
+    assert!(pixel(width - 8, height - 8).iter().all(|c| *c > 200));
+    std::fs::remove_dir_all(&dir).unwrap();
+}
+
+#[test]
+fn test_subtitles() {
+    if !ffmpeg_available() {
+        eprintln!("FFmpeg not found on system path, so skipping filter test.");
+        return;
+    }
+    // NOTE: The `subtitles` filter requires an FFmpeg build with libass.
+    let filters = std::process::Command::new("ffmpeg")
+        .args(["-hide_banner", "-filters"])
+        .output()
+        .unwrap();
+    if !String::from_utf8_lossy(&filters.stdout).contains(" subtitles ") {
+        eprintln!("FFmpeg built without libass, so skipping subtitles test.");
+        return;
+    }
+    // NOTE: A path with spaces and colons, which must be escaped within the graph.
+    let dir = std::env::temp_dir().join(format!(
+        "ffmpeg_gif_maker_subtitles {}: part 1",
+        std::process::id()
+    ));
+    std::fs::create_dir_all(&dir).unwrap();
+    let srt = dir.join("my captions: intro.srt");
+    std::fs::write(&srt, "1\n00:00:00,000 --> 00:00:02,000\nHello, world!\n").unwrap();
+
+    let settings = Settings::with_standard_fps(common::input_video(), 120)
+        .subtitles(srt.clone())
+        .trim("0:00.5", "0:01.5");
+    let info = ffmpeg_gif_maker::gif_info::parse_gif_info(&convert(settings)).unwrap();
+    assert_eq!(info.width, 120);
+    assert!(info.frame_count > 0);
+
+    // NOTE: A missing file is reported as such.
+    let missing = dir.join("missing.srt");
+    let settings =
+        Settings::with_standard_fps(common::input_video(), 120).subtitles(missing.clone());
+    let (converter, _tx, mut rx) = Converter::new_with_channels();
+    let handle = std::thread::spawn(move || converter.convert(settings));
+    let mut error = None;
+    while let Some(message) = common::recv(&mut rx) {
+        match message {
+            Message::Error(e) => error = Some(e),
+            Message::Done => break,
+            _ => {}
+        }
+    }
+    handle.join().expect("Failed to join");
+    match error {
+        Some(ffmpeg_gif_maker::Error::SubtitlesFile { path, .. }) => assert_eq!(path, missing),
+        e => panic!("Expected a 'SubtitlesFile' error: {:?}", e),
+    }
+    std::fs::remove_dir_all(&dir).unwrap();
+}
+
+#[test]
+fn test_scale_algorithms() {
+    if !ffmpeg_available() {
+        eprintln!("FFmpeg not found on system path, so skipping filter test.");
+        return;
+    }
+    for algorithm in [
+        ScaleAlgorithm::Bilinear,
+        ScaleAlgorithm::Bicubic,
+        ScaleAlgorithm::Lanczos,
+        ScaleAlgorithm::Neighbor,
+        ScaleAlgorithm::Area,
+    ] {
+        let settings = Settings::with_standard_fps(common::input_video(), 120)
+            .gif_height(90)
+            .fit_mode(FitMode::Letterbox)
+            .scale_algorithm(algorithm)
+            .trim("0:00", "0:01");
+        let info = ffmpeg_gif_maker::gif_info::parse_gif_info(&convert(settings)).unwrap();
+        assert_eq!((info.width, info.height), (120, 90), "{:?}", algorithm);
+    }
+}
+
+#[test]
+fn test_custom_filter_complex() {
+    if !ffmpeg_available() {
+        eprintln!("FFmpeg not found on system path, so skipping filter test.");
+        return;
+    }
+    let settings = Settings::with_standard_fps(common::input_video(), 120)
+        .custom_filter_complex(
+            "[0:v]fps=5,zoompan=z='min(zoom+0.05,1.5)':d=1:s=160x90,drawbox=x=10:y=10:w=40:h=20:color=red,split[a][b]; [a]palettegen[p]; [b][p]paletteuse",
+        )
+        .trim("0:00", "0:01");
+    let info = ffmpeg_gif_maker::gif_info::parse_gif_info(&convert(settings)).unwrap();
+    // NOTE: The graph's own size wins over the width passed to the constructor.
+    assert_eq!((info.width, info.height), (160, 90));
+}
+
+#[test]
+fn test_additional_widths() {
+    if !ffmpeg_available() {
+        eprintln!("FFmpeg not found on system path, so skipping filter test.");
+        return;
+    }
+    let settings = Settings::with_standard_fps(common::input_video(), 200)
+        .additional_widths(vec![400, 800])
+        .hflip(true)
+        .trim("0:00", "0:01");
+    let widths = Vec::from_iter(convert_all(settings).iter().map(|bytes| {
+        let info = ffmpeg_gif_maker::gif_info::parse_gif_info(bytes).unwrap();
+        assert!(info.frame_count > 0);
+        info.width
+    }));
+    assert_eq!(widths, [200, 400, 800]);
+}
+
+#[test]
+fn test_output_formats() {
+    if !ffmpeg_available() {
+        eprintln!("FFmpeg not found on system path, so skipping filter test.");
+        return;
+    }
+    // NOTE: The animated WebP encoder requires an FFmpeg build with libwebp.
+    let encoders = std::process::Command::new("ffmpeg")
+        .args(["-hide_banner", "-encoders"])
+        .output()
+        .unwrap();
+    let has_libwebp = String::from_utf8_lossy(&encoders.stdout).contains(" libwebp_anim ");
+    let webp = OutputFormat::WebP {
+        quality: 75,
+        lossless: false,
+    };
+    for format in [OutputFormat::Gif, webp, OutputFormat::Apng] {
+        if format == webp && !has_libwebp {
+            eprintln!("FFmpeg built without libwebp, so skipping animated WebP output.");
+            continue;
+        }
+        let settings = Settings::with_standard_fps(common::input_video(), 120)
+            .output_format(format)
+            .trim("0:00", "0:01");
+        let bytes = convert(settings);
+        match format {
+            OutputFormat::Gif => {
+                let info = ffmpeg_gif_maker::gif_info::parse_gif_info(&bytes).unwrap();
+                assert_eq!(info.width, 120);
+            }
+            OutputFormat::WebP { .. } => {
+                assert_eq!(&bytes[..4], b"RIFF");
+                assert_eq!(&bytes[8..12], b"WEBP");
+            }
+            _ => {
+                assert_eq!(&bytes[..8], b"\x89PNG\r\n\x1a\n");
+                // NOTE: The animation control chunk, which tells an APNG from a PNG.
+                assert!(bytes.windows(4).any(|chunk| chunk == b"acTL"));
+            }
+        }
+    }
+}
@@ -0,0 +1,125 @@
+//! A minimal application built against the library by `tests/features.rs`, once per supported
+//! combination of feature flags (which its own feature flags forward to the library), so that
+//! the part of the public API that the examples rely on (see `examples/common`), along with the
+//! items provided by each feature flag, is known to exist in every combination. It is only
+//! compiled, never run.
+
+mod common;
+
+use ffmpeg_gif_maker::{
+    Batch, BatchEvent, Command, Converter, Error, JobId, Message, MessageReceiver,
+    MessageReceiverExt, Settings, Summary,
+};
+
+fn main() {
+    let settings = Settings::with_standard_fps(common::input_video(), 200);
+    println!("{:?}", settings.plan());
+    let _ = convert(settings.clone());
+    batch(settings.clone());
+    features(settings);
+}
+
+/// Like the `how_to` example (and the `cancel` one).
+fn convert(settings: Settings) -> Result<Vec<u8>, Error> {
+    let (converter, tx, mut rx) = Converter::new_with_channels();
+    let id: JobId = converter.id();
+    let handle = std::thread::spawn(move || converter.convert(settings));
+    let mut output = Err(Error::MissingResult);
+    while let Some(message) = common::recv(&mut rx) {
+        match message {
+            Message::Progress(progress) if progress > 0.5 => {
+                common::send(&tx, Command::Cancel);
+            }
+            Message::Success(bytes) => output = Ok(bytes),
+            Message::Error(error) => {
+                eprintln!("{}: {} ({})", id, error, error.kind());
+                output = Err(error);
+            }
+            Message::Summary(summary) => {
+                let _: Summary = summary;
+            }
+            Message::Done => break,
+            _ => {}
+        }
+    }
+    handle.join().unwrap();
+    let _ = next_blocking(&mut rx);
+    output
+}
+
+/// Like the `batch` example.
+fn batch(settings: Settings) {
+    let mut batch = Batch::new(2);
+    batch.add(settings);
+    let report = batch.run(|event| {
+        if let BatchEvent::Message(id, Message::Success(bytes)) = event {
+            println!("{}: {} bytes", id, bytes.len());
+        }
+    });
+    for (id, error) in report.failures {
+        println!("{}: failed ({})", id, error);
+    }
+}
+
+/// Receives the next message whichever kind of channel is used (see `MessageReceiverExt`).
+fn next_blocking(rx: &mut MessageReceiver) -> Option<Message> {
+    rx.next_blocking()
+}
+
+/// Uses the items provided by the enabled feature flags.
+fn features(settings: Settings) {
+    #[cfg(any(feature = "tokio", feature = "async-channel"))]
+    {
+        let (_, _, mut rx) = Converter::new_with_channels();
+        drop(next_async(&mut rx));
+    }
+    #[cfg(feature = "regex")]
+    let settings = settings.deny_stderr_regexes(vec!["^Error".into()]);
+    #[cfg(feature = "serde")]
+    serializable(&settings.plan());
+    #[cfg(feature = "v2-messages")]
+    {
+        let (_, _, mut rx) = Converter::new_with_v2_channels();
+        let _ = v2_success(&mut rx);
+    }
+    drop(settings);
+}
+
+/// Like the `how_to_async` example.
+#[cfg(feature = "tokio")]
+async fn next_async(rx: &mut MessageReceiver) -> Option<Message> {
+    match rx.recv().await {
+        Some(message) => Some(message),
+        None => rx.next().await,
+    }
+}
+
+/// Same as with the `tokio` feature flag, using the `async-channel` channels.
+#[cfg(feature = "async-channel")]
+async fn next_async(rx: &mut MessageReceiver) -> Option<Message> {
+    match rx.recv().await {
+        Ok(message) => Some(message),
+        Err(_) => rx.next().await,
+    }
+}
+
+#[cfg(feature = "serde")]
+fn serializable(plan: &impl serde::Serialize) {
+    fn assert_serialize<T: serde::Serialize>(_: &T) {}
+    assert_serialize(plan);
+    assert_serialize(&ffmpeg_gif_maker::HwAccel::Auto);
+}
+
+#[cfg(feature = "v2-messages")]
+fn v2_success(rx: &mut ffmpeg_gif_maker::v2::MessageReceiver) -> Option<Vec<u8>> {
+    #[cfg(not(any(feature = "tokio", feature = "async-channel")))]
+    let message = rx.recv().ok()?;
+    #[cfg(feature = "tokio")]
+    let message = rx.blocking_recv()?;
+    #[cfg(feature = "async-channel")]
+    let message = rx.recv_blocking().ok()?;
+    match message.event {
+        ffmpeg_gif_maker::v2::Event::Success(success) => Some(success.bytes),
+        _ => None,
+    }
+}
@@ -0,0 +1,78 @@
+//! Converts a remote video (see `Settings::from_url`) into an animated GIF, which requires
+//! network access, so this only runs when the video's URL is given by the
+//! `FFMPEG_GIF_MAKER_TEST_URL` environment variable (and when FFmpeg is available on the
+//! system path), e.g.:
+//!
+//! `FFMPEG_GIF_MAKER_TEST_URL=https://example.com/video.mp4 cargo test --test remote_url`
+
+#[path = "../examples/common/mod.rs"]
+mod common;
+
+use ffmpeg_gif_maker::{Converter, Message, Settings};
+
+const URL_VAR: &str = "FFMPEG_GIF_MAKER_TEST_URL";
+
+fn ffmpeg_available() -> bool {
+    std::process::Command::new("ffmpeg")
+        .arg("-version")
+        .stdin(std::process::Stdio::null())
+        .stdout(std::process::Stdio::null())
+        .stderr(std::process::Stdio::null())
+        .status()
+        .map(|s| s.success())
+        .unwrap_or(false)
+}
+
+#[test]
+fn test_convert_remote_url() {
+    let Ok(url) = std::env::var(URL_VAR) else {
+        eprintln!("Skipping test, since {} is not set.", URL_VAR);
+        return;
+    };
+    if !ffmpeg_available() {
+        eprintln!("Skipping test, since FFmpeg is not available.");
+        return;
+    }
+
+    let settings = Settings::from_url(url, 100).clip_duration(std::time::Duration::from_secs(2));
+    let (converter, _tx, mut rx) = Converter::new_with_channels();
+    let handle = std::thread::spawn(move || converter.convert(settings));
+    let mut bytes = None;
+    let mut progressed = false;
+    while let Some(message) = common::recv(&mut rx) {
+        match message {
+            Message::Success(b) => bytes = Some(b),
+            Message::Error(e) => panic!("Conversion failed: {:?}", e),
+            // NOTE: Whether the duration is known depends on the server (and the format).
+            Message::Progress(_) | Message::FramesProcessed(_) => progressed = true,
+            Message::Done => break,
+            _ => {}
+        }
+    }
+    handle.join().unwrap();
+    let bytes = bytes.expect("Expected a 'Success' message");
+    assert!(ffmpeg_gif_maker::gif_info::parse_gif_info(&bytes).is_ok());
+    assert!(progressed);
+}
+
+#[test]
+fn test_reject_local_url() {
+    // NOTE: No network access (nor FFmpeg) is needed, since the job fails before spawning it.
+    let (converter, _tx, mut rx) = Converter::new_with_channels();
+    let settings = Settings::from_url("file:///etc/passwd", 100);
+    let handle = std::thread::spawn(move || converter.convert(settings));
+    let mut error = None;
+    while let Some(message) = common::recv(&mut rx) {
+        match message {
+            Message::Error(e) => error = Some(e),
+            Message::Success(_) => panic!("Expected the job to fail"),
+            Message::Done => break,
+            _ => {}
+        }
+    }
+    handle.join().unwrap();
+    assert_eq!(
+        error.expect("Expected an 'Error' message").kind(),
+        "invalid_settings"
+    );
+}